@@ -0,0 +1,163 @@
+// 前処理パラメータの自動調整（表示中のテキストを正解として設定の組み合わせを探索する）
+use anyhow::{bail, Result};
+use image::DynamicImage;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+use crate::monitor::edit_distance;
+use crate::ocr::{BinarizationMode, GrayscaleMode, OcrConfig, OcrEngine};
+
+/// 探索全体の制限時間
+pub const TIME_BUDGET: Duration = Duration::from_secs(30);
+
+/// 返す結果の件数（正解に近い順）
+pub const TOP_RESULTS: usize = 5;
+
+/// 探索する拡大の目標の幅
+const SCALE_TARGET_WIDTHS: [u32; 2] = [1000, 1600];
+
+/// 探索するページセグメンテーションモード（6 = 単一ブロック、7 = 単一行、11 = まばらなテキスト）
+const PAGE_SEG_MODES: [u32; 3] = [6, 7, 11];
+
+/// 探索するグレースケール変換の方式
+const GRAYSCALE_MODES: [GrayscaleMode; 3] = [GrayscaleMode::Luminance, GrayscaleMode::ChannelMax, GrayscaleMode::ChannelMedian];
+
+/// 1つの組み合わせの評価結果
+#[derive(Debug, Clone, Serialize)]
+pub struct TuneResult {
+    /// 評価した設定
+    pub config: OcrConfig,
+    /// 認識されたテキスト
+    pub text: String,
+    /// 正解との編集距離（空白を除いた文字単位）
+    pub distance: usize,
+    /// 正解との一致度（0.0-1.0、1.0で完全一致）
+    pub accuracy: f32,
+    /// 正規化済みの信頼度（0.0-1.0、信頼度を返さないエンジンの場合はNone）
+    pub confidence: Option<f32>,
+    /// 前処理と認識の所要時間（ミリ秒）
+    pub duration_ms: u64,
+}
+
+/// 自動調整の結果
+#[derive(Debug, Clone, Serialize)]
+pub struct AutoTuneReport {
+    /// 正解に近い順の上位の結果
+    pub results: Vec<TuneResult>,
+    /// 評価した組み合わせの数
+    pub evaluated: usize,
+    /// 探索する組み合わせの総数
+    pub total: usize,
+    /// 制限時間により途中で打ち切ったかどうか
+    pub timed_out: bool,
+    /// 探索全体の所要時間（ミリ秒）
+    pub elapsed_ms: u64,
+}
+
+/// 探索する設定の一覧（比較のため現在の設定を先頭に置く）
+pub fn candidates(base: &OcrConfig) -> Vec<OcrConfig> {
+    let mut configs = vec![base.clone()];
+    for grayscale_mode in GRAYSCALE_MODES {
+        for binarization in [BinarizationMode::Equalize, BinarizationMode::Otsu] {
+            for invert in [false, true] {
+                for scale_target_width in SCALE_TARGET_WIDTHS {
+                    for page_seg_mode in PAGE_SEG_MODES {
+                        let config = OcrConfig {
+                            grayscale_mode,
+                            binarization,
+                            invert,
+                            scale_target_width,
+                            page_seg_mode: Some(page_seg_mode),
+                            ..base.clone()
+                        };
+                        if !configs.contains(&config) {
+                            configs.push(config);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    configs
+}
+
+/// 設定の組み合わせを順に評価し、正解に近い順に並べる
+///
+/// 認識中の組み合わせは中断できないため、制限時間を過ぎた時点で評価中のものが終わってから打ち切る。
+/// progressには評価済みの数・総数・その時点の最良の結果を渡す。
+pub fn auto_tune(
+    engine: &mut OcrEngine,
+    image: &DynamicImage,
+    expected_text: &str,
+    base: &OcrConfig,
+    time_budget: Duration,
+    mut progress: impl FnMut(usize, usize, Option<&TuneResult>),
+) -> Result<AutoTuneReport> {
+    let expected: Vec<char> = comparable_chars(expected_text);
+    if expected.is_empty() {
+        bail!("正解のテキストを入力してください");
+    }
+
+    let start = Instant::now();
+    let configs = candidates(base);
+    let total = configs.len();
+    let mut results: Vec<TuneResult> = Vec::new();
+    let mut evaluated = 0;
+    let mut timed_out = false;
+
+    for config in configs {
+        if start.elapsed() >= time_budget {
+            timed_out = true;
+            break;
+        }
+
+        let step_start = Instant::now();
+        engine.set_config(config.clone());
+        match engine.recognize_detailed(image) {
+            Ok(result) => {
+                let actual = comparable_chars(&result.text);
+                let distance = edit_distance(&actual, &expected);
+                let length = expected.len().max(actual.len());
+                results.push(TuneResult {
+                    config,
+                    text: result.text,
+                    distance,
+                    accuracy: 1.0 - distance as f32 / length as f32,
+                    confidence: result.confidence,
+                    duration_ms: step_start.elapsed().as_millis() as u64,
+                });
+            }
+            Err(e) => log::warn!("自動調整の認識に失敗: {:?}: {}", config, e),
+        }
+        evaluated += 1;
+
+        results.sort_by(|a, b| {
+            a.distance
+                .cmp(&b.distance)
+                .then(b.confidence.unwrap_or(0.0).total_cmp(&a.confidence.unwrap_or(0.0)))
+                .then(a.duration_ms.cmp(&b.duration_ms))
+        });
+        progress(evaluated, total, results.first());
+    }
+
+    results.truncate(TOP_RESULTS);
+    log::info!(
+        "自動調整: {}/{} 件を評価（{}ms{}）",
+        evaluated,
+        total,
+        start.elapsed().as_millis(),
+        if timed_out { "、制限時間により打ち切り" } else { "" }
+    );
+    Ok(AutoTuneReport {
+        results,
+        evaluated,
+        total,
+        timed_out,
+        elapsed_ms: start.elapsed().as_millis() as u64,
+    })
+}
+
+/// 比較用の文字列（OCRは文字間に空白や改行を入れることがあるため空白を除く）
+fn comparable_chars(text: &str) -> Vec<char> {
+    text.chars().filter(|c| !c.is_whitespace()).collect()
+}
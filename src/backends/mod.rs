@@ -0,0 +1,78 @@
+// Tesseract以外のOCRバックエンド
+use anyhow::Result;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+use crate::ocr::OcrLine;
+
+pub mod subprocess;
+#[cfg(target_os = "windows")]
+pub mod windows_ocr;
+
+/// 前処理済みの画像を認識する外部のOCRエンジン
+///
+/// page_seg_modeはTesseractのページセグメンテーションモードで、Tesseract以外のエンジンは無視する。
+pub trait OcrBackend: Send + Sync {
+    /// 行ごとのテキストと位置を認識（位置は渡した画像の座標）
+    fn recognize_lines(&self, image: &DynamicImage, page_seg_mode: u32) -> Result<Vec<OcrLine>>;
+
+    /// 行を連結したテキストと生の信頼度（0.0-1.0）を認識
+    ///
+    /// 信頼度を返さないエンジンはNoneとする（信頼度のしきい値での除外を行わない）。
+    fn recognize(&self, image: &DynamicImage, page_seg_mode: u32) -> Result<(String, Option<f32>)> {
+        let lines = self.recognize_lines(image, page_seg_mode)?;
+        let text = lines.iter().map(|line| line.text.as_str()).collect::<Vec<_>>().join("\n");
+        Ok((text, None))
+    }
+
+    /// 前回の取得以降にエンジンを再起動した回数
+    fn take_restarts(&self) -> u64 {
+        0
+    }
+}
+
+/// 使用するOCRエンジンの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OcrBackendKind {
+    /// Tesseract（全プラットフォーム）
+    Tesseract,
+    /// 子プロセスで実行するTesseract（OcrConfigのisolationで選択）
+    TesseractSubprocess,
+    /// Windows 10以降の組み込みOCR（Windows.Media.Ocr）
+    #[cfg(target_os = "windows")]
+    Windows,
+}
+
+/// 使用するOCRエンジンの指定（OcrConfigのbackend）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OcrBackendPreference {
+    /// 実行環境で使えるエンジンを選択（Windows 10以降は組み込みOCRを優先）
+    #[default]
+    Auto,
+    /// 組み込みOCRが使える環境でもTesseractを使う
+    Tesseract,
+}
+
+impl OcrBackendKind {
+    /// 指定に従って実行環境で使えるエンジンを選択
+    pub fn detect(language: &str, preference: OcrBackendPreference) -> Self {
+        #[cfg(target_os = "windows")]
+        if preference == OcrBackendPreference::Auto && windows_ocr::WindowsOcrBackend::is_available(language) {
+            return OcrBackendKind::Windows;
+        }
+        let _ = (language, preference);
+        OcrBackendKind::Tesseract
+    }
+
+    /// 外部のエンジンを作成（Tesseractの場合はOcrEngine自身が認識するためNone）
+    pub fn create(&self, language: &str) -> Result<Option<Box<dyn OcrBackend>>> {
+        #[cfg(target_os = "windows")]
+        if *self == OcrBackendKind::Windows {
+            return Ok(Some(Box::new(windows_ocr::WindowsOcrBackend::new(language)?)));
+        }
+        let _ = language;
+        Ok(None)
+    }
+}
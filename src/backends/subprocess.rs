@@ -0,0 +1,312 @@
+// 子プロセスで実行するTesseract（クラッシュしてもアプリを巻き込まないようにする）
+//
+// 同じ実行ファイルを --ocr-worker で起動し、標準入出力で長さ付きのメッセージをやり取りする。
+// メッセージは4バイトのリトルエンディアンの長さに続く本体で、要求は設定のJSONとPNG画像の2つ、
+// 応答は結果のJSONの1つ。
+use anyhow::{anyhow, bail, Context, Result};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use super::OcrBackend;
+use crate::ocr::{OcrConfig, OcrEngine, OcrIsolation, OcrLine};
+
+/// 子プロセスとして起動する場合の引数
+pub const WORKER_ARG: &str = "--ocr-worker";
+
+/// メッセージの最大の長さ（壊れた長さで巨大な領域を確保しないため）
+const MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
+/// 子プロセスへの認識の要求
+#[derive(Debug, Serialize, Deserialize)]
+struct WorkerRequest {
+    /// ページセグメンテーションモード
+    page_seg_mode: u32,
+    /// 行ごとの位置も認識するかどうか
+    lines: bool,
+}
+
+/// 子プロセスからの認識結果
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct WorkerResponse {
+    /// 認識されたテキスト
+    text: String,
+    /// 生の信頼度（0.0-1.0）
+    confidence: f32,
+    /// 行ごとのテキストと位置（要求された場合のみ）
+    lines: Vec<OcrLine>,
+    /// 認識に失敗した場合のエラー
+    error: Option<String>,
+}
+
+/// 長さ付きのメッセージを書き込む
+fn write_message(writer: &mut impl Write, body: &[u8]) -> io::Result<()> {
+    writer.write_all(&(body.len() as u32).to_le_bytes())?;
+    writer.write_all(body)
+}
+
+/// 長さ付きのメッセージを読み込む
+fn read_message(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut length = [0u8; 4];
+    reader.read_exact(&mut length)?;
+    let length = u32::from_le_bytes(length) as usize;
+    if length > MAX_MESSAGE_SIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("メッセージが大きすぎます: {}バイト", length)));
+    }
+    let mut body = vec![0u8; length];
+    reader.read_exact(&mut body)?;
+    Ok(body)
+}
+
+/// 起動中の子プロセス
+struct Worker {
+    child: Child,
+    stdin: ChildStdin,
+    /// 標準出力を読むスレッドが受け取った応答
+    responses: Receiver<io::Result<Vec<u8>>>,
+}
+
+impl Worker {
+    /// 要求を送り、応答を待つ
+    fn exchange(&mut self, request: &[u8], image: &[u8], timeout: Duration) -> Result<WorkerResponse> {
+        write_message(&mut self.stdin, request)
+            .and_then(|_| write_message(&mut self.stdin, image))
+            .and_then(|_| self.stdin.flush())
+            .context("OCRの子プロセスへの送信に失敗しました")?;
+
+        match self.responses.recv_timeout(timeout) {
+            Ok(Ok(body)) => serde_json::from_slice(&body).context("OCRの子プロセスの応答の形式が正しくありません"),
+            Ok(Err(e)) => Err(anyhow!("OCRの子プロセスが終了しました: {}", e)),
+            Err(RecvTimeoutError::Timeout) => bail!("OCRの子プロセスが{}ms以内に応答しませんでした", timeout.as_millis()),
+            Err(RecvTimeoutError::Disconnected) => bail!("OCRの子プロセスが終了しました"),
+        }
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        // 応答しない子プロセスも確実に終了させる
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// 子プロセスのTesseractで認識するエンジン
+///
+/// 子プロセスがクラッシュした、または応答しなくなった場合はその認識をエラーとし、
+/// 次の認識の前に子プロセスを起動し直す。
+pub struct SubprocessBackend {
+    /// 子プロセスの引数
+    args: Vec<String>,
+    /// 応答を待つ時間
+    timeout: Duration,
+    /// 起動中の子プロセス（未起動・異常終了後はNone）
+    worker: Mutex<Option<Worker>>,
+    /// 一度でも子プロセスを起動したかどうか（2回目以降の起動を再起動として数える）
+    started: AtomicBool,
+    /// 前回の取得以降に再起動した回数
+    restarts: AtomicU64,
+}
+
+impl SubprocessBackend {
+    /// 子プロセスの設定を作成（子プロセスは最初の認識時に起動する）
+    pub fn new(tessdata_dir: Option<&str>, language: &str, config: &OcrConfig) -> Self {
+        let mut args = vec![WORKER_ARG.to_string(), "--lang".to_string(), language.to_string()];
+        if let Some(dir) = tessdata_dir {
+            args.extend(["--tessdata".to_string(), dir.to_string()]);
+        }
+        // 数値と文字列のみのためシリアライズは失敗しない
+        let config_json = serde_json::to_string(config).expect("OcrConfigのJSON変換に失敗しました");
+        args.extend(["--config".to_string(), config_json]);
+
+        Self {
+            args,
+            timeout: Duration::from_millis(config.worker_timeout_ms),
+            worker: Mutex::new(None),
+            started: AtomicBool::new(false),
+            restarts: AtomicU64::new(0),
+        }
+    }
+
+    /// 子プロセスを起動
+    fn spawn(&self) -> Result<Worker> {
+        let exe = std::env::current_exe().context("実行ファイルのパスを取得できません")?;
+        let mut child = Command::new(exe)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .context("OCRの子プロセスの起動に失敗しました")?;
+
+        let stdin = child.stdin.take().context("OCRの子プロセスの標準入力を取得できません")?;
+        let mut stdout = child.stdout.take().context("OCRの子プロセスの標準出力を取得できません")?;
+        let (sender, responses) = mpsc::channel();
+        thread::spawn(move || loop {
+            let message = read_message(&mut stdout);
+            let finished = message.is_err();
+            if sender.send(message).is_err() || finished {
+                break;
+            }
+        });
+
+        if self.started.swap(true, Ordering::Relaxed) {
+            self.restarts.fetch_add(1, Ordering::Relaxed);
+            log::warn!("OCRの子プロセスを再起動しました");
+        } else {
+            log::info!("OCRの子プロセスを起動しました（PID {}）", child.id());
+        }
+        Ok(Worker { child, stdin, responses })
+    }
+
+    /// 子プロセスに認識を要求（失敗した子プロセスは破棄し、次の要求で起動し直す）
+    fn request(&self, image: &DynamicImage, page_seg_mode: u32, lines: bool) -> Result<WorkerResponse> {
+        let mut png = io::Cursor::new(Vec::new());
+        image.write_to(&mut png, image::ImageOutputFormat::Png)
+            .context("PNGへのエンコードに失敗しました")?;
+        let request = serde_json::to_vec(&WorkerRequest { page_seg_mode, lines })?;
+
+        let mut worker = self.worker.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let active = match worker.take() {
+            Some(active) => active,
+            None => self.spawn()?,
+        };
+        let active = worker.insert(active);
+        let response = match active.exchange(&request, png.get_ref(), self.timeout) {
+            Ok(response) => response,
+            Err(e) => {
+                *worker = None;
+                return Err(e);
+            }
+        };
+
+        match response.error {
+            Some(error) => Err(anyhow!(error)),
+            None => Ok(response),
+        }
+    }
+}
+
+impl OcrBackend for SubprocessBackend {
+    fn recognize_lines(&self, image: &DynamicImage, page_seg_mode: u32) -> Result<Vec<OcrLine>> {
+        Ok(self.request(image, page_seg_mode, true)?.lines)
+    }
+
+    fn recognize(&self, image: &DynamicImage, page_seg_mode: u32) -> Result<(String, Option<f32>)> {
+        let response = self.request(image, page_seg_mode, false)?;
+        Ok((response.text, Some(response.confidence)))
+    }
+
+    fn take_restarts(&self) -> u64 {
+        self.restarts.swap(0, Ordering::Relaxed)
+    }
+}
+
+/// 子プロセスとして認識を行う（標準入力が閉じられるまで要求に応答し、終了コードを返す）
+pub fn run_worker(args: &[String]) -> i32 {
+    match serve(args) {
+        Ok(()) => 0,
+        Err(e) => {
+            log::error!("OCRの子プロセスを終了します: {:#}", e);
+            1
+        }
+    }
+}
+
+/// 引数からエンジンを作成し、要求に応答する
+fn serve(args: &[String]) -> Result<()> {
+    let mut language = crate::tessdata::DEFAULT_LANGUAGE.to_string();
+    let mut tessdata_dir = None;
+    let mut config = OcrConfig::default();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = |name: &str| iter.next().with_context(|| format!("{} の値がありません", name));
+        match arg.as_str() {
+            "--lang" => language = value("--lang")?.clone(),
+            "--tessdata" => tessdata_dir = Some(PathBuf::from(value("--tessdata")?)),
+            "--config" => config = serde_json::from_str(value("--config")?).context("--config の形式が正しくありません")?,
+            other => bail!("不明なオプションです: {}", other),
+        }
+    }
+    // 子プロセスの中でさらに子プロセスを起動しない
+    config.isolation = OcrIsolation::InProcess;
+
+    let mut engine = OcrEngine::with_language(tessdata_dir, &language)?;
+    engine.set_config(config);
+
+    let mut stdin = io::stdin().lock();
+    let mut stdout = io::stdout().lock();
+    loop {
+        // 親プロセスが標準入力を閉じたら終了
+        let request = match read_message(&mut stdin) {
+            Ok(request) => request,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        let image = read_message(&mut stdin)?;
+
+        let response = respond(&engine, &request, &image).unwrap_or_else(|e| WorkerResponse {
+            error: Some(format!("{:#}", e)),
+            ..WorkerResponse::default()
+        });
+        write_message(&mut stdout, &serde_json::to_vec(&response)?)?;
+        stdout.flush()?;
+    }
+}
+
+/// 1件の要求を認識
+fn respond(engine: &OcrEngine, request: &[u8], image: &[u8]) -> Result<WorkerResponse> {
+    let request: WorkerRequest = serde_json::from_slice(request).context("要求の形式が正しくありません")?;
+    let image = image::load_from_memory(image).context("画像の読み込みに失敗しました")?;
+
+    if request.lines {
+        let lines = engine.recognize_tsv_lines(&image, request.page_seg_mode)?;
+        return Ok(WorkerResponse {
+            lines,
+            ..WorkerResponse::default()
+        });
+    }
+    let (text, confidence) = engine.recognize_in_process(&image, request.page_seg_mode)?;
+    Ok(WorkerResponse {
+        text,
+        confidence,
+        ..WorkerResponse::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends::OcrBackendKind;
+
+    fn engine() -> OcrEngine {
+        OcrEngine::from_parts(None, "eng", OcrBackendKind::Tesseract, None)
+    }
+
+    fn request(lines: bool) -> Vec<u8> {
+        serde_json::to_vec(&WorkerRequest { page_seg_mode: 6, lines }).unwrap()
+    }
+
+    #[test]
+    fn corrupt_png_is_rejected_before_recognition() {
+        // PNGのシグネチャの後が壊れている
+        let corrupt = b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR\xff\xff";
+        for lines in [false, true] {
+            let error = respond(&engine(), &request(lines), corrupt).unwrap_err();
+            assert!(error.to_string().contains("画像の読み込みに失敗しました"), "{:#}", error);
+        }
+    }
+
+    #[test]
+    fn malformed_request_is_rejected() {
+        let error = respond(&engine(), b"{\"lines\":", &[]).unwrap_err();
+        assert!(error.to_string().contains("要求の形式が正しくありません"), "{:#}", error);
+    }
+}
@@ -0,0 +1,121 @@
+// Windows 10以降の組み込みOCR（Windows.Media.Ocr）によるバックエンド
+use anyhow::{bail, Context, Result};
+use image::{imageops::FilterType, DynamicImage};
+use windows::core::HSTRING;
+use windows::Globalization::Language;
+use windows::Graphics::Imaging::{BitmapPixelFormat, SoftwareBitmap};
+use windows::Media::Ocr::OcrEngine as WinOcrEngine;
+use windows::Storage::Streams::DataWriter;
+
+use super::OcrBackend;
+use crate::ocr::{join_word, OcrLine};
+use crate::tiling::ImageRect;
+
+/// Windows.Media.Ocrによる認識
+///
+/// WinRTのオブジェクトはスレッドをまたいで保持せず、Tesseractと同様に認識ごとに作成する。
+pub struct WindowsOcrBackend {
+    /// 認識言語のBCP 47タグ
+    language_tag: &'static str,
+}
+
+impl WindowsOcrBackend {
+    /// Tesseractの言語コードに対応する言語で作成（対応する言語が無い場合はエラー）
+    ///
+    /// ユーザープロファイルの言語で代用すると、指定と異なる言語で認識してしまうため代用しない。
+    pub fn new(language: &str) -> Result<Self> {
+        let language_tag = language_tag(language)
+            .with_context(|| format!("言語 {} に対応する組み込みOCRの言語がありません", language))?;
+        Ok(Self { language_tag })
+    }
+
+    /// 組み込みOCRが使えるかどうか（Windows 10未満、対応しない言語、言語パック未導入の場合はfalse）
+    pub fn is_available(language: &str) -> bool {
+        Self::new(language).and_then(|backend| backend.create_engine()).is_ok()
+    }
+
+    /// WinRTのOCRエンジンを作成
+    fn create_engine(&self) -> Result<WinOcrEngine> {
+        let language = Language::CreateLanguage(&HSTRING::from(self.language_tag))?;
+        // 言語が使えない場合はエラーではなくnullが返る
+        WinOcrEngine::TryCreateFromLanguage(&language)
+            .context("Windows.Media.Ocrのエンジンを作成できません（言語パックを確認してください）")
+    }
+}
+
+impl OcrBackend for WindowsOcrBackend {
+    fn recognize_lines(&self, image: &DynamicImage, _page_seg_mode: u32) -> Result<Vec<OcrLine>> {
+        let engine = self.create_engine()?;
+
+        // 上限を超える画像は縮小して認識し、位置を元の座標に戻す
+        let max_dimension = WinOcrEngine::MaxImageDimension()?;
+        let (width, height) = (image.width(), image.height());
+        let resized;
+        let (input, scale) = if width.max(height) > max_dimension {
+            resized = image.resize(max_dimension, max_dimension, FilterType::Triangle);
+            (&resized, width as f32 / resized.width() as f32)
+        } else {
+            (image, 1.0)
+        };
+
+        let bitmap = to_software_bitmap(input)?;
+        let result = engine.RecognizeAsync(&bitmap)?.get()?;
+
+        let mut lines = Vec::new();
+        for line in result.Lines()? {
+            let mut text = String::new();
+            let mut bbox: Option<ImageRect> = None;
+            for word in line.Words()? {
+                join_word(&mut text, &word.Text()?.to_string_lossy());
+                let rect = word.BoundingRect()?;
+                let word_rect = ImageRect {
+                    x: (rect.X * scale) as u32,
+                    y: (rect.Y * scale) as u32,
+                    width: (rect.Width * scale).ceil() as u32,
+                    height: (rect.Height * scale).ceil() as u32,
+                };
+                bbox = Some(bbox.map_or(word_rect, |bbox| bbox.union(&word_rect)));
+            }
+            if let Some(bbox) = bbox {
+                lines.push(OcrLine { text, bbox, language: None, confidence: None });
+            }
+        }
+        Ok(lines)
+    }
+}
+
+/// 画像の画素をBGRA8のSoftwareBitmapにコピー
+fn to_software_bitmap(image: &DynamicImage) -> Result<SoftwareBitmap> {
+    let rgba = image.to_rgba8();
+    let (width, height) = rgba.dimensions();
+    if width == 0 || height == 0 {
+        bail!("空の画像は認識できません");
+    }
+
+    let mut bgra = rgba.into_raw();
+    for pixel in bgra.chunks_exact_mut(4) {
+        pixel.swap(0, 2);
+    }
+
+    let writer = DataWriter::new()?;
+    writer.WriteBytes(&bgra)?;
+    let buffer = writer.DetachBuffer()?;
+    Ok(SoftwareBitmap::CreateCopyFromBuffer(
+        &buffer,
+        BitmapPixelFormat::Bgra8,
+        width as i32,
+        height as i32,
+    )?)
+}
+
+/// Tesseractの言語コードをBCP 47の言語タグに変換（対応が無い場合はNone）
+fn language_tag(language: &str) -> Option<&'static str> {
+    match language {
+        "jpn" | "jpn_vert" => Some("ja"),
+        "eng" => Some("en-US"),
+        "chi_sim" => Some("zh-Hans"),
+        "chi_tra" => Some("zh-Hant"),
+        "kor" => Some("ko"),
+        _ => None,
+    }
+}
@@ -2,18 +2,226 @@
 use anyhow::{Result, Context};
 use image::{DynamicImage, RgbaImage};
 use screenshots::Screen;
+use std::collections::VecDeque;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
 use std::time::Instant;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::screen_change::CaptureTrigger;
+use crate::validation::{Validate, Validator};
+
+/// キャプチャした画素のチャンネル順
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PixelFormat {
+    /// R, G, B, A の順
+    Rgba,
+    /// B, G, R, A の順（一部の環境のキャプチャAPIが返す形式）
+    Bgra,
+}
+
+impl PixelFormat {
+    /// キャプチャライブラリが返す既定の形式
+    pub fn native() -> Self {
+        PixelFormat::Rgba
+    }
+
+    /// 既知の色（RGB）を写した画素から、変換前の画素バッファの形式を判定
+    ///
+    /// どちらの形式として読んでも既知の色から離れている場合や、RとBが同じ色で区別できない場合はNone。
+    pub fn detect(raw_pixel: [u8; 4], expected_rgb: [u8; 3]) -> Option<Self> {
+        let distance = |channels: [u8; 3]| -> u32 {
+            channels.iter().zip(expected_rgb).map(|(&actual, expected)| u32::from(actual.abs_diff(expected))).sum()
+        };
+        let [first, second, third, _] = raw_pixel;
+        let as_rgba = distance([first, second, third]);
+        let as_bgra = distance([third, second, first]);
+        if as_rgba == as_bgra || as_rgba.min(as_bgra) > FORMAT_DETECT_TOLERANCE {
+            return None;
+        }
+        Some(if as_rgba < as_bgra { PixelFormat::Rgba } else { PixelFormat::Bgra })
+    }
+
+    /// この形式の画素バッファを正規のRGBAに変換
+    pub fn to_canonical_rgba(self, mut image: RgbaImage) -> RgbaImage {
+        if self == PixelFormat::Bgra {
+            // BとRを入れ替える
+            for pixel in image.pixels_mut() {
+                pixel.0.swap(0, 2);
+            }
+        }
+        image
+    }
+}
+
+/// 形式の判定で既知の色と一致するとみなす、チャンネルごとの差の合計の上限
+const FORMAT_DETECT_TOLERANCE: u32 = 48;
+
+/// 複数領域の一括キャプチャを行う面積比の既定値
+const DEFAULT_OVERLAP_RATIO_THRESHOLD: f32 = 1.0;
+
+/// キャプチャの設定
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureConfig {
+    /// キャプチャした画素のチャンネル順（Noneの場合はキャプチャライブラリの既定値）
+    #[serde(default)]
+    pub pixel_format: Option<PixelFormat>,
+    /// 複数領域を囲む矩形の面積が、各領域の面積の合計のこの倍数未満なら一括でキャプチャする
+    #[serde(default = "default_overlap_ratio_threshold")]
+    pub overlap_ratio_threshold: f32,
+    /// キャプチャを行うきっかけ（画面の更新通知はWindowsのみ、他の環境ではポーリング）
+    #[serde(default)]
+    pub trigger: CaptureTrigger,
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        Self {
+            pixel_format: None,
+            overlap_ratio_threshold: DEFAULT_OVERLAP_RATIO_THRESHOLD,
+            trigger: CaptureTrigger::Polling,
+        }
+    }
+}
+
+impl Validate for CaptureConfig {
+    const PREFIX: &'static str = "capture";
+
+    fn check(&self, validator: &mut Validator) {
+        validator.range("overlap_ratio_threshold", self.overlap_ratio_threshold, 0.0, 10.0);
+    }
+}
+
+fn default_overlap_ratio_threshold() -> f32 {
+    DEFAULT_OVERLAP_RATIO_THRESHOLD
+}
+
+impl CaptureConfig {
+    /// 実際に使用するチャンネル順
+    pub fn effective_pixel_format(&self) -> PixelFormat {
+        self.pixel_format.unwrap_or_else(PixelFormat::native)
+    }
+}
+
+/// 格子に合わせる場合の格子の間隔の既定値（ピクセル）
+pub const DEFAULT_GRID_SIZE: u32 = 8;
+
+/// 領域選択の設定
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelectorConfig {
+    /// 選択した領域の位置と大きさを格子に合わせるかどうか
+    #[serde(default)]
+    pub snap_to_grid: bool,
+    /// 格子の間隔（ピクセル、16・32・64などUI部品の大きさに合わせる）
+    #[serde(default = "default_grid_size")]
+    pub grid_size: u32,
+}
+
+impl Default for SelectorConfig {
+    fn default() -> Self {
+        Self {
+            snap_to_grid: false,
+            grid_size: DEFAULT_GRID_SIZE,
+        }
+    }
+}
+
+impl Validate for SelectorConfig {
+    const PREFIX: &'static str = "selector";
+
+    fn check(&self, validator: &mut Validator) {
+        validator.range("grid_size", self.grid_size, 2, 256);
+    }
+}
+
+fn default_grid_size() -> u32 {
+    DEFAULT_GRID_SIZE
+}
+
+impl SelectorConfig {
+    /// 設定に応じた領域の補正方法
+    pub fn snapper(&self) -> RegionSnapper {
+        if self.snap_to_grid {
+            RegionSnapper::GridSnap { grid_size: self.grid_size.max(1) }
+        } else {
+            RegionSnapper::None
+        }
+    }
+}
+
+/// 選択した領域の補正方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionSnapper {
+    /// 補正しない
+    None,
+    /// 位置と大きさを最も近い grid_size の倍数に合わせる（大きさは最小で grid_size）
+    GridSnap { grid_size: u32 },
+}
+
+impl RegionSnapper {
+    /// 領域を補正
+    pub fn snap(&self, region: CaptureRegion) -> CaptureRegion {
+        match *self {
+            RegionSnapper::None => region,
+            RegionSnapper::GridSnap { grid_size } => {
+                let grid = f64::from(grid_size);
+                let snap_position = |value: i32| ((f64::from(value) / grid).round() * grid) as i32;
+                let snap_length = |value: u32| (((f64::from(value) / grid).round() * grid) as u32).max(grid_size);
+                CaptureRegion {
+                    x: snap_position(region.x),
+                    y: snap_position(region.y),
+                    width: snap_length(region.width),
+                    height: snap_length(region.height),
+                    display: region.display,
+                }
+            }
+        }
+    }
+}
+
+/// テストキャプチャのチャンネル順の確認結果
+#[derive(Debug, Clone, Serialize)]
+pub struct CaptureFormatReport {
+    /// 変換前の画素バッファの形式（中央の画素の既知の色から判定、色の指定が無いか判定できない場合はNone）
+    pub raw_format: Option<PixelFormat>,
+    /// 変換に使用した形式（設定値、未設定ならキャプチャライブラリの既定値）
+    pub effective_format: PixelFormat,
+    /// キャプチャした画像の幅
+    pub width: u32,
+    /// キャプチャした画像の高さ
+    pub height: u32,
+    /// 変換前の画素バッファのSHA-256
+    pub raw_checksum: String,
+    /// 変換後の画素バッファのSHA-256
+    pub canonical_checksum: String,
+    /// 中央の画素の変換前の値
+    pub raw_center_pixel: [u8; 4],
+    /// 中央の画素の変換後の値（RGBA）
+    pub canonical_center_pixel: [u8; 4],
+}
+
+/// 画像の取得元（実際の画面の代わりにテスト用の画像を使えるようにする）
+pub trait CaptureSource: Send + Sync {
+    /// 指定された領域の画像を取得
+    fn capture(&self, region: &CaptureRegion) -> Result<DynamicImage>;
+}
 
 /// 画面上の指定領域をキャプチャする構造体
-#[derive(Debug, Clone)]
 pub struct ScreenCapture {
     /// キャプチャする領域の情報
     pub region: CaptureRegion,
+    /// 画像の取得元
+    source: Box<dyn CaptureSource>,
+    /// 一括キャプチャを行う面積比のしきい値
+    overlap_ratio_threshold: f32,
 }
 
 /// キャプチャ領域を表す構造体
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "rest", derive(utoipa::ToSchema))]
 pub struct CaptureRegion {
     /// 左上のX座標
     pub x: i32,
@@ -23,79 +231,1175 @@ pub struct CaptureRegion {
     pub width: u32,
     /// 高さ
     pub height: u32,
+    /// 領域を選択した時点のモニターの情報（解像度・拡大率の変更の検出用、不明ならNone）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<DisplayGeometry>,
+}
+
+/// モニターの位置・解像度・拡大率
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "rest", derive(utoipa::ToSchema))]
+pub struct DisplayGeometry {
+    /// モニターの識別子
+    pub id: u32,
+    /// 左上のX座標（デスクトップ座標）
+    pub x: i32,
+    /// 左上のY座標（デスクトップ座標）
+    pub y: i32,
+    /// 幅
+    pub width: u32,
+    /// 高さ
+    pub height: u32,
+    /// 拡大率
+    pub scale_factor: f32,
+}
+
+impl DisplayGeometry {
+    /// キャプチャライブラリのモニターの情報から作成
+    pub fn from_screen(screen: &Screen) -> Self {
+        let info = &screen.display_info;
+        Self {
+            id: info.id,
+            x: info.x,
+            y: info.y,
+            width: info.width,
+            height: info.height,
+            scale_factor: info.scale_factor,
+        }
+    }
+
+    /// 接続中のすべてのモニターの情報
+    pub fn all() -> Result<Vec<Self>> {
+        let screens = Screen::all()
+            .context("スクリーンの取得に失敗しました")?;
+        Ok(screens.iter().map(Self::from_screen).collect())
+    }
+
+    /// 領域の中心を含むモニターの情報
+    pub fn for_region(region: &CaptureRegion) -> Result<Self> {
+        let screens = Screen::all()
+            .context("スクリーンの取得に失敗しました")?;
+        let index = screen_index_for_region(&screens, region)
+            .context("領域の中心を含むモニターが見つかりません")?;
+        Ok(Self::from_screen(&screens[index]))
+    }
+
+    /// 接続の順番や再起動で変わらないモニターの識別子（位置・解像度・拡大率から作る、例: `2560x1440+0+0@150`）
+    ///
+    /// idはモニターを列挙した順番などで変わりうるため、保存した領域のモニターの照合にはこちらを使う。
+    pub fn stable_id(&self) -> String {
+        format!(
+            "{}x{}+{}+{}@{}",
+            self.width,
+            self.height,
+            self.x,
+            self.y,
+            (self.scale_factor * 100.0).round() as u32
+        )
+    }
+
+    /// 同じモニターの現在の情報（識別子が一致しなければ最も近いモニター、取り外された場合はNone）
+    pub fn current(&self) -> Result<Option<Self>> {
+        Ok(match self.resolve()? {
+            DisplayMatch::Exact(display) | DisplayMatch::Closest(display) => Some(display),
+            DisplayMatch::NotFound => None,
+        })
+    }
+
+    /// 接続中のモニターから同じモニターを探す
+    pub fn resolve(&self) -> Result<DisplayMatch> {
+        let displays = Self::all()?;
+        Ok(match self.position_in(&displays) {
+            Some((index, true)) => DisplayMatch::Exact(displays[index]),
+            Some((index, false)) => DisplayMatch::Closest(displays[index]),
+            None => DisplayMatch::NotFound,
+        })
+    }
+
+    /// 一覧の中の同じモニターの番号と、識別子が一致したかどうか
+    ///
+    /// 識別子が一致するモニターが無ければ、同じ解像度か元の範囲に重なるモニターのうち
+    /// 位置と大きさが最も近いもの（同じ距離ならidが同じもの）を選ぶ。
+    fn position_in(&self, displays: &[DisplayGeometry]) -> Option<(usize, bool)> {
+        let stable_id = self.stable_id();
+        if let Some(index) = displays.iter().position(|display| display.stable_id() == stable_id) {
+            return Some((index, true));
+        }
+        let bounds = self.bounds();
+        displays
+            .iter()
+            .enumerate()
+            .filter(|(_, display)| {
+                (display.width, display.height) == (self.width, self.height) || display.bounds().intersect(&bounds).is_some()
+            })
+            .min_by_key(|(_, display)| (self.distance(display), display.id != self.id))
+            .map(|(index, _)| (index, false))
+    }
+
+    /// 領域全体がこのモニターの範囲に収まるかどうか
+    pub fn contains_region(&self, region: &CaptureRegion) -> bool {
+        self.bounds()
+            .intersect(region)
+            .is_some_and(|inside| (inside.width, inside.height) == (region.width, region.height))
+    }
+
+    /// 領域をこのモニターの範囲との重なりに切り詰める（重ならなければNone）
+    pub fn clamp_region(&self, region: &CaptureRegion) -> Option<CaptureRegion> {
+        region.intersect(&self.bounds())
+    }
+
+    /// 位置と大きさの差の合計（ピクセル）
+    fn distance(&self, other: &DisplayGeometry) -> u64 {
+        (i64::from(self.x) - i64::from(other.x)).unsigned_abs()
+            + (i64::from(self.y) - i64::from(other.y)).unsigned_abs()
+            + u64::from(self.width.abs_diff(other.width))
+            + u64::from(self.height.abs_diff(other.height))
+    }
+
+    /// モニターの範囲（デスクトップ座標）
+    fn bounds(&self) -> CaptureRegion {
+        CaptureRegion {
+            x: self.x,
+            y: self.y,
+            width: self.width,
+            height: self.height,
+            display: None,
+        }
+    }
+
+    /// このモニター上の領域を、解像度が変わった後のモニター上の同じ位置に比例で移す
+    pub fn remap(&self, region: &CaptureRegion, current: &DisplayGeometry) -> CaptureRegion {
+        let scale_x = f64::from(current.width) / f64::from(self.width.max(1));
+        let scale_y = f64::from(current.height) / f64::from(self.height.max(1));
+        let local = region.to_local((self.x, self.y));
+        CaptureRegion {
+            x: current.x.saturating_add((f64::from(local.x) * scale_x).round() as i32),
+            y: current.y.saturating_add((f64::from(local.y) * scale_y).round() as i32),
+            width: ((f64::from(region.width) * scale_x).round() as u32).max(1),
+            height: ((f64::from(region.height) * scale_y).round() as u32).max(1),
+            display: Some(*current),
+        }
+    }
+}
+
+/// 接続中のモニターとの照合の結果
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DisplayMatch {
+    /// 識別子が一致するモニター
+    Exact(DisplayGeometry),
+    /// 識別子は一致しないが、位置と大きさが最も近いモニター
+    Closest(DisplayGeometry),
+    /// 該当するモニターが無い（取り外された）
+    NotFound,
+}
+
+/// list_screensで返すモニターの情報
+#[derive(Debug, Clone, Serialize)]
+pub struct ScreenListing {
+    /// 現在の列挙の順番（接続の順番などで変わる）
+    pub index: usize,
+    /// 接続の順番や再起動で変わらない識別子（DisplayGeometry::stable_id）
+    pub stable_id: String,
+    /// プライマリモニターかどうか
+    pub is_primary: bool,
+    /// 位置・解像度・拡大率
+    pub geometry: DisplayGeometry,
+}
+
+impl ScreenListing {
+    /// 接続中のすべてのモニター
+    pub fn all() -> Result<Vec<Self>> {
+        let screens = Screen::all()
+            .context("スクリーンの取得に失敗しました")?;
+        Ok(screens
+            .iter()
+            .enumerate()
+            .map(|(index, screen)| {
+                let geometry = DisplayGeometry::from_screen(screen);
+                Self {
+                    index,
+                    stable_id: geometry.stable_id(),
+                    is_primary: screen.display_info.is_primary,
+                    geometry,
+                }
+            })
+            .collect())
+    }
+}
+
+/// 領域の中心を含むモニターの番号
+fn screen_index_for_region(screens: &[Screen], region: &CaptureRegion) -> Option<usize> {
+    let (center_x, center_y) = region.center();
+    screens.iter().position(|screen| {
+        let info = &screen.display_info;
+        let bounds = CaptureRegion {
+            x: info.x,
+            y: info.y,
+            width: info.width,
+            height: info.height,
+            display: None,
+        };
+        bounds.contains(center_x, center_y)
+    })
+}
+
+/// 監視する領域を確認し、モニターの情報が無ければ現在のモニターを選択時の値とする
+///
+/// 監視の開始（start_monitoring）と開始前の確認（validate_monitoring）で同じ判定にするため、両方から使う。
+pub fn prepare_session_region(region: CaptureRegion) -> Result<CaptureRegion> {
+    prepare_session_region_with(region, DisplayGeometry::for_region)
+}
+
+/// モニターの取得方法を指定して監視する領域を確認
+fn prepare_session_region_with(
+    mut region: CaptureRegion,
+    find_display: impl FnOnce(&CaptureRegion) -> Result<DisplayGeometry>,
+) -> Result<CaptureRegion> {
+    if region.width == 0 || region.height == 0 {
+        anyhow::bail!("大きさが0の領域です: {}x{}", region.width, region.height);
+    }
+    let display = match region.display {
+        Some(display) => display,
+        None => find_display(&region)?,
+    };
+    if !display.contains_region(&region) {
+        anyhow::bail!("領域がモニター {} の範囲からはみ出しています", display.stable_id());
+    }
+    region.display = Some(display);
+    Ok(region)
+}
+
+#[allow(dead_code)]
+impl CaptureRegion {
+    /// JSON文字列から領域を読み込む
+    pub fn from_json(s: &str) -> Result<CaptureRegion, serde_json::Error> {
+        serde_json::from_str(s)
+    }
+
+    /// 領域をJSON文字列に変換
+    pub fn into_json(self) -> String {
+        // 数値と固定のフィールド名のみのためシリアライズは失敗しない
+        serde_json::to_string(&self).expect("CaptureRegionのJSON変換に失敗しました")
+    }
+
+    /// JSONファイルから領域を読み込む
+    pub fn from_file(path: &Path) -> Result<CaptureRegion> {
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("領域ファイルの読み込みに失敗しました: {}", path.display()))?;
+        Self::from_json(&json)
+            .with_context(|| format!("領域ファイルの形式が正しくありません: {}", path.display()))
+    }
+
+    /// 面積（ピクセル数）
+    pub fn area(&self) -> u64 {
+        u64::from(self.width) * u64::from(self.height)
+    }
+
+    /// 複数の領域を囲む最小の領域（空ならNone）
+    pub fn bounding_union(regions: &[CaptureRegion]) -> Option<CaptureRegion> {
+        let first = regions.first()?;
+        let (mut left, mut top) = (i64::from(first.x), i64::from(first.y));
+        let (mut right, mut bottom) = (first.right(), first.bottom());
+        for region in &regions[1..] {
+            left = left.min(i64::from(region.x));
+            top = top.min(i64::from(region.y));
+            right = right.max(region.right());
+            bottom = bottom.max(region.bottom());
+        }
+        // 大きさが0の領域だけの場合も左上の位置は残す
+        Some(first.with_edges(left, top, right, bottom).unwrap_or(*first))
+    }
+
+    /// 左上と右下（右下は含まない）の座標から領域を作成（空の矩形はNone、i32に収まらない部分は切り詰める）
+    pub fn from_edges(left: i64, top: i64, right: i64, bottom: i64) -> Option<CaptureRegion> {
+        let clamp = |value: i64| value.clamp(i64::from(i32::MIN), i64::from(i32::MAX));
+        let (left, top, right, bottom) = (clamp(left), clamp(top), clamp(right), clamp(bottom));
+        if right <= left || bottom <= top {
+            return None;
+        }
+        Some(CaptureRegion {
+            x: left as i32,
+            y: top as i32,
+            width: (right - left).min(i64::from(u32::MAX)) as u32,
+            height: (bottom - top).min(i64::from(u32::MAX)) as u32,
+            display: None,
+        })
+    }
+
+    /// 右端のX座標（含まない）
+    fn right(&self) -> i64 {
+        i64::from(self.x) + i64::from(self.width)
+    }
+
+    /// 下端のY座標（含まない）
+    fn bottom(&self) -> i64 {
+        i64::from(self.y) + i64::from(self.height)
+    }
+
+    /// 四辺の座標から、モニターの情報を引き継いだ領域を作成
+    fn with_edges(&self, left: i64, top: i64, right: i64, bottom: i64) -> Option<CaptureRegion> {
+        Self::from_edges(left, top, right, bottom).map(|region| CaptureRegion {
+            display: self.display,
+            ..region
+        })
+    }
+
+    /// 四辺を margin だけ広げた領域
+    pub fn expanded(&self, margin: u32) -> CaptureRegion {
+        let margin = i64::from(margin);
+        self.with_edges(
+            i64::from(self.x) - margin,
+            i64::from(self.y) - margin,
+            self.right() + margin,
+            self.bottom() + margin,
+        )
+        .unwrap_or(*self)
+    }
+
+    /// 2つの領域の重なり（重ならなければNone）
+    pub fn intersect(&self, other: &CaptureRegion) -> Option<CaptureRegion> {
+        self.with_edges(
+            i64::from(self.x.max(other.x)),
+            i64::from(self.y.max(other.y)),
+            self.right().min(other.right()),
+            self.bottom().min(other.bottom()),
+        )
+    }
+
+    /// 平行移動した領域（座標はi32の範囲で飽和する）
+    pub fn translated(&self, dx: i32, dy: i32) -> CaptureRegion {
+        self.offset(i64::from(dx), i64::from(dy))
+    }
+
+    /// 平行移動（i32::MINの符号反転でも溢れないようi64で計算）
+    fn offset(&self, dx: i64, dy: i64) -> CaptureRegion {
+        CaptureRegion {
+            x: (i64::from(self.x) + dx).clamp(i64::from(i32::MIN), i64::from(i32::MAX)) as i32,
+            y: (i64::from(self.y) + dy).clamp(i64::from(i32::MIN), i64::from(i32::MAX)) as i32,
+            ..*self
+        }
+    }
+
+    /// 座標と大きさを factor 倍（正の値）した領域
+    ///
+    /// 拡大率の変換用。四辺をそれぞれ丸めるため、往復しても各辺は1ピクセル以内に戻る。
+    /// 縮小しても幅・高さは1ピクセル以上残す。
+    pub fn scaled(&self, factor: f64) -> CaptureRegion {
+        // f64からの変換は範囲外なら飽和する
+        let scale = |value: i64| (value as f64 * factor).round() as i64;
+        let (left, top) = (scale(i64::from(self.x)), scale(i64::from(self.y)));
+        let right = scale(self.right()).max(left + 1);
+        let bottom = scale(self.bottom()).max(top + 1);
+        self.with_edges(left, top, right, bottom).unwrap_or(*self)
+    }
+
+    /// デスクトップ座標をモニターの左上を原点とする座標にする
+    pub fn to_local(self, display_origin: (i32, i32)) -> CaptureRegion {
+        self.offset(-i64::from(display_origin.0), -i64::from(display_origin.1))
+    }
+
+    /// モニターの左上を原点とする座標をデスクトップ座標にする（to_localの逆）
+    #[allow(clippy::wrong_self_convention)]
+    pub fn from_local(self, display_origin: (i32, i32)) -> CaptureRegion {
+        self.offset(i64::from(display_origin.0), i64::from(display_origin.1))
+    }
+
+    /// 点が領域に含まれるかどうか（右端・下端は含まない）
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        let (x, y) = (i64::from(x), i64::from(y));
+        x >= i64::from(self.x) && x < self.right() && y >= i64::from(self.y) && y < self.bottom()
+    }
+
+    /// 中心の座標
+    fn center(&self) -> (i32, i32) {
+        // 幅の半分はi32に収まる
+        (self.x.saturating_add((self.width / 2) as i32), self.y.saturating_add((self.height / 2) as i32))
+    }
+
+    /// 領域をJSONファイルに保存
+    pub fn to_file(self, path: &Path) -> Result<()> {
+        fs::write(path, self.into_json())
+            .with_context(|| format!("領域ファイルの保存に失敗しました: {}", path.display()))
+    }
 }
 
 impl ScreenCapture {
-    /// 新しいScreenCaptureインスタンスを作成
+    /// 新しいScreenCaptureインスタンスを作成（実際の画面をキャプチャ）
+    #[allow(dead_code)]
     pub fn new(region: CaptureRegion) -> Self {
-        Self { region }
+        Self::with_source(region, Box::new(LiveScreenSource::default()))
+    }
+
+    /// キャプチャの設定を反映して実際の画面をキャプチャするインスタンスを作成
+    pub fn with_config(region: CaptureRegion, config: &CaptureConfig) -> Self {
+        let mut capture = Self::with_source(region, Box::new(LiveScreenSource::new(config.effective_pixel_format())));
+        capture.overlap_ratio_threshold = config.overlap_ratio_threshold;
+        capture
+    }
+
+    /// 画像の取得元を指定してScreenCaptureインスタンスを作成
+    pub fn with_source(region: CaptureRegion, source: Box<dyn CaptureSource>) -> Self {
+        Self {
+            region,
+            source,
+            overlap_ratio_threshold: DEFAULT_OVERLAP_RATIO_THRESHOLD,
+        }
     }
 
     /// 指定された領域の画面をキャプチャ
     pub fn capture(&self) -> Result<DynamicImage> {
-        let start = Instant::now();
-        
-        // プライマリスクリーンを取得
+        self.source.capture(&self.region)
+    }
+
+    /// 複数の領域をキャプチャし、指定順に画像を返す
+    ///
+    /// 領域が重なっている・近い場合は、全体を囲む矩形を1回だけキャプチャして切り出す。
+    /// 囲む矩形の面積が各領域の面積の合計のしきい値倍以上になる（領域が離れている）場合は
+    /// 領域ごとにキャプチャする。
+    #[allow(dead_code)]
+    pub fn capture_batch(&self, regions: &[CaptureRegion]) -> Result<Vec<DynamicImage>> {
+        let Some(union) = CaptureRegion::bounding_union(regions) else {
+            return Ok(Vec::new());
+        };
+        let total_area: u64 = regions.iter().map(CaptureRegion::area).sum();
+
+        let capture_each = || regions.iter().map(|region| self.source.capture(region)).collect();
+        if regions.len() < 2 || union.area() as f64 >= total_area as f64 * f64::from(self.overlap_ratio_threshold) {
+            return capture_each();
+        }
+
+        log::debug!("{}領域を一括でキャプチャします: {:?}", regions.len(), union);
+        let image = match self.source.capture(&union) {
+            Ok(image) => image,
+            Err(e) => {
+                // 囲む矩形がサイズ制限を超える場合などは領域ごとにキャプチャする
+                log::debug!("一括キャプチャに失敗したため領域ごとにキャプチャします: {}", e);
+                return capture_each();
+            }
+        };
+        Ok(regions
+            .iter()
+            .map(|region| {
+                let offset = region.to_local((union.x, union.y));
+                image.crop_imm(offset.x as u32, offset.y as u32, region.width, region.height)
+            })
+            .collect())
+    }
+
+    /// 現在のマウスカーソルの位置（デスクトップ座標）
+    pub fn capture_cursor_position() -> Result<(i32, i32)> {
+        #[cfg(target_os = "windows")]
+        {
+            let mut point = windows::Win32::Foundation::POINT::default();
+            unsafe { windows::Win32::UI::WindowsAndMessaging::GetCursorPos(&mut point) }
+                .context("カーソルの位置を取得できませんでした")?;
+            Ok((point.x, point.y))
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            macos_cursor::position()
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            if !Self::cursor_position_supported() {
+                anyhow::bail!("X11のセッションでのみカーソルの位置を取得できます（Waylandでは取得できません）");
+            }
+            x11_cursor::position()
+        }
+
+        #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+        {
+            anyhow::bail!("この環境ではカーソルの位置を取得できません")
+        }
+    }
+
+    /// この環境でカーソルの位置を取得できるか（LinuxではX11のセッションのみ、Waylandはカーソルの位置を公開しない）
+    pub fn cursor_position_supported() -> bool {
+        if cfg!(any(target_os = "windows", target_os = "macos")) {
+            true
+        } else if cfg!(target_os = "linux") {
+            std::env::var_os("WAYLAND_DISPLAY").is_none() && std::env::var_os("DISPLAY").is_some()
+        } else {
+            false
+        }
+    }
+
+    /// マウスカーソルが領域の中にあるかどうか
+    pub fn region_contains_cursor(region: &CaptureRegion) -> Result<bool> {
+        let (x, y) = Self::capture_cursor_position()?;
+        Ok(region.contains(x, y))
+    }
+
+    /// 全画面をキャプチャ（領域選択用）
+    pub fn capture_full_screen() -> Result<DynamicImage> {
         let screens = Screen::all()
             .context("スクリーンの取得に失敗しました")?;
         
         let screen = screens.first()
             .context("プライマリスクリーンが見つかりません")?;
 
+        let image = screen.capture()
+            .context("全画面のキャプチャに失敗しました")?;
+
+        // DynamicImageに変換
+        Ok(DynamicImage::ImageRgba8(image))
+    }
+
+    /// すべてのモニターの全画面をキャプチャし、モニターの番号と画像の組を返す
+    #[allow(dead_code)]
+    pub fn capture_all_monitors() -> Result<Vec<(usize, DynamicImage)>> {
+        let screens = Screen::all()
+            .context("スクリーンの取得に失敗しました")?;
+
+        screens
+            .iter()
+            .enumerate()
+            .map(|(index, screen)| {
+                let image = screen.capture()
+                    .with_context(|| format!("モニター{}のキャプチャに失敗しました", index))?;
+                Ok((index, DynamicImage::ImageRgba8(image)))
+            })
+            .collect()
+    }
+
+    /// 領域の中心を含むモニターの番号
+    #[allow(dead_code)]
+    pub fn find_monitor_for_region(region: &CaptureRegion) -> Result<usize> {
+        let screens = Screen::all()
+            .context("スクリーンの取得に失敗しました")?;
+
+        screen_index_for_region(&screens, region)
+            .with_context(|| format!("領域の中心を含むモニターが見つかりません: {:?}", region))
+    }
+}
+
+/// 実際の画面からキャプチャする取得元
+pub struct LiveScreenSource {
+    /// キャプチャライブラリが返す画素のチャンネル順
+    pixel_format: PixelFormat,
+}
+
+impl Default for LiveScreenSource {
+    fn default() -> Self {
+        Self::new(PixelFormat::native())
+    }
+}
+
+impl LiveScreenSource {
+    /// チャンネル順を指定してLiveScreenSourceを作成
+    pub fn new(pixel_format: PixelFormat) -> Self {
+        Self { pixel_format }
+    }
+
+    /// ScreenImageを正規のRgbaImageに変換
+    fn screen_image_to_rgba(&self, screen_img: RgbaImage) -> RgbaImage {
+        self.pixel_format.to_canonical_rgba(screen_img)
+    }
+
+    /// テストキャプチャを行い、変換前後のチェックサムと中央の画素を報告
+    ///
+    /// expected_center_colorには領域の中央に写っている色（RGB）を指定し、変換前の形式の判定に使う。
+    pub fn inspect(&self, region: &CaptureRegion, expected_center_color: Option<[u8; 3]>) -> Result<CaptureFormatReport> {
+        let raw = self.capture_raw(region)?;
+        let (width, height) = raw.dimensions();
+        let raw_center_pixel = raw.get_pixel(width / 2, height / 2).0;
+        let raw_checksum = sha256_hex(raw.as_raw());
+
+        let canonical = self.screen_image_to_rgba(raw);
+        Ok(CaptureFormatReport {
+            raw_format: expected_center_color.and_then(|expected| PixelFormat::detect(raw_center_pixel, expected)),
+            effective_format: self.pixel_format,
+            width,
+            height,
+            raw_checksum,
+            canonical_checksum: sha256_hex(canonical.as_raw()),
+            raw_center_pixel,
+            canonical_center_pixel: canonical.get_pixel(width / 2, height / 2).0,
+        })
+    }
+
+    /// 指定された領域の画面を変換せずにキャプチャ（チャンネル順の確認用）
+    pub fn capture_raw(&self, region: &CaptureRegion) -> Result<RgbaImage> {
+        let screens = Screen::all()
+            .context("スクリーンの取得に失敗しました")?;
+        
+        // 選択時のモニターが分かれば同じモニター（識別子が一致しなければ最も近いモニター）で、
+        // 分からなければプライマリスクリーンでキャプチャする（座標はモニターの左上からにする）
+        let displays: Vec<DisplayGeometry> = screens.iter().map(DisplayGeometry::from_screen).collect();
+        let matched = region
+            .display
+            .and_then(|original| original.position_in(&displays).map(|(index, _)| (index, original)));
+        let (screen, region) = match matched {
+            Some((index, original)) => (&screens[index], region.to_local((original.x, original.y))),
+            None => (
+                screens.first().context("プライマリスクリーンが見つかりません")?,
+                *region,
+            ),
+        };
+
         // 座標とサイズのバリデーション（EXC_BAD_ACCESS回避）
-        if self.region.x < 0 || self.region.y < 0 {
-            return Err(anyhow::anyhow!("座標が負の値です: x={}, y={}", self.region.x, self.region.y));
+        if region.x < 0 || region.y < 0 {
+            return Err(anyhow::anyhow!("座標が負の値です: x={}, y={}", region.x, region.y));
         }
         
-        if self.region.width == 0 || self.region.height == 0 {
-            return Err(anyhow::anyhow!("サイズが無効です: width={}, height={}", self.region.width, self.region.height));
+        if region.width == 0 || region.height == 0 {
+            return Err(anyhow::anyhow!("サイズが無効です: width={}, height={}", region.width, region.height));
         }
 
         // 安全なサイズ制限（メモリ保護）
-        if self.region.width > 2048 || self.region.height > 2048 {
-            return Err(anyhow::anyhow!("キャプチャサイズが大きすぎます: {}x{}", self.region.width, self.region.height));
+        if region.width > 2048 || region.height > 2048 {
+            return Err(anyhow::anyhow!("キャプチャサイズが大きすぎます: {}x{}", region.width, region.height));
         }
 
         // 画面境界チェック（可能な範囲で）
-        if self.region.x > 5120 || self.region.y > 2880 { // 一般的な大画面の最大解像度考慮
-            return Err(anyhow::anyhow!("座標が画面範囲を超えています: x={}, y={}", self.region.x, self.region.y));
+        if region.x > 5120 || region.y > 2880 { // 一般的な大画面の最大解像度考慮
+            return Err(anyhow::anyhow!("座標が画面範囲を超えています: x={}, y={}", region.x, region.y));
         }
 
         // 指定領域をキャプチャ
-        let image = screen.capture_area(
-            self.region.x,
-            self.region.y,
-            self.region.width,
-            self.region.height,
-        ).context("画面のキャプチャに失敗しました")?;
+        screen.capture_area(
+            region.x,
+            region.y,
+            region.width,
+            region.height,
+        ).context("画面のキャプチャに失敗しました")
+    }
+}
 
-        // DynamicImageに変換
-        let rgba_image = self.screen_image_to_rgba(image)?;
+impl CaptureSource for LiveScreenSource {
+    /// 指定された領域の画面をキャプチャ
+    fn capture(&self, region: &CaptureRegion) -> Result<DynamicImage> {
+        let start = Instant::now();
+        let image = self.capture_raw(region)?;
+
+        // 正規のRGBAに変換してからDynamicImageにする
+        let rgba_image = self.screen_image_to_rgba(image);
         let dynamic_image = DynamicImage::ImageRgba8(rgba_image);
 
         log::debug!("キャプチャ完了: {:?}", start.elapsed());
 
         Ok(dynamic_image)
     }
+}
+
+/// バイト列のSHA-256を16進文字列にする
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
 
-    /// ScreenImageをRgbaImageに変換
-    fn screen_image_to_rgba(&self, screen_img: image::RgbaImage) -> Result<RgbaImage> {
-        Ok(screen_img)
+/// 常に同じ画像を返す取得元（領域の指定は無視する）
+#[allow(dead_code)]
+pub struct StaticImageSource {
+    pub image: DynamicImage,
+}
+
+impl CaptureSource for StaticImageSource {
+    fn capture(&self, _region: &CaptureRegion) -> Result<DynamicImage> {
+        Ok(self.image.clone())
     }
+}
 
-    /// 全画面をキャプチャ（領域選択用）
-    #[allow(dead_code)]
-    pub fn capture_full_screen() -> Result<DynamicImage> {
-        let screens = Screen::all()
-            .context("スクリーンの取得に失敗しました")?;
-        
-        let screen = screens.first()
-            .context("プライマリスクリーンが見つかりません")?;
+/// 登録した画像を順番に繰り返し返す取得元（領域の指定は無視する）
+#[allow(dead_code)]
+pub struct ImageSequenceSource {
+    frames: Mutex<VecDeque<DynamicImage>>,
+}
 
-        let image = screen.capture()
-            .context("全画面のキャプチャに失敗しました")?;
+#[allow(dead_code)]
+impl ImageSequenceSource {
+    /// 新しいImageSequenceSourceを作成
+    pub fn new(frames: impl IntoIterator<Item = DynamicImage>) -> Self {
+        Self {
+            frames: Mutex::new(frames.into_iter().collect()),
+        }
+    }
+}
 
-        // DynamicImageに変換
-        Ok(DynamicImage::ImageRgba8(image))
+impl CaptureSource for ImageSequenceSource {
+    fn capture(&self, _region: &CaptureRegion) -> Result<DynamicImage> {
+        let mut frames = self.frames.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        // 先頭のフレームを返し、末尾に戻して循環させる
+        let frame = frames.pop_front().context("画像が登録されていません")?;
+        frames.push_back(frame.clone());
+        Ok(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::Xorshift;
+
+    #[test]
+    fn session_region_must_fit_its_display() {
+        let screen = display(0, 1920, 1080, 1.0);
+        let found = |_: &CaptureRegion| Ok(screen);
+        let prepared = prepare_session_region_with(region(100, 100, 400, 300), found).unwrap();
+        assert_eq!(prepared.display, Some(screen));
+
+        assert!(prepare_session_region_with(region(1800, 100, 400, 300), found).is_err());
+        assert!(prepare_session_region_with(region(100, 100, 0, 300), found).is_err());
+        assert!(prepare_session_region_with(region(100, 100, 400, 300), |_: &CaptureRegion| {
+            Err(anyhow::anyhow!("モニターが見つかりません"))
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn detects_raw_format_from_a_known_color() {
+        // 赤（255, 32, 0）を写した画素
+        assert_eq!(PixelFormat::detect([250, 30, 4, 255], [255, 32, 0]), Some(PixelFormat::Rgba));
+        assert_eq!(PixelFormat::detect([4, 30, 250, 255], [255, 32, 0]), Some(PixelFormat::Bgra));
+        // RとBが同じ色や、既知の色と異なる画素は判定しない
+        assert_eq!(PixelFormat::detect([128, 128, 128, 255], [128, 128, 128]), None);
+        assert_eq!(PixelFormat::detect([0, 255, 0, 255], [255, 32, 0]), None);
+    }
+
+    /// モニターの範囲外をキャプチャすると失敗する、画面の代わりの取得元（画像は物理ピクセルの大きさ）
+    struct MockDisplaySource {
+        display: DisplayGeometry,
+    }
+
+    impl CaptureSource for MockDisplaySource {
+        fn capture(&self, region: &CaptureRegion) -> Result<DynamicImage> {
+            let local = region.to_local((self.display.x, self.display.y));
+            if local.x < 0
+                || local.y < 0
+                || local.right() > i64::from(self.display.width)
+                || local.bottom() > i64::from(self.display.height)
+            {
+                anyhow::bail!("モニターの範囲外です: {:?}", local);
+            }
+            let scale = self.display.scale_factor;
+            Ok(DynamicImage::ImageRgba8(RgbaImage::new(
+                (local.width as f32 * scale).round() as u32,
+                (local.height as f32 * scale).round() as u32,
+            )))
+        }
+    }
+
+    fn display(x: i32, width: u32, height: u32, scale_factor: f32) -> DisplayGeometry {
+        DisplayGeometry {
+            id: 1,
+            x,
+            y: 0,
+            width,
+            height,
+            scale_factor,
+        }
+    }
+
+    fn region(x: i32, y: i32, width: u32, height: u32) -> CaptureRegion {
+        CaptureRegion {
+            x,
+            y,
+            width,
+            height,
+            display: None,
+        }
+    }
+
+    fn capture_on(display: DisplayGeometry, region: CaptureRegion) -> Result<DynamicImage> {
+        ScreenCapture::with_source(region, Box::new(MockDisplaySource { display })).capture()
+    }
+
+    #[test]
+    fn clamps_off_by_one_region_on_1x_display() {
+        let display = display(0, 1920, 1080, 1.0);
+        let oversized = region(0, 0, 1920, 1082);
+        assert!(capture_on(display, oversized).is_err());
+
+        let clamped = display.clamp_region(&oversized).unwrap();
+        assert_eq!(clamped, region(0, 0, 1920, 1080));
+        assert!(display.contains_region(&clamped));
+        let image = capture_on(display, clamped).unwrap();
+        assert_eq!((image.width(), image.height()), (1920, 1080));
+    }
+
+    #[test]
+    fn clamps_off_by_one_region_on_2x_display() {
+        // 1920x1080のモニターの右にある、論理解像度1440x900で拡大率2のモニター
+        let display = display(1920, 1440, 900, 2.0);
+        let oversized = region(2000, -1, 1361, 902);
+        assert!(capture_on(display, oversized).is_err());
+
+        let clamped = display.clamp_region(&oversized).unwrap();
+        assert_eq!(clamped, region(2000, 0, 1360, 900));
+        assert!(display.contains_region(&clamped));
+        let image = capture_on(display, clamped).unwrap();
+        assert_eq!((image.width(), image.height()), (2720, 1800));
+    }
+
+    #[test]
+    fn keeps_regions_inside_the_display() {
+        let display = display(0, 1920, 1080, 1.0);
+        let inside = region(100, 200, 300, 40);
+        assert_eq!(display.clamp_region(&inside), Some(inside));
+    }
+
+    #[test]
+    fn rejects_regions_outside_the_display() {
+        let display = display(1920, 1440, 900, 2.0);
+        assert_eq!(display.clamp_region(&region(0, 0, 1920, 1080)), None);
+        assert_eq!(display.clamp_region(&region(1920, 900, 100, 100)), None);
+    }
+
+    /// テストごとの一時ディレクトリ
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("capture_region_{}_{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn region_file_round_trips() {
+        let dir = temp_dir("round_trip");
+        let with_display = CaptureRegion {
+            display: Some(display(1920, 1440, 900, 2.0)),
+            ..region(-10, 20, 300, 40)
+        };
+        for (name, saved) in [("plain.json", region(100, 200, 300, 40)), ("display.json", with_display)] {
+            let path = dir.join(name);
+            saved.to_file(&path).unwrap();
+            assert_eq!(CaptureRegion::from_file(&path).unwrap(), saved);
+            assert_eq!(CaptureRegion::from_json(&saved.into_json()).unwrap(), saved);
+        }
+        // モニターの情報が無い領域はdisplayを書き出さない
+        assert_eq!(
+            fs::read_to_string(dir.join("plain.json")).unwrap(),
+            r#"{"x":100,"y":200,"width":300,"height":40}"#
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn malformed_region_file_is_an_error() {
+        let dir = temp_dir("malformed");
+        let path = dir.join("region.json");
+        fs::write(&path, r#"{"x":100,"y":200,"width":-300}"#).unwrap();
+        let error = CaptureRegion::from_file(&path).unwrap_err();
+        assert!(error.to_string().contains("領域ファイルの形式が正しくありません"), "{:#}", error);
+        assert!(CaptureRegion::from_json("not json").is_err());
+        assert!(CaptureRegion::from_json(r#"{"x":1,"y":2,"width":3}"#).is_err());
+
+        let missing = CaptureRegion::from_file(&dir.join("missing.json")).unwrap_err();
+        assert!(missing.to_string().contains("領域ファイルの読み込みに失敗しました"), "{:#}", missing);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// 左上が負の座標にもなるランダムな領域
+    fn random_region(rng: &mut Xorshift) -> CaptureRegion {
+        region(
+            rng.between(-3000, 3000),
+            rng.between(-3000, 3000),
+            rng.between(1, 2000) as u32,
+            rng.between(1, 2000) as u32,
+        )
+    }
+
+    /// 四辺の座標（左、上、右、下）
+    fn edges(region: &CaptureRegion) -> [i64; 4] {
+        [i64::from(region.x), i64::from(region.y), region.right(), region.bottom()]
+    }
+
+    #[test]
+    fn intersection_is_commutative_and_inside_both() {
+        let mut rng = Xorshift(0x51a7_e3c1_0b5d_d6a9);
+        for _ in 0..1_000 {
+            let (a, b) = (random_region(&mut rng), random_region(&mut rng));
+            let overlap = a.intersect(&b);
+            assert_eq!(overlap, b.intersect(&a), "{:?} / {:?}", a, b);
+            for _ in 0..20 {
+                let (x, y) = (rng.between(-3000, 5000), rng.between(-3000, 5000));
+                let in_both = a.contains(x, y) && b.contains(x, y);
+                assert_eq!(overlap.is_some_and(|overlap| overlap.contains(x, y)), in_both, "{:?} / {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn intersection_with_itself_or_an_expanded_copy_is_unchanged() {
+        let mut rng = Xorshift(0x0ddc_0ffe_ebad_f00d);
+        for _ in 0..1_000 {
+            let region = random_region(&mut rng);
+            assert_eq!(region.intersect(&region), Some(region));
+            let margin = rng.below(500) as u32;
+            let expanded = region.expanded(margin);
+            let [left, top, right, bottom] = edges(&region);
+            let m = i64::from(margin);
+            assert_eq!(edges(&expanded), [left - m, top - m, right + m, bottom + m]);
+            assert_eq!(expanded.intersect(&region), Some(region));
+        }
+        assert_eq!(region(-10, -20, 5, 5).expanded(0), region(-10, -20, 5, 5));
+    }
+
+    #[test]
+    fn disjoint_regions_do_not_intersect() {
+        assert_eq!(region(-100, -100, 100, 100).intersect(&region(0, 0, 10, 10)), None);
+        assert_eq!(region(-100, 0, 100, 100).intersect(&region(-50, 100, 10, 10)), None);
+        assert_eq!(
+            region(-100, -100, 101, 101).intersect(&region(0, 0, 10, 10)),
+            Some(region(0, 0, 1, 1))
+        );
+    }
+
+    #[test]
+    fn scaling_round_trips_within_a_pixel() {
+        let mut rng = Xorshift(0x7f4a_7c15_9e37_79b9);
+        for _ in 0..1_000 {
+            let region = random_region(&mut rng);
+            let factor = [1.0, 1.25, 1.5, 1.75, 2.0, 2.5, 3.0][rng.below(7)];
+            let round_trip = region.scaled(factor).scaled(1.0 / factor);
+            for (original, restored) in edges(&region).into_iter().zip(edges(&round_trip)) {
+                assert!((original - restored).abs() <= 1, "{:?} x{} -> {:?}", region, factor, round_trip);
+            }
+        }
+        assert_eq!(region(-101, -51, 3, 3).scaled(2.0), region(-202, -102, 6, 6));
+        // 縮小しても1ピクセルは残す
+        assert_eq!(region(-7, 9, 1, 1).scaled(0.1), region(-1, 1, 1, 1));
+    }
+}
+
+/// CoreGraphicsのイベントからカーソルの位置を取得（macOSのみ）
+#[cfg(target_os = "macos")]
+mod macos_cursor {
+    use anyhow::Result;
+    use std::ffi::c_void;
+
+    #[repr(C)]
+    struct CGPoint {
+        x: f64,
+        y: f64,
+    }
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGEventCreate(source: *const c_void) -> *mut c_void;
+        fn CGEventGetLocation(event: *const c_void) -> CGPoint;
     }
-}
\ No newline at end of file
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        fn CFRelease(cf: *const c_void);
+    }
+
+    /// 現在のカーソルの位置（メインディスプレイの左上を原点とするポイント単位の座標、キャプチャの領域と同じ）
+    pub fn position() -> Result<(i32, i32)> {
+        // 元の無いイベントを作ると、作った時点のカーソルの位置が入る
+        let event = unsafe { CGEventCreate(std::ptr::null()) };
+        if event.is_null() {
+            anyhow::bail!("カーソルの位置を取得できませんでした（CGEventCreateが失敗しました）");
+        }
+        let point = unsafe { CGEventGetLocation(event) };
+        unsafe { CFRelease(event) };
+        Ok((point.x.floor() as i32, point.y.floor() as i32))
+    }
+}
+
+/// X11のルートウィンドウからカーソルの位置を取得（Linuxのみ）
+#[cfg(target_os = "linux")]
+mod x11_cursor {
+    use anyhow::Result;
+    use std::ffi::c_void;
+    use std::os::raw::{c_char, c_int, c_uint, c_ulong};
+    use std::ptr;
+
+    #[link(name = "X11")]
+    extern "C" {
+        fn XOpenDisplay(name: *const c_char) -> *mut c_void;
+        fn XDefaultRootWindow(display: *mut c_void) -> c_ulong;
+        fn XQueryPointer(
+            display: *mut c_void,
+            window: c_ulong,
+            root_return: *mut c_ulong,
+            child_return: *mut c_ulong,
+            root_x: *mut c_int,
+            root_y: *mut c_int,
+            window_x: *mut c_int,
+            window_y: *mut c_int,
+            mask: *mut c_uint,
+        ) -> c_int;
+        fn XCloseDisplay(display: *mut c_void) -> c_int;
+    }
+
+    /// 現在のカーソルの位置（ルートウィンドウの座標、キャプチャの領域と同じ）
+    ///
+    /// Xlibの接続はスレッドをまたいで使えないため、呼び出しごとに接続する（監視の間隔より十分短い）。
+    pub fn position() -> Result<(i32, i32)> {
+        let display = unsafe { XOpenDisplay(ptr::null()) };
+        if display.is_null() {
+            anyhow::bail!("X11のディスプレイに接続できないため、カーソルの位置を取得できません");
+        }
+        let (mut root, mut child) = (0, 0);
+        let (mut root_x, mut root_y, mut window_x, mut window_y) = (0, 0, 0, 0);
+        let mut mask = 0;
+        let on_screen = unsafe {
+            let root_window = XDefaultRootWindow(display);
+            let result = XQueryPointer(
+                display,
+                root_window,
+                &mut root,
+                &mut child,
+                &mut root_x,
+                &mut root_y,
+                &mut window_x,
+                &mut window_y,
+                &mut mask,
+            );
+            XCloseDisplay(display);
+            result != 0
+        };
+        // カーソルが別のスクリーンにある場合は位置が無い
+        if !on_screen {
+            anyhow::bail!("カーソルが既定のスクリーンの外にあります");
+        }
+        Ok((root_x, root_y))
+    }
+}
+
+/// 仮想フレームバッファの画面を実際にキャプチャするテスト（ci_x11フィーチャー有効時のみ）
+///
+/// ルートウィンドウをxsetrootで既知の色に塗り、キャプチャした画素の色を確かめる。
+/// DISPLAYが設定されていない場合は何もせずに成功とする。
+#[cfg(all(test, target_os = "linux", feature = "ci_x11"))]
+mod x11_tests {
+    use super::*;
+    use std::process::Command;
+
+    /// ルートウィンドウを指定した色で塗る（塗れなければfalse）
+    fn paint_root(color: &str) -> bool {
+        Command::new("xsetroot")
+            .args(["-solid", color])
+            .status()
+            .map(|status| status.success())
+            .unwrap_or(false)
+    }
+
+    fn dominant_pixel(image: &DynamicImage) -> [u8; 3] {
+        let image = image.to_rgb8();
+        let pixel = image.get_pixel(image.width() / 2, image.height() / 2);
+        pixel.0
+    }
+
+    #[test]
+    fn captures_known_colors_from_xvfb() {
+        if std::env::var_os("DISPLAY").is_none() {
+            eprintln!("DISPLAYが設定されていないため、Xvfbのキャプチャのテストを省略します");
+            return;
+        }
+        let region = CaptureRegion {
+            x: 8,
+            y: 8,
+            width: 32,
+            height: 16,
+            display: None,
+        };
+        for (color, expected) in [("#ff0000", [255, 0, 0]), ("#0000ff", [0, 0, 255]), ("#ffffff", [255, 255, 255])] {
+            assert!(paint_root(color), "xsetrootでルートウィンドウを塗れません");
+            let image = ScreenCapture::new(region).capture().unwrap();
+            assert_eq!((image.width(), image.height()), (32, 16));
+            let pixel = dominant_pixel(&image);
+            for (actual, expected) in pixel.iter().zip(expected) {
+                assert!(actual.abs_diff(expected) <= 8, "{}: {:?}", color, pixel);
+            }
+        }
+    }
+
+    #[test]
+    fn reads_the_cursor_position_from_xvfb() {
+        if std::env::var_os("DISPLAY").is_none() {
+            eprintln!("DISPLAYが設定されていないため、Xvfbのカーソルの位置のテストを省略します");
+            return;
+        }
+        let (x, y) = ScreenCapture::capture_cursor_position().unwrap();
+        assert!(x >= 0 && y >= 0, "({}, {})", x, y);
+    }
+}
+
+/// キャプチャと認識を繰り返してメモリの増加を確かめるテスト（leak_testフィーチャー有効時のみ、--ignoredで実行）
+///
+/// 常駐メモリ（RSS）は /proc/self/statm から読むため、Linux以外では常駐メモリの比較を省略する。
+#[cfg(all(test, feature = "leak_test"))]
+mod leak_tests {
+    use super::*;
+    use crate::ocr::{OcrEngine, OcrEnginePool};
+    use image::{Rgba, RgbaImage};
+    use std::sync::Arc;
+
+    /// 繰り返す回数
+    const CYCLES: usize = 1000;
+
+    /// 基準とする常駐メモリを測る回（初期化直後の確保が落ち着いてから）
+    const BASELINE_CYCLE: usize = 10;
+
+    /// 常駐メモリ（バイト、ページサイズは4KiBとする。取得できなければNone）
+    fn resident_bytes() -> Option<u64> {
+        let statm = fs::read_to_string("/proc/self/statm").ok()?;
+        let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+        Some(pages * 4096)
+    }
+
+    /// 画面をキャプチャできない環境で使う、縞模様の画像
+    fn synthetic_frame() -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(160, 48, |x, y| {
+            if (x / 6 + y / 12) % 2 == 0 {
+                Rgba([20, 20, 20, 255])
+            } else {
+                Rgba([240, 240, 240, 255])
+            }
+        }))
+    }
+
+    #[test]
+    #[ignore]
+    fn repeated_capture_and_ocr_does_not_grow_memory() {
+        let region = CaptureRegion {
+            x: 0,
+            y: 0,
+            width: 160,
+            height: 48,
+            display: None,
+        };
+        let mut capture = ScreenCapture::new(region);
+        if capture.capture().is_err() {
+            eprintln!("画面をキャプチャできないため、合成した画像で繰り返します");
+            capture = ScreenCapture::with_source(region, Box::new(ImageSequenceSource::new([synthetic_frame()])));
+        }
+        let pool = OcrEnginePool::new(2, OcrEngine::new).unwrap();
+
+        let mut baseline = None;
+        let mut failures = 0;
+        for cycle in 1..=CYCLES {
+            let image = capture.capture().unwrap();
+            let engine = pool.acquire();
+            if engine.recognize_text(&image).is_err() {
+                failures += 1;
+            }
+            drop(engine);
+
+            if cycle == BASELINE_CYCLE {
+                baseline = resident_bytes();
+            }
+            if cycle % 100 == 0 {
+                eprintln!("{} 回目: 常駐メモリ {:?} バイト（認識の失敗 {} 回）", cycle, resident_bytes(), failures);
+            }
+        }
+
+        // 監視の外でエンジンへの参照が残っていない
+        for _ in 0..pool.len() {
+            assert_eq!(Arc::strong_count(&pool.acquire()), 2, "OcrEngineへの参照が残っています");
+        }
+        if let (Some(baseline), Some(last)) = (baseline, resident_bytes()) {
+            assert!(
+                last < baseline * 2,
+                "常駐メモリが増え続けています: {} 回目 {} バイト → {} 回目 {} バイト",
+                BASELINE_CYCLE,
+                baseline,
+                CYCLES,
+                last
+            );
+        }
+    }
+}
@@ -0,0 +1,137 @@
+// 変化イベントの調査用の比較画像（前回の認識のフレーム・今回のフレーム・変化した画素を赤くした画像を横に並べる）
+//
+// 画像の作成は監視スレッドとは別のスレッドで行い、作成した画像はイベントの連番と対応付けて
+// イベント画像と同じ保持領域に記録する。
+use image::{imageops::FilterType, DynamicImage, GenericImage, GrayImage, Luma, Rgb, RgbImage};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::thread::{self, JoinHandle};
+
+use crate::evidence::{EvidenceConfig, SharedEvidence};
+use crate::locking::lock;
+
+/// 比較する画像の長辺の上限（大きな領域は縮小してから比較する）
+const VISUAL_MAX_DIMENSION: u32 = 480;
+
+/// 輝度の差がこれを超える画素を変化したとみなす
+const DIFF_THRESHOLD: u8 = 32;
+
+/// 作成待ちの比較画像の上限（超えた分は作成しない）
+const QUEUE_CAPACITY: usize = 2;
+
+/// 変化していない画素の明るさの割合（変化した画素を目立たせる）
+const DIM_FACTOR: f32 = 0.4;
+
+/// 前回の認識のフレームと今回のフレームから比較画像を作成（3枚を横に並べた画像）
+pub fn compose(previous: &DynamicImage, current: &DynamicImage) -> DynamicImage {
+    let current = if current.width().max(current.height()) > VISUAL_MAX_DIMENSION {
+        current.resize(VISUAL_MAX_DIMENSION, VISUAL_MAX_DIMENSION, FilterType::Triangle)
+    } else {
+        current.clone()
+    };
+    let (width, height) = (current.width(), current.height());
+    // 領域を移した後などで大きさが違う場合も今回のフレームに合わせる
+    let previous = previous.resize_exact(width, height, FilterType::Triangle);
+
+    let mask = open_mask(&difference_mask(&previous.to_luma8(), &current.to_luma8()));
+    let current_rgb = current.to_rgb8();
+    let mut highlight = RgbImage::new(width, height);
+    for (x, y, pixel) in highlight.enumerate_pixels_mut() {
+        *pixel = if mask.get_pixel(x, y)[0] > 0 {
+            Rgb([255, 0, 0])
+        } else {
+            let Rgb([r, g, b]) = *current_rgb.get_pixel(x, y);
+            Rgb([r, g, b].map(|channel| (channel as f32 * DIM_FACTOR) as u8))
+        };
+    }
+
+    let mut composite = RgbImage::new(width * 3, height);
+    // 大きさは合わせてあるため貼り付けは失敗しない
+    let _ = composite.copy_from(&previous.to_rgb8(), 0, 0);
+    let _ = composite.copy_from(&current_rgb, width, 0);
+    let _ = composite.copy_from(&highlight, width * 2, 0);
+    DynamicImage::ImageRgb8(composite)
+}
+
+/// 輝度の差がしきい値を超える画素を255にしたマスク
+fn difference_mask(previous: &GrayImage, current: &GrayImage) -> GrayImage {
+    GrayImage::from_fn(current.width(), current.height(), |x, y| {
+        let difference = previous.get_pixel(x, y)[0].abs_diff(current.get_pixel(x, y)[0]);
+        Luma([if difference > DIFF_THRESHOLD { 255 } else { 0 }])
+    })
+}
+
+/// 3x3のオープニング（収縮してから膨張）で孤立した画素のノイズを除く
+fn open_mask(mask: &GrayImage) -> GrayImage {
+    let neighborhood = |image: &GrayImage, x: u32, y: u32, erode: bool| {
+        let mut values = (-1i32..=1).flat_map(|dy| (-1i32..=1).map(move |dx| (dx, dy))).filter_map(|(dx, dy)| {
+            let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+            (nx >= 0 && ny >= 0 && (nx as u32) < image.width() && (ny as u32) < image.height())
+                .then(|| image.get_pixel(nx as u32, ny as u32)[0])
+        });
+        if erode {
+            values.all(|value| value > 0)
+        } else {
+            values.any(|value| value > 0)
+        }
+    };
+    let on = |set: bool| Luma([if set { 255 } else { 0 }]);
+    let eroded = GrayImage::from_fn(mask.width(), mask.height(), |x, y| on(neighborhood(mask, x, y, true)));
+    GrayImage::from_fn(mask.width(), mask.height(), |x, y| on(neighborhood(&eroded, x, y, false)))
+}
+
+/// 作成を依頼した比較画像
+struct VisualRequest {
+    sequences: Vec<u64>,
+    previous: DynamicImage,
+    current: DynamicImage,
+}
+
+/// 比較画像を作成するスレッド（破棄すると作成待ちの画像を作成してから終了する）
+pub struct ChangeVisualWorker {
+    sender: Option<SyncSender<VisualRequest>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl ChangeVisualWorker {
+    /// スレッドを起動（起動できない場合は比較画像を作成しない）
+    pub fn start(config: EvidenceConfig, evidence: SharedEvidence) -> Option<Self> {
+        let (sender, receiver) = mpsc::sync_channel::<VisualRequest>(QUEUE_CAPACITY);
+        let handle = thread::Builder::new()
+            .name("change-visual".to_string())
+            .spawn(move || {
+                for request in receiver {
+                    let visual = compose(&request.previous, &request.current);
+                    lock(&evidence).record_change_visual(&config, request.sequences, visual);
+                }
+            })
+            .map_err(|e| log::warn!("比較画像のスレッドを起動できません: {}", e))
+            .ok()?;
+        Some(Self {
+            sender: Some(sender),
+            handle: Some(handle),
+        })
+    }
+
+    /// 比較画像の作成を依頼（作成待ちが上限に達していれば作成しない）
+    pub fn submit(&self, sequences: Vec<u64>, previous: DynamicImage, current: DynamicImage) {
+        let Some(sender) = &self.sender else {
+            return;
+        };
+        match sender.try_send(VisualRequest { sequences, previous, current }) {
+            Ok(()) => {}
+            Err(TrySendError::Full(request)) => {
+                log::debug!("比較画像の作成待ちが上限に達したため作成しません: {:?}", request.sequences);
+            }
+            Err(TrySendError::Disconnected(_)) => log::warn!("比較画像のスレッドが終了しています"),
+        }
+    }
+}
+
+impl Drop for ChangeVisualWorker {
+    fn drop(&mut self) {
+        self.sender.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
@@ -0,0 +1,192 @@
+// GUIを起動せずに実行するCLIサブコマンド
+//
+// 例: main ocr ./screenshot.png --lang jpn --psm 7 --json
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use crate::backends::subprocess;
+use crate::ocr::{OcrConfig, OcrEngine, OcrLine, PreprocessTimings, DEFAULT_PAGE_SEG_MODE};
+use crate::tessdata;
+
+/// 認識に失敗した場合の終了コード
+const EXIT_RECOGNITION_FAILED: i32 = 1;
+
+/// 引数の指定が不正な場合の終了コード
+const EXIT_USAGE: i32 = 2;
+
+/// ocrサブコマンドの使い方
+const OCR_USAGE: &str = "使い方: ocr <画像ファイル> [--lang <言語コード>] [--psm <0-13>] [--defringe <on|off|auto>] [--tessdata <ディレクトリ>] [--json]";
+
+/// サブコマンドが指定されていれば実行して終了コードを返す（指定が無ければNone）
+pub fn run(args: &[String]) -> Option<i32> {
+    match args.get(1).map(String::as_str) {
+        Some("ocr") => Some(run_ocr(&args[2..])),
+        // OCRを子プロセスで実行する場合の内部用のモード
+        Some(subprocess::WORKER_ARG) => Some(subprocess::run_worker(&args[2..])),
+        _ => None,
+    }
+}
+
+/// ocrサブコマンドの引数
+#[derive(Debug)]
+struct OcrArgs {
+    /// 認識する画像ファイル
+    path: PathBuf,
+    /// 認識言語
+    language: String,
+    /// ページセグメンテーションモード
+    page_seg_mode: u32,
+    /// サブピクセル描画の色にじみ除去（Noneの場合は自動判定）
+    defringe_lcd: Option<bool>,
+    /// 言語データのディレクトリ（Noneの場合はTesseractの既定パス）
+    tessdata_dir: Option<PathBuf>,
+    /// JSONで出力するかどうか
+    json: bool,
+}
+
+impl OcrArgs {
+    /// 引数を解釈
+    fn parse(args: &[String]) -> Result<Self> {
+        let mut path = None;
+        let mut language = tessdata::DEFAULT_LANGUAGE.to_string();
+        let mut page_seg_mode = DEFAULT_PAGE_SEG_MODE;
+        let mut defringe_lcd = None;
+        let mut tessdata_dir = None;
+        let mut json = false;
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let mut value = |name: &str| iter.next().with_context(|| format!("{} の値がありません", name));
+            match arg.as_str() {
+                "--lang" => language = value("--lang")?.clone(),
+                "--psm" => {
+                    let psm = value("--psm")?;
+                    page_seg_mode = psm
+                        .parse()
+                        .with_context(|| format!("--psm の値が不正です: {}", psm))?;
+                }
+                "--defringe" => {
+                    defringe_lcd = match value("--defringe")?.as_str() {
+                        "on" => Some(true),
+                        "off" => Some(false),
+                        "auto" => None,
+                        other => bail!("--defringe の値が不正です（on / off / auto）: {}", other),
+                    };
+                }
+                "--tessdata" => tessdata_dir = Some(PathBuf::from(value("--tessdata")?)),
+                "--json" => json = true,
+                other if other.starts_with("--") => bail!("不明なオプションです: {}", other),
+                other if path.is_none() => path = Some(PathBuf::from(other)),
+                other => bail!("画像ファイルは1つだけ指定してください: {}", other),
+            }
+        }
+
+        Ok(Self {
+            path: path.context("画像ファイルを指定してください")?,
+            language,
+            page_seg_mode,
+            defringe_lcd,
+            tessdata_dir,
+            json,
+        })
+    }
+}
+
+/// JSON出力の所要時間
+#[derive(Debug, Serialize)]
+struct OcrTimings {
+    /// 前処理の各ステップの所要時間（マイクロ秒、全文認識時のもの）
+    preprocess: PreprocessTimings,
+    /// 全文認識の所要時間（ミリ秒）
+    recognition_ms: u64,
+    /// 行単位の認識の所要時間（ミリ秒）
+    lines_ms: u64,
+    /// 画像の読み込みを含む全体の所要時間（ミリ秒）
+    total_ms: u64,
+}
+
+/// JSON出力
+#[derive(Debug, Serialize)]
+struct OcrOutput {
+    /// 認識されたテキスト
+    text: String,
+    /// 正規化済みの信頼度（0.0-1.0、信頼度を返さないエンジンの場合はNone）
+    confidence: Option<f32>,
+    /// 行ごとの認識結果
+    lines: Vec<OcrLine>,
+    /// 所要時間
+    timings: OcrTimings,
+}
+
+/// ocrサブコマンドを実行
+fn run_ocr(args: &[String]) -> i32 {
+    let args = match OcrArgs::parse(args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("{}", OCR_USAGE);
+            return EXIT_USAGE;
+        }
+    };
+
+    match recognize_file(&args) {
+        Ok(output) if args.json => match serde_json::to_string_pretty(&output) {
+            Ok(json) => {
+                println!("{}", json);
+                0
+            }
+            Err(e) => {
+                eprintln!("JSONの出力に失敗: {}", e);
+                EXIT_RECOGNITION_FAILED
+            }
+        },
+        Ok(output) => {
+            println!("{}", output.text);
+            0
+        }
+        Err(e) => {
+            eprintln!("{:#}", e);
+            EXIT_RECOGNITION_FAILED
+        }
+    }
+}
+
+/// 監視時と同じ前処理と認識を画像ファイルに適用
+fn recognize_file(args: &OcrArgs) -> Result<OcrOutput> {
+    let start = Instant::now();
+    let image = image::open(&args.path)
+        .with_context(|| format!("画像ファイルを読み込めません: {}", args.path.display()))?;
+
+    let mut engine = OcrEngine::with_language(args.tessdata_dir.clone(), &args.language)?;
+    engine.set_page_seg_mode(args.page_seg_mode)?;
+    engine.set_config(OcrConfig {
+        defringe_lcd: args.defringe_lcd,
+        ..OcrConfig::default()
+    });
+
+    let recognition_start = Instant::now();
+    let result = engine
+        .recognize_detailed(&image)
+        .with_context(|| format!("認識に失敗しました: {}", args.path.display()))?;
+    let recognition_ms = recognition_start.elapsed().as_millis() as u64;
+    let preprocess = engine.take_preprocess_timings().unwrap_or_default();
+
+    // 行単位の結果はJSON出力時のみ使うため、テキスト出力時は省略
+    let lines_start = Instant::now();
+    let lines = if args.json { engine.recognize_lines(&image)? } else { Vec::new() };
+    let lines_ms = lines_start.elapsed().as_millis() as u64;
+
+    Ok(OcrOutput {
+        text: result.text,
+        confidence: result.confidence,
+        lines,
+        timings: OcrTimings {
+            preprocess,
+            recognition_ms,
+            lines_ms,
+            total_ms: start.elapsed().as_millis() as u64,
+        },
+    })
+}
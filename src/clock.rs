@@ -0,0 +1,257 @@
+// 監視セッションの時計（システム時刻の変更に影響されない時刻）
+//
+// セッションの開始時にシステム時刻を1回だけ読み、以降は単調増加する経過時間を足して時刻とする。
+// NTPの補正や手動の時刻変更がセッション中にあっても、イベントの順序や表示期間が逆転しない。
+//
+// macOSやLinuxの単調増加する時刻はスリープ中に進まないため、システム時刻が経過時間から求めた時刻より
+// RESYNC_THRESHOLD_MS 以上進んでいれば、スリープから復帰したとみなして基準を取り直す（時刻は戻さない）。
+use std::fmt;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::events::now_millis;
+use crate::locking::lock;
+
+/// システム時刻が経過時間から求めた時刻より進んでいた場合に、基準を取り直すしきい値（ミリ秒）
+pub const RESYNC_THRESHOLD_MS: u64 = 5_000;
+
+/// 時計が読む時刻の取得元（テストで時刻の変更やスリープを再現するために差し替える）
+pub trait TimeSource: Send + Sync {
+    /// システム時刻（UNIXエポックからのミリ秒）
+    fn wall_ms(&self) -> u64;
+
+    /// 単調増加する時刻（任意の起点からの経過時間）
+    fn monotonic(&self) -> Duration;
+}
+
+/// OSの時計を読む取得元
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemTimeSource;
+
+impl TimeSource for SystemTimeSource {
+    fn wall_ms(&self) -> u64 {
+        now_millis()
+    }
+
+    fn monotonic(&self) -> Duration {
+        static ORIGIN: OnceLock<Instant> = OnceLock::new();
+        ORIGIN.get_or_init(Instant::now).elapsed()
+    }
+}
+
+/// 時刻の基準（基準を取り直すたびに更新する）
+#[derive(Debug)]
+struct Anchor {
+    /// 基準のシステム時刻（UNIXエポックからのミリ秒）
+    wall_ms: u64,
+    /// 基準の単調増加する時刻
+    monotonic: Duration,
+    /// 最後に返した時刻（時刻を戻さないため）
+    last_ms: u64,
+}
+
+/// セッションの開始時刻と、そこからの単調増加する経過時間（複製した時計は同じ基準を共有する）
+#[derive(Clone)]
+pub struct SessionClock {
+    source: Arc<dyn TimeSource>,
+    /// 開始時のシステム時刻（UNIXエポックからのミリ秒）
+    started_at_ms: u64,
+    anchor: Arc<Mutex<Anchor>>,
+}
+
+impl fmt::Debug for SessionClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SessionClock")
+            .field("started_at_ms", &self.started_at_ms)
+            .field("anchor", &*lock(&self.anchor))
+            .finish()
+    }
+}
+
+impl Default for SessionClock {
+    fn default() -> Self {
+        Self::start()
+    }
+}
+
+impl SessionClock {
+    /// OSの時計で現在の時刻を基準に開始
+    pub fn start() -> Self {
+        Self::with_source(Arc::new(SystemTimeSource))
+    }
+
+    /// 指定した取得元の現在の時刻を基準に開始
+    pub fn with_source(source: Arc<dyn TimeSource>) -> Self {
+        let started_at_ms = source.wall_ms();
+        let anchor = Anchor {
+            wall_ms: started_at_ms,
+            monotonic: source.monotonic(),
+            last_ms: started_at_ms,
+        };
+        Self {
+            source,
+            started_at_ms,
+            anchor: Arc::new(Mutex::new(anchor)),
+        }
+    }
+
+    /// 開始時のシステム時刻（UNIXエポックからのミリ秒）
+    pub fn started_at_ms(&self) -> u64 {
+        self.started_at_ms
+    }
+
+    /// 開始からの経過時間（ミリ秒、スリープから復帰した場合はスリープしていた時間を含む）
+    pub fn offset_ms(&self) -> u64 {
+        self.now_ms() - self.started_at_ms
+    }
+
+    /// 現在の時刻（基準のシステム時刻に経過時間を足したもの、UNIXエポックからのミリ秒）
+    ///
+    /// 返す時刻は減らない。システム時刻が RESYNC_THRESHOLD_MS 以上進んでいればその時刻に合わせる。
+    pub fn now_ms(&self) -> u64 {
+        let mut anchor = lock(&self.anchor);
+        let monotonic = self.source.monotonic();
+        let elapsed_ms = monotonic.saturating_sub(anchor.monotonic).as_millis() as u64;
+        let mut now_ms = anchor.wall_ms + elapsed_ms;
+
+        let wall_ms = self.source.wall_ms();
+        if wall_ms > now_ms + RESYNC_THRESHOLD_MS {
+            log::info!(
+                "システム時刻が経過時間より {}ms 進んでいるため、スリープからの復帰とみなして時刻の基準を取り直します",
+                wall_ms - now_ms
+            );
+            anchor.wall_ms = wall_ms;
+            anchor.monotonic = monotonic;
+            now_ms = wall_ms;
+        }
+
+        let now_ms = now_ms.max(anchor.last_ms);
+        anchor.last_ms = now_ms;
+        now_ms
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    /// 手動で進める時計（システム時刻と単調増加する時刻を別々に動かせる）
+    #[derive(Default)]
+    struct ManualTimeSource {
+        wall_ms: AtomicU64,
+        monotonic_ms: AtomicU64,
+    }
+
+    impl ManualTimeSource {
+        fn new(wall_ms: u64) -> Arc<Self> {
+            let source = Self::default();
+            source.wall_ms.store(wall_ms, Ordering::SeqCst);
+            Arc::new(source)
+        }
+
+        /// 通常の時間の経過（両方の時刻が進む）
+        fn advance(&self, ms: u64) {
+            self.wall_ms.fetch_add(ms, Ordering::SeqCst);
+            self.monotonic_ms.fetch_add(ms, Ordering::SeqCst);
+        }
+
+        /// システム時刻だけを変更（NTPの補正や手動の変更）
+        fn set_wall(&self, wall_ms: u64) {
+            self.wall_ms.store(wall_ms, Ordering::SeqCst);
+        }
+    }
+
+    impl TimeSource for ManualTimeSource {
+        fn wall_ms(&self) -> u64 {
+            self.wall_ms.load(Ordering::SeqCst)
+        }
+
+        fn monotonic(&self) -> Duration {
+            Duration::from_millis(self.monotonic_ms.load(Ordering::SeqCst))
+        }
+    }
+
+    const START_MS: u64 = 1_700_000_000_000;
+
+    #[test]
+    fn follows_elapsed_time() {
+        let source = ManualTimeSource::new(START_MS);
+        let clock = SessionClock::with_source(source.clone());
+        source.advance(1_500);
+        assert_eq!(clock.now_ms(), START_MS + 1_500);
+        assert_eq!(clock.offset_ms(), 1_500);
+        assert_eq!(clock.started_at_ms(), START_MS);
+    }
+
+    #[test]
+    fn backward_clock_jump_keeps_durations_positive() {
+        let source = ManualTimeSource::new(START_MS);
+        let clock = SessionClock::with_source(source.clone());
+        source.advance(1_000);
+        let appeared = clock.now_ms();
+
+        // システム時刻が1時間戻っても、経過時間の分だけ進む
+        source.set_wall(START_MS - 3_600_000);
+        source.advance(2_000);
+        let disappeared = clock.now_ms();
+        assert_eq!(disappeared - appeared, 2_000);
+        assert_eq!(clock.offset_ms(), 3_000);
+    }
+
+    #[test]
+    fn small_forward_jump_is_ignored() {
+        let source = ManualTimeSource::new(START_MS);
+        let clock = SessionClock::with_source(source.clone());
+        source.set_wall(START_MS + RESYNC_THRESHOLD_MS);
+        source.advance(100);
+        assert_eq!(clock.now_ms(), START_MS + 100);
+    }
+
+    #[test]
+    fn resyncs_after_suspend() {
+        let source = ManualTimeSource::new(START_MS);
+        let clock = SessionClock::with_source(source.clone());
+        source.advance(1_000);
+        let before = clock.now_ms();
+
+        // スリープ中はシステム時刻だけが進む
+        source.set_wall(START_MS + 1_000 + 600_000);
+        let after = clock.now_ms();
+        assert_eq!(after, START_MS + 601_000);
+        assert_eq!(clock.offset_ms(), 601_000);
+
+        // 復帰後は新しい基準から経過時間の分だけ進む
+        source.advance(250);
+        assert_eq!(clock.now_ms(), after + 250);
+        assert!(after > before);
+    }
+
+    #[test]
+    fn clones_share_the_anchor() {
+        let source = ManualTimeSource::new(START_MS);
+        let clock = SessionClock::with_source(source.clone());
+        let copy = clock.clone();
+        source.set_wall(START_MS + 600_000);
+        assert_eq!(clock.now_ms(), START_MS + 600_000);
+        source.advance(10);
+        assert_eq!(copy.now_ms(), START_MS + 600_010);
+    }
+
+    #[test]
+    fn never_goes_backwards() {
+        let source = ManualTimeSource::new(START_MS);
+        let clock = SessionClock::with_source(source.clone());
+        let mut previous = clock.now_ms();
+        for step in 0..20u64 {
+            source.advance(step * 37 % 200);
+            if step % 5 == 0 {
+                source.set_wall(START_MS - step * 1_000);
+            }
+            let now = clock.now_ms();
+            assert!(now >= previous);
+            previous = now;
+        }
+    }
+}
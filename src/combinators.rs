@@ -0,0 +1,245 @@
+// 監視のイベントの流れを変換するコンビネーター
+//
+// monitor.map_events(translate).filter_events(is_change).start_monitoring(sender) のように重ねられる。
+// 各段は内側の監視に自分用のチャンネルを渡し、受け取ったイベントを変換して外側へ送る。
+use anyhow::Result;
+use std::future::Future;
+use tokio::sync::mpsc;
+
+use crate::monitor::{ScreenMonitor, TextChangeEvent};
+
+/// 各段が内側の監視から受け取るチャンネルの容量
+const FORWARD_CHANNEL_CAPACITY: usize = 32;
+
+/// イベントを送信する監視（ScreenMonitorと、それを変換したもの）
+#[allow(dead_code)]
+pub trait MonitorEvents {
+    /// 監視を開始し、イベントを event_sender に送信する
+    fn start_monitoring(&self, event_sender: mpsc::Sender<TextChangeEvent>) -> impl Future<Output = Result<()>> + '_;
+
+    /// 各イベントを変換する（Noneを返したイベントは送信しない）
+    fn map_events<F>(self, f: F) -> MappedMonitor<F, Self>
+    where
+        Self: Sized,
+        F: Fn(TextChangeEvent) -> Option<TextChangeEvent>,
+    {
+        MappedMonitor { inner: self, f }
+    }
+
+    /// 条件を満たすイベントだけを送信する
+    fn filter_events<F>(self, pred: F) -> FilteredMonitor<F, Self>
+    where
+        Self: Sized,
+        F: Fn(&TextChangeEvent) -> bool,
+    {
+        FilteredMonitor { inner: self, pred }
+    }
+
+    /// 各イベントを0個以上のイベントに置き換える
+    fn flat_map_events<F, I>(self, f: F) -> FlatMappedMonitor<F, Self>
+    where
+        Self: Sized,
+        F: Fn(TextChangeEvent) -> I,
+        I: IntoIterator<Item = TextChangeEvent>,
+    {
+        FlatMappedMonitor { inner: self, f }
+    }
+}
+
+impl MonitorEvents for ScreenMonitor {
+    fn start_monitoring(&self, event_sender: mpsc::Sender<TextChangeEvent>) -> impl Future<Output = Result<()>> + '_ {
+        ScreenMonitor::start_monitoring(self, event_sender)
+    }
+}
+
+/// map_events で作成した監視
+pub struct MappedMonitor<F, M = ScreenMonitor> {
+    inner: M,
+    f: F,
+}
+
+impl<F, M> MonitorEvents for MappedMonitor<F, M>
+where
+    M: MonitorEvents,
+    F: Fn(TextChangeEvent) -> Option<TextChangeEvent>,
+{
+    fn start_monitoring(&self, event_sender: mpsc::Sender<TextChangeEvent>) -> impl Future<Output = Result<()>> + '_ {
+        forward_events(&self.inner, event_sender, &self.f)
+    }
+}
+
+/// filter_events で作成した監視
+pub struct FilteredMonitor<F, M = ScreenMonitor> {
+    inner: M,
+    pred: F,
+}
+
+impl<F, M> MonitorEvents for FilteredMonitor<F, M>
+where
+    M: MonitorEvents,
+    F: Fn(&TextChangeEvent) -> bool,
+{
+    fn start_monitoring(&self, event_sender: mpsc::Sender<TextChangeEvent>) -> impl Future<Output = Result<()>> + '_ {
+        forward_events(&self.inner, event_sender, |event| (self.pred)(&event).then_some(event))
+    }
+}
+
+/// flat_map_events で作成した監視
+pub struct FlatMappedMonitor<F, M = ScreenMonitor> {
+    inner: M,
+    f: F,
+}
+
+impl<F, I, M> MonitorEvents for FlatMappedMonitor<F, M>
+where
+    M: MonitorEvents,
+    F: Fn(TextChangeEvent) -> I,
+    I: IntoIterator<Item = TextChangeEvent> + 'static,
+{
+    fn start_monitoring(&self, event_sender: mpsc::Sender<TextChangeEvent>) -> impl Future<Output = Result<()>> + '_ {
+        forward_events(&self.inner, event_sender, &self.f)
+    }
+}
+
+/// 内側の監視のイベントを変換して送信（内側の監視が終わるか、送信先が閉じたら終える）
+///
+/// 内側の監視が終わった時点でチャンネルに残っているイベントも送信してから終える。
+async fn forward_events<M, I>(
+    inner: &M,
+    event_sender: mpsc::Sender<TextChangeEvent>,
+    transform: impl Fn(TextChangeEvent) -> I,
+) -> Result<()>
+where
+    M: MonitorEvents,
+    I: IntoIterator<Item = TextChangeEvent>,
+{
+    let (inner_sender, mut receiver) = mpsc::channel(FORWARD_CHANNEL_CAPACITY);
+    let monitoring = inner.start_monitoring(inner_sender);
+    tokio::pin!(monitoring);
+
+    loop {
+        tokio::select! {
+            event = receiver.recv() => match event {
+                Some(event) => {
+                    for event in transform(event) {
+                        if event_sender.send(event).await.is_err() {
+                            return Ok(());
+                        }
+                    }
+                }
+                // 内側の監視が送信側を手放した場合は、終わるまで待つ
+                None => return monitoring.await,
+            },
+            result = &mut monitoring => {
+                while let Ok(event) = receiver.try_recv() {
+                    for event in transform(event) {
+                        if event_sender.send(event).await.is_err() {
+                            return result;
+                        }
+                    }
+                }
+                return result;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 登録したイベントを順番に送信して終わる監視
+    struct ScriptedMonitor(Vec<TextChangeEvent>);
+
+    impl MonitorEvents for ScriptedMonitor {
+        async fn start_monitoring(&self, event_sender: mpsc::Sender<TextChangeEvent>) -> Result<()> {
+            for event in self.0.clone() {
+                event_sender.send(event).await?;
+            }
+            Ok(())
+        }
+    }
+
+    fn scripted(texts: &[&str]) -> ScriptedMonitor {
+        ScriptedMonitor(texts.iter().map(|text| TextChangeEvent::NewText(text.to_string())).collect())
+    }
+
+    /// 監視が終わるまでに送信されたイベントのテキスト
+    fn collect(monitor: &impl MonitorEvents) -> Vec<String> {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let (sender, mut receiver) = mpsc::channel(64);
+        runtime.block_on(monitor.start_monitoring(sender)).unwrap();
+        let mut texts = Vec::new();
+        while let Ok(event) = receiver.try_recv() {
+            match event {
+                TextChangeEvent::NewText(text) => texts.push(text),
+                other => panic!("想定外のイベント: {:?}", other),
+            }
+        }
+        texts
+    }
+
+    fn rename(suffix: &'static str) -> impl Fn(TextChangeEvent) -> Option<TextChangeEvent> {
+        move |event| match event {
+            TextChangeEvent::NewText(text) => Some(TextChangeEvent::NewText(format!("{}{}", text, suffix))),
+            other => Some(other),
+        }
+    }
+
+    fn text_of(event: &TextChangeEvent) -> &str {
+        match event {
+            TextChangeEvent::NewText(text) => text,
+            _ => "",
+        }
+    }
+
+    #[test]
+    fn map_events_transforms_and_drops_events() {
+        let monitor = scripted(&["a", "skip", "b"]).map_events(|event| match event {
+            TextChangeEvent::NewText(text) if text == "skip" => None,
+            TextChangeEvent::NewText(text) => Some(TextChangeEvent::NewText(text.to_uppercase())),
+            other => Some(other),
+        });
+        assert_eq!(collect(&monitor), ["A", "B"]);
+    }
+
+    #[test]
+    fn filter_events_keeps_only_matching_events() {
+        let monitor = scripted(&["1", "22", "333"]).filter_events(|event| text_of(event).len() != 2);
+        assert_eq!(collect(&monitor), ["1", "333"]);
+    }
+
+    #[test]
+    fn flat_map_events_replaces_each_event_with_several() {
+        let monitor = scripted(&["a b", "", "c"]).flat_map_events(|event| {
+            text_of(&event)
+                .split_whitespace()
+                .map(|word| TextChangeEvent::NewText(word.to_string()))
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(collect(&monitor), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn chained_combinators_apply_in_order() {
+        // 内側から順に適用されるため、変換の後の条件は変換した結果で判定される
+        let monitor = scripted(&["a", "b"])
+            .map_events(rename("-1"))
+            .flat_map_events(|event| vec![event.clone(), event])
+            .map_events(rename("-2"))
+            .filter_events(|event| text_of(event).starts_with('a'));
+        assert_eq!(collect(&monitor), ["a-1-2", "a-1-2"]);
+
+        let monitor = scripted(&["a"]).map_events(rename("-2")).map_events(rename("-1"));
+        assert_eq!(collect(&monitor), ["a-2-1"]);
+    }
+
+    #[test]
+    fn forwarding_stops_when_the_receiver_is_closed() {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let monitor = scripted(&["a", "b"]).map_events(Some);
+        let (sender, receiver) = mpsc::channel(1);
+        drop(receiver);
+        runtime.block_on(monitor.start_monitoring(sender)).unwrap();
+    }
+}
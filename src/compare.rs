@@ -0,0 +1,145 @@
+// 2つの領域のOCR結果の比較（レイアウト変更の調査用、監視とは独立して実行する）
+use anyhow::{Context, Result};
+use image::DynamicImage;
+use serde::Serialize;
+use std::path::PathBuf;
+
+use crate::capture::{CaptureConfig, CaptureRegion, ScreenCapture};
+use crate::monitor::TextDiffer;
+use crate::ocr::{encode_png_base64, OcrConfig, OcrEngine};
+use crate::process_guard::{self, ProcessGuardConfig};
+
+/// 1つの領域の認識結果
+#[derive(Debug, Clone)]
+pub struct RegionCapture {
+    /// 認識されたテキスト
+    pub text: String,
+    /// キャプチャした画像（PNGのBase64）
+    pub image_base64: String,
+}
+
+/// 2つの領域の比較結果
+#[derive(Debug, Clone, Serialize)]
+pub struct RegionComparison {
+    /// 領域Aのテキスト
+    pub text_a: String,
+    /// 領域Bのテキスト
+    pub text_b: String,
+    /// テキストの類似度（0.0〜1.0）
+    pub similarity: f32,
+    /// AからBへの行単位のunified diff
+    pub diff: String,
+    /// 領域Aの画像（PNGのBase64）
+    pub image_a_base64: String,
+    /// 領域Bの画像（PNGのBase64）
+    pub image_b_base64: String,
+}
+
+/// 領域をキャプチャして認識（監視と同じ言語と前処理を使う）
+pub fn recognize_region(
+    region: CaptureRegion,
+    capture_config: &CaptureConfig,
+    process_guard: &ProcessGuardConfig,
+    tessdata_dir: Option<PathBuf>,
+    language: &str,
+    ocr_config: OcrConfig,
+) -> Result<RegionCapture> {
+    let image = process_guard::guarded_capture(process_guard, &ScreenCapture::with_config(region, capture_config))
+        .context("キャプチャに失敗しました")?;
+
+    let mut ocr_engine = OcrEngine::with_backend(tessdata_dir, language, ocr_config.backend)?;
+    ocr_engine.set_config(ocr_config);
+    recognize_image(&ocr_engine, &image)
+}
+
+/// キャプチャした画像を認識
+fn recognize_image(ocr_engine: &OcrEngine, image: &DynamicImage) -> Result<RegionCapture> {
+    Ok(RegionCapture {
+        text: ocr_engine.recognize_detailed(image)?.text,
+        image_base64: encode_png_base64(image)?,
+    })
+}
+
+/// 2つの認識結果を比較
+pub fn compare(a: RegionCapture, b: RegionCapture) -> RegionComparison {
+    let differ = TextDiffer::new(1);
+    RegionComparison {
+        similarity: differ.similarity_score(&a.text, &b.text),
+        diff: differ.unified_diff(&a.text, &b.text, "region_a", "region_b"),
+        text_a: a.text,
+        text_b: b.text,
+        image_a_base64: a.image_base64,
+        image_b_base64: b.image_base64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends::{OcrBackend, OcrBackendKind};
+    use crate::ocr::OcrLine;
+    use crate::tiling::ImageRect;
+    use image::{GrayImage, Luma};
+
+    /// 画像を横に3等分し、帯ごとに暗いか明るいかを1行として返すOCR
+    struct BandReader;
+
+    impl OcrBackend for BandReader {
+        fn recognize_lines(&self, image: &DynamicImage, _page_seg_mode: u32) -> Result<Vec<OcrLine>> {
+            let gray = image.to_luma8();
+            let band_height = gray.height() / 3;
+            Ok((0..3)
+                .map(|band| {
+                    let y = band * band_height;
+                    let pixels: Vec<u8> = (y..y + band_height)
+                        .flat_map(|y| (0..gray.width()).map(move |x| (x, y)))
+                        .map(|(x, y)| gray.get_pixel(x, y)[0])
+                        .collect();
+                    let mean = pixels.iter().map(|&v| u32::from(v)).sum::<u32>() / pixels.len() as u32;
+                    OcrLine {
+                        text: format!("band {}: {}", band + 1, if mean < 128 { "dark" } else { "light" }),
+                        bbox: ImageRect { x: 0, y, width: gray.width(), height: band_height },
+                        language: None,
+                        confidence: None,
+                    }
+                })
+                .collect())
+        }
+    }
+
+    /// dark_bandsに含む帯（0始まり）だけを黒く塗った画像
+    fn banded_image(dark_bands: &[u32]) -> DynamicImage {
+        DynamicImage::ImageLuma8(GrayImage::from_fn(90, 60, |_, y| {
+            Luma([if dark_bands.contains(&(y / 20)) { 20 } else { 235 }])
+        }))
+    }
+
+    #[test]
+    fn reports_the_band_that_differs_between_two_images() {
+        let mut engine = OcrEngine::from_parts(None, "eng", OcrBackendKind::Tesseract, Some(Box::new(BandReader)));
+        engine.set_config(OcrConfig { fast_pipeline: true, ..OcrConfig::default() });
+        let a = recognize_image(&engine, &banded_image(&[0])).unwrap();
+        let b = recognize_image(&engine, &banded_image(&[0, 1])).unwrap();
+        assert_ne!(a.image_base64, b.image_base64);
+
+        let comparison = compare(a, b);
+        assert_eq!(comparison.text_a, "band 1: dark\nband 2: light\nband 3: light");
+        assert_eq!(comparison.text_b, "band 1: dark\nband 2: dark\nband 3: light");
+        assert_eq!(
+            comparison.diff,
+            "--- region_a\n+++ region_b\n@@ -1,3 +1,3 @@\n band 1: dark\n-band 2: light\n+band 2: dark\n band 3: light\n"
+        );
+        // 「light」を「dark」にする5文字の編集（長い方のテキストは40文字）
+        assert!((comparison.similarity - (1.0 - 5.0 / 40.0)).abs() < 1e-6, "{}", comparison.similarity);
+    }
+
+    #[test]
+    fn identical_images_have_no_changed_lines() {
+        let engine = OcrEngine::from_parts(None, "eng", OcrBackendKind::Tesseract, Some(Box::new(BandReader)));
+        let image = banded_image(&[2]);
+        let comparison = compare(recognize_image(&engine, &image).unwrap(), recognize_image(&engine, &image).unwrap());
+        assert_eq!(comparison.similarity, 1.0);
+        assert!(comparison.diff.lines().skip(3).all(|line| line.starts_with(' ')), "{}", comparison.diff);
+        assert_eq!(comparison.image_a_base64, comparison.image_b_base64);
+    }
+}
@@ -0,0 +1,44 @@
+// 明暗が反転したテーマ（暗い背景に明るい文字）の自動判定
+//
+// 既定の前処理は明るい背景に暗い文字を前提とするため、最初のフレームを現在の設定と
+// 明暗を反転した設定の両方で認識し、信頼度の高い方を以降のフレームにも使う。
+use anyhow::{bail, Result};
+use image::DynamicImage;
+
+use crate::ocr::{OcrConfig, OcrEngine};
+
+/// 明暗の反転の要否を判定する
+pub struct ContrastProber;
+
+impl ContrastProber {
+    /// 判定し、反転した方が良ければ反転した設定のエンジンを返す（現在の設定のままで良ければNone）
+    ///
+    /// 片方の認識に失敗した場合はもう片方を採用し、両方とも失敗した場合はエラーにする。
+    /// 反転した方で文字を認識できなかった場合は、信頼度に関わらず反転しない。
+    pub fn probe_engine(engine: &OcrEngine, image: &DynamicImage) -> Result<Option<OcrEngine>> {
+        let config = OcrConfig {
+            invert: !engine.config().invert,
+            ..engine.config().clone()
+        };
+        let inverted_engine = engine.with_config_override(config)?;
+
+        let normal = engine.recognize_detailed(image);
+        let inverted = inverted_engine.recognize_detailed(image);
+        let use_inverted = match (&normal, &inverted) {
+            (Err(normal), Err(inverted)) => {
+                bail!("明暗の判定のための認識に失敗しました: {}（反転: {}）", normal, inverted)
+            }
+            (_, Err(_)) => false,
+            (Err(_), Ok(inverted)) => !inverted.text.trim().is_empty(),
+            (Ok(normal), Ok(inverted)) => {
+                log::debug!(
+                    "明暗の判定: 現在の設定の信頼度 {:?}、反転した設定の信頼度 {:?}",
+                    normal.confidence,
+                    inverted.confidence
+                );
+                !inverted.text.trim().is_empty() && inverted.confidence.unwrap_or(0.0) > normal.confidence.unwrap_or(0.0)
+            }
+        };
+        Ok(use_inverted.then_some(inverted_engine))
+    }
+}
@@ -0,0 +1,295 @@
+// 利用者の訂正から学習する文字単位の誤認識補正
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::validation::{Validate, Validator};
+
+/// 訂正として受け付ける置換数の上限（元テキストの文字数に対する割合）
+///
+/// これを超える訂正は誤認識の修正ではなく別のテキストとみなし、学習しない。
+const MAX_SUBSTITUTION_RATIO: f32 = 0.5;
+
+/// 補正の設定
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CorrectionConfig {
+    /// 学習した補正を認識結果に適用するかどうか（既定は無効）
+    pub enabled: bool,
+    /// 保持する補正ルールの上限数
+    pub max_rules: usize,
+    /// 適用に必要な最小の重み（同じ訂正が行われた回数）
+    pub min_weight: u32,
+}
+
+impl Default for CorrectionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_rules: 200,
+            min_weight: 1,
+        }
+    }
+}
+
+impl Validate for CorrectionConfig {
+    const PREFIX: &'static str = "corrections";
+
+    fn check(&self, validator: &mut Validator) {
+        validator.range("max_rules", self.max_rules, 1, 10_000);
+        validator.range("min_weight", self.min_weight, 1, 1_000);
+    }
+}
+
+/// 1文字の置換ルール
+///
+/// 前後の文字（認識結果側）が一致する場合のみ適用する。前後がNoneの場合はテキストの先頭・末尾を表す。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CorrectionRule {
+    /// 削除時に指定する識別子
+    pub id: u64,
+    /// 誤認識された文字
+    pub from: char,
+    /// 正しい文字
+    pub to: char,
+    /// 直前の文字
+    pub before: Option<char>,
+    /// 直後の文字
+    pub after: Option<char>,
+    /// 同じ訂正が行われた回数
+    pub weight: u32,
+}
+
+impl CorrectionRule {
+    /// 同じ文脈・同じ置換のルールかどうか
+    fn same_substitution(&self, other: &Substitution) -> bool {
+        self.from == other.from && self.to == other.to && self.before == other.before && self.after == other.after
+    }
+}
+
+/// 訂正から抽出した置換
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Substitution {
+    from: char,
+    to: char,
+    before: Option<char>,
+    after: Option<char>,
+}
+
+/// 学習済みの補正ルールと設定（設定ファイルに保存する）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CorrectionTable {
+    /// 補正の設定
+    pub config: CorrectionConfig,
+    /// 補正ルール
+    rules: Vec<CorrectionRule>,
+    /// 次に割り当てるルールの識別子
+    next_id: u64,
+}
+
+impl CorrectionTable {
+    /// ファイルから読み込む（ファイルが無ければ空のテーブル）
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("補正ファイルの読み込みに失敗: {}", path.display()))?;
+        let mut table: Self = serde_json::from_str(&json)
+            .with_context(|| format!("補正ファイルの形式が不正です: {}", path.display()))?;
+        // 範囲外の設定は既定値に戻し、学習済みのルールは残す
+        if let Err(e) = table.config.validate() {
+            log::warn!("補正ファイルの設定を既定値に戻します: {}", e);
+            table.config = CorrectionConfig::default();
+        }
+        table.enforce_cap();
+        Ok(table)
+    }
+
+    /// ファイルに保存
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json).with_context(|| format!("補正ファイルの保存に失敗: {}", path.display()))
+    }
+
+    /// 学習済みのルール一覧
+    pub fn rules(&self) -> &[CorrectionRule] {
+        &self.rules
+    }
+
+    /// 設定を変更（上限が下がった場合は重みの低いルールから削除）
+    pub fn set_config(&mut self, config: CorrectionConfig) {
+        self.config = config;
+        self.enforce_cap();
+    }
+
+    /// ルールを削除（存在しなければfalse）
+    pub fn remove(&mut self, id: u64) -> bool {
+        let before = self.rules.len();
+        self.rules.retain(|rule| rule.id != id);
+        self.rules.len() != before
+    }
+
+    /// 認識結果と利用者の訂正を比較して置換ルールを学習し、更新されたルールを返す
+    pub fn learn(&mut self, recognized: &str, corrected: &str) -> Result<Vec<CorrectionRule>> {
+        let recognized: Vec<char> = recognized.chars().collect();
+        let corrected: Vec<char> = corrected.chars().collect();
+
+        let (distance, substitutions) = align_substitutions(&recognized, &corrected);
+        if distance == 0 {
+            return Ok(Vec::new());
+        }
+        if distance as f32 > recognized.len() as f32 * MAX_SUBSTITUTION_RATIO {
+            bail!(
+                "訂正後のテキストが認識結果と大きく異なるため学習しません（編集距離: {}）",
+                distance
+            );
+        }
+
+        let mut updated_ids = Vec::new();
+        for substitution in substitutions {
+            let id = match self.rules.iter_mut().find(|rule| rule.same_substitution(&substitution)) {
+                Some(rule) => {
+                    rule.weight += 1;
+                    rule.id
+                }
+                None => {
+                    let id = self.next_id;
+                    self.next_id += 1;
+                    self.rules.push(CorrectionRule {
+                        id,
+                        from: substitution.from,
+                        to: substitution.to,
+                        before: substitution.before,
+                        after: substitution.after,
+                        weight: 1,
+                    });
+                    id
+                }
+            };
+            if !updated_ids.contains(&id) {
+                updated_ids.push(id);
+            }
+        }
+        self.enforce_cap();
+
+        Ok(self
+            .rules
+            .iter()
+            .filter(|rule| updated_ids.contains(&rule.id))
+            .cloned()
+            .collect())
+    }
+
+    /// 学習済みの補正を適用（無効時はそのまま返す）
+    ///
+    /// 置換は1文字を同じ位置の1文字に置き換えるのみで、挿入・削除は行わない。
+    /// そのため補正後のテキストと認識結果の編集距離は、適用した置換の数を超えない。
+    pub fn apply(&self, text: &str) -> String {
+        if !self.config.enabled || self.rules.is_empty() {
+            return text.to_string();
+        }
+
+        let chars: Vec<char> = text.chars().collect();
+        let mut applied = 0;
+        let corrected: String = chars
+            .iter()
+            .enumerate()
+            .map(|(i, &c)| {
+                // 文脈は補正前の文字で判定し、置換結果が後続の判定に影響しないようにする
+                let before = i.checked_sub(1).map(|prev| chars[prev]);
+                let after = chars.get(i + 1).copied();
+                let best = self
+                    .rules
+                    .iter()
+                    .filter(|rule| rule.weight >= self.config.min_weight)
+                    .filter(|rule| rule.from == c && rule.before == before && rule.after == after)
+                    .max_by_key(|rule| (rule.weight, rule.id));
+                match best {
+                    Some(rule) => {
+                        applied += 1;
+                        rule.to
+                    }
+                    None => c,
+                }
+            })
+            .collect();
+
+        if applied > 0 {
+            log::debug!("学習済みの補正を{}箇所適用しました", applied);
+        }
+        corrected
+    }
+
+    /// 上限を超えたルールを重みの低い順（同じ重みなら古い順）に削除
+    fn enforce_cap(&mut self) {
+        while self.rules.len() > self.config.max_rules {
+            let Some(index) = self
+                .rules
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, rule)| (rule.weight, rule.id))
+                .map(|(index, _)| index)
+            else {
+                break;
+            };
+            self.rules.remove(index);
+        }
+    }
+}
+
+/// 編集距離で2つの文字列を対応付け、編集距離と1文字の置換の一覧を返す
+///
+/// 挿入・削除は学習の対象外とし、空白文字の置換も無視する。
+fn align_substitutions(recognized: &[char], corrected: &[char]) -> (usize, Vec<Substitution>) {
+    let (n, m) = (recognized.len(), corrected.len());
+
+    // dp[i][j]: recognized[..i] と corrected[..j] の編集距離
+    let mut dp = vec![vec![0usize; m + 1]; n + 1];
+    for (i, row) in dp.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    dp[0] = (0..=m).collect();
+    for i in 1..=n {
+        for j in 1..=m {
+            let cost = usize::from(recognized[i - 1] != corrected[j - 1]);
+            dp[i][j] = (dp[i - 1][j - 1] + cost).min(dp[i - 1][j] + 1).min(dp[i][j - 1] + 1);
+        }
+    }
+
+    // 末尾から辿って置換の位置を求める
+    let mut substitutions = Vec::new();
+    let (mut i, mut j) = (n, m);
+    while i > 0 && j > 0 {
+        let cost = usize::from(recognized[i - 1] != corrected[j - 1]);
+        if dp[i][j] == dp[i - 1][j - 1] + cost {
+            let (from, to) = (recognized[i - 1], corrected[j - 1]);
+            if cost == 1 && !from.is_whitespace() && !to.is_whitespace() {
+                substitutions.push(Substitution {
+                    from,
+                    to,
+                    before: (i >= 2).then(|| recognized[i - 2]),
+                    after: recognized.get(i).copied(),
+                });
+            }
+            i -= 1;
+            j -= 1;
+        } else if dp[i][j] == dp[i - 1][j] + 1 {
+            i -= 1;
+        } else {
+            j -= 1;
+        }
+    }
+    substitutions.reverse();
+
+    (dp[n][m], substitutions)
+}
+
+/// スレッド間で共有する補正テーブル
+pub type SharedCorrections = Arc<Mutex<CorrectionTable>>;
+
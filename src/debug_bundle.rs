@@ -0,0 +1,351 @@
+// 不具合報告用のデバッグバンドル（設定・ログ・統計・画面の情報などを1つのZIPファイルにまとめる）
+//
+// 書き出すJSONは秘密情報を含みうる項目とURLを伏せてから追加する。ファイルの合計が上限を
+// 超える場合は後から追加したもの（画像など）を省き、省いたことを manifest.json に記録する。
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use crate::capture::DisplayGeometry;
+use crate::tessdata;
+
+/// バンドルの最大サイズ（バイト）
+pub const MAX_BUNDLE_BYTES: usize = 20 * 1024 * 1024;
+
+/// 伏せた値の代わりに書き出す文字列
+const REDACTED: &str = "[REDACTED]";
+
+/// 値を伏せる項目名（大文字小文字を区別せず、項目名に含まれていれば伏せる）
+const SENSITIVE_KEYS: &[&str] = &[
+    "password",
+    "passwd",
+    "secret",
+    "token",
+    "credential",
+    "api_key",
+    "apikey",
+    "webhook",
+    "authorization",
+    "cookie",
+];
+
+/// manifest.json のために確保しておくサイズ
+const MANIFEST_RESERVE_BYTES: usize = 64 * 1024;
+
+/// ZIPのローカルヘッダーと中央ディレクトリの1ファイルあたりの固定長
+const ZIP_ENTRY_OVERHEAD: usize = 30 + 46;
+
+/// ZIPの終端レコードの長さ
+const ZIP_END_RECORD_BYTES: usize = 22;
+
+/// 秘密情報を伏せる処理
+pub struct Redactor {
+    url: Regex,
+}
+
+impl Redactor {
+    pub fn new() -> Self {
+        Self {
+            // スキーム://（ユーザー情報@）ホスト と、それに続くパス・クエリ
+            url: Regex::new(r#"(?P<scheme>[A-Za-z][A-Za-z0-9+.\-]*)://(?:[^/\s@"']*@)?(?P<host>[^/\s?#"']*)(?P<rest>[^\s"']*)"#)
+                .expect("URLの正規表現が正しくありません"),
+        }
+    }
+
+    /// JSONの値の秘密情報を伏せる（項目名で判定した値と、文字列中のURLの認証情報・パス）
+    pub fn value(&self, value: &mut Value) {
+        match value {
+            Value::Object(map) => {
+                for (key, item) in map.iter_mut() {
+                    if is_sensitive_key(key) && !item.is_null() {
+                        *item = Value::String(REDACTED.to_string());
+                    } else {
+                        self.value(item);
+                    }
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| self.value(item)),
+            Value::String(text) => *text = self.text(text),
+            _ => {}
+        }
+    }
+
+    /// 文字列中のURLをスキームとホストだけにする（Webhookのように秘密がパスに含まれる場合があるため）
+    pub fn text(&self, text: &str) -> String {
+        self.url
+            .replace_all(text, |captures: &regex::Captures| {
+                let rest = if captures["rest"].is_empty() { String::new() } else { format!("/{}", REDACTED) };
+                format!("{}://{}{}", &captures["scheme"], &captures["host"], rest)
+            })
+            .into_owned()
+    }
+}
+
+/// 値を伏せる項目名かどうか
+fn is_sensitive_key(key: &str) -> bool {
+    let key = key.to_lowercase();
+    SENSITIVE_KEYS.iter().any(|sensitive| key.contains(sensitive))
+}
+
+/// バンドルに含める1つのファイル
+struct BundleFile {
+    name: String,
+    data: Vec<u8>,
+}
+
+/// manifest.json の1項目
+#[derive(Debug, Clone, Serialize)]
+struct ManifestEntry {
+    name: String,
+    bytes: usize,
+}
+
+/// バンドルの内容の一覧
+#[derive(Debug, Clone, Serialize)]
+struct Manifest {
+    /// 作成した時刻（ローカル時刻）
+    created_at: String,
+    /// アプリのバージョン
+    app_version: String,
+    /// 含めたファイル
+    included: Vec<ManifestEntry>,
+    /// サイズの上限のため省いたファイル
+    omitted: Vec<ManifestEntry>,
+}
+
+/// 作成中のデバッグバンドル
+pub struct DebugBundle {
+    files: Vec<BundleFile>,
+    redactor: Redactor,
+}
+
+impl DebugBundle {
+    pub fn new() -> Self {
+        Self {
+            files: Vec::new(),
+            redactor: Redactor::new(),
+        }
+    }
+
+    /// 値を秘密情報を伏せたJSONのファイルとして追加
+    pub fn add_json(&mut self, name: &str, value: &impl Serialize) -> Result<()> {
+        let mut value = serde_json::to_value(value).with_context(|| format!("{} をJSONに変換できません", name))?;
+        self.redactor.value(&mut value);
+        let data = serde_json::to_vec_pretty(&value)?;
+        self.add_file(name, data);
+        Ok(())
+    }
+
+    /// ファイルをそのまま追加（画像など、秘密情報を含まないもの）
+    pub fn add_file(&mut self, name: &str, data: Vec<u8>) {
+        self.files.push(BundleFile {
+            name: name.to_string(),
+            data,
+        });
+    }
+
+    /// 上限を超えない範囲で先に追加したファイルから順にZIPファイルに書き出す
+    pub fn write(self, path: &Path) -> Result<()> {
+        let mut budget = MAX_BUNDLE_BYTES - MANIFEST_RESERVE_BYTES - ZIP_END_RECORD_BYTES;
+        let mut included = Vec::new();
+        let mut omitted = Vec::new();
+        for file in self.files {
+            let size = file.data.len() + ZIP_ENTRY_OVERHEAD + file.name.len() * 2;
+            let entry = ManifestEntry {
+                name: file.name.clone(),
+                bytes: file.data.len(),
+            };
+            if size <= budget {
+                budget -= size;
+                included.push(file);
+            } else {
+                log::warn!("デバッグバンドルのサイズの上限を超えるため {} を省きました", file.name);
+                omitted.push(entry);
+            }
+        }
+
+        let manifest = Manifest {
+            created_at: chrono::Local::now().to_rfc3339(),
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            included: included
+                .iter()
+                .map(|file| ManifestEntry {
+                    name: file.name.clone(),
+                    bytes: file.data.len(),
+                })
+                .collect(),
+            omitted,
+        };
+        included.push(BundleFile {
+            name: "manifest.json".to_string(),
+            data: serde_json::to_vec_pretty(&manifest)?,
+        });
+
+        fs::write(path, zip_stored(&included))
+            .with_context(|| format!("デバッグバンドルを書き込めません: {}", path.display()))
+    }
+}
+
+/// 環境とモニターの情報
+#[derive(Debug, Clone, Serialize)]
+pub struct PlatformInfo {
+    pub os: &'static str,
+    pub arch: &'static str,
+    pub app_version: &'static str,
+    /// モニターの位置・解像度・拡大率（取得できない場合は空）
+    pub displays: Vec<DisplayGeometry>,
+    /// モニターの情報を取得できなかった理由
+    pub display_error: Option<String>,
+}
+
+impl PlatformInfo {
+    pub fn collect() -> Self {
+        let (displays, display_error) = match DisplayGeometry::all() {
+            Ok(displays) => (displays, None),
+            Err(e) => (Vec::new(), Some(e.to_string())),
+        };
+        Self {
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            app_version: env!("CARGO_PKG_VERSION"),
+            displays,
+            display_error,
+        }
+    }
+}
+
+/// Tesseractのバージョンと言語データ
+#[derive(Debug, Clone, Serialize)]
+pub struct TesseractInfo {
+    /// 設定中の言語での利用可否
+    pub availability: tessdata::OcrAvailability,
+    /// アプリのディレクトリにある言語データ
+    pub installed_languages: Vec<String>,
+    /// tesseract --version の出力（コマンドが無い場合はエラーの内容）
+    pub cli_version: String,
+    /// tesseract --list-langs の出力
+    pub cli_languages: String,
+}
+
+impl TesseractInfo {
+    pub fn collect(tessdata_dir: Option<&Path>, language: &str) -> Self {
+        Self {
+            availability: tessdata::check_availability(tessdata_dir, language),
+            installed_languages: tessdata_dir.map(tessdata::installed_languages).unwrap_or_default(),
+            cli_version: run_tesseract_cli("--version"),
+            cli_languages: run_tesseract_cli("--list-langs"),
+        }
+    }
+}
+
+/// tesseractコマンドを実行して出力を返す（実行できない場合はエラーの内容）
+fn run_tesseract_cli(argument: &str) -> String {
+    match Command::new("tesseract").arg(argument).output() {
+        // バージョンは標準エラーに出力される版もある
+        Ok(output) => format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        )
+        .trim()
+        .to_string(),
+        Err(e) => format!("tesseractコマンドを実行できません: {}", e),
+    }
+}
+
+/// 無圧縮（stored）のZIPを作成（ファイル名はUTF-8）
+fn zip_stored(files: &[BundleFile]) -> Vec<u8> {
+    const UTF8_FLAG: u16 = 0x0800;
+    let (time, date) = dos_datetime(chrono::Local::now());
+    let mut out = Vec::new();
+    let mut central = Vec::new();
+
+    for file in files {
+        let offset = out.len() as u32;
+        let crc = crc32(&file.data);
+        let size = file.data.len() as u32;
+        let name = file.name.as_bytes();
+
+        push_u32(&mut out, 0x0403_4b50);
+        for value in [20, UTF8_FLAG, 0, time, date] {
+            push_u16(&mut out, value);
+        }
+        for value in [crc, size, size] {
+            push_u32(&mut out, value);
+        }
+        push_u16(&mut out, name.len() as u16);
+        push_u16(&mut out, 0);
+        out.extend_from_slice(name);
+        out.extend_from_slice(&file.data);
+
+        push_u32(&mut central, 0x0201_4b50);
+        for value in [20, 20, UTF8_FLAG, 0, time, date] {
+            push_u16(&mut central, value);
+        }
+        for value in [crc, size, size] {
+            push_u32(&mut central, value);
+        }
+        for value in [name.len() as u16, 0, 0, 0, 0] {
+            push_u16(&mut central, value);
+        }
+        push_u32(&mut central, 0);
+        push_u32(&mut central, offset);
+        central.extend_from_slice(name);
+    }
+
+    let central_offset = out.len() as u32;
+    let central_size = central.len() as u32;
+    out.extend_from_slice(&central);
+    push_u32(&mut out, 0x0605_4b50);
+    for value in [0, 0, files.len() as u16, files.len() as u16] {
+        push_u16(&mut out, value);
+    }
+    push_u32(&mut out, central_size);
+    push_u32(&mut out, central_offset);
+    push_u16(&mut out, 0);
+    out
+}
+
+fn push_u16(out: &mut Vec<u8>, value: u16) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn push_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+/// ZIPのMS-DOS形式の時刻と日付
+fn dos_datetime(now: chrono::DateTime<chrono::Local>) -> (u16, u16) {
+    use chrono::{Datelike, Timelike};
+
+    let time = ((now.hour() << 11) | (now.minute() << 5) | (now.second() / 2)) as u16;
+    let date = (((now.year().clamp(1980, 2107) - 1980) as u32) << 9) | (now.month() << 5) | now.day();
+    (time, date as u16)
+}
+
+/// CRC-32（ZIPで使うIEEEの多項式）
+fn crc32(data: &[u8]) -> u32 {
+    const TABLE: [u32; 256] = {
+        let mut table = [0u32; 256];
+        let mut i = 0;
+        while i < 256 {
+            let mut crc = i as u32;
+            let mut bit = 0;
+            while bit < 8 {
+                crc = if crc & 1 != 0 { 0xEDB8_8320 ^ (crc >> 1) } else { crc >> 1 };
+                bit += 1;
+            }
+            table[i] = crc;
+            i += 1;
+        }
+        table
+    };
+
+    !data
+        .iter()
+        .fold(!0u32, |crc, &byte| TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8))
+}
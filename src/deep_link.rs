@@ -0,0 +1,192 @@
+// ocr:// のURLによる監視の開始（シェルのエイリアスや他のアプリのリンクから領域を指定して開く）
+//
+// 例: ocr://monitor?x=10&y=20&w=300&h=100&lang=jpn
+// アプリ内で開いたリンクはカスタムプロトコルとして、起動時の --url 引数は起動後に、同じ解析で処理する。
+// OSのスキームの関連付けはインストーラーで登録し（tauri.conf.json）、起動中に開かれたURLは
+// single_instance で起動中のプロセスに転送する。
+use crate::capture::CaptureRegion;
+
+/// 登録するURLのスキーム
+pub const URI_SCHEME: &str = "ocr";
+
+/// 起動時にURLを受け取る引数（`--url <URL>`）
+const URL_ARG: &str = "--url";
+
+/// 監視を開始するURLのホスト
+const MONITOR_HOST: &str = "monitor";
+
+/// 指定できる領域の幅・高さの上限（キャプチャの上限と揃える）
+const MAX_REGION_SIZE: u32 = 2048;
+
+/// URLで指定した監視
+#[derive(Debug, Clone)]
+pub struct MonitorLink {
+    /// 監視する領域（モニターの情報は監視開始時に補う）
+    pub region: CaptureRegion,
+    /// 認識言語（指定が無ければ現在の設定のまま）
+    pub language: Option<String>,
+}
+
+/// カスタムプロトコルの要求がリンクを開く操作（ページの移動）かどうか
+///
+/// 画像やスクリプトの読み込みで監視が始まらないよう、移動先が文書の要求のみ受け付ける。
+/// Sec-Fetch-Dest を送らないウェブビューでは、Acceptが文書（text/html）を求めているかで判断する。
+pub fn is_explicit_navigation(method: &str, sec_fetch_dest: Option<&str>, accept: Option<&str>) -> bool {
+    if !method.eq_ignore_ascii_case("GET") {
+        return false;
+    }
+    match sec_fetch_dest {
+        Some(destination) => destination.eq_ignore_ascii_case("document"),
+        None => accept.is_some_and(|accept| accept.to_ascii_lowercase().contains("text/html")),
+    }
+}
+
+/// 起動時の引数からURLを取得
+pub fn url_argument(args: &[String]) -> Option<String> {
+    args.windows(2).find(|pair| pair[0] == URL_ARG).map(|pair| pair[1].clone())
+}
+
+/// ocr://monitor?x=..&y=..&w=..&h=..&lang=.. を解析（エラーは利用者向けの説明）
+pub fn parse_monitor_url(url: &str) -> Result<MonitorLink, String> {
+    // Windowsのウェブビューはカスタムプロトコルを https://ocr.localhost/ として渡す
+    let url = url.trim();
+    let rest = [format!("{}://", URI_SCHEME), format!("https://{}.localhost/", URI_SCHEME)]
+        .iter()
+        .find_map(|prefix| url.strip_prefix(prefix.as_str()))
+        .ok_or_else(|| format!("{}:// で始まるURLを指定してください", URI_SCHEME))?;
+    let (host, query) = rest.split_once('?').unwrap_or((rest, ""));
+    if host.trim_end_matches('/') != MONITOR_HOST {
+        return Err(format!("対応していない操作です: {}（{}://{} のみ指定できます）", host, URI_SCHEME, MONITOR_HOST));
+    }
+
+    let (mut x, mut y, mut width, mut height, mut language) = (None, None, None, None, None);
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = percent_decode(value).ok_or_else(|| format!("{} の値のエンコードが不正です", key))?;
+        let slot = match key {
+            "x" => &mut x,
+            "y" => &mut y,
+            "w" => &mut width,
+            "h" => &mut height,
+            "lang" => &mut language,
+            _ => return Err(format!("不明なパラメーターです: {}", key)),
+        };
+        if slot.replace(value).is_some() {
+            return Err(format!("パラメーター {} が複数あります", key));
+        }
+    }
+
+    let region = CaptureRegion {
+        x: parse_required("x", x, i32::MIN, i32::MAX)?,
+        y: parse_required("y", y, i32::MIN, i32::MAX)?,
+        width: parse_required("w", width, 1, MAX_REGION_SIZE)?,
+        height: parse_required("h", height, 1, MAX_REGION_SIZE)?,
+        display: None,
+    };
+    if let Some(language) = &language {
+        // jpn+eng のような組み合わせも言語データのファイル名として安全な文字のみ許可する
+        let valid = language
+            .split('+')
+            .all(|code| !code.is_empty() && code.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'));
+        if !valid {
+            return Err(format!("言語コードが不正です: {}（例: jpn、jpn+eng）", language));
+        }
+    }
+    Ok(MonitorLink { region, language })
+}
+
+/// 必須の数値のパラメーターを範囲を確かめて解析
+fn parse_required<T>(key: &str, value: Option<String>, min: T, max: T) -> Result<T, String>
+where
+    T: std::str::FromStr + PartialOrd + std::fmt::Display,
+{
+    let value = value.ok_or_else(|| format!("パラメーター {} を指定してください", key))?;
+    let parsed: T = value.parse().map_err(|_| format!("パラメーター {} は整数で指定してください: {}", key, value))?;
+    if parsed < min || parsed > max {
+        return Err(format!("パラメーター {} は {}〜{} の範囲で指定してください: {}", key, min, max, parsed));
+    }
+    Ok(parsed)
+}
+
+/// %XX のエンコードを戻す（不正なエンコードや、UTF-8として読めない場合はNone）
+fn percent_decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// カスタムプロトコルの応答のページ（成功でもエラーでも同じ体裁）
+pub fn result_page(title: &str, message: &str) -> String {
+    format!(
+        "<!DOCTYPE html><html lang=\"ja\"><head><meta charset=\"utf-8\"><title>{title}</title></head>\
+         <body style=\"font-family: sans-serif; padding: 2em\"><h1>{title}</h1><p>{message}</p>\
+         <p>例: <code>{scheme}://{host}?x=10&amp;y=20&amp;w=300&amp;h=100&amp;lang=jpn</code></p></body></html>",
+        title = escape_html(title),
+        message = escape_html(message),
+        scheme = URI_SCHEME,
+        host = MONITOR_HOST,
+    )
+}
+
+/// HTMLの特殊文字をエスケープ
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_monitor_url() {
+        let link = parse_monitor_url("ocr://monitor?x=-10&y=20&w=300&h=100&lang=jpn%2Beng").unwrap();
+        assert_eq!((link.region.x, link.region.y, link.region.width, link.region.height), (-10, 20, 300, 100));
+        assert_eq!(link.language.as_deref(), Some("jpn+eng"));
+        assert!(parse_monitor_url("https://ocr.localhost/monitor?x=0&y=0&w=1&h=1").is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_monitor_urls() {
+        for url in [
+            "http://monitor?x=0&y=0&w=1&h=1",
+            "ocr://other?x=0&y=0&w=1&h=1",
+            "ocr://monitor?x=0&y=0&w=0&h=1",
+            "ocr://monitor?x=0&x=1&y=0&w=1&h=1",
+            "ocr://monitor?x=0&y=0&w=1&h=1&lang=../jpn",
+        ] {
+            assert!(parse_monitor_url(url).is_err(), "{}", url);
+        }
+    }
+
+    #[test]
+    fn only_document_navigation_opens_links() {
+        assert!(is_explicit_navigation("GET", Some("document"), None));
+        assert!(!is_explicit_navigation("GET", Some("image"), Some("text/html")));
+        assert!(!is_explicit_navigation("POST", Some("document"), None));
+        // Sec-Fetch-Dest を送らないウェブビューの判断
+        assert!(is_explicit_navigation("GET", None, Some("text/html,application/xhtml+xml,*/*;q=0.8")));
+        assert!(!is_explicit_navigation("GET", None, Some("image/webp,image/png,*/*;q=0.8")));
+        assert!(!is_explicit_navigation("GET", None, None));
+    }
+
+    #[test]
+    fn url_argument_needs_a_value() {
+        let args = |values: &[&str]| values.iter().map(|value| value.to_string()).collect::<Vec<_>>();
+        assert_eq!(url_argument(&args(&["app", "--url", "ocr://monitor"])).as_deref(), Some("ocr://monitor"));
+        assert_eq!(url_argument(&args(&["app", "--url"])), None);
+    }
+}
@@ -0,0 +1,828 @@
+// イベント通知と履歴管理の実装
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::Window;
+
+use crate::capture::{CaptureRegion, DisplayGeometry};
+use crate::clock::SessionClock;
+use crate::hooks::{HookChain, MonitorError, MonitorHooks};
+use crate::line_parser::ParsedLine;
+use crate::locking::lock;
+use crate::preprocessing::ImageMetrics;
+use crate::memory::MemoryAccounted;
+use crate::monitor;
+use crate::ocr::OcrResult;
+use crate::pipe_output::{write_to_pipe, PipeRecord, SharedEventPipe};
+use crate::schema::{v1, EventChannels, SCHEMA_VERSION};
+use crate::stability::LineStability;
+use crate::stats::SharedStats;
+use crate::summary::SessionSummary;
+
+/// 履歴バッファに保持する最大件数
+const HISTORY_CAPACITY: usize = 500;
+
+/// 履歴のページの最大件数
+pub const MAX_EVENT_PAGE_SIZE: usize = HISTORY_CAPACITY;
+
+/// 同じコードの情報イベントを再送するまでの最小間隔
+const INFO_RATE_LIMIT: Duration = Duration::from_secs(10);
+
+/// 連続して送信できるテキスト変化イベントの数（トークンバケットの容量）
+const THROTTLE_BURST: f64 = 20.0;
+
+/// 1秒あたりに回復する送信可能数
+const THROTTLE_REFILL_PER_SECOND: f64 = 10.0;
+
+/// テキスト変化イベント
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum TextChangeEvent {
+    /// 新しいテキストが検出された
+    #[serde(rename = "new")]
+    NewText { text: String },
+    /// テキストが変更された
+    #[serde(rename = "changed")]
+    TextChanged { old: String, new: String },
+    /// サムネイル付きのテキストの新規・変更（oldは新規の場合None、
+    /// confidenceは正規化済みの信頼度、metricsは前処理済みの画像のゆがみの指標で、
+    /// 部分OCRなどで無い場合はNone）
+    #[serde(rename = "rich_changed")]
+    RichTextChanged {
+        old: Option<String>,
+        new: String,
+        thumbnail: Option<String>,
+        confidence: Option<f32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        metrics: Option<ImageMetrics>,
+    },
+    /// テキストがクリアされた
+    #[serde(rename = "cleared")]
+    TextCleared { text: String },
+    /// クリアの直後に新しいテキストが現れた（クリアと新規をまとめたもの、
+    /// cleared_at_msとreplaced_at_msはそれぞれを観測した時刻）
+    #[serde(rename = "replaced")]
+    TextReplaced {
+        old: String,
+        new: String,
+        cleared_at_ms: u64,
+        replaced_at_ms: u64,
+    },
+    /// 差分テキストが検出された（line_stabilityは関係する行の安定度、
+    /// parsed_addedは行の分解が有効な場合の追加行ごとの発言者とメッセージ、
+    /// added_languagesは複数の言語を設定した場合の追加行ごとの推定した言語で、判定できない行はNone）
+    #[serde(rename = "diff")]
+    DiffDetected {
+        added: Vec<String>,
+        removed: Vec<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        line_stability: Vec<LineStability>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        parsed_added: Vec<ParsedLine>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        added_languages: Vec<Option<String>>,
+    },
+    /// 情報メッセージ（codeはフロントエンドでのローカライズ用の固定識別子）
+    #[serde(rename = "info")]
+    Info { code: String, message: String },
+    /// 言語データのダウンロード進捗
+    #[serde(rename = "download_progress")]
+    DownloadProgress {
+        language: String,
+        downloaded_bytes: u64,
+        total_bytes: u64,
+        percent: f32,
+    },
+    /// 前処理パラメータの自動調整の進捗（best_accuracyはその時点の最良の一致度）
+    #[serde(rename = "tune_progress")]
+    TuneProgress {
+        evaluated: usize,
+        total: usize,
+        best_accuracy: f32,
+    },
+    /// 比較の基準とするテキストが設定された（次の認識結果をこのテキストと比較する）
+    #[serde(rename = "reference_set")]
+    ReferenceSet { text: String },
+    /// 監視中のキーワードが認識テキストに現れた（lineはキーワードを含む行）
+    #[serde(rename = "keyword_matched")]
+    KeywordMatched { keyword: String, line: String },
+    /// 領域のモニターの解像度・拡大率が選択時から変わった（currentはモニターが
+    /// 見つからなければNone、remappedは比例で移して監視を続ける場合の新しい領域で、
+    /// Noneなら監視を終了したため領域を選択し直す必要がある）
+    #[serde(rename = "region_invalidated")]
+    RegionInvalidated {
+        region: CaptureRegion,
+        original: DisplayGeometry,
+        current: Option<DisplayGeometry>,
+        remapped: Option<CaptureRegion>,
+    },
+    /// 領域に拒否リストのプロセスのウィンドウが重なっているためキャプチャしなかった
+    /// （同じプロセスについては設定の間隔ごとに1回だけ送信する）
+    #[serde(rename = "capture_suppressed")]
+    CaptureSuppressed { process: String },
+    /// 行が現れた（行ごとの表示期間の追跡が有効な場合、appeared_at_msは最初に認識した時刻）
+    #[serde(rename = "line_appeared")]
+    LineAppeared { line: String, appeared_at_ms: u64 },
+    /// 行が消えた（lineは最後に認識したテキスト、disappeared_at_msは認識されなくなった時刻。
+    /// 監視の終了時に表示されていた行は終了時に消えたものとする）
+    #[serde(rename = "line_disappeared")]
+    LineDisappeared {
+        line: String,
+        appeared_at_ms: u64,
+        disappeared_at_ms: u64,
+    },
+    /// 送信レートの制限で抑制したイベントのまとめ（total_droppedは抑制した総数、
+    /// eventsはそのうち新しいものから最大max_batch_size件。履歴には個々のイベントを記録する）
+    #[serde(rename = "batch")]
+    Batch {
+        events: Vec<TextChangeEvent>,
+        total_dropped: u64,
+    },
+}
+
+impl TextChangeEvent {
+    /// 情報イベントを作成
+    pub fn info(code: &str, message: impl Into<String>) -> Self {
+        TextChangeEvent::Info {
+            code: code.to_string(),
+            message: message.into(),
+        }
+    }
+
+    /// イベントの種類（シリアライズ時のtypeと同じ値）
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            TextChangeEvent::NewText { .. } => "new",
+            TextChangeEvent::TextChanged { .. } => "changed",
+            TextChangeEvent::RichTextChanged { .. } => "rich_changed",
+            TextChangeEvent::TextCleared { .. } => "cleared",
+            TextChangeEvent::TextReplaced { .. } => "replaced",
+            TextChangeEvent::DiffDetected { .. } => "diff",
+            TextChangeEvent::Info { .. } => "info",
+            TextChangeEvent::DownloadProgress { .. } => "download_progress",
+            TextChangeEvent::TuneProgress { .. } => "tune_progress",
+            TextChangeEvent::ReferenceSet { .. } => "reference_set",
+            TextChangeEvent::KeywordMatched { .. } => "keyword_matched",
+            TextChangeEvent::RegionInvalidated { .. } => "region_invalidated",
+            TextChangeEvent::CaptureSuppressed { .. } => "capture_suppressed",
+            TextChangeEvent::LineAppeared { .. } => "line_appeared",
+            TextChangeEvent::LineDisappeared { .. } => "line_disappeared",
+            TextChangeEvent::Batch { .. } => "batch",
+        }
+    }
+
+    /// 認識されたテキスト（新規・変更イベントのみ）
+    pub fn recognized_text(&self) -> Option<&str> {
+        match self {
+            TextChangeEvent::NewText { text } => Some(text),
+            TextChangeEvent::TextChanged { new, .. }
+            | TextChangeEvent::RichTextChanged { new, .. }
+            | TextChangeEvent::TextReplaced { new, .. } => Some(new),
+            _ => None,
+        }
+    }
+
+    /// イベントの後に画面に表示されているテキスト（テキストが変わらないイベントはNone、クリアは空文字列）
+    #[cfg_attr(not(feature = "rest"), allow(dead_code))]
+    pub fn current_text(&self) -> Option<&str> {
+        match self {
+            TextChangeEvent::TextCleared { .. } => Some(""),
+            TextChangeEvent::ReferenceSet { text } => Some(text),
+            _ => self.recognized_text(),
+        }
+    }
+
+    /// 本文（テキストやサムネイル）のおおよその大きさ（文字列の長さの合計、履歴の使用量の見積もり用）
+    ///
+    /// 記録のたびにシリアライズしないよう、JSONにした長さではなく文字列の長さで見積もる。
+    pub fn payload_bytes(&self) -> usize {
+        let total = |lines: &[String]| lines.iter().map(String::len).sum::<usize>();
+        let optional = |text: &Option<String>| text.as_ref().map_or(0, String::len);
+        match self {
+            TextChangeEvent::NewText { text }
+            | TextChangeEvent::TextCleared { text }
+            | TextChangeEvent::ReferenceSet { text } => text.len(),
+            TextChangeEvent::TextChanged { old, new } | TextChangeEvent::TextReplaced { old, new, .. } => {
+                old.len() + new.len()
+            }
+            TextChangeEvent::RichTextChanged { old, new, thumbnail, .. } => {
+                optional(old) + new.len() + optional(thumbnail)
+            }
+            TextChangeEvent::DiffDetected {
+                added,
+                removed,
+                line_stability,
+                parsed_added,
+                added_languages,
+            } => {
+                total(added)
+                    + total(removed)
+                    + line_stability.iter().map(|line| line.line_text.len()).sum::<usize>()
+                    + parsed_added
+                        .iter()
+                        .map(|parsed| parsed.line.len() + optional(&parsed.speaker) + parsed.message.len())
+                        .sum::<usize>()
+                    + added_languages.iter().map(optional).sum::<usize>()
+            }
+            TextChangeEvent::Info { code, message } => code.len() + message.len(),
+            TextChangeEvent::DownloadProgress { language, .. } => language.len(),
+            TextChangeEvent::KeywordMatched { keyword, line } => keyword.len() + line.len(),
+            TextChangeEvent::CaptureSuppressed { process } => process.len(),
+            TextChangeEvent::LineAppeared { line, .. } | TextChangeEvent::LineDisappeared { line, .. } => line.len(),
+            TextChangeEvent::Batch { events, .. } => events
+                .iter()
+                .map(|event| std::mem::size_of::<TextChangeEvent>() + event.payload_bytes())
+                .sum(),
+            TextChangeEvent::TuneProgress { .. } | TextChangeEvent::RegionInvalidated { .. } => 0,
+        }
+    }
+
+    /// 情報イベントかどうか
+    pub fn is_info(&self) -> bool {
+        matches!(self, TextChangeEvent::Info { .. })
+    }
+
+    /// イベントのテキスト（認識テキスト・差分の行・情報メッセージ）に文字列を含むかどうか
+    pub fn contains_text(&self, needle: &str) -> bool {
+        match self {
+            TextChangeEvent::NewText { text }
+            | TextChangeEvent::TextCleared { text }
+            | TextChangeEvent::ReferenceSet { text } => text.contains(needle),
+            TextChangeEvent::TextChanged { old, new } | TextChangeEvent::TextReplaced { old, new, .. } => {
+                old.contains(needle) || new.contains(needle)
+            }
+            TextChangeEvent::RichTextChanged { old, new, .. } => {
+                old.as_deref().is_some_and(|old| old.contains(needle)) || new.contains(needle)
+            }
+            TextChangeEvent::DiffDetected { added, removed, .. } => {
+                added.iter().chain(removed).any(|line| line.contains(needle))
+            }
+            TextChangeEvent::Info { message, .. } => message.contains(needle),
+            TextChangeEvent::KeywordMatched { keyword, line } => keyword.contains(needle) || line.contains(needle),
+            TextChangeEvent::CaptureSuppressed { process } => process.contains(needle),
+            TextChangeEvent::LineAppeared { line, .. } | TextChangeEvent::LineDisappeared { line, .. } => {
+                line.contains(needle)
+            }
+            TextChangeEvent::Batch { events, .. } => events.iter().any(|event| event.contains_text(needle)),
+            TextChangeEvent::DownloadProgress { .. }
+            | TextChangeEvent::TuneProgress { .. }
+            | TextChangeEvent::RegionInvalidated { .. } => false,
+        }
+    }
+}
+
+/// 履歴に記録されたイベント
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryEntry {
+    /// 記録順の連番
+    pub sequence: u64,
+    /// 記録時刻（UNIXエポックからのミリ秒、session_started_at_msにsession_offset_msを足したもの）
+    pub timestamp_ms: u64,
+    /// 記録したセッションの開始時のシステム時刻（UNIXエポックからのミリ秒）
+    pub session_started_at_ms: u64,
+    /// セッションの開始からの経過時間（ミリ秒、システム時刻の変更に影響されない）
+    pub session_offset_ms: u64,
+    /// 情報イベントかどうか（エクスポート時の除外用）
+    pub is_info: bool,
+    /// イベント本体
+    pub event: TextChangeEvent,
+}
+
+/// 履歴の絞り込み条件（指定しない条件は絞り込まない）
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct EventFilter {
+    /// イベントの種類（typeの値、空なら全種類）
+    pub event_types: Vec<String>,
+    /// 領域名（履歴のイベントには領域名が無いため、CSVのregion_label列と同じく空文字のみ一致）
+    pub region_label: Option<String>,
+    /// テキストに含む文字列
+    pub text_contains: Option<String>,
+    /// この時刻以降（UNIXエポックからのミリ秒）
+    pub since: Option<u64>,
+    /// この時刻以前（UNIXエポックからのミリ秒）
+    pub until: Option<u64>,
+}
+
+impl EventFilter {
+    /// 条件に一致するかどうか
+    pub fn matches(&self, entry: &HistoryEntry) -> bool {
+        (self.event_types.is_empty() || self.event_types.iter().any(|t| t == entry.event.type_name()))
+            && self.region_label.as_deref().map_or(true, str::is_empty)
+            && self
+                .text_contains
+                .as_deref()
+                .map_or(true, |needle| entry.event.contains_text(needle))
+            && self.since.map_or(true, |since| entry.timestamp_ms >= since)
+            && self.until.map_or(true, |until| entry.timestamp_ms <= until)
+    }
+}
+
+/// 履歴のページ
+#[derive(Debug, Clone, Serialize)]
+pub struct EventPage {
+    /// イベント（新しい順）
+    pub events: Vec<HistoryEntry>,
+    /// 条件に一致したイベントの総数
+    pub total: usize,
+    /// ページ番号（0始まり）
+    pub page: usize,
+}
+
+/// 直近のイベントを保持するリングバッファ
+#[derive(Debug, Default)]
+pub struct EventHistory {
+    entries: VecDeque<HistoryEntry>,
+    /// 各イベントのおおよその使用量（entriesと同じ順）
+    entry_bytes: VecDeque<usize>,
+    /// entry_bytesの合計
+    total_bytes: usize,
+    next_sequence: u64,
+}
+
+impl EventHistory {
+    /// イベントを記録し、割り当てた連番を返す（時刻はセッションの時計で決める）
+    pub fn push(&mut self, event: TextChangeEvent, clock: &SessionClock) -> u64 {
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+
+        if self.entries.len() >= HISTORY_CAPACITY {
+            self.pop_oldest();
+        }
+        let bytes = std::mem::size_of::<HistoryEntry>() + event.payload_bytes();
+        self.entry_bytes.push_back(bytes);
+        self.total_bytes += bytes;
+        let session_offset_ms = clock.offset_ms();
+        self.entries.push_back(HistoryEntry {
+            sequence,
+            timestamp_ms: clock.started_at_ms() + session_offset_ms,
+            session_started_at_ms: clock.started_at_ms(),
+            session_offset_ms,
+            is_info: event.is_info(),
+            event,
+        });
+
+        sequence
+    }
+
+    /// 最も古いイベントを破棄
+    fn pop_oldest(&mut self) {
+        self.entries.pop_front();
+        if let Some(bytes) = self.entry_bytes.pop_front() {
+            self.total_bytes -= bytes;
+        }
+    }
+
+    /// 連番を指定してイベントを取得（履歴から押し出されていればNone）
+    pub fn get(&self, sequence: u64) -> Option<&HistoryEntry> {
+        self.entries.iter().find(|entry| entry.sequence == sequence)
+    }
+
+    /// 現在のテキストを決めた最新のイベント
+    #[cfg_attr(not(feature = "rest"), allow(dead_code))]
+    pub fn latest_text(&self) -> Option<&HistoryEntry> {
+        self.entries.iter().rev().find(|entry| entry.event.current_text().is_some())
+    }
+
+    /// 記録済みのイベントを古い順に取得
+    pub fn entries(&self, include_info: bool) -> Vec<HistoryEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| include_info || !entry.is_info)
+            .cloned()
+            .collect()
+    }
+
+    /// 条件に一致するイベントを新しい順に並べ、指定したページを取得
+    pub fn page(&self, page: usize, page_size: usize, filter: &EventFilter) -> EventPage {
+        let page_size = page_size.clamp(1, MAX_EVENT_PAGE_SIZE);
+        let matching: Vec<&HistoryEntry> = self.entries.iter().rev().filter(|entry| filter.matches(entry)).collect();
+        EventPage {
+            events: matching
+                .iter()
+                .skip(page.saturating_mul(page_size))
+                .take(page_size)
+                .map(|entry| (*entry).clone())
+                .collect(),
+            total: matching.len(),
+            page,
+        }
+    }
+}
+
+impl MemoryAccounted for EventHistory {
+    fn approximate_bytes(&self) -> usize {
+        self.total_bytes
+    }
+
+    fn trim_to(&mut self, target_bytes: usize) {
+        while self.total_bytes > target_bytes && !self.entries.is_empty() {
+            self.pop_oldest();
+        }
+    }
+}
+
+/// スレッド間で共有する履歴バッファ
+pub type SharedHistory = Arc<Mutex<EventHistory>>;
+
+/// 同じコードの情報イベントが短時間に繰り返し送信されるのを防ぐ
+#[derive(Debug, Default)]
+pub struct InfoRateLimiter {
+    /// コードごとの最終送信時刻
+    last_emitted: HashMap<String, Instant>,
+    /// コードごとの抑制した件数
+    suppressed: HashMap<String, u32>,
+}
+
+impl InfoRateLimiter {
+    /// 送信してよければ、前回以降に抑制した件数を返す
+    pub fn allow(&mut self, code: &str) -> Option<u32> {
+        let now = Instant::now();
+        if let Some(last) = self.last_emitted.get(code) {
+            if now.duration_since(*last) < INFO_RATE_LIMIT {
+                *self.suppressed.entry(code.to_string()).or_insert(0) += 1;
+                return None;
+            }
+        }
+
+        self.last_emitted.insert(code.to_string(), now);
+        Some(self.suppressed.remove(code).unwrap_or(0))
+    }
+}
+
+/// テキスト変化イベントの送信数をトークンバケットで制限する
+///
+/// 制限を超えたイベントは保留し、トークンが回復した時点でまとめて1回で送信する。
+#[derive(Debug)]
+pub struct EventThrottle {
+    /// 現在送信できる数
+    tokens: f64,
+    /// 最後にトークンを回復した時刻
+    last_refill: Instant,
+    /// 保留中のイベント（新しいものを最大max_batch_size件）
+    held: VecDeque<v1::TextChangedPayload>,
+    /// 前回のまとめ送信以降に保留した総数
+    total_dropped: u64,
+    /// まとめて送信する最大件数
+    max_batch_size: usize,
+}
+
+impl Default for EventThrottle {
+    fn default() -> Self {
+        Self {
+            tokens: THROTTLE_BURST,
+            last_refill: Instant::now(),
+            held: VecDeque::new(),
+            total_dropped: 0,
+            max_batch_size: crate::monitor::MonitorConfig::default().max_batch_size,
+        }
+    }
+}
+
+impl EventThrottle {
+    /// まとめて送信する最大件数を変更
+    pub fn set_max_batch_size(&mut self, max_batch_size: usize) {
+        self.max_batch_size = max_batch_size.max(1);
+        while self.held.len() > self.max_batch_size {
+            self.held.pop_front();
+        }
+    }
+
+    /// 経過時間に応じてトークンを回復
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * THROTTLE_REFILL_PER_SECOND).min(THROTTLE_BURST);
+        self.last_refill = now;
+    }
+
+    /// 送信してよければトークンを1つ消費してtrueを返す
+    pub fn try_acquire(&mut self) -> bool {
+        self.refill();
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// 送信できなかったイベントを保留（上限を超えた分は古いものから捨てる）
+    pub fn hold(&mut self, payload: v1::TextChangedPayload) {
+        self.total_dropped += 1;
+        if self.held.len() >= self.max_batch_size {
+            self.held.pop_front();
+        }
+        self.held.push_back(payload);
+    }
+
+    /// 保留中のイベントがあるかどうか
+    pub fn has_held(&self) -> bool {
+        !self.held.is_empty()
+    }
+
+    /// 保留中のイベントと保留した総数を取り出す
+    pub fn take_held(&mut self) -> (Vec<v1::TextChangedPayload>, u64) {
+        let total_dropped = std::mem::take(&mut self.total_dropped);
+        (self.held.drain(..).collect(), total_dropped)
+    }
+}
+
+/// テキスト変化イベントのアプリの外への配信先（MQTTなど）
+pub trait EventSink: Send {
+    /// イベントを配信（監視スレッドを止めないよう、送信は待たずに戻る）
+    fn publish(&self, event_type: &str, payload: &v1::TextChangedPayload);
+
+    /// 接続先（ホスト:ポート、validate_monitoringで接続できるか確認する）
+    fn address(&self) -> String;
+}
+
+/// スレッド間で共有する外への配信先（起動していなければNone）
+pub type SharedEventSink = Arc<Mutex<Option<Box<dyn EventSink>>>>;
+
+/// イベントの送信先のウィンドウ（テストでは送信したイベントを記録するウィンドウに差し替える）
+pub trait EventWindow: Send {
+    /// チャンネルにペイロードを送信
+    fn emit_payload(&self, channel: &str, payload: serde_json::Value) -> tauri::Result<()>;
+}
+
+impl EventWindow for Window {
+    fn emit_payload(&self, channel: &str, payload: serde_json::Value) -> tauri::Result<()> {
+        self.emit(channel, payload)
+    }
+}
+
+/// ウィンドウへの通知と履歴への記録をまとめて行う送信器
+///
+/// 送信するペイロードは schema モジュールのバージョン付きの型に変換される。
+pub struct EventEmitter {
+    window: Box<dyn EventWindow>,
+    history: SharedHistory,
+    channels: EventChannels,
+    stats: SharedStats,
+    /// 監視の各段階で呼ぶフック（外への配信とファイル・標準出力への書き出しもフックとして登録する。
+    /// 送信レートの制限とは関係なく、すべてのイベントを通知する）
+    hooks: HookChain,
+    limiter: InfoRateLimiter,
+    throttle: EventThrottle,
+    /// ライフサイクルイベントに付ける監視セッションの識別子
+    session_id: u64,
+    /// イベントの時刻を決める時計（監視セッションの送信器はセッションの開始時に作成した時計を使う）
+    clock: SessionClock,
+}
+
+impl EventEmitter {
+    /// 新しいEventEmitterを作成
+    pub fn new(
+        window: impl EventWindow + 'static,
+        history: SharedHistory,
+        channels: EventChannels,
+        stats: SharedStats,
+        sink: SharedEventSink,
+        pipe: SharedEventPipe,
+        clock: SessionClock,
+    ) -> Self {
+        let mut hooks = HookChain::default();
+        hooks.add(Box::new(SinkHooks(sink, clock.clone())));
+        hooks.add(Box::new(PipeHooks(pipe, clock.clone())));
+        Self {
+            window: Box::new(window),
+            history,
+            channels,
+            stats,
+            hooks,
+            limiter: InfoRateLimiter::default(),
+            throttle: EventThrottle::default(),
+            session_id: 0,
+            clock,
+        }
+    }
+
+    /// イベントの時刻を決める時計
+    pub fn clock(&self) -> SessionClock {
+        self.clock.clone()
+    }
+
+    /// ライフサイクルイベントに付ける監視セッションの識別子を設定
+    pub fn set_session_id(&mut self, session_id: u64) {
+        self.session_id = session_id;
+    }
+
+    /// フックを追加（外への配信・ファイルへの書き出しのフックの後に呼ばれる）
+    #[allow(dead_code)]
+    pub fn add_hooks(&mut self, hooks: Box<dyn MonitorHooks + Send>) {
+        self.hooks.add(hooks);
+    }
+
+    /// キャプチャした画像をフックに通知
+    pub fn frame_captured(&self, image: &DynamicImage) {
+        self.hooks.frame_captured(image);
+    }
+
+    /// 領域全体の認識結果をフックに通知
+    pub fn text_recognized(&self, result: &OcrResult) {
+        self.hooks.text_recognized(result);
+    }
+
+    /// 監視のエラーをフックに通知して送信
+    pub fn monitor_error(&self, error: MonitorError) {
+        self.hooks.error(&error);
+        self.error(error.to_string());
+    }
+
+    /// まとめて送信する最大件数を変更
+    pub fn set_max_batch_size(&mut self, max_batch_size: usize) {
+        self.throttle.set_max_batch_size(max_batch_size);
+    }
+
+    /// テキスト変化イベントを記録して送信し、割り当てた連番を返す
+    ///
+    /// 送信レートの制限を超えた場合は履歴にのみ記録し、後でまとめて送信する。
+    pub fn emit(&mut self, event: TextChangeEvent) -> u64 {
+        let sequence = lock(&self.history).push(event.clone(), &self.clock);
+        lock(&self.stats).record_event(event.type_name());
+        let payload = text_changed_payload(Some(sequence), &event, &self.clock);
+        self.hooks.event(&event, Some(sequence));
+
+        // 保留中のイベントがあれば、順序を保つため先にまとめて送信
+        self.flush_throttled();
+        if !self.throttle.has_held() && self.throttle.try_acquire() {
+            self.send(&self.channels.text_changed, payload);
+        } else {
+            self.throttle.hold(payload);
+        }
+        sequence
+    }
+
+    /// 送信レートが回復していれば、保留中のイベントをまとめて送信
+    pub fn flush_throttled(&mut self) {
+        if self.throttle.has_held() && self.throttle.try_acquire() {
+            self.send_batch();
+        }
+    }
+
+    /// 送信レートに関わらず保留中のイベントをまとめて送信（監視の終了時用）
+    pub fn flush_all(&mut self) {
+        if self.throttle.has_held() {
+            self.send_batch();
+        }
+    }
+
+    fn send_batch(&mut self) {
+        let (events, total_dropped) = self.throttle.take_held();
+        log::debug!("保留中のイベント {} 件をまとめて送信します（抑制 {} 件）", events.len(), total_dropped);
+        let payload = v1::TextChangedPayload {
+            schema_version: SCHEMA_VERSION,
+            sequence: None,
+            timestamp_ms: self.clock.now_ms(),
+            session_started_at_ms: Some(self.clock.started_at_ms()),
+            session_offset_ms: Some(self.clock.offset_ms()),
+            event: v1::Event::Batch { events, total_dropped },
+        };
+        lock(&self.stats).record_event("batch");
+        self.send(&self.channels.text_changed, payload);
+    }
+
+    /// 履歴に残さずに送信（進捗通知など一時的なイベント用、送信レートの制限は受けない）
+    pub fn emit_transient(&self, event: TextChangeEvent) {
+        lock(&self.stats).record_event(event.type_name());
+        self.send(&self.channels.text_changed, text_changed_payload(None, &event, &self.clock));
+    }
+
+    /// 情報イベントをレート制限付きで送信
+    pub fn info(&mut self, code: &str, message: impl Into<String>) {
+        let Some(suppressed) = self.limiter.allow(code) else {
+            return;
+        };
+
+        let mut message = message.into();
+        if suppressed > 0 {
+            message = format!("{}（同様の通知 {} 件を省略）", message, suppressed);
+        }
+        log::info!("[{}] {}", code, message);
+        self.emit(TextChangeEvent::info(code, message));
+    }
+
+    /// エラーメッセージを送信
+    pub fn error(&self, message: String) {
+        let payload = v1::ErrorPayload {
+            schema_version: SCHEMA_VERSION,
+            timestamp_ms: self.clock.now_ms(),
+            message,
+        };
+        lock(&self.stats).record_event("error");
+        self.send(&self.channels.error, payload);
+    }
+
+    /// ペイロードをJSONに変換してウィンドウに送信（失敗してもイベントの記録には影響させない）
+    fn send(&self, channel: &str, payload: impl Serialize) {
+        match serde_json::to_value(payload) {
+            Ok(payload) => {
+                let _ = self.window.emit_payload(channel, payload);
+            }
+            Err(e) => log::warn!("イベント {} のペイロードを変換できません: {}", channel, e),
+        }
+    }
+
+    /// 監視の開始・終了を送信
+    pub fn lifecycle(&self, state: v1::LifecycleState) {
+        self.send_lifecycle(state, None, None);
+    }
+
+    /// 監視の終了をセッションの要約付きで送信（続けられずに終了した場合は理由と対処方法も付ける）
+    pub fn lifecycle_stopped(&self, summary: &SessionSummary, failure: Option<v1::SessionFailure>) {
+        self.send_lifecycle(v1::LifecycleState::Stopped, Some(summary.into()), failure);
+    }
+
+    fn send_lifecycle(
+        &self,
+        state: v1::LifecycleState,
+        summary: Option<v1::SessionSummary>,
+        failure: Option<v1::SessionFailure>,
+    ) {
+        let payload = v1::LifecyclePayload {
+            schema_version: SCHEMA_VERSION,
+            timestamp_ms: self.clock.now_ms(),
+            session_id: self.session_id,
+            state,
+            summary,
+            failure,
+        };
+        self.send(&self.channels.lifecycle, payload);
+    }
+}
+
+/// 外への配信先にイベントを配信するフック
+struct SinkHooks(SharedEventSink, SessionClock);
+
+impl MonitorHooks for SinkHooks {
+    fn on_event(&self, event: &TextChangeEvent, sequence: Option<u64>) {
+        if let Some(sink) = lock(&self.0).as_ref() {
+            sink.publish(event.type_name(), &text_changed_payload(sequence, event, &self.1));
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "event_sink"
+    }
+}
+
+/// イベントをファイル・標準出力に書き出すフック
+struct PipeHooks(SharedEventPipe, SessionClock);
+
+impl MonitorHooks for PipeHooks {
+    fn on_event(&self, event: &TextChangeEvent, sequence: Option<u64>) {
+        write_to_pipe(&self.0, || PipeRecord::from_event(event, &text_changed_payload(sequence, event, &self.1)));
+    }
+
+    fn name(&self) -> &'static str {
+        "pipe_output"
+    }
+}
+
+/// ScreenMonitorのイベントをフックに渡すイベントに変換（エラーと情報メッセージはそれぞれ error・info コードの情報イベントにする）
+impl From<&monitor::TextChangeEvent> for TextChangeEvent {
+    fn from(event: &monitor::TextChangeEvent) -> Self {
+        match event {
+            monitor::TextChangeEvent::NewText(text) => TextChangeEvent::NewText { text: text.clone() },
+            monitor::TextChangeEvent::TextChanged { old, new } => TextChangeEvent::TextChanged {
+                old: old.clone(),
+                new: new.clone(),
+            },
+            monitor::TextChangeEvent::TextCleared(text) => TextChangeEvent::TextCleared { text: text.clone() },
+            monitor::TextChangeEvent::DiffDetected { added, removed } => TextChangeEvent::DiffDetected {
+                added: added.clone(),
+                removed: removed.clone(),
+                line_stability: Vec::new(),
+                parsed_added: Vec::new(),
+                added_languages: Vec::new(),
+            },
+            monitor::TextChangeEvent::Error(message) => TextChangeEvent::info("error", message.clone()),
+            monitor::TextChangeEvent::ReferenceSet { text } => TextChangeEvent::ReferenceSet { text: text.clone() },
+            monitor::TextChangeEvent::Info { message } => TextChangeEvent::info("info", message.clone()),
+        }
+    }
+}
+
+/// テキスト変化チャンネルのペイロードを作成
+fn text_changed_payload(sequence: Option<u64>, event: &TextChangeEvent, clock: &SessionClock) -> v1::TextChangedPayload {
+    let session_offset_ms = clock.offset_ms();
+    v1::TextChangedPayload {
+        schema_version: SCHEMA_VERSION,
+        sequence,
+        timestamp_ms: clock.started_at_ms() + session_offset_ms,
+        session_started_at_ms: Some(clock.started_at_ms()),
+        session_offset_ms: Some(session_offset_ms),
+        event: event.into(),
+    }
+}
+
+/// 現在時刻をUNIXエポックからのミリ秒で取得（システム時刻の変更の影響を受けるため、セッション中の時刻はSessionClockで取得する）
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
@@ -0,0 +1,188 @@
+// イベントの元になった前処理済み画像の保持（誤認識の調査用）
+use image::{imageops::FilterType, DynamicImage};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use crate::memory::MemoryAccounted;
+use crate::ocr::encode_png_base64;
+use crate::validation::{Validate, Validator};
+
+/// 前処理済み画像の保持の設定
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EvidenceConfig {
+    /// 画像を保持するかどうか（無効時は画像の複製も行わない）
+    pub enabled: bool,
+    /// 保持する認識結果の数
+    pub max_entries: usize,
+    /// 保持する画像の長辺の上限（超える場合は縮小して保持）
+    pub max_dimension: u32,
+    /// 変化イベントの比較画像（前回の認識との差を赤くした画像）を作成して保持するかどうか
+    ///
+    /// 前回の認識に使ったフレームを1枚保持する。画像の保持（enabled）とは別に設定できる。
+    pub change_visual: bool,
+}
+
+impl Default for EvidenceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_entries: 5,
+            max_dimension: 1024,
+            change_visual: false,
+        }
+    }
+}
+
+impl Validate for EvidenceConfig {
+    const PREFIX: &'static str = "evidence";
+
+    fn check(&self, validator: &mut Validator) {
+        validator.range("max_entries", self.max_entries, 1, 50);
+        validator.range("max_dimension", self.max_dimension, 64, 4096);
+    }
+}
+
+/// イベント画像の取得エラー
+#[derive(Debug, Clone)]
+pub enum EventImageError {
+    /// 画像の保持（比較画像の場合は比較画像の作成）が無効
+    Disabled,
+    /// 指定した連番の画像が保持されていない
+    NotFound { sequence: u64 },
+    /// PNGへのエンコードに失敗
+    Encode(String),
+}
+
+impl EventImageError {
+    /// フロントエンドで判別するための固定識別子
+    fn kind(&self) -> &'static str {
+        match self {
+            EventImageError::Disabled => "disabled",
+            EventImageError::NotFound { .. } => "not_found",
+            EventImageError::Encode(_) => "encode",
+        }
+    }
+}
+
+impl std::fmt::Display for EventImageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EventImageError::Disabled => write!(f, "イベント画像の保持が無効です"),
+            EventImageError::NotFound { sequence } => {
+                write!(f, "イベントの画像が保持されていません: sequence={}", sequence)
+            }
+            EventImageError::Encode(message) => write!(f, "画像のエンコードに失敗: {}", message),
+        }
+    }
+}
+
+// フロントエンドには { kind, message } の形式で返す
+impl Serialize for EventImageError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("EventImageError", 2)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+}
+
+/// 1回の認識で送信したイベントの連番と、認識に使った画像
+struct EvidenceEntry {
+    sequences: Vec<u64>,
+    image: DynamicImage,
+}
+
+/// 直近の認識結果の前処理済み画像と変化イベントの比較画像
+#[derive(Default)]
+pub struct EvidenceCache {
+    entries: VecDeque<EvidenceEntry>,
+    change_visuals: VecDeque<EvidenceEntry>,
+}
+
+impl EvidenceCache {
+    /// 認識に使った画像を、その認識から送信したイベントの連番と対応付けて記録
+    pub fn record(&mut self, config: &EvidenceConfig, sequences: Vec<u64>, image: &DynamicImage) {
+        if sequences.is_empty() {
+            return;
+        }
+
+        // メモリを抑えるため長辺を上限まで縮小して保持
+        let image = if image.width().max(image.height()) > config.max_dimension {
+            image.resize(config.max_dimension, config.max_dimension, FilterType::Triangle)
+        } else {
+            image.clone()
+        };
+
+        while self.entries.len() >= config.max_entries.max(1) {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(EvidenceEntry { sequences, image });
+    }
+
+    /// 変化イベントの比較画像を、そのイベントの連番と対応付けて記録（作成済みの画像をそのまま保持する）
+    pub fn record_change_visual(&mut self, config: &EvidenceConfig, sequences: Vec<u64>, image: DynamicImage) {
+        while self.change_visuals.len() >= config.max_entries.max(1) {
+            self.change_visuals.pop_front();
+        }
+        self.change_visuals.push_back(EvidenceEntry { sequences, image });
+    }
+
+    /// 指定したイベントの画像をbase64エンコードしたPNGで取得
+    pub fn png_base64(&self, sequence: u64) -> Result<String, EventImageError> {
+        find_png_base64(&self.entries, sequence)
+    }
+
+    /// 指定したイベントの比較画像をbase64エンコードしたPNGで取得（作成中の場合も NotFound）
+    pub fn change_visual_png_base64(&self, sequence: u64) -> Result<String, EventImageError> {
+        find_png_base64(&self.change_visuals, sequence)
+    }
+
+    /// 新しいものから最大 limit 件の画像と、そのイベントの連番
+    pub fn recent_images(&self, limit: usize) -> Vec<(Vec<u64>, DynamicImage)> {
+        self.entries
+            .iter()
+            .rev()
+            .take(limit)
+            .map(|entry| (entry.sequences.clone(), entry.image.clone()))
+            .collect()
+    }
+
+    /// 保持している画像をすべて破棄
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.change_visuals.clear();
+    }
+}
+
+/// 指定したイベントの画像をbase64エンコードしたPNGで取得
+fn find_png_base64(entries: &VecDeque<EvidenceEntry>, sequence: u64) -> Result<String, EventImageError> {
+    let entry = entries
+        .iter()
+        .find(|entry| entry.sequences.contains(&sequence))
+        .ok_or(EventImageError::NotFound { sequence })?;
+    encode_png_base64(&entry.image).map_err(|e| EventImageError::Encode(e.to_string()))
+}
+
+impl MemoryAccounted for EvidenceCache {
+    fn approximate_bytes(&self) -> usize {
+        self.entries
+            .iter()
+            .chain(self.change_visuals.iter())
+            .map(|entry| entry.image.as_bytes().len() + entry.sequences.len() * std::mem::size_of::<u64>())
+            .sum()
+    }
+
+    // 比較画像から先に削る
+    fn trim_to(&mut self, target_bytes: usize) {
+        while self.approximate_bytes() > target_bytes && self.change_visuals.pop_front().is_some() {}
+        while self.approximate_bytes() > target_bytes && self.entries.pop_front().is_some() {}
+    }
+}
+
+/// スレッド間で共有する画像の保持領域
+pub type SharedEvidence = Arc<Mutex<EvidenceCache>>;
+
@@ -0,0 +1,418 @@
+// 監視履歴のCSVエクスポート（表計算ソフトでの分析用）とSRTエクスポート（字幕用）
+use anyhow::{bail, Result};
+use chrono::{DateTime, Local, SecondsFormat, TimeZone, Utc};
+use std::path::Path;
+
+use crate::events::{HistoryEntry, TextChangeEvent};
+use crate::summary::SessionSummary;
+
+/// CSVの列名
+const CSV_COLUMNS: [&str; 9] = [
+    "event_id",
+    "captured_at",
+    "region_label",
+    "event_type",
+    "old_text",
+    "new_text",
+    "confidence",
+    "capture_duration_ms",
+    "ocr_duration_ms",
+];
+
+/// タイムスタンプのタイムゾーン
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampZone {
+    Utc,
+    Local,
+}
+
+impl TimestampZone {
+    /// "utc" または "local"（大文字小文字は区別しない）を解釈
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "utc" => Ok(TimestampZone::Utc),
+            "local" | "localtime" => Ok(TimestampZone::Local),
+            _ => bail!("タイムゾーンの指定が不正です（utc または local）: {}", value),
+        }
+    }
+
+    /// UNIXエポックからのミリ秒をRFC 3339形式に変換
+    fn format(&self, timestamp_ms: u64) -> String {
+        let Some(utc) = Utc.timestamp_millis_opt(timestamp_ms as i64).single() else {
+            return String::new();
+        };
+        match self {
+            TimestampZone::Utc => utc.to_rfc3339_opts(SecondsFormat::Millis, true),
+            TimestampZone::Local => DateTime::<Local>::from(utc).to_rfc3339_opts(SecondsFormat::Millis, false),
+        }
+    }
+}
+
+/// CSVエクスポートの設定
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    /// 区切り文字
+    pub delimiter: char,
+    /// 先頭行に列名を出力するかどうか
+    pub include_headers: bool,
+    /// テキストの変化以外（情報イベント）を除外するかどうか
+    pub include_diff_only: bool,
+    /// タイムスタンプのタイムゾーン
+    pub timestamp_zone: TimestampZone,
+}
+
+/// 履歴をCSVとしてファイルに書き出す
+pub fn write_history_csv(
+    entries: &[HistoryEntry],
+    summaries: &[SessionSummary],
+    options: &CsvOptions,
+    path: &Path,
+) -> Result<()> {
+    std::fs::write(path, history_to_csv(entries, summaries, options)?)?;
+    Ok(())
+}
+
+/// 履歴をCSV文字列に変換
+///
+/// 監視セッションの要約は event_type が session_summary の行として履歴の後に出力する
+/// （include_diff_only の場合は情報イベントと同様に除外する）。
+pub fn history_to_csv(entries: &[HistoryEntry], summaries: &[SessionSummary], options: &CsvOptions) -> Result<String> {
+    if matches!(options.delimiter, '"' | '\r' | '\n') {
+        bail!("区切り文字として使用できない文字です: {:?}", options.delimiter);
+    }
+
+    let mut out = String::new();
+    if options.include_headers {
+        push_record(&mut out, CSV_COLUMNS.iter().map(|column| column.to_string()), options.delimiter);
+    }
+
+    for entry in entries {
+        if options.include_diff_only && entry.is_info {
+            continue;
+        }
+        let (old_text, new_text) = event_texts(&entry.event);
+        // 領域名・信頼度・所要時間は履歴に記録していないため空欄とする
+        let record = [
+            entry.sequence.to_string(),
+            options.timestamp_zone.format(entry.timestamp_ms),
+            String::new(),
+            entry.event.type_name().to_string(),
+            old_text,
+            new_text,
+            String::new(),
+            String::new(),
+            String::new(),
+        ];
+        push_record(&mut out, record.into_iter(), options.delimiter);
+    }
+
+    if !options.include_diff_only {
+        for summary in summaries {
+            let record = [
+                String::new(),
+                options.timestamp_zone.format(summary.stopped_at_ms),
+                String::new(),
+                "session_summary".to_string(),
+                String::new(),
+                describe_summary(summary, options.timestamp_zone),
+                String::new(),
+                String::new(),
+                format!("{:.1}", summary.ocr_latency_avg_ms),
+            ];
+            push_record(&mut out, record.into_iter(), options.delimiter);
+        }
+    }
+
+    Ok(out)
+}
+
+/// 監視セッションの要約の説明文
+fn describe_summary(summary: &SessionSummary, zone: TimestampZone) -> String {
+    let mut text = format!(
+        "セッション {}（終了理由 {:?}）: {}秒、変化 {} 件、重複を除いた行 {}{}、エラー {} 件、OCR {} 回（平均 {:.1}ms、p50 {:.1}ms、p95 {:.1}ms）",
+        summary.session_id,
+        summary.stop_reason,
+        summary.duration_ms / 1000,
+        summary.change_events,
+        summary.unique_lines,
+        if summary.unique_lines_capped { " 以上" } else { "" },
+        summary.error_count,
+        summary.ocr_count,
+        summary.ocr_latency_avg_ms,
+        summary.ocr_latency_p50_ms,
+        summary.ocr_latency_p95_ms
+    );
+    if let Some(period) = summary.busiest_period {
+        text.push_str(&format!(
+            "、最多の期間 {} から {}秒間（{} 件）",
+            zone.format(period.start_ms),
+            period.duration_ms / 1000,
+            period.change_events
+        ));
+    }
+    if let Some(coverage) = summary.text_coverage {
+        text.push_str(&format!(
+            "、文字の占める割合 平均 {:.0}%（最小 {:.0}%、最大 {:.0}%）",
+            coverage.mean * 100.0,
+            coverage.min * 100.0,
+            coverage.max * 100.0
+        ));
+    }
+    text
+}
+
+/// イベントの変化前・変化後のテキスト
+pub(crate) fn event_texts(event: &TextChangeEvent) -> (String, String) {
+    match event {
+        TextChangeEvent::NewText { text } => (String::new(), text.clone()),
+        TextChangeEvent::TextChanged { old, new } => (old.clone(), new.clone()),
+        TextChangeEvent::RichTextChanged { old, new, .. } => (old.clone().unwrap_or_default(), new.clone()),
+        TextChangeEvent::TextReplaced { old, new, .. } => (old.clone(), new.clone()),
+        TextChangeEvent::TextCleared { text } => (text.clone(), String::new()),
+        TextChangeEvent::DiffDetected { added, removed, .. } => (removed.join("\n"), added.join("\n")),
+        TextChangeEvent::Info { message, .. } => (String::new(), message.clone()),
+        TextChangeEvent::ReferenceSet { text } => (String::new(), text.clone()),
+        TextChangeEvent::KeywordMatched { keyword, line } => (keyword.clone(), line.clone()),
+        TextChangeEvent::CaptureSuppressed { process } => (String::new(), process.clone()),
+        TextChangeEvent::LineAppeared { line, .. } => (String::new(), line.clone()),
+        TextChangeEvent::LineDisappeared { line, .. } => (line.clone(), String::new()),
+        TextChangeEvent::DownloadProgress { .. }
+        | TextChangeEvent::TuneProgress { .. }
+        | TextChangeEvent::RegionInvalidated { .. }
+        | TextChangeEvent::Batch { .. } => (String::new(), String::new()),
+    }
+}
+
+/// SRTの字幕の作り方
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SrtMode {
+    /// テキスト全体を、表示されてから変わるかクリアされるまでの1つの字幕にする
+    WholeText,
+    /// 行ごとの表示期間（行が消えた記録）を1行ずつの字幕にする
+    Lines,
+}
+
+impl SrtMode {
+    /// "whole_text" または "lines"（大文字小文字は区別しない）を解釈
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "whole_text" | "text" => Ok(SrtMode::WholeText),
+            "lines" | "line" => Ok(SrtMode::Lines),
+            _ => bail!("字幕の作り方の指定が不正です（whole_text または lines）: {}", value),
+        }
+    }
+}
+
+/// SRTの1つの字幕
+struct SrtCue {
+    start_ms: u64,
+    end_ms: u64,
+    text: String,
+}
+
+/// 履歴をSRTとしてファイルに書き出す
+pub fn write_history_srt(entries: &[HistoryEntry], mode: SrtMode, path: &Path) -> Result<()> {
+    std::fs::write(path, history_to_srt(entries, mode))?;
+    Ok(())
+}
+
+/// 履歴をSRT文字列に変換（時刻は履歴の最初のイベントからの経過時間）
+///
+/// 行ごとの字幕は消えた記録のある行のみ出力する（表示中の行は監視の終了時に記録される）。
+pub fn history_to_srt(entries: &[HistoryEntry], mode: SrtMode) -> String {
+    let Some(origin_ms) = entries.iter().map(|entry| entry.timestamp_ms).min() else {
+        return String::new();
+    };
+    let mut cues = match mode {
+        SrtMode::WholeText => text_cues(entries),
+        SrtMode::Lines => line_cues(entries),
+    };
+    cues.retain(|cue| cue.end_ms > cue.start_ms && !cue.text.is_empty());
+    cues.sort_by_key(|cue| cue.start_ms);
+
+    let mut out = String::new();
+    for (index, cue) in cues.iter().enumerate() {
+        out.push_str(&format!(
+            "{}\n{} --> {}\n{}\n\n",
+            index + 1,
+            srt_timestamp(cue.start_ms.saturating_sub(origin_ms)),
+            srt_timestamp(cue.end_ms.saturating_sub(origin_ms)),
+            cue.text
+        ));
+    }
+    out
+}
+
+/// テキスト全体の字幕（表示中のまま履歴が終わった場合は最後のイベントの時刻で終える）
+fn text_cues(entries: &[HistoryEntry]) -> Vec<SrtCue> {
+    let mut cues = Vec::new();
+    let mut current: Option<(u64, &str)> = None;
+    for entry in entries {
+        let next = match &entry.event {
+            TextChangeEvent::TextCleared { .. } => None,
+            event => match event.recognized_text() {
+                Some(text) => Some(text),
+                None => continue,
+            },
+        };
+        if let Some((start_ms, text)) = current.take() {
+            cues.push(SrtCue {
+                start_ms,
+                end_ms: entry.timestamp_ms,
+                text: cue_text(text),
+            });
+        }
+        current = next.map(|text| (entry.timestamp_ms, text));
+    }
+    if let (Some((start_ms, text)), Some(last)) = (current, entries.last()) {
+        cues.push(SrtCue {
+            start_ms,
+            end_ms: last.timestamp_ms,
+            text: cue_text(text),
+        });
+    }
+    cues
+}
+
+/// 行ごとの字幕
+fn line_cues(entries: &[HistoryEntry]) -> Vec<SrtCue> {
+    entries
+        .iter()
+        .filter_map(|entry| match &entry.event {
+            TextChangeEvent::LineDisappeared {
+                line,
+                appeared_at_ms,
+                disappeared_at_ms,
+            } => Some(SrtCue {
+                start_ms: *appeared_at_ms,
+                end_ms: *disappeared_at_ms,
+                text: cue_text(line),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// 字幕のテキスト（空行は字幕の区切りになるため取り除く）
+fn cue_text(text: &str) -> String {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// SRTの時刻（時:分:秒,ミリ秒）
+fn srt_timestamp(ms: u64) -> String {
+    format!(
+        "{:02}:{:02}:{:02},{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+/// 1行分のフィールドを出力（行末はRFC 4180に従いCRLF）
+pub(crate) fn push_record(out: &mut String, fields: impl Iterator<Item = String>, delimiter: char) {
+    for (i, field) in fields.enumerate() {
+        if i > 0 {
+            out.push(delimiter);
+        }
+        out.push_str(&escape_field(&field, delimiter));
+    }
+    out.push_str("\r\n");
+}
+
+/// 区切り文字・引用符・改行を含むフィールドを二重引用符で囲む（RFC 4180）
+fn escape_field(field: &str, delimiter: char) -> String {
+    if field.contains(delimiter) || field.contains(['"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(sequence: u64, timestamp_ms: u64, event: TextChangeEvent) -> HistoryEntry {
+        HistoryEntry {
+            sequence,
+            timestamp_ms,
+            session_started_at_ms: 1_000_000,
+            session_offset_ms: timestamp_ms - 1_000_000,
+            is_info: false,
+            event,
+        }
+    }
+
+    fn new_text(text: &str) -> TextChangeEvent {
+        TextChangeEvent::NewText { text: text.to_string() }
+    }
+
+    fn line_disappeared(line: &str, appeared_at_ms: u64, disappeared_at_ms: u64) -> TextChangeEvent {
+        TextChangeEvent::LineDisappeared {
+            line: line.to_string(),
+            appeared_at_ms,
+            disappeared_at_ms,
+        }
+    }
+
+    #[test]
+    fn formats_srt_timestamps() {
+        assert_eq!(srt_timestamp(0), "00:00:00,000");
+        assert_eq!(srt_timestamp(3_723_045), "01:02:03,045");
+    }
+
+    #[test]
+    fn whole_text_cues_end_at_the_next_change_or_clear() {
+        let entries = vec![
+            entry(1, 1_000_000, new_text("一つ目")),
+            entry(
+                2,
+                1_001_500,
+                TextChangeEvent::TextChanged {
+                    old: "一つ目".to_string(),
+                    new: "二つ目".to_string(),
+                },
+            ),
+            entry(3, 1_003_000, TextChangeEvent::TextCleared { text: "二つ目".to_string() }),
+        ];
+        assert_eq!(
+            history_to_srt(&entries, SrtMode::WholeText),
+            "1\n00:00:00,000 --> 00:00:01,500\n一つ目\n\n2\n00:00:01,500 --> 00:00:03,000\n二つ目\n\n"
+        );
+    }
+
+    #[test]
+    fn line_cues_follow_line_lifetimes() {
+        let entries = vec![
+            entry(1, 1_000_000, new_text("上\n下")),
+            entry(2, 1_002_000, line_disappeared("下", 1_000_500, 1_002_000)),
+            entry(3, 1_004_000, line_disappeared("上", 1_000_000, 1_004_000)),
+        ];
+        assert_eq!(
+            history_to_srt(&entries, SrtMode::Lines),
+            "1\n00:00:00,000 --> 00:00:04,000\n上\n\n2\n00:00:00,500 --> 00:00:02,000\n下\n\n"
+        );
+    }
+
+    #[test]
+    fn drops_empty_and_zero_length_cues() {
+        let entries = vec![
+            entry(1, 1_000_000, line_disappeared("   ", 1_000_000, 1_001_000)),
+            entry(2, 1_001_000, line_disappeared("一瞬", 1_001_000, 1_001_000)),
+        ];
+        assert_eq!(history_to_srt(&entries, SrtMode::Lines), "");
+        assert_eq!(history_to_srt(&[], SrtMode::WholeText), "");
+    }
+
+    #[test]
+    fn parses_srt_modes() {
+        assert_eq!(SrtMode::parse("LINES").unwrap(), SrtMode::Lines);
+        assert_eq!(SrtMode::parse("whole_text").unwrap(), SrtMode::WholeText);
+        assert!(SrtMode::parse("words").is_err());
+    }
+}
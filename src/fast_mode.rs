@@ -0,0 +1,197 @@
+// 高速モード（変化の速いカウンターなどを短い間隔で読み取る）
+//
+// 前処理を最小限にし、フレームのハッシュとタイル単位の変化検出で認識の回数を減らす。
+// 1フレームの所要時間が予算を超える状態が続いたら、代わりの間隔に落として監視を続ける。
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::monitor::MonitorConfig;
+use crate::ocr::{BinarizationMode, OcrConfig, OcrIsolation};
+use crate::tiling::TileConfig;
+use crate::transform::CaptureTransform;
+use crate::validation::{Validate, Validator};
+
+/// 高速モードで使うページセグメンテーションモード（1行のテキスト）
+pub const FAST_MODE_PAGE_SEG_MODE: u32 = 7;
+
+/// 達成した頻度の計算に使う直近のフレーム数
+const RATE_WINDOW: usize = 20;
+
+/// 高速モードの設定（start_monitoringで指定した場合のみ有効）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FastModeConfig {
+    /// 監視の間隔（ミリ秒、通常の監視より短い50ミリ秒から指定できる）
+    pub interval_ms: u64,
+    /// 間に合わない場合に切り替える間隔（ミリ秒）
+    pub fallback_interval_ms: u64,
+    /// 間隔を切り替えるまでに予算を超えたフレームが続く回数
+    pub degrade_after_ticks: u32,
+}
+
+impl Default for FastModeConfig {
+    fn default() -> Self {
+        Self {
+            interval_ms: 100,
+            fallback_interval_ms: 500,
+            degrade_after_ticks: 5,
+        }
+    }
+}
+
+impl Validate for FastModeConfig {
+    const PREFIX: &'static str = "fast_mode";
+
+    fn check(&self, validator: &mut Validator) {
+        validator.range("interval_ms", self.interval_ms, 50, 1_000);
+        validator.range("fallback_interval_ms", self.fallback_interval_ms, 100, 60_000);
+        validator.range("degrade_after_ticks", self.degrade_after_ticks, 1, 100);
+        if self.fallback_interval_ms <= self.interval_ms {
+            validator.invalid("fallback_interval_ms", "interval_ms より長い間隔を指定してください");
+        }
+    }
+}
+
+impl FastModeConfig {
+    /// 高速モードと両立しない設定（理由の一覧、空なら開始できる）
+    ///
+    /// 高速モードが自動で変更する設定（二値化、拡大、ハッシュ、タイル）は含めない。
+    pub fn incompatibilities(&self, monitor_config: &MonitorConfig, ocr_config: &OcrConfig) -> Vec<String> {
+        let mut reasons = Vec::new();
+        if ocr_config.auto_detect_orientation {
+            reasons.push("ocr.auto_detect_orientation: 縦書きの判定で認識を繰り返すため使用できません".to_string());
+        }
+        if ocr_config.page_seg_mode.is_some_and(|mode| mode != FAST_MODE_PAGE_SEG_MODE) {
+            reasons.push(format!(
+                "ocr.page_seg_mode: 高速モードは{}（1行のテキスト）で認識します",
+                FAST_MODE_PAGE_SEG_MODE
+            ));
+        }
+        if ocr_config.transform != CaptureTransform::None_ {
+            reasons.push("ocr.transform: 幾何補正は毎回画像全体を変換するため使用できません".to_string());
+        }
+        if ocr_config.isolation == OcrIsolation::Subprocess {
+            reasons.push("ocr.isolation: 子プロセスでの認識は画像の受け渡しに時間がかかるため使用できません".to_string());
+        }
+        if monitor_config.column_split.is_some() {
+            reasons.push("monitor.column_split: 列ごとに認識を繰り返すため使用できません".to_string());
+        }
+        if monitor_config.attach_thumbnail {
+            reasons.push("monitor.attach_thumbnail: サムネイルのエンコードに時間がかかるため使用できません".to_string());
+        }
+        reasons
+    }
+
+    /// OCRの設定を最小限の前処理にする（グレースケールと大津の二値化、拡大は2倍まで、1行のテキスト）
+    pub fn apply_ocr(&self, config: &mut OcrConfig) {
+        config.fast_pipeline = true;
+        config.binarization = BinarizationMode::Otsu;
+        config.defringe_lcd = Some(false);
+        config.page_seg_mode = Some(FAST_MODE_PAGE_SEG_MODE);
+    }
+
+    /// タイル単位の変化検出を必ず有効にする
+    pub fn apply_tile(&self, config: &mut TileConfig) {
+        config.enabled = true;
+    }
+}
+
+/// 高速モードの状態（get_statsで報告する）
+#[derive(Debug, Clone, Serialize)]
+pub struct FastModeStats {
+    /// 指定した間隔（ミリ秒）
+    pub requested_interval_ms: u64,
+    /// 現在の間隔（ミリ秒、切り替え後は代わりの間隔）
+    pub effective_interval_ms: u64,
+    /// 指定した間隔での頻度（回/秒）
+    pub requested_rate_hz: f64,
+    /// 直近のフレームの開始時刻の間隔から求めた頻度（回/秒、フレームが2つ未満ならNone）
+    pub achieved_rate_hz: Option<f64>,
+    /// 所要時間が予算を超えたフレームの数
+    pub over_budget_ticks: u64,
+    /// 代わりの間隔に切り替えたかどうか
+    pub degraded: bool,
+}
+
+/// 高速モードの間隔の切り替えと達成した頻度の計測
+#[derive(Debug)]
+pub struct FastModeGovernor {
+    config: FastModeConfig,
+    /// 予算を超えたフレームが続いている回数
+    over_budget_streak: u32,
+    over_budget_ticks: u64,
+    degraded: bool,
+    /// 直近のフレームの開始時刻
+    recent_starts: VecDeque<Instant>,
+}
+
+impl FastModeGovernor {
+    pub fn new(config: FastModeConfig) -> Self {
+        Self {
+            config,
+            over_budget_streak: 0,
+            over_budget_ticks: 0,
+            degraded: false,
+            recent_starts: VecDeque::with_capacity(RATE_WINDOW),
+        }
+    }
+
+    /// 現在の監視の間隔（ミリ秒）
+    pub fn interval_ms(&self) -> u64 {
+        if self.degraded {
+            self.config.fallback_interval_ms
+        } else {
+            self.config.interval_ms
+        }
+    }
+
+    /// 監視の設定を高速モードの間隔にし、ハッシュによるOCRの省略を必ず有効にする
+    pub fn apply_monitor(&self, config: &mut MonitorConfig) {
+        config.interval_ms = self.interval_ms();
+        if config.dhash_skip_threshold == 0 {
+            config.dhash_skip_threshold = MonitorConfig::default().dhash_skip_threshold;
+        }
+    }
+
+    /// フレームの開始を記録
+    pub fn record_start(&mut self, at: Instant) {
+        if self.recent_starts.len() >= RATE_WINDOW {
+            self.recent_starts.pop_front();
+        }
+        self.recent_starts.push_back(at);
+    }
+
+    /// 認識したフレームの所要時間を記録（代わりの間隔に切り替えた場合はtrue）
+    pub fn record_duration(&mut self, duration: Duration, budget: Duration) -> bool {
+        if duration <= budget {
+            self.over_budget_streak = 0;
+            return false;
+        }
+        self.over_budget_ticks += 1;
+        self.over_budget_streak += 1;
+        if self.degraded || self.over_budget_streak < self.config.degrade_after_ticks {
+            return false;
+        }
+        self.degraded = true;
+        true
+    }
+
+    /// 現在の状態
+    pub fn stats(&self) -> FastModeStats {
+        let achieved_rate_hz = match (self.recent_starts.front(), self.recent_starts.back()) {
+            (Some(first), Some(last)) if self.recent_starts.len() >= 2 && last > first => {
+                Some((self.recent_starts.len() - 1) as f64 / last.duration_since(*first).as_secs_f64())
+            }
+            _ => None,
+        };
+        FastModeStats {
+            requested_interval_ms: self.config.interval_ms,
+            effective_interval_ms: self.interval_ms(),
+            requested_rate_hz: 1000.0 / self.config.interval_ms as f64,
+            achieved_rate_hz,
+            over_budget_ticks: self.over_budget_ticks,
+            degraded: self.degraded,
+        }
+    }
+}
@@ -0,0 +1,123 @@
+// 監視の各段階に利用者の処理を差し込むフック（キャプチャ・認識・イベント・エラーの通知）
+//
+// フックは監視のスレッドで同期的に呼ばれるため、1回の呼び出しは HOOK_TIME_BUDGET 以内に戻ること。
+// 時間のかかる処理（ネットワークへの送信など）はチャンネルで別のスレッドに渡す。
+// フックでのパニックは捕捉してログに残し、監視は続ける。
+use image::DynamicImage;
+use std::any::Any;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::time::{Duration, Instant};
+
+use crate::events::TextChangeEvent;
+use crate::ocr::OcrResult;
+
+/// 1回のフックの呼び出しにかける時間の目安（超えた場合は警告をログに出す）
+pub const HOOK_TIME_BUDGET: Duration = Duration::from_millis(5);
+
+/// フックに通知する監視のエラー
+#[derive(Debug, Clone)]
+pub enum MonitorError {
+    /// 画面のキャプチャに失敗
+    Capture(String),
+    /// 認識に失敗
+    Ocr(String),
+}
+
+impl fmt::Display for MonitorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MonitorError::Capture(message) => write!(f, "キャプチャエラー: {}", message),
+            MonitorError::Ocr(message) => write!(f, "OCRエラー: {}", message),
+        }
+    }
+}
+
+/// 監視の各段階で呼ばれる処理（必要なメソッドだけ実装する）
+pub trait MonitorHooks {
+    /// 画面をキャプチャした直後（変化の無いフレームとして認識を省略する場合も含む）
+    fn on_frame_captured(&self, _image: &DynamicImage) {}
+
+    /// 領域全体を認識した直後（補正や変化の比較より前、タイル単位の部分OCRでは呼ばれない）
+    fn on_text_recognized(&self, _result: &OcrResult) {}
+
+    /// イベントを送信する時（sequenceは履歴の連番で、履歴を持たない場合はNone）
+    fn on_event(&self, _event: &TextChangeEvent, _sequence: Option<u64>) {}
+
+    /// キャプチャ・認識に失敗した時
+    fn on_error(&self, _error: &MonitorError) {}
+
+    /// ログに出す名前
+    fn name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+}
+
+/// 登録順にフックを呼び出す
+#[derive(Default)]
+pub struct HookChain {
+    hooks: Vec<Box<dyn MonitorHooks + Send>>,
+}
+
+#[allow(dead_code)]
+impl HookChain {
+    /// フックを末尾に追加
+    pub fn add(&mut self, hooks: Box<dyn MonitorHooks + Send>) {
+        self.hooks.push(hooks);
+    }
+
+    /// 登録したフックをすべて削除
+    pub fn clear(&mut self) {
+        self.hooks.clear();
+    }
+
+    pub fn frame_captured(&self, image: &DynamicImage) {
+        self.each("on_frame_captured", |hooks| hooks.on_frame_captured(image));
+    }
+
+    pub fn text_recognized(&self, result: &OcrResult) {
+        self.each("on_text_recognized", |hooks| hooks.on_text_recognized(result));
+    }
+
+    pub fn event(&self, event: &TextChangeEvent, sequence: Option<u64>) {
+        self.each("on_event", |hooks| hooks.on_event(event, sequence));
+    }
+
+    pub fn error(&self, error: &MonitorError) {
+        self.each("on_error", |hooks| hooks.on_error(error));
+    }
+
+    /// 各フックを呼び出し、パニックと時間の超過をログに残す
+    fn each(&self, stage: &str, call: impl Fn(&dyn MonitorHooks)) {
+        for hooks in &self.hooks {
+            let start = Instant::now();
+            if let Err(panic) = panic::catch_unwind(AssertUnwindSafe(|| call(hooks.as_ref()))) {
+                log::error!(
+                    "フック {} の {} でパニックが発生しました（監視は続けます）: {}",
+                    hooks.name(),
+                    stage,
+                    panic_message(panic.as_ref())
+                );
+            }
+            let elapsed = start.elapsed();
+            if elapsed > HOOK_TIME_BUDGET {
+                log::warn!(
+                    "フック {} の {} に時間がかかっています: {}ms（目安 {}ms）",
+                    hooks.name(),
+                    stage,
+                    elapsed.as_millis(),
+                    HOOK_TIME_BUDGET.as_millis()
+                );
+            }
+        }
+    }
+}
+
+/// パニックの値からメッセージを取り出す
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    panic
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "不明なパニック".to_string())
+}
@@ -0,0 +1,173 @@
+// 日本語の認識結果に特有の崩れの補正（Tesseractのjpnの言語データ向け）
+//
+// jpnの言語データは文字と文字の間に空白を入れたり、長音符とダッシュ・ハイフンを取り違えたりする。
+// 補正は認識結果の正規化の一部として変化の比較より前に行うため、補正そのものでイベントは発生しない。
+
+/// 長音符と取り違えやすい文字（長音符、ダッシュ、全角・半角のハイフンなど）
+const DASH_LIKE: [char; 7] = ['ー', '―', '－', '-', '‐', '—', '─'];
+
+/// 認識の崩れで重複しやすい句読点（「！！」や「……」のような意図的な繰り返しや、
+/// 「（（注））」「」」」のように入れ子で正しく重なる括弧は残す）
+const DUPLICATED_PUNCTUATION: [char; 4] = ['、', '。', '，', '．'];
+
+/// 言語の設定に日本語（jpn、jpn_vert）が含まれるかどうか
+pub fn includes_japanese(language: &str) -> bool {
+    language.split('+').any(|code| code.trim_end_matches("_vert") == "jpn")
+}
+
+/// 日本語の認識結果の崩れを行ごとに補正
+///
+/// 1. 日本語の文字どうしの間の空白を削除する（英単語の間や日本語と英語の間の空白は残す）
+/// 2. 長音符と取り違えやすい文字を前後の文字種に合わせる（かなの後ろで英数字が続かなければ「ー」、英数字の隣は「-」）
+/// 3. 2で長音符にした文字の後ろの空白を削除する
+/// 4. 同じ句読点の重複を1つにまとめる
+pub fn normalize_japanese(text: &str) -> String {
+    text.split('\n')
+        .map(|line| collapse_punctuation(&remove_cjk_spaces(&normalize_dashes(&remove_cjk_spaces(line)))))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 日本語の文字（かな、漢字、全角の記号や英数字、ダッシュ）かどうか
+fn is_japanese(c: char) -> bool {
+    matches!(c,
+        '\u{3001}'..='\u{303F}'   // 句読点・括弧（全角の空白は含めない）
+        | '\u{3040}'..='\u{30FF}' // ひらがな・カタカナ
+        | '\u{31F0}'..='\u{31FF}' // カタカナの小書き
+        | '\u{3400}'..='\u{4DBF}' // 漢字（拡張A）
+        | '\u{4E00}'..='\u{9FFF}' // 漢字
+        | '\u{F900}'..='\u{FAFF}' // 互換漢字
+        | '\u{FF01}'..='\u{FF9F}' // 全角の英数字・記号、半角カタカナ
+        | '\u{2015}'              // 日本語の文で使うダッシュ
+    )
+}
+
+/// かな（長音符を付けられる文字）かどうか
+fn is_kana(c: char) -> bool {
+    matches!(c, '\u{3041}'..='\u{3096}' | '\u{30A1}'..='\u{30FA}' | 'ー' | '\u{31F0}'..='\u{31FF}' | '\u{FF66}'..='\u{FF9D}')
+}
+
+/// 日本語の文字に挟まれた空白を削除
+fn remove_cjk_spaces(line: &str) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    let mut out = String::with_capacity(line.len());
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] != ' ' {
+            out.push(chars[i]);
+            i += 1;
+            continue;
+        }
+        let end = chars[i..].iter().position(|&c| c != ' ').map_or(chars.len(), |offset| i + offset);
+        let between_japanese = i > 0 && end < chars.len() && is_japanese(chars[i - 1]) && is_japanese(chars[end]);
+        if !between_japanese {
+            out.extend(&chars[i..end]);
+        }
+        i = end;
+    }
+    out
+}
+
+/// 長音符と取り違えやすい文字を前後の文字種に合わせる
+fn normalize_dashes(line: &str) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    let mut out: Vec<char> = Vec::with_capacity(chars.len());
+    for (i, &c) in chars.iter().enumerate() {
+        if !DASH_LIKE.contains(&c) {
+            out.push(c);
+            continue;
+        }
+        // 直前は補正後の文字を見て「ーー」のような連続にも対応する
+        let previous = out.last().copied();
+        let next = chars.get(i + 1).copied();
+        // 「モード-2」のようにかなと英数字を繋ぐハイフンは長音符にしない
+        let normalized = if previous.is_some_and(is_kana) && !next.is_some_and(|n| n.is_ascii_alphanumeric()) {
+            'ー'
+        } else if previous.is_some_and(|p| p.is_ascii_alphanumeric()) || next.is_some_and(|n| n.is_ascii_alphanumeric()) {
+            '-'
+        } else {
+            c
+        };
+        out.push(normalized);
+    }
+    out.into_iter().collect()
+}
+
+/// 同じ句読点の重複を1つにまとめる
+fn collapse_punctuation(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut previous = None;
+    for c in line.chars() {
+        if previous == Some(c) && DUPLICATED_PUNCTUATION.contains(&c) {
+            continue;
+        }
+        out.push(c);
+        previous = Some(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_japanese_languages() {
+        assert!(includes_japanese("jpn"));
+        assert!(includes_japanese("eng+jpn_vert"));
+        assert!(!includes_japanese("eng"));
+        assert!(!includes_japanese("chi_sim"));
+    }
+
+    #[test]
+    fn removes_spaces_between_japanese_characters() {
+        assert_eq!(normalize_japanese("日 本 語 の テ キ ス ト"), "日本語のテキスト");
+        assert_eq!(normalize_japanese("こんにちは、 世界。"), "こんにちは、世界。");
+    }
+
+    #[test]
+    fn keeps_spaces_in_mixed_lines() {
+        assert_eq!(normalize_japanese("Hello World"), "Hello World");
+        assert_eq!(normalize_japanese("今日は Rust で 開 発"), "今日は Rust で開発");
+        assert_eq!(normalize_japanese("iPhone 15 を 買 っ た"), "iPhone 15 を買った");
+    }
+
+    #[test]
+    fn normalizes_dashes_after_kana() {
+        assert_eq!(normalize_japanese("ラ―メン"), "ラーメン");
+        assert_eq!(normalize_japanese("サーバ－"), "サーバー");
+        assert_eq!(normalize_japanese("ス-パ- マ-ケット"), "スーパーマーケット");
+        assert_eq!(normalize_japanese("ワ——イ"), "ワーーイ");
+    }
+
+    #[test]
+    fn keeps_hyphens_next_to_ascii() {
+        assert_eq!(normalize_japanese("モード-2"), "モード-2");
+        assert_eq!(normalize_japanese("モード－A"), "モード-A");
+        assert_eq!(normalize_japanese("A－B間"), "A-B間");
+        assert_eq!(normalize_japanese("2024-01-01"), "2024-01-01");
+    }
+
+    #[test]
+    fn keeps_dashes_between_kanji() {
+        assert_eq!(normalize_japanese("東京―大阪"), "東京―大阪");
+    }
+
+    #[test]
+    fn collapses_repeated_sentence_punctuation() {
+        assert_eq!(normalize_japanese("はい、、そうです。。"), "はい、そうです。");
+        assert_eq!(normalize_japanese("本当！！"), "本当！！");
+        assert_eq!(normalize_japanese("えっと……"), "えっと……");
+    }
+
+    #[test]
+    fn keeps_nested_brackets() {
+        assert_eq!(normalize_japanese("「彼は「はい」」と言った"), "「彼は「はい」」と言った");
+        assert_eq!(normalize_japanese("（注（1））"), "（注（1））");
+    }
+
+    #[test]
+    fn normalizes_each_line_independently() {
+        assert_eq!(normalize_japanese("テ ス ト\nA - B\n"), "テスト\nA - B\n");
+    }
+}
@@ -0,0 +1,243 @@
+// 行ごとの表示期間の追跡（字幕の作成など、各行が現れてから消えるまでの時刻を記録する）
+//
+// 行の対応付けは安定度の追跡と同じあいまいな一致で行い、OCRの揺れで行が現れ直したことにならないようにする。
+use crate::events::TextChangeEvent;
+use crate::stability::{similarity, MATCH_THRESHOLD};
+
+/// 追跡する行の最大数（超えた場合は先に現れた行から消えたことにする）
+const MAX_TRACKED_LINES: usize = 200;
+
+/// 行が消えたとみなす、続けて認識されなかった回数（1回の認識漏れでは消えたことにしない）
+const MISSING_TICKS: u32 = 2;
+
+/// 行の表示の変化
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LineTransition {
+    /// 行が現れた
+    Appeared { line: String, appeared_at_ms: u64 },
+    /// 行が消えた（lineは最後に認識したテキスト）
+    Disappeared {
+        line: String,
+        appeared_at_ms: u64,
+        disappeared_at_ms: u64,
+    },
+}
+
+impl From<LineTransition> for TextChangeEvent {
+    fn from(transition: LineTransition) -> Self {
+        match transition {
+            LineTransition::Appeared { line, appeared_at_ms } => TextChangeEvent::LineAppeared { line, appeared_at_ms },
+            LineTransition::Disappeared {
+                line,
+                appeared_at_ms,
+                disappeared_at_ms,
+            } => TextChangeEvent::LineDisappeared {
+                line,
+                appeared_at_ms,
+                disappeared_at_ms,
+            },
+        }
+    }
+}
+
+/// 追跡中の1行
+#[derive(Debug)]
+struct TrackedLine {
+    /// 最後に認識されたテキスト
+    text: String,
+    /// 最後に検出された行位置
+    position: usize,
+    /// 最初に認識された時刻
+    appeared_at_ms: u64,
+    /// 認識されなくなった時刻（認識されている間はNone）
+    missing_since_ms: Option<u64>,
+    /// 続けて認識されなかった回数
+    missing_ticks: u32,
+}
+
+impl TrackedLine {
+    fn disappeared(self, disappeared_at_ms: u64) -> LineTransition {
+        LineTransition::Disappeared {
+            line: self.text,
+            appeared_at_ms: self.appeared_at_ms,
+            disappeared_at_ms,
+        }
+    }
+}
+
+/// ティックをまたいで行ごとに現れた時刻と消えた時刻を追跡する
+#[derive(Debug, Default)]
+pub struct LineLifetimeTracker {
+    /// 現れた順の追跡中の行
+    lines: Vec<TrackedLine>,
+}
+
+impl LineLifetimeTracker {
+    /// 1ティック分の認識結果を記録し、行の表示の変化を返す
+    ///
+    /// 消えた時刻は最初に認識されなかったティックの時刻とする。
+    pub fn observe(&mut self, text: &str, now_ms: u64) -> Vec<LineTransition> {
+        let mut transitions = Vec::new();
+        let mut matched = vec![false; self.lines.len()];
+
+        for (position, line) in text.lines().map(str::trim).enumerate() {
+            if line.is_empty() {
+                continue;
+            }
+
+            // 未対応の行の中から最も類似した行を探す（同程度なら位置が近い方を優先）
+            let best = self
+                .lines
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| !matched[*index])
+                .map(|(index, tracked)| {
+                    let distance = tracked.position.abs_diff(position) as f32;
+                    (index, similarity(&tracked.text, line) - distance * 0.01)
+                })
+                .filter(|(_, score)| *score >= MATCH_THRESHOLD)
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+
+            match best {
+                Some((index, _)) => {
+                    matched[index] = true;
+                    let tracked = &mut self.lines[index];
+                    tracked.text = line.to_string();
+                    tracked.position = position;
+                    tracked.missing_since_ms = None;
+                    tracked.missing_ticks = 0;
+                }
+                None => {
+                    self.lines.push(TrackedLine {
+                        text: line.to_string(),
+                        position,
+                        appeared_at_ms: now_ms,
+                        missing_since_ms: None,
+                        missing_ticks: 0,
+                    });
+                    matched.push(true);
+                    transitions.push(LineTransition::Appeared {
+                        line: line.to_string(),
+                        appeared_at_ms: now_ms,
+                    });
+                }
+            }
+        }
+
+        // 認識されなかった行は、続けて認識されなければ消えたことにする
+        let lines = std::mem::take(&mut self.lines);
+        for (mut tracked, matched) in lines.into_iter().zip(matched) {
+            if !matched {
+                let missing_since_ms = *tracked.missing_since_ms.get_or_insert(now_ms);
+                tracked.missing_ticks += 1;
+                if tracked.missing_ticks >= MISSING_TICKS {
+                    transitions.push(tracked.disappeared(missing_since_ms));
+                    continue;
+                }
+            }
+            self.lines.push(tracked);
+        }
+
+        // 上限を超えた分は先に現れた行から消えたことにする
+        let excess = self.lines.len().saturating_sub(MAX_TRACKED_LINES);
+        transitions.extend(self.lines.drain(..excess).map(|tracked| tracked.disappeared(now_ms)));
+        transitions
+    }
+
+    /// 追跡中の行をすべて消えたことにする（監視の終了時や、追跡を無効にした時）
+    pub fn finish(&mut self, now_ms: u64) -> Vec<LineTransition> {
+        self.lines
+            .drain(..)
+            .map(|tracked| {
+                let disappeared_at_ms = tracked.missing_since_ms.unwrap_or(now_ms);
+                tracked.disappeared(disappeared_at_ms)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn appeared(line: &str, at: u64) -> LineTransition {
+        LineTransition::Appeared {
+            line: line.to_string(),
+            appeared_at_ms: at,
+        }
+    }
+
+    fn disappeared(line: &str, appeared_at_ms: u64, disappeared_at_ms: u64) -> LineTransition {
+        LineTransition::Disappeared {
+            line: line.to_string(),
+            appeared_at_ms,
+            disappeared_at_ms,
+        }
+    }
+
+    #[test]
+    fn reports_new_lines_once() {
+        let mut tracker = LineLifetimeTracker::default();
+        assert_eq!(
+            tracker.observe("こんにちは\n世界", 100),
+            vec![appeared("こんにちは", 100), appeared("世界", 100)]
+        );
+        assert!(tracker.observe("こんにちは\n世界", 200).is_empty());
+    }
+
+    #[test]
+    fn ocr_noise_keeps_the_birth_time() {
+        let mut tracker = LineLifetimeTracker::default();
+        tracker.observe("The quick brown fox", 0);
+        assert!(tracker.observe("The quick brovvn fox", 100).is_empty());
+        assert_eq!(tracker.finish(500), vec![disappeared("The quick brovvn fox", 0, 500)]);
+    }
+
+    #[test]
+    fn single_missed_tick_does_not_end_a_line() {
+        let mut tracker = LineLifetimeTracker::default();
+        tracker.observe("字幕の一行目", 0);
+        assert!(tracker.observe("", 100).is_empty());
+        assert!(tracker.observe("字幕の一行目", 200).is_empty());
+        assert_eq!(tracker.finish(300), vec![disappeared("字幕の一行目", 0, 300)]);
+    }
+
+    #[test]
+    fn disappearance_uses_the_first_missing_tick() {
+        let mut tracker = LineLifetimeTracker::default();
+        tracker.observe("消える行\n残る行", 0);
+        assert!(tracker.observe("残る行", 100).is_empty());
+        assert_eq!(tracker.observe("残る行", 200), vec![disappeared("消える行", 0, 100)]);
+    }
+
+    #[test]
+    fn replaced_line_appears_and_old_line_disappears() {
+        let mut tracker = LineLifetimeTracker::default();
+        tracker.observe("最初の台詞です", 0);
+        assert_eq!(tracker.observe("全然違うテキスト", 100), vec![appeared("全然違うテキスト", 100)]);
+        assert_eq!(tracker.observe("全然違うテキスト", 200), vec![disappeared("最初の台詞です", 0, 100)]);
+    }
+
+    #[test]
+    fn finish_closes_all_open_lines() {
+        let mut tracker = LineLifetimeTracker::default();
+        tracker.observe("a line\nanother one", 10);
+        let mut closed = tracker.finish(90);
+        closed.sort_by_key(|transition| format!("{:?}", transition));
+        assert_eq!(closed, vec![disappeared("a line", 10, 90), disappeared("another one", 10, 90)]);
+        assert!(tracker.finish(100).is_empty());
+    }
+
+    #[test]
+    fn tracked_lines_are_bounded() {
+        let mut tracker = LineLifetimeTracker::default();
+        let text = (0..MAX_TRACKED_LINES + 5).map(|i| format!("line {}", i)).collect::<Vec<_>>().join("\n");
+        let transitions = tracker.observe(&text, 0);
+        let evicted = transitions
+            .iter()
+            .filter(|transition| matches!(transition, LineTransition::Disappeared { .. }))
+            .count();
+        assert_eq!(tracker.lines.len(), MAX_TRACKED_LINES);
+        assert_eq!(evicted, 5);
+    }
+}
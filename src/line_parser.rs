@@ -0,0 +1,100 @@
+// 差分の追加行の分解（"名前: メッセージ" 形式のチャット向け）
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::validation::{Validate, Validator};
+
+/// 既定の分解パターン（全角・半角のコロンで発言者とメッセージを区切る）
+pub const DEFAULT_LINE_PATTERN: &str = r"^(?P<speaker>[^:：]+)[:：]\s*(?P<message>.+)$";
+
+/// 差分の設定
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiffConfig {
+    /// 追加行を発言者とメッセージに分解するかどうか
+    pub parse_lines: bool,
+    /// 分解に使う正規表現（名前付きグループ speaker と message を含む）
+    pub line_pattern: String,
+}
+
+impl Default for DiffConfig {
+    fn default() -> Self {
+        Self {
+            parse_lines: false,
+            line_pattern: DEFAULT_LINE_PATTERN.to_string(),
+        }
+    }
+}
+
+impl Validate for DiffConfig {
+    const PREFIX: &'static str = "diff";
+
+    fn check(&self, validator: &mut Validator) {
+        match Regex::new(&self.line_pattern) {
+            Ok(regex) => {
+                for group in ["speaker", "message"] {
+                    if !regex.capture_names().flatten().any(|name| name == group) {
+                        validator.invalid("line_pattern", format!("名前付きグループ {} がありません", group));
+                    }
+                }
+            }
+            Err(e) => validator.invalid("line_pattern", e),
+        }
+    }
+}
+
+/// 分解した追加行
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParsedLine {
+    /// 元の行
+    pub line: String,
+    /// 発言者（パターンに一致しない行はNone）
+    pub speaker: Option<String>,
+    /// メッセージ（パターンに一致しない行は元の行）
+    pub message: String,
+}
+
+/// 追加行を分解する（変化の検出には影響しない）
+#[derive(Debug, Clone)]
+pub struct LineParser {
+    pattern: Regex,
+}
+
+impl LineParser {
+    /// 設定から作成（分解が無効、またはパターンが不正な場合はNone）
+    pub fn from_config(config: &DiffConfig) -> Option<Self> {
+        if !config.parse_lines {
+            return None;
+        }
+        match Regex::new(&config.line_pattern) {
+            Ok(pattern) => Some(Self { pattern }),
+            Err(e) => {
+                log::warn!("行の分解パターンが不正です: {}", e);
+                None
+            }
+        }
+    }
+
+    /// 1行を分解
+    pub fn parse(&self, line: &str) -> ParsedLine {
+        let captures = self.pattern.captures(line);
+        let group = |name: &str| {
+            captures
+                .as_ref()
+                .and_then(|captures| captures.name(name))
+                .map(|m| m.as_str().trim().to_string())
+        };
+        match (group("speaker"), group("message")) {
+            (Some(speaker), Some(message)) => ParsedLine {
+                line: line.to_string(),
+                speaker: Some(speaker),
+                message,
+            },
+            _ => ParsedLine {
+                line: line.to_string(),
+                speaker: None,
+                message: line.to_string(),
+            },
+        }
+    }
+}
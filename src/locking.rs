@@ -0,0 +1,15 @@
+// 汚染（poison）されたMutexの回復
+//
+// ロックを保持したスレッドがパニックするとMutexは汚染されるが、共有する状態はいずれも
+// 1回の更新の途中で壊れても使い続けられる値（フラグ、カウンタ、履歴など）のため、
+// 中身をそのまま回復して使う。これにより一度のパニックで以降の全コマンドが失敗し続けることを防ぐ。
+use std::sync::{Mutex, MutexGuard};
+
+/// ロックを取得（汚染されていれば中身を回復し、汚染の状態も解除する）
+pub fn lock<T: ?Sized>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| {
+        log::warn!("ロックが汚染されていたため回復しました");
+        mutex.clear_poison();
+        poisoned.into_inner()
+    })
+}
@@ -0,0 +1,80 @@
+// アプリ内のログの保持（不具合報告用のデバッグバンドルに直近のログを含める）
+//
+// 出力はこれまで通りenv_loggerに任せ、RUST_LOGの指定に関わらずINFO以上の直近のログを保持する。
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use crate::events::now_millis;
+use crate::locking::lock;
+
+/// 保持するログの件数
+pub const LOG_BUFFER_CAPACITY: usize = 1000;
+
+/// 出力の設定に関わらず保持するログのレベル
+const BUFFERED_LEVEL: log::LevelFilter = log::LevelFilter::Info;
+
+/// 保持している直近のログ
+static RECENT_LOGS: Mutex<VecDeque<LogRecord>> = Mutex::new(VecDeque::new());
+
+/// 保持した1件のログ
+#[derive(Debug, Clone, Serialize)]
+pub struct LogRecord {
+    /// 記録した時刻（UNIXエポックからのミリ秒）
+    pub timestamp_ms: u64,
+    /// レベル（ERROR、WARNなど）
+    pub level: String,
+    /// 出力元のモジュール
+    pub target: String,
+    /// メッセージ
+    pub message: String,
+}
+
+/// env_loggerに出力しつつ直近のログを保持するロガー
+struct BufferedLogger {
+    inner: env_logger::Logger,
+}
+
+impl log::Log for BufferedLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= BUFFERED_LEVEL || self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if record.level() <= BUFFERED_LEVEL {
+            let mut logs = lock(&RECENT_LOGS);
+            if logs.len() >= LOG_BUFFER_CAPACITY {
+                logs.pop_front();
+            }
+            logs.push_back(LogRecord {
+                timestamp_ms: now_millis(),
+                level: record.level().to_string(),
+                target: record.target().to_string(),
+                message: record.args().to_string(),
+            });
+        }
+        if self.inner.matches(record) {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// ログを初期化（env_logger::init の代わりに起動時に1回呼ぶ）
+pub fn init() {
+    let inner = env_logger::Builder::from_default_env().build();
+    let max_level = inner.filter().max(BUFFERED_LEVEL);
+    if log::set_boxed_logger(Box::new(BufferedLogger { inner })).is_ok() {
+        log::set_max_level(max_level);
+    }
+}
+
+/// 直近のログ（古い順、最大 limit 件）
+pub fn recent(limit: usize) -> Vec<LogRecord> {
+    let logs = lock(&RECENT_LOGS);
+    logs.iter().skip(logs.len().saturating_sub(limit)).cloned().collect()
+}
+
@@ -5,74 +5,476 @@
 )]
 
 use anyhow::Result;
-use serde::{Deserialize, Serialize};
+use image::DynamicImage;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc as std_mpsc;
 use std::sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tauri::{State, Window, Manager};
 use log::info;
+use regex::Regex;
 
+mod autotune;
+mod backends;
 mod capture;
+mod change_visual;
+mod cli;
+mod clock;
+mod combinators;
+mod compare;
+mod contrast_probe;
+mod corrections;
+mod debug_bundle;
+mod deep_link;
+mod events;
+mod evidence;
+mod export;
+mod fast_mode;
+mod hooks;
+mod japanese_text;
+mod line_lifetime;
+mod line_parser;
+mod locking;
+mod log_buffer;
+mod memory;
+mod middleware;
+mod monitor;
+mod mqtt;
 mod ocr;
+mod ocr_stats;
+mod palette;
+mod phase;
+mod pipe_output;
+mod preprocessing;
+mod process_guard;
+mod region_payload;
+#[cfg(feature = "rest")]
+mod rest;
+mod report;
+mod schema;
+mod screen_change;
+mod script_check;
+mod shutdown;
+mod single_instance;
+mod stability;
+mod startup_check;
+mod state_commands;
+mod stats;
+mod summary;
+mod tessdata;
+mod tesseract_api;
+#[cfg(test)]
+mod test_helpers;
+mod text_assert;
+mod text_server;
+mod tiling;
+mod transform;
+mod validation;
+mod watchlist;
+mod wizard;
+mod worker;
 
-use crate::capture::{CaptureRegion, ScreenCapture};
-use crate::ocr::OcrEngine;
+use crate::autotune::AutoTuneReport;
+use crate::capture::{
+    CaptureConfig, CaptureFormatReport, CaptureRegion, DisplayGeometry, LiveScreenSource, ScreenCapture,
+    ScreenListing, SelectorConfig,
+};
+use crate::clock::SessionClock;
+use crate::compare::RegionComparison;
+use crate::corrections::{CorrectionConfig, CorrectionRule, CorrectionTable, SharedCorrections};
+use crate::debug_bundle::{DebugBundle, PlatformInfo, TesseractInfo};
+use crate::events::{
+    now_millis, EventEmitter, EventFilter, EventPage, EventWindow, HistoryEntry, SharedEventSink, SharedHistory,
+    TextChangeEvent,
+};
+use crate::evidence::{EventImageError, EvidenceConfig, SharedEvidence};
+use crate::export::{CsvOptions, SrtMode, TimestampZone};
+use crate::fast_mode::FastModeConfig;
+use crate::line_parser::DiffConfig;
+use crate::locking::lock;
+use crate::monitor::{
+    snapshot_delay, MonitorConfig, MonitorSnapshot, SharedMonitorConfig, SharedTextFrequency,
+    TextFrequencyEntry,
+};
+#[cfg(feature = "html_diff")]
+use crate::monitor::TextDiffer;
+use crate::mqtt::MqttSinkConfig;
+use crate::ocr::{encode_png, encode_png_base64, OcrConfig, OcrEngine, OcrInitError, PipelineTrace, PreprocessTimings};
+use crate::ocr_stats::OcrStats;
+use crate::palette::DominantColor;
+use crate::phase::{InvalidStateError, MonitorCommandError, MonitorPhase};
+use crate::pipe_output::{EventPipe, FileRotationPolicy, OutputFormat, SharedEventPipe};
+use crate::preprocessing::ImageMetrics;
+use crate::process_guard::{ensure_allowed, guarded_capture, ProcessGuardConfig, ProcessGuardStatus};
+use crate::region_payload::REGION_SELECTED_ERROR_EVENT;
+use crate::report::ReportInput;
+use crate::schema::EventChannels;
+use crate::script_check::SharedLanguageSuggestion;
+use crate::stability::{LineStability, SharedStability};
+use crate::tessdata::{DownloadError, DownloadReport, OcrAvailability};
+use crate::text_assert::{AssertResult, TextProbe};
+use crate::startup_check::StartupReport;
+use crate::stats::{MetricsServer, MonitorStats, SharedStats};
+use crate::summary::{SessionSummary, SharedSummaries};
+use crate::text_server::TextServerConfig;
+use crate::tiling::TileConfig;
+use crate::validation::Validate;
+use crate::watchlist::{SharedWatchlist, WatchlistConfig};
+use crate::wizard::{RemediationCode, WizardEnvironment, WizardError, WizardTestResult};
+use crate::worker::{MonitorSession, MonitorWorker};
+
+/// デバッグバンドルに含めるログの件数
+const DEBUG_BUNDLE_LOG_RECORDS: usize = 500;
+
+/// デバッグバンドルに含めるイベントの画像の数
+const DEBUG_BUNDLE_EVIDENCE_IMAGES: usize = 5;
 
 /// アプリケーションの状態
 #[derive(Default)]
 struct AppState {
     /// 選択中の領域
     selected_region: Option<CaptureRegion>,
-    /// 監視が実行中かどうか
-    is_monitoring: bool,
+    /// 監視の状態（状態を変更するコマンドはこの状態を遷移させてから実行する）
+    phase: MonitorPhase,
     /// 監視停止シグナル
     stop_monitoring: Arc<AtomicBool>,
     /// 監視スレッドのハンドル
     monitor_handle: Option<thread::JoinHandle<()>>,
+    /// 計測済みのOCR信頼度ベースライン
+    ocr_baseline: Option<f32>,
+    /// 送信済みイベントの履歴
+    history: SharedHistory,
+    /// アプリが管理する言語データのディレクトリ
+    tessdata_dir: Option<PathBuf>,
+    /// 初回起動時の言語データ自動ダウンロードを行わない（オフライン環境用）
+    skip_auto_download: bool,
+    /// パイプライン追跡時に中間画像を保存するかどうか
+    debug_pipeline: bool,
+    /// 行ごとの認識安定度
+    line_stability: SharedStability,
+    /// 認識された行ごとの出現回数
+    text_frequency: SharedTextFrequency,
+    /// キャプチャの設定
+    capture_config: CaptureConfig,
+    /// OCRの設定
+    ocr_config: OcrConfig,
+    /// タイル単位の変化検出の設定
+    tile_config: TileConfig,
+    /// 監視処理の統計
+    stats: SharedStats,
+    /// 起動中のメトリクスサーバー
+    metrics_server: Option<MetricsServer>,
+    /// 起動中のREST APIサーバーのポート
+    #[cfg(feature = "rest")]
+    rest_server_port: Option<u16>,
+    /// 最新のテキストの配信サーバーの設定
+    text_server_config: TextServerConfig,
+    /// 起動中のテキスト配信サーバー
+    #[cfg(feature = "rest")]
+    text_server: Option<text_server::TextServer>,
+    /// イベントの送信先チャンネル名
+    event_channels: EventChannels,
+    /// イベントのアプリの外への配信先（MQTTへの配信を起動中のみ）
+    event_sink: SharedEventSink,
+    /// イベントのファイル・標準出力への書き出し（pipe_outputで開始した場合のみ）
+    event_pipe: SharedEventPipe,
+    /// 監視の設定（監視中の変更も即時に反映）
+    monitor_config: SharedMonitorConfig,
+    /// 現在（または直前）の監視セッションの識別子
+    session_id: u64,
+    /// 利用者の訂正から学習した補正
+    corrections: SharedCorrections,
+    /// 補正の保存先ファイル
+    corrections_file: Option<PathBuf>,
+    /// イベントの元になった画像の保持の設定
+    evidence_config: EvidenceConfig,
+    /// 直近の認識に使った前処理済み画像
+    evidence: SharedEvidence,
+    /// 差分の設定
+    diff_config: DiffConfig,
+    /// 領域選択の設定
+    selector_config: SelectorConfig,
+    /// 直近の監視セッションの要約
+    summaries: SharedSummaries,
+    /// 認識言語（Noneの場合は既定の言語）
+    ocr_language: Option<String>,
+    /// キーワードの監視（監視中にも変更できる）
+    watchlist: SharedWatchlist,
+    /// 監視中のスレッドへのOCRエンジン再読み込みの要求の送信先
+    ocr_reload: Option<std_mpsc::Sender<OcrReloadRequest>>,
+    /// 比較の基準とするテキスト（監視の開始時に前回のテキストとして使う）
+    reference_text: Option<String>,
+    /// 監視中のスレッドへの基準のテキストの変更の送信先（Noneを送ると解除）
+    reference_updates: Option<std_mpsc::Sender<Option<String>>>,
+    /// 認識結果の文字種から提案した認識言語（apply_suggested_languageで反映する）
+    language_suggestion: SharedLanguageSuggestion,
+    /// キャプチャしないプロセスの設定
+    process_guard_config: ProcessGuardConfig,
+    /// デバッグバンドルの保存先（Noneなら一時ディレクトリ）
+    debug_bundle_dir: Option<PathBuf>,
+    /// 監視中のセッションの高速モードの設定（再開始しても同じ設定で監視する）
+    fast_mode: Option<FastModeConfig>,
+}
+
+/// 監視スレッドへのOCRエンジン再読み込みの要求
+struct OcrReloadRequest {
+    /// 読み込む言語
+    language: String,
+    /// 再読み込みの結果の返信先
+    reply: std_mpsc::Sender<Result<(), String>>,
+}
+
+impl AppState {
+    /// 現在の履歴バッファとチャンネル設定を使う送信器を作成
+    fn emitter(&self, window: Window) -> EventEmitter {
+        self.emitter_with_clock(window, SessionClock::start())
+    }
+
+    /// 現在の監視セッションの識別子を付けた送信器を作成（時刻はセッションの時計で決める）
+    fn session_emitter(&self, window: Window, clock: SessionClock) -> EventEmitter {
+        let mut emitter = self.emitter_with_clock(window, clock);
+        emitter.set_session_id(self.session_id);
+        emitter
+    }
+
+    fn emitter_with_clock(&self, window: impl EventWindow + 'static, clock: SessionClock) -> EventEmitter {
+        EventEmitter::new(
+            window,
+            self.history.clone(),
+            self.event_channels.clone(),
+            self.stats.clone(),
+            self.event_sink.clone(),
+            self.event_pipe.clone(),
+            clock,
+        )
+    }
+
+    /// 監視セッションで使う設定の範囲を確認（高速モードで開始する場合はその設定も確認）
+    fn validate_session_config(&self, fast_mode: Option<&FastModeConfig>) -> Result<(), String> {
+        let mut errors: Vec<String> = [
+            lock(&self.monitor_config).validate(),
+            self.tile_config.validate(),
+            self.capture_config.validate(),
+            self.ocr_config.validate(),
+            self.diff_config.validate(),
+            self.process_guard_config.validate(),
+        ]
+        .into_iter()
+        .filter_map(|result| result.err().map(|e| e.to_string()))
+        .collect();
+
+        // 高速モードは設定の範囲に加えて、両立しない設定が無いことを確認する
+        if let Some(fast_mode) = fast_mode {
+            if let Err(e) = fast_mode.validate() {
+                errors.push(e.to_string());
+            }
+            let incompatibilities = fast_mode.incompatibilities(&lock(&self.monitor_config), &self.ocr_config);
+            if !incompatibilities.is_empty() {
+                errors.push(format!(
+                    "高速モードでは次の設定を使用できません（高速モードは最小限の前処理と1行のテキストの認識で短い間隔を保ちます）:\n{}",
+                    incompatibilities.join("\n")
+                ));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+
+    /// 補正テーブルを保存（保存先が無い場合は何もしない）
+    fn save_corrections(&self) -> Result<()> {
+        if let Some(path) = &self.corrections_file {
+            lock(&self.corrections).save(path)?;
+        }
+        Ok(())
+    }
+}
+
+/// 領域選択のエラー
+#[derive(Debug, Clone)]
+enum RegionSelectError {
+    /// 選択画面が期限内に読み込まれなかった
+    SelectorFailedToLoad,
+    /// 利用者がキャンセルした
+    Cancelled,
+    /// 選択がタイムアウトした
+    Timeout,
+    /// 現在の状態では領域選択できない（選択中・監視の開始中など）
+    InvalidState(InvalidStateError),
+    /// 選択した領域が領域の中心を含むモニターの範囲に重ならない
+    OutsideDisplay(CaptureRegion),
+    /// ウィンドウの作成失敗などその他のエラー
+    Internal(String),
+}
+
+impl RegionSelectError {
+    /// フロントエンドで判別するための固定識別子
+    fn kind(&self) -> &'static str {
+        match self {
+            RegionSelectError::SelectorFailedToLoad => "selector_failed_to_load",
+            RegionSelectError::Cancelled => "cancelled",
+            RegionSelectError::Timeout => "timeout",
+            RegionSelectError::InvalidState(_) => "invalid_state",
+            RegionSelectError::OutsideDisplay(_) => "outside_display",
+            RegionSelectError::Internal(_) => "internal",
+        }
+    }
+}
+
+impl std::fmt::Display for RegionSelectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegionSelectError::SelectorFailedToLoad => write!(f, "領域選択画面を読み込めませんでした"),
+            RegionSelectError::Cancelled => write!(f, "領域選択がキャンセルされました"),
+            RegionSelectError::Timeout => write!(f, "領域選択がタイムアウトしました"),
+            RegionSelectError::InvalidState(error) => write!(f, "{}", error),
+            RegionSelectError::OutsideDisplay(region) => write!(f, "選択した領域がモニターの範囲外です: {:?}", region),
+            RegionSelectError::Internal(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<MonitorCommandError> for RegionSelectError {
+    fn from(error: MonitorCommandError) -> Self {
+        match error {
+            MonitorCommandError::InvalidState(error) => RegionSelectError::InvalidState(error),
+            error @ MonitorCommandError::OcrInit { .. } => RegionSelectError::Internal(error.to_string()),
+            MonitorCommandError::Failed(message) => RegionSelectError::Internal(message),
+        }
+    }
+}
+
+// フロントエンドには { kind, message } の形式で返し、状態のエラーには current と required を付ける
+impl serde::Serialize for RegionSelectError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("RegionSelectError", 4)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        if let RegionSelectError::InvalidState(error) = self {
+            state.serialize_field("current", &error.current)?;
+            state.serialize_field("required", &error.required)?;
+        }
+        state.end()
+    }
+}
+
+/// 選択画面の読み込み完了（region-selector-ready）を待つ期限
+const SELECTOR_READY_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// 領域選択の期限
+const SELECTOR_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 選択した領域をモニターの範囲に収めた際に警告する、切り詰めた幅・高さ（ピクセル）
+const REGION_CLAMP_WARN_PIXELS: u32 = 2;
+
+/// キーボード操作中の領域のプレビューの大きさ
+const SELECTOR_PREVIEW_SIZE: (u32, u32) = (160, 120);
+
+/// 選択中の領域のプレビュー（region-preview-image としてメインウィンドウに送る）
+#[derive(Debug, Clone, serde::Serialize)]
+struct RegionPreview {
+    region: CaptureRegion,
+    /// 領域のサムネイル（PNGのBase64）
+    thumbnail: String,
+}
+
+/// ルーペで拡大する範囲の一辺（ピクセル、カーソルが中央になるよう奇数にする）
+const LOUPE_SIZE: u32 = 11;
+
+/// ルーペの拡大率
+const LOUPE_SCALE: u32 = 8;
+
+/// 選択画面のルーペの要求（region-loupe-request、選択画面のCSSピクセルの座標）
+#[derive(Debug, Clone, Copy, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LoupeRequest {
+    x: i32,
+    y: i32,
+    /// 選択画面の幅と高さ（CSSピクセル、無ければ画面の画素と同じ大きさとみなす）
+    #[serde(default)]
+    viewport_width: Option<u32>,
+    #[serde(default)]
+    viewport_height: Option<u32>,
+}
+
+impl LoupeRequest {
+    /// 選択画面の座標を、キャプチャした画面の画素の座標に変換
+    ///
+    /// 高DPIの画面では選択画面のCSSピクセルがキャプチャの複数の画素にあたるため、幅と高さの比で拡大する。
+    fn snapshot_position(&self, snapshot_width: u32, snapshot_height: u32) -> (i32, i32) {
+        let scale = |position: i32, viewport: Option<u32>, snapshot: u32| match viewport.filter(|size| *size > 0) {
+            Some(viewport) => (f64::from(position) * f64::from(snapshot) / f64::from(viewport)).floor() as i32,
+            None => position,
+        };
+        (
+            scale(self.x, self.viewport_width, snapshot_width),
+            scale(self.y, self.viewport_height, snapshot_height),
+        )
+    }
+}
+
+/// カーソルの周りを拡大した画像（region-loupe として選択画面に送り返す）
+#[derive(Debug, Clone, serde::Serialize)]
+struct RegionLoupe {
+    x: i32,
+    y: i32,
+    /// 拡大した画像（PNGのBase64、画面の外は黒）
+    image: String,
 }
 
-/// テキスト変化イベント
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(tag = "type")]
-enum TextChangeEvent {
-    /// 新しいテキストが検出された
-    #[serde(rename = "new")]
-    NewText { text: String },
-    /// テキストが変更された
-    #[serde(rename = "changed")]
-    TextChanged { old: String, new: String },
-    /// テキストがクリアされた
-    #[serde(rename = "cleared")]
-    TextCleared { text: String },
-    /// 差分テキストが検出された
-    #[serde(rename = "diff")]
-    DiffDetected { added: Vec<String>, removed: Vec<String> },
-    /// 情報メッセージ
-    #[serde(rename = "info")]
-    Info { message: String },
+/// 接続中のモニターの一覧を取得するコマンド（保存した領域のモニターとの対応付け用）
+#[tauri::command]
+fn list_screens() -> Result<Vec<ScreenListing>, String> {
+    ScreenListing::all().map_err(|e| e.to_string())
 }
 
 /// 領域選択のコマンド
 #[tauri::command]
-async fn select_region(state: State<'_, Mutex<AppState>>, app_handle: tauri::AppHandle) -> Result<CaptureRegion, String> {
-    // 現在監視中の場合は停止
-    {
-        let mut app_state = state.lock().map_err(|e| format!("状態ロックエラー: {}", e))?;
-        if app_state.is_monitoring {
-            // 停止シグナルを送信
-            app_state.stop_monitoring.store(true, Ordering::Relaxed);
-            app_state.is_monitoring = false;
-            info!("領域選択のために監視を停止しました");
-        }
+async fn select_region(state: State<'_, Mutex<AppState>>, app_handle: tauri::AppHandle) -> Result<CaptureRegion, RegionSelectError> {
+    // 現在監視中の場合は停止し、オーバーレイが写り込まないようスレッドの終了を待つ
+    let phase = lock(&state).phase;
+    if phase == MonitorPhase::Monitoring {
+        stop_and_join(&state, "select_region").await?;
+        info!("領域選択のために監視を停止しました");
     }
-    
+
+    // 選択中は他の領域選択や監視の開始を受け付けない（オーバーレイ表示中はロックを保持しない）
+    lock(&state)
+        .phase
+        .transition("select_region", &[MonitorPhase::Idle], MonitorPhase::Selecting)
+        .map_err(RegionSelectError::InvalidState)?;
+
     // 領域選択用のオーバーレイウィンドウを作成
-    let region = match create_region_selector(app_handle).await {
-        Ok(region) => region,
-        Err(e) => return Err(format!("領域選択エラー: {}", e)),
-    };
-    
-    let mut app_state = state.lock().map_err(|e| format!("状態ロックエラー: {}", e))?;
+    let result = create_region_selector(app_handle).await;
+
+    let mut app_state = lock(&state);
+    app_state.phase = MonitorPhase::Idle;
+    let region = result.map_err(|e| {
+        log::warn!("領域選択エラー: {}", e);
+        e
+    })?;
+    // 選択画面でも補正済みだが、設定と一致するようここでも補正する
+    let mut region = app_state.selector_config.snapper().snap(region);
+    // 解像度・拡大率の変更を検出できるよう、選択時のモニターの情報を領域と一緒に保存する
+    let display = DisplayGeometry::for_region(&region)
+        .map_err(|e| log::warn!("モニターの情報を取得できません: {}", e))
+        .ok();
+    // 全画面のアプリの上で選択するとCSSピクセルの丸めでモニターの外に1〜2ピクセルはみ出すことがあるため、範囲に収める
+    if let Some(display) = &display {
+        let clamped = display.clamp_region(&region).ok_or(RegionSelectError::OutsideDisplay(region))?;
+        let trimmed = region.width.abs_diff(clamped.width).max(region.height.abs_diff(clamped.height));
+        if trimmed > REGION_CLAMP_WARN_PIXELS {
+            log::warn!("選択した領域がモニターの範囲をはみ出していたため切り詰めました: {:?} -> {:?}", region, clamped);
+        }
+        region = clamped;
+    }
+    region.display = display;
     app_state.selected_region = Some(region);
     
     info!("領域が選択されました: {:?}", region);
@@ -80,22 +482,74 @@ async fn select_region(state: State<'_, Mutex<AppState>>, app_handle: tauri::App
 }
 
 /// 領域選択用のオーバーレイウィンドウを作成
-async fn create_region_selector(app_handle: tauri::AppHandle) -> Result<CaptureRegion> {
+///
+/// 選択画面のスクリプトが region-selector-ready を送信しないまま期限を過ぎた場合は
+/// ウィンドウを閉じて SelectorFailedToLoad を返す（空の最前面ウィンドウで画面が
+/// 塞がれたままにならないようにするため）。
+async fn create_region_selector(app_handle: tauri::AppHandle) -> Result<CaptureRegion, RegionSelectError> {
     use tauri::WindowBuilder;
     use tokio::sync::oneshot;
     
     // 結果を受け取るためのチャンネルを作成
     let (tx, rx) = oneshot::channel::<Option<CaptureRegion>>();
     let tx = Arc::new(Mutex::new(Some(tx)));
+    let (ready_tx, ready_rx) = oneshot::channel::<()>();
+    let ready_tx = Mutex::new(Some(ready_tx));
     
     // スクリーンのサイズを取得
     let screens = screenshots::Screen::all()
-        .map_err(|e| anyhow::anyhow!("スクリーン情報の取得に失敗: {}", e))?;
+        .map_err(|e| RegionSelectError::Internal(format!("スクリーン情報の取得に失敗: {}", e)))?;
     let primary_screen = screens.first()
-        .ok_or_else(|| anyhow::anyhow!("プライマリスクリーンが見つかりません"))?;
+        .ok_or_else(|| RegionSelectError::Internal("プライマリスクリーンが見つかりません".to_string()))?;
+    
+    // オーバーレイが写り込まないよう、プレビュー用の画面はウィンドウ作成前にキャプチャしておく
+    // （拒否リストのプロセスのウィンドウが画面にあればプレビューなしで選択する）
+    let screen = DisplayGeometry::from_screen(primary_screen);
+    let screen_region = CaptureRegion {
+        x: screen.x,
+        y: screen.y,
+        width: screen.width,
+        height: screen.height,
+        display: Some(screen),
+    };
+    let process_guard_config = lock(&app_handle.state::<Mutex<AppState>>()).process_guard_config.clone();
+    let snapshot = ensure_allowed(&process_guard_config, &screen_region)
+        .and_then(|()| ScreenCapture::capture_full_screen())
+        .map_err(|e| log::warn!("領域のプレビュー用の画面をキャプチャできません: {}", e))
+        .ok()
+        .map(Arc::new);
+    
+    // 選択画面は表示したモニターの識別子を選択結果と一緒に送り返す
+    let screen_id = screen.stable_id();
+    // 読み込み完了の通知より前に押されたEscapeは、選択画面のスクリプトが通知の後に送るよう記録しておく
+    let init_script = format!(
+        "window.__REGION_SELECTOR__ = {{ screenId: {}, ready: false, pendingCancel: false }};\n\
+         window.addEventListener('keydown', function (e) {{\n\
+             if (e.key === 'Escape' && !window.__REGION_SELECTOR__.ready) window.__REGION_SELECTOR__.pendingCancel = true;\n\
+         }}, true);",
+        serde_json::to_string(&screen_id).unwrap_or_else(|_| "null".to_string())
+    );
+    
+    // 読み込み完了の通知はウィンドウ作成前から待ち受ける
+    let ready_handler = app_handle.listen_global("region-selector-ready", move |_| {
+        if let Some(ready_tx) = ready_tx.lock().ok().and_then(|mut sender| sender.take()) {
+            let _ = ready_tx.send(());
+        }
+    });
+    
+    // キャンセルもウィンドウ作成前から待ち受ける（読み込み完了の直後に送られる場合があるため）
+    let tx_cancelled = tx.clone();
+    let cancelled_handler = app_handle.listen_global("region-cancelled", move |_| {
+        info!("領域選択がキャンセルされました");
+        if let Ok(mut sender) = tx_cancelled.lock() {
+            if let Some(tx) = sender.take() {
+                let _ = tx.send(None);
+            }
+        }
+    });
     
     // オーバーレイウィンドウを作成（半透明とフルスクリーン）
-    let overlay_window = WindowBuilder::new(
+    let overlay_window = match WindowBuilder::new(
         &app_handle,
         "region_selector",
         tauri::WindowUrl::App("region_selector.html".into())
@@ -107,8 +561,16 @@ async fn create_region_selector(app_handle: tauri::AppHandle) -> Result<CaptureR
     .always_on_top(true)
     .skip_taskbar(true) // タスクバーに表示しない
     .transparent(true) // ウィンドウを透明にする
+    .initialization_script(&init_script)
     .build()
-    .map_err(|e| anyhow::anyhow!("オーバーレイウィンドウの作成に失敗: {}", e))?;
+    {
+        Ok(window) => window,
+        Err(e) => {
+            app_handle.unlisten(ready_handler);
+            app_handle.unlisten(cancelled_handler);
+            return Err(RegionSelectError::Internal(format!("オーバーレイウィンドウの作成に失敗: {}", e)));
+        }
+    };
     
     // 透明化を試行
     #[cfg(target_os = "macos")]
@@ -124,264 +586,2199 @@ async fn create_region_selector(app_handle: tauri::AppHandle) -> Result<CaptureR
     
     // イベントリスナーを設定
     let tx_selected = tx.clone();
-    let tx_cancelled = tx.clone();
     
-    // 領域選択イベントのリスナー
-    app_handle.listen_global("region-selected", move |event| {
-        if let Some(payload) = event.payload() {
-            info!("領域選択イベントを受信: payload={}", payload);
-            
-            // 複数の形式でパースを試行
-            let region_result = serde_json::from_str::<CaptureRegion>(payload)
-                .or_else(|_| {
-                    // 二重エンコードされている場合の処理
-                    info!("直接パースに失敗、二重エンコードを試行");
-                    serde_json::from_str::<String>(payload)
-                        .and_then(|s| {
-                            info!("二重エンコードされた文字列: {}", s);
-                            serde_json::from_str::<CaptureRegion>(&s)
-                        })
-                })
-                .or_else(|_| {
-                    // 型チェック付きでパース
-                    info!("型チェック付きでパース試行");
-                    serde_json::from_str::<serde_json::Value>(payload)
-                        .and_then(|v| {
-                            info!("パースした値: {:?}", v);
-                            serde_json::from_value::<CaptureRegion>(v)
-                        })
-                });
-            
-            match region_result {
-                Ok(region) => {
-                    info!("領域のパースに成功: {:?}", region);
-                    if let Ok(mut sender) = tx_selected.lock() {
-                        if let Some(tx) = sender.take() {
-                            let _ = tx.send(Some(region));
-                        }
+    // 領域選択イベントのリスナー（解析できない場合は選択画面にエラーを送り返し、送り直しを待つ）
+    let error_window = overlay_window.clone();
+    let selected_handler = app_handle.listen_global("region-selected", move |event| {
+        let Some(payload) = event.payload() else {
+            return;
+        };
+        info!("領域選択イベントを受信: payload={}", payload);
+        match region_payload::parse_region_selected(payload) {
+            Ok((selected, legacy)) => {
+                if let Some(legacy) = legacy {
+                    log::warn!(
+                        "以前の形式（{:?}）の領域選択イベントを受信しました。この形式は今後のバージョンで受け付けなくなります",
+                        legacy
+                    );
+                }
+                if let Some(id) = selected.screen_id.as_deref().filter(|id| *id != screen_id) {
+                    log::warn!("選択画面のモニター（{}）が表示したモニター（{}）と異なります", id, screen_id);
+                }
+                info!("領域のパースに成功: {:?}", selected.region);
+                if let Ok(mut sender) = tx_selected.lock() {
+                    if let Some(tx) = sender.take() {
+                        let _ = tx.send(Some(selected.region));
                     }
                 }
-                Err(e) => {
-                    log::error!("すべてのパース方法に失敗: {}, payload={}", e, payload);
+            }
+            Err(e) => {
+                log::error!("領域選択イベントを解析できません: {}, payload={}", e, payload);
+                if let Err(emit_error) = error_window.emit(REGION_SELECTED_ERROR_EVENT, e) {
+                    log::warn!("解析のエラーを選択画面に送信できません: {}", emit_error);
                 }
             }
         }
     });
     
-    // キャンセルイベントのリスナー
-    app_handle.listen_global("region-cancelled", move |_| {
-        if let Ok(mut sender) = tx_cancelled.lock() {
-            if let Some(tx) = sender.take() {
-                let _ = tx.send(None);
-            }
+    // ルーペの要求にはカーソルの周りを拡大した画像を選択画面に送り返す
+    let loupe_snapshot = snapshot.clone();
+    let loupe_window = overlay_window.clone();
+    let loupe_handler = app_handle.listen_global("region-loupe-request", move |event| {
+        let Some(snapshot) = loupe_snapshot.as_deref() else {
+            return;
+        };
+        let Some(request) = event.payload().and_then(|payload| serde_json::from_str::<LoupeRequest>(payload).ok()) else {
+            return;
+        };
+        let Some(loupe) = region_loupe(snapshot, request) else {
+            return;
+        };
+        if let Err(e) = loupe_window.emit("region-loupe", loupe) {
+            log::warn!("ルーペの画像の送信に失敗: {}", e);
         }
     });
     
-    // 結果を待機（タイムアウト30秒）
-    let result = tokio::time::timeout(Duration::from_secs(30), rx)
-        .await
-        .map_err(|_| anyhow::anyhow!("領域選択がタイムアウトしました"))?
-        .map_err(|_| anyhow::anyhow!("領域選択の受信エラー"))?;
+    // 選択中の領域のプレビューをメインウィンドウに送る（ドラッグ中・キーボードでの調整中）
+    let preview_app = app_handle.clone();
+    let preview_handler = app_handle.listen_global("region-preview", move |event| {
+        let Some(snapshot) = snapshot.as_deref() else {
+            return;
+        };
+        let Some(region) = event.payload().and_then(|payload| serde_json::from_str::<CaptureRegion>(payload).ok()) else {
+            return;
+        };
+        let Some(preview) = region_preview(snapshot, region) else {
+            return;
+        };
+        if let Err(e) = preview_app.emit_all("region-preview-image", preview) {
+            log::warn!("領域のプレビューの送信に失敗: {}", e);
+        }
+    });
+    
+    // 読み込み完了を待ってから選択結果を待機
+    let result = match tokio::time::timeout(SELECTOR_READY_TIMEOUT, ready_rx).await {
+        Ok(Ok(())) => {
+            info!("領域選択画面の読み込みが完了しました");
+            match tokio::time::timeout(SELECTOR_TIMEOUT, rx).await {
+                Ok(Ok(Some(region))) => Ok(region),
+                Ok(Ok(None)) => Err(RegionSelectError::Cancelled),
+                Ok(Err(_)) => Err(RegionSelectError::Internal("領域選択の受信エラー".to_string())),
+                Err(_) => Err(RegionSelectError::Timeout),
+            }
+        }
+        _ => {
+            log::error!("領域選択画面が{}秒以内に読み込まれませんでした", SELECTOR_READY_TIMEOUT.as_secs());
+            Err(RegionSelectError::SelectorFailedToLoad)
+        }
+    };
     
-    // オーバーレイウィンドウを閉じる
+    // オーバーレイウィンドウを閉じ、リスナーを解除
     let _ = overlay_window.close();
+    app_handle.unlisten(ready_handler);
+    app_handle.unlisten(selected_handler);
+    app_handle.unlisten(preview_handler);
+    app_handle.unlisten(loupe_handler);
+    app_handle.unlisten(cancelled_handler);
+    
+    if let Ok(region) = &result {
+        info!("領域選択が完了しました: {:?}", region);
+    }
+    result
+}
+
+/// 選択画面を開いた時点の画面から領域のプレビューを作成（画面の外側だけの領域はNone）
+fn region_preview(snapshot: &DynamicImage, region: CaptureRegion) -> Option<RegionPreview> {
+    use image::GenericImageView;
     
-    match result {
-        Some(region) => {
-            info!("領域選択が完了しました: {:?}", region);
-            Ok(region)
+    let (width, height) = snapshot.dimensions();
+    let screen = CaptureRegion { x: 0, y: 0, width, height, display: None };
+    let visible = region.intersect(&screen)?;
+    let image = snapshot.crop_imm(visible.x as u32, visible.y as u32, visible.width, visible.height);
+    let thumbnail = image.thumbnail(SELECTOR_PREVIEW_SIZE.0, SELECTOR_PREVIEW_SIZE.1);
+    match encode_png_base64(&thumbnail) {
+        Ok(thumbnail) => Some(RegionPreview { region, thumbnail }),
+        Err(e) => {
+            log::warn!("領域のプレビューの作成に失敗: {}", e);
+            None
+        }
+    }
+}
+
+/// 選択画面の作成前にキャプチャした画面から、カーソルの周りを切り出して拡大する
+///
+/// 選択中にキャプチャするとオーバーレイが写り込むため、キャプチャ済みの画面を使う。
+/// 返す位置は要求と同じ選択画面の座標とする。
+fn region_loupe(snapshot: &DynamicImage, request: LoupeRequest) -> Option<RegionLoupe> {
+    use image::{imageops, GenericImageView, Rgba, RgbaImage};
+    
+    let (width, height) = snapshot.dimensions();
+    let (center_x, center_y) = request.snapshot_position(width, height);
+    let half = (LOUPE_SIZE / 2) as i32;
+    let mut area = RgbaImage::from_pixel(LOUPE_SIZE, LOUPE_SIZE, Rgba([0, 0, 0, 255]));
+    for (dx, dy, pixel) in area.enumerate_pixels_mut() {
+        let x = center_x - half + dx as i32;
+        let y = center_y - half + dy as i32;
+        if (0..width as i32).contains(&x) && (0..height as i32).contains(&y) {
+            *pixel = snapshot.get_pixel(x as u32, y as u32);
+        }
+    }
+    // 画素の境界が分かるよう補間せずに拡大する
+    let size = LOUPE_SIZE * LOUPE_SCALE;
+    let magnified = imageops::resize(&area, size, size, imageops::FilterType::Nearest);
+    match encode_png_base64(&DynamicImage::ImageRgba8(magnified)) {
+        Ok(image) => Some(RegionLoupe {
+            x: request.x,
+            y: request.y,
+            image,
+        }),
+        Err(e) => {
+            log::warn!("ルーペの画像の作成に失敗: {}", e);
+            None
         }
-        None => Err(anyhow::anyhow!("領域選択がキャンセルされました"))
     }
 }
 
 /// 監視開始のコマンド
 #[tauri::command]
 fn start_monitoring(
-    region: CaptureRegion,
+    mut region: CaptureRegion,
+    fast_mode: Option<FastModeConfig>,
     state: State<Mutex<AppState>>,
     window: Window,
-) -> Result<(), String> {
+) -> Result<(), MonitorCommandError> {
     info!("監視開始コマンドが呼ばれました: region={:?}", region);
+    let app_handle = window.app_handle();
     
-    let mut app_state = state.lock().map_err(|e| format!("状態ロックエラー: {}", e))?;
-    
-    if app_state.is_monitoring {
-        return Err("既に監視が実行中です".to_string());
-    }
-    
-    // 受け取った領域を保存
-    app_state.selected_region = Some(region);
-    
-    // 停止シグナルをリセット
-    app_state.stop_monitoring.store(false, Ordering::Relaxed);
-    let stop_signal = app_state.stop_monitoring.clone();
-    
-    // 監視スレッドを起動
-    let handle = thread::spawn(move || {
-        info!("画面監視スレッドを開始しました: region={:?}", region);
+    // 状態の確認と更新だけをロック内で行い、スレッド起動中はロックを保持しない
+    let mut session = {
+        let mut app_state = lock(&state);
+        
+        // 監視中・領域選択中などは開始しない（スレッドの二重起動を防ぐ）
+        app_state.phase.transition("start_monitoring", &[MonitorPhase::Idle], MonitorPhase::Starting)?;
+        
+        // どの経路で設定された値でも、範囲外なら監視を開始しない
+        if let Err(e) = app_state.validate_session_config(fast_mode.as_ref()) {
+            app_state.phase = MonitorPhase::Idle;
+            return Err(e.into());
+        }
+        app_state.fast_mode = fast_mode.clone();
         
-        // OCRエンジンの初期化
-        let ocr_engine = match OcrEngine::new() {
-            Ok(engine) => engine,
+        // 受け取った領域を確認して保存（モニターの情報が無い古い領域は現在のモニターを選択時の値とする）
+        region = match capture::prepare_session_region(region) {
+            Ok(region) => region,
             Err(e) => {
-                let _ = window.emit("error", format!("OCR初期化エラー: {}", e));
-                return;
+                app_state.phase = MonitorPhase::Idle;
+                return Err(MonitorCommandError::Failed(format!("{:#}", e)));
             }
         };
+        app_state.selected_region = Some(region);
         
-        // 画面キャプチャの初期化（渡された領域を使用）
-        let capture = ScreenCapture::new(region);
-        let mut last_text: Option<String> = None;
-        
-        loop {
-            // 停止シグナルをチェック
-            if stop_signal.load(Ordering::Relaxed) {
-                info!("監視停止シグナルを受信しました");
-                break;
-            }
-            
-            // 500ms間隔で監視
-            thread::sleep(Duration::from_millis(500));
-            
-            // 再度停止シグナルをチェック
-            if stop_signal.load(Ordering::Relaxed) {
-                info!("監視停止シグナルを受信しました");
-                break;
-            }
-            
-            // 画面をキャプチャ
-            let image = match capture.capture() {
-                Ok(img) => img,
-                Err(e) => {
-                    log::error!("キャプチャエラー: {}", e);
-                    let _ = window.emit("error", format!("キャプチャエラー: {}", e));
-                    continue;
-                }
-            };
-            
-            // OCRでテキスト認識
-            let current_text = match ocr_engine.recognize_text(&image) {
-                Ok(text) => text,
-                Err(e) => {
-                    log::error!("OCRエラー: {}", e);
-                    let _ = window.emit("error", format!("OCRエラー: {}", e));
-                    continue;
-                }
-            };
-            
-            // 前回のテキストと比較
-            match &last_text {
-                None => {
-                    // 初回認識
-                    if !current_text.is_empty() {
-                        info!("新しいテキストを検出: {}", current_text);
-                        let event = TextChangeEvent::NewText { text: current_text.clone() };
-                        let _ = window.emit("text-changed", event);
-                        last_text = Some(current_text);
-                    }
-                }
-                Some(prev_text) => {
-                    if prev_text != &current_text {
-                        if current_text.is_empty() {
-                            // テキストがクリアされた
-                            info!("テキストがクリアされました");
-                            let event = TextChangeEvent::TextCleared { text: prev_text.clone() };
-                            let _ = window.emit("text-changed", event);
-                            last_text = None;
-                        } else {
-                            // テキストが変更された
-                            info!("テキストが変更されました: {} -> {}", prev_text, current_text);
-                            
-                            // 差分を検出
-                            let (added, removed) = detect_text_diff(prev_text, &current_text);
-                            
-                            // 差分がある場合は差分イベントも送信
-                            if !added.is_empty() || !removed.is_empty() {
-                                info!("差分検出 - 追加: {:?}, 削除: {:?}", added, removed);
-                                let diff_event = TextChangeEvent::DiffDetected {
-                                    added: added.clone(),
-                                    removed: removed.clone(),
-                                };
-                                let _ = window.emit("text-changed", diff_event);
-                            }
-                            
-                            // 通常の変更イベントも送信
-                            let event = TextChangeEvent::TextChanged {
-                                old: prev_text.clone(),
-                                new: current_text.clone(),
-                            };
-                            let _ = window.emit("text-changed", event);
-                            last_text = Some(current_text);
-                        }
-                    }
-                }
+        // 設定されていれば監視に合わせてテキスト配信サーバーも起動（失敗しても監視は開始する）
+        #[cfg(feature = "rest")]
+        if app_state.text_server_config.enabled && app_state.text_server.is_none() {
+            match text_server::TextServer::start(&app_state.text_server_config, window.app_handle(), true) {
+                Ok(server) => app_state.text_server = Some(server),
+                Err(e) => log::warn!("テキスト配信サーバーを起動できません: {:#}", e),
             }
         }
         
-        info!("画面監視スレッドを終了しました");
+        // セッションごとに新しい停止シグナルを使う
+        app_state.stop_monitoring = Arc::new(AtomicBool::new(false));
+        app_state.session_id += 1;
+        // セッション中の時刻はシステム時刻の変更に影響されないよう、開始時の時刻からの経過時間で決める
+        let session_clock = SessionClock::start();
+        // 領域が変わると行の対応が無意味になるため安定度をリセット
+        lock(&app_state.line_stability).clear();
+        lock(&app_state.text_frequency).clear();
+        let emitter = app_state.session_emitter(window, session_clock);
+        MonitorSession::from_state(&mut app_state, region, fast_mode, emitter)
+    };
+    
+    // 言語データをダウンロードしない場合はOCRエンジンをここで作成し、言語データが無いなどの設定の誤りはコマンドの結果として返す
+    let session_id = session.session_id();
+    if let Err(e) = session.prepare_engine() {
+        let (message, remediation) = ocr_init_failure(&e, session.has_app_tessdata());
+        log::error!("OCRエンジンを初期化できないため監視を開始しません: {}", message);
+        release_session(&mut lock(&state));
+        return Err(MonitorCommandError::OcrInit { message, remediation });
+    }
+    
+    // 監視スレッドを起動
+    let handle = thread::spawn(move || {
+        MonitorWorker::run(session);
+        // 停止の要求以外で終了した場合も、次の監視を開始できるようIdleに戻す
+        release_worker_session(&app_handle, session_id);
     });
     
-    app_state.monitor_handle = Some(handle);
-    app_state.is_monitoring = true;
+    // 監視スレッドが初期化に失敗して既に片付けていれば、開始しなかったことにする
+    let mut app_state = lock(&state);
+    if app_state.phase == MonitorPhase::Starting && app_state.session_id == session_id {
+        app_state.monitor_handle = Some(handle);
+        app_state.phase = MonitorPhase::Monitoring;
+    }
     
     Ok(())
 }
 
-/// 監視停止のコマンド
+/// 開始できなかった、または開始直後に続けられなくなった監視セッションの状態を片付ける（Idleに戻す）
+fn release_session(app_state: &mut AppState) {
+    app_state.phase = MonitorPhase::Idle;
+    app_state.monitor_handle = None;
+    app_state.ocr_reload = None;
+    app_state.reference_updates = None;
+    // 監視に合わせて起動したテキスト配信サーバーは一緒に停止（コマンドで起動したものは残す）
+    #[cfg(feature = "rest")]
+    if app_state.text_server.as_ref().is_some_and(|server| server.with_monitoring) {
+        app_state.text_server = None;
+    }
+}
+
+/// 監視スレッドが終了したセッションの状態を片付ける（続けられなくなった場合と、停止ファイルや領域の無効化で
+/// 自ら終了した場合に使う。停止の要求で終了した場合と、すでに次のセッションが始まっていれば何もしない）
+fn release_worker_session(app_handle: &tauri::AppHandle, session_id: u64) {
+    let state = app_handle.state::<Mutex<AppState>>();
+    let mut app_state = lock(&state);
+    if app_state.session_id == session_id && matches!(app_state.phase, MonitorPhase::Starting | MonitorPhase::Monitoring) {
+        release_session(&mut app_state);
+    }
+}
+
+/// 終了シグナルを受け取ったら監視スレッドの終了を待ち、書き出しと配信を閉じてからアプリを終了する
+///
+/// 監視スレッドは停止ファイルと同じ手順で終了する（保留していたイベントの送信と要約の保存を行う）。
+fn exit_on_termination(app_handle: tauri::AppHandle) {
+    thread::spawn(move || {
+        while !shutdown::termination_requested() {
+            thread::sleep(worker::STOP_POLL_INTERVAL);
+        }
+        info!("終了シグナルを受け取りました。監視の終了を待ってから終了します");
+        let state = app_handle.state::<Mutex<AppState>>();
+        let (handle, event_pipe, event_sink) = loop {
+            let mut app_state = lock(&state);
+            // 開始中・停止中のセッションは、監視スレッドの参照の保存と停止コマンドの終了待ちが済むまで待つ
+            if !matches!(app_state.phase, MonitorPhase::Starting | MonitorPhase::Stopping) {
+                break (app_state.monitor_handle.take(), app_state.event_pipe.clone(), app_state.event_sink.clone());
+            }
+            drop(app_state);
+            thread::sleep(worker::STOP_POLL_INTERVAL);
+        };
+        if let Some(handle) = handle {
+            if handle.join().is_err() {
+                log::warn!("監視スレッドが異常終了しました");
+            }
+        }
+        // キューに残ったイベントを書き終えてから閉じる
+        drop(lock(&event_pipe).take());
+        drop(lock(&event_sink).take());
+        app_handle.exit(0);
+    });
+}
+
+/// OCRエンジン再読み込みの応答を待つ時間
+const OCR_RELOAD_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// OCRエンジンの再読み込みコマンド
+///
+/// 監視中は監視スレッドがキャプチャの合間にエンジンを作り直す（前回のテキストは保持する）。
+/// 監視中でなければ言語の指定のみを保存し、次の監視開始時に読み込む。
 #[tauri::command]
-fn stop_monitoring(state: State<Mutex<AppState>>) -> Result<(), String> {
-    let mut app_state = state.lock().map_err(|e| format!("状態ロックエラー: {}", e))?;
-    
-    if !app_state.is_monitoring {
-        return Err("監視が実行されていません".to_string());
+async fn reload_ocr_engine(language: Option<String>, state: State<'_, Mutex<AppState>>) -> Result<(), String> {
+    info!("OCRエンジンの再読み込みコマンドが呼ばれました: language={:?}", language);
+    let (language, sender) = {
+        let app_state = lock(&state);
+        let language = language
+            .or_else(|| app_state.ocr_language.clone())
+            .unwrap_or_else(|| tessdata::DEFAULT_LANGUAGE.to_string());
+        let sender = match app_state.phase {
+            MonitorPhase::Monitoring => app_state.ocr_reload.clone(),
+            _ => None,
+        };
+        (language, sender)
+    };
+
+    if let Some(sender) = sender {
+        let (reply, result) = std_mpsc::channel();
+        sender
+            .send(OcrReloadRequest {
+                language: language.clone(),
+                reply,
+            })
+            .map_err(|_| "監視スレッドが終了しています".to_string())?;
+        tauri::async_runtime::spawn_blocking(move || result.recv_timeout(OCR_RELOAD_TIMEOUT))
+            .await
+            .map_err(|e| format!("再読み込みの待機に失敗: {}", e))?
+            .map_err(|_| "OCRエンジンの再読み込みの応答がありません".to_string())??;
     }
-    
-    // 停止シグナルを送信
-    app_state.stop_monitoring.store(true, Ordering::Relaxed);
-    app_state.is_monitoring = false;
-    
-    info!("監視停止を要求しました");
+
+    lock(&state).ocr_language = Some(language);
     Ok(())
 }
 
-/// テキストの差分を検出する関数
-fn detect_text_diff(old_text: &str, new_text: &str) -> (Vec<String>, Vec<String>) {
-    let old_lines: Vec<&str> = old_text.lines().collect();
-    let new_lines: Vec<&str> = new_text.lines().collect();
-    
-    let mut added = Vec::new();
-    let mut removed = Vec::new();
-    
-    // 新しく追加された行を検出
-    for new_line in &new_lines {
-        if !old_lines.contains(new_line) && !new_line.trim().is_empty() {
-            added.push(new_line.to_string());
+/// 文字種の確認で提案された認識言語を反映するコマンド（監視中なら監視中のエンジンを再読み込み）
+#[tauri::command]
+async fn apply_suggested_language(state: State<'_, Mutex<AppState>>) -> Result<String, String> {
+    let suggestion = lock(&state).language_suggestion.clone();
+    let language = lock(&suggestion)
+        .clone()
+        .ok_or_else(|| "提案されている言語はありません".to_string())?;
+    reload_ocr_engine(Some(language.clone()), state).await?;
+    *lock(&suggestion) = None;
+    info!("提案された認識言語を反映しました: {}", language);
+    Ok(language)
+}
+
+/// 比較の基準とするテキストの設定コマンド
+///
+/// 監視中は次の認識結果をこのテキストと比較し、監視中でなければ次の監視開始時に使う。
+#[tauri::command]
+fn set_reference_text(text: String, state: State<Mutex<AppState>>) -> Result<(), String> {
+    state_commands::set_reference_text(text, &state)
+}
+
+/// 比較の基準とするテキストの解除コマンド
+#[tauri::command]
+fn clear_reference_text(state: State<Mutex<AppState>>) {
+    state_commands::clear_reference_text(&state);
+}
+
+/// 監視セッションで使うOCRエンジンを作成（validate_monitoringの確認でも同じ手順で作成する）
+fn create_session_engine(
+    tessdata_dir: Option<&Path>,
+    language: &str,
+    ocr_baseline: Option<f32>,
+    ocr_config: &OcrConfig,
+    retain_preprocessed: bool,
+) -> anyhow::Result<OcrEngine> {
+    ocr::check_temp_dir()?;
+    let datapath = tessdata::resolve_datapath(tessdata_dir, language);
+    let mut engine = OcrEngine::with_backend(datapath, language, ocr_config.backend)?;
+    // 計測済みのベースラインがあれば信頼度の正規化に使用
+    engine.set_calibrated_baseline(ocr_baseline);
+    engine.set_config(ocr_config.clone());
+    engine.set_retain_preprocessed(retain_preprocessed);
+    engine.set_text_coverage_interval(ocr::MONITOR_TEXT_COVERAGE_INTERVAL);
+    // 監視を始める前に、言語データが正しく動作しているかを同梱の画像で確かめる
+    engine.validate_language_pack()?;
+    Ok(engine)
+}
+
+/// OCRエンジンの初期化の試行回数（一時ファイルの書き込みなど回復しうる失敗の場合のみやり直す）
+const ENGINE_INIT_ATTEMPTS: u32 = 3;
+
+/// 初期化をやり直すまでの待ち時間（やり直すたびに倍にする）
+const ENGINE_INIT_BACKOFF: Duration = Duration::from_millis(200);
+
+/// 監視セッションで使うOCRエンジンを作成し、回復しうる失敗は間隔を空けてやり直す
+fn create_session_engine_with_retry(
+    tessdata_dir: Option<&Path>,
+    language: &str,
+    ocr_baseline: Option<f32>,
+    ocr_config: &OcrConfig,
+    retain_preprocessed: bool,
+) -> anyhow::Result<OcrEngine> {
+    let mut backoff = ENGINE_INIT_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        match create_session_engine(tessdata_dir, language, ocr_baseline, ocr_config, retain_preprocessed) {
+            Err(e)
+                if attempt < ENGINE_INIT_ATTEMPTS
+                    && e.downcast_ref::<OcrInitError>().is_some_and(OcrInitError::is_recoverable) =>
+            {
+                log::warn!(
+                    "OCRエンジンの初期化に失敗しました（{}ms後にやり直します、{}/{}回目）: {:#}",
+                    backoff.as_millis(),
+                    attempt,
+                    ENGINE_INIT_ATTEMPTS,
+                    e
+                );
+                thread::sleep(backoff);
+                backoff *= 2;
+                attempt += 1;
+            }
+            result => return result,
         }
     }
-    
-    // 削除された行を検出
-    for old_line in &old_lines {
-        if !new_lines.contains(old_line) && !old_line.trim().is_empty() {
-            removed.push(old_line.to_string());
+}
+
+/// OCRエンジンの初期化のエラーのメッセージと対処方法（種類を判別できないエラーはやり直しを促す）
+fn ocr_init_failure(error: &anyhow::Error, has_app_tessdata: bool) -> (String, RemediationCode) {
+    let remediation = error
+        .downcast_ref::<OcrInitError>()
+        .map_or(RemediationCode::Retry, |error| error.remediation(has_app_tessdata));
+    (format!("{:#}", error), remediation)
+}
+
+/// 言語データを進捗イベント付きでダウンロード
+fn download_with_progress(
+    emitter: &mut EventEmitter,
+    language: &str,
+    dir: &std::path::Path,
+) -> Result<DownloadReport, DownloadError> {
+    emitter.info(
+        "tessdata_download_started",
+        format!("言語データ（{}）をダウンロードしています", language),
+    );
+    let download_start = Instant::now();
+
+    // 進捗イベントは1%刻み（総サイズ不明時は512KB刻み）に間引いて送信
+    let mut last_reported = None;
+    let report = tessdata::download_language(language, dir, |downloaded_bytes, total_bytes| {
+        let step = (downloaded_bytes * 100)
+            .checked_div(total_bytes)
+            .unwrap_or(downloaded_bytes / (512 * 1024));
+        if last_reported == Some(step) {
+            return;
+        }
+        last_reported = Some(step);
+
+        let percent = if total_bytes > 0 {
+            downloaded_bytes as f32 / total_bytes as f32 * 100.0
+        } else {
+            0.0
+        };
+        emitter.emit_transient(TextChangeEvent::DownloadProgress {
+            language: language.to_string(),
+            downloaded_bytes,
+            total_bytes,
+            percent,
+        });
+    })?;
+
+    emitter.info(
+        "tessdata_download_finished",
+        format!(
+            "言語データ（{}）のダウンロードが完了しました（{}ms）",
+            language,
+            download_start.elapsed().as_millis()
+        ),
+    );
+    Ok(report)
+}
+
+/// 言語データのダウンロードコマンド
+///
+/// 保存先はアプリのデータディレクトリで、監視開始時に毎回参照されるため
+/// ダウンロード後は再起動せずに利用できる。
+#[tauri::command]
+async fn download_language_data(
+    language: String,
+    state: State<'_, Mutex<AppState>>,
+    window: Window,
+) -> Result<DownloadReport, DownloadError> {
+    info!("言語データのダウンロードコマンドが呼ばれました: language={}", language);
+
+    let (tessdata_dir, mut emitter) = {
+        let app_state = lock(&state);
+        (app_state.tessdata_dir.clone(), app_state.emitter(window))
+    };
+    let dir = tessdata_dir.ok_or_else(|| DownloadError::Io {
+        message: "アプリのデータディレクトリを取得できません".to_string(),
+    })?;
+
+    // ダウンロードはブロッキング処理のため専用スレッドで実行
+    tauri::async_runtime::spawn_blocking(move || {
+        download_with_progress(&mut emitter, &language, &dir)
+    })
+    .await
+    .map_err(|e| DownloadError::Io { message: e.to_string() })?
+}
+
+/// OCRの利用可否の確認コマンド
+#[tauri::command]
+fn check_ocr_available(language: Option<String>, state: State<Mutex<AppState>>) -> OcrAvailability {
+    let tessdata_dir = lock(&state).tessdata_dir.clone();
+    let language = language.unwrap_or_else(|| tessdata::DEFAULT_LANGUAGE.to_string());
+    tessdata::check_availability(tessdata_dir.as_deref(), &language)
+}
+
+/// 初回起動のウィザードで使う環境の情報の取得コマンド（モニター・OCRの利用可否・言語データ・使える機能）
+#[tauri::command]
+async fn wizard_environment(language: Option<String>, state: State<'_, Mutex<AppState>>) -> Result<WizardEnvironment, String> {
+    let (tessdata_dir, language) = {
+        let app_state = lock(&state);
+        (
+            app_state.tessdata_dir.clone(),
+            language
+                .or_else(|| app_state.ocr_language.clone())
+                .unwrap_or_else(|| tessdata::DEFAULT_LANGUAGE.to_string()),
+        )
+    };
+    // Tesseractの初期化を試すため、非同期のスレッドを塞がないようにする
+    tauri::async_runtime::spawn_blocking(move || WizardEnvironment::collect(tessdata_dir.as_deref(), &language))
+        .await
+        .map_err(|e| format!("環境の情報の取得に失敗: {}", e))
+}
+
+/// 初回起動のウィザードで領域を1回キャプチャ・認識して結果を確かめるコマンド（サムネイルと前処理の提案を含む）
+#[tauri::command]
+async fn wizard_test(
+    region: CaptureRegion,
+    language: Option<String>,
+    state: State<'_, Mutex<AppState>>,
+) -> Result<WizardTestResult, WizardError> {
+    info!("ウィザードの認識の確認コマンドが呼ばれました: region={:?}, language={:?}", region, language);
+    let (tessdata_dir, language, ocr_config, capture_config, process_guard_config) = {
+        let app_state = lock(&state);
+        (
+            app_state.tessdata_dir.clone(),
+            language
+                .or_else(|| app_state.ocr_language.clone())
+                .unwrap_or_else(|| tessdata::DEFAULT_LANGUAGE.to_string()),
+            app_state.ocr_config.clone(),
+            app_state.capture_config.clone(),
+            app_state.process_guard_config.clone(),
+        )
+    };
+    tauri::async_runtime::spawn_blocking(move || {
+        wizard::run_test(region, &capture_config, &process_guard_config, tessdata_dir.as_deref(), &language, ocr_config)
+    })
+    .await
+    .map_err(|e| WizardError::Internal(format!("認識の確認に失敗: {}", e)))?
+}
+
+/// 初回起動時の言語データ自動ダウンロード設定コマンド
+#[tauri::command]
+fn set_skip_auto_download(skip: bool, state: State<Mutex<AppState>>) {
+    lock(&state).skip_auto_download = skip;
+    info!("言語データの自動ダウンロードを{}にしました", if skip { "無効" } else { "有効" });
+}
+
+/// 設定変更の結果
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+enum SettingChange {
+    /// 反映された（監視中でない、または監視中でも即時に反映される設定）
+    Applied,
+    /// 監視中のため、反映には監視の再開始が必要
+    RequiresRestart,
+    /// 監視を再開始して反映した
+    Restarted { session_id: u64 },
+}
+
+/// 監視セッションの開始時に読み込まれる設定を変更
+///
+/// 監視中に変更した場合、apply_and_restartが指定されていれば同じ領域で監視を再開始し、
+/// 指定されていなければRequiresRestartを返してフロントエンドに確認を促す。
+async fn update_session_setting<F>(
+    state: State<'_, Mutex<AppState>>,
+    window: Window,
+    apply_and_restart: bool,
+    update: F,
+) -> Result<SettingChange, String>
+where
+    F: FnOnce(&mut AppState),
+{
+    let is_monitoring = {
+        let mut app_state = lock(&state);
+        update(&mut app_state);
+        matches!(app_state.phase, MonitorPhase::Starting | MonitorPhase::Monitoring)
+    };
+
+    match (is_monitoring, apply_and_restart) {
+        (false, _) => Ok(SettingChange::Applied),
+        (true, false) => Ok(SettingChange::RequiresRestart),
+        (true, true) => {
+            let session_id = restart_monitoring(state, window).await?;
+            Ok(SettingChange::Restarted { session_id })
         }
     }
-    
-    (added, removed)
 }
 
-fn main() {
-    // ログの初期化
-    env_logger::init();
-    info!("Tauri版画面テキスト監視システムを起動しています...");
-    
-    tauri::Builder::default()
-        .manage(Mutex::new(AppState::default()))
-        .invoke_handler(tauri::generate_handler![
-            select_region,
-            start_monitoring,
-            stop_monitoring
-        ])
-        .run(tauri::generate_context!())
-        .expect("Tauriアプリケーションの起動エラー");
-}
\ No newline at end of file
+/// 監視を開始せずに、同じ領域と設定で開始できるかを確認するコマンド（アプリの状態は変更しない）
+///
+/// start_monitoringと同じ設定の確認とOCRエンジンの作成に加え、1回のキャプチャと認識、
+/// 配信先への接続を確認し、すべての確認の結果を返す。
+#[tauri::command]
+async fn validate_monitoring(
+    region: CaptureRegion,
+    fast_mode: Option<FastModeConfig>,
+    state: State<'_, Mutex<AppState>>,
+) -> Result<StartupReport, String> {
+    info!("監視の開始の確認コマンドが呼ばれました: region={:?}", region);
+    let mut report = StartupReport::new();
+
+    let (capture_config, process_guard_config, mut ocr_config, tessdata_dir, skip_auto_download, ocr_baseline, retain_preprocessed, language, event_sink, text_server_address) = {
+        let app_state = lock(&state);
+        report.record(
+            "phase",
+            if app_state.phase == MonitorPhase::Idle {
+                Ok("監視を開始できる状態です".to_string())
+            } else {
+                Err(format!("{:?} の状態では監視を開始できません", app_state.phase))
+            },
+        );
+        report.record(
+            "config",
+            app_state.validate_session_config(fast_mode.as_ref()).map(|()| "設定は範囲内です".to_string()),
+        );
+        // 監視に合わせて起動するテキスト配信サーバーは、起動していなければ待ち受けられるか確認する
+        #[cfg(feature = "rest")]
+        let text_server_address = (app_state.text_server_config.enabled && app_state.text_server.is_none())
+            .then(|| app_state.text_server_config.socket_addr())
+            .flatten();
+        #[cfg(not(feature = "rest"))]
+        let text_server_address: Option<std::net::SocketAddr> = None;
+        (
+            app_state.capture_config.clone(),
+            app_state.process_guard_config.clone(),
+            app_state.ocr_config.clone(),
+            app_state.tessdata_dir.clone(),
+            app_state.skip_auto_download,
+            app_state.ocr_baseline,
+            app_state.evidence_config.enabled,
+            app_state.ocr_language.clone().unwrap_or_else(|| tessdata::DEFAULT_LANGUAGE.to_string()),
+            app_state.event_sink.clone(),
+            text_server_address,
+        )
+    };
+    if let Some(fast_mode) = &fast_mode {
+        fast_mode.apply_ocr(&mut ocr_config);
+    }
+
+    let report = tauri::async_runtime::spawn_blocking(move || {
+        // 開始時と同じ関数で確かめる（モニターの情報が無い領域は現在のモニターを選択時の値とする）
+        let prepared = capture::prepare_session_region(region);
+        let region_ok = report.record(
+            "region",
+            match &prepared {
+                Ok(region) => Ok(format!(
+                    "{}x{} の領域はモニター {} に収まっています",
+                    region.width,
+                    region.height,
+                    region.display.map(|display| display.stable_id()).unwrap_or_default()
+                )),
+                Err(e) => Err(format!("{:#}", e)),
+            },
+        );
+        let region = prepared.unwrap_or(region);
+
+        let image = if region_ok {
+            let result = guarded_capture(&process_guard_config, &ScreenCapture::with_config(region, &capture_config));
+            let details = match &result {
+                Ok(image) => Ok(format!("{}x{} の画像をキャプチャしました", image.width(), image.height())),
+                Err(e) => Err(format!("キャプチャエラー: {:#}", e)),
+            };
+            report.record("capture", details);
+            result.ok()
+        } else {
+            report.skip("capture", "領域の確認に失敗したためキャプチャしません");
+            None
+        };
+
+        let availability = tessdata::check_availability(tessdata_dir.as_deref(), &language);
+        let first_run_setup = tessdata_dir
+            .as_deref()
+            .is_some_and(|dir| tessdata::needs_first_run_setup(dir, &language));
+        let language_ok = report.record(
+            "language",
+            match () {
+                _ if availability.available => Ok(availability.message.clone()),
+                _ if first_run_setup && !skip_auto_download => {
+                    Ok(format!("{}（監視の開始時に言語データをダウンロードします）", availability.message))
+                }
+                _ => Err(availability.message.clone()),
+            },
+        );
+
+        if !availability.available {
+            report.skip(
+                "ocr_engine",
+                if language_ok { "言語データのダウンロード前のため作成しません" } else { "言語データが無いため作成しません" },
+            );
+        } else {
+            let page_seg_mode = ocr_config.page_seg_mode.unwrap_or(ocr::DEFAULT_PAGE_SEG_MODE);
+            let details = create_session_engine(tessdata_dir.as_deref(), &language, ocr_baseline, &ocr_config, retain_preprocessed)
+                .and_then(|engine| match &image {
+                    Some(image) => engine.recognize_detailed(image).map(|result| {
+                        format!(
+                            "言語 {}、ページセグメンテーションモード {} で {} 文字を認識しました（信頼度 {}）",
+                            language,
+                            page_seg_mode,
+                            result.text.chars().count(),
+                            result.confidence.map_or("なし".to_string(), |confidence| format!("{:.2}", confidence))
+                        )
+                    }),
+                    None => Ok(format!("言語 {}、ページセグメンテーションモード {} でエンジンを作成しました", language, page_seg_mode)),
+                })
+                .map_err(|e| format!("OCRエンジンのエラー: {:#}", e));
+            report.record("ocr_engine", details);
+        }
+
+        let sink_address = lock(&event_sink).as_ref().map(|sink| sink.address());
+        match sink_address {
+            Some(address) => {
+                report.record("event_sink", startup_check::check_tcp(&address, startup_check::CONNECT_TIMEOUT));
+            }
+            None => report.skip("event_sink", "外への配信は起動していません"),
+        }
+
+        match text_server_address {
+            Some(address) => {
+                let result = std::net::TcpListener::bind(address)
+                    .map(|_| format!("{} で待ち受けられます", address))
+                    .map_err(|e| format!("{} で待ち受けられません: {}", address, e));
+                report.record("text_server", result);
+            }
+            None => report.skip("text_server", "監視に合わせて起動するテキスト配信サーバーはありません"),
+        }
+        report
+    })
+    .await
+    .map_err(|e| format!("開始の確認に失敗しました: {}", e))?;
+
+    info!("監視の開始の確認が完了しました: ok={}", report.ok);
+    Ok(report)
+}
+
+/// 監視を停止してスレッドの終了を待ち、同じ領域で新しいセッションとして再開始
+///
+/// 履歴は保持したまま、新しいセッションの識別子を返す。
+async fn restart_monitoring(state: State<'_, Mutex<AppState>>, window: Window) -> Result<u64, String> {
+    let region = lock(&state)
+        .selected_region
+        .ok_or_else(|| "監視中の領域が見つかりません".to_string())?;
+
+    // 新しいスレッドの起動前に前のスレッドを確実に終了させる
+    stop_and_join(&state, "restart_monitoring").await.map_err(|e| e.to_string())?;
+    info!("設定を反映するため監視を再開始します");
+
+    let fast_mode = lock(&state).fast_mode.clone();
+    start_monitoring(region, fast_mode, state.clone(), window).map_err(|e| e.to_string())?;
+    let session_id = lock(&state).session_id;
+    Ok(session_id)
+}
+
+/// 監視を停止し、監視スレッドの終了を待つ（終了後はIdleに戻る）
+async fn stop_and_join(state: &State<'_, Mutex<AppState>>, command: &'static str) -> Result<(), MonitorCommandError> {
+    let handle = {
+        let mut app_state = lock(state);
+        app_state.phase.transition(command, &[MonitorPhase::Monitoring], MonitorPhase::Stopping)?;
+        app_state.stop_monitoring.store(true, Ordering::Relaxed);
+        lock(&app_state.evidence).clear();
+        app_state.ocr_reload = None;
+        app_state.reference_updates = None;
+        lock(&app_state.watchlist).reset();
+        // 監視に合わせて起動したテキスト配信サーバーは一緒に停止（コマンドで起動したものは残す）
+        #[cfg(feature = "rest")]
+        if app_state.text_server.as_ref().is_some_and(|server| server.with_monitoring) {
+            app_state.text_server = None;
+        }
+        app_state.monitor_handle.take()
+    };
+
+    let joined = match handle {
+        Some(handle) => tauri::async_runtime::spawn_blocking(move || handle.join())
+            .await
+            .map_err(|e| format!("監視スレッドの終了待ちに失敗: {}", e))
+            .and_then(|result| result.map_err(|_| "監視スレッドが異常終了しました".to_string())),
+        None => Ok(()),
+    };
+
+    // 異常終了した場合もスレッドは残っていないため停止済みとする
+    lock(state).phase = MonitorPhase::Idle;
+    joined.map_err(MonitorCommandError::Failed)
+}
+
+/// 監視の状態
+#[derive(Debug, Clone, serde::Serialize)]
+struct MonitoringStatus {
+    /// 監視が実行中かどうか
+    is_monitoring: bool,
+    /// 監視の状態
+    phase: MonitorPhase,
+    /// 現在（または直前）の監視セッションの識別子
+    session_id: u64,
+    /// 選択中の領域
+    selected_region: Option<CaptureRegion>,
+    /// 範囲チェック済みの監視の設定
+    monitor_config: MonitorConfig,
+    /// 範囲チェック済みのタイル設定
+    tile_config: TileConfig,
+    /// 範囲チェック済みのキャプチャ設定
+    capture_config: CaptureConfig,
+    /// OCRの設定
+    ocr_config: OcrConfig,
+    /// 現在テキストに現れている（再通知しない状態の）キーワード
+    latched_keywords: Vec<String>,
+    /// キャプチャしないプロセスの確認の状態
+    process_guard: ProcessGuardStatus,
+}
+
+/// 監視の状態と現在有効な設定の取得コマンド
+#[tauri::command]
+fn get_status(state: State<Mutex<AppState>>) -> MonitoringStatus {
+    state_commands::get_status(&state)
+}
+
+/// 不具合報告用のデバッグバンドルの作成コマンド（監視中でも作成でき、作成したZIPファイルのパスを返す）
+///
+/// 設定・直近のログ・統計・環境とモニターの情報・Tesseractの情報と、保持していれば
+/// 直近のイベントの画像を含める。秘密情報を含みうる値は伏せて書き出す。
+#[tauri::command]
+fn create_debug_bundle(state: State<Mutex<AppState>>) -> Result<PathBuf, String> {
+    info!("デバッグバンドルの作成コマンドが呼ばれました");
+    // 時間のかかる処理の間は状態のロックを保持しない
+    let (settings, stats, evidence, tessdata_dir, language, bundle_dir, images_allowed) = {
+        let app_state = lock(&state);
+        let settings = serde_json::json!({
+            "phase": app_state.phase,
+            "session_id": app_state.session_id,
+            "selected_region": app_state.selected_region,
+            "ocr_language": app_state.ocr_language,
+            "monitor_config": *lock(&app_state.monitor_config),
+            "capture_config": app_state.capture_config,
+            "ocr_config": app_state.ocr_config,
+            "tile_config": app_state.tile_config,
+            "diff_config": app_state.diff_config,
+            "evidence_config": app_state.evidence_config,
+            "selector_config": app_state.selector_config,
+            "watchlist": lock(&app_state.watchlist).config(),
+            "text_server_config": app_state.text_server_config,
+            "process_guard_config": app_state.process_guard_config,
+            "event_channels": app_state.event_channels,
+        });
+        (
+            settings,
+            app_state.stats.clone(),
+            app_state.evidence.clone(),
+            app_state.tessdata_dir.clone(),
+            app_state.ocr_language.clone().unwrap_or_else(|| tessdata::DEFAULT_LANGUAGE.to_string()),
+            app_state.debug_bundle_dir.clone().unwrap_or_else(std::env::temp_dir),
+            // 画像は監視の領域から取り込んだものなので、今その領域に拒否リストのウィンドウがあれば含めない
+            app_state.selected_region.map_or(Ok(()), |region| ensure_allowed(&app_state.process_guard_config, &region)),
+        )
+    };
+
+    let mut bundle = DebugBundle::new();
+    let stats = lock(&stats).clone();
+    let result: Result<()> = (|| {
+        // サイズの上限を超えた場合は後に追加したものから省かれる
+        bundle.add_json("settings.json", &settings)?;
+        bundle.add_json("stats.json", &stats)?;
+        bundle.add_json("platform.json", &PlatformInfo::collect())?;
+        bundle.add_json("tesseract.json", &TesseractInfo::collect(tessdata_dir.as_deref(), &language))?;
+        bundle.add_json("logs.json", &log_buffer::recent(DEBUG_BUNDLE_LOG_RECORDS))?;
+        let images = match &images_allowed {
+            Ok(()) => lock(&evidence).recent_images(DEBUG_BUNDLE_EVIDENCE_IMAGES),
+            Err(e) => {
+                info!("デバッグバンドルにイベントの画像を含めません: {}", e);
+                Vec::new()
+            }
+        };
+        for (sequences, image) in images {
+            let name = format!("evidence/{}.png", sequences.first().copied().unwrap_or_default());
+            bundle.add_file(&name, encode_png(&image)?);
+        }
+
+        std::fs::create_dir_all(&bundle_dir)?;
+        Ok(())
+    })();
+    result.map_err(|e| format!("デバッグバンドルの作成に失敗: {}", e))?;
+
+    let path = bundle_dir.join(format!("debug_bundle_{}.zip", chrono::Local::now().format("%Y%m%d_%H%M%S")));
+    bundle.write(&path).map_err(|e| e.to_string())?;
+    info!("デバッグバンドルを作成しました: {}", path.display());
+    Ok(path)
+}
+
+/// 監視停止のコマンド（監視スレッドの終了を待ってから返る）
+#[tauri::command]
+async fn stop_monitoring(state: State<'_, Mutex<AppState>>) -> Result<(), MonitorCommandError> {
+    stop_and_join(&state, "stop_monitoring").await?;
+    info!("監視を停止しました");
+    Ok(())
+}
+
+/// OCR信頼度のベースライン計測コマンド（キャプチャと計測を一度に実行）
+#[tauri::command]
+async fn calibrate_ocr(
+    region: CaptureRegion,
+    state: State<'_, Mutex<AppState>>,
+    window: Window,
+) -> Result<f32, String> {
+    info!("OCRベースライン計測コマンドが呼ばれました: region={:?}", region);
+    // ベースラインは言語ごとに異なるため、監視と同じ言語・設定のエンジンで計測する
+    let (mut emitter, tessdata_dir, language, ocr_config, capture_config, process_guard_config) = {
+        let app_state = lock(&state);
+        (
+            app_state.emitter(window),
+            app_state.tessdata_dir.clone(),
+            app_state.ocr_language.clone().unwrap_or_else(|| tessdata::DEFAULT_LANGUAGE.to_string()),
+            app_state.ocr_config.clone(),
+            app_state.capture_config.clone(),
+            app_state.process_guard_config.clone(),
+        )
+    };
+    emitter.info("calibration_started", "OCR信頼度のベースラインを計測しています");
+    let calibration_start = Instant::now();
+
+    // 計測はロックを保持せずに実行
+    let image = guarded_capture(&process_guard_config, &ScreenCapture::with_config(region, &capture_config))
+        .map_err(|e| format!("キャプチャエラー: {}", e))?;
+
+    let baseline = tauri::async_runtime::spawn_blocking(move || {
+        let datapath = tessdata::resolve_datapath(tessdata_dir.as_deref(), &language);
+        let mut ocr_engine =
+            OcrEngine::with_backend(datapath, &language, ocr_config.backend).map_err(|e| format!("OCR初期化エラー: {}", e))?;
+        ocr_engine.set_config(ocr_config);
+        ocr_engine
+            .calibrate_confidence_baseline(&image)
+            .map_err(|e| format!("ベースライン計測エラー: {}", e))
+    })
+    .await
+    .map_err(|e| format!("ベースライン計測タスクエラー: {}", e))??;
+
+    // 次回以降の監視で使用するため保存
+    lock(&state).ocr_baseline = Some(baseline);
+    emitter.info(
+        "calibration_finished",
+        format!(
+            "ベースラインの計測が完了しました: {:.3}（{}ms）",
+            baseline,
+            calibration_start.elapsed().as_millis()
+        ),
+    );
+
+    Ok(baseline)
+}
+
+/// 前処理パイプラインの追跡コマンド（1回キャプチャして各段階の結果を返す）
+#[tauri::command]
+async fn trace_pipeline(region: CaptureRegion, state: State<'_, Mutex<AppState>>) -> Result<PipelineTrace, String> {
+    info!("パイプライン追跡コマンドが呼ばれました: region={:?}", region);
+
+    let (tessdata_dir, language, debug_pipeline, ocr_config, capture_config, process_guard_config) = {
+        let app_state = lock(&state);
+        (
+            app_state.tessdata_dir.clone(),
+            app_state.ocr_language.clone().unwrap_or_else(|| tessdata::DEFAULT_LANGUAGE.to_string()),
+            app_state.debug_pipeline,
+            app_state.ocr_config.clone(),
+            app_state.capture_config.clone(),
+            app_state.process_guard_config.clone(),
+        )
+    };
+
+    let image = guarded_capture(&process_guard_config, &ScreenCapture::with_config(region, &capture_config))
+        .map_err(|e| format!("キャプチャエラー: {}", e))?;
+
+    let datapath = tessdata::resolve_datapath(tessdata_dir.as_deref(), &language);
+    let mut ocr_engine = OcrEngine::with_backend(datapath, &language, ocr_config.backend)
+        .map_err(|e| format!("OCR初期化エラー: {}", e))?;
+    ocr_engine.set_debug_pipeline(debug_pipeline);
+    ocr_engine.set_config(ocr_config);
+
+    ocr_engine
+        .recognize_with_pipeline_trace(&image)
+        .map_err(|e| format!("パイプライン追跡エラー: {}", e))
+}
+
+/// 4組の対応点から幾何補正の射影変換の行列を求めるコマンド（OCR設定のtransformに指定する）
+#[tauri::command]
+fn calibrate_homography(src_points: [(f32, f32); 4], dst_points: [(f32, f32); 4]) -> Result<[[f64; 3]; 3], String> {
+    transform::homography_from_points(&src_points, &dst_points).map_err(|e| e.to_string())
+}
+
+/// 2つの領域を同時にキャプチャ・認識して比較するコマンド（監視とは独立して実行）
+#[tauri::command]
+async fn compare_regions(
+    region_a: CaptureRegion,
+    region_b: CaptureRegion,
+    state: State<'_, Mutex<AppState>>,
+) -> Result<RegionComparison, String> {
+    info!("領域の比較コマンドが呼ばれました: a={:?}, b={:?}", region_a, region_b);
+    let (tessdata_dir, language, ocr_config, capture_config, process_guard_config) = {
+        let app_state = lock(&state);
+        (
+            app_state.tessdata_dir.clone(),
+            app_state.ocr_language.clone().unwrap_or_else(|| tessdata::DEFAULT_LANGUAGE.to_string()),
+            app_state.ocr_config.clone(),
+            app_state.capture_config.clone(),
+            app_state.process_guard_config.clone(),
+        )
+    };
+
+    // キャプチャと認識はブロッキング処理のため、領域ごとに専用スレッドで並行して実行
+    let recognize = |region: CaptureRegion| {
+        let datapath = tessdata::resolve_datapath(tessdata_dir.as_deref(), &language);
+        let language = language.clone();
+        let ocr_config = ocr_config.clone();
+        let capture_config = capture_config.clone();
+        let process_guard_config = process_guard_config.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+            compare::recognize_region(region, &capture_config, &process_guard_config, datapath, &language, ocr_config)
+        })
+    };
+    let (result_a, result_b) = tokio::join!(recognize(region_a), recognize(region_b));
+
+    let recognized = |result: Result<anyhow::Result<compare::RegionCapture>, tauri::Error>, name: &str| {
+        result
+            .map_err(|e| format!("領域{}の処理に失敗: {}", name, e))?
+            .map_err(|e| format!("領域{}の認識エラー: {:#}", name, e))
+    };
+    Ok(compare::compare(recognized(result_a, "A")?, recognized(result_b, "B")?))
+}
+
+/// 領域のテキストが正規表現に一致するまで200msごとに認識するコマンド（UIの自動テスト用）
+///
+/// timeout_ms を過ぎても一致しなければ matched: false を返す（エラーにはしない）。
+#[tauri::command]
+async fn assert_text(
+    region: CaptureRegion,
+    expected_pattern: String,
+    timeout_ms: u64,
+    state: State<'_, Mutex<AppState>>,
+) -> Result<AssertResult, String> {
+    info!("テキストの確認コマンドが呼ばれました: region={:?}, pattern={}", region, expected_pattern);
+    let pattern = Regex::new(&expected_pattern).map_err(|e| format!("正規表現が不正です: {}", e))?;
+    run_text_probe(region, &state, move |probe| {
+        probe.wait_for_match(&pattern, Duration::from_millis(timeout_ms))
+    })
+    .await
+}
+
+/// 領域のテキストが stable_for_ms の間変わらないか確かめるコマンド（変わった場合は matched: false）
+#[tauri::command]
+async fn assert_no_text_change(
+    region: CaptureRegion,
+    stable_for_ms: u64,
+    state: State<'_, Mutex<AppState>>,
+) -> Result<AssertResult, String> {
+    info!("テキストの安定の確認コマンドが呼ばれました: region={:?}, stable_for_ms={}", region, stable_for_ms);
+    run_text_probe(region, &state, move |probe| probe.wait_for_stable(Duration::from_millis(stable_for_ms))).await
+}
+
+/// 指定した時刻のスナップショットの結果
+#[derive(Debug, Clone, serde::Serialize)]
+struct SnapshotResult {
+    /// 認識したテキスト
+    text: String,
+    /// 認識の信頼度（0.0-1.0、信頼度を返さないエンジンの場合はNone）
+    confidence: Option<f32>,
+    /// 指定した時刻（UNIXエポックからのミリ秒）
+    requested_at_ms: u64,
+    /// 実際にキャプチャした時刻（UNIXエポックからのミリ秒）
+    captured_at_ms: u64,
+}
+
+/// 指定した時刻（UNIXエポックからのミリ秒）に領域を1回キャプチャ・認識するコマンド
+///
+/// 監視とは別のエンジンで認識するため、監視中でも監視の前回のテキストは変わらない。
+/// 時刻ちょうどにキャプチャできるよう、エンジンは待つ前に作成しておく。
+#[tauri::command]
+async fn take_snapshot_at(
+    region: CaptureRegion,
+    unix_ts_ms: u64,
+    state: State<'_, Mutex<AppState>>,
+) -> Result<SnapshotResult, String> {
+    info!("時刻指定のスナップショットのコマンドが呼ばれました: region={:?}, unix_ts_ms={}", region, unix_ts_ms);
+    let deadline = Instant::now() + snapshot_delay(unix_ts_ms).map_err(|e| e.to_string())?;
+    let (tessdata_dir, language, ocr_config, capture_config, process_guard_config) = {
+        let app_state = lock(&state);
+        (
+            app_state.tessdata_dir.clone(),
+            app_state.ocr_language.clone().unwrap_or_else(|| tessdata::DEFAULT_LANGUAGE.to_string()),
+            app_state.ocr_config.clone(),
+            app_state.capture_config.clone(),
+            app_state.process_guard_config.clone(),
+        )
+    };
+    tauri::async_runtime::spawn_blocking(move || -> anyhow::Result<SnapshotResult> {
+        let datapath = tessdata::resolve_datapath(tessdata_dir.as_deref(), &language);
+        let mut engine = OcrEngine::with_language(datapath, &language)?;
+        engine.set_config(ocr_config);
+        let capture = ScreenCapture::with_config(region, &capture_config);
+
+        thread::sleep(deadline.saturating_duration_since(Instant::now()));
+        let captured_at_ms = now_millis();
+        let image = guarded_capture(&process_guard_config, &capture).map_err(|e| anyhow::anyhow!("キャプチャに失敗しました: {}", e))?;
+        let result = engine.recognize_detailed(&image)?;
+        Ok(SnapshotResult {
+            text: result.text,
+            confidence: result.confidence,
+            requested_at_ms: unix_ts_ms,
+            captured_at_ms,
+        })
+    })
+    .await
+    .map_err(|e| format!("スナップショットに失敗: {}", e))?
+    .map_err(|e| format!("スナップショットのエラー: {:#}", e))
+}
+
+/// 現在の認識の設定で領域を繰り返し認識する処理を専用スレッドで実行
+async fn run_text_probe(
+    region: CaptureRegion,
+    state: &State<'_, Mutex<AppState>>,
+    check: impl FnOnce(&TextProbe) -> anyhow::Result<AssertResult> + Send + 'static,
+) -> Result<AssertResult, String> {
+    let (tessdata_dir, language, ocr_config, capture_config, process_guard_config) = {
+        let app_state = lock(state);
+        (
+            app_state.tessdata_dir.clone(),
+            app_state.ocr_language.clone().unwrap_or_else(|| tessdata::DEFAULT_LANGUAGE.to_string()),
+            app_state.ocr_config.clone(),
+            app_state.capture_config.clone(),
+            app_state.process_guard_config.clone(),
+        )
+    };
+    tauri::async_runtime::spawn_blocking(move || {
+        let datapath = tessdata::resolve_datapath(tessdata_dir.as_deref(), &language);
+        let probe = TextProbe::new(region, &capture_config, process_guard_config, datapath, &language, ocr_config)?;
+        check(&probe)
+    })
+    .await
+    .map_err(|e| format!("テキストの確認に失敗: {}", e))?
+    .map_err(|e| format!("テキストの確認エラー: {:#}", e))
+}
+
+/// 前処理パラメータの自動調整の結果
+#[derive(Debug, Clone, serde::Serialize)]
+struct AutoTuneResponse {
+    #[serde(flatten)]
+    report: AutoTuneReport,
+    /// 最良の設定を反映した結果（apply_bestが指定された場合のみ）
+    applied: Option<SettingChange>,
+}
+
+/// 前処理パラメータの自動調整コマンド
+///
+/// 選択中の領域を1回キャプチャし、利用者が入力した表示中のテキストに最も近い結果になる
+/// 設定の組み合わせを探す。apply_bestが指定されていれば最良の設定をOCR設定に反映する。
+#[tauri::command]
+async fn auto_tune(
+    expected_text: String,
+    apply_best: Option<bool>,
+    apply_and_restart: Option<bool>,
+    state: State<'_, Mutex<AppState>>,
+    window: Window,
+) -> Result<AutoTuneResponse, String> {
+    info!("前処理の自動調整コマンドが呼ばれました");
+    let (region, tessdata_dir, language, ocr_baseline, ocr_config, capture_config, process_guard_config, mut emitter) = {
+        let app_state = lock(&state);
+        (
+            app_state.selected_region.ok_or_else(|| "先に領域を選択してください".to_string())?,
+            app_state.tessdata_dir.clone(),
+            app_state.ocr_language.clone().unwrap_or_else(|| tessdata::DEFAULT_LANGUAGE.to_string()),
+            app_state.ocr_baseline,
+            app_state.ocr_config.clone(),
+            app_state.capture_config.clone(),
+            app_state.process_guard_config.clone(),
+            app_state.emitter(window.clone()),
+        )
+    };
+    emitter.info("auto_tune_started", "前処理の自動調整を開始しました");
+
+    let image = guarded_capture(&process_guard_config, &ScreenCapture::with_config(region, &capture_config))
+        .map_err(|e| format!("キャプチャエラー: {}", e))?;
+
+    // 探索はブロッキング処理のため専用スレッドで実行
+    let report = tauri::async_runtime::spawn_blocking(move || {
+        // 監視と同じ言語で認識した結果で設定を比べる
+        let datapath = tessdata::resolve_datapath(tessdata_dir.as_deref(), &language);
+        let mut ocr_engine =
+            OcrEngine::with_backend(datapath, &language, ocr_config.backend).map_err(|e| format!("OCR初期化エラー: {}", e))?;
+        ocr_engine.set_calibrated_baseline(ocr_baseline);
+
+        let report = autotune::auto_tune(
+            &mut ocr_engine,
+            &image,
+            &expected_text,
+            &ocr_config,
+            autotune::TIME_BUDGET,
+            |evaluated, total, best| {
+                emitter.emit_transient(TextChangeEvent::TuneProgress {
+                    evaluated,
+                    total,
+                    best_accuracy: best.map_or(0.0, |result| result.accuracy),
+                });
+            },
+        )
+        .map_err(|e| format!("自動調整エラー: {}", e))?;
+        emitter.info(
+            "auto_tune_finished",
+            format!("前処理の自動調整が完了しました（{}/{} 件、{}ms）", report.evaluated, report.total, report.elapsed_ms),
+        );
+        Ok::<_, String>(report)
+    })
+    .await
+    .map_err(|e| format!("自動調整の実行に失敗: {}", e))??;
+
+    let best = report.results.first().map(|result| result.config.clone());
+    let applied = match best {
+        Some(config) if apply_best.unwrap_or(false) => {
+            info!("自動調整の最良の設定を反映します: {:?}", config);
+            let change = update_session_setting(state, window, apply_and_restart.unwrap_or(false), |app_state| {
+                app_state.ocr_config = config;
+            })
+            .await?;
+            Some(change)
+        }
+        _ => None,
+    };
+
+    Ok(AutoTuneResponse { report, applied })
+}
+
+/// パイプライン追跡時の中間画像保存の設定コマンド
+#[tauri::command]
+fn set_debug_pipeline(enabled: bool, state: State<Mutex<AppState>>) {
+    lock(&state).debug_pipeline = enabled;
+    info!("パイプラインの中間画像保存を{}にしました", if enabled { "有効" } else { "無効" });
+}
+
+/// 領域選択の設定の取得コマンド（選択画面の読み込み時にも呼ばれる）
+#[tauri::command]
+fn get_selector_config(state: State<Mutex<AppState>>) -> SelectorConfig {
+    lock(&state).selector_config.clone()
+}
+
+/// 領域選択の設定の変更コマンド（次の領域選択から反映）
+#[tauri::command]
+fn set_selector_config(config: SelectorConfig, state: State<Mutex<AppState>>) -> Result<(), String> {
+    config.validate().map_err(|e| e.to_string())?;
+    info!("領域選択の設定を変更しました: {:?}", config);
+    lock(&state).selector_config = config;
+    Ok(())
+}
+
+/// キャプチャ設定の取得コマンド
+#[tauri::command]
+fn get_capture_config(state: State<Mutex<AppState>>) -> CaptureConfig {
+    lock(&state).capture_config.clone()
+}
+
+/// キャプチャ設定の変更コマンド（監視中は再開始が必要）
+#[tauri::command]
+async fn set_capture_config(
+    config: CaptureConfig,
+    apply_and_restart: Option<bool>,
+    state: State<'_, Mutex<AppState>>,
+    window: Window,
+) -> Result<SettingChange, String> {
+    config.validate().map_err(|e| e.to_string())?;
+    info!("キャプチャ設定を変更しました: {:?}", config);
+    update_session_setting(state, window, apply_and_restart.unwrap_or(false), |app_state| {
+        app_state.capture_config = config;
+    })
+    .await
+}
+
+/// キャプチャのチャンネル順の確認コマンド
+///
+/// 色が入れ替わって見える環境で、変換前の形式とテストキャプチャのチェックサムを確認するために使う。
+/// 領域の中央の色（RGB）を指定すると、キャプチャした画素バッファの形式を判定して報告する。
+#[tauri::command]
+async fn inspect_capture_format(
+    region: CaptureRegion,
+    expected_center_color: Option<[u8; 3]>,
+    state: State<'_, Mutex<AppState>>,
+) -> Result<CaptureFormatReport, String> {
+    let pixel_format = lock(&state).capture_config.effective_pixel_format();
+    let report = LiveScreenSource::new(pixel_format)
+        .inspect(&region, expected_center_color)
+        .map_err(|e| format!("キャプチャエラー: {}", e))?;
+    info!("キャプチャ形式の確認結果: {:?}", report);
+    if let Some(raw_format) = report.raw_format.filter(|format| *format != report.effective_format) {
+        log::warn!("キャプチャした画素は {:?} ですが、{:?} として変換しています（capture.pixel_formatを確認してください）", raw_format, report.effective_format);
+    }
+    Ok(report)
+}
+
+/// 領域の代表色の取得コマンド（色の多い順、最大8色）
+#[tauri::command]
+async fn pick_dominant_colors(
+    region: CaptureRegion,
+    n_colors: usize,
+    state: State<'_, Mutex<AppState>>,
+) -> Result<Vec<DominantColor>, String> {
+    let image = capture_for_palette(region, &state)?;
+    palette::dominant_colors(&image, n_colors).map_err(|e| format!("色の抽出エラー: {}", e))
+}
+
+/// 領域の背景と文字の色の取得コマンド（最も多い2色のうち明るい方を背景、暗い方を文字として返す）
+#[tauri::command]
+async fn pick_text_background_pair(
+    region: CaptureRegion,
+    state: State<'_, Mutex<AppState>>,
+) -> Result<(DominantColor, DominantColor), String> {
+    let image = capture_for_palette(region, &state)?;
+    palette::text_background_pair(&image).map_err(|e| format!("色の抽出エラー: {}", e))
+}
+
+/// 代表色を求める領域をキャプチャ
+fn capture_for_palette(region: CaptureRegion, state: &State<'_, Mutex<AppState>>) -> Result<DynamicImage, String> {
+    info!("代表色の取得コマンドが呼ばれました: region={:?}", region);
+    let (capture_config, process_guard_config) = {
+        let app_state = lock(state);
+        (app_state.capture_config.clone(), app_state.process_guard_config.clone())
+    };
+    guarded_capture(&process_guard_config, &ScreenCapture::with_config(region, &capture_config))
+        .map_err(|e| format!("キャプチャエラー: {}", e))
+}
+
+/// OCR設定の取得コマンド
+#[tauri::command]
+fn get_ocr_config(state: State<Mutex<AppState>>) -> OcrConfig {
+    lock(&state).ocr_config.clone()
+}
+
+/// OCR設定の変更コマンド（監視中は再開始が必要）
+#[tauri::command]
+async fn set_ocr_config(
+    config: OcrConfig,
+    apply_and_restart: Option<bool>,
+    state: State<'_, Mutex<AppState>>,
+    window: Window,
+) -> Result<SettingChange, String> {
+    config.validate().map_err(|e| e.to_string())?;
+    info!("OCR設定を変更しました: {:?}", config);
+    update_session_setting(state, window, apply_and_restart.unwrap_or(false), |app_state| {
+        app_state.ocr_config = config;
+    })
+    .await
+}
+
+/// 認識しやすくする文字列のパターンを追加するコマンド（監視中は再開始が必要）
+///
+/// パターンの書式は OcrEngine::set_user_patterns を参照（例: 時刻は `\d\d:\d\d:\d\d`）。
+#[tauri::command]
+async fn add_user_pattern(
+    pattern: String,
+    apply_and_restart: Option<bool>,
+    state: State<'_, Mutex<AppState>>,
+    window: Window,
+) -> Result<SettingChange, String> {
+    let mut config = lock(&state).ocr_config.clone();
+    let patterns = config.user_patterns.get_or_insert_with(Vec::new);
+    if patterns.contains(&pattern) {
+        return Err(format!("既に追加されているパターンです: {}", pattern));
+    }
+    patterns.push(pattern.clone());
+    config.validate().map_err(|e| e.to_string())?;
+    info!("認識のパターンを追加しました: {}", pattern);
+    update_session_setting(state, window, apply_and_restart.unwrap_or(false), |app_state| {
+        app_state.ocr_config.user_patterns = config.user_patterns;
+    })
+    .await
+}
+
+/// 認識しやすくする文字列のパターンを削除するコマンド（監視中は再開始が必要）
+#[tauri::command]
+async fn remove_user_pattern(
+    pattern: String,
+    apply_and_restart: Option<bool>,
+    state: State<'_, Mutex<AppState>>,
+    window: Window,
+) -> Result<SettingChange, String> {
+    let mut patterns = lock(&state).ocr_config.user_patterns.clone().unwrap_or_default();
+    let Some(index) = patterns.iter().position(|registered| *registered == pattern) else {
+        return Err(format!("追加されていないパターンです: {}", pattern));
+    };
+    patterns.remove(index);
+    info!("認識のパターンを削除しました: {}", pattern);
+    update_session_setting(state, window, apply_and_restart.unwrap_or(false), |app_state| {
+        app_state.ocr_config.user_patterns = (!patterns.is_empty()).then_some(patterns);
+    })
+    .await
+}
+
+/// タイル単位の変化検出の設定コマンド（監視中は再開始が必要）
+#[tauri::command]
+async fn set_tile_config(
+    config: TileConfig,
+    apply_and_restart: Option<bool>,
+    state: State<'_, Mutex<AppState>>,
+    window: Window,
+) -> Result<SettingChange, String> {
+    config.validate().map_err(|e| e.to_string())?;
+    info!("タイル設定を変更しました: {:?}", config);
+    update_session_setting(state, window, apply_and_restart.unwrap_or(false), |app_state| {
+        app_state.tile_config = config;
+    })
+    .await
+}
+
+/// イベントの元になった画像の保持の設定コマンド（監視中は再開始が必要）
+#[tauri::command]
+async fn set_evidence_config(
+    config: EvidenceConfig,
+    apply_and_restart: Option<bool>,
+    state: State<'_, Mutex<AppState>>,
+    window: Window,
+) -> Result<SettingChange, String> {
+    config.validate().map_err(|e| e.to_string())?;
+    info!("イベント画像の保持の設定を変更しました: {:?}", config);
+    update_session_setting(state, window, apply_and_restart.unwrap_or(false), |app_state| {
+        app_state.evidence_config = config;
+    })
+    .await
+}
+
+/// キャプチャしないプロセスの設定の取得コマンド
+#[tauri::command]
+fn get_process_guard_config(state: State<Mutex<AppState>>) -> ProcessGuardConfig {
+    lock(&state).process_guard_config.clone()
+}
+
+/// キャプチャしないプロセスの設定の変更コマンド
+#[tauri::command]
+async fn set_process_guard_config(
+    config: ProcessGuardConfig,
+    apply_and_restart: Option<bool>,
+    state: State<'_, Mutex<AppState>>,
+    window: Window,
+) -> Result<SettingChange, String> {
+    config.validate().map_err(|e| e.to_string())?;
+    info!("キャプチャしないプロセスの設定を変更しました: {:?}", config);
+    update_session_setting(state, window, apply_and_restart.unwrap_or(false), |app_state| {
+        app_state.process_guard_config = config;
+    })
+    .await
+}
+
+/// イベントの元になった前処理済み画像の取得コマンド（base64エンコードしたPNG）
+#[tauri::command]
+fn get_event_image(sequence: u64, state: State<Mutex<AppState>>) -> Result<String, EventImageError> {
+    let app_state = lock(&state);
+    if !app_state.evidence_config.enabled {
+        return Err(EventImageError::Disabled);
+    }
+    let image = lock(&app_state.evidence).png_base64(sequence);
+    image
+}
+
+/// 変化イベントの比較画像の取得コマンド（前回の認識のフレーム・今回のフレーム・変化した画素を赤くした画像を横に並べたPNGのbase64）
+#[tauri::command]
+fn get_change_visual(sequence: u64, state: State<Mutex<AppState>>) -> Result<String, EventImageError> {
+    let app_state = lock(&state);
+    if !app_state.evidence_config.change_visual {
+        return Err(EventImageError::Disabled);
+    }
+    let image = lock(&app_state.evidence).change_visual_png_base64(sequence);
+    image
+}
+
+/// 監視処理の統計の取得コマンド（メトリクスエンドポイントと同じ値を返す）
+#[tauri::command]
+fn get_stats(state: State<Mutex<AppState>>) -> MonitorStats {
+    state_commands::get_stats(&state)
+}
+
+/// アプリの起動以降（または前回のリセット以降）のOCRエンジンの累計の統計の取得コマンド
+#[tauri::command]
+fn get_ocr_stats() -> OcrStats {
+    ocr_stats::snapshot()
+}
+
+/// OCRエンジンの累計の統計のリセットコマンド
+#[tauri::command]
+fn reset_ocr_stats() {
+    info!("OCRエンジンの累計の統計をリセットしました");
+    ocr_stats::reset();
+}
+
+/// 直近100フレームの前処理の平均所要時間の取得コマンド
+#[tauri::command]
+fn get_preprocess_timings(state: State<Mutex<AppState>>) -> PreprocessTimings {
+    let stats = lock(&state).stats.clone();
+    let average = lock(&stats).preprocess_average();
+    average
+}
+
+/// 直近に全体をOCRしたフレームの画像のゆがみの指標の取得コマンド（まだ無ければNone）
+#[tauri::command]
+fn get_last_image_metrics(state: State<Mutex<AppState>>) -> Option<ImageMetrics> {
+    let stats = lock(&state).stats.clone();
+    let metrics = lock(&stats).last_image_metrics;
+    metrics
+}
+
+/// REST APIサーバーの起動コマンド（restフィーチャー有効時のみ利用可能）
+///
+/// bind_addressの既定はループバックで、それ以外のアドレスで待ち受けるにはauth_tokenが必要。
+#[tauri::command]
+fn start_rest_server(
+    port: u16,
+    bind_address: Option<String>,
+    auth_token: Option<String>,
+    state: State<Mutex<AppState>>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    #[cfg(feature = "rest")]
+    {
+        let address = rest::resolve_bind_address(bind_address.as_deref(), auth_token.as_deref())?;
+        {
+            let mut app_state = lock(&state);
+            if let Some(running_port) = app_state.rest_server_port {
+                return Err(format!("REST APIサーバーは既にポート {} で起動しています", running_port));
+            }
+            app_state.rest_server_port = Some(port);
+        }
+
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = rest::serve(app_handle.clone(), address, port, auth_token).await {
+                log::error!("REST APIサーバーエラー: {}", e);
+                lock(&app_handle.state::<Mutex<AppState>>()).rest_server_port = None;
+            }
+        });
+        Ok(())
+    }
+
+    #[cfg(not(feature = "rest"))]
+    {
+        let _ = (port, bind_address, auth_token, state, app_handle);
+        Err("REST APIはこのビルドでは無効です（restフィーチャーを有効にしてビルドしてください）".to_string())
+    }
+}
+
+/// テキスト配信サーバーの設定の取得コマンド
+#[tauri::command]
+fn get_text_server_config(state: State<Mutex<AppState>>) -> TextServerConfig {
+    lock(&state).text_server_config.clone()
+}
+
+/// テキスト配信サーバーの設定の変更コマンド（起動中のサーバーには次の起動から反映）
+#[tauri::command]
+fn set_text_server_config(config: TextServerConfig, state: State<Mutex<AppState>>) -> Result<(), String> {
+    config.validate().map_err(|e| e.to_string())?;
+    info!("テキスト配信サーバーの設定を変更しました: {:?}", config);
+    lock(&state).text_server_config = config;
+    Ok(())
+}
+
+/// テキスト配信サーバーの起動コマンド（監視とは独立して起動し、停止コマンドまで動作する。restフィーチャー有効時のみ利用可能）
+#[tauri::command]
+async fn start_text_server(state: State<'_, Mutex<AppState>>, app_handle: tauri::AppHandle) -> Result<(), String> {
+    #[cfg(feature = "rest")]
+    {
+        let mut app_state = lock(&state);
+        match &mut app_state.text_server {
+            // 監視に合わせて起動したものは監視の停止後も残す
+            Some(server) => server.with_monitoring = false,
+            None => {
+                let server = text_server::TextServer::start(&app_state.text_server_config, app_handle, false)
+                    .map_err(|e| format!("テキスト配信サーバーを起動できません: {:#}", e))?;
+                app_state.text_server = Some(server);
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(not(feature = "rest"))]
+    {
+        let _ = (state, app_handle);
+        Err("テキスト配信サーバーはこのビルドでは無効です（restフィーチャーを有効にしてビルドしてください）".to_string())
+    }
+}
+
+/// テキスト配信サーバーの停止コマンド
+#[tauri::command]
+fn stop_text_server(state: State<Mutex<AppState>>) -> Result<(), String> {
+    #[cfg(feature = "rest")]
+    {
+        match lock(&state).text_server.take() {
+            Some(_) => Ok(()),
+            None => Err("テキスト配信サーバーは起動していません".to_string()),
+        }
+    }
+
+    #[cfg(not(feature = "rest"))]
+    {
+        let _ = state;
+        Err("テキスト配信サーバーはこのビルドでは無効です（restフィーチャーを有効にしてビルドしてください）".to_string())
+    }
+}
+
+/// MQTTへのイベント配信の開始コマンド（監視とは独立して動作し、停止コマンドまで配信する。mqttフィーチャー有効時のみ利用可能）
+#[tauri::command]
+fn start_mqtt_sink(config: MqttSinkConfig, state: State<Mutex<AppState>>) -> Result<(), String> {
+    #[cfg(feature = "mqtt")]
+    {
+        let app_state = lock(&state);
+        let mut sink = lock(&app_state.event_sink);
+        if sink.is_some() {
+            return Err("MQTTへの配信は既に起動しています".to_string());
+        }
+        info!("MQTTへの配信を開始します: {}:{}", config.host(), config.port);
+        let mqtt_sink = mqtt::MqttSink::start(config).map_err(|e| format!("MQTTへの配信を開始できません: {:#}", e))?;
+        *sink = Some(Box::new(mqtt_sink));
+        Ok(())
+    }
+
+    #[cfg(not(feature = "mqtt"))]
+    {
+        let _ = (config, state);
+        Err("MQTTへの配信はこのビルドでは無効です（mqttフィーチャーを有効にしてビルドしてください）".to_string())
+    }
+}
+
+/// MQTTへのイベント配信の停止コマンド
+#[tauri::command]
+fn stop_mqtt_sink(state: State<Mutex<AppState>>) -> Result<(), String> {
+    let event_sink = lock(&state).event_sink.clone();
+    let stopped = lock(&event_sink).take();
+    match stopped {
+        Some(_) => Ok(()),
+        None => Err("MQTTへの配信は起動していません".to_string()),
+    }
+}
+
+/// イベントのファイルへの書き出しの開始コマンド（パスが "-" なら標準出力、形式は text・jsonl・csv）
+///
+/// 監視とは独立して動作し、停止コマンドまで書き出す。既に書き出し中なら書き出し先を切り替える。
+#[tauri::command]
+fn pipe_output(
+    path: String,
+    format: String,
+    rotation: Option<FileRotationPolicy>,
+    state: State<Mutex<AppState>>,
+) -> Result<(), String> {
+    let format = OutputFormat::parse(&format).map_err(|e| e.to_string())?;
+    let pipe = EventPipe::open(Path::new(&path), format, rotation.unwrap_or_default())
+        .map_err(|e| format!("書き出しを開始できません: {:#}", e))?;
+    info!("イベントの書き出しを開始します: {}（{:?}）", pipe.destination(), format);
+    let event_pipe = lock(&state).event_pipe.clone();
+    *lock(&event_pipe) = Some(pipe);
+    Ok(())
+}
+
+/// イベントのファイルへの書き出しの停止コマンド
+#[tauri::command]
+fn stop_pipe_output(state: State<Mutex<AppState>>) -> Result<(), String> {
+    let event_pipe = lock(&state).event_pipe.clone();
+    let stopped = lock(&event_pipe).take();
+    match stopped {
+        Some(pipe) => {
+            info!("イベントの書き出しを停止しました: {}", pipe.destination());
+            Ok(())
+        }
+        None => Err("イベントの書き出しは起動していません".to_string()),
+    }
+}
+
+/// 監視の設定の取得コマンド
+#[tauri::command]
+fn get_monitor_config(state: State<Mutex<AppState>>) -> MonitorConfig {
+    state_commands::get_monitor_config(&state)
+}
+
+/// 監視の設定の変更コマンド（監視中でも次のフレームから反映）
+#[tauri::command]
+fn set_monitor_config(config: MonitorConfig, state: State<Mutex<AppState>>) -> Result<SettingChange, String> {
+    state_commands::set_monitor_config(config, &state)
+}
+
+/// キーワード監視の設定の取得コマンド
+#[tauri::command]
+fn get_watchlist_config(state: State<Mutex<AppState>>) -> WatchlistConfig {
+    state_commands::get_watchlist_config(&state)
+}
+
+/// キーワード監視の設定の変更コマンド（監視中でも次のフレームから反映、一致の状態は破棄）
+#[tauri::command]
+fn set_watchlist_config(config: WatchlistConfig, state: State<Mutex<AppState>>) -> Result<SettingChange, String> {
+    state_commands::set_watchlist_config(config, &state)
+}
+
+/// イベントに付けるサムネイルの最大サイズの変更コマンド（監視中でも次のフレームから反映）
+#[tauri::command]
+fn set_thumbnail_size(width: u32, height: u32, state: State<Mutex<AppState>>) -> Result<SettingChange, String> {
+    state_commands::set_thumbnail_size(width, height, &state)
+}
+
+/// イベントの送信先チャンネル名の設定コマンド（監視中は再開始が必要）
+#[tauri::command]
+async fn set_event_channels(
+    channels: EventChannels,
+    apply_and_restart: Option<bool>,
+    state: State<'_, Mutex<AppState>>,
+    window: Window,
+) -> Result<SettingChange, String> {
+    info!("イベントのチャンネル名を変更しました: {:?}", channels);
+    update_session_setting(state, window, apply_and_restart.unwrap_or(false), |app_state| {
+        app_state.event_channels = channels;
+    })
+    .await
+}
+
+/// 差分の設定の取得コマンド
+#[tauri::command]
+fn get_diff_config(state: State<Mutex<AppState>>) -> DiffConfig {
+    lock(&state).diff_config.clone()
+}
+
+/// 差分の設定の変更コマンド（分解パターンは設定時に検証、監視中は再開始が必要）
+#[tauri::command]
+async fn set_diff_config(
+    config: DiffConfig,
+    apply_and_restart: Option<bool>,
+    state: State<'_, Mutex<AppState>>,
+    window: Window,
+) -> Result<SettingChange, String> {
+    config.validate().map_err(|e| e.to_string())?;
+    info!("差分の設定を変更しました: {:?}", config);
+    update_session_setting(state, window, apply_and_restart.unwrap_or(false), |app_state| {
+        app_state.diff_config = config;
+    })
+    .await
+}
+
+/// 変化前後のテキストの差分をHTMLで取得するコマンド（追加は `<ins>`、削除は `<del>`）
+///
+/// 長いテキストの比較は時間がかかるため、専用スレッドで実行する。
+#[tauri::command]
+async fn render_diff(old: String, new: String) -> Result<String, String> {
+    #[cfg(feature = "html_diff")]
+    {
+        tauri::async_runtime::spawn_blocking(move || TextDiffer::render_html_diff(&old, &new))
+            .await
+            .map_err(|e| format!("差分の作成に失敗: {}", e))
+    }
+
+    #[cfg(not(feature = "html_diff"))]
+    {
+        let _ = (old, new);
+        Err("HTMLの差分表示はこのビルドでは無効です（html_diffフィーチャーを有効にしてビルドしてください）".to_string())
+    }
+}
+
+/// イベント履歴の取得コマンド
+#[tauri::command]
+fn get_history(include_info: Option<bool>, state: State<Mutex<AppState>>) -> Vec<HistoryEntry> {
+    state_commands::get_history(include_info, &state)
+}
+
+/// 履歴のページ取得コマンド（長い履歴を少しずつ表示する用）
+#[tauri::command]
+fn get_event_page(page: usize, page_size: usize, filter: EventFilter, state: State<Mutex<AppState>>) -> EventPage {
+    state_commands::get_event_page(page, page_size, filter, &state)
+}
+
+/// 履歴のCSVエクスポートコマンド（区切り文字等を含むフィールドはRFC 4180に従い引用符で囲む）
+#[tauri::command]
+fn export_history_csv(
+    output_path: String,
+    delimiter: char,
+    include_headers: bool,
+    include_diff_only: bool,
+    timestamp_tz: String,
+    state: State<Mutex<AppState>>,
+) -> Result<(), String> {
+    info!("履歴をCSVにエクスポートします: {}", output_path);
+    let options = CsvOptions {
+        delimiter,
+        include_headers,
+        include_diff_only,
+        timestamp_zone: TimestampZone::parse(&timestamp_tz).map_err(|e| e.to_string())?,
+    };
+
+    let (history, summaries) = {
+        let app_state = lock(&state);
+        (app_state.history.clone(), app_state.summaries.clone())
+    };
+    let entries = lock(&history).entries(true);
+    let summaries = lock(&summaries).summaries();
+    export::write_history_csv(&entries, &summaries, &options, std::path::Path::new(&output_path))
+        .map_err(|e| format!("CSVのエクスポートに失敗: {}", e))
+}
+
+/// 履歴のSRTエクスポートコマンド（modeは whole_text でテキスト全体、lines で行ごとの表示期間を字幕にする）
+#[tauri::command]
+fn export_history_srt(output_path: String, mode: String, state: State<Mutex<AppState>>) -> Result<(), String> {
+    info!("履歴をSRTにエクスポートします: {}（{}）", output_path, mode);
+    let mode = SrtMode::parse(&mode).map_err(|e| e.to_string())?;
+    let history = lock(&state).history.clone();
+    let entries = lock(&history).entries(false);
+    export::write_history_srt(&entries, mode, std::path::Path::new(&output_path))
+        .map_err(|e| format!("SRTのエクスポートに失敗: {}", e))
+}
+
+/// 直近の監視セッションの要約の取得コマンド（古い順）
+#[tauri::command]
+fn get_session_summaries(state: State<Mutex<AppState>>) -> Vec<SessionSummary> {
+    state_commands::get_session_summaries(&state)
+}
+
+/// 監視セッションの実行時の設定の取得コマンド（過去のセッションと同じ条件で監視し直す用）
+#[tauri::command]
+fn get_session_config(session_id: u64, state: State<Mutex<AppState>>) -> Result<MonitorSnapshot, String> {
+    state_commands::get_session_config(session_id, &state)
+}
+
+/// 監視セッションのレポートをMarkdownで出力するコマンド（期間はUNIXエポックからのミリ秒）
+#[tauri::command]
+fn generate_session_report(
+    start_ts: u64,
+    end_ts: u64,
+    output_path: String,
+    state: State<Mutex<AppState>>,
+) -> Result<(), String> {
+    info!("監視セッションのレポートを出力します: {}", output_path);
+    let (history, stats, region) = {
+        let app_state = lock(&state);
+        (app_state.history.clone(), app_state.stats.clone(), app_state.selected_region)
+    };
+    let entries = lock(&history).entries(true);
+    let stats = lock(&stats).clone();
+
+    let input = ReportInput {
+        start_ts,
+        end_ts,
+        entries: &entries,
+        stats: &stats,
+        region,
+    };
+    report::write_session_report(&input, std::path::Path::new(&output_path))
+        .map_err(|e| format!("レポートの出力に失敗: {}", e))
+}
+
+/// 行ごとの認識安定度の取得コマンド
+#[tauri::command]
+fn get_line_stability(state: State<Mutex<AppState>>) -> Vec<LineStability> {
+    let tracker = lock(&state).line_stability.clone();
+    let lines = lock(&tracker).snapshot();
+    lines
+}
+
+/// 出現回数の多い行の取得コマンド（監視の開始時にリセット）
+#[tauri::command]
+fn get_text_frequency(top_n: usize, state: State<Mutex<AppState>>) -> Vec<TextFrequencyEntry> {
+    let tracker = lock(&state).text_frequency.clone();
+    let entries = lock(&tracker).top_n(top_n);
+    entries
+}
+
+/// 認識結果の訂正コマンド
+///
+/// 履歴の連番で指定したイベントの認識テキストと訂正後のテキストを比較し、
+/// 文字単位の置換を補正ルールとして学習する。更新されたルールを返す。
+#[tauri::command]
+fn correct_text(sequence: u64, corrected_text: String, state: State<Mutex<AppState>>) -> Result<Vec<CorrectionRule>, String> {
+    let app_state = lock(&state);
+    let recognized = lock(&app_state.history)
+        .get(sequence)
+        .and_then(|entry| entry.event.recognized_text().map(str::to_string))
+        .ok_or_else(|| format!("訂正できるイベントが見つかりません: sequence={}", sequence))?;
+
+    let updated = lock(&app_state.corrections)
+        .learn(&recognized, &corrected_text)
+        .map_err(|e| e.to_string())?;
+    info!("訂正から{}件の補正ルールを学習しました", updated.len());
+
+    app_state.save_corrections().map_err(|e| e.to_string())?;
+    Ok(updated)
+}
+
+/// 学習済みの補正ルールの取得コマンド
+#[tauri::command]
+fn get_learned_corrections(state: State<Mutex<AppState>>) -> Vec<CorrectionRule> {
+    let corrections = lock(&state).corrections.clone();
+    let rules = lock(&corrections).rules().to_vec();
+    rules
+}
+
+/// 学習済みの補正ルールの削除コマンド
+#[tauri::command]
+fn delete_learned_correction(id: u64, state: State<Mutex<AppState>>) -> Result<(), String> {
+    let app_state = lock(&state);
+    if !lock(&app_state.corrections).remove(id) {
+        return Err(format!("補正ルールが見つかりません: id={}", id));
+    }
+    app_state.save_corrections().map_err(|e| e.to_string())
+}
+
+/// 補正の設定の取得コマンド
+#[tauri::command]
+fn get_correction_config(state: State<Mutex<AppState>>) -> CorrectionConfig {
+    let corrections = lock(&state).corrections.clone();
+    let config = lock(&corrections).config.clone();
+    config
+}
+
+/// 補正の設定の変更コマンド（監視中でも次の認識から反映）
+#[tauri::command]
+fn set_correction_config(config: CorrectionConfig, state: State<Mutex<AppState>>) -> Result<(), String> {
+    config.validate().map_err(|e| e.to_string())?;
+    info!("補正の設定を変更しました: {:?}", config);
+    let app_state = lock(&state);
+    lock(&app_state.corrections).set_config(config);
+    app_state.save_corrections().map_err(|e| e.to_string())
+}
+
+/// ocr:// のURLで指定した領域と言語で監視を開始
+///
+/// 言語は監視の開始時に読まれるため先に設定し、開始できなかった場合は元の言語に戻す
+/// （開けなかったリンクで以降の認識の言語が変わらないようにする）。
+fn open_monitor_link(app: &tauri::AppHandle, url: &str) -> Result<CaptureRegion, String> {
+    let link = deep_link::parse_monitor_url(url)?;
+    let window = app.get_window("main").ok_or_else(|| "メインウィンドウが見つかりません".to_string())?;
+    let state = app.state::<Mutex<AppState>>();
+    let previous_language = match link.language {
+        Some(language) => {
+            let mut app_state = lock(&state);
+            if app_state.phase != MonitorPhase::Idle {
+                return Err("監視中などのため開始できません（監視を停止してから開いてください）".to_string());
+            }
+            Some(app_state.ocr_language.replace(language))
+        }
+        None => None,
+    };
+    if let Err(e) = start_monitoring(link.region, None, app.state::<Mutex<AppState>>(), window) {
+        if let Some(previous_language) = previous_language {
+            lock(&state).ocr_language = previous_language;
+        }
+        return Err(e.to_string());
+    }
+    Ok(link.region)
+}
+
+/// 起動中のプロセスに転送されたURLで監視を開始し、メインウィンドウを前面に出す
+fn open_forwarded_link(app: &tauri::AppHandle, url: &str) {
+    if let Some(window) = app.get_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+    if url.is_empty() {
+        return;
+    }
+    match open_monitor_link(app, url) {
+        Ok(region) => info!("転送されたURLで指定した領域の監視を開始しました: {:?}", region),
+        Err(e) => log::error!("転送されたURLから監視を開始できません: {}: {}", url, e),
+    }
+}
+
+/// メトリクスサーバーのポートを取得（`--metrics-port <port>` または環境変数で指定）
+fn metrics_port() -> Option<u16> {
+    let args: Vec<String> = std::env::args().collect();
+    let from_args = args
+        .windows(2)
+        .find(|pair| pair[0] == "--metrics-port")
+        .map(|pair| pair[1].clone());
+
+    let value = from_args.or_else(|| std::env::var("SCREEN_TEXT_MONITOR_METRICS_PORT").ok())?;
+    match value.parse() {
+        Ok(port) => Some(port),
+        Err(_) => {
+            log::warn!("メトリクスのポート指定が不正です: {}", value);
+            None
+        }
+    }
+}
+
+fn main() {
+    // ログの初期化（デバッグバンドル用に直近のログも保持する）
+    log_buffer::init();
+
+    // サブコマンドが指定されていればGUIを起動せずに実行
+    if let Some(exit_code) = cli::run(&std::env::args().collect::<Vec<_>>()) {
+        std::process::exit(exit_code);
+    }
+    // CLIのサブコマンドは1回の処理で終わるため既定の動作のまま終了し、GUIでは監視を終えてから終了する
+    shutdown::install();
+
+    // 既に起動していれば --url をそのプロセスに渡して終了する
+    let url_argument = deep_link::url_argument(&std::env::args().collect::<Vec<_>>());
+    let instance_listener = match single_instance::acquire(url_argument.as_deref()) {
+        single_instance::Instance::Primary(listener) => Some(listener),
+        single_instance::Instance::Forwarded => {
+            info!("起動中のプロセスに引数を渡して終了します");
+            return;
+        }
+        single_instance::Instance::Unavailable => None,
+    };
+
+    info!("Tauri版画面テキスト監視システムを起動しています...");
+    
+    let app = tauri::Builder::default()
+        .manage(Mutex::new(AppState::default()))
+        // アプリ内のリンク（ocr://monitor?x=..&y=..&w=..&h=..）から監視を開始する
+        .register_uri_scheme_protocol(deep_link::URI_SCHEME, |app, request| {
+            let header = |name: &str| request.headers().get(name).and_then(|value| value.to_str().ok());
+            let navigation = deep_link::is_explicit_navigation(request.method().as_str(), header("sec-fetch-dest"), header("accept"));
+            let result = if navigation {
+                open_monitor_link(app, request.uri())
+            } else {
+                // 画像などの埋め込みの読み込みでは監視を始めない
+                Err("リンクを開いた場合のみ監視を開始します".to_string())
+            };
+            let (status, page) = match result {
+                Ok(region) => {
+                    let message = format!("{}x{} の領域（{}, {}）の監視を開始しました", region.width, region.height, region.x, region.y);
+                    (200, deep_link::result_page("監視を開始しました", &message))
+                }
+                Err(e) => {
+                    log::warn!("URLから監視を開始できません: {}: {}", request.uri(), e);
+                    (400, deep_link::result_page("監視を開始できません", &e))
+                }
+            };
+            tauri::http::ResponseBuilder::new()
+                .status(status)
+                .mimetype("text/html")
+                .body(page.into_bytes())
+        })
+        .setup(move |app| {
+            let app_state_handle = app.state::<Mutex<AppState>>();
+            let mut app_state = lock(&app_state_handle);
+
+            // アプリのデータディレクトリ配下を言語データの保存先とする
+            let app_data_dir = app.path_resolver().app_data_dir();
+            let tessdata_dir = app_data_dir.as_ref().map(|dir| dir.join("tessdata"));
+            info!("言語データのディレクトリ: {:?}", tessdata_dir);
+            app_state.tessdata_dir = tessdata_dir;
+
+            // 学習済みの補正を読み込む
+            app_state.debug_bundle_dir = app_data_dir.as_ref().map(|dir| dir.join("debug_bundles"));
+            let corrections_file = app_data_dir.map(|dir| dir.join("corrections.json"));
+            if let Some(path) = &corrections_file {
+                match CorrectionTable::load(path) {
+                    Ok(table) => *lock(&app_state.corrections) = table,
+                    Err(e) => log::warn!("学習済みの補正を読み込めませんでした: {}", e),
+                }
+            }
+            app_state.corrections_file = corrections_file;
+
+            // 指定があればメトリクスサーバーを起動（127.0.0.1のみで待ち受け）
+            if let Some(port) = metrics_port() {
+                match MetricsServer::start(port, app_state.stats.clone()) {
+                    Ok(server) => app_state.metrics_server = Some(server),
+                    Err(e) => log::error!("メトリクスサーバーの起動に失敗: {}", e),
+                }
+            }
+            drop(app_state);
+
+            // 終了シグナルを受け取ったら監視を通常の停止と同じ手順で終えてから終了する
+            exit_on_termination(app.handle());
+
+            // 後から起動したプロセスが渡したURLは、このプロセスで開く
+            if let Some(listener) = instance_listener {
+                let handle = app.handle();
+                single_instance::listen(listener, move |url| open_forwarded_link(&handle, &url));
+            }
+
+            // 起動時に --url が指定されていれば、その領域の監視を開始
+            if let Some(url) = url_argument {
+                match open_monitor_link(&app.handle(), &url) {
+                    Ok(region) => info!("URLで指定した領域の監視を開始しました: {:?}", region),
+                    Err(e) => log::error!("URLから監視を開始できません: {}: {}", url, e),
+                }
+            }
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            list_screens,
+            select_region,
+            start_monitoring,
+            validate_monitoring,
+            stop_monitoring,
+            get_status,
+            calibrate_ocr,
+            get_history,
+            get_event_page,
+            export_history_csv,
+            export_history_srt,
+            generate_session_report,
+            get_session_summaries,
+            get_session_config,
+            set_skip_auto_download,
+            download_language_data,
+            check_ocr_available,
+            wizard_environment,
+            wizard_test,
+            trace_pipeline,
+            auto_tune,
+            compare_regions,
+            assert_text,
+            assert_no_text_change,
+            take_snapshot_at,
+            calibrate_homography,
+            pick_dominant_colors,
+            pick_text_background_pair,
+            reload_ocr_engine,
+            apply_suggested_language,
+            set_reference_text,
+            clear_reference_text,
+            set_debug_pipeline,
+            get_line_stability,
+            get_text_frequency,
+            get_selector_config,
+            set_selector_config,
+            get_capture_config,
+            set_capture_config,
+            inspect_capture_format,
+            get_ocr_config,
+            set_ocr_config,
+            add_user_pattern,
+            remove_user_pattern,
+            set_tile_config,
+            set_evidence_config,
+            get_process_guard_config,
+            set_process_guard_config,
+            get_event_image,
+            get_change_visual,
+            get_stats,
+            get_ocr_stats,
+            reset_ocr_stats,
+            get_preprocess_timings,
+            get_last_image_metrics,
+            create_debug_bundle,
+            start_rest_server,
+            get_text_server_config,
+            set_text_server_config,
+            start_text_server,
+            stop_text_server,
+            start_mqtt_sink,
+            stop_mqtt_sink,
+            pipe_output,
+            stop_pipe_output,
+            set_event_channels,
+            get_diff_config,
+            set_diff_config,
+            render_diff,
+            get_monitor_config,
+            set_monitor_config,
+            set_thumbnail_size,
+            get_watchlist_config,
+            set_watchlist_config,
+            correct_text,
+            get_learned_corrections,
+            delete_learned_correction,
+            get_correction_config,
+            set_correction_config
+        ])
+        .build(tauri::generate_context!())
+        .expect("Tauriアプリケーションの起動エラー");
+
+    app.run(|app_handle, event| {
+        // 終了時にメトリクスサーバーを停止
+        if let tauri::RunEvent::Exit = event {
+            let server = lock(&app_handle.state::<Mutex<AppState>>()).metrics_server.take();
+            if let Some(mut server) = server {
+                server.stop();
+            }
+        }
+    });
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loupe_request(x: i32, y: i32, viewport: Option<(u32, u32)>) -> LoupeRequest {
+        LoupeRequest {
+            x,
+            y,
+            viewport_width: viewport.map(|(width, _)| width),
+            viewport_height: viewport.map(|(_, height)| height),
+        }
+    }
+
+    #[test]
+    fn loupe_position_is_scaled_to_the_snapshot_pixels() {
+        // 2倍の画面では選択画面の座標の2倍の画素を中心にする
+        assert_eq!(loupe_request(100, 50, Some((1280, 720))).snapshot_position(2560, 1440), (200, 100));
+        assert_eq!(loupe_request(100, 50, Some((1280, 720))).snapshot_position(1280, 720), (100, 50));
+        // 大きさの無い要求は画素の座標とみなす
+        assert_eq!(loupe_request(100, 50, None).snapshot_position(2560, 1440), (100, 50));
+        assert_eq!(loupe_request(100, 50, Some((0, 0))).snapshot_position(2560, 1440), (100, 50));
+    }
+}
@@ -0,0 +1,142 @@
+// 監視で保持するデータのメモリ使用量の集計と上限
+//
+// 長時間の監視で履歴・画像・集計が積み上がっても際限なく増えないよう、
+// 各構造体がおおよそのバイト数を報告し、合計が上限を超えたら同じ割合まで削る。
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// 集計の対象: イベントの履歴
+pub const COMPONENT_HISTORY: &str = "history";
+
+/// 集計の対象: イベントの元になった画像
+pub const COMPONENT_EVIDENCE: &str = "evidence";
+
+/// 集計の対象: 行ごとの出現回数
+pub const COMPONENT_TEXT_FREQUENCY: &str = "text_frequency";
+
+/// おおよそのメモリ使用量を報告し、指定した量まで削れる構造体
+pub trait MemoryAccounted {
+    /// おおよその使用量（バイト）
+    fn approximate_bytes(&self) -> usize;
+
+    /// 使用量が target_bytes 以下になるまで古い（重要度の低い）ものから破棄
+    fn trim_to(&mut self, target_bytes: usize);
+}
+
+/// メモリ使用量の集計結果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MemoryUsage {
+    /// 対象ごとのおおよその使用量（バイト）
+    pub components: BTreeMap<String, u64>,
+    /// 合計（バイト）
+    pub total_bytes: u64,
+    /// 削り始める上限（バイト、0なら上限なし）
+    pub soft_limit_bytes: u64,
+    /// 上限を超えて削った回数
+    pub trims: u64,
+}
+
+impl MemoryUsage {
+    /// 対象ごとの使用量を集計し、上限を超えていれば各対象を同じ割合まで削る
+    ///
+    /// trimsは前回の集計結果から引き継ぐ。
+    pub fn account(&mut self, soft_limit_bytes: u64, components: &mut [(&str, &mut dyn MemoryAccounted)]) {
+        let mut usage: Vec<usize> = components.iter().map(|(_, component)| component.approximate_bytes()).collect();
+        let total: usize = usage.iter().sum();
+
+        if soft_limit_bytes > 0 && total as u64 > soft_limit_bytes {
+            let ratio = soft_limit_bytes as f64 / total as f64;
+            log::warn!("メモリ使用量が上限を超えたため削ります: {} > {}バイト", total, soft_limit_bytes);
+            for ((_, component), bytes) in components.iter_mut().zip(usage.iter_mut()) {
+                component.trim_to((*bytes as f64 * ratio) as usize);
+                *bytes = component.approximate_bytes();
+            }
+            self.trims += 1;
+        }
+
+        self.components = components
+            .iter()
+            .zip(&usage)
+            .map(|((name, _), bytes)| (name.to_string(), *bytes as u64))
+            .collect();
+        self.total_bytes = usage.iter().sum::<usize>() as u64;
+        self.soft_limit_bytes = soft_limit_bytes;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SessionClock;
+    use crate::events::{EventHistory, HistoryEntry, TextChangeEvent};
+    use crate::evidence::{EvidenceCache, EvidenceConfig};
+    use crate::monitor::TextFrequencyTracker;
+    use image::{DynamicImage, RgbImage};
+
+    /// 監視のティックごとと同じ順に3つの対象を集計する
+    fn account(
+        usage: &mut MemoryUsage,
+        soft_limit_bytes: u64,
+        history: &mut EventHistory,
+        evidence: &mut EvidenceCache,
+        text_frequency: &mut TextFrequencyTracker,
+    ) {
+        let mut components: [(&str, &mut dyn MemoryAccounted); 3] = [
+            (COMPONENT_HISTORY, history),
+            (COMPONENT_EVIDENCE, evidence),
+            (COMPONENT_TEXT_FREQUENCY, text_frequency),
+        ];
+        usage.account(soft_limit_bytes, &mut components);
+    }
+
+    #[test]
+    fn usage_under_the_limit_is_not_trimmed() {
+        let clock = SessionClock::start();
+        let mut history = EventHistory::default();
+        history.push(TextChangeEvent::NewText { text: "a".repeat(100) }, &clock);
+        let mut usage = MemoryUsage::default();
+        account(&mut usage, 0, &mut history, &mut EvidenceCache::default(), &mut TextFrequencyTracker::default());
+
+        assert_eq!(usage.trims, 0);
+        // 履歴の本文はテキストの長さで見積もる
+        let entry_bytes = std::mem::size_of::<HistoryEntry>() + 100;
+        assert_eq!(usage.total_bytes, entry_bytes as u64);
+        assert_eq!(usage.components[COMPONENT_EVIDENCE], 0);
+        assert_eq!(history.entries(true).len(), 1);
+    }
+
+    #[test]
+    fn long_session_stays_under_the_soft_limit() {
+        const SOFT_LIMIT_BYTES: u64 = 256 * 1024;
+        let clock = SessionClock::start();
+        let config = EvidenceConfig { enabled: true, ..EvidenceConfig::default() };
+        let image = DynamicImage::ImageRgb8(RgbImage::new(64, 64));
+        let mut history = EventHistory::default();
+        let mut evidence = EvidenceCache::default();
+        let mut text_frequency = TextFrequencyTracker::default();
+        let mut usage = MemoryUsage::default();
+
+        for tick in 0..20_000u64 {
+            // 毎回違う行が現れ続け、出現回数も積み上がる
+            let text = format!("{}行目: {}", tick, "テキスト".repeat((tick % 40) as usize));
+            text_frequency.record(&text);
+            let sequence = history.push(TextChangeEvent::NewText { text }, &clock);
+            if tick % 10 == 0 {
+                evidence.record(&config, vec![sequence], &image);
+            }
+            account(&mut usage, SOFT_LIMIT_BYTES, &mut history, &mut evidence, &mut text_frequency);
+
+            assert!(usage.total_bytes <= SOFT_LIMIT_BYTES, "{}: {:?}", tick, usage);
+            assert_eq!(usage.total_bytes, usage.components.values().sum::<u64>());
+            // 削っても最新のイベントは残る
+            assert!(history.get(sequence).is_some());
+        }
+
+        assert!(usage.trims > 0);
+        assert_eq!(usage.components.len(), 3);
+        assert!(usage.components.values().all(|&bytes| bytes > 0), "{:?}", usage);
+        assert_eq!(usage.components[COMPONENT_HISTORY], history.approximate_bytes() as u64);
+        assert_eq!(usage.components[COMPONENT_EVIDENCE], evidence.approximate_bytes() as u64);
+        assert_eq!(usage.components[COMPONENT_TEXT_FREQUENCY], text_frequency.approximate_bytes() as u64);
+    }
+}
@@ -0,0 +1,131 @@
+// ScreenMonitorのイベント送信前に処理を差し込むミドルウェア
+use crate::monitor::TextChangeEvent;
+
+/// イベントの送信時に渡される認識結果の情報
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EmitContext {
+    /// イベントの元になった認識の信頼度（0.0-1.0、エラー等で無い場合はNone）
+    pub confidence: Option<f32>,
+}
+
+/// イベントの送信前に呼ばれる処理（ログ出力、内容の変換、抑制など）
+pub trait EventMiddleware {
+    /// イベントを変更でき、falseを返すとそのイベントは送信されない
+    fn before_emit(&self, event: &mut TextChangeEvent, context: &EmitContext) -> bool;
+}
+
+/// 登録したミドルウェアの識別子（削除時に指定する）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MiddlewareId(u64);
+
+/// 登録順にミドルウェアを実行する
+#[derive(Default)]
+pub struct MiddlewareChain {
+    middlewares: Vec<(MiddlewareId, Box<dyn EventMiddleware + Send + Sync>)>,
+    next_id: u64,
+}
+
+impl MiddlewareChain {
+    /// ミドルウェアを末尾に追加
+    pub fn add(&mut self, middleware: Box<dyn EventMiddleware + Send + Sync>) -> MiddlewareId {
+        let id = MiddlewareId(self.next_id);
+        self.next_id += 1;
+        self.middlewares.push((id, middleware));
+        id
+    }
+
+    /// ミドルウェアを削除（存在しなければfalse）
+    pub fn remove(&mut self, id: MiddlewareId) -> bool {
+        let before = self.middlewares.len();
+        self.middlewares.retain(|(registered, _)| *registered != id);
+        self.middlewares.len() != before
+    }
+
+    /// 登録順に実行し、いずれかが抑制した場合はfalseを返す（以降のミドルウェアは実行しない）
+    pub fn run(&self, event: &mut TextChangeEvent, context: &EmitContext) -> bool {
+        self.middlewares
+            .iter()
+            .all(|(_, middleware)| middleware.before_emit(event, context))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    use crate::locking::lock;
+
+    /// 呼ばれた順に名前と受け取ったテキスト・信頼度を記録し、テキストに名前を付け足すミドルウェア
+    struct Recorder {
+        name: &'static str,
+        calls: Arc<Mutex<Vec<String>>>,
+        pass: bool,
+    }
+
+    impl EventMiddleware for Recorder {
+        fn before_emit(&self, event: &mut TextChangeEvent, context: &EmitContext) -> bool {
+            if let TextChangeEvent::NewText(text) = event {
+                lock(&self.calls).push(format!("{}:{}:{:?}", self.name, text, context.confidence));
+                text.push_str(self.name);
+            }
+            self.pass
+        }
+    }
+
+    fn recorder(name: &'static str, calls: &Arc<Mutex<Vec<String>>>, pass: bool) -> Box<Recorder> {
+        Box::new(Recorder { name, calls: Arc::clone(calls), pass })
+    }
+
+    #[test]
+    fn runs_middlewares_in_registration_order() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let mut chain = MiddlewareChain::default();
+        chain.add(recorder("a", &calls, true));
+        chain.add(recorder("b", &calls, true));
+        chain.add(recorder("c", &calls, true));
+
+        let mut event = TextChangeEvent::NewText("x".to_string());
+        assert!(chain.run(&mut event, &EmitContext { confidence: Some(0.5) }));
+        assert_eq!(*lock(&calls), vec!["a:x:Some(0.5)", "b:xa:Some(0.5)", "c:xab:Some(0.5)"]);
+        assert!(matches!(event, TextChangeEvent::NewText(text) if text == "xabc"));
+    }
+
+    #[test]
+    fn suppression_stops_the_remaining_middlewares() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let mut chain = MiddlewareChain::default();
+        chain.add(recorder("a", &calls, true));
+        chain.add(recorder("b", &calls, false));
+        chain.add(recorder("c", &calls, true));
+
+        let mut event = TextChangeEvent::NewText("x".to_string());
+        assert!(!chain.run(&mut event, &EmitContext::default()));
+        assert_eq!(*lock(&calls), vec!["a:x:None", "b:xa:None"]);
+    }
+
+    #[test]
+    fn removed_middleware_is_no_longer_run() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let mut chain = MiddlewareChain::default();
+        let first = chain.add(recorder("a", &calls, false));
+        let second = chain.add(recorder("b", &calls, true));
+        assert_ne!(first, second);
+
+        assert!(chain.remove(first));
+        assert!(!chain.remove(first));
+        let mut event = TextChangeEvent::NewText("x".to_string());
+        assert!(chain.run(&mut event, &EmitContext::default()));
+        assert_eq!(*lock(&calls), vec!["b:x:None"]);
+
+        // 削除後に追加しても識別子は再利用しない
+        let third = chain.add(recorder("c", &calls, true));
+        assert_ne!(third, first);
+    }
+
+    #[test]
+    fn empty_chain_passes_every_event() {
+        let mut event = TextChangeEvent::Error("失敗".to_string());
+        assert!(MiddlewareChain::default().run(&mut event, &EmitContext::default()));
+    }
+}
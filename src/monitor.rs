@@ -1,13 +1,355 @@
 // テキスト変化の監視機能の実装
-use anyhow::Result;
-use std::sync::Arc;
+use anyhow::{bail, Result};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use regex::Regex;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use tokio::sync::{mpsc, RwLock};
 use tokio::time::{interval, Duration};
 
-use crate::capture::{ScreenCapture, CaptureRegion};
-use crate::ocr::OcrEngine;
+use crate::capture::{CaptureSource, LiveScreenSource, ScreenCapture, CaptureRegion};
+use crate::clock::SessionClock;
+use crate::contrast_probe::ContrastProber;
+use crate::events::{self, now_millis};
+use crate::hooks::{HookChain, MonitorError, MonitorHooks};
+use crate::locking::lock;
+use crate::memory::MemoryAccounted;
+use crate::middleware::{EmitContext, EventMiddleware, MiddlewareChain, MiddlewareId};
+use crate::ocr::{encode_png_base64, OcrConfig, OcrEngine, OcrEnginePool, OcrLine, OcrResult};
+use crate::ocr_stats;
+use crate::pipe_output::{write_to_pipe, EventPipe, FileRotationPolicy, OutputFormat, PipeRecord, SharedEventPipe};
+use crate::preprocessing::{FrameAnalysis, ImageHasher, ImageMetrics};
+use crate::transform::CaptureTransform;
+use crate::validation::{Validate, Validator};
+
+/// サムネイルの最大の幅
+pub const MAX_THUMBNAIL_WIDTH: u32 = 320;
+
+/// サムネイルの最大の高さ
+pub const MAX_THUMBNAIL_HEIGHT: u32 = 240;
+
+/// watch_for などで監視のイベントを受け取るチャンネルの容量
+const WATCH_CHANNEL_CAPACITY: usize = 32;
+
+/// 段組みの列として分割できる最大の数
+pub const MAX_COLUMN_SPLIT: u32 = 8;
+
+/// take_snapshot_at で指定できる、現在からの最大の待ち時間（ミリ秒）
+pub const MAX_SNAPSHOT_DELAY_MS: u64 = 3_600_000;
+
+/// 停止ファイルのパスを指定する環境変数（設定で指定されていない場合に使う）
+pub const KILL_SWITCH_ENV: &str = "SCREEN_TEXT_MONITOR_KILL_SWITCH";
+
+/// 監視の設定
+///
+/// 監視中に変更しても次のフレームから反映される
+/// （pool_size のみ監視の開始時に参照する）。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MonitorConfig {
+    /// キャプチャの間隔（ミリ秒）
+    pub interval_ms: u64,
+    /// 前回OCRしたフレームとのdHashの距離がこれ未満ならOCRを省略（0で無効）
+    pub dhash_skip_threshold: u32,
+    /// 1フレームの前処理がこれを超えたら警告を通知（ミリ秒、0で無効）
+    pub slow_preprocess_threshold_ms: u64,
+    /// 送信レートの制限で保留したイベントをまとめて送信する最大件数
+    pub max_batch_size: usize,
+    /// 複数の領域で共有するOCRエンジンの最大数
+    pub pool_size: usize,
+    /// このファイルが存在したら監視を停止する（管理者が無人の端末の監視を止める用）
+    pub kill_switch_path: Option<PathBuf>,
+    /// 1フレームの時間の予算（キャプチャの間隔に対する倍率）
+    ///
+    /// 予算を使い切った後は任意の処理（2回目以降の認識試行など）を省略する。
+    pub tick_budget_multiplier: f32,
+    /// 認識結果が空でも、輝度の分散がこれ以上なら読み取れないフレームとみなす条件の1つ
+    pub unreadable_min_variance: f32,
+    /// 認識結果が空でも、前景の成分がこれ以上なら読み取れないフレームとみなす条件の1つ（0で無効）
+    ///
+    /// 読み取れないフレーム（アニメーション等で一時的に隠れた状態）ではテキストのクリアを通知しない。
+    pub unreadable_min_components: usize,
+    /// 新規・変更イベントにキャプチャした領域のサムネイルを付けるかどうか
+    /// （有効時は新規・変更イベントの代わりにサムネイル付きの変更イベントを送信する）
+    pub attach_thumbnail: bool,
+    /// サムネイルの最大の幅（縦横比は保つ）
+    pub thumbnail_width: u32,
+    /// サムネイルの最大の高さ
+    pub thumbnail_height: u32,
+    /// テキストのクリア後、この回数のキャプチャ以内に新しいテキストが現れたら
+    /// クリアと新規を1つの置き換えイベントにまとめる（0ならまとめない）
+    pub coalesce_clear_ticks: u32,
+    /// 履歴・画像・集計のメモリ使用量の合計の上限（MB、超えたら同じ割合まで削る、0なら上限なし）
+    pub memory_soft_limit_mb: u64,
+    /// 認識結果の文字種が認識言語と合わない状態がこの回数続いたら言語を提案する（0で無効）
+    pub script_check_ticks: u32,
+    /// 文字種が合わないとみなす、言語の文字種以外の1つの文字種が文字全体に占める割合
+    pub script_mismatch_ratio: f32,
+    /// 領域のモニターの解像度・拡大率の変更を確認する間隔（ミリ秒、0なら確認しない）
+    pub display_check_interval_ms: u64,
+    /// 解像度・拡大率が変わった場合に領域を比例で移して監視を続けるかどうか
+    /// （無効時は領域の選択し直しを求めて監視を終了する）
+    pub remap_on_display_change: bool,
+    /// 領域を同じ幅の縦の列に分割して列ごとに認識する（段組みのレイアウト向け、Noneなら分割しない）
+    ///
+    /// 認識結果は同じ行の列のテキストをタブでつなぐ。指定時はタイル単位の変化検出を使わない。
+    pub column_split: Option<u32>,
+    /// 文字の占める割合の平均がこれ未満なら領域を狭めるよう一度だけ提案する（0.0-1.0）
+    pub low_coverage_threshold: f32,
+    /// 提案の判定に必要な計測したフレームの数（0で提案しない）
+    pub low_coverage_min_samples: u32,
+    /// 行ごとに現れた時刻と消えた時刻を追跡し、行の表示・消去のイベントとして履歴に記録するかどうか
+    /// （行ごとの字幕のSRTのエクスポート用）
+    pub track_line_lifetimes: bool,
+    /// マウスカーソルが領域の中にある間だけキャプチャ・認識するかどうか
+    /// （カーソルの位置を取得できない環境では指定できない。監視中に一時的に取得できない間は常に監視する）
+    pub monitor_on_hover: bool,
+}
+
+impl Default for MonitorConfig {
+    fn default() -> Self {
+        Self {
+            interval_ms: 500,
+            // 4ビット未満の違いはほぼ同一のフレームとみなす
+            dhash_skip_threshold: 4,
+            slow_preprocess_threshold_ms: 500,
+            max_batch_size: 50,
+            pool_size: 1,
+            kill_switch_path: None,
+            tick_budget_multiplier: 1.0,
+            unreadable_min_variance: 400.0,
+            unreadable_min_components: 8,
+            attach_thumbnail: false,
+            thumbnail_width: 160,
+            thumbnail_height: 120,
+            coalesce_clear_ticks: 0,
+            memory_soft_limit_mb: 256,
+            script_check_ticks: 5,
+            script_mismatch_ratio: 0.8,
+            display_check_interval_ms: 5_000,
+            remap_on_display_change: false,
+            column_split: None,
+            low_coverage_threshold: 0.1,
+            low_coverage_min_samples: 20,
+            track_line_lifetimes: false,
+            monitor_on_hover: false,
+        }
+    }
+}
+
+impl MonitorConfig {
+    /// 1フレームの時間の予算
+    pub fn tick_budget(&self) -> Duration {
+        Duration::from_secs_f64(self.interval_ms as f64 * self.tick_budget_multiplier as f64 / 1000.0)
+    }
+
+    /// 停止ファイルが存在するかどうか（設定・環境変数のどちらも無ければ確認しない）
+    pub fn kill_switch_triggered(&self, env_path: Option<&Path>) -> bool {
+        match self.kill_switch_path.as_deref().or(env_path) {
+            Some(path) => path.exists(),
+            None => false,
+        }
+    }
+
+    /// イベントに付けるサムネイル（PNGのBase64、無効時や変換に失敗した場合はNone）
+    pub fn thumbnail(&self, image: &DynamicImage) -> Option<String> {
+        if !self.attach_thumbnail {
+            return None;
+        }
+        let thumbnail = image.thumbnail(self.thumbnail_width, self.thumbnail_height);
+        match encode_png_base64(&thumbnail) {
+            Ok(encoded) => Some(encoded),
+            Err(e) => {
+                log::warn!("サムネイルの作成に失敗しました: {}", e);
+                None
+            }
+        }
+    }
+
+    /// 認識結果が空のフレームが、空白ではなく読み取れない内容を含むかどうか
+    pub fn is_unreadable_frame(&self, image: &DynamicImage) -> bool {
+        if self.unreadable_min_components == 0 {
+            return false;
+        }
+        let analysis = FrameAnalysis::analyze(image);
+        log::debug!("認識結果が空のフレームの解析: {:?}", analysis);
+        analysis.variance >= self.unreadable_min_variance
+            && analysis.foreground_components >= self.unreadable_min_components
+    }
+
+    /// 前回OCRしたフレームのハッシュと比較し、OCRを省略すべきかどうかを判定
+    ///
+    /// OCRを行う場合は現在のハッシュを記録する。省略した場合は記録しないため、
+    /// 少しずつ変化して累積した差分も検出できる。
+    pub fn should_skip_frame(&self, last_hash: &mut Option<u64>, image: &DynamicImage) -> bool {
+        if self.dhash_skip_threshold == 0 {
+            return false;
+        }
+
+        let current_hash = ImageHasher::dhash(image);
+        if let Some(previous) = *last_hash {
+            let distance = ImageHasher::hamming_distance(previous, current_hash);
+            if distance < self.dhash_skip_threshold {
+                log::debug!("類似フレームのためOCRを省略しました（距離: {}）", distance);
+                return true;
+            }
+        }
+
+        *last_hash = Some(current_hash);
+        false
+    }
+}
+
+impl Validate for MonitorConfig {
+    const PREFIX: &'static str = "monitor";
+
+    fn check(&self, validator: &mut Validator) {
+        validator.range("interval_ms", self.interval_ms, 100, 60_000);
+        validator.range("dhash_skip_threshold", self.dhash_skip_threshold, 0, 64);
+        validator.range("slow_preprocess_threshold_ms", self.slow_preprocess_threshold_ms, 0, 60_000);
+        validator.range("max_batch_size", self.max_batch_size, 1, 500);
+        validator.range("pool_size", self.pool_size, 1, 16);
+        validator.range("tick_budget_multiplier", self.tick_budget_multiplier, 0.1, 10.0);
+        validator.range("unreadable_min_variance", self.unreadable_min_variance, 0.0, 16_384.0);
+        validator.range("unreadable_min_components", self.unreadable_min_components, 0, 10_000);
+        validator.range("thumbnail_width", self.thumbnail_width, 16, MAX_THUMBNAIL_WIDTH);
+        validator.range("thumbnail_height", self.thumbnail_height, 16, MAX_THUMBNAIL_HEIGHT);
+        validator.range("coalesce_clear_ticks", self.coalesce_clear_ticks, 0, 100);
+        validator.range("memory_soft_limit_mb", self.memory_soft_limit_mb, 0, 16_384);
+        validator.range("script_check_ticks", self.script_check_ticks, 0, 100);
+        validator.range("script_mismatch_ratio", self.script_mismatch_ratio, 0.5, 1.0);
+        validator.range("display_check_interval_ms", self.display_check_interval_ms, 0, 600_000);
+        validator.range("low_coverage_threshold", self.low_coverage_threshold, 0.0, 1.0);
+        validator.range("low_coverage_min_samples", self.low_coverage_min_samples, 0, 10_000);
+        if let Some(columns) = self.column_split {
+            validator.range("column_split", columns, 2, MAX_COLUMN_SPLIT);
+        }
+        if self.monitor_on_hover && !ScreenCapture::cursor_position_supported() {
+            validator.invalid("monitor_on_hover", "この環境ではカーソルの位置を取得できません（Windows、macOS、LinuxのX11のセッションで使えます）");
+        }
+    }
+}
+
+/// 監視セッションの実行時の設定（過去のセッションと同じ条件を再現する用）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonitorSnapshot {
+    /// 監視セッションの識別子（ScreenMonitor単体の場合は0）
+    #[serde(default)]
+    pub session_id: u64,
+    /// 監視領域
+    pub region: CaptureRegion,
+    /// 認識言語
+    pub language: String,
+    /// キャプチャの間隔（ミリ秒）
+    pub interval_ms: u64,
+    /// OCRの設定
+    pub ocr_config: OcrConfig,
+    /// 監視の設定
+    pub monitor_config: MonitorConfig,
+    /// 言語データのディレクトリ（Noneの場合はTesseractの既定パス）
+    pub tessdata_dir: Option<PathBuf>,
+    /// 監視の開始時刻（UNIXエポックからのミリ秒）
+    pub started_at: u64,
+    /// 設定を記録した時刻（UNIXエポックからのミリ秒）
+    pub snapshot_at: u64,
+}
+
+/// スレッド間で共有する監視の設定
+pub type SharedMonitorConfig = Arc<Mutex<MonitorConfig>>;
+
+/// 行ごとの出現回数
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TextFrequencyEntry {
+    /// 認識された行
+    pub line: String,
+    /// 新規・変更イベントのテキストに含まれていた回数
+    pub count: u64,
+}
+
+/// 認識された行ごとの出現回数（繰り返し現れるメッセージやUIの要素の把握用）
+#[derive(Debug, Default)]
+pub struct TextFrequencyTracker {
+    counts: HashMap<String, u64>,
+    total_events: u64,
+    /// 記録している行のおおよその使用量
+    bytes: usize,
+}
+
+/// 出現回数の1件あたりの固定の使用量（行の文字列と回数）
+const FREQUENCY_ENTRY_OVERHEAD: usize = std::mem::size_of::<String>() + std::mem::size_of::<u64>();
+
+impl TextFrequencyTracker {
+    /// 新規・変更イベントの認識テキストを行ごとに数える（空行は数えない）
+    pub fn record(&mut self, text: &str) {
+        self.total_events += 1;
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            match self.counts.get_mut(line) {
+                Some(count) => *count += 1,
+                None => {
+                    self.bytes += line.len() + FREQUENCY_ENTRY_OVERHEAD;
+                    self.counts.insert(line.to_string(), 1);
+                }
+            }
+        }
+    }
+
+    /// 出現回数の多い行から最大n件（同じ回数なら行の順）
+    pub fn top_n(&self, n: usize) -> Vec<TextFrequencyEntry> {
+        let mut entries: Vec<TextFrequencyEntry> = self
+            .counts
+            .iter()
+            .map(|(line, &count)| TextFrequencyEntry { line: line.clone(), count })
+            .collect();
+        entries.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.line.cmp(&b.line)));
+        entries.truncate(n);
+        entries
+    }
+
+    /// 数えたイベントの数
+    #[allow(dead_code)]
+    pub fn total_events(&self) -> u64 {
+        self.total_events
+    }
+
+    /// 回数を破棄（監視の開始時）
+    pub fn clear(&mut self) {
+        self.counts.clear();
+        self.total_events = 0;
+        self.bytes = 0;
+    }
+}
+
+impl MemoryAccounted for TextFrequencyTracker {
+    fn approximate_bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// 出現回数の少ない行から破棄
+    fn trim_to(&mut self, target_bytes: usize) {
+        if self.bytes <= target_bytes {
+            return;
+        }
+        let mut lines: Vec<(String, u64)> = self.counts.iter().map(|(line, &count)| (line.clone(), count)).collect();
+        lines.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)));
+        for (line, _) in lines {
+            if self.bytes <= target_bytes {
+                break;
+            }
+            self.counts.remove(&line);
+            self.bytes -= line.len() + FREQUENCY_ENTRY_OVERHEAD;
+        }
+    }
+}
+
+/// スレッド間で共有する行ごとの出現回数
+pub type SharedTextFrequency = Arc<Mutex<TextFrequencyTracker>>;
 
 /// テキスト変化イベント
+#[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub enum TextChangeEvent {
     /// 新しいテキストが検出された
@@ -20,9 +362,50 @@ pub enum TextChangeEvent {
     DiffDetected { added: Vec<String>, removed: Vec<String> },
     /// エラーが発生した
     Error(String),
+    /// 比較の基準とするテキストが設定された
+    ReferenceSet { text: String },
+    /// 情報メッセージ（明暗の反転の自動判定の結果など）
+    Info { message: String },
+}
+
+/// 指定した時刻までの待ち時間（時刻が過去、または MAX_SNAPSHOT_DELAY_MS より先の場合はエラー）
+pub fn snapshot_delay(unix_ts_ms: u64) -> Result<Duration> {
+    let now = now_millis();
+    if unix_ts_ms < now {
+        bail!("スナップショット時刻が過去です");
+    }
+    let delay_ms = unix_ts_ms - now;
+    if delay_ms > MAX_SNAPSHOT_DELAY_MS {
+        bail!("スナップショット時刻は {}ms 以内で指定してください: {}ms 後", MAX_SNAPSHOT_DELAY_MS, delay_ms);
+    }
+    Ok(Duration::from_millis(delay_ms))
+}
+
+/// watch_for / watch_for_change のエラー
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchError {
+    /// 期限までに条件に合うイベントが無かった
+    Timeout,
+    /// 条件に合うイベントの前に監視が終了した
+    MonitorStopped,
+    /// 正規表現が不正
+    InvalidPattern(String),
 }
 
+impl fmt::Display for WatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WatchError::Timeout => write!(f, "期限までに条件に合うテキストが認識されませんでした"),
+            WatchError::MonitorStopped => write!(f, "監視が終了しました"),
+            WatchError::InvalidPattern(message) => write!(f, "正規表現が正しくありません: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for WatchError {}
+
 /// 画面監視を行う構造体
+#[allow(dead_code)]
 pub struct ScreenMonitor {
     /// 画面キャプチャ
     capture: ScreenCapture,
@@ -34,23 +417,172 @@ pub struct ScreenMonitor {
     interval_ms: u64,
     /// テキスト差分検出器
     text_differ: TextDiffer,
+    /// 監視の設定
+    config: MonitorConfig,
+    /// 前回OCRしたフレームのdHash
+    last_hash: Arc<RwLock<Option<u64>>>,
+    /// イベント送信前に実行するミドルウェア
+    middlewares: MiddlewareChain,
+    /// 作成した時刻を基準にした時計（スナップショットやファイルへの書き出しの時刻に使う）
+    clock: SessionClock,
+    /// イベントのファイル・標準出力への書き出し（pipe_to_fileで開始した場合のみ）
+    pipe: SharedEventPipe,
+    /// 監視の各段階で呼ぶフック（監視中も共有できるようロックで保護する）
+    hooks: Mutex<HookChain>,
 }
 
+#[allow(dead_code)]
 impl ScreenMonitor {
     /// 新しいScreenMonitorを作成
     pub fn new(region: CaptureRegion, interval_ms: u64) -> Result<Self> {
-        let capture = ScreenCapture::new(region);
+        Self::with_capture_source(region, interval_ms, Box::new(LiveScreenSource::default()))
+    }
+
+    /// 画像の取得元を指定してScreenMonitorを作成（テスト用の画像で監視する場合など）
+    pub fn with_capture_source(region: CaptureRegion, interval_ms: u64, source: Box<dyn CaptureSource>) -> Result<Self> {
         let ocr_engine = Arc::new(OcrEngine::new()?);
-        let last_text = Arc::new(RwLock::new(None));
-        let text_differ = TextDiffer::new(1); // 最小1文字の変更を検出
+        Ok(Self::from_parts(ScreenCapture::with_source(region, source), ocr_engine, interval_ms))
+    }
 
-        Ok(Self {
+    /// キャプチャに幾何補正を適用するScreenMonitorを作成（曲面のディスプレイや斜めから撮影した映像向け）
+    pub fn with_transform(region: CaptureRegion, transform: CaptureTransform, interval_ms: u64) -> Result<Self> {
+        let config = OcrConfig {
+            transform,
+            ..OcrConfig::default()
+        };
+        config.validate()?;
+        let mut ocr_engine = OcrEngine::new()?;
+        ocr_engine.set_config(config);
+        let capture = ScreenCapture::with_source(region, Box::new(LiveScreenSource::default()));
+        Ok(Self::from_parts(capture, Arc::new(ocr_engine), interval_ms))
+    }
+
+    /// 共有のOCRエンジンを使うScreenMonitorを作成（複数の領域でエンジンを共有する場合）
+    pub fn with_custom_ocr_engine(region: CaptureRegion, engine: Arc<OcrEngine>, interval_ms: u64) -> Result<Self> {
+        let capture = ScreenCapture::with_source(region, Box::new(LiveScreenSource::default()));
+        Ok(Self::from_parts(capture, engine, interval_ms))
+    }
+
+    /// 複数の領域のScreenMonitorを作成し、最大 pool_size 個のOCRエンジンを共有させる
+    pub fn for_regions(regions: &[CaptureRegion], config: &MonitorConfig) -> Result<Vec<Self>> {
+        let pool = OcrEnginePool::new(config.pool_size.min(regions.len()), OcrEngine::new)?;
+        regions
+            .iter()
+            .map(|region| {
+                let mut monitor = Self::with_custom_ocr_engine(*region, pool.acquire(), config.interval_ms)?;
+                monitor.update_config(config.clone());
+                Ok(monitor)
+            })
+            .collect()
+    }
+
+    fn from_parts(capture: ScreenCapture, ocr_engine: Arc<OcrEngine>, interval_ms: u64) -> Self {
+        Self {
             capture,
             ocr_engine,
-            last_text,
+            last_text: Arc::new(RwLock::new(None)),
             interval_ms,
-            text_differ,
-        })
+            text_differ: TextDiffer::new(1), // 最小1文字の変更を検出
+            config: MonitorConfig::default(),
+            last_hash: Arc::new(RwLock::new(None)),
+            middlewares: MiddlewareChain::default(),
+            clock: SessionClock::start(),
+            pipe: SharedEventPipe::default(),
+            hooks: Mutex::new(HookChain::default()),
+        }
+    }
+
+    /// 画像を同じ幅の縦の帯に分割（割り切れない幅は最後の列に含める）
+    pub fn split_text_columns(image: &DynamicImage, n_columns: u32) -> Vec<DynamicImage> {
+        let n_columns = n_columns.clamp(1, image.width().max(1));
+        let column_width = image.width() / n_columns;
+        (0..n_columns)
+            .map(|column| {
+                let x = column * column_width;
+                let width = if column + 1 == n_columns { image.width() - x } else { column_width };
+                image.crop_imm(x, 0, width, image.height())
+            })
+            .collect()
+    }
+
+    /// 列ごとに認識し、同じ行の列のテキストをタブでつないだ結果を返す
+    ///
+    /// 行の対応付けは行番号ではなく行の縦の位置で行い、行数の違う列や空行のある列でもずれないようにする。
+    /// 対応する行の無い列は空の文字列で埋め、列の位置を保つ。各列は行単位で1回だけ認識し、信頼度は
+    /// 認識したすべての行の平均、画像の指標は最初の列のものとする。
+    /// OCRの統計には、すべての列の認識をまとめて1回の認識として数える。
+    pub fn recognize_columns(engine: &OcrEngine, image: &DynamicImage, n_columns: u32) -> Result<OcrResult> {
+        ocr_stats::recognition(|| Self::recognize_each_column(engine, image, n_columns), OcrResult::stats_outcome)
+    }
+
+    /// 列ごとに認識して結果をまとめる（recognize_columnsの中身）
+    fn recognize_each_column(engine: &OcrEngine, image: &DynamicImage, n_columns: u32) -> Result<OcrResult> {
+        // 幾何補正は列に分ける前の全体に行う
+        let corrected = engine.correct_geometry(image)?;
+        let columns = Self::split_text_columns(&corrected, n_columns);
+        let lines = columns
+            .iter()
+            .map(|column| engine.recognize_lines_corrected(column))
+            .collect::<Result<Vec<_>>>()?;
+        let text = align_column_rows(&lines);
+        let confidences: Vec<f32> = lines.iter().flatten().filter_map(|line| line.confidence).collect();
+        let confidence = (!confidences.is_empty()).then(|| confidences.iter().sum::<f32>() / confidences.len() as f32);
+        let mut metrics = ImageMetrics::measure(&columns[0]);
+        metrics.text_coverage = engine.sample_text_coverage(image);
+        Ok(OcrResult::new(text, confidence, metrics))
+    }
+
+    /// 設定に応じて領域全体、または列ごとに認識
+    fn recognize_frame(&self, engine: &OcrEngine, image: &DynamicImage) -> Result<OcrResult> {
+        match self.config.column_split {
+            Some(columns) => Self::recognize_columns(engine, image, columns),
+            None => engine.recognize_detailed(image),
+        }
+    }
+
+    /// 最初のフレームで明暗の反転を判定し、反転した方が良ければ反転した設定のエンジンを返す
+    ///
+    /// 設定で既に反転している場合は判定しない。キャプチャ・認識に失敗した場合は反転しない。
+    async fn probe_contrast(&self, event_sender: &mpsc::Sender<TextChangeEvent>) -> Option<OcrEngine> {
+        if self.ocr_engine.config().invert {
+            return None;
+        }
+        let image = match self.capture.capture() {
+            Ok(image) => image,
+            Err(e) => {
+                log::warn!("明暗の判定のためのキャプチャに失敗しました（反転せずに監視します）: {}", e);
+                return None;
+            }
+        };
+        match ContrastProber::probe_engine(&self.ocr_engine, &image) {
+            Ok(Some(engine)) => {
+                log::info!("暗い背景に明るい文字の画面と判定したため、画像を反転して認識します");
+                self.send_event(event_sender, TextChangeEvent::Info {
+                    message: "ダークモード検出: 画像を反転しました".to_string(),
+                }, EmitContext::default()).await;
+                Some(engine)
+            }
+            Ok(None) => None,
+            Err(e) => {
+                log::warn!("明暗の判定に失敗しました（反転せずに監視します）: {}", e);
+                None
+            }
+        }
+    }
+
+    /// 実行時の設定をまとめて取得
+    pub fn export_config(&self) -> MonitorSnapshot {
+        MonitorSnapshot {
+            session_id: 0,
+            region: self.capture.region,
+            language: self.ocr_engine.language().to_string(),
+            interval_ms: self.interval_ms,
+            ocr_config: self.ocr_engine.config().clone(),
+            monitor_config: self.config.clone(),
+            tessdata_dir: self.ocr_engine.tessdata_dir(),
+            started_at: self.clock.started_at_ms(),
+            snapshot_at: self.clock.now_ms(),
+        }
     }
 
     /// 監視を開始
@@ -62,6 +594,10 @@ impl ScreenMonitor {
 
         log::info!("画面監視を開始しました（間隔: {}ms）", self.interval_ms);
 
+        // 反転した方が良ければ、以降のフレームはすべて反転して認識する
+        let inverted_engine = self.probe_contrast(&event_sender).await;
+        let engine = inverted_engine.as_ref().unwrap_or(&*self.ocr_engine);
+
         loop {
             interval.tick().await;
 
@@ -70,21 +606,26 @@ impl ScreenMonitor {
                 Ok(img) => img,
                 Err(e) => {
                     log::error!("キャプチャエラー: {}", e);
-                    let _ = event_sender.send(TextChangeEvent::Error(
-                        format!("キャプチャエラー: {}", e)
-                    )).await;
+                    self.report_error(&event_sender, MonitorError::Capture(e.to_string())).await;
                     continue;
                 }
             };
+            lock(&self.hooks).frame_captured(&image);
+
+            // 前回OCRしたフレームとほぼ同じならOCRを省略
+            if self.skip_similar_frames(&image).await {
+                continue;
+            }
 
             // OCRでテキスト認識
-            let current_text = match self.ocr_engine.recognize_text(&image) {
-                Ok(text) => text,
+            let (current_text, context) = match self.recognize_frame(engine, &image) {
+                Ok(result) => {
+                    lock(&self.hooks).text_recognized(&result);
+                    (result.text, EmitContext { confidence: result.confidence })
+                }
                 Err(e) => {
                     log::error!("OCRエラー: {}", e);
-                    let _ = event_sender.send(TextChangeEvent::Error(
-                        format!("OCRエラー: {}", e)
-                    )).await;
+                    self.report_error(&event_sender, MonitorError::Ocr(e.to_string())).await;
                     continue;
                 }
             };
@@ -97,16 +638,17 @@ impl ScreenMonitor {
                     // 初回認識
                     if !current_text.is_empty() {
                         log::info!("新しいテキストを検出: {}", current_text);
-                        let _ = event_sender.send(TextChangeEvent::NewText(current_text.clone())).await;
+                        self.send_event(&event_sender, TextChangeEvent::NewText(current_text.clone()), context).await;
                         *last_text = Some(current_text);
                     }
                 }
                 Some(prev_text) => {
-                    if prev_text != &current_text {
+                    // 空白の違いだけは変化とみなさない（送信するテキストは認識したまま）
+                    if !texts_equivalent(prev_text, &current_text) {
                         if current_text.is_empty() {
                             // テキストがクリアされた
                             log::info!("テキストがクリアされました");
-                            let _ = event_sender.send(TextChangeEvent::TextCleared(prev_text.clone())).await;
+                            self.send_event(&event_sender, TextChangeEvent::TextCleared(prev_text.clone()), context).await;
                             *last_text = None;
                         } else {
                             // テキストが変更された
@@ -118,17 +660,17 @@ impl ScreenMonitor {
                             // 差分がある場合は差分イベントも送信
                             if !added.is_empty() || !removed.is_empty() {
                                 log::info!("差分検出 - 追加: {:?}, 削除: {:?}", added, removed);
-                                let _ = event_sender.send(TextChangeEvent::DiffDetected {
+                                self.send_event(&event_sender, TextChangeEvent::DiffDetected {
                                     added: added.clone(),
                                     removed: removed.clone(),
-                                }).await;
+                                }, context).await;
                             }
                             
                             // 通常の変更イベントも送信
-                            let _ = event_sender.send(TextChangeEvent::TextChanged {
+                            self.send_event(&event_sender, TextChangeEvent::TextChanged {
                                 old: prev_text.clone(),
                                 new: current_text.clone(),
-                            }).await;
+                            }, context).await;
                             
                             *last_text = Some(current_text);
                         }
@@ -138,10 +680,175 @@ impl ScreenMonitor {
         }
     }
 
+    /// 指定した時刻（UNIXエポックからのミリ秒）まで待ち、1回だけキャプチャ・認識する
+    ///
+    /// 監視のループとは独立して認識するため、前回のテキストやフレームのハッシュは変わらず、イベントも送らない。
+    /// 時刻が過去の場合はエラーを返す。
+    pub fn take_snapshot_at(&self, unix_ts_ms: u64) -> impl Future<Output = Result<OcrResult>> + '_ {
+        // 待ち時間は呼び出した時点で決める（待つ前に時刻が過ぎた場合も過去として扱う）
+        let delay = snapshot_delay(unix_ts_ms);
+        async move {
+            tokio::time::sleep(delay?).await;
+            let image = self.capture.capture()?;
+            self.recognize_frame(&self.ocr_engine, &image)
+        }
+    }
+
+    /// 監視を行い、正規表現に一致するテキストが最初に認識されたらそのテキストを返す
+    ///
+    /// 新規・変更のイベントのテキスト全体と照合する。監視の終了時にはイベントも送らないため、
+    /// すでに表示されているテキストは前回の認識結果として扱われ、変化するまで一致しない。
+    pub fn watch_for(&self, pattern: &str, timeout: Duration) -> impl Future<Output = Result<String, WatchError>> + '_ {
+        let pattern = Regex::new(pattern).map_err(|e| WatchError::InvalidPattern(e.to_string()));
+        async move {
+            let pattern = pattern?;
+            self.watch_events(timeout, |event| match event {
+                TextChangeEvent::NewText(text) | TextChangeEvent::TextChanged { new: text, .. } if pattern.is_match(&text) => {
+                    Some(text)
+                }
+                _ => None,
+            })
+            .await
+        }
+    }
+
+    /// 監視を行い、テキストの新規・変更・クリアのいずれかのイベントが最初に届いたらそのイベントを返す
+    pub async fn watch_for_change(&self, timeout: Duration) -> Result<TextChangeEvent, WatchError> {
+        self.watch_events(timeout, |event| match event {
+            TextChangeEvent::NewText(_) | TextChangeEvent::TextChanged { .. } | TextChangeEvent::TextCleared(_) => Some(event),
+            _ => None,
+        })
+        .await
+    }
+
+    /// 期限まで監視し、acceptがSomeを返した最初のイベントの結果を返す（戻る時点で監視も終える）
+    async fn watch_events<T>(
+        &self,
+        timeout: Duration,
+        mut accept: impl FnMut(TextChangeEvent) -> Option<T>,
+    ) -> Result<T, WatchError> {
+        let (sender, mut receiver) = mpsc::channel(WATCH_CHANNEL_CAPACITY);
+        let monitoring = self.start_monitoring(sender);
+        tokio::pin!(monitoring);
+
+        let watching = async {
+            loop {
+                tokio::select! {
+                    event = receiver.recv() => match event {
+                        Some(event) => {
+                            if let Some(found) = accept(event) {
+                                return Ok(found);
+                            }
+                        }
+                        None => return Err(WatchError::MonitorStopped),
+                    },
+                    result = &mut monitoring => {
+                        if let Err(e) = result {
+                            log::warn!("監視が終了しました: {}", e);
+                        }
+                        return Err(WatchError::MonitorStopped);
+                    }
+                }
+            }
+        };
+        tokio::time::timeout(timeout, watching).await.unwrap_or(Err(WatchError::Timeout))
+    }
+
+    /// 比較の基準とするテキストを設定（キャプチャは行わず、次の認識結果をこのテキストと比較する）
+    pub async fn set_reference_text(&self, text: String, event_sender: &mpsc::Sender<TextChangeEvent>) {
+        log::info!("基準のテキストを設定しました: {}", text);
+        *self.last_text.write().await = Some(text.clone());
+        // 画面が変わらなくても次のフレームを認識し直して基準と比較する
+        *self.last_hash.write().await = None;
+        self.send_event(event_sender, TextChangeEvent::ReferenceSet { text }, EmitContext::default()).await;
+    }
+
+    /// 基準のテキストを解除（次の認識結果は新規のテキストとして扱う）
+    pub async fn clear_reference_text(&self) {
+        *self.last_text.write().await = None;
+        *self.last_hash.write().await = None;
+    }
+
+    /// ミドルウェアを登録順に実行し、抑制されなければイベントを送信
+    async fn send_event(
+        &self,
+        event_sender: &mpsc::Sender<TextChangeEvent>,
+        mut event: TextChangeEvent,
+        context: EmitContext,
+    ) {
+        if !self.middlewares.run(&mut event, &context) {
+            log::debug!("ミドルウェアによりイベントを抑制しました（信頼度 {:?}）: {:?}", context.confidence, event);
+            return;
+        }
+        write_to_pipe(&self.pipe, || PipeRecord::from_monitor_event(&event, &self.clock));
+        lock(&self.hooks).event(&events::TextChangeEvent::from(&event), None);
+        let _ = event_sender.send(event).await;
+    }
+
+    /// エラーをフックに通知し、エラーのイベントを送信
+    async fn report_error(&self, event_sender: &mpsc::Sender<TextChangeEvent>, error: MonitorError) {
+        lock(&self.hooks).error(&error);
+        self.send_event(event_sender, TextChangeEvent::Error(error.to_string()), EmitContext::default()).await;
+    }
+
+    /// 監視の各段階で呼ぶフックを設定（以前に設定したフックは置き換える）
+    ///
+    /// フックは監視のタスクで同期的に呼ばれるため、時間のかかる処理は別のスレッドに渡すこと。
+    pub fn set_hooks(&mut self, hooks: Box<dyn MonitorHooks + Send>) {
+        let chain = self.hooks.get_mut().unwrap_or_else(|poisoned| poisoned.into_inner());
+        chain.clear();
+        chain.add(hooks);
+    }
+
+    /// フックを解除
+    pub fn clear_hooks(&mut self) {
+        self.hooks.get_mut().unwrap_or_else(|poisoned| poisoned.into_inner()).clear();
+    }
+
+    /// 送信するイベントをファイルにも1行ずつ追記する（パスが "-" なら標準出力、既に書き出し中なら切り替える）
+    pub fn pipe_to_file(&self, path: &Path, format: OutputFormat) -> Result<(), String> {
+        self.pipe_to_file_with_rotation(path, format, FileRotationPolicy::default())
+    }
+
+    /// pipe_to_file と同じく書き出し、条件を満たしたらファイルを切り替える
+    pub fn pipe_to_file_with_rotation(&self, path: &Path, format: OutputFormat, rotation: FileRotationPolicy) -> Result<(), String> {
+        let pipe = EventPipe::open(path, format, rotation).map_err(|e| format!("{:#}", e))?;
+        *lock(&self.pipe) = Some(pipe);
+        Ok(())
+    }
+
+    /// ファイルへの書き出しを停止（書き出していなければfalse）
+    pub fn stop_pipe(&self) -> bool {
+        lock(&self.pipe).take().is_some()
+    }
+
+    /// イベント送信前に実行するミドルウェアを追加（登録順に実行される）
+    pub fn add_middleware(&mut self, middleware: Box<dyn EventMiddleware + Send + Sync>) -> MiddlewareId {
+        self.middlewares.add(middleware)
+    }
+
+    /// ミドルウェアを削除（登録されていなければfalse）
+    pub fn remove_middleware(&mut self, id: MiddlewareId) -> bool {
+        self.middlewares.remove(id)
+    }
+
+    /// 前回OCRしたフレームと知覚ハッシュがほぼ同じならtrueを返す
+    pub async fn skip_similar_frames(&self, image: &DynamicImage) -> bool {
+        let mut last_hash = self.last_hash.write().await;
+        self.config.should_skip_frame(&mut last_hash, image)
+    }
+
+    /// 監視の設定を更新
+    pub fn update_config(&mut self, config: MonitorConfig) {
+        log::info!("監視の設定を更新しました: {:?}", config);
+        self.config = config;
+    }
+
     /// 監視領域を更新
     #[allow(dead_code)]
     pub fn update_region(&mut self, region: CaptureRegion) {
-        self.capture = ScreenCapture::new(region);
+        // 画像の取得元はそのままで領域のみ変更
+        self.capture.region = region;
         log::info!("監視領域を更新しました: {:?}", region);
     }
 
@@ -191,4 +898,725 @@ impl TextDiffer {
 
         (added, removed)
     }
-}
\ No newline at end of file
+
+    /// 2つのテキストの類似度（0.0〜1.0、文字単位の編集距離から計算）
+    pub fn similarity_score(&self, a: &str, b: &str) -> f32 {
+        let a: Vec<char> = a.chars().collect();
+        let b: Vec<char> = b.chars().collect();
+        let length = a.len().max(b.len());
+        if length == 0 {
+            return 1.0;
+        }
+        1.0 - edit_distance(&a, &b) as f32 / length as f32
+    }
+
+    /// 行単位のunified diff（全体を1つのハンクとして出力）
+    pub fn unified_diff(&self, old_text: &str, new_text: &str, old_label: &str, new_label: &str) -> String {
+        let old_lines: Vec<&str> = old_text.lines().collect();
+        let new_lines: Vec<&str> = new_text.lines().collect();
+
+        let mut out = format!(
+            "--- {}\n+++ {}\n@@ -1,{} +1,{} @@\n",
+            old_label,
+            new_label,
+            old_lines.len(),
+            new_lines.len()
+        );
+        for op in diff_ops(&old_lines, &new_lines) {
+            match op {
+                DiffOp::Equal(i) => out.push_str(&format!(" {}\n", old_lines[i])),
+                DiffOp::Delete(i) => out.push_str(&format!("-{}\n", old_lines[i])),
+                DiffOp::Insert(j) => out.push_str(&format!("+{}\n", new_lines[j])),
+            }
+        }
+        out
+    }
+
+    /// 追加した部分を `<ins>`、削除した部分を `<del>` で囲んだHTML（変わらない部分はそのまま、すべてエスケープ済み）
+    ///
+    /// 英数字の並びは単語として、それ以外（日本語など）は1文字ずつ比較する。
+    /// 比較する量が多すぎる場合は全体を削除と追加として出力する。
+    #[cfg(feature = "html_diff")]
+    pub fn render_html_diff(old: &str, new: &str) -> String {
+        let old_tokens = diff_tokens(old);
+        let new_tokens = diff_tokens(new);
+        if old_tokens.len().saturating_mul(new_tokens.len()) > MAX_HTML_DIFF_CELLS {
+            return format!("<del>{}</del><ins>{}</ins>", escape_html(old), escape_html(new));
+        }
+
+        let mut out = String::new();
+        // 同じ種類の操作が続く間は1つの要素にまとめる
+        let mut open: Option<&str> = None;
+        for op in diff_ops(&old_tokens, &new_tokens) {
+            let (tag, token) = match op {
+                DiffOp::Equal(i) => (None, old_tokens[i]),
+                DiffOp::Delete(i) => (Some("del"), old_tokens[i]),
+                DiffOp::Insert(j) => (Some("ins"), new_tokens[j]),
+            };
+            if tag != open {
+                if let Some(previous) = open {
+                    out.push_str(&format!("</{}>", previous));
+                }
+                if let Some(tag) = tag {
+                    out.push_str(&format!("<{}>", tag));
+                }
+                open = tag;
+            }
+            out.push_str(&escape_html(token));
+        }
+        if let Some(tag) = open {
+            out.push_str(&format!("</{}>", tag));
+        }
+        out
+    }
+}
+
+/// HTMLの差分で比較する要素の数の積の上限（最長共通部分列の表の大きさ）
+///
+/// 表は要素の数の積の大きさで確保するため、数MBに収まる大きさにする（500要素同士の比較まで）。
+#[cfg(feature = "html_diff")]
+const MAX_HTML_DIFF_CELLS: usize = 250_000;
+
+/// 差分の1つの操作（要素の位置）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DiffOp {
+    Equal(usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// 最長共通部分列に基づく差分（同じ位置の変更は削除を先にする）
+fn diff_ops<T: PartialEq>(old: &[T], new: &[T]) -> Vec<DiffOp> {
+    // 最長共通部分列の長さの表（lcs[i][j] = old[i..] と new[j..] のLCS）
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            ops.push(DiffOp::Equal(i));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            ops.push(DiffOp::Delete(i));
+            i += 1;
+        } else {
+            ops.push(DiffOp::Insert(j));
+            j += 1;
+        }
+    }
+    ops
+}
+
+/// HTMLの差分で比較する要素（英数字の並び、またはそれ以外の1文字）
+#[cfg(feature = "html_diff")]
+fn diff_tokens(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((_, c)) = chars.next() {
+        if c.is_ascii_alphanumeric() {
+            while chars.peek().is_some_and(|&(_, next)| next.is_ascii_alphanumeric()) {
+                chars.next();
+            }
+        }
+        let end = chars.peek().map_or(text.len(), |&(next, _)| next);
+        tokens.push(&text[start..end]);
+        start = end;
+    }
+    tokens
+}
+
+/// HTMLの特殊文字をエスケープ
+#[cfg(feature = "html_diff")]
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// 列ごとに認識した行を縦の位置で対応付け、同じ行の列のテキストをタブでつなぐ
+///
+/// 縦の範囲が短い方の行の高さの半分以上重なる行を同じ行とする。列の中の空行は認識結果に現れないため、
+/// 位置で対応付けることで他の列の行がずれない。
+pub fn align_column_rows(columns: &[Vec<OcrLine>]) -> String {
+    let mut lines: Vec<(usize, &OcrLine)> = columns
+        .iter()
+        .enumerate()
+        .flat_map(|(column, lines)| lines.iter().map(move |line| (column, line)))
+        .filter(|(_, line)| !line.text.trim().is_empty())
+        .collect();
+    lines.sort_by_key(|(_, line)| line.bbox.y * 2 + line.bbox.height);
+
+    // 行ごとの縦の範囲と列ごとのテキスト
+    let mut rows: Vec<(u32, u32, Vec<Option<&str>>)> = Vec::new();
+    for (column, line) in lines {
+        let (top, bottom) = (line.bbox.y, line.bbox.y + line.bbox.height);
+        let matched = rows
+            .iter_mut()
+            .filter(|(_, _, cells)| cells[column].is_none())
+            .map(|row| {
+                let overlap = bottom.min(row.1).saturating_sub(top.max(row.0));
+                (overlap, row)
+            })
+            .filter(|(overlap, row)| *overlap * 2 >= (bottom - top).min(row.1 - row.0).max(1))
+            .max_by_key(|(overlap, _)| *overlap);
+        match matched {
+            Some((_, row)) => {
+                row.0 = row.0.min(top);
+                row.1 = row.1.max(bottom);
+                row.2[column] = Some(line.text.trim());
+            }
+            None => {
+                let mut cells = vec![None; columns.len()];
+                cells[column] = Some(line.text.trim());
+                rows.push((top, bottom, cells));
+            }
+        }
+    }
+
+    rows.sort_by_key(|(top, _, _)| *top);
+    rows.iter()
+        .map(|(_, _, cells)| cells.iter().map(|cell| cell.unwrap_or("")).collect::<Vec<_>>().join("\t"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 文字単位の編集距離（レーベンシュタイン距離）
+pub fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// 比較専用の正規化（行内の連続する空白を1つにまとめ、行の前後の空白と空行を除く）
+///
+/// 送信するイベントのテキストには使わず、認識したままの形を残す。
+pub fn canonicalize_for_comparison(text: &str) -> String {
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 空白の違いだけなら同じテキストとみなす
+pub fn texts_equivalent(a: &str, b: &str) -> bool {
+    a == b || canonicalize_for_comparison(a) == canonicalize_for_comparison(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::Xorshift;
+    use crate::tiling::ImageRect;
+    use image::{Rgb, RgbImage};
+
+    fn line(text: &str, y: u32, height: u32) -> OcrLine {
+        OcrLine {
+            text: text.to_string(),
+            bbox: ImageRect { x: 0, y, width: 100, height },
+            language: None,
+            confidence: None,
+        }
+    }
+
+    /// 左の列を黒、右の列を灰色で塗った2段組みの画像
+    fn two_column_image(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, _| {
+            if x < width / 2 {
+                Rgb([0, 0, 0])
+            } else {
+                Rgb([128, 128, 128])
+            }
+        }))
+    }
+
+    #[test]
+    fn splits_two_column_image_into_strips() {
+        let strips = ScreenMonitor::split_text_columns(&two_column_image(200, 50), 2);
+        assert_eq!(strips.len(), 2);
+        assert_eq!((strips[0].width(), strips[0].height()), (100, 50));
+        assert_eq!((strips[1].width(), strips[1].height()), (100, 50));
+        assert!(strips[0].to_rgb8().pixels().all(|pixel| pixel.0 == [0, 0, 0]));
+        assert!(strips[1].to_rgb8().pixels().all(|pixel| pixel.0 == [128, 128, 128]));
+    }
+
+    #[test]
+    fn last_strip_takes_remainder_and_columns_are_clamped() {
+        let strips = ScreenMonitor::split_text_columns(&two_column_image(101, 10), 2);
+        assert_eq!(strips.iter().map(DynamicImage::width).collect::<Vec<_>>(), vec![50, 51]);
+        assert_eq!(ScreenMonitor::split_text_columns(&two_column_image(3, 10), 8).len(), 3);
+        assert_eq!(ScreenMonitor::split_text_columns(&two_column_image(10, 10), 0).len(), 1);
+    }
+
+    /// 呼ばれた順に列の名前と信頼度を返し、呼ばれた回数を数えるOCR
+    struct CountingColumns {
+        calls: Arc<Mutex<usize>>,
+    }
+
+    impl crate::backends::OcrBackend for CountingColumns {
+        fn recognize_lines(&self, _image: &DynamicImage, _page_seg_mode: u32) -> Result<Vec<OcrLine>> {
+            let mut calls = lock(&self.calls);
+            let (text, confidence) = [("left", 0.8), ("right", 0.6)][*calls % 2];
+            *calls += 1;
+            Ok(vec![OcrLine { confidence: Some(confidence), ..line(text, 0, 20) }])
+        }
+    }
+
+    #[test]
+    fn recognizes_each_column_once_and_averages_line_confidence() {
+        let calls = Arc::new(Mutex::new(0));
+        let backend = CountingColumns { calls: Arc::clone(&calls) };
+        let mut engine =
+            OcrEngine::from_parts(None, "eng", crate::backends::OcrBackendKind::Tesseract, Some(Box::new(backend)));
+        engine.set_config(OcrConfig { fast_pipeline: true, ..OcrConfig::default() });
+
+        let result = ScreenMonitor::recognize_columns(&engine, &two_column_image(200, 50), 2).unwrap();
+        assert_eq!(*lock(&calls), 2);
+        assert_eq!(result.text, "left\tright");
+        assert!((result.confidence.unwrap() - 0.7).abs() < 1e-6);
+    }
+
+    #[test]
+    fn aligns_two_columns_by_row() {
+        let left = vec![line("名前", 10, 20), line("HP", 40, 20)];
+        let right = vec![line("勇者", 12, 18), line("120", 41, 20)];
+        assert_eq!(align_column_rows(&[left, right]), "名前\t勇者\nHP\t120");
+    }
+
+    #[test]
+    fn aligns_columns_with_different_line_counts() {
+        // 右の列の1行目は空行のため認識されない
+        let left = vec![line("A1", 0, 20), line("A2", 30, 20), line("A3", 60, 20)];
+        let right = vec![line("B2", 31, 19), line("B3", 62, 18)];
+        assert_eq!(align_column_rows(&[left, right]), "A1\t\nA2\tB2\nA3\tB3");
+    }
+
+    #[test]
+    fn keeps_rows_only_present_in_later_columns() {
+        let left = vec![line("A1", 0, 20)];
+        let right = vec![line("B1", 0, 20), line("B2", 30, 20)];
+        assert_eq!(align_column_rows(&[left, right]), "A1\tB1\n\tB2");
+    }
+
+    #[test]
+    fn reference_text_forces_recognition_of_an_unchanged_frame() {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let region = CaptureRegion { x: 0, y: 0, width: 64, height: 32, display: None };
+        let engine = OcrEngine::from_parts(None, "eng", crate::backends::OcrBackendKind::Tesseract, None);
+        let monitor = ScreenMonitor::from_parts(ScreenCapture::new(region), Arc::new(engine), 100);
+        let image = two_column_image(64, 32);
+        let (sender, _receiver) = mpsc::channel(8);
+        runtime.block_on(async {
+            assert!(!monitor.skip_similar_frames(&image).await);
+            assert!(monitor.skip_similar_frames(&image).await);
+            monitor.set_reference_text("基準".to_string(), &sender).await;
+            assert!(!monitor.skip_similar_frames(&image).await);
+            monitor.clear_reference_text().await;
+            assert!(!monitor.skip_similar_frames(&image).await);
+        });
+    }
+
+    #[test]
+    fn does_not_merge_two_lines_of_the_same_column() {
+        let left = vec![line("上", 0, 20), line("下", 12, 20)];
+        let right = vec![line("右", 0, 20)];
+        assert_eq!(align_column_rows(&[left, right]), "上\t右\n下\t");
+    }
+
+    /// 1つのフィールドだけを変えた設定で、範囲外と判定されたフィールド
+    fn rejected_fields(update: impl FnOnce(&mut MonitorConfig)) -> Vec<String> {
+        let mut config = MonitorConfig::default();
+        update(&mut config);
+        match config.validate() {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors.0.into_iter().map(|error| error.field).collect(),
+        }
+    }
+
+    /// 範囲の両端は受け付け、すぐ外側の値は拒否する（下限が型の最小値なら下側は確かめない）
+    fn assert_bounds<T: Copy + std::fmt::Debug>(
+        field: &str,
+        set: fn(&mut MonitorConfig, T),
+        below_min: Option<T>,
+        [min, max]: [T; 2],
+        above_max: T,
+    ) {
+        for value in [min, max] {
+            assert_eq!(rejected_fields(|config| set(config, value)), Vec::<String>::new(), "{} = {:?}", field, value);
+        }
+        for value in below_min.into_iter().chain([above_max]) {
+            assert_eq!(rejected_fields(|config| set(config, value)), vec![format!("monitor.{}", field)], "{} = {:?}", field, value);
+        }
+    }
+
+    /// すぐ下・すぐ上の浮動小数点数（0以上の値のみ、f32::next_down/next_upはrust-versionより新しい）
+    fn just_below(value: f32) -> f32 {
+        if value == 0.0 {
+            -f32::from_bits(1)
+        } else {
+            f32::from_bits(value.to_bits() - 1)
+        }
+    }
+
+    fn just_above(value: f32) -> f32 {
+        f32::from_bits(value.to_bits() + 1)
+    }
+
+    /// 浮動小数点数のフィールドの境界とNaN
+    fn assert_float_bounds(field: &str, set: fn(&mut MonitorConfig, f32), min: f32, max: f32) {
+        assert_bounds(field, set, Some(just_below(min)), [min, max], just_above(max));
+        assert_eq!(rejected_fields(|config| set(config, f32::NAN)), vec![format!("monitor.{}", field)], "{} = NaN", field);
+    }
+
+    #[test]
+    fn default_monitor_config_is_valid() {
+        assert_eq!(MonitorConfig::default().validate(), Ok(()));
+    }
+
+    #[test]
+    fn integer_fields_accept_their_bounds_only() {
+        assert_bounds("interval_ms", |c, v| c.interval_ms = v, Some(99), [100, 60_000], 60_001);
+        assert_bounds("dhash_skip_threshold", |c, v| c.dhash_skip_threshold = v, None, [0, 64], 65);
+        assert_bounds("slow_preprocess_threshold_ms", |c, v| c.slow_preprocess_threshold_ms = v, None, [0, 60_000], 60_001);
+        assert_bounds("max_batch_size", |c, v| c.max_batch_size = v, Some(0), [1, 500], 501);
+        assert_bounds("pool_size", |c, v| c.pool_size = v, Some(0), [1, 16], 17);
+        assert_bounds("unreadable_min_components", |c, v| c.unreadable_min_components = v, None, [0, 10_000], 10_001);
+        assert_bounds("thumbnail_width", |c, v| c.thumbnail_width = v, Some(15), [16, MAX_THUMBNAIL_WIDTH], MAX_THUMBNAIL_WIDTH + 1);
+        assert_bounds("thumbnail_height", |c, v| c.thumbnail_height = v, Some(15), [16, MAX_THUMBNAIL_HEIGHT], MAX_THUMBNAIL_HEIGHT + 1);
+        assert_bounds("coalesce_clear_ticks", |c, v| c.coalesce_clear_ticks = v, None, [0, 100], 101);
+        assert_bounds("memory_soft_limit_mb", |c, v| c.memory_soft_limit_mb = v, None, [0, 16_384], 16_385);
+        assert_bounds("script_check_ticks", |c, v| c.script_check_ticks = v, None, [0, 100], 101);
+        assert_bounds("display_check_interval_ms", |c, v| c.display_check_interval_ms = v, None, [0, 600_000], 600_001);
+        assert_bounds("low_coverage_min_samples", |c, v| c.low_coverage_min_samples = v, None, [0, 10_000], 10_001);
+        assert_bounds("column_split", |c, v| c.column_split = Some(v), Some(1), [2, MAX_COLUMN_SPLIT], MAX_COLUMN_SPLIT + 1);
+    }
+
+    #[test]
+    fn float_fields_accept_their_bounds_only_and_reject_nan() {
+        assert_float_bounds("tick_budget_multiplier", |c, v| c.tick_budget_multiplier = v, 0.1, 10.0);
+        assert_float_bounds("unreadable_min_variance", |c, v| c.unreadable_min_variance = v, 0.0, 16_384.0);
+        assert_float_bounds("script_mismatch_ratio", |c, v| c.script_mismatch_ratio = v, 0.5, 1.0);
+        assert_float_bounds("low_coverage_threshold", |c, v| c.low_coverage_threshold = v, 0.0, 1.0);
+    }
+
+    #[test]
+    fn every_out_of_range_field_is_reported_at_once() {
+        let fields = rejected_fields(|config| {
+            config.interval_ms = 0;
+            config.pool_size = 0;
+            config.script_mismatch_ratio = f32::NAN;
+        });
+        assert_eq!(fields, vec!["monitor.interval_ms", "monitor.pool_size", "monitor.script_mismatch_ratio"]);
+    }
+
+    #[test]
+    fn kill_switch_is_not_checked_without_a_path() {
+        assert!(!MonitorConfig::default().kill_switch_triggered(None));
+    }
+
+    #[test]
+    fn kill_switch_triggers_when_the_file_exists() {
+        let dir = std::env::temp_dir().join(format!("kill_switch_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let configured = dir.join("configured");
+        let from_env = dir.join("from_env");
+        std::fs::write(&from_env, b"").unwrap();
+
+        // 設定のパスが無ければ環境変数のパスを確認する
+        assert!(MonitorConfig::default().kill_switch_triggered(Some(&from_env)));
+        assert!(!MonitorConfig::default().kill_switch_triggered(Some(&configured)));
+
+        // 設定のパスがあれば環境変数のパスより優先する
+        let config = MonitorConfig {
+            kill_switch_path: Some(configured.clone()),
+            ..MonitorConfig::default()
+        };
+        assert!(!config.kill_switch_triggered(Some(&from_env)));
+        std::fs::write(&configured, b"").unwrap();
+        assert!(config.kill_switch_triggered(Some(&from_env)));
+        assert!(config.kill_switch_triggered(None));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "html_diff")]
+    #[test]
+    fn html_diff_matches_the_expected_markup() {
+        let cases = [
+            ("", "", ""),
+            ("", "新規", "<ins>新規</ins>"),
+            ("消去", "", "<del>消去</del>"),
+            ("同じ", "同じ", "同じ"),
+            // 英数字の並びは単語ごと、日本語は1文字ずつ比較し、続く同じ操作は1つの要素にまとめる
+            ("HP 120 / MP 30", "HP 95 / MP 30", "HP <del>120</del><ins>95</ins> / MP 30"),
+            ("勇者の攻撃", "魔王の攻撃！", "<del>勇者</del><ins>魔王</ins>の攻撃<ins>！</ins>"),
+            ("1行目\n2行目", "1行目\n3行目\n", "1行目\n<del>2</del><ins>3</ins>行目<ins>\n</ins>"),
+            (
+                r#"a<b> & "c""#,
+                "a<b> & 'c'",
+                "a&lt;b&gt; &amp; <del>&quot;</del><ins>&#39;</ins>c<del>&quot;</del><ins>&#39;</ins>",
+            ),
+        ];
+        for (old, new, expected) in cases {
+            assert_eq!(TextDiffer::render_html_diff(old, new), expected, "{:?} -> {:?}", old, new);
+        }
+    }
+
+    #[cfg(feature = "html_diff")]
+    #[test]
+    fn large_html_diff_falls_back_to_whole_replacement() {
+        assert_eq!(TextDiffer::render_html_diff("a b", "a c"), "a <del>b</del><ins>c</ins>");
+
+        let old = "あ".repeat(600);
+        let new = format!("{}<", "い".repeat(600));
+        assert_eq!(
+            TextDiffer::render_html_diff(&old, &new),
+            format!("<del>{}</del><ins>{}&lt;</ins>", old, "い".repeat(600))
+        );
+    }
+
+    /// 認識のたびに登録したテキストを順番に返すOCR（最後のテキストは繰り返し返す）
+    struct ScriptedTexts {
+        texts: Mutex<Vec<&'static str>>,
+    }
+
+    impl crate::backends::OcrBackend for ScriptedTexts {
+        fn recognize_lines(&self, _image: &DynamicImage, _page_seg_mode: u32) -> Result<Vec<OcrLine>> {
+            let mut texts = lock(&self.texts);
+            let text = if texts.len() > 1 { texts.remove(0) } else { texts[0] };
+            Ok(vec![line(text, 0, 20)])
+        }
+    }
+
+    /// 取得元とOCRの結果を差し替えた監視（明暗の判定は行わない）
+    fn scripted_monitor(source: Box<dyn CaptureSource>, texts: Vec<&'static str>) -> ScreenMonitor {
+        let region = CaptureRegion { x: 0, y: 0, width: 64, height: 32, display: None };
+        let backend = ScriptedTexts { texts: Mutex::new(texts) };
+        let mut engine =
+            OcrEngine::from_parts(None, "eng", crate::backends::OcrBackendKind::Tesseract, Some(Box::new(backend)));
+        // 反転済みの設定なら最初のフレームでの明暗の判定を省くため、OCRの結果の順番がずれない
+        // （前処理は最小限にし、並行して動く他のテストがあっても期限内に認識を終える）
+        engine.set_config(OcrConfig { invert: true, fast_pipeline: true, ..OcrConfig::default() });
+        ScreenMonitor::from_parts(ScreenCapture::with_source(region, source), Arc::new(engine), 10)
+    }
+
+    /// 左右の向きが逆のグラデーションを交互に返す取得元（似たフレームとして認識を省かれないようにする）
+    fn changing_frames() -> Box<dyn CaptureSource> {
+        let gradient = |increasing: bool| {
+            DynamicImage::ImageRgb8(RgbImage::from_fn(64, 32, |x, _| {
+                let level = (x * 4) as u8;
+                Rgb([if increasing { level } else { 255 - level }; 3])
+            }))
+        };
+        Box::new(crate::capture::ImageSequenceSource::new([gradient(true), gradient(false)]))
+    }
+
+    fn block_on<T>(future: impl Future<Output = T>) -> T {
+        tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap().block_on(future)
+    }
+
+    #[test]
+    fn watch_for_returns_the_first_text_that_matches() {
+        let monitor = scripted_monitor(changing_frames(), vec!["読み込み中", "読み込み中", "完了: 42件", "終了"]);
+        let found = block_on(monitor.watch_for(r"完了: \d+件", Duration::from_secs(30)));
+        assert_eq!(found, Ok("完了: 42件".to_string()));
+    }
+
+    #[test]
+    fn watch_for_times_out_when_no_text_matches() {
+        let monitor = scripted_monitor(changing_frames(), vec!["読み込み中"]);
+        let found = block_on(monitor.watch_for("完了", Duration::from_millis(100)));
+        assert_eq!(found, Err(WatchError::Timeout));
+    }
+
+    #[test]
+    fn watch_for_rejects_an_invalid_pattern_before_monitoring() {
+        let monitor = scripted_monitor(changing_frames(), vec!["完了"]);
+        let found = block_on(monitor.watch_for("(完了", Duration::from_secs(5)));
+        assert!(matches!(found, Err(WatchError::InvalidPattern(_))));
+    }
+
+    #[test]
+    fn watch_for_change_returns_the_first_text_event() {
+        let monitor = scripted_monitor(changing_frames(), vec!["開始", "次の画面"]);
+        let event = block_on(monitor.watch_for_change(Duration::from_secs(30))).unwrap();
+        assert!(matches!(event, TextChangeEvent::NewText(text) if text == "開始"));
+    }
+
+    #[test]
+    fn watch_for_change_times_out_on_an_unchanged_screen() {
+        // 同じ画像が続くと2回目以降の認識は省かれ、空のテキストのままではイベントも送られない
+        let image = two_column_image(64, 32);
+        let monitor = scripted_monitor(Box::new(crate::capture::StaticImageSource { image }), vec![""]);
+        let event = block_on(monitor.watch_for_change(Duration::from_millis(100)));
+        assert!(matches!(event, Err(WatchError::Timeout)));
+    }
+
+    /// 白い背景に黒い点を並べた、文字の無い込み入った画像（columns x rows 個）
+    fn busy_frame(columns: u32, rows: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(columns * 10, rows * 10, |x, y| {
+            if x % 10 < 4 && y % 10 < 4 {
+                Rgb([0, 0, 0])
+            } else {
+                Rgb([255, 255, 255])
+            }
+        }))
+    }
+
+    #[test]
+    fn blank_frame_is_not_unreadable() {
+        let blank = DynamicImage::ImageRgb8(RgbImage::from_pixel(100, 40, Rgb([240, 240, 240])));
+        assert!(!MonitorConfig::default().is_unreadable_frame(&blank));
+    }
+
+    #[test]
+    fn busy_frame_is_unreadable() {
+        let config = MonitorConfig::default();
+        assert!(config.is_unreadable_frame(&busy_frame(10, 4)));
+        // ぼやけても前景の成分が残っていれば読み取れない内容とみなす
+        assert!(config.is_unreadable_frame(&busy_frame(10, 4).blur(1.0)));
+    }
+
+    #[test]
+    fn frame_with_few_components_is_not_unreadable() {
+        // 2段組みの画像は分散が大きくても前景の成分が1つだけ
+        let config = MonitorConfig::default();
+        assert!(!config.is_unreadable_frame(&two_column_image(100, 40)));
+        assert!(!config.is_unreadable_frame(&busy_frame(7, 1)));
+        assert!(config.is_unreadable_frame(&busy_frame(8, 1)));
+    }
+
+    #[test]
+    fn unreadable_thresholds_are_configurable() {
+        let frame = busy_frame(10, 4);
+        let disabled = MonitorConfig { unreadable_min_components: 0, ..MonitorConfig::default() };
+        assert!(!disabled.is_unreadable_frame(&frame));
+
+        let strict = MonitorConfig { unreadable_min_components: 41, ..MonitorConfig::default() };
+        assert!(!strict.is_unreadable_frame(&frame));
+        let strict = MonitorConfig { unreadable_min_variance: 16_384.0, ..MonitorConfig::default() };
+        assert!(!strict.is_unreadable_frame(&frame));
+    }
+
+    const WORDS: [&str; 6] = ["HP", "120", "勇者", "ｱｲｳ", "Ａｂｃ", "。"];
+    const SPACES: [&str; 4] = [" ", "\t", "\u{3000}", "  "];
+
+    /// ランダムな単語の行（単語は1つの空白で区切る）
+    fn random_lines(rng: &mut Xorshift) -> Vec<String> {
+        (0..1 + rng.below(3))
+            .map(|_| (0..1 + rng.below(4)).map(|_| rng.pick(&WORDS)).collect::<Vec<_>>().join(" "))
+            .collect()
+    }
+
+    /// 行の前後と単語の間の空白を全角や連続した空白に変え、空行も挟んだテキスト
+    fn respaced(rng: &mut Xorshift, lines: &[String]) -> String {
+        let mut text = String::new();
+        for line in lines {
+            if rng.below(3) == 0 {
+                text.push_str(rng.pick(&SPACES));
+                text.push('\n');
+            }
+            for _ in 0..rng.below(3) {
+                text.push_str(rng.pick(&SPACES));
+            }
+            for (i, word) in line.split(' ').enumerate() {
+                if i > 0 {
+                    for _ in 0..1 + rng.below(3) {
+                        text.push_str(rng.pick(&SPACES));
+                    }
+                }
+                text.push_str(word);
+            }
+            for _ in 0..rng.below(3) {
+                text.push_str(rng.pick(&SPACES));
+            }
+            text.push_str(if rng.below(2) == 0 { "\n" } else { "\r\n" });
+        }
+        text
+    }
+
+    #[test]
+    fn canonicalization_is_idempotent() {
+        let mut rng = Xorshift(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..500 {
+            let lines = random_lines(&mut rng);
+            let text = respaced(&mut rng, &lines);
+            let canonical = canonicalize_for_comparison(&text);
+            assert_eq!(canonicalize_for_comparison(&canonical), canonical, "{:?}", text);
+            assert_eq!(canonical, lines.join("\n"), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn whitespace_variants_are_equivalent() {
+        let mut rng = Xorshift(0x2545_f491_4f6c_dd1d);
+        for _ in 0..500 {
+            let lines = random_lines(&mut rng);
+            let (a, b) = (respaced(&mut rng, &lines), respaced(&mut rng, &lines));
+            assert!(texts_equivalent(&a, &b), "{:?} / {:?}", a, b);
+            assert!(texts_equivalent(&b, &a), "{:?} / {:?}", b, a);
+        }
+    }
+
+    #[test]
+    fn changes_other_than_whitespace_are_not_equivalent() {
+        let mut rng = Xorshift(0x1234_5678_9abc_def1);
+        for _ in 0..500 {
+            let lines = random_lines(&mut rng);
+            let mut changed = lines.clone();
+            changed[0].push('x');
+            let (a, b) = (respaced(&mut rng, &lines), respaced(&mut rng, &changed));
+            assert!(!texts_equivalent(&a, &b), "{:?} / {:?}", a, b);
+        }
+        // 全角と半角の文字は別の文字として扱い、空白が無くなる・増える違いも変化とみなす
+        assert!(!texts_equivalent("Ａｂｃ", "Abc"));
+        assert!(!texts_equivalent("HP 120", "HP120"));
+        assert!(!texts_equivalent("HP 120", "HP\n120"));
+        assert!(texts_equivalent("HP\u{3000}120", "HP 120"));
+    }
+
+    #[test]
+    fn thumbnail_is_attached_only_when_enabled() {
+        use base64::Engine;
+        let image = two_column_image(640, 200);
+        assert_eq!(MonitorConfig::default().thumbnail(&image), None);
+
+        let config = MonitorConfig { attach_thumbnail: true, ..MonitorConfig::default() };
+        let encoded = config.thumbnail(&image).unwrap();
+        assert!(!encoded.is_empty());
+        let png = base64::engine::general_purpose::STANDARD.decode(encoded).unwrap();
+        let thumbnail = image::load_from_memory(&png).unwrap();
+        // 縦横比を保って160x120に収める
+        assert_eq!((thumbnail.width(), thumbnail.height()), (160, 50));
+        assert_eq!(thumbnail.to_rgb8().get_pixel(10, 25).0, [0, 0, 0]);
+        assert_eq!(thumbnail.to_rgb8().get_pixel(150, 25).0, [128, 128, 128]);
+    }
+}
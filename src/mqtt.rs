@@ -0,0 +1,303 @@
+// テキスト変化イベントのMQTTへの配信（ホームオートメーションなどのIoT機器向け）
+//
+// 設定はフィーチャーに関わらず保存できるが、配信はmqttフィーチャー有効時のみ利用できる。
+// イベントはスキーマのバージョン付きのJSONで {topic_prefix}/{領域名}/{イベントの種類} に送信する。
+use serde::{Deserialize, Serialize};
+
+use crate::validation::{Validate, Validator};
+
+/// トピックに使う領域名（アプリの監視は1つの領域のみ）
+#[cfg_attr(not(feature = "mqtt"), allow(dead_code))]
+pub const REGION_LABEL: &str = "default";
+
+/// 送信するパケットの大きさの上限の既定値（KiB、サムネイル付きのイベントも収まる大きさ）
+const DEFAULT_MAX_PACKET_KB: u32 = 256;
+
+/// 送信するパケットの大きさの上限に指定できる最大値（KiB、MQTTの仕様上の上限の256MiB）
+const MAX_PACKET_KB_LIMIT: u32 = 256 * 1024;
+
+/// MQTTへの配信の設定
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MqttSinkConfig {
+    /// ブローカーのホスト（`mqtt://` または `tcp://` で始めてもよい、TLSは未対応）
+    pub broker_url: String,
+    /// ブローカーのポート
+    pub port: u16,
+    /// クライアントID
+    pub client_id: String,
+    /// トピックの接頭辞
+    pub topic_prefix: String,
+    /// QoS（0〜2）
+    pub qos: u8,
+    /// ユーザー名（認証しない場合はNone）
+    pub username: Option<String>,
+    /// パスワード
+    pub password: Option<String>,
+    /// 送信するパケットの大きさの上限（KiB、超えるイベントは送信せずに警告する）
+    pub max_packet_kb: u32,
+}
+
+impl Default for MqttSinkConfig {
+    fn default() -> Self {
+        Self {
+            broker_url: "localhost".to_string(),
+            port: 1883,
+            client_id: "screen_text_monitor".to_string(),
+            topic_prefix: "screen_text_monitor".to_string(),
+            qos: 0,
+            username: None,
+            password: None,
+            max_packet_kb: DEFAULT_MAX_PACKET_KB,
+        }
+    }
+}
+
+impl MqttSinkConfig {
+    /// スキームを除いたブローカーのホスト
+    #[cfg_attr(not(feature = "mqtt"), allow(dead_code))]
+    pub fn host(&self) -> &str {
+        let url = self.broker_url.trim();
+        ["mqtt://", "tcp://"]
+            .iter()
+            .find_map(|scheme| url.strip_prefix(scheme))
+            .unwrap_or(url)
+            .trim_end_matches('/')
+    }
+
+    /// イベントを送信するトピック
+    #[cfg_attr(not(feature = "mqtt"), allow(dead_code))]
+    pub fn topic(&self, event_type: &str) -> String {
+        format!("{}/{}/{}", self.topic_prefix.trim_end_matches('/'), REGION_LABEL, event_type)
+    }
+
+    /// 送信するパケットの大きさの上限（バイト）
+    #[cfg_attr(not(feature = "mqtt"), allow(dead_code))]
+    pub fn max_packet_bytes(&self) -> usize {
+        self.max_packet_kb as usize * 1024
+    }
+
+    /// PUBLISHパケットの固定ヘッダーを除いた大きさ（トピックの長さ、QoS 1以上のパケットID、本文）
+    ///
+    /// rumqttcはこの大きさを送信の上限と比べる。
+    #[cfg_attr(not(feature = "mqtt"), allow(dead_code))]
+    pub fn publish_size(&self, topic: &str, body_len: usize) -> usize {
+        let packet_id = if self.qos > 0 { 2 } else { 0 };
+        2 + topic.len() + packet_id + body_len
+    }
+}
+
+impl Validate for MqttSinkConfig {
+    const PREFIX: &'static str = "mqtt";
+
+    fn check(&self, validator: &mut Validator) {
+        let host = self.host();
+        if host.is_empty() {
+            validator.invalid("broker_url", "ブローカーのホストを指定してください");
+        } else if host.contains("://") || host.contains('/') {
+            validator.invalid("broker_url", "mqtt:// または tcp:// のホストのみ指定できます（TLSは未対応です）");
+        }
+        validator.range("port", self.port, 1, u16::MAX);
+        if self.client_id.trim().is_empty() {
+            validator.invalid("client_id", "クライアントIDを指定してください");
+        }
+        // ワイルドカードはトピックの名前に使えない
+        if self.topic_prefix.trim().is_empty() || self.topic_prefix.contains(['+', '#']) {
+            validator.invalid("topic_prefix", "空の接頭辞とワイルドカード（+、#）は指定できません");
+        }
+        validator.range("qos", self.qos, 0, 2);
+        validator.range("max_packet_kb", self.max_packet_kb, 1, MAX_PACKET_KB_LIMIT);
+        if self.password.is_some() && self.username.is_none() {
+            validator.invalid("password", "パスワードを指定する場合はユーザー名も指定してください");
+        }
+    }
+}
+
+#[cfg(feature = "mqtt")]
+pub use sink::MqttSink;
+
+/// rumqttcによる配信（mqttフィーチャー有効時のみ）
+#[cfg(feature = "mqtt")]
+mod sink {
+    use anyhow::{Context, Result};
+    use rumqttc::{Client, ConnectionError, Event, MqttOptions, Packet, QoS};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::MqttSinkConfig;
+    use crate::events::EventSink;
+    use crate::schema::v1;
+    use crate::validation::Validate;
+
+    /// 送信待ちのメッセージの上限（超えた分は送信しない）
+    const REQUEST_CAPACITY: usize = 256;
+
+    /// 接続を確認する間隔
+    const KEEP_ALIVE: Duration = Duration::from_secs(30);
+
+    /// 再接続までの待ち時間の初期値
+    const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+    /// 再接続までの待ち時間の上限
+    const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+    /// 待っている間に停止の要求を確認する間隔
+    const STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+    /// 起動中のMQTTへの配信（破棄すると切断する）
+    pub struct MqttSink {
+        client: Client,
+        config: MqttSinkConfig,
+        qos: QoS,
+        stopping: Arc<AtomicBool>,
+    }
+
+    impl MqttSink {
+        /// ブローカーへの接続を開始（接続と再接続は別スレッドで行う）
+        pub fn start(config: MqttSinkConfig) -> Result<Self> {
+            config.validate()?;
+            let qos = rumqttc::qos(config.qos).context("QoSは0〜2で指定してください")?;
+
+            let mut options = MqttOptions::new(config.client_id.clone(), config.host(), config.port);
+            options.set_keep_alive(KEEP_ALIVE);
+            // 受信するのは接続の応答などの小さなパケットのみのため、上限は送信と同じにする
+            options.set_max_packet_size(config.max_packet_bytes(), config.max_packet_bytes());
+            if let Some(username) = &config.username {
+                options.set_credentials(username.clone(), config.password.clone().unwrap_or_default());
+            }
+            let (client, mut connection) = Client::new(options, REQUEST_CAPACITY);
+
+            let stopping = Arc::new(AtomicBool::new(false));
+            let thread_stopping = stopping.clone();
+            let broker = format!("{}:{}", config.host(), config.port);
+            thread::Builder::new()
+                .name("mqtt-sink".to_string())
+                .spawn(move || {
+                    let mut backoff = INITIAL_BACKOFF;
+                    // 接続が切れた後も反復を続けると再接続する
+                    for notification in connection.iter() {
+                        match notification {
+                            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                                log::info!("MQTTブローカーに接続しました: {}", broker);
+                                backoff = INITIAL_BACKOFF;
+                            }
+                            Ok(_) => {}
+                            Err(_) if thread_stopping.load(Ordering::Relaxed) => break,
+                            Err(ConnectionError::RequestsDone) => break,
+                            Err(e) => {
+                                log::warn!("MQTTブローカーとの接続エラー（{}ms後に再接続します）: {}", backoff.as_millis(), e);
+                                wait_backoff(backoff, &thread_stopping);
+                                backoff = (backoff * 2).min(MAX_BACKOFF);
+                            }
+                        }
+                        if thread_stopping.load(Ordering::Relaxed) {
+                            break;
+                        }
+                    }
+                    log::info!("MQTTへの配信を終了しました: {}", broker);
+                })
+                .context("MQTTの接続スレッドを起動できません")?;
+
+            Ok(Self {
+                client,
+                config,
+                qos,
+                stopping,
+            })
+        }
+    }
+
+    /// 再接続までの待ち時間（停止の要求があれば打ち切る）
+    fn wait_backoff(backoff: Duration, stopping: &AtomicBool) {
+        let mut waited = Duration::ZERO;
+        while waited < backoff && !stopping.load(Ordering::Relaxed) {
+            let step = STOP_POLL_INTERVAL.min(backoff - waited);
+            thread::sleep(step);
+            waited += step;
+        }
+    }
+
+    impl EventSink for MqttSink {
+        fn publish(&self, event_type: &str, payload: &v1::TextChangedPayload) {
+            let topic = self.config.topic(event_type);
+            let body = match serde_json::to_vec(payload) {
+                Ok(body) => body,
+                Err(e) => {
+                    log::warn!("MQTTのメッセージをJSONに変換できません: {}", e);
+                    return;
+                }
+            };
+            let size = body.len();
+            // 上限を超えるパケットは接続を切られる原因になるため送信しない
+            let packet_size = self.config.publish_size(&topic, size);
+            if packet_size > self.config.max_packet_bytes() {
+                log::warn!(
+                    "MQTTのパケットの大きさの上限（{}バイト）を超えるため送信しません: {}（{}バイト）",
+                    self.config.max_packet_bytes(),
+                    topic,
+                    packet_size
+                );
+                return;
+            }
+            // 接続していない間も送信待ちに積み、上限を超えたら捨てる
+            match self.client.try_publish(topic.as_str(), self.qos, false, body) {
+                Ok(()) => log::debug!("MQTTに送信しました: {}（{}バイト）", topic, size),
+                Err(e) => log::warn!("MQTTに送信できません: {}: {}", topic, e),
+            }
+        }
+
+        fn address(&self) -> String {
+            format!("{}:{}", self.config.host(), self.config.port)
+        }
+    }
+
+    impl Drop for MqttSink {
+        fn drop(&mut self) {
+            self.stopping.store(true, Ordering::Relaxed);
+            let _ = self.client.try_disconnect();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_config_is_valid() {
+        assert!(MqttSinkConfig::default().validate().is_ok());
+        assert_eq!(MqttSinkConfig::default().max_packet_bytes(), 256 * 1024);
+    }
+
+    #[test]
+    fn rejects_out_of_range_packet_limit() {
+        for max_packet_kb in [0, MAX_PACKET_KB_LIMIT + 1] {
+            let config = MqttSinkConfig {
+                max_packet_kb,
+                ..MqttSinkConfig::default()
+            };
+            assert!(config.validate().is_err(), "{}", max_packet_kb);
+        }
+    }
+
+    #[test]
+    fn publish_size_includes_topic_and_packet_id() {
+        let mut config = MqttSinkConfig::default();
+        let topic = config.topic("changed");
+        assert_eq!(topic, "screen_text_monitor/default/changed");
+        assert_eq!(config.publish_size(&topic, 100), 2 + topic.len() + 100);
+        config.qos = 1;
+        assert_eq!(config.publish_size(&topic, 100), 2 + topic.len() + 2 + 100);
+    }
+
+    #[test]
+    fn strips_scheme_from_broker_url() {
+        let config = MqttSinkConfig {
+            broker_url: "mqtt://broker.local/".to_string(),
+            ..MqttSinkConfig::default()
+        };
+        assert_eq!(config.host(), "broker.local");
+    }
+}
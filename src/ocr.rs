@@ -1,47 +1,1032 @@
 // OCR（光学文字認識）機能の実装
 use anyhow::{Result, Context};
-use image::{DynamicImage, ImageBuffer, Luma};
+use image::{DynamicImage, ImageBuffer, Luma, Rgba, RgbaImage};
 use tesseract::Tesseract;
+use std::borrow::Cow;
+use std::fmt;
 use std::fs;
 use std::env;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use serde::{Deserialize, Serialize};
+
+use crate::backends::subprocess::SubprocessBackend;
+use crate::backends::{OcrBackend, OcrBackendKind, OcrBackendPreference};
+use crate::japanese_text::{includes_japanese, normalize_japanese};
+use crate::locking::lock;
+use crate::monitor::edit_distance;
+use crate::ocr_stats::{self, OcrErrorKind};
+use crate::preprocessing::{self, ImageMetrics, ImagePreprocessor};
+use crate::script_check::line_language;
+use crate::tesseract_api::TesseractApi;
+use crate::tiling::ImageRect;
+use crate::transform::CaptureTransform;
+use crate::validation::{Validate, Validator};
+use crate::wizard::RemediationCode;
+
+/// 信頼度ベースライン計測時の認識回数
+const CALIBRATION_ATTEMPTS: usize = 10;
+
+/// 時間の予算が尽きたため省略した処理: 2回目以降の認識試行
+pub const STAGE_EXTRA_ATTEMPTS: &str = "extra-attempts";
+
+/// 時間の予算が尽きたため省略した処理: 簡素化した画像での再認識
+pub const STAGE_SIMPLIFIED_FALLBACK: &str = "simplified-fallback";
+
+/// 既定のページセグメンテーションモード（6 = 均一なブロックの単一テキスト）
+pub const DEFAULT_PAGE_SEG_MODE: u32 = 6;
+
+/// 日本語の言語データの動作確認に使う画像（Unifontで描画した1行のテキスト）
+const WARMUP_REFERENCE_JPN: &[u8] = include_bytes!("assets/warmup_test_jpn.png");
+
+/// WARMUP_REFERENCE_JPN に描かれたテキスト
+const WARMUP_REFERENCE_JPN_TEXT: &str = "画面の文字を認識します";
+
+/// 縦書きと判定した場合のページセグメンテーションモード（5 = 縦書きの単一ブロック）
+const VERTICAL_PAGE_SEG_MODE: u32 = 5;
+
+/// 列方向の投影の分散が行方向のこの倍数を超えたら縦書きと判定する（横書きを優先するため1より大きくする）
+const VERTICAL_VARIANCE_RATIO: f64 = 1.5;
+
+/// サブピクセル検出でエッジとみなす緑チャンネルの輝度差
+const SUBPIXEL_EDGE_THRESHOLD: i32 = 48;
+
+/// サブピクセル検出で色にじみとみなす赤・青チャンネルのずれ
+const SUBPIXEL_FRINGE_THRESHOLD: i32 = 16;
+
+/// サブピクセル描画と判定するのに必要なエッジ画素数
+const SUBPIXEL_MIN_EDGES: usize = 50;
+
+/// サブピクセル描画と判定する色にじみ画素の割合
+const SUBPIXEL_FRINGE_RATIO: f32 = 0.3;
+
+/// 前処理で拡大する目標の幅の既定値（ピクセル）
+pub const DEFAULT_SCALE_TARGET_WIDTH: u32 = 1000;
+
+/// 最小限の前処理での拡大率の上限
+const FAST_PIPELINE_MAX_SCALE: f32 = 2.0;
+
+/// 前処理する画像の幅・高さの上限（超える画像は縮小してから前処理する）
+const MAX_INPUT_DIMENSION: u32 = 4096;
+
+/// 監視中に文字の占める割合を計測する間隔（認識したフレーム数、連結成分の探索を毎回行わないため）
+pub const MONITOR_TEXT_COVERAGE_INTERVAL: u32 = 10;
+
+/// コントラスト強化後の二値化の方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BinarizationMode {
+    /// ヒストグラム均等化のみ（二値化しない）
+    #[default]
+    Equalize,
+    /// ヒストグラム均等化の後に大津の方法で二値化
+    Otsu,
+}
+
+/// グレースケール変換の方式
+///
+/// ClearTypeなどのサブピクセル描画では文字の輪郭に色のにじみが付き、輝度で変換すると
+/// 灰色の縁として残って二値化を乱す。チャンネルの最大値・中央値で変換すると、
+/// 色の付いた縁は背景（明るい背景なら最大値、どちらの背景でも中央値）に近い値になる。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GrayscaleMode {
+    /// 輝度の重み付け（ITU-R BT.709）
+    #[default]
+    Luminance,
+    /// RGBの最大値
+    ChannelMax,
+    /// RGBの中央値
+    ChannelMedian,
+}
+
+/// Tesseractを実行するプロセス
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OcrIsolation {
+    /// アプリと同じプロセスで認識
+    #[default]
+    InProcess,
+    /// 子プロセスで認識（Tesseractがクラッシュしてもアプリは終了せず、子プロセスを再起動する）
+    Subprocess,
+}
+
+/// OCRの設定
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OcrConfig {
+    /// サブピクセル描画（ClearType等）の色にじみ除去
+    /// （Noneの場合は画像から自動判定）
+    #[serde(default)]
+    pub defringe_lcd: Option<bool>,
+    /// 二値化の方式
+    #[serde(default)]
+    pub binarization: BinarizationMode,
+    /// グレースケール変換の方式（サブピクセル描画の色にじみが残る場合は輝度以外を選ぶ）
+    #[serde(default)]
+    pub grayscale_mode: GrayscaleMode,
+    /// 明暗を反転するかどうか（暗い背景に明るい文字の場合）
+    #[serde(default)]
+    pub invert: bool,
+    /// 拡大の目標の幅（ピクセル、これより狭い画像を1.5倍〜4倍に拡大する）
+    #[serde(default = "default_scale_target_width")]
+    pub scale_target_width: u32,
+    /// ページセグメンテーションモード（Noneの場合はエンジンの既定値）
+    #[serde(default)]
+    pub page_seg_mode: Option<u32>,
+    /// 縦書きを自動で判定するかどうか（縦書きの場合はモード5で回転した画像を認識）
+    #[serde(default)]
+    pub auto_detect_orientation: bool,
+    /// 認識しやすくする単語（ユーザー名や専門用語など、Tesseractのuser_words_file）
+    ///
+    /// 辞書はレガシーエンジンの認識に使われる。LSTMエンジンに効かせるには、
+    /// 言語データ側で辞書を使う設定にしておく必要がある。
+    #[serde(default)]
+    pub user_words: Option<Vec<String>>,
+    /// 認識しやすくする文字列のパターン（Tesseractのuser_patterns_file、\d は数字など）
+    #[serde(default)]
+    pub user_patterns: Option<Vec<String>>,
+    /// 使用するOCRエンジン（組み込みOCRの結果が合わない場合はTesseractを指定する）
+    #[serde(default)]
+    pub backend: OcrBackendPreference,
+    /// Tesseractを実行するプロセス（組み込みOCRを使う場合は無視する）
+    #[serde(default)]
+    pub isolation: OcrIsolation,
+    /// 子プロセスで認識する場合の応答を待つ時間（ミリ秒、超えたら子プロセスを再起動する）
+    #[serde(default = "default_worker_timeout_ms")]
+    pub worker_timeout_ms: u64,
+    /// 前処理の最初に適用する幾何補正（曲面のディスプレイや斜めから撮影した映像向け）
+    #[serde(default)]
+    pub transform: CaptureTransform,
+    /// 最小限の前処理にするかどうか（高速モード用、グレースケールと大津の二値化のみで拡大は2倍まで、認識は1回）
+    #[serde(default)]
+    pub fast_pipeline: bool,
+    /// 文字の占める割合（前景の成分の外接矩形の面積の割合）を計測するかどうか
+    /// （監視中は MONITOR_TEXT_COVERAGE_INTERVAL フレームごとに計測する）
+    #[serde(default = "default_measure_text_coverage")]
+    pub measure_text_coverage: bool,
+    /// 日本語の認識結果の崩れ（文字間の空白、長音符とダッシュの取り違えなど）を補正するかどうか
+    /// （Noneの場合は言語にjpnが含まれれば補正する）
+    #[serde(default)]
+    pub japanese_cleanup: Option<bool>,
+    /// 正解のテキストのある画像での動作確認（warm_up_with_validation、監視の開始時にも行う）で許容する文字誤り率（0.0-1.0）
+    #[serde(default = "default_max_acceptable_cer")]
+    pub max_acceptable_cer: f32,
+    /// 小さな画像を拡大する前に同梱のSRCNNモデルで2倍に超解像するかどうか
+    /// （super_resフィーチャーが必要、最小限の前処理では使わない）
+    #[serde(default)]
+    pub use_super_resolution: bool,
+    /// 超解像を適用する画像の大きさ（ピクセル、長い辺がこれ未満のキャプチャに適用する）
+    #[serde(default = "default_super_res_threshold_px")]
+    pub super_res_threshold_px: u32,
+}
+
+impl Default for OcrConfig {
+    fn default() -> Self {
+        Self {
+            defringe_lcd: None,
+            binarization: BinarizationMode::default(),
+            grayscale_mode: GrayscaleMode::default(),
+            invert: false,
+            scale_target_width: DEFAULT_SCALE_TARGET_WIDTH,
+            page_seg_mode: None,
+            auto_detect_orientation: false,
+            user_words: None,
+            user_patterns: None,
+            backend: OcrBackendPreference::default(),
+            isolation: OcrIsolation::default(),
+            worker_timeout_ms: default_worker_timeout_ms(),
+            transform: CaptureTransform::default(),
+            fast_pipeline: false,
+            measure_text_coverage: default_measure_text_coverage(),
+            japanese_cleanup: None,
+            max_acceptable_cer: default_max_acceptable_cer(),
+            use_super_resolution: false,
+            super_res_threshold_px: default_super_res_threshold_px(),
+        }
+    }
+}
+
+impl Validate for OcrConfig {
+    const PREFIX: &'static str = "ocr";
+
+    fn check(&self, validator: &mut Validator) {
+        validator.range("scale_target_width", self.scale_target_width, 200, 3000);
+        if let Some(mode) = self.page_seg_mode {
+            validator.range("page_seg_mode", mode, 0, 13);
+        }
+        // 1行1件のファイルに書き出すため、空の項目と改行を含む項目は受け付けない
+        for (field, entries) in [("user_words", &self.user_words), ("user_patterns", &self.user_patterns)] {
+            let entries = entries.as_deref().unwrap_or_default();
+            if entries.iter().any(|entry| entry.trim().is_empty()) {
+                validator.invalid(field, "空の項目は指定できません");
+            }
+            if entries.iter().any(|entry| entry.contains(['\r', '\n'])) {
+                validator.invalid(field, "改行を含む項目は指定できません");
+            }
+        }
+        validator.range("worker_timeout_ms", self.worker_timeout_ms, 1_000, 120_000);
+        validator.range("max_acceptable_cer", self.max_acceptable_cer, 0.0, 1.0);
+        if !self.transform.is_invertible() {
+            validator.invalid("transform", "逆変換を求められない変換行列です");
+        }
+        if self.use_super_resolution && !cfg!(feature = "super_res") {
+            validator.invalid("use_super_resolution", "super_resフィーチャーを有効にしてビルドする必要があります");
+        }
+        validator.range("super_res_threshold_px", self.super_res_threshold_px, 16, 1000);
+    }
+}
+
+fn default_scale_target_width() -> u32 {
+    DEFAULT_SCALE_TARGET_WIDTH
+}
+
+fn default_worker_timeout_ms() -> u64 {
+    10_000
+}
+
+fn default_measure_text_coverage() -> bool {
+    true
+}
+
+fn default_max_acceptable_cer() -> f32 {
+    0.3
+}
+
+fn default_super_res_threshold_px() -> u32 {
+    100
+}
+
+/// 前処理の各ステップの所要時間（マイクロ秒）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreprocessTimings {
+    /// サブピクセル描画の色にじみ除去（判定を含む）
+    pub defringe_us: u64,
+    /// グレースケール変換
+    pub grayscale_us: u64,
+    /// 拡大
+    pub scale_us: u64,
+    /// コントラスト強化と二値化
+    pub clahe_us: u64,
+    /// ノイズ除去
+    pub denoise_us: u64,
+    /// シャープネス強化
+    pub sharpen_us: u64,
+    /// 余白の追加（現在のパイプラインには無いため常に0）
+    pub padding_us: u64,
+    /// 前処理全体
+    pub total_us: u64,
+}
+
+impl PreprocessTimings {
+    /// 各ステップの所要時間を加算
+    pub fn accumulate(&mut self, other: &PreprocessTimings) {
+        self.defringe_us += other.defringe_us;
+        self.grayscale_us += other.grayscale_us;
+        self.scale_us += other.scale_us;
+        self.clahe_us += other.clahe_us;
+        self.denoise_us += other.denoise_us;
+        self.sharpen_us += other.sharpen_us;
+        self.padding_us += other.padding_us;
+        self.total_us += other.total_us;
+    }
+
+    /// 各ステップの所要時間を割る（平均の計算用）
+    pub fn divided_by(&self, count: u64) -> PreprocessTimings {
+        if count == 0 {
+            return PreprocessTimings::default();
+        }
+        PreprocessTimings {
+            defringe_us: self.defringe_us / count,
+            grayscale_us: self.grayscale_us / count,
+            scale_us: self.scale_us / count,
+            clahe_us: self.clahe_us / count,
+            denoise_us: self.denoise_us / count,
+            sharpen_us: self.sharpen_us / count,
+            padding_us: self.padding_us / count,
+            total_us: self.total_us / count,
+        }
+    }
+
+    /// ステップ名と所要時間の一覧
+    pub fn steps(&self) -> [(&'static str, u64); 7] {
+        [
+            ("defringe", self.defringe_us),
+            ("grayscale", self.grayscale_us),
+            ("scale", self.scale_us),
+            ("clahe", self.clahe_us),
+            ("denoise", self.denoise_us),
+            ("sharpen", self.sharpen_us),
+            ("padding", self.padding_us),
+        ]
+    }
+}
+
+/// 一時ファイル名の連番（同じプロセス内で複数の認識が並行しても衝突しないように）
+static TEMP_FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// 認識用の一時画像ファイルのパスを取得
+fn temp_image_path(prefix: &str) -> PathBuf {
+    let id = TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed);
+    env::temp_dir().join(format!("{}_{}_{}.bmp", prefix, std::process::id(), id))
+}
+
+/// OCRエンジンの初期化のエラー（anyhow::Errorからdowncastして種類を判別する）
+#[derive(Debug, Clone)]
+pub enum OcrInitError {
+    /// 言語データのパスを文字列に変換できない
+    InvalidDataPath(PathBuf),
+    /// 言語データが無い、またはTesseractを初期化できない
+    MissingLanguageData { language: String, message: String },
+    /// 認識用の一時ファイルを書き込めない（ディスクの空きや一時的なロックなど）
+    TempIo(String),
+    /// 動作確認の画像を正しく認識できない（言語データの取り違えや破損など）
+    QualityCheckFailed { language: String, message: String },
+}
+
+impl OcrInitError {
+    /// 時間をおいて初期化し直せば成功する見込みがあるか
+    pub fn is_recoverable(&self) -> bool {
+        matches!(self, OcrInitError::TempIo(_))
+    }
+
+    /// 初期化の失敗の対処方法（アプリのディレクトリがあり、照合できる言語ならダウンロードで解決できる）
+    pub fn remediation(&self, has_app_tessdata: bool) -> RemediationCode {
+        match self {
+            OcrInitError::InvalidDataPath(_) => RemediationCode::InstallTesseract,
+            // ダウンロードした言語データを照合できない言語は、手動での導入を案内する
+            OcrInitError::MissingLanguageData { language, .. } if has_app_tessdata && crate::tessdata::is_downloadable(language) => {
+                RemediationCode::DownloadLanguage
+            }
+            OcrInitError::MissingLanguageData { .. } => RemediationCode::InstallTesseract,
+            OcrInitError::QualityCheckFailed { language, .. } if has_app_tessdata && crate::tessdata::is_downloadable(language) => {
+                RemediationCode::DownloadLanguage
+            }
+            OcrInitError::QualityCheckFailed { .. } => RemediationCode::InstallTesseract,
+            OcrInitError::TempIo(_) => RemediationCode::Retry,
+        }
+    }
+}
+
+impl fmt::Display for OcrInitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OcrInitError::InvalidDataPath(path) => write!(f, "言語データのパスの変換に失敗しました: {}", path.display()),
+            OcrInitError::MissingLanguageData { language, message } => {
+                write!(f, "Tesseract（{}）の初期化テストに失敗しました: {}", language, message)
+            }
+            OcrInitError::TempIo(message) => write!(f, "認識用の一時ファイルを書き込めません: {}", message),
+            OcrInitError::QualityCheckFailed { language, message } => {
+                write!(f, "言語 {} の動作確認に失敗しました: {}", language, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for OcrInitError {}
+
+/// 認識用の一時ファイルを書き込めるか確認する（認識のたびに一時ファイルを使うため、監視の開始時に確認する）
+pub fn check_temp_dir() -> Result<()> {
+    let path = temp_image_path("ocr_probe");
+    let written = fs::write(&path, b"probe").map_err(|e| OcrInitError::TempIo(format!("{}: {}", path.display(), e)));
+    let _ = fs::remove_file(&path);
+    written.map_err(anyhow::Error::new)
+}
+
+/// エンジンの呼び出しの所要時間をOCRの統計に記録（失敗は認識が最終的に失敗した場合にだけ数える）
+fn timed_engine_call<T>(call: impl FnOnce() -> Result<T>) -> Result<T> {
+    let start = Instant::now();
+    let result = call();
+    ocr_stats::record_engine_call(start.elapsed());
+    if result.is_err() {
+        ocr_stats::note_error(OcrErrorKind::Engine);
+    }
+    result
+}
+
+/// 経過時間をマイクロ秒で取得
+fn elapsed_us(start: Instant) -> u64 {
+    start.elapsed().as_micros() as u64
+}
+
+/// テキストの向き
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextOrientation {
+    /// 横書き
+    Horizontal,
+    /// 縦書き（上から下、右から左）
+    Vertical,
+}
+
+/// 投影プロファイルからテキストの向きを判定
+///
+/// 横書きでは行と行間が交互に並ぶため行ごとの文字の画素数（行方向の投影）のばらつきが大きく、
+/// 縦書きでは列ごとの投影のばらつきが大きくなる。文字の画素は平均輝度で分けた少ない側とする。
+pub fn detect_text_orientation(image: &DynamicImage) -> TextOrientation {
+    let gray = image.to_luma8();
+    let (width, height) = gray.dimensions();
+    if width == 0 || height == 0 {
+        return TextOrientation::Horizontal;
+    }
+
+    let mean = gray.pixels().map(|p| p[0] as u64).sum::<u64>() / (width as u64 * height as u64);
+    let dark_count = gray.pixels().filter(|p| (p[0] as u64) < mean).count();
+    let ink_is_dark = dark_count * 2 <= (width * height) as usize;
+
+    let mut row_sums = vec![0u32; height as usize];
+    let mut col_sums = vec![0u32; width as usize];
+    for (x, y, pixel) in gray.enumerate_pixels() {
+        if ((pixel[0] as u64) < mean) == ink_is_dark {
+            row_sums[y as usize] += 1;
+            col_sums[x as usize] += 1;
+        }
+    }
+
+    // 行と列で画素数が異なるため、割合にしてから比べる
+    let row_variance = profile_variance(&row_sums, width);
+    let col_variance = profile_variance(&col_sums, height);
+    if col_variance > row_variance * VERTICAL_VARIANCE_RATIO {
+        TextOrientation::Vertical
+    } else {
+        TextOrientation::Horizontal
+    }
+}
+
+/// 投影（文字の画素数の割合）の分散
+fn profile_variance(sums: &[u32], length: u32) -> f64 {
+    let ratios: Vec<f64> = sums.iter().map(|&sum| sum as f64 / length as f64).collect();
+    let mean = ratios.iter().sum::<f64>() / ratios.len() as f64;
+    ratios.iter().map(|ratio| (ratio - mean).powi(2)).sum::<f64>() / ratios.len() as f64
+}
 
 /// OCRエンジンのラッパー構造体
 pub struct OcrEngine {
-    // Tesseractは毎回新しいインスタンスを作成するため、フィールドは不要
+    // Tesseractは毎回新しいインスタンスを作成するため、インスタンス自体は保持しない
     // （Bus Error回避のため、共有インスタンスではなく都度作成方式を採用）
+    /// 言語ごとの信頼度ベースライン（0.0-1.0、未計測ならNone）
+    calibrated_baseline: Option<f32>,
+    /// 言語データ（.traineddata）のディレクトリ（Noneの場合はTesseractの既定パス）
+    tessdata_dir: Option<String>,
+    /// 認識言語（Tesseractの言語コード）
+    language: String,
+    /// 使用するOCRエンジンの種類
+    backend_kind: OcrBackendKind,
+    /// Tesseract以外のエンジン（Tesseractを使う場合はNone）
+    backend: Option<Box<dyn OcrBackend>>,
+    /// ページセグメンテーションモード（tessedit_pageseg_mode）
+    page_seg_mode: u32,
+    /// パイプライン追跡時に中間画像を保存するかどうか
+    debug_pipeline: bool,
+    /// OCRの設定
+    config: OcrConfig,
+    /// 前回の取得以降に行った前処理の所要時間の合計
+    preprocess_timings: Mutex<Option<PreprocessTimings>>,
+    /// 直前に前処理した画像を保持するかどうか
+    retain_preprocessed: bool,
+    /// 直前に前処理した画像（retain_preprocessed有効時のみ）
+    last_preprocessed: Mutex<Option<DynamicImage>>,
+    /// 設定の単語を書き出したファイル（Tesseractの作成時に指定する）
+    user_words_file: Option<PathBuf>,
+    /// 設定のパターンを書き出したファイル
+    user_patterns_file: Option<PathBuf>,
+    /// 任意の処理を行ってよい期限（Noneの場合は制限なし）
+    deadline: Mutex<Option<Instant>>,
+    /// 前回の取得以降に期限切れで省略した処理
+    skipped_stages: Mutex<Vec<&'static str>>,
+    /// 文字の占める割合を計測する間隔（認識したフレーム数、1なら毎回）
+    text_coverage_interval: u32,
+    /// 文字の占める割合の計測の要求の回数
+    text_coverage_requests: AtomicU32,
+}
+
+impl Drop for OcrEngine {
+    fn drop(&mut self) {
+        // 単語・パターンの一時ファイルを削除
+        replace_vocabulary_file(&mut self.user_words_file, "ocr_user_words", None);
+        replace_vocabulary_file(&mut self.user_patterns_file, "ocr_user_patterns", None);
+    }
 }
 
 impl OcrEngine {
     /// 新しいOCRエンジンを作成
     pub fn new() -> Result<Self> {
-        // Tesseractの動作確認（初期化テスト）- 日本語のみ対応
-        let _test_tesseract = Tesseract::new(None, Some("jpn"))
-            .context("Tesseract（日本語）の初期化テストに失敗しました")?;
-        
-        log::info!("Tesseractの動作確認が完了しました（Bus Error回避）");
+        Self::with_tessdata_dir(None)
+    }
+
+    /// 言語データのディレクトリを指定してOCRエンジンを作成
+    pub fn with_tessdata_dir(tessdata_dir: Option<PathBuf>) -> Result<Self> {
+        Self::with_language(tessdata_dir, crate::tessdata::DEFAULT_LANGUAGE)
+    }
+
+    /// 言語データのディレクトリと認識言語を指定してOCRエンジンを作成
+    pub fn with_language(tessdata_dir: Option<PathBuf>, language: &str) -> Result<Self> {
+        Self::with_backend(tessdata_dir, language, OcrBackendPreference::Auto)
+    }
+
+    /// 使用するエンジンの指定に従ってOCRエンジンを作成
+    pub fn with_backend(tessdata_dir: Option<PathBuf>, language: &str, preference: OcrBackendPreference) -> Result<Self> {
+        let tessdata_dir = match tessdata_dir {
+            Some(dir) => Some(
+                dir.to_str()
+                    .ok_or_else(|| OcrInitError::InvalidDataPath(dir.clone()))?
+                    .to_string(),
+            ),
+            None => None,
+        };
+
+        // 組み込みOCRが使える環境ではTesseractを必要としない
+        let backend_kind = OcrBackendKind::detect(language, preference);
+        let backend = backend_kind.create(language)?;
+        if backend.is_none() {
+            // Tesseractの動作確認（初期化テスト）
+            let _test_tesseract = Tesseract::new(tessdata_dir.as_deref(), Some(language)).map_err(|e| {
+                OcrInitError::MissingLanguageData {
+                    language: language.to_string(),
+                    message: e.to_string(),
+                }
+            })?;
+
+            log::info!("Tesseractの動作確認が完了しました（Bus Error回避）");
+        }
+        log::info!("OCRエンジン: {:?}", backend_kind);
+
+        Ok(Self::from_parts(tessdata_dir, language, backend_kind, backend))
+    }
+
+    /// 初期化の確認を済ませたエンジンの構成から作成（テストではTesseractを使わずに作成する）
+    pub(crate) fn from_parts(
+        tessdata_dir: Option<String>,
+        language: &str,
+        backend_kind: OcrBackendKind,
+        backend: Option<Box<dyn OcrBackend>>,
+    ) -> Self {
+        Self {
+            calibrated_baseline: None,
+            tessdata_dir,
+            language: language.to_string(),
+            backend_kind,
+            backend,
+            page_seg_mode: DEFAULT_PAGE_SEG_MODE,
+            debug_pipeline: false,
+            config: OcrConfig::default(),
+            preprocess_timings: Mutex::new(None),
+            retain_preprocessed: false,
+            last_preprocessed: Mutex::new(None),
+            user_words_file: None,
+            user_patterns_file: None,
+            deadline: Mutex::new(None),
+            skipped_stages: Mutex::new(Vec::new()),
+            text_coverage_interval: 1,
+            text_coverage_requests: AtomicU32::new(0),
+        }
+    }
+
+    /// 正解のテキストが分かっている画像を認識し、文字誤り率（0.0以上）を返す
+    ///
+    /// 監視を始める前に、言語データが正しくインストールされ動作しているかを確かめる。
+    /// 文字誤り率が設定の max_acceptable_cer を超える場合はエラーにする。
+    pub fn warm_up_with_validation(&self, reference_image: &DynamicImage, expected_text: &str) -> Result<f32> {
+        if expected_text.trim().is_empty() {
+            anyhow::bail!("正解のテキストを指定してください");
+        }
+        let result = self.recognize_detailed(reference_image).context("動作確認の画像を認識できませんでした")?;
+        let cer = character_error_rate(&result.text, expected_text);
+        log::info!("OCRの動作確認: 文字誤り率 {:.3}（言語: {}）", cer, self.language);
+        if cer > self.config.max_acceptable_cer {
+            anyhow::bail!(
+                "OCRの品質が不十分です: 文字誤り率 {:.1}% が上限 {:.1}% を超えています（言語 {} のデータが正しくインストールされているか確認してください、認識結果: {:?}）",
+                cer * 100.0,
+                self.config.max_acceptable_cer * 100.0,
+                self.language,
+                result.text
+            );
+        }
+        Ok(cer)
+    }
 
-        Ok(Self {})
+    /// 同梱の画像で言語データを動作確認し、文字誤り率を返す（同梱の画像の無い言語は確認せずNone）
+    ///
+    /// 同梱の画像はキャプチャではないため、幾何補正と明暗の反転は外して認識する。
+    pub fn validate_language_pack(&mut self) -> Result<Option<f32>> {
+        if !includes_japanese(&self.language) {
+            return Ok(None);
+        }
+        let reference = image::load_from_memory(WARMUP_REFERENCE_JPN).context("動作確認の画像を読み込めません")?;
+        let config = self.config.clone();
+        self.config.transform = CaptureTransform::None_;
+        self.config.invert = false;
+        let result = self.warm_up_with_validation(&reference, WARMUP_REFERENCE_JPN_TEXT);
+        self.config = config;
+        result.map(Some).map_err(|e| {
+            anyhow::Error::new(OcrInitError::QualityCheckFailed {
+                language: self.language.clone(),
+                message: format!("{:#}", e),
+            })
+        })
     }
 
     /// 画像から文字を認識
+    #[allow(dead_code)]
     pub fn recognize_text(&self, image: &DynamicImage) -> Result<String> {
-        // 画像の前処理
-        let processed_image = self.preprocess_image(image)?;
+        self.recognize_detailed(image).map(|result| result.text)
+    }
+
+    /// 画像から文字を認識し、正規化済みの信頼度付きで結果を返す
+    ///
+    /// 文字の占める割合は measure_text_coverage が有効な場合に、計測の間隔ごとに計測する。
+    pub fn recognize_detailed(&self, image: &DynamicImage) -> Result<OcrResult> {
+        ocr_stats::recognition(
+            || {
+                let corrected = self
+                    .correct_geometry(image)
+                    .inspect_err(|_| ocr_stats::note_error(OcrErrorKind::Preprocess))?;
+                let mut result = self.recognize_detailed_corrected(&corrected)?;
+                result.metrics.text_coverage = self.text_coverage_due().then(|| preprocessing::text_coverage(&corrected));
+                Ok(result)
+            },
+            OcrResult::stats_outcome,
+        )
+    }
+
+    /// 文字の占める割合を計測する間隔を設定（認識したフレーム数、監視中は毎回計測しない）
+    pub fn set_text_coverage_interval(&mut self, frames: u32) {
+        self.text_coverage_interval = frames.max(1);
+    }
+
+    /// 計測の間隔ごとにキャプチャの文字の占める割合を計測（recognize_detailedを使わない認識の経路用）
+    pub fn sample_text_coverage(&self, image: &DynamicImage) -> Option<f32> {
+        if !self.text_coverage_due() {
+            return None;
+        }
+        let corrected = self.correct_geometry(image).ok()?;
+        Some(preprocessing::text_coverage(&corrected))
+    }
+
+    /// 今回の認識で文字の占める割合を計測するかどうか（計測しない設定ならfalse）
+    fn text_coverage_due(&self) -> bool {
+        self.config.measure_text_coverage
+            && self.text_coverage_requests.fetch_add(1, Ordering::Relaxed).checked_rem(self.text_coverage_interval) == Some(0)
+    }
+
+    /// 幾何補正の済んだ画像（correct_geometryの結果やその一部）から文字を認識（文字の占める割合は計測しない）
+    ///
+    /// 列やタイルごとの認識のように別の認識の中で呼んだ場合は、統計には外側の認識の1回として数える。
+    pub fn recognize_detailed_corrected(&self, image: &DynamicImage) -> Result<OcrResult> {
+        ocr_stats::recognition(
+            || {
+                // 画像の前処理
+                let (processed_image, _) = self
+                    .preprocess_image(image)
+                    .inspect_err(|_| ocr_stats::note_error(OcrErrorKind::Preprocess))?;
+                // 前処理の強調の影響を受けないよう、前処理の前の画像で計算する（傾きは画像の向きのまま報告する）
+                let metrics = ImageMetrics::measure(image);
+
+                let (processed_image, page_seg_mode, _) = self.orient(processed_image);
+
+                // 複数回認識で精度向上
+                let (text, raw_confidence) = self.recognize_with_multiple_attempts(&processed_image, page_seg_mode)?;
+                let confidence = raw_confidence.map(|raw| self.normalize_confidence(raw));
+                Ok(OcrResult::new(text, confidence, metrics))
+            },
+            OcrResult::stats_outcome,
+        )
+    }
+
+    /// 前処理の各段階と認識結果を記録しながら認識（トラブルシューティング用）
+    pub fn recognize_with_pipeline_trace(&self, image: &DynamicImage) -> Result<PipelineTrace> {
+        ocr_stats::recognition(|| self.trace_pipeline_steps(image), |(_, characters, confidence)| (*characters, *confidence))
+            .map(|(trace, _, _)| trace)
+    }
+
+    /// 前処理の各段階と認識結果を記録しながら認識（統計に記録する文字数と信頼度も返す）
+    fn trace_pipeline_steps(&self, image: &DynamicImage) -> Result<(PipelineTrace, u64, Option<f32>)> {
+        let mut steps = Vec::new();
+        let step_start = Instant::now();
+        let corrected = self
+            .correct_geometry(image)
+            .inspect_err(|_| ocr_stats::note_error(OcrErrorKind::Preprocess))?;
+        if self.config.transform != CaptureTransform::None_ {
+            self.record_step(&mut Some(&mut steps), "transform", step_start, String::new(), || corrected.clone().into_owned());
+        }
+        let (processed_image, _) = self
+            .preprocess_traced(&corrected, Some(&mut steps))
+            .inspect_err(|_| ocr_stats::note_error(OcrErrorKind::Preprocess))?;
+        let (processed_image, page_seg_mode, orientation) = self.orient(processed_image);
+
+        // 最後のステップとしてOCR結果を記録
+        let step_start = Instant::now();
+        let (text, raw_confidence) = self.recognize_with_multiple_attempts(&processed_image, page_seg_mode)?;
+        let confidence = raw_confidence.map(|raw| self.normalize_confidence(raw));
+        let characters = ocr_stats::count_characters(&text);
+        steps.push(PipelineStep {
+            name: "ocr".to_string(),
+            output_image_base64: String::new(),
+            duration_ms: step_start.elapsed().as_millis() as u64,
+            notes: format!(
+                "信頼度 {}（向き {:?}、モード {}）\n{}",
+                confidence.map_or("なし".to_string(), |confidence| format!("{:.3}", confidence)),
+                orientation,
+                page_seg_mode,
+                text
+            ),
+        });
+
+        Ok((PipelineTrace { steps }, characters, confidence))
+    }
+
+    /// 認識言語（Tesseractの言語コード）
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// 言語データのディレクトリ（Noneの場合はTesseractの既定パス）
+    pub fn tessdata_dir(&self) -> Option<PathBuf> {
+        self.tessdata_dir.as_ref().map(PathBuf::from)
+    }
+
+    /// 現在のOCRの設定
+    pub fn config(&self) -> &OcrConfig {
+        &self.config
+    }
+
+    /// 同じ言語・言語データ・信頼度のベースラインで、OCRの設定だけを変えたエンジンを作成
+    pub fn with_config_override(&self, config: OcrConfig) -> Result<OcrEngine> {
+        let mut engine = OcrEngine::with_language(self.tessdata_dir(), &self.language)?;
+        engine.set_calibrated_baseline(self.calibrated_baseline);
+        engine.set_config(config);
+        Ok(engine)
+    }
+
+    /// パイプライン追跡時に中間画像を保存するかどうかを設定
+    pub fn set_debug_pipeline(&mut self, enabled: bool) {
+        self.debug_pipeline = enabled;
+    }
+
+    /// 使用しているOCRエンジンの種類
+    #[allow(dead_code)]
+    pub fn backend_kind(&self) -> OcrBackendKind {
+        self.backend_kind
+    }
+
+    /// ページセグメンテーションモードを変更（0-13、Tesseract使用時のみ有効）
+    pub fn set_page_seg_mode(&mut self, mode: u32) -> Result<()> {
+        if mode > 13 {
+            anyhow::bail!("ページセグメンテーションモードは 0 以上 13 以下で指定してください（指定値: {}）", mode);
+        }
+        self.page_seg_mode = mode;
+        Ok(())
+    }
+
+    /// 縦書きを自動で判定するかどうかを設定
+    #[allow(dead_code)]
+    pub fn set_auto_detect_orientation(&mut self, enabled: bool) {
+        self.config.auto_detect_orientation = enabled;
+    }
+
+    /// 認識しやすくする文字列のパターンを設定（Tesseractのuser_patterns_file、空なら解除）
+    ///
+    /// パターンは一時ファイルに1行に1つずつ書き出し、認識ごとに作成するTesseractの初期化時に渡すため
+    /// 次の認識から使われる。Tesseractのパターンは正規表現ではなく、次のエスケープで文字の種類を表す
+    /// （その他の文字はそのまま一致）。
+    ///
+    /// - `\c` 英字、`\d` 数字、`\n` 英数字、`\p` 記号、`\a` 小文字、`\A` 大文字
+    /// - `\*` 直前の文字の種類の0回以上の繰り返し
+    ///
+    /// 例:
+    ///
+    /// - 時刻（`12:34:56`）: `\d\d:\d\d:\d\d`
+    /// - 動画のタイムコード（`1:02:03` と `12:34`）: `\d\*:\d\d:\d\d` と `\d\*:\d\d`
+    /// - バージョン（`v1.2.3`、桁数が変わる場合も含む）: `v\d\*.\d\*.\d\*`
+    /// - 日付（`2024-01-31`）: `\d\d\d\d-\d\d-\d\d`
+    ///
+    /// user_wordsと同じく、LSTMエンジンに効かせるには言語データ側で辞書を使う設定にしておく必要がある。
+    #[allow(dead_code)]
+    pub fn set_user_patterns(&mut self, patterns: Vec<String>) -> Result<()> {
+        let config = OcrConfig {
+            user_patterns: (!patterns.is_empty()).then_some(patterns),
+            ..self.config.clone()
+        };
+        config.validate()?;
+        let expects_file = config.user_patterns.is_some();
+        self.set_config(config);
+        if expects_file && self.user_patterns_file.is_none() {
+            anyhow::bail!("パターンのファイルを書き出せません");
+        }
+        Ok(())
+    }
+
+    /// 設定したパターンを解除
+    #[allow(dead_code)]
+    pub fn clear_user_patterns(&mut self) {
+        self.set_config(OcrConfig {
+            user_patterns: None,
+            ..self.config.clone()
+        });
+    }
+
+    /// 前処理済みの画像の向きを判定し、認識する画像とページセグメンテーションモードを決める
+    ///
+    /// 縦書きの場合は時計回りに90度回転した画像とモード5を返す（判定が無効なら常に横書き）。
+    fn orient(&self, processed_image: DynamicImage) -> (DynamicImage, u32, TextOrientation) {
+        if !self.config.auto_detect_orientation {
+            return (processed_image, self.page_seg_mode, TextOrientation::Horizontal);
+        }
+        match detect_text_orientation(&processed_image) {
+            TextOrientation::Vertical => {
+                log::debug!("縦書きと判定しました");
+                (processed_image.rotate90(), VERTICAL_PAGE_SEG_MODE, TextOrientation::Vertical)
+            }
+            TextOrientation::Horizontal => (processed_image, self.page_seg_mode, TextOrientation::Horizontal),
+        }
+    }
+
+    /// OCRの設定を変更（ページセグメンテーションモードの指定があれば合わせて変更）
+    pub fn set_config(&mut self, config: OcrConfig) {
+        if let Some(mode) = config.page_seg_mode {
+            self.page_seg_mode = mode;
+        }
+        // Tesseractは認識ごとに作成して初期化時にファイルを渡すため、書き出し直したファイルは次の認識から使われる
+        if config.user_words != self.config.user_words {
+            replace_vocabulary_file(&mut self.user_words_file, "ocr_user_words", config.user_words.as_deref());
+        }
+        if config.user_patterns != self.config.user_patterns {
+            replace_vocabulary_file(&mut self.user_patterns_file, "ocr_user_patterns", config.user_patterns.as_deref());
+        }
+        // エンジンの指定が変わったら選択し直す（組み込みOCRを作成できなければTesseractを使う）
+        let backend_changed = config.backend != self.config.backend;
+        if backend_changed {
+            self.backend_kind = OcrBackendKind::detect(&self.language, config.backend);
+            self.backend = self.backend_kind.create(&self.language).unwrap_or_else(|e| {
+                log::warn!("指定のOCRエンジンを作成できないため、Tesseractを使います: {:#}", e);
+                self.backend_kind = OcrBackendKind::Tesseract;
+                None
+            });
+        }
+        // 子プロセスは設定を引数で受け取るため、設定が変わったら作り直す
+        let tesseract_kind = matches!(self.backend_kind, OcrBackendKind::Tesseract | OcrBackendKind::TesseractSubprocess);
+        let rebuild = backend_changed
+            || config.isolation != self.config.isolation
+            || (config.isolation == OcrIsolation::Subprocess && config != self.config);
+        if tesseract_kind && rebuild {
+            match config.isolation {
+                OcrIsolation::InProcess => {
+                    self.backend_kind = OcrBackendKind::Tesseract;
+                    self.backend = None;
+                }
+                OcrIsolation::Subprocess => {
+                    self.backend_kind = OcrBackendKind::TesseractSubprocess;
+                    self.backend = Some(Box::new(SubprocessBackend::new(
+                        self.tessdata_dir.as_deref(),
+                        &self.language,
+                        &config,
+                    )));
+                }
+            }
+        }
+        self.config = config;
+    }
+
+    /// 前回の取得以降に子プロセスを再起動した回数（子プロセスで認識しない場合は0）
+    pub fn take_worker_restarts(&self) -> u64 {
+        self.backend.as_ref().map_or(0, |backend| backend.take_restarts())
+    }
+
+    /// 前回の取得以降に行った前処理の所要時間の合計を取得（前処理が無ければNone）
+    ///
+    /// 部分OCRなどで1フレームに複数回前処理した場合は合計を返す。
+    pub fn take_preprocess_timings(&self) -> Option<PreprocessTimings> {
+        lock(&self.preprocess_timings).take()
+    }
+
+    /// 直前に前処理した画像を保持するかどうかを設定（無効時は画像を複製しない）
+    pub fn set_retain_preprocessed(&mut self, enabled: bool) {
+        self.retain_preprocessed = enabled;
+    }
+
+    /// 直前に前処理した画像（Tesseractに渡した画像）を取得
+    pub fn take_last_preprocessed_image(&self) -> Option<DynamicImage> {
+        self.last_preprocessed
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .take()
+    }
+
+    /// 任意の処理（2回目以降の認識試行、簡素化した画像での再認識）を行ってよい期限を設定
+    ///
+    /// 期限を過ぎると任意の処理は省略され、take_skipped_stagesで確認できる。
+    pub fn set_deadline(&self, deadline: Option<Instant>) {
+        *self.deadline.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = deadline;
+    }
+
+    /// 前回の取得以降に期限切れで省略した処理を取得
+    pub fn take_skipped_stages(&self) -> Vec<&'static str> {
+        std::mem::take(&mut *self.skipped_stages.lock().unwrap_or_else(|poisoned| poisoned.into_inner()))
+    }
+
+    /// 期限を過ぎていれば処理を省略したことを記録してtrueを返す
+    fn skip_if_over_deadline(&self, stage: &'static str) -> bool {
+        let over = self
+            .deadline
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .is_some_and(|deadline| Instant::now() >= deadline);
+        if over {
+            let mut skipped = self.skipped_stages.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            if !skipped.contains(&stage) {
+                log::debug!("時間の予算が尽きたため省略: {}", stage);
+                skipped.push(stage);
+            }
+        }
+        over
+    }
+
+    /// 同じ画像を複数回認識し、安定した平均信頼度を言語ごとのベースラインとして記録
+    ///
+    /// 信頼度は言語によって系統的に異なる（日本語の70%が英語の85%相当など）ため、
+    /// 計測したベースラインで生の信頼度を割ることで言語間の差を吸収する。
+    pub fn calibrate_confidence_baseline(&mut self, test_image: &DynamicImage) -> Result<f32> {
+        // 画像の前処理は1回だけ行い、同じ画像で認識を繰り返す
+        let corrected = self.correct_geometry(test_image)?;
+        let (processed_image, _) = self.preprocess_image(&corrected)?;
+
+        let mut confidences = Vec::with_capacity(CALIBRATION_ATTEMPTS);
+        for i in 0..CALIBRATION_ATTEMPTS {
+            match self.recognize_with_fallback(&processed_image, self.page_seg_mode) {
+                Ok((_, None)) => {
+                    return Err(anyhow::anyhow!("ベースライン計測: 信頼度を返さないOCRエンジンでは計測できません"));
+                }
+                Ok((text, Some(confidence))) => {
+                    if !text.trim().is_empty() {
+                        confidences.push(confidence);
+                    }
+                }
+                Err(e) => {
+                    log::warn!("ベースライン計測 {} 回目の認識に失敗: {}", i + 1, e);
+                }
+            }
+        }
+
+        if confidences.is_empty() {
+            return Err(anyhow::anyhow!("ベースライン計測: テキストを認識できませんでした"));
+        }
+
+        // 外れ値の影響を抑えるため、3件以上あれば最小値と最大値を除いて平均する
+        confidences.sort_by(|a, b| a.total_cmp(b));
+        let stable = if confidences.len() >= 3 {
+            &confidences[1..confidences.len() - 1]
+        } else {
+            &confidences[..]
+        };
+        let baseline = stable.iter().sum::<f32>() / stable.len() as f32;
+
+        if baseline <= 0.0 {
+            return Err(anyhow::anyhow!("ベースライン計測: 信頼度が0のため計測結果を採用できません"));
+        }
+
+        log::info!(
+            "信頼度ベースラインを計測しました: {:.3}（有効な認識 {}/{} 回）",
+            baseline,
+            confidences.len(),
+            CALIBRATION_ATTEMPTS
+        );
+        self.calibrated_baseline = Some(baseline);
 
-        // 複数回認識で精度向上
-        self.recognize_with_multiple_attempts(&processed_image)
+        Ok(baseline)
     }
 
-    /// 複数回認識による精度向上
-    fn recognize_with_multiple_attempts(&self, image: &DynamicImage) -> Result<String> {
+    /// 計測済みのベースラインを設定（別スレッドで計測した値の引き継ぎ用）
+    pub fn set_calibrated_baseline(&mut self, baseline: Option<f32>) {
+        self.calibrated_baseline = baseline.filter(|b| *b > 0.0);
+    }
+
+    /// 生の信頼度（0.0-1.0）をベースラインで正規化
+    pub fn normalize_confidence(&self, raw_confidence: f32) -> f32 {
+        match self.calibrated_baseline {
+            Some(baseline) => (raw_confidence / baseline).clamp(0.0, 1.0),
+            None => raw_confidence.clamp(0.0, 1.0),
+        }
+    }
+
+    /// 複数回認識による精度向上（テキストと生の平均信頼度を返す、信頼度を返さないエンジンの場合はNone）
+    fn recognize_with_multiple_attempts(&self, image: &DynamicImage, page_seg_mode: u32) -> Result<(String, Option<f32>)> {
         let mut results = Vec::new();
+        let mut confidences = Vec::new();
         
-        // 3回認識を試行
-        for i in 0..3 {
-            match self.recognize_with_fallback(image) {
-                Ok(text) => {
+        // 3回認識を試行（組み込みOCRは結果が変わらないため1回、子プロセスでは子プロセス側で試行する）
+        // 最小限の前処理では認識も1回にする
+        let attempts = if self.backend.is_some() || self.config.fast_pipeline { 1 } else { 3 };
+        for i in 0..attempts {
+            // 結果が得られていれば、期限を過ぎた後の試行は省略
+            if !results.is_empty() && self.skip_if_over_deadline(STAGE_EXTRA_ATTEMPTS) {
+                break;
+            }
+            match self.recognize_with_fallback(image, page_seg_mode) {
+                Ok((text, confidence)) => {
                     if !text.trim().is_empty() {
                         results.push(text);
+                        confidences.extend(confidence);
                     }
                 }
                 Err(e) => {
@@ -51,15 +1036,19 @@ impl OcrEngine {
         }
         
         if results.is_empty() {
+            ocr_stats::note_error(OcrErrorKind::NoResult);
             return Err(anyhow::anyhow!("すべての認識試行が失敗しました"));
         }
+
+        let mean_confidence =
+            (!confidences.is_empty()).then(|| confidences.iter().sum::<f32>() / confidences.len() as f32);
         
         // 最も頻度の高い結果を選択
         if results.len() == 1 {
-            Ok(results[0].clone())
+            Ok((results[0].clone(), mean_confidence))
         } else {
             // 複数の結果から最適なものを選択
-            self.select_best_result(&results)
+            Ok((self.select_best_result(&results)?, mean_confidence))
         }
     }
 
@@ -92,24 +1081,38 @@ impl OcrEngine {
         Ok(best_lines.join("\n"))
     }
 
-    /// フォールバック方式でのOCR認識（複数の方法を試行）
-    fn recognize_with_fallback(&self, image: &DynamicImage) -> Result<String> {
+    /// フォールバック方式でのOCR認識（複数の方法を試行、信頼度を返さないエンジンの場合はNone）
+    fn recognize_with_fallback(&self, image: &DynamicImage, page_seg_mode: u32) -> Result<(String, Option<f32>)> {
+        // Tesseract以外のエンジンを使う場合はフォールバックしない
+        if let Some(backend) = &self.backend {
+            let (text, confidence) = timed_engine_call(|| backend.recognize(image, page_seg_mode))?;
+            return Ok((self.normalize_text(&text), confidence));
+        }
+        let (text, confidence) = self.recognize_in_process(image, page_seg_mode)?;
+        Ok((text, Some(confidence)))
+    }
+
+    /// 前処理済みの画像をこのプロセスのTesseractで認識（子プロセスでの認識にも使う）
+    pub(crate) fn recognize_in_process(&self, image: &DynamicImage, page_seg_mode: u32) -> Result<(String, f32)> {
         // 方法1: BMPフォーマットでの保存を試行
-        match self.try_bmp_recognition(image) {
-            Ok(text) => {
+        match timed_engine_call(|| self.try_bmp_recognition(image, page_seg_mode)) {
+            Ok(result) => {
                 log::debug!("BMP方式での認識が成功しました");
-                return Ok(text);
+                return Ok(result);
             }
             Err(e) => {
                 log::warn!("BMP方式での認識に失敗: {}", e);
+                if self.skip_if_over_deadline(STAGE_SIMPLIFIED_FALLBACK) {
+                    return Err(e);
+                }
             }
         }
 
         // 方法2: より簡素な画像で再試行
-        match self.try_simplified_recognition(image) {
-            Ok(text) => {
+        match timed_engine_call(|| self.try_simplified_recognition(image, page_seg_mode)) {
+            Ok(result) => {
                 log::debug!("簡素化方式での認識が成功しました");
-                return Ok(text);
+                return Ok(result);
             }
             Err(e) => {
                 log::warn!("簡素化方式での認識に失敗: {}", e);
@@ -120,10 +1123,9 @@ impl OcrEngine {
         Err(anyhow::anyhow!("全てのOCR方式が失敗しました"))
     }
 
-    /// BMP方式でのOCR認識
-    fn try_bmp_recognition(&self, image: &DynamicImage) -> Result<String> {
-        let temp_dir = env::temp_dir();
-        let temp_path = temp_dir.join(format!("ocr_temp_{}.bmp", std::process::id()));
+    /// BMP方式でのOCR認識（テキストと生の信頼度 0.0-1.0 を返す）
+    fn try_bmp_recognition(&self, image: &DynamicImage, page_seg_mode: u32) -> Result<(String, f32)> {
+        let temp_path = temp_image_path("ocr_temp");
         
         // より安全な画像保存（ImageIO EXC_BAD_ACCESS回避）
         image.save_with_format(&temp_path, image::ImageFormat::Bmp)
@@ -137,40 +1139,148 @@ impl OcrEngine {
             return Err(anyhow::anyhow!("一時ファイルが作成されませんでした"));
         }
 
-        // Tesseractでの認識実行（日本語のみ対応）
-        let mut tesseract = Tesseract::new(None, Some("jpn"))
-            .context("Tesseract（日本語）の初期化に失敗しました")?;
+        // Tesseractでの認識実行
+        let tesseract = self.create_tesseract(page_seg_mode)?;
+        
+        let mut tesseract_with_image = tesseract.set_image(image, temp_path_str)
+            .context("画像の設定に失敗しました")?;
+        
+        let text = tesseract_with_image.get_text()
+            .context("テキストの取得に失敗しました")?;
+
+        // 認識後に単語単位の平均信頼度（0-100）を取得
+        let confidence = tesseract_with_image.mean_text_conf().max(0) as f32 / 100.0;
+
+        // 一時ファイルを削除
+        let _ = fs::remove_file(&temp_path);
+
+        Ok((self.normalize_text(&text), confidence))
+    }
+
+    /// 認識用の設定を適用したTesseractを作成
+    fn create_tesseract(&self, page_seg_mode: u32) -> Result<TesseractApi> {
+        let mut tesseract = TesseractApi::new(self.tessdata_dir.as_deref(), &self.language, &self.vocabulary_variables())
+            .with_context(|| format!("Tesseract（{}）の初期化に失敗しました", self.language))?;
         
         // OCRエンジンモード設定（より高精度なLSTM OCRエンジンを使用）
         tesseract = tesseract.set_variable("tessedit_ocr_engine_mode", "2")?; // 2 = Legacy + LSTM
         
         // ページセグメンテーションモード設定
-        // 6 = 均一なブロックの単一テキスト（YouTubeチャット向け）
-        tesseract = tesseract.set_variable("tessedit_pageseg_mode", "6")?;
+        // 既定は6 = 均一なブロックの単一テキスト（YouTubeチャット向け）
+        tesseract = tesseract.set_variable("tessedit_pageseg_mode", &page_seg_mode.to_string())?;
         
         // 日本語認識の最適化設定
         tesseract = tesseract.set_variable("preserve_interword_spaces", "1")?; // 単語間スペースを保持
         tesseract = tesseract.set_variable("tessedit_char_whitelist", "")?; // 全文字を許可
-        
-        let mut tesseract_with_image = tesseract.set_image(temp_path_str)
-            .context("画像の設定に失敗しました")?;
-        
-        let text = tesseract_with_image.get_text()
-            .context("テキストの取得に失敗しました")?;
 
-        // 一時ファイルを削除
-        let _ = fs::remove_file(&temp_path);
+        Ok(tesseract)
+    }
+
+    /// 設定の単語・パターンのファイルを指定する変数（辞書の読み込み時にだけ読まれるため、初期化時に渡す）
+    fn vocabulary_variables(&self) -> Vec<(&'static str, &str)> {
+        [("user_words_file", &self.user_words_file), ("user_patterns_file", &self.user_patterns_file)]
+            .into_iter()
+            .filter_map(|(name, path)| Some((name, path.as_deref()?.to_str()?)))
+            .collect()
+    }
+
+    /// 画像から行ごとのテキストと位置を認識（位置は入力画像の座標）
+    pub fn recognize_lines(&self, image: &DynamicImage) -> Result<Vec<OcrLine>> {
+        ocr_stats::recognition(
+            || {
+                let corrected = self
+                    .correct_geometry(image)
+                    .inspect_err(|_| ocr_stats::note_error(OcrErrorKind::Preprocess))?;
+                let mut lines = self.recognize_lines_corrected(&corrected)?;
+                if self.config.transform != CaptureTransform::None_ {
+                    // 補正後の画像の座標を、逆変換で入力画像の座標に戻す
+                    for line in &mut lines {
+                        line.bbox = self.config.transform.unmap_rect(line.bbox, image.width(), image.height());
+                    }
+                }
+                Ok(lines)
+            },
+            |lines| lines_stats_outcome(lines),
+        )
+    }
 
-        Ok(self.normalize_text(&text))
+    /// 幾何補正の済んだ画像から行ごとのテキストと位置を認識（位置は渡した画像の座標）
+    ///
+    /// 列やタイルごとの認識のように別の認識の中で呼んだ場合は、統計には外側の認識の1回として数える。
+    pub fn recognize_lines_corrected(&self, image: &DynamicImage) -> Result<Vec<OcrLine>> {
+        ocr_stats::recognition(|| self.read_lines_corrected(image), |lines| lines_stats_outcome(lines))
+    }
+
+    /// 幾何補正の済んだ画像から行を認識（統計には記録しない）
+    fn read_lines_corrected(&self, image: &DynamicImage) -> Result<Vec<OcrLine>> {
+        let (processed_image, _) = self
+            .preprocess_image(image)
+            .inspect_err(|_| ocr_stats::note_error(OcrErrorKind::Preprocess))?;
+
+        // 前処理で拡大されているため、元の画像の座標に戻す倍率
+        let scale_x = image.width() as f32 / processed_image.width() as f32;
+        let scale_y = image.height() as f32 / processed_image.height() as f32;
+        let processed_height = processed_image.height();
+
+        let (oriented_image, page_seg_mode, orientation) = self.orient(processed_image);
+        let raw_lines = timed_engine_call(|| match &self.backend {
+            Some(backend) => backend.recognize_lines(&oriented_image, page_seg_mode),
+            None => self.recognize_tsv_lines(&oriented_image, page_seg_mode),
+        })?;
+
+        let lines: Vec<OcrLine> = raw_lines
+            .into_iter()
+            .map(|mut line| {
+                // 回転した画像の座標を回転前の座標に戻す
+                if orientation == TextOrientation::Vertical {
+                    line.bbox = unrotate_bbox(line.bbox, processed_height);
+                }
+                line
+            })
+            .map(|line| {
+                let text = self.normalize_text(&line.text);
+                OcrLine {
+                    // Tesseractは行ごとの言語を返さないため、行の文字種から推定する
+                    language: line_language(&self.language, &text),
+                    confidence: line.confidence.map(|raw| self.normalize_confidence(raw)),
+                    text,
+                    bbox: ImageRect {
+                        x: (line.bbox.x as f32 * scale_x) as u32,
+                        y: (line.bbox.y as f32 * scale_y) as u32,
+                        width: (line.bbox.width as f32 * scale_x).ceil() as u32,
+                        height: (line.bbox.height as f32 * scale_y).ceil() as u32,
+                    },
+                }
+            })
+            .filter(|line| !line.text.is_empty())
+            .collect();
+
+        log::debug!("行単位の認識: {} 行", lines.len());
+        Ok(lines)
+    }
+
+    /// TesseractのTSV出力から行を認識（位置は渡した画像の座標）
+    pub(crate) fn recognize_tsv_lines(&self, processed_image: &DynamicImage, page_seg_mode: u32) -> Result<Vec<OcrLine>> {
+        let temp_path = temp_image_path("ocr_lines");
+        processed_image.save_with_format(&temp_path, image::ImageFormat::Bmp)
+            .context("BMP画像の保存に失敗しました")?;
+        let temp_path_str = temp_path.to_str()
+            .context("一時ファイルパスの変換に失敗しました")?;
+
+        let result = self
+            .create_tesseract(page_seg_mode)
+            .and_then(|tesseract| tesseract.set_image(processed_image, temp_path_str).context("画像の設定に失敗しました"))
+            .and_then(|mut tesseract| tesseract.get_tsv_text(0).context("TSVの取得に失敗しました"));
+        let _ = fs::remove_file(&temp_path);
+        Ok(parse_tsv_lines(&result?))
     }
 
     /// より簡素な方式でのOCR認識（最小限の処理）
-    fn try_simplified_recognition(&self, image: &DynamicImage) -> Result<String> {
+    fn try_simplified_recognition(&self, image: &DynamicImage, page_seg_mode: u32) -> Result<(String, f32)> {
         // 画像を極めて小さくしてメモリ使用量を削減
         let small_image = image.resize(200, 100, image::imageops::FilterType::Nearest);
         
-        let temp_dir = env::temp_dir();
-        let temp_path = temp_dir.join(format!("ocr_simple_{}.bmp", std::process::id()));
+        let temp_path = temp_image_path("ocr_simple");
         
         small_image.save_with_format(&temp_path, image::ImageFormat::Bmp)
             .context("簡素画像の保存に失敗しました")?;
@@ -178,68 +1288,253 @@ impl OcrEngine {
         let temp_path_str = temp_path.to_str()
             .context("簡素ファイルパスの変換に失敗しました")?;
 
-        let mut tesseract = Tesseract::new(None, Some("jpn"))
-            .context("簡素Tesseract（日本語）の初期化に失敗しました")?;
+        let mut tesseract = TesseractApi::new(self.tessdata_dir.as_deref(), &self.language, &self.vocabulary_variables())
+            .with_context(|| format!("簡素Tesseract（{}）の初期化に失敗しました", self.language))?;
         
         // 簡素版でも基本的な設定を適用
         tesseract = tesseract.set_variable("tessedit_ocr_engine_mode", "2")?;
-        tesseract = tesseract.set_variable("tessedit_pageseg_mode", "6")?;
+        tesseract = tesseract.set_variable("tessedit_pageseg_mode", &page_seg_mode.to_string())?;
         
-        let mut tesseract_with_image = tesseract.set_image(temp_path_str)
+        let mut tesseract_with_image = tesseract.set_image(&small_image, temp_path_str)
             .context("簡素画像の設定に失敗しました")?;
         
         let text = tesseract_with_image.get_text()
             .context("簡素テキストの取得に失敗しました")?;
 
+        let confidence = tesseract_with_image.mean_text_conf().max(0) as f32 / 100.0;
+
         let _ = fs::remove_file(&temp_path);
 
-        Ok(self.normalize_text(&text))
+        Ok((self.normalize_text(&text), confidence))
+    }
+
+    /// 幾何補正を適用（タイルや列に分ける前のキャプチャ全体に1回だけ行う、補正しない設定ならそのまま返す）
+    ///
+    /// 変換行列はキャプチャ全体の座標で指定されるため、切り出した部分に適用してはいけない。
+    pub fn correct_geometry<'a>(&self, image: &'a DynamicImage) -> Result<Cow<'a, DynamicImage>> {
+        if self.config.transform == CaptureTransform::None_ {
+            return Ok(Cow::Borrowed(image));
+        }
+        self.config.transform.apply(image).map(Cow::Owned)
     }
 
-    /// 画像の前処理（OCR精度向上のため）
-    fn preprocess_image(&self, image: &DynamicImage) -> Result<DynamicImage> {
+    /// 画像の前処理（OCR精度向上のため、幾何補正は済んでいるものとする）
+    fn preprocess_image(&self, image: &DynamicImage) -> Result<(DynamicImage, PreprocessTimings)> {
+        let (processed, timings) = self.preprocess_traced(image, None)?;
+
+        // 取得されるまで所要時間を合算しておく
+        lock(&self.preprocess_timings)
+            .get_or_insert_with(PreprocessTimings::default)
+            .accumulate(&timings);
+        if self.retain_preprocessed {
+            *self.last_preprocessed.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(processed.clone());
+        }
+
+        Ok((processed, timings))
+    }
+
+    /// 画像の前処理（traceを渡すと各ステップの結果を記録する）
+    fn preprocess_traced(
+        &self,
+        image: &DynamicImage,
+        mut trace: Option<&mut Vec<PipelineStep>>,
+    ) -> Result<(DynamicImage, PreprocessTimings)> {
         use image::imageops;
 
+        let preprocess_start = Instant::now();
+        let mut timings = PreprocessTimings::default();
+
         // 画像サイズの事前チェック（メモリ安全性）
         if image.width() == 0 || image.height() == 0 {
             return Err(anyhow::anyhow!("無効な画像サイズ: {}x{}", image.width(), image.height()));
         }
 
-        // 画像が巨大すぎる場合は拒否（メモリ保護）
-        if image.width() > 4096 || image.height() > 4096 {
-            return Err(anyhow::anyhow!("画像サイズが大きすぎます: {}x{}", image.width(), image.height()));
-        }
+        // 画像が巨大すぎる場合は縦横比を保って縮小（メモリ保護）
+        let downscaled;
+        let image = if image.width() > MAX_INPUT_DIMENSION || image.height() > MAX_INPUT_DIMENSION {
+            log::warn!(
+                "画像サイズが大きすぎるため {}px 以内に縮小します: {}x{}",
+                MAX_INPUT_DIMENSION,
+                image.width(),
+                image.height()
+            );
+            downscaled = image.resize(MAX_INPUT_DIMENSION, MAX_INPUT_DIMENSION, imageops::FilterType::Triangle);
+            &downscaled
+        } else {
+            image
+        };
 
         let mut processed = image.clone();
 
+        // 0. サブピクセル描画の色にじみ除去（グレースケール変換前に行う必要がある）
+        let step_start = Instant::now();
+        let defringe = !self.config.fast_pipeline
+            && self
+                .config
+                .defringe_lcd
+                .unwrap_or_else(|| detect_subpixel_rendering(&processed));
+        if defringe {
+            processed = defringe_lcd(&processed);
+        }
+        // 中間画像のエンコード時間を含めないよう記録前に計測する
+        timings.defringe_us = elapsed_us(step_start);
+        if defringe {
+            let notes = if self.config.defringe_lcd.is_some() { "設定により適用" } else { "自動検出により適用" };
+            self.record_step(&mut trace, "defringe_lcd", step_start, notes.to_string(), || processed.clone());
+        }
+
         // 1. グレースケール変換
-        processed = processed.grayscale();
+        let step_start = Instant::now();
+        processed = grayscale(&processed, self.config.grayscale_mode);
+        timings.grayscale_us = elapsed_us(step_start);
+        let notes = match self.config.grayscale_mode {
+            GrayscaleMode::Luminance => String::new(),
+            mode => format!("{:?}", mode),
+        };
+        self.record_step(&mut trace, "grayscale", step_start, notes, || processed.clone());
 
         // 2. 解像度の最適化（OCR向けに高解像度化）
-        if processed.width() < 1000 { // OCRは高解像度の方が精度が高い
-            let scale_factor = 1000.0 / processed.width() as f32;
-            let safe_scale_factor = scale_factor.min(4.0).max(1.5); // 1.5倍〜4倍に制限
+        let step_start = Instant::now();
+        let original_size = (processed.width(), processed.height());
+        let mut super_resolved = false;
+        let target_width = self.config.scale_target_width;
+        if processed.width() < target_width { // OCRは高解像度の方が精度が高い
+            let scale_factor = target_width as f32 / processed.width() as f32;
+            // 1.5倍〜4倍に制限（最小限の前処理では2倍まで）
+            let max_scale_factor = if self.config.fast_pipeline { FAST_PIPELINE_MAX_SCALE } else { 4.0 };
+            let safe_scale_factor = scale_factor.min(max_scale_factor).max(1.5);
             
             let new_width = (processed.width() as f32 * safe_scale_factor) as u32;
             let new_height = (processed.height() as f32 * safe_scale_factor) as u32;
             
             if new_width <= 3000 && new_height <= 3000 {
-                processed = processed.resize(new_width, new_height, imageops::FilterType::Lanczos3);
+                // 超解像で2倍にしてから残りを補間で合わせる（拡大後の大きさは超解像の有無で変わらない）
+                if let Some(resolved) = self.super_resolve(&processed) {
+                    processed = resolved;
+                    super_resolved = true;
+                }
+                if (processed.width(), processed.height()) != (new_width, new_height) {
+                    processed = processed.resize(new_width, new_height, imageops::FilterType::Lanczos3);
+                }
             }
         }
+        timings.scale_us = elapsed_us(step_start);
+        let notes = format!(
+            "{}x{} -> {}x{}{}",
+            original_size.0,
+            original_size.1,
+            processed.width(),
+            processed.height(),
+            if super_resolved { "（超解像）" } else { "" }
+        );
+        self.record_step(&mut trace, "scale", step_start, notes, || processed.clone());
 
         // 3. コントラスト強化と二値化
+        let step_start = Instant::now();
         let gray_image = processed.to_luma8();
-        let enhanced = self.enhance_contrast(&gray_image)?;
+        // 最小限の前処理ではヒストグラム均等化を省き、グレースケールをそのまま二値化する
+        let mut enhanced = if self.config.fast_pipeline { gray_image } else { self.enhance_contrast(&gray_image)? };
+        if self.config.binarization == BinarizationMode::Otsu || self.config.fast_pipeline {
+            binarize_otsu(&mut enhanced);
+        }
+        if self.config.invert {
+            imageops::invert(&mut enhanced);
+        }
+        timings.clahe_us = elapsed_us(step_start);
+        let mut notes = match self.config.binarization {
+            _ if self.config.fast_pipeline => "大津の二値化".to_string(),
+            BinarizationMode::Equalize => "ヒストグラム均等化".to_string(),
+            BinarizationMode::Otsu => "ヒストグラム均等化 + 大津の二値化".to_string(),
+        };
+        if self.config.invert {
+            notes.push_str(" + 反転");
+        }
+        self.record_step(&mut trace, "contrast", step_start, notes, || {
+            DynamicImage::ImageLuma8(enhanced.clone())
+        });
         
+        if self.config.fast_pipeline {
+            timings.total_us = elapsed_us(preprocess_start);
+            log::debug!("最小限の画像前処理完了: {}x{}（{}us）", enhanced.width(), enhanced.height(), timings.total_us);
+            return Ok((DynamicImage::ImageLuma8(enhanced), timings));
+        }
+
         // 4. ノイズ除去（メディアンフィルタの簡易実装）
+        let step_start = Instant::now();
         let denoised = self.denoise_image(&enhanced)?;
+        timings.denoise_us = elapsed_us(step_start);
+        self.record_step(&mut trace, "denoise", step_start, "3x3メディアンフィルタ".to_string(), || {
+            DynamicImage::ImageLuma8(denoised.clone())
+        });
         
         // 5. シャープネス強化
+        let step_start = Instant::now();
         let sharpened = self.sharpen_image(&denoised)?;
+        timings.sharpen_us = elapsed_us(step_start);
+        self.record_step(&mut trace, "sharpen", step_start, String::new(), || {
+            DynamicImage::ImageLuma8(sharpened.clone())
+        });
+
+        timings.total_us = elapsed_us(preprocess_start);
+        for (name, duration_us) in timings.steps() {
+            log::trace!("前処理 {}: {}us", name, duration_us);
+        }
+        log::debug!("画像前処理完了: {}x{}（{}us）", sharpened.width(), sharpened.height(), timings.total_us);
+        Ok((DynamicImage::ImageLuma8(sharpened), timings))
+    }
+
+    /// 前処理の各段階を記録（中間画像はdebug_pipeline有効時のみ保存）
+    fn record_step<F>(
+        &self,
+        trace: &mut Option<&mut Vec<PipelineStep>>,
+        name: &str,
+        step_start: Instant,
+        notes: String,
+        output: F,
+    ) where
+        F: FnOnce() -> DynamicImage,
+    {
+        let Some(steps) = trace else {
+            return;
+        };
+
+        let duration_ms = step_start.elapsed().as_millis() as u64;
+        let output_image_base64 = if self.debug_pipeline {
+            encode_png_base64(&output()).unwrap_or_else(|e| {
+                log::warn!("中間画像のエンコードに失敗: {}", e);
+                String::new()
+            })
+        } else {
+            String::new()
+        };
+
+        steps.push(PipelineStep {
+            name: name.to_string(),
+            output_image_base64,
+            duration_ms,
+            notes,
+        });
+    }
 
-        log::debug!("画像前処理完了: {}x{}", sharpened.width(), sharpened.height());
-        Ok(DynamicImage::ImageLuma8(sharpened))
+    /// 設定と画像の大きさに応じて2倍に超解像した画像（適用しない場合と失敗した場合はNone）
+    fn super_resolve(&self, image: &DynamicImage) -> Option<DynamicImage> {
+        let config = &self.config;
+        if !config.use_super_resolution
+            || config.fast_pipeline
+            || !ImagePreprocessor::needs_super_resolution(image.width(), image.height(), config.super_res_threshold_px)
+        {
+            return None;
+        }
+        #[cfg(feature = "super_res")]
+        match ImagePreprocessor::super_resolve_2x(&image.to_luma8()) {
+            Ok(resolved) => return Some(DynamicImage::ImageLuma8(resolved)),
+            // モデルを読み込めない場合は毎フレーム失敗するため、警告は最初の1回だけにする
+            Err(e) => {
+                static WARNED: std::sync::Once = std::sync::Once::new();
+                WARNED.call_once(|| log::warn!("超解像に失敗したため補間で拡大します: {}", e));
+            }
+        }
+        None
     }
 
     /// コントラスト強化と適応的二値化
@@ -326,14 +1621,366 @@ impl OcrEngine {
         Ok(output)
     }
 
-    /// テキストの正規化（不要な空白や改行を削除）
+    /// テキストの正規化（制御文字と不要な空白や改行を削除し、必要なら日本語の崩れを補正）
+    ///
+    /// 日本語の補正（japanese_text::normalize_japanese）は空白を整えた後に行う。
     fn normalize_text(&self, text: &str) -> String {
-        text.lines()
-            .map(|line| line.trim())
-            .filter(|line| !line.is_empty())
-            .collect::<Vec<_>>()
-            .join("\n")
+        let text = sanitize_text(text);
+        if self.japanese_cleanup_enabled() {
+            normalize_japanese(&text)
+        } else {
+            text
+        }
+    }
+
+    /// 日本語の認識結果の崩れを補正するかどうか（設定が無ければ言語から判断）
+    fn japanese_cleanup_enabled(&self) -> bool {
+        self.config
+            .japanese_cleanup
+            .unwrap_or_else(|| includes_japanese(&self.language))
+    }
+}
+
+/// Tesseractの出力から制御文字と不要な空白や改行を削除
+///
+/// Tesseractの既知の癖として、結果の末尾には必ず改行とページ区切り（`\n\f`）が付き、
+/// まれにヌル文字などの制御文字が混ざる。後の文字列処理で途中が切れないよう、
+/// 改行とタブ以外のASCIIの制御文字（0x00〜0x1F）は最初に取り除く。
+fn sanitize_text(text: &str) -> String {
+    let text: String = text
+        .chars()
+        .filter(|&c| c > '\x1f' || matches!(c, '\n' | '\t'))
+        .collect();
+    text.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 大津の方法でしきい値を求めて二値化
+fn binarize_otsu(image: &mut ImageBuffer<Luma<u8>, Vec<u8>>) {
+    let mut histogram = [0u64; 256];
+    for pixel in image.pixels() {
+        histogram[pixel[0] as usize] += 1;
+    }
+
+    let total: u64 = histogram.iter().sum();
+    let total_sum: f64 = histogram.iter().enumerate().map(|(value, count)| value as f64 * *count as f64).sum();
+    let mut background_count = 0u64;
+    let mut background_sum = 0.0;
+    let mut best_threshold = 0u8;
+    let mut best_variance = 0.0;
+
+    // クラス間分散が最大になるしきい値を選ぶ
+    for (value, count) in histogram.iter().enumerate() {
+        background_count += count;
+        if background_count == 0 || background_count == total {
+            continue;
+        }
+        background_sum += value as f64 * *count as f64;
+        let foreground_count = total - background_count;
+        let background_mean = background_sum / background_count as f64;
+        let foreground_mean = (total_sum - background_sum) / foreground_count as f64;
+        let variance = background_count as f64
+            * foreground_count as f64
+            * (background_mean - foreground_mean).powi(2);
+        if variance > best_variance {
+            best_variance = variance;
+            best_threshold = value as u8;
+        }
+    }
+
+    for pixel in image.pixels_mut() {
+        pixel[0] = if pixel[0] > best_threshold { 255 } else { 0 };
+    }
+}
+
+/// 複数の監視で共有するOCRエンジンの集まり
+///
+/// 同じ言語・設定で複数の領域を監視する場合に、領域ごとにエンジンを作らず
+/// 最大 pool_size 個のエンジンを順番に割り当てる。
+#[allow(dead_code)]
+pub struct OcrEnginePool {
+    engines: Vec<Arc<OcrEngine>>,
+    round_robin_idx: AtomicUsize,
+}
+
+#[allow(dead_code)]
+impl OcrEnginePool {
+    /// 指定した数のエンジンを作成（0の場合も1つは作成）
+    pub fn new(pool_size: usize, mut create: impl FnMut() -> Result<OcrEngine>) -> Result<Self> {
+        let engines = (0..pool_size.max(1))
+            .map(|_| create().map(Arc::new))
+            .collect::<Result<Vec<_>>>()?;
+        log::info!("OCRエンジンを {} 個作成しました", engines.len());
+        Ok(Self {
+            engines,
+            round_robin_idx: AtomicUsize::new(0),
+        })
+    }
+
+    /// 次のエンジンを取得（順番に割り当てる）
+    pub fn acquire(&self) -> Arc<OcrEngine> {
+        let index = self.round_robin_idx.fetch_add(1, Ordering::Relaxed) % self.engines.len();
+        self.engines[index].clone()
+    }
+
+    /// エンジンの数
+    pub fn len(&self) -> usize {
+        self.engines.len()
+    }
+}
+
+/// 前処理パイプラインの追跡結果
+#[derive(Debug, Clone, Serialize)]
+pub struct PipelineTrace {
+    /// 実行順のステップ
+    pub steps: Vec<PipelineStep>,
+}
+
+/// 前処理パイプラインの1ステップ
+#[derive(Debug, Clone, Serialize)]
+pub struct PipelineStep {
+    /// ステップ名
+    pub name: String,
+    /// 出力画像（base64エンコードしたPNG、debug_pipeline無効時は空）
+    pub output_image_base64: String,
+    /// 所要時間（ミリ秒）
+    pub duration_ms: u64,
+    /// 補足情報
+    pub notes: String,
+}
+
+/// サブピクセル描画（ClearType/Quartz）による色にじみがあるかどうかを判定
+///
+/// 緑チャンネルの水平方向のエッジで、赤と青が緑に対して逆方向にずれている
+/// （片方が先行し、もう片方が遅れる）画素が一定の割合を超えればtrueを返す。
+pub fn detect_subpixel_rendering(image: &DynamicImage) -> bool {
+    let rgb = image.to_rgb8();
+    if rgb.width() < 3 {
+        return false;
+    }
+
+    let mut edges = 0usize;
+    let mut fringed = 0usize;
+    for y in 0..rgb.height() {
+        for x in 1..rgb.width() - 1 {
+            let left = rgb.get_pixel(x - 1, y)[1] as i32;
+            let right = rgb.get_pixel(x + 1, y)[1] as i32;
+            if (right - left).abs() < SUBPIXEL_EDGE_THRESHOLD {
+                continue;
+            }
+            edges += 1;
+
+            let pixel = rgb.get_pixel(x, y);
+            let red_shift = pixel[0] as i32 - pixel[1] as i32;
+            let blue_shift = pixel[2] as i32 - pixel[1] as i32;
+            if red_shift.abs() >= SUBPIXEL_FRINGE_THRESHOLD
+                && blue_shift.abs() >= SUBPIXEL_FRINGE_THRESHOLD
+                && red_shift.signum() != blue_shift.signum()
+            {
+                fringed += 1;
+            }
+        }
+    }
+
+    if edges < SUBPIXEL_MIN_EDGES {
+        return false;
     }
+    let ratio = fringed as f32 / edges as f32;
+    log::debug!("サブピクセル判定: エッジ {} 画素中 {:.1}% に色にじみ", edges, ratio * 100.0);
+    ratio >= SUBPIXEL_FRINGE_RATIO
+}
+
+/// サブピクセル描画の色にじみを除去
+///
+/// 各チャンネルに水平方向のカーネル `[-1/6, 1, -1/6]` を畳み込む。
+/// 輝度が変わらないよう係数の合計で正規化し、アルファは元の値を保持する。
+pub fn defringe_lcd(image: &DynamicImage) -> DynamicImage {
+    const SIDE: f32 = -1.0 / 6.0;
+    const CENTER: f32 = 1.0;
+    const NORMALIZE: f32 = 1.0 / (CENTER + 2.0 * SIDE);
+
+    let rgba = image.to_rgba8();
+    let width = rgba.width();
+    let mut output = RgbaImage::new(width, rgba.height());
+
+    for (x, y, pixel) in rgba.enumerate_pixels() {
+        // 端の画素は隣接画素を複製して扱う
+        let left = rgba.get_pixel(x.saturating_sub(1), y);
+        let right = rgba.get_pixel((x + 1).min(width - 1), y);
+
+        let mut channels = [0u8; 4];
+        for c in 0..3 {
+            let sum = left[c] as f32 * SIDE + pixel[c] as f32 * CENTER + right[c] as f32 * SIDE;
+            channels[c] = (sum * NORMALIZE).clamp(0.0, 255.0) as u8;
+        }
+        channels[3] = pixel[3];
+        output.put_pixel(x, y, Rgba(channels));
+    }
+
+    DynamicImage::ImageRgba8(output)
+}
+
+/// 指定した方式でグレースケールに変換
+pub fn grayscale(image: &DynamicImage, mode: GrayscaleMode) -> DynamicImage {
+    let pick: fn([u8; 3]) -> u8 = match mode {
+        GrayscaleMode::Luminance => return image.grayscale(),
+        GrayscaleMode::ChannelMax => |[r, g, b]| r.max(g).max(b),
+        GrayscaleMode::ChannelMedian => |[r, g, b]| r.max(g).min(r.min(g).max(b)),
+    };
+    let rgb = image.to_rgb8();
+    let gray = ImageBuffer::from_fn(rgb.width(), rgb.height(), |x, y| Luma([pick(rgb.get_pixel(x, y).0)]));
+    DynamicImage::ImageLuma8(gray)
+}
+
+/// 認識された1行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OcrLine {
+    /// 行のテキスト
+    pub text: String,
+    /// 行の位置
+    pub bbox: ImageRect,
+    /// 複数の言語を設定した場合に、行の文字種から推定したその行の言語（jpn、engなど）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// 行の単語の平均の信頼度（0.0-1.0、信頼度を返さないエンジンの場合はNone）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f32>,
+}
+
+/// TSV出力の集計中の行（行の番号と、単語の信頼度の合計・信頼度のある単語数）
+struct TsvLine {
+    key: (u32, u32, u32, u32),
+    line: OcrLine,
+    confidence_sum: f32,
+    confident_words: u32,
+}
+
+/// TesseractのTSV出力から単語を行ごとにまとめる（行の信頼度は単語の信頼度の平均）
+fn parse_tsv_lines(tsv: &str) -> Vec<OcrLine> {
+    // 列: level page_num block_num par_num line_num word_num left top width height conf text
+    let mut lines: Vec<TsvLine> = Vec::new();
+
+    for row in tsv.lines() {
+        let columns: Vec<&str> = row.split('\t').collect();
+        if columns.len() < 12 || columns[0] != "5" {
+            continue; // ヘッダー行や単語以外の行
+        }
+        let word = columns[11].trim();
+        if word.is_empty() {
+            continue;
+        }
+        let numbers: Vec<u32> = columns[1..10].iter().filter_map(|c| c.parse().ok()).collect();
+        let [page, block, par, line, _, left, top, width, height] = numbers[..] else {
+            continue;
+        };
+        // 信頼度の無い単語は-1になる
+        let confidence = columns[10].parse::<f32>().ok().filter(|conf| *conf >= 0.0);
+
+        let key = (page, block, par, line);
+        let bbox = ImageRect { x: left, y: top, width, height };
+        let index = match lines.iter().position(|existing| existing.key == key) {
+            Some(index) => {
+                let existing = &mut lines[index].line;
+                join_word(&mut existing.text, word);
+                existing.bbox = existing.bbox.union(&bbox);
+                index
+            }
+            None => {
+                lines.push(TsvLine {
+                    key,
+                    line: OcrLine { text: word.to_string(), bbox, language: None, confidence: None },
+                    confidence_sum: 0.0,
+                    confident_words: 0,
+                });
+                lines.len() - 1
+            }
+        };
+        if let Some(confidence) = confidence {
+            lines[index].confidence_sum += confidence;
+            lines[index].confident_words += 1;
+        }
+    }
+
+    lines
+        .into_iter()
+        .map(|tsv_line| OcrLine {
+            confidence: (tsv_line.confident_words > 0)
+                .then(|| tsv_line.confidence_sum / tsv_line.confident_words as f32 / 100.0),
+            ..tsv_line.line
+        })
+        .collect()
+}
+
+/// 単語・パターンを1行1件で一時ファイルに書き出し、以前のファイルと置き換える（空ならファイルを作らない）
+fn replace_vocabulary_file(file: &mut Option<PathBuf>, prefix: &str, entries: Option<&[String]>) {
+    if let Some(old) = file.take() {
+        let _ = fs::remove_file(old);
+    }
+    let entries = entries.unwrap_or_default();
+    if entries.is_empty() {
+        return;
+    }
+
+    let path = env::temp_dir().join(format!(
+        "{}_{}_{}.txt",
+        prefix,
+        std::process::id(),
+        TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let mut content = entries.join("\n");
+    content.push('\n');
+    match fs::write(&path, content) {
+        Ok(()) => *file = Some(path),
+        Err(e) => log::warn!("{} の書き出しに失敗したため使用しません: {}", prefix, e),
+    }
+}
+
+/// 時計回りに90度回転した画像上の矩形を、回転前の画像の座標に戻す（heightは回転前の画像の高さ）
+fn unrotate_bbox(rotated: ImageRect, height: u32) -> ImageRect {
+    ImageRect {
+        x: rotated.y,
+        y: height.saturating_sub(rotated.x + rotated.width),
+        width: rotated.height,
+        height: rotated.width,
+    }
+}
+
+/// 行のテキストに単語を連結（英数字の単語どうしの間のみ空白を入れ、日本語は詰めて連結）
+pub fn join_word(line: &mut String, word: &str) {
+    let needs_space = line.chars().last().is_some_and(|c| c.is_ascii_alphanumeric())
+        && word.chars().next().is_some_and(|c| c.is_ascii_alphanumeric());
+    if needs_space {
+        line.push(' ');
+    }
+    line.push_str(word);
+}
+
+/// 画像をPNGとしてエンコードし、base64文字列に変換
+pub fn encode_png_base64(image: &DynamicImage) -> Result<String> {
+    use base64::Engine;
+
+    Ok(base64::engine::general_purpose::STANDARD.encode(encode_png(image)?))
+}
+
+/// 画像をPNGにエンコード
+pub fn encode_png(image: &DynamicImage) -> Result<Vec<u8>> {
+    let mut png = std::io::Cursor::new(Vec::new());
+    image.write_to(&mut png, image::ImageOutputFormat::Png)
+        .context("PNGへのエンコードに失敗しました")?;
+    Ok(png.into_inner())
+}
+
+/// 文字誤り率（正解の文字数に対する編集距離の割合、OCRが入れる空白や改行は除いて比較する）
+pub fn character_error_rate(actual: &str, expected: &str) -> f32 {
+    let actual: Vec<char> = actual.chars().filter(|c| !c.is_whitespace()).collect();
+    let expected: Vec<char> = expected.chars().filter(|c| !c.is_whitespace()).collect();
+    if expected.is_empty() {
+        return if actual.is_empty() { 0.0 } else { 1.0 };
+    }
+    edit_distance(&actual, &expected) as f32 / expected.len() as f32
 }
 
 /// OCR結果を表す構造体
@@ -342,20 +1989,406 @@ impl OcrEngine {
 pub struct OcrResult {
     /// 認識されたテキスト
     pub text: String,
-    /// 認識の信頼度（0.0-1.0）
-    pub confidence: f32,
+    /// 認識の信頼度（0.0-1.0、信頼度を返さないエンジンの場合はNone）
+    pub confidence: Option<f32>,
+    /// 前処理済みの画像のゆがみの指標
+    pub metrics: ImageMetrics,
     /// タイムスタンプ
     pub timestamp: std::time::SystemTime,
 }
 
-#[allow(dead_code)]
 impl OcrResult {
     /// 新しいOCR結果を作成
-    pub fn new(text: String, confidence: f32) -> Self {
+    pub fn new(text: String, confidence: Option<f32>, metrics: ImageMetrics) -> Self {
         Self {
             text,
             confidence,
+            metrics,
             timestamp: std::time::SystemTime::now(),
         }
     }
-}
\ No newline at end of file
+
+    /// OCRの統計に記録する文字数と信頼度
+    pub fn stats_outcome(&self) -> (u64, Option<f32>) {
+        (ocr_stats::count_characters(&self.text), self.confidence)
+    }
+}
+
+/// 行単位の認識についてOCRの統計に記録する文字数と信頼度（行単位の認識には信頼度が無い）
+pub fn lines_stats_outcome(lines: &[OcrLine]) -> (u64, Option<f32>) {
+    (lines.iter().map(|line| ocr_stats::count_characters(&line.text)).sum(), None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tsv_lines_average_word_confidence() {
+        let tsv = [
+            "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext",
+            "4\t1\t1\t1\t1\t0\t0\t0\t100\t20\t-1\t",
+            "5\t1\t1\t1\t1\t1\t0\t0\t40\t20\t90\tHello",
+            "5\t1\t1\t1\t1\t2\t50\t0\t50\t20\t70\tworld",
+            "5\t1\t1\t1\t2\t1\t0\t30\t30\t20\t-1\t画面",
+        ]
+        .join("\n");
+        let lines = parse_tsv_lines(&tsv);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].text, "Hello world");
+        assert_eq!(lines[0].bbox, ImageRect { x: 0, y: 0, width: 100, height: 20 });
+        assert!((lines[0].confidence.unwrap() - 0.8).abs() < 1e-6);
+        assert_eq!(lines[1].text, "画面");
+        assert_eq!(lines[1].confidence, None);
+    }
+
+    #[test]
+    fn character_error_rate_of_identical_text_is_zero() {
+        assert_eq!(character_error_rate("画面の文字", "画面の文字"), 0.0);
+        assert_eq!(character_error_rate("", ""), 0.0);
+    }
+
+    #[test]
+    fn character_error_rate_ignores_whitespace() {
+        assert_eq!(character_error_rate("画 面 の\n文 字", "画面の文字"), 0.0);
+    }
+
+    #[test]
+    fn character_error_rate_counts_edits_against_expected_length() {
+        // 置換1文字
+        assert!((character_error_rate("画面の文学", "画面の文字") - 0.2).abs() < 1e-6);
+        // 脱落1文字と挿入1文字
+        assert!((character_error_rate("画面文字", "画面の文字") - 0.2).abs() < 1e-6);
+        assert!((character_error_rate("画面のの文字", "画面の文字") - 0.2).abs() < 1e-6);
+        // 挿入が多ければ1.0を超える
+        assert!(character_error_rate("abcdef", "ab") > 1.0);
+    }
+
+    #[test]
+    fn character_error_rate_with_empty_expected_text() {
+        assert_eq!(character_error_rate("余分", ""), 1.0);
+        assert_eq!(character_error_rate("", "文字"), 1.0);
+    }
+
+    #[test]
+    fn bundled_reference_image_decodes() {
+        let reference = image::load_from_memory(WARMUP_REFERENCE_JPN).unwrap();
+        assert!(reference.width() > reference.height());
+        assert!(!WARMUP_REFERENCE_JPN_TEXT.is_empty());
+    }
+
+    #[test]
+    fn quality_check_failure_is_fatal_and_points_to_language_data() {
+        let error = OcrInitError::QualityCheckFailed {
+            language: "eng".to_string(),
+            message: "文字誤り率 80%".to_string(),
+        };
+        assert!(!error.is_recoverable());
+        assert_eq!(error.remediation(true), RemediationCode::DownloadLanguage);
+        assert_eq!(error.remediation(false), RemediationCode::InstallTesseract);
+
+        // マニフェストで照合できない言語はダウンロードを案内しない
+        let unverifiable = OcrInitError::MissingLanguageData {
+            language: "zz_not_in_manifest".to_string(),
+            message: "初期化に失敗しました".to_string(),
+        };
+        assert_eq!(unverifiable.remediation(true), RemediationCode::InstallTesseract);
+    }
+
+    /// ClearTypeの色にじみを再現した縦線の画像（ideal_maskは色にじみを背景とみなした正解の文字の位置）
+    ///
+    /// 12ピクセル周期で、背景・左の色にじみ（赤寄り）・3ピクセルの黒い線・右の色にじみ（青寄り）を並べる。
+    /// fringesがfalseなら色にじみの代わりに同じ明るさのグレーの階調を置く。
+    fn fringe_fixture(fringes: bool) -> (DynamicImage, Vec<bool>) {
+        const WIDTH: u32 = 60;
+        const HEIGHT: u32 = 20;
+        let column = |x: u32| x % 12;
+        let image = RgbaImage::from_fn(WIDTH, HEIGHT, |x, _| match column(x) {
+            4 if fringes => Rgba([200, 90, 20, 255]),
+            8 if fringes => Rgba([20, 90, 200, 255]),
+            4 | 8 => Rgba([100, 100, 100, 255]),
+            5..=7 => Rgba([0, 0, 0, 255]),
+            _ => Rgba([255, 255, 255, 255]),
+        });
+        let ideal_mask = (0..WIDTH * HEIGHT).map(|i| matches!(column(i % WIDTH), 5..=7)).collect();
+        (DynamicImage::ImageRgba8(image), ideal_mask)
+    }
+
+    /// グレースケール変換して大津の方法で二値化した結果が、正解の文字の位置と異なる画素数
+    fn binarization_errors(image: &DynamicImage, ideal_mask: &[bool], mode: GrayscaleMode) -> usize {
+        let mut gray = grayscale(image, mode).to_luma8();
+        binarize_otsu(&mut gray);
+        gray.pixels()
+            .zip(ideal_mask)
+            .filter(|(pixel, &is_text)| (pixel[0] == 0) != is_text)
+            .count()
+    }
+
+    #[test]
+    fn detects_subpixel_fringes_in_fixture() {
+        assert!(detect_subpixel_rendering(&fringe_fixture(true).0));
+        assert!(!detect_subpixel_rendering(&fringe_fixture(false).0));
+    }
+
+    #[test]
+    fn channel_max_suppresses_subpixel_halos() {
+        let (image, ideal_mask) = fringe_fixture(true);
+        let luminance_errors = binarization_errors(&image, &ideal_mask, GrayscaleMode::Luminance);
+        let channel_max_errors = binarization_errors(&image, &ideal_mask, GrayscaleMode::ChannelMax);
+        // 輝度の重み付けでは色にじみが灰色のにじみになって線が太る
+        assert!(luminance_errors > 0);
+        assert_eq!(channel_max_errors, 0);
+    }
+
+    #[test]
+    fn grayscale_modes_pick_expected_channel() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, Rgba([200, 90, 20, 255])));
+        let value = |mode| grayscale(&image, mode).to_luma8().get_pixel(0, 0)[0];
+        assert_eq!(value(GrayscaleMode::ChannelMax), 200);
+        assert_eq!(value(GrayscaleMode::ChannelMedian), 90);
+        assert!((60..=130).contains(&value(GrayscaleMode::Luminance)));
+    }
+
+    #[test]
+    fn strips_null_bytes_and_control_characters() {
+        assert_eq!(sanitize_text("ab\0c\x01d\x1be"), "abcde");
+        assert_eq!(sanitize_text("\0\0テスト\0"), "テスト");
+        assert_eq!(sanitize_text("a\tb"), "a\tb");
+    }
+
+    #[test]
+    fn strips_trailing_form_feed() {
+        assert_eq!(sanitize_text("Hello\nWorld\n\x0c"), "Hello\nWorld");
+        assert_eq!(sanitize_text("\n\x0c"), "");
+    }
+
+    #[test]
+    fn trims_lines_and_drops_blank_lines() {
+        assert_eq!(sanitize_text("  one  \n\n   \ntwo\r\n"), "one\ntwo");
+    }
+
+    /// エンジンが受け取った画像のサイズ
+    type ReceivedSizes = Arc<Mutex<Vec<(u32, u32)>>>;
+
+    /// 決められた結果を順に返すエンジン（受け取った画像のサイズを記録する）
+    struct ScriptedBackend {
+        results: Mutex<Vec<Result<Vec<OcrLine>>>>,
+        received: ReceivedSizes,
+    }
+
+    fn scripted_backend(results: Vec<Result<Vec<OcrLine>>>) -> (Box<dyn OcrBackend>, ReceivedSizes) {
+        let received = ReceivedSizes::default();
+        let backend = ScriptedBackend {
+            results: Mutex::new(results.into_iter().rev().collect()),
+            received: received.clone(),
+        };
+        (Box::new(backend), received)
+    }
+
+    impl OcrBackend for ScriptedBackend {
+        fn recognize_lines(&self, image: &DynamicImage, _page_seg_mode: u32) -> Result<Vec<OcrLine>> {
+            self.received.lock().unwrap().push((image.width(), image.height()));
+            self.results.lock().unwrap().pop().unwrap_or_else(|| Err(anyhow::anyhow!("結果がありません")))
+        }
+    }
+
+    const UNIT_RECT: ImageRect = ImageRect {
+        x: 0,
+        y: 0,
+        width: 1,
+        height: 1,
+    };
+
+    fn line(text: &str, bbox: ImageRect) -> OcrLine {
+        OcrLine {
+            text: text.to_string(),
+            bbox,
+            language: None,
+            confidence: None,
+        }
+    }
+
+    fn engine_with(backend: Option<Box<dyn OcrBackend>>, language: &str) -> OcrEngine {
+        OcrEngine::from_parts(None, language, OcrBackendKind::Tesseract, backend)
+    }
+
+    fn text_image(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageLuma8(ImageBuffer::from_fn(width, height, |x, y| {
+            Luma([if (x / 8 + y / 8) % 2 == 0 { 0 } else { 255 }])
+        }))
+    }
+
+    #[test]
+    fn zero_dimension_image_is_rejected_before_recognition() {
+        let (backend, received) = scripted_backend(vec![Ok(vec![])]);
+        let engine = engine_with(Some(backend), "eng");
+        for image in [text_image(0, 10), text_image(10, 0)] {
+            let error = engine.recognize_text(&image).unwrap_err();
+            assert!(error.to_string().contains("無効な画像サイズ"), "{:#}", error);
+            assert!(engine.recognize_lines(&image).is_err());
+        }
+        assert!(received.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn oversized_image_is_downscaled_and_lines_are_mapped_back() {
+        let (backend, received) = scripted_backend(vec![Ok(vec![line(
+            "wide",
+            ImageRect {
+                x: 2048,
+                y: 0,
+                width: 2048,
+                height: 40,
+            },
+        )])]);
+        let engine = engine_with(Some(backend), "eng");
+        let lines = engine.recognize_lines(&text_image(5000, 100)).unwrap();
+
+        let (width, height) = received.lock().unwrap()[0];
+        assert_eq!(width, MAX_INPUT_DIMENSION);
+        assert!(height <= 100 * MAX_INPUT_DIMENSION / 5000 + 1);
+        // 位置は入力画像の座標に戻る
+        assert_eq!(lines.len(), 1);
+        assert!((2495..=2505).contains(&lines[0].bbox.x), "{:?}", lines[0].bbox);
+        assert!(lines[0].bbox.x + lines[0].bbox.width >= 4995);
+    }
+
+    #[test]
+    fn failed_backend_call_reports_no_result() {
+        let (backend, received) = scripted_backend(vec![Err(anyhow::anyhow!("エンジンの呼び出しに失敗"))]);
+        let engine = engine_with(Some(backend), "eng");
+        let error = engine.recognize_detailed(&text_image(64, 32)).unwrap_err();
+        assert_eq!(error.to_string(), "すべての認識試行が失敗しました");
+        // 他のエンジンは結果が変わらないため再試行しない
+        assert_eq!(received.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn empty_backend_result_is_not_a_recognition() {
+        let (backend, _) = scripted_backend(vec![Ok(vec![line("   ", UNIT_RECT)])]);
+        let engine = engine_with(Some(backend), "eng");
+        assert!(engine.recognize_text(&text_image(64, 32)).is_err());
+    }
+
+    #[test]
+    fn backend_text_is_normalized() {
+        let (backend, _) = scripted_backend(vec![Ok(vec![
+            line(" first\u{0} ", UNIT_RECT),
+            line("second\u{c}", UNIT_RECT),
+        ])]);
+        let engine = engine_with(Some(backend), "eng");
+        let result = engine.recognize_detailed(&text_image(64, 32)).unwrap();
+        assert_eq!(result.text, "first\nsecond");
+        // 信頼度を返さないエンジンの結果は信頼度なしとして扱う
+        assert_eq!(result.confidence, None);
+    }
+
+    #[test]
+    fn text_coverage_is_sampled_at_the_interval() {
+        let mut engine = engine_with(None, "eng");
+        engine.set_text_coverage_interval(3);
+        let image = text_image(64, 32);
+        let sampled: Vec<bool> = (0..5).map(|_| engine.sample_text_coverage(&image).is_some()).collect();
+        assert_eq!(sampled, vec![true, false, false, true, false]);
+
+        engine.set_config(OcrConfig {
+            measure_text_coverage: false,
+            ..OcrConfig::default()
+        });
+        assert!((0..3).all(|_| engine.sample_text_coverage(&image).is_none()));
+    }
+
+    #[test]
+    fn forcing_tesseract_replaces_the_native_backend() {
+        let (backend, _) = scripted_backend(vec![Ok(vec![line("text", UNIT_RECT)])]);
+        let mut engine = engine_with(Some(backend), "eng");
+        engine.set_config(OcrConfig {
+            backend: OcrBackendPreference::Tesseract,
+            ..OcrConfig::default()
+        });
+        assert_eq!(engine.backend_kind(), OcrBackendKind::Tesseract);
+        assert!(engine.backend.is_none());
+    }
+
+    #[test]
+    fn line_positions_are_mapped_back_through_the_transform() {
+        let bbox = ImageRect { x: 16, y: 8, width: 32, height: 8 };
+        let recognize = |transform: CaptureTransform| {
+            let (backend, _) = scripted_backend(vec![Ok(vec![line("text", bbox)])]);
+            let mut engine = engine_with(Some(backend), "eng");
+            engine.config.transform = transform;
+            engine.recognize_lines(&text_image(128, 64)).unwrap()[0].bbox
+        };
+        let plain = recognize(CaptureTransform::None_);
+        // 内容を左に10px、上に4pxずらす補正
+        let shifted = recognize(CaptureTransform::Affine([[1.0, 0.0, -10.0], [0.0, 1.0, -4.0]]));
+        assert_eq!((shifted.x, shifted.y), (plain.x + 10, plain.y + 4));
+        assert_eq!((shifted.width, shifted.height), (plain.width, plain.height));
+    }
+
+    #[test]
+    fn tesseract_failure_falls_back_to_simplified_recognition() {
+        // 存在しない言語データのディレクトリでは、BMP方式も簡素化方式も初期化に失敗する
+        let tessdata = env::temp_dir().join(format!("ocr_missing_tessdata_{}", std::process::id()));
+        let mut engine = engine_with(None, "zzz");
+        engine.tessdata_dir = Some(tessdata.to_str().unwrap().to_string());
+        let image = text_image(64, 32);
+
+        let error = engine.recognize_in_process(&image, DEFAULT_PAGE_SEG_MODE).unwrap_err();
+        assert_eq!(error.to_string(), "全てのOCR方式が失敗しました");
+        assert!(engine.take_skipped_stages().is_empty());
+
+        // 期限を過ぎていれば簡素化方式を省き、BMP方式のエラーを返す
+        engine.set_deadline(Some(Instant::now()));
+        let error = engine.recognize_in_process(&image, DEFAULT_PAGE_SEG_MODE).unwrap_err();
+        assert!(error.to_string().contains("初期化に失敗しました"), "{:#}", error);
+        assert_eq!(engine.take_skipped_stages(), vec![STAGE_SIMPLIFIED_FALLBACK]);
+    }
+
+    #[test]
+    fn temp_io_errors_are_recoverable() {
+        let error = OcrInitError::TempIo("読み取り専用".to_string());
+        assert!(error.is_recoverable());
+        assert_eq!(error.remediation(true), RemediationCode::Retry);
+        assert!(check_temp_dir().is_ok());
+    }
+
+    #[test]
+    fn vocabulary_files_are_passed_at_initialization() {
+        let mut engine = engine_with(None, "eng");
+        assert!(engine.vocabulary_variables().is_empty());
+
+        engine.set_user_patterns(vec![r"\d\d:\d\d".to_string()]).unwrap();
+        engine.set_config(OcrConfig {
+            user_words: Some(vec!["ゆっくり".to_string()]),
+            ..engine.config().clone()
+        });
+        let variables = engine.vocabulary_variables();
+        let names: Vec<&str> = variables.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, vec!["user_words_file", "user_patterns_file"]);
+        assert_eq!(fs::read_to_string(variables[1].1).unwrap(), "\\d\\d:\\d\\d\n");
+        let patterns_file = PathBuf::from(variables[1].1);
+
+        // 解除すると一時ファイルも削除する
+        engine.clear_user_patterns();
+        assert_eq!(engine.vocabulary_variables().len(), 1);
+        assert!(!patterns_file.exists());
+    }
+
+    #[test]
+    fn super_resolution_needs_the_feature_and_a_small_image() {
+        let config = OcrConfig { use_super_resolution: true, ..OcrConfig::default() };
+        assert_eq!(config.validate().is_ok(), cfg!(feature = "super_res"));
+        let config = OcrConfig { super_res_threshold_px: 8, ..OcrConfig::default() };
+        let error = config.validate().unwrap_err();
+        assert_eq!(error.0[0].field, "ocr.super_res_threshold_px");
+
+        // しきい値以上の画像と最小限の前処理ではモデルを読み込まずに補間で拡大する
+        let mut engine = engine_with(None, "eng");
+        engine.set_config(OcrConfig { use_super_resolution: true, ..OcrConfig::default() });
+        let large = DynamicImage::new_luma8(320, 40);
+        assert!(engine.super_resolve(&large).is_none());
+        engine.set_config(OcrConfig { use_super_resolution: true, fast_pipeline: true, ..OcrConfig::default() });
+        assert!(engine.super_resolve(&DynamicImage::new_luma8(60, 20)).is_none());
+    }
+}
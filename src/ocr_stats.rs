@@ -0,0 +1,233 @@
+// アプリの起動以降のOCRエンジンの累計の統計（監視・テスト認識・一括認識などすべての呼び出しを含む）
+//
+// どのOcrEngineのインスタンスから呼び出しても同じ集計に加算するよう、プロセスで1つの集計を持つ。
+// 子プロセスで認識する場合は、子プロセスでの認識の所要時間を親プロセスの側で計る。
+//
+// 認識の回数とエラーは、画面の1回の読み取り（recognitionで囲んだ範囲）ごとに1回だけ数える。
+// 複数回の試行やフォールバック、タイル・列ごとの認識は、その中の呼び出しとしてまとめる。
+use anyhow::Result;
+use serde::Serialize;
+use std::cell::Cell;
+use std::sync::Mutex;
+use std::time::Duration;
+use crate::locking::lock;
+
+/// プロセスで共有する集計（更新は数回の加算のみでロックはすぐに解放する）
+static OCR_STATS: Mutex<OcrTotals> = Mutex::new(OcrTotals::new());
+
+thread_local! {
+    /// このスレッドで実行中の認識で最後に起きたエラー（認識の外ではNone、エラーが無ければSome(None)）
+    static CURRENT: Cell<Option<Option<OcrErrorKind>>> = const { Cell::new(None) };
+}
+
+/// 認識のエラーの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OcrErrorKind {
+    /// 前処理に失敗
+    Preprocess,
+    /// エンジン（Tesseractまたは他のエンジン）の呼び出しに失敗
+    Engine,
+    /// すべての認識の試行が失敗
+    NoResult,
+}
+
+/// エラーの種類ごとの回数
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct OcrErrorCounts {
+    pub preprocess: u64,
+    pub engine: u64,
+    pub no_result: u64,
+}
+
+/// OCRエンジンの累計の統計（get_ocr_statsの結果）
+#[derive(Debug, Clone, Serialize)]
+pub struct OcrStats {
+    /// 成功した認識の回数（画面の1回の読み取りを1回と数える）
+    pub recognitions: u64,
+    /// 認識した文字数（空白を除く）
+    pub characters: u64,
+    /// 失敗した認識のエラーの種類ごとの回数（1回の認識で最大1回）
+    pub errors: OcrErrorCounts,
+    /// 信頼度の平均（0.0-1.0、信頼度のある認識がまだ無ければNone）
+    pub average_confidence: Option<f32>,
+    /// エンジンを呼び出した回数（1回の認識で複数回呼び出すことがある）
+    pub engine_calls: u64,
+    /// エンジンの呼び出しにかかった時間の合計（ミリ秒）
+    pub engine_time_ms: u64,
+}
+
+/// 集計中の値
+struct OcrTotals {
+    recognitions: u64,
+    characters: u64,
+    errors: OcrErrorCounts,
+    confidence_sum: f64,
+    confidence_samples: u64,
+    engine_calls: u64,
+    engine_time_us: u64,
+}
+
+impl OcrTotals {
+    const fn new() -> Self {
+        Self {
+            recognitions: 0,
+            characters: 0,
+            errors: OcrErrorCounts {
+                preprocess: 0,
+                engine: 0,
+                no_result: 0,
+            },
+            confidence_sum: 0.0,
+            confidence_samples: 0,
+            engine_calls: 0,
+            engine_time_us: 0,
+        }
+    }
+}
+
+/// 1回の認識として統計に記録
+///
+/// 成功した場合はoutcomeが返す文字数（count_charactersで数える）と信頼度（信頼度の無い行単位の認識などはNone）を、失敗した場合は
+/// 中で最後に起きたエラーの種類を記録する。途中の試行が失敗しても、最終的に成功すればエラーとは数えない。
+/// 認識の中でさらに呼んだ場合は、外側の認識の一部として何も記録しない。
+pub fn recognition<T>(recognize: impl FnOnce() -> Result<T>, outcome: impl FnOnce(&T) -> (u64, Option<f32>)) -> Result<T> {
+    if CURRENT.with(Cell::get).is_some() {
+        return recognize();
+    }
+    let scope = Scope::enter();
+    let result = recognize();
+    let last_error = scope.last_error();
+    drop(scope);
+    match &result {
+        Ok(value) => {
+            let (characters, confidence) = outcome(value);
+            record_recognition(characters, confidence);
+        }
+        Err(_) => record_error(last_error.unwrap_or(OcrErrorKind::NoResult)),
+    }
+    result
+}
+
+/// 実行中の認識で起きたエラーの種類を記録（認識が最終的に失敗した場合にだけ数える、認識の外では何もしない）
+pub fn note_error(kind: OcrErrorKind) {
+    CURRENT.with(|current| {
+        if current.get().is_some() {
+            current.set(Some(Some(kind)));
+        }
+    });
+}
+
+/// 実行中の認識の範囲（パニックした場合も範囲を閉じる）
+struct Scope;
+
+impl Scope {
+    fn enter() -> Self {
+        CURRENT.with(|current| current.set(Some(None)));
+        Scope
+    }
+
+    fn last_error(&self) -> Option<OcrErrorKind> {
+        CURRENT.with(Cell::get).flatten()
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        CURRENT.with(|current| current.set(None));
+    }
+}
+
+/// 統計に記録する文字数（空白を除く）
+pub fn count_characters(text: &str) -> u64 {
+    text.chars().filter(|c| !c.is_whitespace()).count() as u64
+}
+
+/// 成功した認識を記録
+fn record_recognition(characters: u64, confidence: Option<f32>) {
+    let mut totals = lock(&OCR_STATS);
+    totals.recognitions += 1;
+    totals.characters += characters;
+    if let Some(confidence) = confidence {
+        totals.confidence_sum += f64::from(confidence);
+        totals.confidence_samples += 1;
+    }
+}
+
+/// 失敗した認識を記録
+fn record_error(kind: OcrErrorKind) {
+    let mut totals = lock(&OCR_STATS);
+    match kind {
+        OcrErrorKind::Preprocess => totals.errors.preprocess += 1,
+        OcrErrorKind::Engine => totals.errors.engine += 1,
+        OcrErrorKind::NoResult => totals.errors.no_result += 1,
+    }
+}
+
+/// エンジンの1回の呼び出しの所要時間を記録
+pub fn record_engine_call(elapsed: Duration) {
+    let mut totals = lock(&OCR_STATS);
+    totals.engine_calls += 1;
+    totals.engine_time_us += elapsed.as_micros() as u64;
+}
+
+/// 現在の統計を取得
+pub fn snapshot() -> OcrStats {
+    let totals = lock(&OCR_STATS);
+    OcrStats {
+        recognitions: totals.recognitions,
+        characters: totals.characters,
+        errors: totals.errors,
+        average_confidence: (totals.confidence_samples > 0)
+            .then(|| (totals.confidence_sum / totals.confidence_samples as f64) as f32),
+        engine_calls: totals.engine_calls,
+        engine_time_ms: totals.engine_time_us / 1000,
+    }
+}
+
+/// 統計を0に戻す
+pub fn reset() {
+    *lock(&OCR_STATS) = OcrTotals::new();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn current() -> Option<Option<OcrErrorKind>> {
+        CURRENT.with(Cell::get)
+    }
+
+    #[test]
+    fn nested_recognitions_belong_to_the_outer_one() {
+        let result = recognition(
+            || {
+                note_error(OcrErrorKind::Engine);
+                let inner = recognition(
+                    || {
+                        assert_eq!(current(), Some(Some(OcrErrorKind::Engine)));
+                        note_error(OcrErrorKind::Preprocess);
+                        Ok(1)
+                    },
+                    |_| (1, None),
+                );
+                // 内側の認識は範囲を閉じない
+                assert_eq!(current(), Some(Some(OcrErrorKind::Preprocess)));
+                inner
+            },
+            |_| (1, None),
+        );
+        assert_eq!(result.unwrap(), 1);
+        assert_eq!(current(), None);
+
+        // 認識の外のエラーは何もしない
+        note_error(OcrErrorKind::Engine);
+        assert_eq!(current(), None);
+    }
+
+    #[test]
+    fn scope_is_closed_after_a_panic() {
+        let panicked = std::panic::catch_unwind(|| recognition::<()>(|| panic!("認識中のパニック"), |_| (0, None)));
+        assert!(panicked.is_err());
+        assert_eq!(current(), None);
+    }
+}
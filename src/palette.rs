@@ -0,0 +1,136 @@
+// 領域の代表色の抽出（色の設定を決めるための補助、監視とは独立して実行する）
+use anyhow::{bail, Result};
+use image::{imageops, DynamicImage, GenericImageView};
+use serde::Serialize;
+
+/// 一度に求められる代表色の最大数
+pub const MAX_COLORS: usize = 8;
+
+/// 計算量を抑えるための画像の最大サイズ（これより大きい領域は縮小してから数える）
+pub const MAX_PICK_SIZE: u32 = 500;
+
+/// k-meansの最大の反復回数
+const MAX_ITERATIONS: usize = 20;
+
+/// 代表色と、その色に近い画素の割合
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct DominantColor {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    /// 画素全体に占める割合（0.0〜1.0）
+    pub fraction: f32,
+}
+
+impl DominantColor {
+    /// 明るさ（ITU-R BT.601の輝度）
+    pub(crate) fn luminance(&self) -> f32 {
+        brightness(&[self.r as f32, self.g as f32, self.b as f32])
+    }
+}
+
+/// 画像の代表色をk-meansで求める（割合の大きい順、完全に透明な画素は数えない）
+pub fn dominant_colors(image: &DynamicImage, n_colors: usize) -> Result<Vec<DominantColor>> {
+    let n_colors = n_colors.clamp(1, MAX_COLORS);
+    let image = if image.width() > MAX_PICK_SIZE || image.height() > MAX_PICK_SIZE {
+        // 色が混ざらないよう最近傍で縮小する
+        image.resize(MAX_PICK_SIZE, MAX_PICK_SIZE, imageops::FilterType::Nearest)
+    } else {
+        image.clone()
+    };
+
+    let pixels: Vec<[f32; 3]> = image
+        .pixels()
+        .filter(|(_, _, pixel)| pixel[3] > 0)
+        .map(|(_, _, pixel)| [pixel[0] as f32, pixel[1] as f32, pixel[2] as f32])
+        .collect();
+    if pixels.is_empty() {
+        bail!("色を数えられる画素がありません");
+    }
+
+    // 初期値は明るさ順に並べた画素から等間隔に選ぶ（毎回同じ結果になるように）
+    let mut sorted = pixels.clone();
+    sorted.sort_by(|a, b| brightness(a).total_cmp(&brightness(b)));
+    let mut centers: Vec<[f32; 3]> = (0..n_colors)
+        .map(|i| sorted[(i * 2 + 1) * sorted.len() / (n_colors * 2)])
+        .collect();
+
+    let mut assignments = vec![0usize; pixels.len()];
+    for iteration in 0..MAX_ITERATIONS {
+        let mut moved = iteration == 0;
+        for (pixel, assignment) in pixels.iter().zip(assignments.iter_mut()) {
+            let nearest = nearest_center(&centers, pixel);
+            if nearest != *assignment {
+                *assignment = nearest;
+                moved = true;
+            }
+        }
+        if !moved {
+            break;
+        }
+
+        let mut sums = vec![[0.0f64; 3]; centers.len()];
+        let mut counts = vec![0usize; centers.len()];
+        for (pixel, &assignment) in pixels.iter().zip(&assignments) {
+            for channel in 0..3 {
+                sums[assignment][channel] += pixel[channel] as f64;
+            }
+            counts[assignment] += 1;
+        }
+        for ((center, sum), &count) in centers.iter_mut().zip(&sums).zip(&counts) {
+            if count > 0 {
+                *center = [0, 1, 2].map(|channel| (sum[channel] / count as f64) as f32);
+            }
+        }
+    }
+
+    let mut counts = vec![0usize; centers.len()];
+    for &assignment in &assignments {
+        counts[assignment] += 1;
+    }
+    // 画素が割り当てられなかった（同じ色しか無い）中心は除く
+    let mut colors: Vec<DominantColor> = centers
+        .iter()
+        .zip(&counts)
+        .filter(|(_, &count)| count > 0)
+        .map(|(center, &count)| DominantColor {
+            r: center[0].round() as u8,
+            g: center[1].round() as u8,
+            b: center[2].round() as u8,
+            fraction: count as f32 / pixels.len() as f32,
+        })
+        .collect();
+    colors.sort_by(|a, b| b.fraction.total_cmp(&a.fraction));
+    Ok(colors)
+}
+
+/// 最も多い2色を背景と文字の色として返す（明るい方を背景、暗い方を文字とする）
+pub fn text_background_pair(image: &DynamicImage) -> Result<(DominantColor, DominantColor)> {
+    let colors = dominant_colors(image, 2)?;
+    match colors.as_slice() {
+        [first, second] => {
+            if first.luminance() >= second.luminance() {
+                Ok((*first, *second))
+            } else {
+                Ok((*second, *first))
+            }
+        }
+        _ => bail!("領域に色が1つしかないため、背景と文字の色を区別できません"),
+    }
+}
+
+/// 画素の明るさ（ITU-R BT.601の輝度）
+fn brightness(pixel: &[f32; 3]) -> f32 {
+    0.299 * pixel[0] + 0.587 * pixel[1] + 0.114 * pixel[2]
+}
+
+/// 最も近い中心の番号
+fn nearest_center(centers: &[[f32; 3]], pixel: &[f32; 3]) -> usize {
+    let distance = |center: &[f32; 3]| (0..3).map(|channel| (center[channel] - pixel[channel]).powi(2)).sum::<f32>();
+    centers
+        .iter()
+        .enumerate()
+        .min_by(|a, b| distance(a.1).total_cmp(&distance(b.1)))
+        .map(|(index, _)| index)
+        .unwrap_or(0)
+}
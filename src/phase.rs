@@ -0,0 +1,212 @@
+// 監視の状態遷移（状態を変更するコマンドどうしの競合を防ぐ）
+use serde::Serialize;
+use std::fmt;
+
+use crate::wizard::RemediationCode;
+
+/// 監視の状態
+///
+/// Idle → Selecting → Idle、Idle → Starting → Monitoring → Stopping → Idle の順に遷移する。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MonitorPhase {
+    /// 監視も領域選択もしていない
+    #[default]
+    Idle,
+    /// 領域選択のオーバーレイを表示中
+    Selecting,
+    /// 監視スレッドを起動中
+    Starting,
+    /// 監視中
+    Monitoring,
+    /// 監視スレッドの終了待ち
+    Stopping,
+}
+
+impl MonitorPhase {
+    /// 表示用の名前
+    fn as_str(&self) -> &'static str {
+        match self {
+            MonitorPhase::Idle => "idle",
+            MonitorPhase::Selecting => "selecting",
+            MonitorPhase::Starting => "starting",
+            MonitorPhase::Monitoring => "monitoring",
+            MonitorPhase::Stopping => "stopping",
+        }
+    }
+
+    /// 現在の状態が許可された状態のいずれかであれば遷移する
+    pub fn transition(
+        &mut self,
+        command: &'static str,
+        allowed: &[MonitorPhase],
+        next: MonitorPhase,
+    ) -> Result<(), InvalidStateError> {
+        if !allowed.contains(self) {
+            return Err(InvalidStateError {
+                command,
+                current: *self,
+                required: allowed.to_vec(),
+            });
+        }
+        *self = next;
+        Ok(())
+    }
+}
+
+/// 現在の状態では実行できないコマンドのエラー
+#[derive(Debug, Clone)]
+pub struct InvalidStateError {
+    /// 実行しようとしたコマンド
+    pub command: &'static str,
+    /// 現在の状態
+    pub current: MonitorPhase,
+    /// コマンドの実行に必要な状態
+    pub required: Vec<MonitorPhase>,
+}
+
+impl fmt::Display for InvalidStateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let required: Vec<&str> = self.required.iter().map(MonitorPhase::as_str).collect();
+        write!(
+            f,
+            "{} は現在の状態（{}）では実行できません（必要な状態: {}）",
+            self.command,
+            self.current.as_str(),
+            required.join(" / ")
+        )
+    }
+}
+
+/// 監視の開始・停止コマンドのエラー
+#[derive(Debug, Clone)]
+pub enum MonitorCommandError {
+    /// 現在の状態では実行できない
+    InvalidState(InvalidStateError),
+    /// OCRエンジンを初期化できない（言語データが無いなど、設定を直さなければ解決しない）
+    OcrInit { message: String, remediation: RemediationCode },
+    /// 設定値の不正などその他のエラー
+    Failed(String),
+}
+
+impl MonitorCommandError {
+    /// フロントエンドで判別するための固定識別子
+    fn kind(&self) -> &'static str {
+        match self {
+            MonitorCommandError::InvalidState(_) => "invalid_state",
+            MonitorCommandError::OcrInit { .. } => "ocr_init_failed",
+            MonitorCommandError::Failed(_) => "failed",
+        }
+    }
+}
+
+impl fmt::Display for MonitorCommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MonitorCommandError::InvalidState(error) => write!(f, "{}", error),
+            MonitorCommandError::OcrInit { message, .. } => write!(f, "OCR初期化エラー: {}", message),
+            MonitorCommandError::Failed(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<InvalidStateError> for MonitorCommandError {
+    fn from(error: InvalidStateError) -> Self {
+        MonitorCommandError::InvalidState(error)
+    }
+}
+
+impl From<String> for MonitorCommandError {
+    fn from(message: String) -> Self {
+        MonitorCommandError::Failed(message)
+    }
+}
+
+// フロントエンドには { kind, message } の形式で返し、状態のエラーには current と required を、
+// OCRエンジンの初期化のエラーには対処方法の remediation を付ける
+impl Serialize for MonitorCommandError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("MonitorCommandError", 4)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        if let MonitorCommandError::InvalidState(error) = self {
+            state.serialize_field("current", &error.current)?;
+            state.serialize_field("required", &error.required)?;
+        }
+        if let MonitorCommandError::OcrInit { remediation, .. } = self {
+            state.serialize_field("remediation", remediation)?;
+        }
+        state.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 監視開始のコマンドと同じ遷移
+    fn start(phase: &mut MonitorPhase) -> Result<(), InvalidStateError> {
+        phase.transition("start_monitoring", &[MonitorPhase::Idle], MonitorPhase::Starting)
+    }
+
+    /// 監視停止のコマンドと同じ遷移
+    fn stop(phase: &mut MonitorPhase) -> Result<(), InvalidStateError> {
+        phase.transition("stop_monitoring", &[MonitorPhase::Monitoring], MonitorPhase::Stopping)
+    }
+
+    #[test]
+    fn second_start_is_rejected_while_starting() {
+        let mut phase = MonitorPhase::Idle;
+        start(&mut phase).unwrap();
+        let error = start(&mut phase).unwrap_err();
+        assert_eq!(error.command, "start_monitoring");
+        assert_eq!(error.current, MonitorPhase::Starting);
+        assert_eq!(error.required, vec![MonitorPhase::Idle]);
+        assert_eq!(phase, MonitorPhase::Starting);
+    }
+
+    #[test]
+    fn start_is_rejected_while_selecting() {
+        let mut phase = MonitorPhase::Idle;
+        phase
+            .transition("select_region", &[MonitorPhase::Idle], MonitorPhase::Selecting)
+            .unwrap();
+        let error = start(&mut phase).unwrap_err();
+        assert_eq!(error.current, MonitorPhase::Selecting);
+        assert_eq!(error.required, vec![MonitorPhase::Idle]);
+        assert_eq!(phase, MonitorPhase::Selecting);
+    }
+
+    #[test]
+    fn stop_is_rejected_while_starting() {
+        let mut phase = MonitorPhase::Idle;
+        start(&mut phase).unwrap();
+        let error = stop(&mut phase).unwrap_err();
+        assert_eq!(error.command, "stop_monitoring");
+        assert_eq!(error.current, MonitorPhase::Starting);
+        assert_eq!(error.required, vec![MonitorPhase::Monitoring]);
+        assert_eq!(phase, MonitorPhase::Starting);
+
+        // 起動が済めば停止できる
+        phase = MonitorPhase::Monitoring;
+        stop(&mut phase).unwrap();
+        assert_eq!(phase, MonitorPhase::Stopping);
+    }
+
+    #[test]
+    fn invalid_state_is_returned_with_current_and_required_phases() {
+        let mut phase = MonitorPhase::Starting;
+        let error = MonitorCommandError::from(start(&mut phase).unwrap_err());
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({
+                "kind": "invalid_state",
+                "message": "start_monitoring は現在の状態（starting）では実行できません（必要な状態: idle）",
+                "current": "starting",
+                "required": ["idle"],
+            })
+        );
+    }
+}
@@ -0,0 +1,542 @@
+// テキスト変化イベントのファイル・標準出力への直接の書き出し（jqやgrepなどのコマンドへのパイプ用）
+//
+// 1件のイベントを1行として追記する。書き出しは専用のスレッドで行い、溜まった分をまとめてフラッシュする。
+// ファイルへの書き出しでは、大きさや1時間ごとに古い内容を別名に移して新しいファイルに書き始められる。
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local, SecondsFormat, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use crate::clock::SessionClock;
+use crate::events::{now_millis, TextChangeEvent};
+use crate::export::{event_texts, push_record};
+use crate::locking::lock;
+use crate::monitor;
+use crate::schema::v1;
+use crate::validation::{Validate, Validator};
+
+/// 標準出力に書き出す場合のパス
+pub const STDOUT_PATH: &str = "-";
+
+/// CSVの列名（新しいファイルの先頭行に出力する）
+const CSV_COLUMNS: [&str; 5] = ["sequence", "captured_at", "event_type", "old_text", "new_text"];
+
+/// 1時間のミリ秒
+const HOUR_MS: u64 = 3_600_000;
+
+/// 書き出す形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// 時刻・種類・テキストを空白で区切った1行（テキストの改行は \n とする）
+    PlainText,
+    /// 1件を1行のJSONとする
+    JsonLines,
+    /// RFC 4180のCSV（改行を含むテキストは引用符で囲む）
+    Csv,
+}
+
+impl OutputFormat {
+    /// "text"・"jsonl"・"csv" などの名前を解釈（大文字小文字は区別しない）
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "text" | "plain" | "plain_text" => Ok(OutputFormat::PlainText),
+            "jsonl" | "json_lines" | "ndjson" => Ok(OutputFormat::JsonLines),
+            "csv" => Ok(OutputFormat::Csv),
+            _ => bail!("書き出す形式の指定が不正です（text、jsonl、csv のいずれか）: {}", value),
+        }
+    }
+}
+
+/// ファイルを切り替える条件（標準出力では無視する）
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileRotationPolicy {
+    /// ファイルがこの大きさ（バイト）を超えたら切り替える（Noneなら大きさでは切り替えない）
+    pub max_bytes: Option<u64>,
+    /// 時刻の「時」が変わるごとに切り替えるかどうか
+    pub hourly: bool,
+}
+
+impl Validate for FileRotationPolicy {
+    const PREFIX: &'static str = "pipe_output";
+
+    fn check(&self, validator: &mut Validator) {
+        if let Some(max_bytes) = self.max_bytes {
+            validator.range("max_bytes", max_bytes, 1024, u64::MAX);
+        }
+    }
+}
+
+/// 書き出す1件のイベント
+pub struct PipeRecord {
+    /// 履歴の連番（履歴に記録しないイベントはNone）
+    pub sequence: Option<u64>,
+    /// UNIXエポックからのミリ秒
+    pub timestamp_ms: u64,
+    /// イベントの種類
+    pub event_type: &'static str,
+    /// 変化前のテキスト
+    pub old_text: String,
+    /// 変化後のテキスト
+    pub new_text: String,
+    /// JSON Linesで書き出す内容
+    pub json: serde_json::Value,
+}
+
+impl PipeRecord {
+    /// アプリのイベントから作成（JSON Linesはウィンドウに送るペイロードと同じ内容）
+    pub fn from_event(event: &TextChangeEvent, payload: &v1::TextChangedPayload) -> Self {
+        let (old_text, new_text) = event_texts(event);
+        Self {
+            sequence: payload.sequence,
+            timestamp_ms: payload.timestamp_ms,
+            event_type: event.type_name(),
+            old_text,
+            new_text,
+            json: serde_json::to_value(payload).unwrap_or_default(),
+        }
+    }
+
+    /// ScreenMonitorのイベントから作成
+    pub fn from_monitor_event(event: &monitor::TextChangeEvent, clock: &SessionClock) -> Self {
+        let (event_type, old_text, new_text) = match event {
+            monitor::TextChangeEvent::NewText(text) => ("new", String::new(), text.clone()),
+            monitor::TextChangeEvent::TextChanged { old, new } => ("changed", old.clone(), new.clone()),
+            monitor::TextChangeEvent::TextCleared(text) => ("cleared", text.clone(), String::new()),
+            monitor::TextChangeEvent::DiffDetected { added, removed } => ("diff", removed.join("\n"), added.join("\n")),
+            monitor::TextChangeEvent::Error(message) => ("error", String::new(), message.clone()),
+            monitor::TextChangeEvent::ReferenceSet { text } => ("reference_set", String::new(), text.clone()),
+            monitor::TextChangeEvent::Info { message } => ("info", String::new(), message.clone()),
+        };
+        let timestamp_ms = clock.now_ms();
+        let json = serde_json::json!({
+            "timestamp_ms": timestamp_ms,
+            "type": event_type,
+            "old_text": old_text,
+            "new_text": new_text,
+        });
+        Self {
+            sequence: None,
+            timestamp_ms,
+            event_type,
+            old_text,
+            new_text,
+            json,
+        }
+    }
+}
+
+/// 書き出しのスレッドに渡すキューの容量（書き出しが追いつかず溢れた分は捨て、監視は止めない）
+const PIPE_QUEUE_CAPACITY: usize = 1024;
+
+/// 書き出し先
+enum PipeTarget {
+    Stdout(BufWriter<io::Stdout>),
+    File {
+        path: PathBuf,
+        /// 書き出し中のファイル（切り替えの途中で開き直せなかった場合はNone）
+        file: Option<BufWriter<File>>,
+        /// 現在のファイルの大きさ
+        written: u64,
+        /// 現在のファイルに書き始めた時刻の「時」（UNIXエポックからの時間数）
+        hour: u64,
+    },
+}
+
+/// 起動中の書き出し（書き出しは専用のスレッドで行い、監視のスレッドはキューに積むだけにする）
+pub struct EventPipe {
+    destination: String,
+    sender: Option<SyncSender<PipeRecord>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl EventPipe {
+    /// 書き出しを開始（パスが "-" なら標準出力、ファイルは追記で開く）
+    pub fn open(path: &Path, format: OutputFormat, rotation: FileRotationPolicy) -> Result<Self> {
+        rotation.validate()?;
+        let target = if path == Path::new(STDOUT_PATH) {
+            PipeTarget::Stdout(BufWriter::new(io::stdout()))
+        } else {
+            let (file, written) = open_append(path)?;
+            PipeTarget::File {
+                path: path.to_path_buf(),
+                file: Some(BufWriter::new(file)),
+                written,
+                hour: now_millis() / HOUR_MS,
+            }
+        };
+        let mut writer = PipeWriter { target, format, rotation };
+        if writer.is_empty() {
+            writer.write_header()?;
+            writer.flush()?;
+        }
+
+        let destination = writer.destination();
+        let (sender, receiver) = mpsc::sync_channel(PIPE_QUEUE_CAPACITY);
+        let worker = thread::Builder::new()
+            .name("event-pipe".to_string())
+            .spawn(move || writer.run(receiver))
+            .context("書き出しのスレッドを起動できません")?;
+        Ok(Self {
+            destination,
+            sender: Some(sender),
+            worker: Some(worker),
+        })
+    }
+
+    /// 書き出し先（ファイルのパス、または標準出力）
+    pub fn destination(&self) -> String {
+        self.destination.clone()
+    }
+
+    /// イベントを書き出しのキューに積む（書き出しの結果はスレッドでログに残す）
+    pub fn write(&self, record: PipeRecord) -> Result<()> {
+        let Some(sender) = &self.sender else {
+            bail!("書き出しは停止しています");
+        };
+        match sender.try_send(record) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => bail!("書き出しが追いつかないためイベントを捨てました"),
+            Err(TrySendError::Disconnected(_)) => bail!("書き出しのスレッドが終了しています"),
+        }
+    }
+}
+
+impl Drop for EventPipe {
+    /// キューに残ったイベントを書き終えるまで待つ
+    fn drop(&mut self) {
+        self.sender.take();
+        if let Some(worker) = self.worker.take() {
+            if worker.join().is_err() {
+                log::warn!("{} への書き出しのスレッドが異常終了しました", self.destination);
+            }
+        }
+    }
+}
+
+/// 書き出しのスレッドで使う書き出し先と形式
+struct PipeWriter {
+    target: PipeTarget,
+    format: OutputFormat,
+    rotation: FileRotationPolicy,
+}
+
+impl PipeWriter {
+    /// キューが閉じられるまで書き出す（溜まっている分をまとめて書いてからフラッシュする）
+    fn run(mut self, receiver: Receiver<PipeRecord>) {
+        while let Ok(record) = receiver.recv() {
+            self.write_logged(&record);
+            while let Ok(record) = receiver.try_recv() {
+                self.write_logged(&record);
+            }
+            if let Err(e) = self.flush() {
+                log::warn!("{} をフラッシュできません: {:#}", self.destination(), e);
+            }
+        }
+    }
+
+    fn write_logged(&mut self, record: &PipeRecord) {
+        if let Err(e) = self.write(record) {
+            log::warn!("イベントを {} に書き出せません: {:#}", self.destination(), e);
+        }
+    }
+
+    fn destination(&self) -> String {
+        match &self.target {
+            PipeTarget::Stdout(_) => "標準出力".to_string(),
+            PipeTarget::File { path, .. } => path.display().to_string(),
+        }
+    }
+
+    /// イベントを1行書き出す（必要ならファイルを切り替えてから書く）
+    fn write(&mut self, record: &PipeRecord) -> Result<()> {
+        let line = self.format_record(record)?;
+        self.rotate_if_needed(line.len() as u64, record.timestamp_ms)?;
+        self.write_raw(line.as_bytes())
+    }
+
+    fn format_record(&self, record: &PipeRecord) -> Result<String> {
+        let captured_at = format_timestamp(record.timestamp_ms);
+        Ok(match self.format {
+            OutputFormat::PlainText => {
+                let text = if record.new_text.is_empty() { &record.old_text } else { &record.new_text };
+                format!("{} {} {}\n", captured_at, record.event_type, text.replace('\n', "\\n"))
+            }
+            OutputFormat::JsonLines => format!("{}\n", serde_json::to_string(&record.json)?),
+            OutputFormat::Csv => {
+                let mut line = String::new();
+                let fields = [
+                    record.sequence.map(|sequence| sequence.to_string()).unwrap_or_default(),
+                    captured_at,
+                    record.event_type.to_string(),
+                    record.old_text.clone(),
+                    record.new_text.clone(),
+                ];
+                push_record(&mut line, fields.into_iter(), ',');
+                line
+            }
+        })
+    }
+
+    /// 切り替えの条件を満たしていれば、現在のファイルを閉じて別名に移し、新しいファイルを開く
+    ///
+    /// Windowsでは開いたままのファイルの名前を変えられないため、閉じてから移す。
+    /// 移せなかった場合は同じパスを開き直して書き続ける。
+    fn rotate_if_needed(&mut self, line_bytes: u64, timestamp_ms: u64) -> Result<()> {
+        let PipeTarget::File { path, file, written, hour } = &mut self.target else {
+            return Ok(());
+        };
+        let current_hour = timestamp_ms / HOUR_MS;
+        let over_size = self.rotation.max_bytes.is_some_and(|max| *written > 0 && *written + line_bytes > max);
+        let hour_changed = self.rotation.hourly && current_hour != *hour;
+        if !over_size && !hour_changed {
+            return Ok(());
+        }
+
+        if let Some(mut current) = file.take() {
+            current.flush()?;
+        }
+        let rotated = rotated_path(path, *hour * HOUR_MS);
+        let renamed = fs::rename(&*path, &rotated).with_context(|| format!("{} を {} に移せません", path.display(), rotated.display()));
+        let (new_file, new_written) = open_append(path)?;
+        *file = Some(BufWriter::new(new_file));
+        *written = new_written;
+        *hour = current_hour;
+        match renamed {
+            Ok(()) => {
+                log::info!("書き出し先のファイルを切り替えました: {}", rotated.display());
+                self.write_header()
+            }
+            Err(e) => {
+                log::warn!("ファイルを切り替えられないため同じファイルに書き続けます: {:#}", e);
+                Ok(())
+            }
+        }
+    }
+
+    /// CSVの列名を書き出す（他の形式では何もしない）
+    fn write_header(&mut self) -> Result<()> {
+        if self.format != OutputFormat::Csv {
+            return Ok(());
+        }
+        let mut header = String::new();
+        push_record(&mut header, CSV_COLUMNS.iter().map(|column| column.to_string()), ',');
+        self.write_raw(header.as_bytes())
+    }
+
+    fn is_empty(&self) -> bool {
+        match &self.target {
+            // 標準出力は続きに書くことがあるため、列名は出力する
+            PipeTarget::Stdout(_) => true,
+            PipeTarget::File { written, .. } => *written == 0,
+        }
+    }
+
+    fn write_raw(&mut self, bytes: &[u8]) -> Result<()> {
+        match &mut self.target {
+            PipeTarget::Stdout(stdout) => stdout.write_all(bytes)?,
+            PipeTarget::File { file, written, path, .. } => {
+                let file = file.as_mut().with_context(|| format!("{} を開けていません", path.display()))?;
+                file.write_all(bytes)?;
+                *written += bytes.len() as u64;
+            }
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        match &mut self.target {
+            PipeTarget::Stdout(stdout) => stdout.flush()?,
+            PipeTarget::File { file, .. } => {
+                if let Some(file) = file.as_mut() {
+                    file.flush()?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// スレッド間で共有する書き出し（起動していなければNone）
+pub type SharedEventPipe = Arc<Mutex<Option<EventPipe>>>;
+
+/// 起動中なら1件を書き出しのキューに積む（書き出せなくても監視は続ける）
+pub fn write_to_pipe(pipe: &Mutex<Option<EventPipe>>, record: impl FnOnce() -> PipeRecord) {
+    let pipe = lock(pipe);
+    let Some(pipe) = pipe.as_ref() else {
+        return;
+    };
+    if let Err(e) = pipe.write(record()) {
+        log::warn!("イベントを {} に書き出せません: {:#}", pipe.destination(), e);
+    }
+}
+
+/// ファイルを追記で開き、現在の大きさとともに返す
+fn open_append(path: &Path) -> Result<(File, u64)> {
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("{} を開けません", path.display()))?;
+    let written = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+    Ok((file, written))
+}
+
+/// 切り替えたファイルの移動先（events.jsonl → events.20240101-09.jsonl、同名があれば連番を付ける）
+fn rotated_path(path: &Path, started_ms: u64) -> PathBuf {
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+    let extension = path.extension().map(|extension| format!(".{}", extension.to_string_lossy())).unwrap_or_default();
+    let period = Utc
+        .timestamp_millis_opt(started_ms as i64)
+        .single()
+        .map(|utc| DateTime::<Local>::from(utc).format("%Y%m%d-%H").to_string())
+        .unwrap_or_default();
+    (0u32..)
+        .map(|index| {
+            let suffix = if index == 0 { String::new() } else { format!("-{}", index) };
+            path.with_file_name(format!("{}.{}{}{}", stem, period, suffix, extension))
+        })
+        .find(|candidate| !candidate.exists())
+        .unwrap_or_else(|| path.with_extension("rotated"))
+}
+
+/// UNIXエポックからのミリ秒をローカル時刻のRFC 3339形式に変換
+fn format_timestamp(timestamp_ms: u64) -> String {
+    Utc.timestamp_millis_opt(timestamp_ms as i64)
+        .single()
+        .map(|utc| DateTime::<Local>::from(utc).to_rfc3339_opts(SecondsFormat::Millis, false))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// テストごとの一時ディレクトリ
+    fn temp_dir(name: &str) -> PathBuf {
+        static COUNTER: AtomicU32 = AtomicU32::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "pipe_output_test_{}_{}_{}",
+            name,
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn record(sequence: u64, event_type: &'static str, old_text: &str, new_text: &str) -> PipeRecord {
+        PipeRecord {
+            sequence: Some(sequence),
+            timestamp_ms: 1_700_000_000_000 + sequence * 1000,
+            event_type,
+            old_text: old_text.to_string(),
+            new_text: new_text.to_string(),
+            json: serde_json::json!({ "sequence": sequence, "type": event_type, "new_text": new_text }),
+        }
+    }
+
+    /// 5件の合成イベント（改行やカンマを含むテキストも混ぜる）
+    fn five_events() -> Vec<PipeRecord> {
+        vec![
+            record(1, "new", "", "こんにちは"),
+            record(2, "changed", "こんにちは", "こんにちは、世界"),
+            record(3, "diff", "", "一行目\n二行目"),
+            record(4, "cleared", "一行目\n二行目", ""),
+            record(5, "new", "", "\"引用\"付き"),
+        ]
+    }
+
+    fn write_all(path: &Path, format: OutputFormat, rotation: FileRotationPolicy, records: Vec<PipeRecord>) {
+        let pipe = EventPipe::open(path, format, rotation).unwrap();
+        for record in records {
+            pipe.write(record).unwrap();
+        }
+        // 停止（drop）でキューに残った分を書き終える
+        drop(pipe);
+    }
+
+    #[test]
+    fn writes_five_events_as_json_lines() {
+        let path = temp_dir("jsonl").join("events.jsonl");
+        write_all(&path, OutputFormat::JsonLines, FileRotationPolicy::default(), five_events());
+
+        let content = fs::read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> = content.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines.iter().map(|line| line["sequence"].as_u64().unwrap()).collect::<Vec<_>>(), vec![1, 2, 3, 4, 5]);
+        assert_eq!(lines[2]["new_text"], "一行目\n二行目");
+        assert_eq!(lines[3]["type"], "cleared");
+    }
+
+    #[test]
+    fn writes_five_events_as_csv_with_header() {
+        let path = temp_dir("csv").join("events.csv");
+        write_all(&path, OutputFormat::Csv, FileRotationPolicy::default(), five_events());
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with("sequence,captured_at,event_type,old_text,new_text\r\n"));
+        assert!(content.contains(",changed,こんにちは,\"こんにちは、世界\"\r\n") || content.contains(",changed,こんにちは,こんにちは、世界\r\n"));
+        assert!(content.contains("\"一行目\n二行目\""));
+        assert!(content.contains("\"\"\"引用\"\"付き\""));
+        // 列名と5件（改行を含むテキストは引用符の中で改行する）
+        assert_eq!(content.matches("\r\n").count(), 6);
+
+        // 既存のファイルに追記する場合は列名を繰り返さない
+        write_all(&path, OutputFormat::Csv, FileRotationPolicy::default(), vec![record(6, "new", "", "追記")]);
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content.matches("sequence,captured_at").count(), 1);
+        assert!(content.ends_with(",new,,追記\r\n"));
+    }
+
+    #[test]
+    fn writes_five_events_as_plain_text() {
+        let path = temp_dir("text").join("events.txt");
+        write_all(&path, OutputFormat::PlainText, FileRotationPolicy::default(), five_events());
+
+        let content = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert!(lines[0].ends_with(" new こんにちは"));
+        assert!(lines[2].ends_with(" diff 一行目\\n二行目"));
+        // クリアは変化前のテキストを出力する
+        assert!(lines[3].ends_with(" cleared 一行目\\n二行目"));
+    }
+
+    #[test]
+    fn rotates_by_size_into_closed_files() {
+        let dir = temp_dir("rotate");
+        let path = dir.join("events.jsonl");
+        let rotation = FileRotationPolicy {
+            max_bytes: Some(1024),
+            hourly: false,
+        };
+        let long = "あ".repeat(200);
+        let records = (1..=5).map(|sequence| record(sequence, "new", "", &long)).collect();
+        write_all(&path, OutputFormat::JsonLines, rotation, records);
+
+        let mut files: Vec<PathBuf> = fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().path()).collect();
+        files.sort();
+        assert!(files.len() > 1, "{:?}", files);
+        let total: usize = files.iter().map(|file| fs::read_to_string(file).unwrap().lines().count()).sum();
+        assert_eq!(total, 5);
+        for file in &files {
+            assert!(fs::metadata(file).unwrap().len() <= 1024 || fs::read_to_string(file).unwrap().lines().count() == 1);
+        }
+    }
+
+    #[test]
+    fn parses_output_formats() {
+        assert_eq!(OutputFormat::parse("JSONL").unwrap(), OutputFormat::JsonLines);
+        assert_eq!(OutputFormat::parse("plain").unwrap(), OutputFormat::PlainText);
+        assert_eq!(OutputFormat::parse("csv").unwrap(), OutputFormat::Csv);
+        assert!(OutputFormat::parse("xml").is_err());
+    }
+}
@@ -0,0 +1,575 @@
+// OCR前のフレーム判定に使う画像処理ユーティリティ
+#[cfg(feature = "super_res")]
+use anyhow::{anyhow, Result};
+#[cfg(feature = "super_res")]
+use image::{imageops, ImageBuffer, Luma};
+use image::{imageops::FilterType, DynamicImage, GrayImage};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// フレーム解析の前に縮小する幅（大きな領域でも解析の時間を一定に抑える）
+const ANALYSIS_MAX_WIDTH: u32 = 320;
+
+/// 背景との輝度差がこれ以上の画素を前景とみなす
+const FOREGROUND_LUMA_DELTA: i16 = 40;
+
+/// これ未満の画素数の前景成分はノイズとして数えない
+const MIN_COMPONENT_PIXELS: usize = 4;
+
+/// コントラストを求める輝度の下位・上位の割合（外れた少数の画素で値が振れないようにする）
+const CONTRAST_PERCENTILE: f32 = 0.05;
+
+/// 傾きを調べる最大の角度（度）
+const MAX_SKEW_DEG: f32 = 10.0;
+
+/// 傾きを調べる角度の刻み（度）
+const SKEW_STEP_DEG: f32 = 0.5;
+
+/// 傾きを推定するのに必要な前景の画素数（これより少なければ傾きは0とする）
+const MIN_SKEW_PIXELS: usize = 50;
+
+/// 知覚ハッシュによるフレームの類似度判定
+pub struct ImageHasher;
+
+impl ImageHasher {
+    /// 64ビットの差分ハッシュ（dHash）を計算
+    ///
+    /// 9x8のグレースケールに縮小し、各行で隣り合う画素の明暗（左が右より明るいか）を
+    /// 1ビットずつ並べる。平均差分と違い、明るさが一様に変わっただけでは値が変わりにくい。
+    pub fn dhash(image: &DynamicImage) -> u64 {
+        let small = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+
+        let mut hash = 0u64;
+        for y in 0..8 {
+            for x in 0..8 {
+                let left = small.get_pixel(x, y)[0];
+                let right = small.get_pixel(x + 1, y)[0];
+                hash = (hash << 1) | u64::from(left > right);
+            }
+        }
+        hash
+    }
+
+    /// 2つのハッシュの異なるビット数
+    pub fn hamming_distance(a: u64, b: u64) -> u32 {
+        (a ^ b).count_ones()
+    }
+}
+
+/// 同梱の2倍超解像モデル（SRCNN、super_resフィーチャー有効時のみ）
+///
+/// 双三次補間で2倍にした画像（1x1xHxW、0.0-1.0）から補間で失われた高周波成分を推定して足す
+/// 3層の畳み込み（5x5で16チャンネル、1x1で8チャンネル、3x3で1チャンネル）。
+/// DejaVuフォントで描画した文字を縮小・拡大した画像の組で学習した、このリポジトリ独自の重み。
+#[cfg(feature = "super_res")]
+const SRCNN_X2_MODEL: &[u8] = include_bytes!("assets/srcnn_x2.onnx");
+
+/// OCR前に画像を作り変える前処理
+pub struct ImagePreprocessor;
+
+impl ImagePreprocessor {
+    /// 超解像を適用する大きさかどうか（長い辺がしきい値未満の画像のみ）
+    ///
+    /// 超解像は画素数に比例して時間がかかるため、双三次補間では文字が潰れるほど小さい画像に限る。
+    pub fn needs_super_resolution(width: u32, height: u32, threshold_px: u32) -> bool {
+        width > 0 && height > 0 && width.max(height) < threshold_px
+    }
+
+    /// 同梱のSRCNNモデルで2倍に超解像
+    ///
+    /// ONNX Runtimeの共有ライブラリ（ORT_DYLIB_PATH、未指定なら実行ファイルと同じ場所の
+    /// onnxruntime）は最初の呼び出しで読み込む。読み込みに失敗した場合はその後も同じエラーを返す。
+    #[cfg(feature = "super_res")]
+    pub fn super_resolve_2x(image: &ImageBuffer<Luma<u8>, Vec<u8>>) -> Result<ImageBuffer<Luma<u8>, Vec<u8>>> {
+        use std::sync::OnceLock;
+
+        static SESSION: OnceLock<std::result::Result<ort::session::Session, String>> = OnceLock::new();
+
+        if image.width() == 0 || image.height() == 0 {
+            return Err(anyhow!("無効な画像サイズ: {}x{}", image.width(), image.height()));
+        }
+        let session = SESSION
+            .get_or_init(|| {
+                // ortは共有ライブラリが見つからないとエラーを返さずpanicするため、ここで止める
+                std::panic::catch_unwind(|| {
+                    ort::session::Session::builder()
+                        .and_then(|builder| builder.with_intra_threads(1))
+                        .and_then(|builder| builder.commit_from_memory(SRCNN_X2_MODEL))
+                        .map_err(|e| e.to_string())
+                })
+                .unwrap_or_else(|_| Err("ONNX Runtimeの共有ライブラリを読み込めません".to_string()))
+            })
+            .as_ref()
+            .map_err(|e| anyhow!("超解像モデルを読み込めません: {}", e))?;
+
+        let (width, height) = (image.width() * 2, image.height() * 2);
+        let upscaled = imageops::resize(image, width, height, FilterType::CatmullRom);
+        let input: Vec<f32> = upscaled.as_raw().iter().map(|&v| f32::from(v) / 255.0).collect();
+        let tensor = ort::value::Tensor::from_array(([1usize, 1, height as usize, width as usize], input))?;
+        let outputs = session.run(ort::inputs![tensor]?)?;
+        let (_, output) = outputs[0].try_extract_raw_tensor::<f32>()?;
+        if output.len() != upscaled.as_raw().len() {
+            return Err(anyhow!("超解像モデルの出力の大きさが異なります: {}", output.len()));
+        }
+
+        let pixels = output.iter().map(|&v| (v * 255.0).round().clamp(0.0, 255.0) as u8).collect();
+        ImageBuffer::from_raw(width, height, pixels).ok_or_else(|| anyhow!("超解像の結果を画像にできません"))
+    }
+}
+
+/// フレームの内容の解析結果
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameAnalysis {
+    /// 輝度の分散
+    pub variance: f32,
+    /// 前景（背景と輝度が大きく異なる画素）の連結成分の数
+    pub foreground_components: usize,
+}
+
+impl FrameAnalysis {
+    /// フレームを解析（背景は最も多い輝度とみなす）
+    pub fn analyze(image: &DynamicImage) -> Self {
+        let gray = if image.width() > ANALYSIS_MAX_WIDTH {
+            image.resize(ANALYSIS_MAX_WIDTH, u32::MAX, FilterType::Triangle).to_luma8()
+        } else {
+            image.to_luma8()
+        };
+        Self {
+            variance: luma_variance(&gray),
+            foreground_components: foreground_component_boxes(&gray).len(),
+        }
+    }
+}
+
+/// OCRの品質の目安になる画像のゆがみの指標（前処理で強調する前のキャプチャのグレースケールで計算する）
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ImageMetrics {
+    /// ラプラシアンの分散（小さいほどぼやけている）
+    pub blur_score: f32,
+    /// 隣り合う画素の輝度差の平均絶対偏差（大きいほどノイズが多い）
+    pub noise_score: f32,
+    /// 文字の行の傾き（度、正の値は右下がり）
+    pub skew_angle_deg: f32,
+    /// 輝度の範囲（(上位5%の輝度 - 下位5%の輝度) / 255）
+    pub contrast_ratio: f32,
+    /// 前景の成分の外接矩形が画像に占める割合（0.0-1.0、計測しない設定や計測しないフレームではNone）
+    #[serde(default)]
+    pub text_coverage: Option<f32>,
+}
+
+impl ImageMetrics {
+    /// 画像の指標を計算（文字の占める割合は連結成分を探すため時間がかかり、text_coverageで別に計測する）
+    ///
+    /// 二値化や拡大の後では、ぼやけやノイズ、コントラストが前処理で変わってしまうため、
+    /// 前処理の前のキャプチャを渡す。
+    pub fn measure(image: &DynamicImage) -> Self {
+        let gray = image.to_luma8();
+        let contrast_ratio = percentile_contrast(&gray);
+        // 傾きは行の並びだけを見るため、縮小して計算量を抑える
+        let small = if image.width() > ANALYSIS_MAX_WIDTH {
+            image.resize(ANALYSIS_MAX_WIDTH, u32::MAX, FilterType::Triangle).to_luma8()
+        } else {
+            gray.clone()
+        };
+        Self {
+            blur_score: laplacian_variance(&gray),
+            noise_score: neighbor_difference_deviation(&gray),
+            skew_angle_deg: estimate_skew(&small),
+            contrast_ratio,
+            text_coverage: None,
+        }
+    }
+}
+
+/// 前景の成分の外接矩形が画像に占める割合（0.0-1.0、傾きと同じく縮小した画像で計算する）
+pub fn text_coverage(image: &DynamicImage) -> f32 {
+    let small = if image.width() > ANALYSIS_MAX_WIDTH {
+        image.resize(ANALYSIS_MAX_WIDTH, u32::MAX, FilterType::Triangle).to_luma8()
+    } else {
+        image.to_luma8()
+    };
+    component_coverage(&small)
+}
+
+/// 輝度の下位・上位 CONTRAST_PERCENTILE の位置の差（0.0-1.0）
+fn percentile_contrast(gray: &GrayImage) -> f32 {
+    let count = gray.pixels().len();
+    if count == 0 {
+        return 0.0;
+    }
+    let mut histogram = [0usize; 256];
+    for pixel in gray.pixels() {
+        histogram[pixel[0] as usize] += 1;
+    }
+    // 累積の画素数が指定の順位を超える最初の輝度
+    let luma_at = |rank: usize| {
+        let mut cumulative = 0;
+        histogram
+            .iter()
+            .position(|&pixels| {
+                cumulative += pixels;
+                cumulative > rank
+            })
+            .unwrap_or(255)
+    };
+    let rank = (count as f32 * CONTRAST_PERCENTILE) as usize;
+    let low = luma_at(rank);
+    let high = luma_at(count - 1 - rank);
+    high.saturating_sub(low) as f32 / 255.0
+}
+
+/// 4近傍のラプラシアンの分散
+fn laplacian_variance(gray: &GrayImage) -> f32 {
+    let (width, height) = gray.dimensions();
+    if width < 3 || height < 3 {
+        return 0.0;
+    }
+    let luma = |x: u32, y: u32| gray.get_pixel(x, y)[0] as f64;
+    // 前処理で拡大された画像は大きいため、値を保持せずに和と二乗和から求める
+    let (mut sum, mut sum_squares) = (0.0f64, 0.0f64);
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            let value = luma(x - 1, y) + luma(x + 1, y) + luma(x, y - 1) + luma(x, y + 1) - 4.0 * luma(x, y);
+            sum += value;
+            sum_squares += value * value;
+        }
+    }
+    let count = ((width - 2) * (height - 2)) as f64;
+    let mean = sum / count;
+    (sum_squares / count - mean * mean).max(0.0) as f32
+}
+
+/// 右・下の画素との輝度差の平均絶対偏差
+fn neighbor_difference_deviation(gray: &GrayImage) -> f32 {
+    let (width, height) = gray.dimensions();
+    let luma = |x: u32, y: u32| gray.get_pixel(x, y)[0] as f64;
+    // 1回目で平均を求め、2回目で平均からの偏差を数える（差を保持しない）
+    let for_each_difference = |f: &mut dyn FnMut(f64)| {
+        for y in 0..height {
+            for x in 0..width {
+                if x + 1 < width {
+                    f((luma(x + 1, y) - luma(x, y)).abs());
+                }
+                if y + 1 < height {
+                    f((luma(x, y + 1) - luma(x, y)).abs());
+                }
+            }
+        }
+    };
+    let (mut sum, mut count) = (0.0f64, 0usize);
+    for_each_difference(&mut |difference| {
+        sum += difference;
+        count += 1;
+    });
+    if count == 0 {
+        return 0.0;
+    }
+    let mean = sum / count as f64;
+    let mut deviation = 0.0f64;
+    for_each_difference(&mut |difference| deviation += (difference - mean).abs());
+    (deviation / count as f64) as f32
+}
+
+/// 投影の偏りが最も大きくなる角度を文字の行の傾きとして推定（度）
+///
+/// 前景の画素を各角度で行方向に投影し、行ごとの画素数の二乗和が最大になる角度を選ぶ
+/// （文字の行と投影の向きが揃うと、行と行間の差がはっきりする）。
+fn estimate_skew(gray: &GrayImage) -> f32 {
+    let mut histogram = [0usize; 256];
+    for pixel in gray.pixels() {
+        histogram[pixel[0] as usize] += 1;
+    }
+    let background = (0..256).max_by_key(|&luma| histogram[luma]).unwrap_or(0) as i16;
+    let points: Vec<(f32, f32)> = gray
+        .enumerate_pixels()
+        .filter(|(_, _, pixel)| (pixel[0] as i16 - background).abs() >= FOREGROUND_LUMA_DELTA)
+        .map(|(x, y, _)| (x as f32, y as f32))
+        .collect();
+    if points.len() < MIN_SKEW_PIXELS {
+        return 0.0;
+    }
+
+    // 回転後の行の位置が負にならないよう、画像の幅の分だけずらして数える
+    let offset = gray.width() as f32;
+    let rows = (gray.width() + gray.height()) as usize * 2 + 1;
+    let steps = (MAX_SKEW_DEG / SKEW_STEP_DEG) as i32;
+    let mut best = (0.0f32, 0u64);
+    for step in -steps..=steps {
+        let angle = step as f32 * SKEW_STEP_DEG;
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let mut counts = vec![0u64; rows];
+        for &(x, y) in &points {
+            let row = (y * cos - x * sin + offset).round().clamp(0.0, (rows - 1) as f32) as usize;
+            counts[row] += 1;
+        }
+        let score = counts.iter().map(|count| count * count).sum::<u64>();
+        // 同点なら傾きの小さい方を選ぶ
+        if score > best.1 || (score == best.1 && angle.abs() < best.0.abs()) {
+            best = (angle, score);
+        }
+    }
+    best.0
+}
+
+/// 輝度の分散
+fn luma_variance(gray: &GrayImage) -> f32 {
+    let count = gray.pixels().len();
+    if count == 0 {
+        return 0.0;
+    }
+    let mean = gray.pixels().map(|p| p[0] as f64).sum::<f64>() / count as f64;
+    let variance = gray.pixels().map(|p| (p[0] as f64 - mean).powi(2)).sum::<f64>() / count as f64;
+    variance as f32
+}
+
+/// 前景の成分の外接矩形の和集合が画像に占める割合
+fn component_coverage(gray: &GrayImage) -> f32 {
+    let (width, height) = gray.dimensions();
+    if width == 0 || height == 0 {
+        return 0.0;
+    }
+    let mut covered = vec![false; (width * height) as usize];
+    for bounds in foreground_component_boxes(gray) {
+        for y in bounds.min_y..=bounds.max_y {
+            let row = (y * width) as usize;
+            covered[row + bounds.min_x as usize..=row + bounds.max_x as usize].fill(true);
+        }
+    }
+    covered.iter().filter(|&&inside| inside).count() as f32 / covered.len() as f32
+}
+
+/// 前景の成分の外接矩形（両端の画素を含む）
+#[derive(Debug, Clone, Copy)]
+struct ComponentBounds {
+    min_x: u32,
+    min_y: u32,
+    max_x: u32,
+    max_y: u32,
+}
+
+/// 前景の連結成分（4近傍）のうち、ノイズより大きいものの外接矩形
+fn foreground_component_boxes(gray: &GrayImage) -> Vec<ComponentBounds> {
+    let (width, height) = gray.dimensions();
+    let mut histogram = [0usize; 256];
+    for pixel in gray.pixels() {
+        histogram[pixel[0] as usize] += 1;
+    }
+    let background = (0..256).max_by_key(|&luma| histogram[luma]).unwrap_or(0) as i16;
+
+    let is_foreground = |x: u32, y: u32| (gray.get_pixel(x, y)[0] as i16 - background).abs() >= FOREGROUND_LUMA_DELTA;
+    let mut visited = vec![false; (width * height) as usize];
+    let mut queue = VecDeque::new();
+    let mut components = Vec::new();
+
+    for start_y in 0..height {
+        for start_x in 0..width {
+            let start = (start_y * width + start_x) as usize;
+            if visited[start] || !is_foreground(start_x, start_y) {
+                continue;
+            }
+            visited[start] = true;
+            queue.push_back((start_x, start_y));
+            let mut pixels = 0;
+            let mut bounds = ComponentBounds {
+                min_x: start_x,
+                min_y: start_y,
+                max_x: start_x,
+                max_y: start_y,
+            };
+            while let Some((x, y)) = queue.pop_front() {
+                pixels += 1;
+                bounds.min_x = bounds.min_x.min(x);
+                bounds.min_y = bounds.min_y.min(y);
+                bounds.max_x = bounds.max_x.max(x);
+                bounds.max_y = bounds.max_y.max(y);
+                let neighbors = [
+                    (x.wrapping_sub(1), y),
+                    (x + 1, y),
+                    (x, y.wrapping_sub(1)),
+                    (x, y + 1),
+                ];
+                for (nx, ny) in neighbors {
+                    if nx >= width || ny >= height {
+                        continue;
+                    }
+                    let index = (ny * width + nx) as usize;
+                    if !visited[index] && is_foreground(nx, ny) {
+                        visited[index] = true;
+                        queue.push_back((nx, ny));
+                    }
+                }
+            }
+            if pixels >= MIN_COMPONENT_PIXELS {
+                components.push(bounds);
+            }
+        }
+    }
+    components
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Luma;
+
+    /// 左半分が暗く右半分が明るい画像（outliersの個数だけ純白と純黒の画素を左上に置く）
+    fn two_tone(dark: u8, light: u8, outliers: u32) -> DynamicImage {
+        DynamicImage::ImageLuma8(GrayImage::from_fn(100, 100, |x, y| {
+            let index = y * 100 + x;
+            if index < outliers {
+                Luma([if index % 2 == 0 { 0 } else { 255 }])
+            } else if x < 50 {
+                Luma([dark])
+            } else {
+                Luma([light])
+            }
+        }))
+    }
+
+    /// 輝度が左から右へ一様に変わる画像（increasingなら右ほど明るい）
+    fn gradient(increasing: bool) -> DynamicImage {
+        DynamicImage::ImageLuma8(GrayImage::from_fn(90, 80, |x, _| {
+            let level = (x * 2) as u8;
+            Luma([if increasing { level } else { 255 - level }])
+        }))
+    }
+
+    /// 場所ごとに輝度の異なる模様の画像（offsetだけ全体を明るくする）
+    fn pattern(offset: u8) -> GrayImage {
+        GrayImage::from_fn(90, 80, |x, y| Luma([((x * 7 + y * 13) % 97) as u8 * 2 + offset]))
+    }
+
+    #[test]
+    fn hamming_distance_counts_differing_bits() {
+        assert_eq!(ImageHasher::hamming_distance(0, 0), 0);
+        assert_eq!(ImageHasher::hamming_distance(0b1011, 0b0001), 2);
+        assert_eq!(ImageHasher::hamming_distance(0, u64::MAX), 64);
+        assert_eq!(ImageHasher::hamming_distance(u64::MAX, 1 << 63), 63);
+    }
+
+    #[test]
+    fn identical_images_have_the_same_dhash() {
+        let image = DynamicImage::ImageLuma8(pattern(0));
+        assert_eq!(ImageHasher::dhash(&image), ImageHasher::dhash(&image.clone()));
+        // 色の形式が違っても輝度が同じなら同じハッシュになる
+        assert_eq!(ImageHasher::dhash(&image), ImageHasher::dhash(&DynamicImage::ImageRgb8(image.to_rgb8())));
+    }
+
+    #[test]
+    fn slightly_different_images_have_close_dhashes() {
+        let original = ImageHasher::dhash(&DynamicImage::ImageLuma8(pattern(0)));
+        // 明るさが一様に変わっただけなら隣り合う画素の明暗は変わらない
+        let brighter = ImageHasher::dhash(&DynamicImage::ImageLuma8(pattern(20)));
+        assert!(ImageHasher::hamming_distance(original, brighter) <= 2);
+
+        // 小さな部分だけが変わった画像
+        let mut touched = pattern(0);
+        for y in 0..4 {
+            for x in 0..4 {
+                touched.put_pixel(40 + x, 40 + y, Luma([255]));
+            }
+        }
+        let touched = ImageHasher::dhash(&DynamicImage::ImageLuma8(touched));
+        assert!(ImageHasher::hamming_distance(original, touched) <= 4);
+    }
+
+    #[test]
+    fn completely_different_images_have_distant_dhashes() {
+        let increasing = ImageHasher::dhash(&gradient(true));
+        let decreasing = ImageHasher::dhash(&gradient(false));
+        assert_eq!(increasing, 0);
+        assert_eq!(decreasing, u64::MAX);
+        assert_eq!(ImageHasher::hamming_distance(increasing, decreasing), 64);
+    }
+
+    #[test]
+    fn super_resolution_is_limited_to_small_images() {
+        assert!(ImagePreprocessor::needs_super_resolution(99, 49, 100));
+        assert!(!ImagePreprocessor::needs_super_resolution(100, 50, 100));
+        // 細長い領域は長い辺で判定する
+        assert!(!ImagePreprocessor::needs_super_resolution(320, 20, 100));
+        assert!(!ImagePreprocessor::needs_super_resolution(0, 20, 100));
+    }
+
+    /// ONNX Runtimeの共有ライブラリ（ORT_DYLIB_PATH）がなければ、panicせずに読み込みのエラーになることを確かめる
+    #[cfg(feature = "super_res")]
+    #[test]
+    fn super_resolution_doubles_the_size_or_reports_a_missing_runtime() {
+        let image = GrayImage::from_fn(40, 20, |x, _| Luma([if x < 20 { 30 } else { 220 }]));
+        let resolved = match ImagePreprocessor::super_resolve_2x(&image) {
+            Ok(resolved) => resolved,
+            Err(e) => {
+                assert!(e.to_string().contains("超解像モデルを読み込めません"), "{:#}", e);
+                return;
+            }
+        };
+        assert_eq!(resolved.dimensions(), (80, 40));
+        assert!(resolved.get_pixel(5, 20)[0].abs_diff(30) <= 8);
+        assert!(resolved.get_pixel(75, 20)[0].abs_diff(220) <= 8);
+    }
+
+    #[test]
+    fn contrast_ignores_a_few_extreme_pixels() {
+        let clean = ImageMetrics::measure(&two_tone(100, 150, 0));
+        let noisy = ImageMetrics::measure(&two_tone(100, 150, 20));
+        assert!((clean.contrast_ratio - 50.0 / 255.0).abs() < 1e-6);
+        assert_eq!(noisy.contrast_ratio, clean.contrast_ratio);
+        assert_eq!(ImageMetrics::measure(&two_tone(0, 255, 0)).contrast_ratio, 1.0);
+    }
+
+    #[test]
+    fn flat_image_has_no_contrast_or_coverage() {
+        let metrics = ImageMetrics::measure(&two_tone(128, 128, 0));
+        assert_eq!(metrics.contrast_ratio, 0.0);
+        assert_eq!(metrics.blur_score, 0.0);
+        assert_eq!(text_coverage(&two_tone(128, 128, 0)), 0.0);
+    }
+
+    #[test]
+    fn coverage_is_measured_separately() {
+        assert_eq!(ImageMetrics::measure(&two_tone(0, 255, 0)).text_coverage, None);
+    }
+
+    /// 白い背景に columns x rows 個の黒い正方形（一辺 size）を12画素おきに並べた画像
+    fn squares(columns: u32, rows: u32, size: u32) -> DynamicImage {
+        DynamicImage::ImageLuma8(GrayImage::from_fn(columns * 12 + 4, rows * 12 + 4, |x, y| {
+            let inside = |v: u32| v >= 4 && (v - 4) % 12 < size;
+            Luma([if inside(x) && inside(y) { 0 } else { 255 }])
+        }))
+    }
+
+    #[test]
+    fn blank_frame_has_no_variance_or_components() {
+        let analysis = FrameAnalysis::analyze(&two_tone(200, 200, 0));
+        assert_eq!(analysis, FrameAnalysis { variance: 0.0, foreground_components: 0 });
+    }
+
+    #[test]
+    fn each_separate_shape_is_one_component() {
+        let analysis = FrameAnalysis::analyze(&squares(6, 4, 4));
+        assert_eq!(analysis.foreground_components, 24);
+        // 黒い画素の割合 p に対して 255^2 * p * (1 - p)
+        let dark = (24 * 16) as f32 / (76 * 52) as f32;
+        assert!((analysis.variance - 255.0 * 255.0 * dark * (1.0 - dark)).abs() < 1.0, "{:?}", analysis);
+    }
+
+    #[test]
+    fn specks_smaller_than_a_component_are_ignored() {
+        let analysis = FrameAnalysis::analyze(&squares(6, 4, 1));
+        assert_eq!(analysis.foreground_components, 0);
+        assert!(analysis.variance > 0.0);
+    }
+
+    #[test]
+    fn blurred_shapes_are_still_counted() {
+        let blurred = squares(6, 4, 4).blur(1.0);
+        assert_eq!(FrameAnalysis::analyze(&blurred).foreground_components, 24);
+    }
+
+    #[test]
+    fn wide_frames_are_analyzed_at_a_reduced_width() {
+        // 縮小しても正方形は別々の成分のまま
+        let wide = squares(60, 2, 8).resize_exact(ANALYSIS_MAX_WIDTH * 3, 28 * 3, FilterType::Nearest);
+        assert_eq!(FrameAnalysis::analyze(&wide).foreground_components, 120);
+    }
+}
@@ -0,0 +1,329 @@
+// 指定したアプリのウィンドウをキャプチャしないための確認（パスワード管理やメールの画面を認識しない）
+//
+// キャプチャの前に領域に重なるトップレベルウィンドウの所有プロセスを調べ、拒否リストの
+// プロセスがあればそのフレームのキャプチャを行わない。ウィンドウの判定はWindowsのみ対応。
+use anyhow::Result;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::capture::{CaptureRegion, ScreenCapture};
+use crate::validation::{Validate, Validator};
+
+/// プロセスを判定できない場合に抑制を通知するときのプロセス名
+pub const UNKNOWN_PROCESS: &str = "unknown";
+
+/// キャプチャしないプロセスの設定
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProcessGuardConfig {
+    /// キャプチャしないプロセスの実行ファイル名（大文字小文字と .exe の有無は区別しない、空なら確認しない）
+    pub denied_processes: Vec<String>,
+    /// ウィンドウのプロセスを判定できない場合にキャプチャしないかどうか
+    /// （falseなら警告を出してキャプチャする）
+    pub fail_closed: bool,
+    /// ウィンドウを調べ直す間隔（ミリ秒、0なら毎回、間の回は前回の結果を使う）
+    pub check_interval_ms: u64,
+    /// 同じプロセスによる抑制を再度通知するまでの時間（ミリ秒）
+    pub notify_interval_ms: u64,
+}
+
+impl Default for ProcessGuardConfig {
+    fn default() -> Self {
+        Self {
+            denied_processes: Vec::new(),
+            fail_closed: false,
+            check_interval_ms: 1000,
+            notify_interval_ms: 60_000,
+        }
+    }
+}
+
+impl ProcessGuardConfig {
+    /// 確認が有効かどうか
+    pub fn enabled(&self) -> bool {
+        !self.denied_processes.is_empty()
+    }
+
+    /// get_statusで報告する状態
+    pub fn status(&self) -> ProcessGuardStatus {
+        ProcessGuardStatus {
+            enabled: self.enabled(),
+            detection_available: detection_available(),
+            fail_closed: self.fail_closed,
+            denied_processes: self.denied_processes.clone(),
+        }
+    }
+
+    /// 実行ファイル名が拒否リストに含まれるかどうか
+    fn is_denied(&self, process: &str) -> bool {
+        let name = normalize_process_name(process);
+        self.denied_processes.iter().any(|denied| normalize_process_name(denied) == name)
+    }
+}
+
+impl Validate for ProcessGuardConfig {
+    const PREFIX: &'static str = "process_guard";
+
+    fn check(&self, validator: &mut Validator) {
+        if self.denied_processes.iter().any(|process| process.trim().is_empty()) {
+            validator.invalid("denied_processes", "空の項目は指定できません");
+        }
+        validator.range("check_interval_ms", self.check_interval_ms, 0, 60_000);
+        validator.range("notify_interval_ms", self.notify_interval_ms, 1_000, 3_600_000);
+    }
+}
+
+/// プロセスの確認の状態
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessGuardStatus {
+    /// 確認が有効かどうか（拒否リストが空でない）
+    pub enabled: bool,
+    /// この環境でウィンドウのプロセスを判定できるかどうか
+    pub detection_available: bool,
+    /// 判定できない場合にキャプチャしないかどうか
+    pub fail_closed: bool,
+    /// キャプチャしないプロセス
+    pub denied_processes: Vec<String>,
+}
+
+/// 比較用のプロセス名（パスを除いた小文字の名前、末尾の .exe は除く）
+fn normalize_process_name(process: &str) -> String {
+    let name = process.rsplit(['\\', '/']).next().unwrap_or(process).trim().to_lowercase();
+    name.strip_suffix(".exe").map(str::to_string).unwrap_or(name)
+}
+
+/// 1フレームの確認結果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GuardDecision {
+    /// キャプチャしてよい
+    Allow,
+    /// キャプチャしない（notifyは抑制を通知するかどうか）
+    Suppress { process: String, notify: bool },
+}
+
+/// キャプチャの前の確認（前回の結果と通知の時刻を保持する）
+#[derive(Debug, Default)]
+pub struct ProcessGuard {
+    /// 前回ウィンドウを調べた時刻と、見つかった拒否リストのプロセス
+    last_check: Option<(Instant, Option<String>)>,
+    /// プロセスごとの最後に抑制を通知した時刻
+    last_notified: HashMap<String, Instant>,
+    /// 判定できない警告を出したかどうか
+    warned_unavailable: bool,
+}
+
+impl ProcessGuard {
+    /// 領域に拒否リストのプロセスのウィンドウが重なっていないか確認
+    pub fn check(&mut self, config: &ProcessGuardConfig, region: &CaptureRegion) -> GuardDecision {
+        if !config.enabled() {
+            return GuardDecision::Allow;
+        }
+
+        let now = Instant::now();
+        let cached = self
+            .last_check
+            .as_ref()
+            .filter(|(checked_at, _)| now.duration_since(*checked_at) < Duration::from_millis(config.check_interval_ms));
+        let denied = match cached {
+            Some((_, denied)) => denied.clone(),
+            None => {
+                let denied = self.find_denied(config, region);
+                self.last_check = Some((now, denied.clone()));
+                denied
+            }
+        };
+
+        let Some(process) = denied else {
+            return GuardDecision::Allow;
+        };
+        let notify = self
+            .last_notified
+            .get(&process)
+            .map_or(true, |notified_at| now.duration_since(*notified_at) >= Duration::from_millis(config.notify_interval_ms));
+        if notify {
+            self.last_notified.insert(process.clone(), now);
+        }
+        GuardDecision::Suppress { process, notify }
+    }
+
+    /// 領域に重なる拒否リストのプロセス（判定できない場合は fail_closed ならUNKNOWN_PROCESS）
+    fn find_denied(&mut self, config: &ProcessGuardConfig, region: &CaptureRegion) -> Option<String> {
+        match overlapping_processes(region) {
+            Ok(processes) => processes.into_iter().find(|process| config.is_denied(process)),
+            Err(e) if config.fail_closed => {
+                log::debug!("ウィンドウのプロセスを判定できないためキャプチャしません: {}", e);
+                Some(UNKNOWN_PROCESS.to_string())
+            }
+            Err(e) => {
+                if !self.warned_unavailable {
+                    self.warned_unavailable = true;
+                    log::warn!("ウィンドウのプロセスを判定できないため、拒否リストを確認せずにキャプチャします: {}", e);
+                }
+                None
+            }
+        }
+    }
+}
+
+/// 領域に拒否リストのプロセスのウィンドウが重なっていればエラー（監視以外の1回限りのキャプチャの前に使う）
+pub fn ensure_allowed(config: &ProcessGuardConfig, region: &CaptureRegion) -> Result<()> {
+    match ProcessGuard::default().check(config, region) {
+        GuardDecision::Allow => Ok(()),
+        GuardDecision::Suppress { process, .. } => {
+            anyhow::bail!("{} のウィンドウが領域に重なっているためキャプチャしません", process)
+        }
+    }
+}
+
+/// 拒否リストを確認してからキャプチャ（監視のループ以外のキャプチャはすべてこれを通す）
+pub fn guarded_capture(config: &ProcessGuardConfig, capture: &ScreenCapture) -> Result<DynamicImage> {
+    ensure_allowed(config, &capture.region)?;
+    capture.capture()
+}
+
+/// この環境でウィンドウのプロセスを判定できるかどうか
+pub fn detection_available() -> bool {
+    cfg!(target_os = "windows")
+}
+
+/// 領域に重なる表示中のトップレベルウィンドウの所有プロセスの実行ファイル名
+///
+/// 他のウィンドウに隠れているウィンドウも含める（隠れている部分が見えることもあるため）。
+fn overlapping_processes(region: &CaptureRegion) -> Result<Vec<String>> {
+    #[cfg(target_os = "windows")]
+    {
+        windows_impl::overlapping_processes(region)
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        let _ = region;
+        anyhow::bail!("ウィンドウのプロセスの判定はWindowsのみ対応しています")
+    }
+}
+
+/// Win32 APIによるウィンドウの列挙
+#[cfg(target_os = "windows")]
+mod windows_impl {
+    use anyhow::Result;
+    use windows::core::PWSTR;
+    use windows::Win32::Foundation::{CloseHandle, BOOL, HWND, LPARAM, RECT};
+    use windows::Win32::System::Threading::{
+        OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+    use windows::Win32::UI::WindowsAndMessaging::{EnumWindows, GetWindowRect, GetWindowThreadProcessId, IsIconic, IsWindowVisible};
+
+    use crate::capture::CaptureRegion;
+
+    /// 領域に重なる表示中のトップレベルウィンドウの所有プロセスの実行ファイル名
+    pub fn overlapping_processes(region: &CaptureRegion) -> Result<Vec<String>> {
+        let mut windows: Vec<HWND> = Vec::new();
+        unsafe { EnumWindows(Some(collect_window), LPARAM(&mut windows as *mut Vec<HWND> as isize)) }?;
+
+        let mut processes = Vec::new();
+        for hwnd in windows {
+            let visible = unsafe { IsWindowVisible(hwnd).as_bool() && !IsIconic(hwnd).as_bool() };
+            if !visible {
+                continue;
+            }
+            let mut rect = RECT::default();
+            if unsafe { GetWindowRect(hwnd, &mut rect) }.is_err() {
+                continue;
+            }
+            let overlaps = CaptureRegion::from_edges(rect.left.into(), rect.top.into(), rect.right.into(), rect.bottom.into())
+                .is_some_and(|window| window.intersect(region).is_some());
+            if !overlaps {
+                continue;
+            }
+
+            let mut pid = 0u32;
+            unsafe { GetWindowThreadProcessId(hwnd, Some(&mut pid)) };
+            if let Some(name) = process_image_name(pid) {
+                if !processes.contains(&name) {
+                    processes.push(name);
+                }
+            }
+        }
+        Ok(processes)
+    }
+
+    /// EnumWindowsのコールバック（ウィンドウのハンドルを集める）
+    unsafe extern "system" fn collect_window(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        let windows = &mut *(lparam.0 as *mut Vec<HWND>);
+        windows.push(hwnd);
+        BOOL::from(true)
+    }
+
+    /// プロセスの実行ファイルのパス（権限が無い場合などはNone）
+    fn process_image_name(pid: u32) -> Option<String> {
+        let handle = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid) }.ok()?;
+        let mut buffer = [0u16; 1024];
+        let mut size = buffer.len() as u32;
+        let result = unsafe { QueryFullProcessImageNameW(handle, PROCESS_NAME_WIN32, PWSTR(buffer.as_mut_ptr()), &mut size) };
+        let _ = unsafe { CloseHandle(handle) };
+        result.ok()?;
+        Some(String::from_utf16_lossy(&buffer[..size as usize]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::StaticImageSource;
+
+    const REGION: CaptureRegion = CaptureRegion {
+        x: 0,
+        y: 0,
+        width: 4,
+        height: 4,
+        display: None,
+    };
+
+    fn static_capture() -> ScreenCapture {
+        let image = DynamicImage::new_rgba8(REGION.width, REGION.height);
+        ScreenCapture::with_source(REGION, Box::new(StaticImageSource { image }))
+    }
+
+    fn denying(fail_closed: bool) -> ProcessGuardConfig {
+        ProcessGuardConfig {
+            denied_processes: vec!["KeePass.exe".to_string()],
+            fail_closed,
+            ..ProcessGuardConfig::default()
+        }
+    }
+
+    #[test]
+    fn normalizes_process_names() {
+        let config = denying(false);
+        assert!(config.is_denied(r"C:\Program Files\KeePass\keepass.EXE"));
+        assert!(config.is_denied("/usr/bin/keepass"));
+        assert!(!config.is_denied("notepad.exe"));
+    }
+
+    #[test]
+    fn guarded_capture_allows_without_denied_processes() {
+        let image = guarded_capture(&ProcessGuardConfig::default(), &static_capture()).unwrap();
+        assert_eq!(image.width(), REGION.width);
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn guarded_capture_follows_fail_closed_when_detection_is_unavailable() {
+        assert!(guarded_capture(&denying(false), &static_capture()).is_ok());
+        let error = guarded_capture(&denying(true), &static_capture()).unwrap_err();
+        assert!(error.to_string().contains(UNKNOWN_PROCESS));
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn repeated_suppression_is_notified_once_per_interval() {
+        let config = denying(true);
+        let mut guard = ProcessGuard::default();
+        let first = guard.check(&config, &REGION);
+        let second = guard.check(&config, &REGION);
+        assert_eq!(first, GuardDecision::Suppress { process: UNKNOWN_PROCESS.to_string(), notify: true });
+        assert_eq!(second, GuardDecision::Suppress { process: UNKNOWN_PROCESS.to_string(), notify: false });
+    }
+}
@@ -0,0 +1,210 @@
+// 領域選択画面から受け取る region-selected イベントのペイロード
+//
+// 選択画面のスクリプトは RegionSelectedPayload をオブジェクトのまま1回だけエンコードして送信する。
+// 以前の形式（領域のみのオブジェクト、文字列として二重にエンコードしたもの）は互換のため
+// 警告付きで受け付けるが、いずれ削除する。
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::capture::CaptureRegion;
+
+/// 現在のペイロードのバージョン（選択画面の REGION_SELECTED_VERSION と揃える）
+pub const REGION_SELECTED_VERSION: u32 = 1;
+
+/// 解析できなかった場合に選択画面へ送り返すイベント
+pub const REGION_SELECTED_ERROR_EVENT: &str = "region-selected-error";
+
+/// region-selected イベントのペイロード
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RegionSelectedPayload {
+    /// ペイロードのバージョン
+    pub version: u32,
+    /// 選択した領域（選択画面のウィンドウ内の座標）
+    pub region: CaptureRegion,
+    /// 選択画面を表示したモニターの識別子（DisplayGeometry::stable_id、不明ならNone）
+    #[serde(default)]
+    pub screen_id: Option<String>,
+}
+
+/// 互換のため受け付けている以前の形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LegacyEncoding {
+    /// 領域のみのオブジェクト
+    BareRegion,
+    /// 領域のみのオブジェクトを文字列としてさらにエンコードしたもの
+    DoubleEncodedRegion,
+    /// ペイロードを文字列としてさらにエンコードしたもの
+    DoubleEncodedPayload,
+}
+
+/// 解析できなかった理由の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PayloadErrorKind {
+    /// JSONとして読めない
+    InvalidJson,
+    /// 対応していないバージョン
+    UnsupportedVersion,
+    /// 項目が足りない、または型が合わない
+    InvalidShape,
+}
+
+/// 選択画面へ送り返す解析のエラー（選択画面は正しい形式で送り直す）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RegionPayloadError {
+    pub kind: PayloadErrorKind,
+    pub message: String,
+    /// アプリが受け付けるバージョン
+    pub expected_version: u32,
+}
+
+impl RegionPayloadError {
+    fn new(kind: PayloadErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            expected_version: REGION_SELECTED_VERSION,
+        }
+    }
+}
+
+impl std::fmt::Display for RegionPayloadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}: {}", self.kind, self.message)
+    }
+}
+
+/// region-selected イベントのペイロードを解析（以前の形式なら形式も返す）
+pub fn parse_region_selected(payload: &str) -> Result<(RegionSelectedPayload, Option<LegacyEncoding>), RegionPayloadError> {
+    let value: Value = serde_json::from_str(payload)
+        .map_err(|e| RegionPayloadError::new(PayloadErrorKind::InvalidJson, format!("JSONとして読めません: {}", e)))?;
+    match value {
+        Value::Object(ref object) if object.contains_key("version") => parse_current(value).map(|parsed| (parsed, None)),
+        Value::Object(_) => parse_bare_region(value).map(|parsed| (parsed, Some(LegacyEncoding::BareRegion))),
+        // 以前の選択画面は JSON.stringify した文字列を送信していた
+        Value::String(inner) => match serde_json::from_str::<Value>(&inner) {
+            Ok(inner @ Value::Object(_)) if inner.get("version").is_some() => {
+                parse_current(inner).map(|parsed| (parsed, Some(LegacyEncoding::DoubleEncodedPayload)))
+            }
+            Ok(inner @ Value::Object(_)) => parse_bare_region(inner).map(|parsed| (parsed, Some(LegacyEncoding::DoubleEncodedRegion))),
+            _ => Err(RegionPayloadError::new(
+                PayloadErrorKind::InvalidShape,
+                "文字列ではなくオブジェクトとして送信してください",
+            )),
+        },
+        _ => Err(RegionPayloadError::new(PayloadErrorKind::InvalidShape, "ペイロードはオブジェクトで送信してください")),
+    }
+}
+
+/// 現在の形式を解析
+fn parse_current(value: Value) -> Result<RegionSelectedPayload, RegionPayloadError> {
+    let version = value.get("version").and_then(Value::as_u64);
+    if version != Some(REGION_SELECTED_VERSION as u64) {
+        return Err(RegionPayloadError::new(
+            PayloadErrorKind::UnsupportedVersion,
+            format!("バージョン {} のペイロードには対応していません", value["version"]),
+        ));
+    }
+    serde_json::from_value(value).map_err(|e| RegionPayloadError::new(PayloadErrorKind::InvalidShape, e.to_string()))
+}
+
+/// 以前の領域のみの形式を解析（モニターの識別子は不明とする）
+fn parse_bare_region(value: Value) -> Result<RegionSelectedPayload, RegionPayloadError> {
+    let region = serde_json::from_value(value).map_err(|e| RegionPayloadError::new(PayloadErrorKind::InvalidShape, e.to_string()))?;
+    Ok(RegionSelectedPayload {
+        version: REGION_SELECTED_VERSION,
+        region,
+        screen_id: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REGION: &str = r#"{"x":10,"y":20,"width":300,"height":40}"#;
+
+    fn assert_region(payload: &RegionSelectedPayload) {
+        assert_eq!(payload.version, REGION_SELECTED_VERSION);
+        assert_eq!(
+            (payload.region.x, payload.region.y, payload.region.width, payload.region.height),
+            (10, 20, 300, 40)
+        );
+    }
+
+    #[test]
+    fn parses_current_payload() {
+        let payload = format!(r#"{{"version":1,"region":{},"screen_id":"1920x1080@0,0"}}"#, REGION);
+        let (parsed, legacy) = parse_region_selected(&payload).unwrap();
+        assert_region(&parsed);
+        assert_eq!(parsed.screen_id.as_deref(), Some("1920x1080@0,0"));
+        assert_eq!(legacy, None);
+    }
+
+    #[test]
+    fn parses_current_payload_without_screen_id() {
+        let payload = format!(r#"{{"version":1,"region":{}}}"#, REGION);
+        let (parsed, legacy) = parse_region_selected(&payload).unwrap();
+        assert_region(&parsed);
+        assert_eq!(parsed.screen_id, None);
+        assert_eq!(legacy, None);
+    }
+
+    #[test]
+    fn parses_legacy_bare_region() {
+        let (parsed, legacy) = parse_region_selected(REGION).unwrap();
+        assert_region(&parsed);
+        assert_eq!(parsed.screen_id, None);
+        assert_eq!(legacy, Some(LegacyEncoding::BareRegion));
+    }
+
+    #[test]
+    fn parses_legacy_double_encoded_region() {
+        let payload = serde_json::to_string(REGION).unwrap();
+        let (parsed, legacy) = parse_region_selected(&payload).unwrap();
+        assert_region(&parsed);
+        assert_eq!(legacy, Some(LegacyEncoding::DoubleEncodedRegion));
+    }
+
+    #[test]
+    fn parses_legacy_double_encoded_payload() {
+        let inner = format!(r#"{{"version":1,"region":{},"screen_id":"a"}}"#, REGION);
+        let payload = serde_json::to_string(&inner).unwrap();
+        let (parsed, legacy) = parse_region_selected(&payload).unwrap();
+        assert_region(&parsed);
+        assert_eq!(parsed.screen_id.as_deref(), Some("a"));
+        assert_eq!(legacy, Some(LegacyEncoding::DoubleEncodedPayload));
+    }
+
+    #[test]
+    fn rejects_invalid_json() {
+        let error = parse_region_selected("{x:").unwrap_err();
+        assert_eq!(error.kind, PayloadErrorKind::InvalidJson);
+        assert_eq!(error.expected_version, REGION_SELECTED_VERSION);
+    }
+
+    #[test]
+    fn rejects_unsupported_version() {
+        let payload = format!(r#"{{"version":2,"region":{}}}"#, REGION);
+        let error = parse_region_selected(&payload).unwrap_err();
+        assert_eq!(error.kind, PayloadErrorKind::UnsupportedVersion);
+    }
+
+    #[test]
+    fn rejects_unknown_fields_and_missing_region() {
+        let unknown = format!(r#"{{"version":1,"region":{},"extra":true}}"#, REGION);
+        assert_eq!(parse_region_selected(&unknown).unwrap_err().kind, PayloadErrorKind::InvalidShape);
+        assert_eq!(
+            parse_region_selected(r#"{"version":1}"#).unwrap_err().kind,
+            PayloadErrorKind::InvalidShape
+        );
+    }
+
+    #[test]
+    fn rejects_non_object_payloads() {
+        for payload in ["[1,2]", "42", r#""not json""#, r#""[1]""#] {
+            assert_eq!(parse_region_selected(payload).unwrap_err().kind, PayloadErrorKind::InvalidShape, "{}", payload);
+        }
+    }
+}
@@ -0,0 +1,195 @@
+// 監視セッションのMarkdownレポート
+use anyhow::{bail, Result};
+use chrono::{Local, TimeZone};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::capture::CaptureRegion;
+use crate::events::{HistoryEntry, TextChangeEvent};
+use crate::stats::MonitorStats;
+
+/// 棒グラフの最大の長さ（文字数）
+const BAR_WIDTH: usize = 40;
+
+/// レポートの対象
+pub struct ReportInput<'a> {
+    /// 対象期間の開始（UNIXエポックからのミリ秒）
+    pub start_ts: u64,
+    /// 対象期間の終了（UNIXエポックからのミリ秒）
+    pub end_ts: u64,
+    /// イベント履歴
+    pub entries: &'a [HistoryEntry],
+    /// 監視処理の統計
+    pub stats: &'a MonitorStats,
+    /// 監視領域
+    pub region: Option<CaptureRegion>,
+}
+
+/// レポートをMarkdownファイルに書き出す
+pub fn write_session_report(input: &ReportInput, path: &Path) -> Result<()> {
+    if input.start_ts > input.end_ts {
+        bail!("期間の指定が不正です（開始 {} が終了 {} より後）", input.start_ts, input.end_ts);
+    }
+    std::fs::write(path, session_report(input))?;
+    Ok(())
+}
+
+/// Markdownのレポートを作成
+///
+/// イベント数とタイムラインは期間内の履歴から、所要時間などの性能は起動以降の統計から集計する。
+pub fn session_report(input: &ReportInput) -> String {
+    let entries: Vec<&HistoryEntry> = input
+        .entries
+        .iter()
+        .filter(|entry| (input.start_ts..=input.end_ts).contains(&entry.timestamp_ms))
+        .collect();
+
+    let mut out = String::new();
+    let _ = writeln!(out, "# 監視セッションレポート\n");
+    let _ = writeln!(
+        out,
+        "対象期間: {} 〜 {}\n",
+        format_timestamp(input.start_ts),
+        format_timestamp(input.end_ts)
+    );
+
+    write_overview(&mut out, &entries, input.stats);
+    write_timeline(&mut out, &entries);
+    write_region_stats(&mut out, &entries, input.region);
+    write_performance(&mut out, input.stats);
+    out
+}
+
+/// 概要
+fn write_overview(out: &mut String, entries: &[&HistoryEntry], stats: &MonitorStats) {
+    let mut by_type: BTreeMap<&str, u64> = BTreeMap::new();
+    for entry in entries.iter().filter(|entry| !entry.is_info) {
+        *by_type.entry(entry.event.type_name()).or_insert(0) += 1;
+    }
+    let change_events: u64 = by_type.values().sum();
+    let error_events: u64 = stats.capture_errors.values().sum();
+
+    let _ = writeln!(out, "## 概要\n");
+    let _ = writeln!(out, "| 項目 | 値 |");
+    let _ = writeln!(out, "| --- | --- |");
+    let _ = writeln!(out, "| キャプチャ回数（起動以降） | {} |", stats.ticks_total);
+    let _ = writeln!(out, "| 変化イベント数 | {} |", change_events);
+    for (event_type, count) in &by_type {
+        let _ = writeln!(out, "| 　{} | {} |", event_type, count);
+    }
+    let _ = writeln!(out, "| エラー数（起動以降） | {} |", error_events);
+    // 信頼度は履歴に記録していない
+    let _ = writeln!(out, "| 平均信頼度 | 記録なし |\n");
+}
+
+/// テキスト変更のタイムライン
+fn write_timeline(out: &mut String, entries: &[&HistoryEntry]) {
+    let _ = writeln!(out, "## タイムライン\n");
+
+    let changes: Vec<(&HistoryEntry, &str, &str)> = entries
+        .iter()
+        .filter_map(|entry| match &entry.event {
+            TextChangeEvent::TextChanged { old, new } => Some((*entry, old.as_str(), new.as_str())),
+            TextChangeEvent::RichTextChanged { old: Some(old), new, .. } => Some((*entry, old.as_str(), new.as_str())),
+            TextChangeEvent::TextReplaced { old, new, .. } => Some((*entry, old.as_str(), new.as_str())),
+            _ => None,
+        })
+        .collect();
+    if changes.is_empty() {
+        let _ = writeln!(out, "期間内のテキスト変更はありません。\n");
+        return;
+    }
+
+    let _ = writeln!(out, "| # | 時刻 | 変更前 | 変更後 |");
+    let _ = writeln!(out, "| --- | --- | --- | --- |");
+    for (entry, old, new) in changes {
+        let _ = writeln!(
+            out,
+            "| {} | {} | {} | {} |",
+            entry.sequence,
+            format_timestamp(entry.timestamp_ms),
+            escape_cell(old),
+            escape_cell(new)
+        );
+    }
+    let _ = writeln!(out);
+}
+
+/// 領域ごとの統計（現在は単一の領域のみ監視する）
+fn write_region_stats(out: &mut String, entries: &[&HistoryEntry], region: Option<CaptureRegion>) {
+    let _ = writeln!(out, "## 領域ごとの統計\n");
+    let Some(region) = region else {
+        let _ = writeln!(out, "監視領域が選択されていません。\n");
+        return;
+    };
+
+    let count = |name: &str| entries.iter().filter(|entry| entry.event.type_name() == name).count();
+    let _ = writeln!(out, "| 領域 | 新規 | 変更 | クリア | 差分 |");
+    let _ = writeln!(out, "| --- | --- | --- | --- | --- |");
+    let _ = writeln!(
+        out,
+        "| ({}, {}) {}x{} | {} | {} | {} | {} |\n",
+        region.x,
+        region.y,
+        region.width,
+        region.height,
+        count("new"),
+        count("changed"),
+        count("cleared"),
+        count("diff")
+    );
+}
+
+/// 性能のグラフ
+fn write_performance(out: &mut String, stats: &MonitorStats) {
+    let _ = writeln!(out, "## 性能\n");
+
+    // 累積のヒストグラムを区間ごとの件数に戻す
+    let _ = writeln!(out, "### OCRの所要時間（起動以降、{}回）\n", stats.ocr_duration.count);
+    let mut previous = 0;
+    let mut rows = Vec::new();
+    for (bound, cumulative) in &stats.ocr_duration.buckets {
+        rows.push((format!("≤{}s", bound), cumulative - previous));
+        previous = *cumulative;
+    }
+    rows.push((">上限".to_string(), stats.ocr_duration.count - previous));
+    write_bar_chart(out, &rows);
+
+    let average = stats.preprocess_average();
+    let _ = writeln!(out, "### 前処理の平均所要時間（直近のフレーム、マイクロ秒）\n");
+    let rows: Vec<(String, u64)> = average
+        .steps()
+        .iter()
+        .map(|(name, duration_us)| (name.to_string(), *duration_us))
+        .collect();
+    write_bar_chart(out, &rows);
+}
+
+/// ASCIIの棒グラフをコードブロックとして出力
+fn write_bar_chart(out: &mut String, rows: &[(String, u64)]) {
+    let max = rows.iter().map(|(_, value)| *value).max().unwrap_or(0);
+    let label_width = rows.iter().map(|(label, _)| label.chars().count()).max().unwrap_or(0);
+
+    let _ = writeln!(out, "```");
+    for (label, value) in rows {
+        let length = if max == 0 { 0 } else { (*value as usize * BAR_WIDTH).div_ceil(max as usize) };
+        let padding = " ".repeat(label_width - label.chars().count());
+        let _ = writeln!(out, "{}{} | {} {}", label, padding, "#".repeat(length), value);
+    }
+    let _ = writeln!(out, "```\n");
+}
+
+/// 表のセル内で使えない文字を置き換える
+fn escape_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', "<br>")
+}
+
+/// UNIXエポックからのミリ秒をローカル時刻で表記
+fn format_timestamp(timestamp_ms: u64) -> String {
+    Local
+        .timestamp_millis_opt(timestamp_ms as i64)
+        .single()
+        .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| timestamp_ms.to_string())
+}
@@ -0,0 +1,412 @@
+// 外部ツール（IFTTT、n8n、Home Assistant等）向けのREST API
+use axum::extract::{Query, Request, State as AxumState};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+use utoipa::{IntoParams, OpenApi, ToSchema};
+
+use crate::capture::{CaptureRegion, ScreenCapture};
+use crate::events::HistoryEntry;
+use crate::locking::lock;
+use crate::ocr::OcrEngine;
+use crate::phase::MonitorCommandError;
+use crate::process_guard;
+use crate::{tessdata, AppState};
+
+/// 1回のリクエストで返す履歴の既定件数
+const DEFAULT_PAGE_SIZE: usize = 100;
+
+/// 1回のリクエストで返す履歴の最大件数
+const MAX_PAGE_SIZE: usize = 500;
+
+/// 既定の待ち受けアドレス（同じPCのツールからのみ接続できる）
+pub const DEFAULT_BIND_ADDRESS: &str = "127.0.0.1";
+
+/// REST APIのOpenAPI定義
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "画面テキスト監視システム REST API", version = "1.0.0"),
+    paths(snapshot, events, monitoring_start, monitoring_stop),
+    components(schemas(CaptureRegion, SnapshotResponse, EventsPage, StatusResponse, ErrorBody)),
+    modifiers(&BearerAuth)
+)]
+struct ApiDoc;
+
+/// OpenAPI定義にBearer認証を追加
+struct BearerAuth;
+
+impl utoipa::Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "bearer",
+                SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+            );
+        }
+    }
+}
+
+/// ハンドラ間で共有する状態
+#[derive(Clone)]
+struct ServerState {
+    app: AppHandle,
+    auth_token: Option<String>,
+}
+
+/// スナップショットの認識結果
+#[derive(Debug, Serialize, ToSchema)]
+struct SnapshotResponse {
+    /// 認識されたテキスト
+    text: String,
+    /// 認識の信頼度（0.0-1.0、信頼度を返さないエンジンの場合はNone）
+    confidence: Option<f32>,
+}
+
+/// 履歴取得のクエリ
+#[derive(Debug, Deserialize, IntoParams)]
+struct EventsQuery {
+    /// この連番より後のイベントを返す（省略時は先頭から）
+    since_id: Option<u64>,
+    /// 最大件数（既定100、上限500）
+    limit: Option<usize>,
+}
+
+/// 履歴のページ
+#[derive(Debug, Serialize, ToSchema)]
+struct EventsPage {
+    /// イベント（古い順）
+    #[schema(value_type = Vec<Object>)]
+    events: Vec<HistoryEntry>,
+    /// 続きを取得する際に since_id に指定する値
+    next_since_id: Option<u64>,
+    /// 続きがあるかどうか
+    has_more: bool,
+}
+
+/// 操作結果
+#[derive(Debug, Serialize, ToSchema)]
+struct StatusResponse {
+    /// 受け付けられたかどうか
+    ok: bool,
+}
+
+/// エラー応答
+#[derive(Debug, Serialize, ToSchema)]
+struct ErrorBody {
+    /// エラーメッセージ
+    error: String,
+}
+
+/// HTTPステータス付きのエラー
+struct ApiError(StatusCode, String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(ErrorBody { error: self.1 })).into_response()
+    }
+}
+
+/// 待ち受けアドレスを検証（省略時はループバック、ループバック以外は認証トークンが必須）
+pub fn resolve_bind_address(bind_address: Option<&str>, auth_token: Option<&str>) -> Result<IpAddr, String> {
+    let bind_address = bind_address.unwrap_or(DEFAULT_BIND_ADDRESS);
+    let address: IpAddr = bind_address
+        .parse()
+        .map_err(|e| format!("待ち受けるアドレスが正しくありません（{}）: {}", bind_address, e))?;
+    let has_token = auth_token.is_some_and(|token| !token.is_empty());
+    if !address.is_loopback() && !has_token {
+        return Err(format!(
+            "ループバック以外のアドレス（{}）で待ち受ける場合はauth_tokenを指定してください",
+            address
+        ));
+    }
+    Ok(address)
+}
+
+/// REST APIサーバーを起動（終了するまで戻らない、アドレスはresolve_bind_addressで検証したもの）
+pub async fn serve(app: AppHandle, address: IpAddr, port: u16, auth_token: Option<String>) -> anyhow::Result<()> {
+    let auth_token = auth_token.filter(|token| !token.is_empty());
+    if auth_token.is_none() {
+        log::warn!("REST APIを認証なしで公開します（auth_tokenの指定を推奨）");
+    }
+    let state = ServerState { app, auth_token };
+
+    let router = Router::new()
+        .route("/snapshot", post(snapshot))
+        .route("/events", get(events))
+        .route("/monitoring/start", post(monitoring_start))
+        .route("/monitoring/stop", post(monitoring_stop))
+        .route_layer(middleware::from_fn_with_state(state.clone(), authorize))
+        // OpenAPI定義は認証なしで取得できる
+        .route("/openapi.json", get(|| async { Json(ApiDoc::openapi()) }))
+        .with_state(state);
+
+    let listener = tokio::net::TcpListener::bind((address, port)).await?;
+    log::info!("REST APIサーバーを起動しました: {}", listener.local_addr()?);
+    axum::serve(listener, router).await?;
+    Ok(())
+}
+
+/// 最新のテキストと監視の状態の配信サーバーを起動（終了するまで戻らない）
+///
+/// 待ち受けの失敗を呼び出し元に返せるよう、ソケットは呼び出し元でバインドしたものを受け取る。
+pub async fn serve_text(app: AppHandle, listener: std::net::TcpListener) -> anyhow::Result<()> {
+    listener.set_nonblocking(true)?;
+    let listener = tokio::net::TcpListener::from_std(listener)?;
+    log::info!("テキスト配信サーバーを起動しました: http://{}/text", listener.local_addr()?);
+
+    let router = Router::new()
+        .route("/text", get(latest_text))
+        .route("/status", get(status))
+        .with_state(app);
+    axum::serve(listener, router).await?;
+    Ok(())
+}
+
+/// 最新のテキストをプレーンテキストで返す（ETagは連番、条件付きリクエストには304を返す）
+async fn latest_text(AxumState(app): AxumState<AppHandle>, headers: HeaderMap) -> Response {
+    let history = lock(&app.state::<Mutex<AppState>>()).history.clone();
+    let latest = lock(&history).latest_text().map(|entry| {
+        let text = entry.event.current_text().unwrap_or_default().to_string();
+        (entry.sequence, entry.timestamp_ms, text)
+    });
+    let Some((sequence, timestamp_ms, text)) = latest else {
+        // まだテキストを認識していない
+        return StatusCode::NO_CONTENT.into_response();
+    };
+
+    let etag = format!("\"{}\"", sequence);
+    let not_modified = match headers.get(header::IF_NONE_MATCH) {
+        // If-None-Matchがあれば If-Modified-Since より優先する
+        Some(value) => value
+            .to_str()
+            .is_ok_and(|value| value.split(',').map(str::trim).any(|tag| tag == etag || tag == "*")),
+        None => headers
+            .get(header::IF_MODIFIED_SINCE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| DateTime::parse_from_rfc2822(value).ok())
+            .is_some_and(|since| since.timestamp() >= (timestamp_ms / 1000) as i64),
+    };
+
+    let cache_headers = [
+        (header::ETAG, etag),
+        (header::LAST_MODIFIED, http_date(timestamp_ms)),
+        (header::CACHE_CONTROL, "no-cache".to_string()),
+    ];
+    if not_modified {
+        return (StatusCode::NOT_MODIFIED, cache_headers).into_response();
+    }
+    (cache_headers, [(header::CONTENT_TYPE, "text/plain; charset=utf-8")], text).into_response()
+}
+
+/// get_statusと同じ監視の状態を返す
+async fn status(AxumState(app): AxumState<AppHandle>) -> Response {
+    Json(crate::get_status(app.state::<Mutex<AppState>>())).into_response()
+}
+
+/// HTTPの日付形式（IMF-fixdate）
+fn http_date(timestamp_ms: u64) -> String {
+    Utc.timestamp_millis_opt(timestamp_ms as i64)
+        .single()
+        .map(|time| time.format("%a, %d %b %Y %H:%M:%S GMT").to_string())
+        .unwrap_or_default()
+}
+
+/// Authorizationヘッダーのトークンを検証（トークン未設定なら常に許可）
+async fn authorize(AxumState(state): AxumState<ServerState>, request: Request, next: Next) -> Response {
+    if let Some(expected) = &state.auth_token {
+        let provided = request
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        if !provided.is_some_and(|provided| tokens_match(provided.as_bytes(), expected.as_bytes())) {
+            return ApiError(StatusCode::UNAUTHORIZED, "認証トークンが正しくありません".to_string()).into_response();
+        }
+    }
+    next.run(request).await
+}
+
+/// トークンを比較（一致した文字数で処理時間が変わらないよう、常に全体を比べる）
+fn tokens_match(provided: &[u8], expected: &[u8]) -> bool {
+    let length = provided.len().max(expected.len());
+    let difference = (0..length).fold(provided.len() ^ expected.len(), |difference, index| {
+        let a = provided.get(index).copied().unwrap_or(0);
+        let b = expected.get(index).copied().unwrap_or(0);
+        difference | usize::from(a ^ b)
+    });
+    difference == 0
+}
+
+/// 指定領域を1回キャプチャして認識
+#[utoipa::path(
+    post,
+    path = "/snapshot",
+    request_body = CaptureRegion,
+    responses(
+        (status = 200, body = SnapshotResponse),
+        (status = 401, body = ErrorBody),
+        (status = 500, body = ErrorBody)
+    ),
+    security(("bearer" = []))
+)]
+async fn snapshot(
+    AxumState(state): AxumState<ServerState>,
+    Json(region): Json<CaptureRegion>,
+) -> Result<Json<SnapshotResponse>, ApiError> {
+    let (tessdata_dir, language, capture_config, process_guard_config, ocr_config, ocr_baseline) = {
+        let managed = state.app.state::<Mutex<AppState>>();
+        let app_state = lock(&managed);
+        (
+            app_state.tessdata_dir.clone(),
+            app_state.ocr_language.clone().unwrap_or_else(|| tessdata::DEFAULT_LANGUAGE.to_string()),
+            app_state.capture_config.clone(),
+            app_state.process_guard_config.clone(),
+            app_state.ocr_config.clone(),
+            app_state.ocr_baseline,
+        )
+    };
+
+    // キャプチャとOCRはブロッキング処理のため別スレッドで実行
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let image = process_guard::guarded_capture(&process_guard_config, &ScreenCapture::with_config(region, &capture_config))?;
+        let datapath = tessdata::resolve_datapath(tessdata_dir.as_deref(), &language);
+        let mut ocr_engine = OcrEngine::with_backend(datapath, &language, ocr_config.backend)?;
+        ocr_engine.set_config(ocr_config);
+        ocr_engine.set_calibrated_baseline(ocr_baseline);
+        ocr_engine.recognize_detailed(&image)
+    })
+    .await
+    .map_err(|e| ApiError(StatusCode::INTERNAL_SERVER_ERROR, format!("認識タスクエラー: {}", e)))?
+    .map_err(|e| ApiError(StatusCode::INTERNAL_SERVER_ERROR, format!("認識エラー: {}", e)))?;
+
+    Ok(Json(SnapshotResponse {
+        text: result.text,
+        confidence: result.confidence,
+    }))
+}
+
+/// イベント履歴をページ単位で取得
+#[utoipa::path(
+    get,
+    path = "/events",
+    params(EventsQuery),
+    responses((status = 200, body = EventsPage), (status = 401, body = ErrorBody)),
+    security(("bearer" = []))
+)]
+async fn events(AxumState(state): AxumState<ServerState>, Query(query): Query<EventsQuery>) -> Json<EventsPage> {
+    let history = lock(&state.app.state::<Mutex<AppState>>()).history.clone();
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+
+    let mut matching: Vec<HistoryEntry> = lock(&history)
+        .entries(true)
+        .into_iter()
+        .filter(|entry| query.since_id.map_or(true, |since| entry.sequence > since))
+        .collect();
+    let has_more = matching.len() > limit;
+    matching.truncate(limit);
+
+    Json(EventsPage {
+        next_since_id: matching.last().map(|entry| entry.sequence).or(query.since_id),
+        events: matching,
+        has_more,
+    })
+}
+
+/// 監視を開始
+#[utoipa::path(
+    post,
+    path = "/monitoring/start",
+    request_body = CaptureRegion,
+    responses(
+        (status = 200, body = StatusResponse),
+        (status = 401, body = ErrorBody),
+        (status = 409, body = ErrorBody)
+    ),
+    security(("bearer" = []))
+)]
+async fn monitoring_start(
+    AxumState(state): AxumState<ServerState>,
+    Json(region): Json<CaptureRegion>,
+) -> Result<Json<StatusResponse>, ApiError> {
+    let window = state
+        .app
+        .get_window("main")
+        .ok_or_else(|| ApiError(StatusCode::INTERNAL_SERVER_ERROR, "メインウィンドウが見つかりません".to_string()))?;
+
+    // 監視の開始はOCRエンジンの初期化などでブロックするため別スレッドで実行
+    let app = state.app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        crate::start_monitoring(region, None, app.state::<Mutex<AppState>>(), window)
+    })
+    .await
+    .map_err(|e| ApiError(StatusCode::INTERNAL_SERVER_ERROR, format!("監視の開始タスクエラー: {}", e)))?
+    .map_err(|e| command_error(e, StatusCode::CONFLICT))?;
+    Ok(Json(StatusResponse { ok: true }))
+}
+
+/// 監視を停止
+#[utoipa::path(
+    post,
+    path = "/monitoring/stop",
+    responses(
+        (status = 200, body = StatusResponse),
+        (status = 401, body = ErrorBody),
+        (status = 409, body = ErrorBody)
+    ),
+    security(("bearer" = []))
+)]
+async fn monitoring_stop(AxumState(state): AxumState<ServerState>) -> Result<Json<StatusResponse>, ApiError> {
+    crate::stop_monitoring(state.app.state::<Mutex<AppState>>())
+        .await
+        .map_err(|e| command_error(e, StatusCode::INTERNAL_SERVER_ERROR))?;
+    Ok(Json(StatusResponse { ok: true }))
+}
+
+/// 監視コマンドのエラーをAPIのエラーに変換（状態のエラーは409、それ以外は指定のステータス）
+fn command_error(error: MonitorCommandError, failed_status: StatusCode) -> ApiError {
+    match error {
+        MonitorCommandError::InvalidState(_) => ApiError(StatusCode::CONFLICT, error.to_string()),
+        MonitorCommandError::Failed(message) => ApiError(failed_status, message),
+        MonitorCommandError::OcrInit { .. } => ApiError(failed_status, error.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binds_to_loopback_by_default() {
+        assert_eq!(resolve_bind_address(None, None), Ok(IpAddr::from([127, 0, 0, 1])));
+        assert!(resolve_bind_address(Some("::1"), None).is_ok());
+    }
+
+    #[test]
+    fn remote_address_requires_token() {
+        assert!(resolve_bind_address(Some("0.0.0.0"), None).is_err());
+        assert!(resolve_bind_address(Some("0.0.0.0"), Some("")).is_err());
+        assert_eq!(
+            resolve_bind_address(Some("0.0.0.0"), Some("secret")),
+            Ok(IpAddr::from([0, 0, 0, 0]))
+        );
+        assert!(resolve_bind_address(Some("localhost"), Some("secret")).is_err());
+    }
+
+    #[test]
+    fn compares_tokens_exactly() {
+        assert!(tokens_match(b"secret", b"secret"));
+        assert!(!tokens_match(b"secret", b"secreT"));
+        assert!(!tokens_match(b"secre", b"secret"));
+        assert!(!tokens_match(b"secret\0", b"secret"));
+        assert!(!tokens_match(b"", b"secret"));
+    }
+}
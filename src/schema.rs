@@ -0,0 +1,736 @@
+// フロントエンドへ送信するイベントのワイヤーフォーマット
+//
+// 内部のイベント型（events::TextChangeEvent）とは分けて定義し、
+// 送信時に変換する。内部の型を変更してもワイヤーフォーマットが変わらないよう、
+// フィールドの追加・変更はここで行い、互換性を壊す変更では新しいバージョンの
+// モジュール（v2など）を追加してSCHEMA_VERSIONを上げる。
+use serde::{Deserialize, Serialize};
+
+use crate::capture;
+use crate::events::TextChangeEvent;
+use crate::preprocessing;
+use crate::summary;
+
+/// 現在送信しているペイロードのスキーマバージョン
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// イベントの送信先チャンネル名
+///
+/// 同じマシンで複数のインスタンスを動かす場合に名前が衝突しないよう変更できる。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EventChannels {
+    /// テキスト変化・情報イベント
+    pub text_changed: String,
+    /// エラー
+    pub error: String,
+    /// 監視の開始・終了
+    pub lifecycle: String,
+}
+
+impl Default for EventChannels {
+    fn default() -> Self {
+        Self {
+            text_changed: "text-changed".to_string(),
+            error: "error".to_string(),
+            lifecycle: "lifecycle".to_string(),
+        }
+    }
+}
+
+/// スキーマバージョン1
+///
+/// テキスト変化チャンネルのペイロードは、`type` でイベントの種類を表し、
+/// 種類ごとのフィールドを同じ階層に持つ。
+///
+/// ```json
+/// {"schema_version":1,"sequence":3,"timestamp_ms":1700000000000,"type":"new","text":"こんにちは"}
+/// ```
+pub mod v1 {
+    use super::*;
+
+    /// テキスト変化チャンネルのペイロード
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct TextChangedPayload {
+        /// スキーマバージョン（常に1）
+        pub schema_version: u32,
+        /// 履歴の連番（履歴に記録しない一時的なイベントはNone）
+        pub sequence: Option<u64>,
+        /// 送信時刻（UNIXエポックからのミリ秒、session_started_at_msにsession_offset_msを足したもの）
+        pub timestamp_ms: u64,
+        /// 送信した監視セッションの開始時のシステム時刻（UNIXエポックからのミリ秒、まとめたイベントの中ではNone）
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub session_started_at_ms: Option<u64>,
+        /// セッションの開始からの経過時間（ミリ秒、システム時刻の変更に影響されない）
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub session_offset_ms: Option<u64>,
+        /// イベント本体
+        #[serde(flatten)]
+        pub event: Event,
+    }
+
+    /// イベント本体
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[serde(tag = "type", rename_all = "snake_case")]
+    pub enum Event {
+        /// 新しいテキストが検出された
+        New { text: String },
+        /// テキストが変更された
+        Changed { old: String, new: String },
+        /// サムネイル付きのテキストの新規・変更
+        RichChanged {
+            old: Option<String>,
+            new: String,
+            thumbnail: Option<String>,
+            confidence: Option<f32>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            metrics: Option<ImageMetrics>,
+        },
+        /// テキストがクリアされた
+        Cleared { text: String },
+        /// クリアの直後に新しいテキストが現れた
+        Replaced {
+            old: String,
+            new: String,
+            cleared_at_ms: u64,
+            replaced_at_ms: u64,
+        },
+        /// 差分テキストが検出された
+        Diff {
+            added: Vec<String>,
+            removed: Vec<String>,
+            #[serde(default)]
+            line_stability: Vec<LineStability>,
+            #[serde(default)]
+            parsed_added: Vec<ParsedLine>,
+            #[serde(default, skip_serializing_if = "Vec::is_empty")]
+            added_languages: Vec<Option<String>>,
+        },
+        /// 情報メッセージ
+        Info { code: String, message: String },
+        /// 言語データのダウンロード進捗
+        DownloadProgress {
+            language: String,
+            downloaded_bytes: u64,
+            total_bytes: u64,
+            percent: f32,
+        },
+        /// 前処理パラメータの自動調整の進捗
+        TuneProgress {
+            evaluated: usize,
+            total: usize,
+            best_accuracy: f32,
+        },
+        /// 比較の基準とするテキストが設定された
+        ReferenceSet { text: String },
+        /// 監視中のキーワードが認識テキストに現れた
+        KeywordMatched { keyword: String, line: String },
+        /// 領域のモニターの解像度・拡大率が選択時から変わった
+        RegionInvalidated {
+            region: Region,
+            original: DisplayGeometry,
+            current: Option<DisplayGeometry>,
+            remapped: Option<Region>,
+        },
+        /// 領域に拒否リストのプロセスのウィンドウが重なっているためキャプチャしなかった
+        CaptureSuppressed { process: String },
+        /// 行が現れた
+        LineAppeared { line: String, appeared_at_ms: u64 },
+        /// 行が消えた
+        LineDisappeared {
+            line: String,
+            appeared_at_ms: u64,
+            disappeared_at_ms: u64,
+        },
+        /// 送信レートの制限で保留したイベントのまとめ（各イベントは通常と同じ形式）
+        Batch {
+            events: Vec<TextChangedPayload>,
+            total_dropped: u64,
+        },
+    }
+
+    /// 行の安定度
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct LineStability {
+        pub line_text: String,
+        pub stability: f32,
+        pub last_seen: u64,
+    }
+
+    /// キャプチャ領域
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+    pub struct Region {
+        pub x: i32,
+        pub y: i32,
+        pub width: u32,
+        pub height: u32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub display: Option<DisplayGeometry>,
+    }
+
+    /// モニターの位置・解像度・拡大率
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+    pub struct DisplayGeometry {
+        pub id: u32,
+        pub x: i32,
+        pub y: i32,
+        pub width: u32,
+        pub height: u32,
+        pub scale_factor: f32,
+    }
+
+    /// 画像のゆがみの指標
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+    pub struct ImageMetrics {
+        pub blur_score: f32,
+        pub noise_score: f32,
+        pub skew_angle_deg: f32,
+        pub contrast_ratio: f32,
+        #[serde(default)]
+        pub text_coverage: Option<f32>,
+    }
+
+    /// 発言者とメッセージに分解した追加行
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct ParsedLine {
+        pub line: String,
+        pub speaker: Option<String>,
+        pub message: String,
+    }
+
+    /// エラーチャンネルのペイロード
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct ErrorPayload {
+        /// スキーマバージョン（常に1）
+        pub schema_version: u32,
+        /// 送信時刻（UNIXエポックからのミリ秒）
+        pub timestamp_ms: u64,
+        /// エラーメッセージ
+        pub message: String,
+    }
+
+    /// ライフサイクルチャンネルのペイロード
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct LifecyclePayload {
+        /// スキーマバージョン（常に1）
+        pub schema_version: u32,
+        /// 送信時刻（UNIXエポックからのミリ秒）
+        pub timestamp_ms: u64,
+        /// 監視セッションの識別子（監視を開始するたびに増える）
+        #[serde(default)]
+        pub session_id: u64,
+        /// 監視の状態
+        pub state: LifecycleState,
+        /// 監視セッションの要約（監視スレッドの終了時のみ）
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub summary: Option<SessionSummary>,
+        /// 監視を続けられなかった理由と対処方法（OCRエンジンの初期化の失敗などで終了した場合のみ）
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub failure: Option<SessionFailure>,
+    }
+
+    /// 監視を続けられなかった理由
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct SessionFailure {
+        /// 利用者向けのメッセージ
+        pub message: String,
+        /// 対処方法の固定コード（download_language など）
+        pub remediation: String,
+    }
+
+    /// 監視セッションの要約
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct SessionSummary {
+        pub session_id: u64,
+        pub started_at_ms: u64,
+        pub stopped_at_ms: u64,
+        pub duration_ms: u64,
+        pub stop_reason: StopReason,
+        pub change_events: u64,
+        pub unique_lines: u64,
+        pub unique_lines_capped: bool,
+        pub error_count: u64,
+        pub ocr_count: u64,
+        pub ocr_latency_avg_ms: f64,
+        pub ocr_latency_p50_ms: f64,
+        pub ocr_latency_p95_ms: f64,
+        pub busiest_period: Option<BusiestPeriod>,
+        #[serde(default)]
+        pub text_coverage: Option<TextCoverage>,
+    }
+
+    /// 文字の占めた割合の集計
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+    pub struct TextCoverage {
+        pub min: f32,
+        pub mean: f32,
+        pub max: f32,
+        pub samples: u64,
+    }
+
+    /// 監視が終了した理由
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum StopReason {
+        Requested,
+        Killswitch,
+        Error,
+        RegionInvalidated,
+    }
+
+    /// 最も変化の多かった期間
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+    pub struct BusiestPeriod {
+        pub start_ms: u64,
+        pub duration_ms: u64,
+        pub change_events: u64,
+    }
+
+    /// 監視の状態
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum LifecycleState {
+        /// 監視スレッドが開始した
+        Started,
+        /// 監視スレッドが終了した
+        Stopped,
+    }
+
+    impl From<&TextChangeEvent> for Event {
+        fn from(event: &TextChangeEvent) -> Self {
+            match event.clone() {
+                TextChangeEvent::NewText { text } => Event::New { text },
+                TextChangeEvent::TextChanged { old, new } => Event::Changed { old, new },
+                TextChangeEvent::RichTextChanged {
+                    old,
+                    new,
+                    thumbnail,
+                    confidence,
+                    metrics,
+                } => Event::RichChanged {
+                    old,
+                    new,
+                    thumbnail,
+                    confidence,
+                    metrics: metrics.map(Into::into),
+                },
+                TextChangeEvent::TextCleared { text } => Event::Cleared { text },
+                TextChangeEvent::TextReplaced {
+                    old,
+                    new,
+                    cleared_at_ms,
+                    replaced_at_ms,
+                } => Event::Replaced {
+                    old,
+                    new,
+                    cleared_at_ms,
+                    replaced_at_ms,
+                },
+                TextChangeEvent::DiffDetected {
+                    added,
+                    removed,
+                    line_stability,
+                    parsed_added,
+                    added_languages,
+                } => Event::Diff {
+                    added,
+                    removed,
+                    line_stability: line_stability
+                        .into_iter()
+                        .map(|line| LineStability {
+                            line_text: line.line_text,
+                            stability: line.stability,
+                            last_seen: line.last_seen,
+                        })
+                        .collect(),
+                    parsed_added: parsed_added
+                        .into_iter()
+                        .map(|line| ParsedLine {
+                            line: line.line,
+                            speaker: line.speaker,
+                            message: line.message,
+                        })
+                        .collect(),
+                    added_languages,
+                },
+                TextChangeEvent::Info { code, message } => Event::Info { code, message },
+                TextChangeEvent::DownloadProgress {
+                    language,
+                    downloaded_bytes,
+                    total_bytes,
+                    percent,
+                } => Event::DownloadProgress {
+                    language,
+                    downloaded_bytes,
+                    total_bytes,
+                    percent,
+                },
+                TextChangeEvent::TuneProgress {
+                    evaluated,
+                    total,
+                    best_accuracy,
+                } => Event::TuneProgress {
+                    evaluated,
+                    total,
+                    best_accuracy,
+                },
+                TextChangeEvent::ReferenceSet { text } => Event::ReferenceSet { text },
+                TextChangeEvent::KeywordMatched { keyword, line } => Event::KeywordMatched { keyword, line },
+                TextChangeEvent::CaptureSuppressed { process } => Event::CaptureSuppressed { process },
+                TextChangeEvent::LineAppeared { line, appeared_at_ms } => Event::LineAppeared { line, appeared_at_ms },
+                TextChangeEvent::LineDisappeared {
+                    line,
+                    appeared_at_ms,
+                    disappeared_at_ms,
+                } => Event::LineDisappeared {
+                    line,
+                    appeared_at_ms,
+                    disappeared_at_ms,
+                },
+                TextChangeEvent::RegionInvalidated {
+                    region,
+                    original,
+                    current,
+                    remapped,
+                } => Event::RegionInvalidated {
+                    region: region.into(),
+                    original: original.into(),
+                    current: current.map(Into::into),
+                    remapped: remapped.map(Into::into),
+                },
+                // 個々の連番と時刻は送信器がまとめる時点で付ける
+                TextChangeEvent::Batch { events, total_dropped } => Event::Batch {
+                    events: events
+                        .iter()
+                        .map(|event| TextChangedPayload {
+                            schema_version: SCHEMA_VERSION,
+                            sequence: None,
+                            timestamp_ms: 0,
+                            session_started_at_ms: None,
+                            session_offset_ms: None,
+                            event: event.into(),
+                        })
+                        .collect(),
+                    total_dropped,
+                },
+            }
+        }
+    }
+
+    impl From<capture::CaptureRegion> for Region {
+        fn from(region: capture::CaptureRegion) -> Self {
+            Region {
+                x: region.x,
+                y: region.y,
+                width: region.width,
+                height: region.height,
+                display: region.display.map(Into::into),
+            }
+        }
+    }
+
+    impl From<capture::DisplayGeometry> for DisplayGeometry {
+        fn from(display: capture::DisplayGeometry) -> Self {
+            DisplayGeometry {
+                id: display.id,
+                x: display.x,
+                y: display.y,
+                width: display.width,
+                height: display.height,
+                scale_factor: display.scale_factor,
+            }
+        }
+    }
+
+    impl From<preprocessing::ImageMetrics> for ImageMetrics {
+        fn from(metrics: preprocessing::ImageMetrics) -> Self {
+            ImageMetrics {
+                blur_score: metrics.blur_score,
+                noise_score: metrics.noise_score,
+                skew_angle_deg: metrics.skew_angle_deg,
+                contrast_ratio: metrics.contrast_ratio,
+                text_coverage: metrics.text_coverage,
+            }
+        }
+    }
+
+    impl From<&summary::SessionSummary> for SessionSummary {
+        fn from(summary: &summary::SessionSummary) -> Self {
+            SessionSummary {
+                session_id: summary.session_id,
+                started_at_ms: summary.started_at_ms,
+                stopped_at_ms: summary.stopped_at_ms,
+                duration_ms: summary.duration_ms,
+                stop_reason: match summary.stop_reason {
+                    summary::StopReason::Requested => StopReason::Requested,
+                    summary::StopReason::Killswitch => StopReason::Killswitch,
+                    summary::StopReason::Error => StopReason::Error,
+                    summary::StopReason::RegionInvalidated => StopReason::RegionInvalidated,
+                },
+                change_events: summary.change_events,
+                unique_lines: summary.unique_lines,
+                unique_lines_capped: summary.unique_lines_capped,
+                error_count: summary.error_count,
+                ocr_count: summary.ocr_count,
+                ocr_latency_avg_ms: summary.ocr_latency_avg_ms,
+                ocr_latency_p50_ms: summary.ocr_latency_p50_ms,
+                ocr_latency_p95_ms: summary.ocr_latency_p95_ms,
+                busiest_period: summary.busiest_period.map(|period| BusiestPeriod {
+                    start_ms: period.start_ms,
+                    duration_ms: period.duration_ms,
+                    change_events: period.change_events,
+                }),
+                text_coverage: summary.text_coverage.map(|coverage| TextCoverage {
+                    min: coverage.min,
+                    mean: coverage.mean,
+                    max: coverage.max,
+                    samples: coverage.samples,
+                }),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::v1::*;
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use std::fmt::Debug;
+
+    /// 送信する形式が文字列のとおりになり、その文字列から同じ値に戻ることを確かめる
+    fn assert_wire_format<T: Serialize + DeserializeOwned + PartialEq + Debug>(value: &T, json: &str) {
+        assert_eq!(serde_json::to_string(value).unwrap(), json);
+        assert_eq!(&serde_json::from_str::<T>(json).unwrap(), value);
+    }
+
+    fn payload(sequence: Option<u64>, timestamp_ms: u64, event: Event) -> TextChangedPayload {
+        TextChangedPayload {
+            schema_version: 1,
+            sequence,
+            timestamp_ms,
+            session_started_at_ms: None,
+            session_offset_ms: None,
+            event,
+        }
+    }
+
+    fn summary() -> SessionSummary {
+        SessionSummary {
+            session_id: 2,
+            started_at_ms: 1_700_000_000_000,
+            stopped_at_ms: 1_700_000_060_000,
+            duration_ms: 60_000,
+            stop_reason: StopReason::Killswitch,
+            change_events: 3,
+            unique_lines: 2,
+            unique_lines_capped: false,
+            error_count: 1,
+            ocr_count: 40,
+            ocr_latency_avg_ms: 12.5,
+            ocr_latency_p50_ms: 10.0,
+            ocr_latency_p95_ms: 30.25,
+            busiest_period: Some(BusiestPeriod {
+                start_ms: 1_700_000_010_000,
+                duration_ms: 10_000,
+                change_events: 2,
+            }),
+            text_coverage: None,
+        }
+    }
+
+    #[test]
+    fn new_text_payload_matches_the_documented_example() {
+        assert_wire_format(
+            &payload(Some(3), 1_700_000_000_000, Event::New { text: "こんにちは".to_string() }),
+            r#"{"schema_version":1,"sequence":3,"timestamp_ms":1700000000000,"type":"new","text":"こんにちは"}"#,
+        );
+    }
+
+    #[test]
+    fn envelope_carries_session_time_and_transient_events_have_no_sequence() {
+        let mut changed = payload(
+            Some(4),
+            1_700_000_000_250,
+            Event::Changed {
+                old: "前".to_string(),
+                new: "後".to_string(),
+            },
+        );
+        changed.session_started_at_ms = Some(1_700_000_000_000);
+        changed.session_offset_ms = Some(250);
+        assert_wire_format(
+            &changed,
+            r#"{"schema_version":1,"sequence":4,"timestamp_ms":1700000000250,"session_started_at_ms":1700000000000,"session_offset_ms":250,"type":"changed","old":"前","new":"後"}"#,
+        );
+
+        assert_wire_format(
+            &payload(
+                None,
+                5,
+                Event::DownloadProgress {
+                    language: "jpn".to_string(),
+                    downloaded_bytes: 512,
+                    total_bytes: 1024,
+                    percent: 50.0,
+                },
+            ),
+            r#"{"schema_version":1,"sequence":null,"timestamp_ms":5,"type":"download_progress","language":"jpn","downloaded_bytes":512,"total_bytes":1024,"percent":50.0}"#,
+        );
+    }
+
+    #[test]
+    fn text_changed_payloads_pin_each_event_shape() {
+        assert_wire_format(
+            &payload(
+                Some(1),
+                10,
+                Event::RichChanged {
+                    old: None,
+                    new: "新".to_string(),
+                    thumbnail: Some("iVBOR".to_string()),
+                    confidence: Some(0.75),
+                    metrics: None,
+                },
+            ),
+            r#"{"schema_version":1,"sequence":1,"timestamp_ms":10,"type":"rich_changed","old":null,"new":"新","thumbnail":"iVBOR","confidence":0.75}"#,
+        );
+        assert_wire_format(
+            &payload(
+                Some(2),
+                20,
+                Event::Replaced {
+                    old: "前".to_string(),
+                    new: "後".to_string(),
+                    cleared_at_ms: 15,
+                    replaced_at_ms: 20,
+                },
+            ),
+            r#"{"schema_version":1,"sequence":2,"timestamp_ms":20,"type":"replaced","old":"前","new":"後","cleared_at_ms":15,"replaced_at_ms":20}"#,
+        );
+        assert_wire_format(
+            &payload(
+                Some(3),
+                30,
+                Event::Diff {
+                    added: vec!["勇者: こんにちは".to_string()],
+                    removed: vec![],
+                    line_stability: vec![LineStability {
+                        line_text: "勇者: こんにちは".to_string(),
+                        stability: 0.5,
+                        last_seen: 30,
+                    }],
+                    parsed_added: vec![ParsedLine {
+                        line: "勇者: こんにちは".to_string(),
+                        speaker: Some("勇者".to_string()),
+                        message: "こんにちは".to_string(),
+                    }],
+                    added_languages: vec![],
+                },
+            ),
+            r#"{"schema_version":1,"sequence":3,"timestamp_ms":30,"type":"diff","added":["勇者: こんにちは"],"removed":[],"line_stability":[{"line_text":"勇者: こんにちは","stability":0.5,"last_seen":30}],"parsed_added":[{"line":"勇者: こんにちは","speaker":"勇者","message":"こんにちは"}]}"#,
+        );
+        assert_wire_format(
+            &payload(
+                Some(4),
+                40,
+                Event::Info {
+                    code: "killswitch".to_string(),
+                    message: "停止".to_string(),
+                },
+            ),
+            r#"{"schema_version":1,"sequence":4,"timestamp_ms":40,"type":"info","code":"killswitch","message":"停止"}"#,
+        );
+    }
+
+    #[test]
+    fn batch_nests_payloads_in_the_same_envelope() {
+        assert_wire_format(
+            &payload(
+                None,
+                50,
+                Event::Batch {
+                    events: vec![
+                        payload(Some(5), 41, Event::New { text: "一".to_string() }),
+                        payload(Some(6), 42, Event::Cleared { text: "一".to_string() }),
+                    ],
+                    total_dropped: 3,
+                },
+            ),
+            r#"{"schema_version":1,"sequence":null,"timestamp_ms":50,"type":"batch","events":[{"schema_version":1,"sequence":5,"timestamp_ms":41,"type":"new","text":"一"},{"schema_version":1,"sequence":6,"timestamp_ms":42,"type":"cleared","text":"一"}],"total_dropped":3}"#,
+        );
+    }
+
+    #[test]
+    fn diff_from_before_line_details_still_parses() {
+        let parsed: TextChangedPayload =
+            serde_json::from_str(r#"{"schema_version":1,"sequence":7,"timestamp_ms":60,"type":"diff","added":["a"],"removed":["b"]}"#)
+                .unwrap();
+        assert_eq!(
+            parsed.event,
+            Event::Diff {
+                added: vec!["a".to_string()],
+                removed: vec!["b".to_string()],
+                line_stability: vec![],
+                parsed_added: vec![],
+                added_languages: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn error_payload_wire_format() {
+        assert_wire_format(
+            &ErrorPayload {
+                schema_version: 1,
+                timestamp_ms: 1_700_000_000_000,
+                message: "OCRエラー: 認識できません".to_string(),
+            },
+            r#"{"schema_version":1,"timestamp_ms":1700000000000,"message":"OCRエラー: 認識できません"}"#,
+        );
+    }
+
+    #[test]
+    fn lifecycle_payload_wire_format() {
+        assert_wire_format(
+            &LifecyclePayload {
+                schema_version: 1,
+                timestamp_ms: 1_700_000_000_000,
+                session_id: 2,
+                state: LifecycleState::Started,
+                summary: None,
+                failure: None,
+            },
+            r#"{"schema_version":1,"timestamp_ms":1700000000000,"session_id":2,"state":"started"}"#,
+        );
+        assert_wire_format(
+            &LifecyclePayload {
+                schema_version: 1,
+                timestamp_ms: 1_700_000_060_000,
+                session_id: 2,
+                state: LifecycleState::Stopped,
+                summary: Some(summary()),
+                failure: Some(SessionFailure {
+                    message: "言語データがありません".to_string(),
+                    remediation: "download_language".to_string(),
+                }),
+            },
+            concat!(
+                r#"{"schema_version":1,"timestamp_ms":1700000060000,"session_id":2,"state":"stopped","summary":{"#,
+                r#""session_id":2,"started_at_ms":1700000000000,"stopped_at_ms":1700000060000,"duration_ms":60000,"#,
+                r#""stop_reason":"killswitch","change_events":3,"unique_lines":2,"unique_lines_capped":false,"#,
+                r#""error_count":1,"ocr_count":40,"ocr_latency_avg_ms":12.5,"ocr_latency_p50_ms":10.0,"ocr_latency_p95_ms":30.25,"#,
+                r#""busiest_period":{"start_ms":1700000010000,"duration_ms":10000,"change_events":2},"text_coverage":null},"#,
+                r#""failure":{"message":"言語データがありません","remediation":"download_language"}}"#,
+            ),
+        );
+    }
+
+    #[test]
+    fn lifecycle_payload_from_before_session_ids_still_parses() {
+        let parsed: LifecyclePayload =
+            serde_json::from_str(r#"{"schema_version":1,"timestamp_ms":1,"state":"stopped"}"#).unwrap();
+        assert_eq!(parsed.session_id, 0);
+        assert_eq!(parsed.state, LifecycleState::Stopped);
+    }
+}
@@ -0,0 +1,185 @@
+// 画面の更新通知によるキャプチャのきっかけ（Windowsのみ、他の環境では一定間隔のポーリング）
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::capture::CaptureRegion;
+
+/// キャプチャを行うきっかけ
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureTrigger {
+    /// 一定間隔でキャプチャ
+    #[default]
+    Polling,
+    /// OSが領域の更新を通知したときにキャプチャ（通知が無くても間隔ごとにキャプチャする）
+    ScreenChange,
+}
+
+/// 画面の更新通知を待つ
+pub trait ScreenChangeWaiter {
+    /// 領域に重なる更新が通知されるまで最大timeoutだけ待つ（通知があればtrue）
+    fn wait(&mut self, region: &CaptureRegion, timeout: Duration) -> Result<bool>;
+}
+
+/// 更新通知を待つ仕組みを作成（使えない環境や初期化に失敗した場合はNoneでポーリングに戻す）
+///
+/// COMのオブジェクトはスレッドをまたげないため、待機するスレッドで作成すること。
+pub fn create_waiter(trigger: CaptureTrigger, region: &CaptureRegion) -> Option<Box<dyn ScreenChangeWaiter>> {
+    if trigger == CaptureTrigger::Polling {
+        return None;
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        match desktop_duplication::DesktopDuplicationWaiter::new(region) {
+            Ok(waiter) => return Some(Box::new(waiter)),
+            Err(e) => log::warn!("画面の更新通知を使えないためポーリングでキャプチャします: {}", e),
+        }
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        let _ = region;
+        log::warn!("画面の更新通知はWindowsのみ対応のため、ポーリングでキャプチャします");
+    }
+    None
+}
+
+/// Desktop Duplication APIによる更新通知
+#[cfg(target_os = "windows")]
+mod desktop_duplication {
+    use anyhow::{anyhow, Context, Result};
+    use std::time::Duration;
+    use windows::core::ComInterface;
+    use windows::Win32::Foundation::{HMODULE, RECT};
+    use windows::Win32::Graphics::Direct3D::D3D_DRIVER_TYPE_HARDWARE;
+    use windows::Win32::Graphics::Direct3D11::{
+        D3D11CreateDevice, ID3D11Device, D3D11_CREATE_DEVICE_FLAG, D3D11_SDK_VERSION,
+    };
+    use windows::Win32::Graphics::Dxgi::{
+        IDXGIDevice, IDXGIOutput1, IDXGIOutputDuplication, IDXGIResource, DXGI_ERROR_ACCESS_LOST,
+        DXGI_ERROR_WAIT_TIMEOUT, DXGI_OUTDUPL_FRAME_INFO, DXGI_OUTDUPL_MOVE_RECT,
+    };
+
+    use super::ScreenChangeWaiter;
+    use crate::capture::CaptureRegion;
+
+    /// 領域を含むモニターの更新を待つ
+    pub struct DesktopDuplicationWaiter {
+        device: ID3D11Device,
+        output: IDXGIOutput1,
+        duplication: IDXGIOutputDuplication,
+        /// モニターの左上のデスクトップ座標（更新矩形はモニター内の座標で通知される）
+        origin: (i32, i32),
+    }
+
+    impl DesktopDuplicationWaiter {
+        /// 領域の左上を含むモニターの更新通知を開始
+        pub fn new(region: &CaptureRegion) -> Result<Self> {
+            let mut device = None;
+            unsafe {
+                D3D11CreateDevice(
+                    None,
+                    D3D_DRIVER_TYPE_HARDWARE,
+                    HMODULE::default(),
+                    D3D11_CREATE_DEVICE_FLAG(0),
+                    None,
+                    D3D11_SDK_VERSION,
+                    Some(&mut device),
+                    None,
+                    None,
+                )
+            }
+            .context("Direct3Dデバイスの作成に失敗しました")?;
+            let device = device.ok_or_else(|| anyhow!("Direct3Dデバイスを取得できませんでした"))?;
+
+            let adapter = unsafe { device.cast::<IDXGIDevice>()?.GetAdapter() }.context("アダプターの取得に失敗しました")?;
+            let mut index = 0;
+            loop {
+                let output = unsafe { adapter.EnumOutputs(index) }
+                    .map_err(|_| anyhow!("領域を含むモニターが見つかりません"))?;
+                let desc = unsafe { output.GetDesc() }?;
+                let bounds = desc.DesktopCoordinates;
+                if region.x >= bounds.left && region.x < bounds.right && region.y >= bounds.top && region.y < bounds.bottom {
+                    let output = output.cast::<IDXGIOutput1>()?;
+                    let duplication = unsafe { output.DuplicateOutput(&device) }.context("画面の複製の開始に失敗しました")?;
+                    log::info!("画面の更新通知を開始しました（モニター {}）", index);
+                    return Ok(Self {
+                        device,
+                        output,
+                        duplication,
+                        origin: (bounds.left, bounds.top),
+                    });
+                }
+                index += 1;
+            }
+        }
+
+        /// 直前に取得したフレームの更新矩形・移動先の矩形に領域が含まれるかどうか
+        fn frame_touches(&self, region: &CaptureRegion, info: &DXGI_OUTDUPL_FRAME_INFO) -> Result<bool> {
+            // マウスの移動のみの更新は無視する
+            if info.LastPresentTime == 0 {
+                return Ok(false);
+            }
+            let buffer_size = info.TotalMetadataBufferSize;
+            if buffer_size == 0 {
+                // 更新箇所が通知されない場合は領域に重なるものとみなす
+                return Ok(true);
+            }
+
+            // 更新矩形はモニターの左上を原点とする座標
+            let touches = |rect: &RECT| {
+                CaptureRegion::from_edges(rect.left.into(), rect.top.into(), rect.right.into(), rect.bottom.into())
+                    .is_some_and(|rect| rect.from_local(self.origin).intersect(region).is_some())
+            };
+
+            let mut move_rects = vec![DXGI_OUTDUPL_MOVE_RECT::default(); buffer_size as usize / std::mem::size_of::<DXGI_OUTDUPL_MOVE_RECT>() + 1];
+            let mut required = 0;
+            unsafe {
+                self.duplication.GetFrameMoveRects(
+                    (move_rects.len() * std::mem::size_of::<DXGI_OUTDUPL_MOVE_RECT>()) as u32,
+                    move_rects.as_mut_ptr(),
+                    &mut required,
+                )
+            }?;
+            move_rects.truncate(required as usize / std::mem::size_of::<DXGI_OUTDUPL_MOVE_RECT>());
+            if move_rects.iter().any(|rect| touches(&rect.DestinationRect)) {
+                return Ok(true);
+            }
+
+            let mut dirty_rects = vec![RECT::default(); buffer_size as usize / std::mem::size_of::<RECT>() + 1];
+            unsafe {
+                self.duplication.GetFrameDirtyRects(
+                    (dirty_rects.len() * std::mem::size_of::<RECT>()) as u32,
+                    dirty_rects.as_mut_ptr(),
+                    &mut required,
+                )
+            }?;
+            dirty_rects.truncate(required as usize / std::mem::size_of::<RECT>());
+            Ok(dirty_rects.iter().any(touches))
+        }
+    }
+
+    impl ScreenChangeWaiter for DesktopDuplicationWaiter {
+        fn wait(&mut self, region: &CaptureRegion, timeout: Duration) -> Result<bool> {
+            let mut info = DXGI_OUTDUPL_FRAME_INFO::default();
+            let mut resource: Option<IDXGIResource> = None;
+            match unsafe { self.duplication.AcquireNextFrame(timeout.as_millis() as u32, &mut info, &mut resource) } {
+                Ok(()) => {}
+                Err(e) if e.code() == DXGI_ERROR_WAIT_TIMEOUT => return Ok(false),
+                // 解像度の変更や全画面表示の切り替えで複製が無効になった場合は作り直す
+                Err(e) if e.code() == DXGI_ERROR_ACCESS_LOST => {
+                    log::info!("画面の複製が無効になったため再開します");
+                    self.duplication = unsafe { self.output.DuplicateOutput(&self.device) }?;
+                    return Ok(true);
+                }
+                Err(e) => return Err(e.into()),
+            }
+
+            let touches = self.frame_touches(region, &info);
+            drop(resource);
+            unsafe { self.duplication.ReleaseFrame() }?;
+            touches
+        }
+    }
+}
@@ -0,0 +1,198 @@
+// 認識言語と文字種の不一致の検出（言語の設定ミスによる文字化けの原因を知らせる）
+//
+// 認識結果の文字種の分布が設定中の言語と合わない状態が続いたら、それらしい言語を1回だけ提案する。
+// 設定は変更せず、提案を反映するかどうかは利用者が決める。
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// 判定に必要な1回の認識結果の文字数（これより少ない認識結果は数えない）
+const MIN_LETTERS: usize = 10;
+
+/// 文字種
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Script {
+    Latin,
+    Kana,
+    Han,
+    Hangul,
+    Cyrillic,
+}
+
+impl Script {
+    /// 文字の文字種（数字・記号や対象外の文字種はNone）
+    fn of(c: char) -> Option<Self> {
+        match c {
+            'A'..='Z' | 'a'..='z' | '\u{00C0}'..='\u{024F}' | '\u{FF21}'..='\u{FF3A}' | '\u{FF41}'..='\u{FF5A}' => {
+                Some(Script::Latin)
+            }
+            '\u{3040}'..='\u{30FF}' | '\u{31F0}'..='\u{31FF}' | '\u{FF66}'..='\u{FF9F}' => Some(Script::Kana),
+            '\u{3400}'..='\u{4DBF}' | '\u{4E00}'..='\u{9FFF}' | '\u{F900}'..='\u{FAFF}' => Some(Script::Han),
+            '\u{1100}'..='\u{11FF}' | '\u{3130}'..='\u{318F}' | '\u{AC00}'..='\u{D7AF}' => Some(Script::Hangul),
+            '\u{0400}'..='\u{04FF}' => Some(Script::Cyrillic),
+            _ => None,
+        }
+    }
+}
+
+/// 言語コードで認識される文字種（判定できない言語はNone）
+fn expected_scripts(language: &str) -> Option<Vec<Script>> {
+    let mut scripts = Vec::new();
+    // jpn+eng のような複数の言語はいずれかの文字種なら一致とする
+    for code in language.split('+') {
+        match code.trim_end_matches("_vert") {
+            "jpn" => scripts.extend([Script::Kana, Script::Han]),
+            "chi_sim" | "chi_tra" => scripts.push(Script::Han),
+            "kor" => scripts.extend([Script::Hangul, Script::Han]),
+            "rus" | "ukr" | "bel" | "bul" | "mkd" | "srp" => scripts.push(Script::Cyrillic),
+            "eng" | "deu" | "fra" | "spa" | "ita" | "por" | "nld" | "pol" | "ces" | "swe" | "dan" | "nor" | "fin"
+            | "tur" | "vie" | "ind" => scripts.push(Script::Latin),
+            _ => return None,
+        }
+    }
+    Some(scripts)
+}
+
+/// 複数の言語（jpn+eng など）を設定した場合に、行の文字種から推定したその行の言語
+///
+/// 仮名は日本語にしか無いため、仮名を1文字でも含む行は日本語とする（英単語の多い日本語の行のため）。
+/// それ以外は設定中の言語のうち、その言語の文字種の文字を最も多く含むものを返す（同数なら先に書いた言語）。
+/// 1つの言語のみの設定や、いずれの言語の文字も含まない行はNone。
+pub fn line_language(language: &str, text: &str) -> Option<String> {
+    let codes: Vec<&str> = language.split('+').collect();
+    if codes.len() < 2 {
+        return None;
+    }
+
+    let mut counts: HashMap<Script, usize> = HashMap::new();
+    for script in text.chars().filter_map(Script::of) {
+        *counts.entry(script).or_insert(0) += 1;
+    }
+    if counts.contains_key(&Script::Kana) {
+        if let Some(code) = codes.iter().find(|code| code.trim_end_matches("_vert") == "jpn") {
+            return Some(code.to_string());
+        }
+    }
+
+    let mut best: Option<(&str, usize)> = None;
+    for code in codes {
+        let Some(scripts) = expected_scripts(code) else {
+            continue;
+        };
+        let count: usize = scripts.iter().filter_map(|script| counts.get(script)).sum();
+        if count > 0 && best.map_or(true, |(_, best_count)| count > best_count) {
+            best = Some((code, count));
+        }
+    }
+    best.map(|(code, _)| code.to_string())
+}
+
+/// 1回の認識結果の判定
+enum Verdict {
+    /// 文字が少ない、または言語の文字種が分からないため判定しない
+    Undecided,
+    /// 言語と文字種が合っている
+    Consistent,
+    /// 言語と文字種が合わない（推測した言語）
+    Mismatch(&'static str),
+}
+
+/// 認識結果の文字種の分布を設定中の言語と比べる
+fn judge(language: &str, text: &str, mismatch_ratio: f32) -> Verdict {
+    let Some(expected) = expected_scripts(language) else {
+        return Verdict::Undecided;
+    };
+
+    let mut counts: HashMap<Script, usize> = HashMap::new();
+    let mut letters = 0;
+    for c in text.chars().filter(|c| c.is_alphabetic()) {
+        letters += 1;
+        if let Some(script) = Script::of(c) {
+            *counts.entry(script).or_insert(0) += 1;
+        }
+    }
+    if letters < MIN_LETTERS {
+        return Verdict::Undecided;
+    }
+    let Some((&dominant, &count)) = counts.iter().max_by_key(|(_, &count)| count) else {
+        return Verdict::Undecided;
+    };
+    if expected.contains(&dominant) || (count as f32) < letters as f32 * mismatch_ratio {
+        return Verdict::Consistent;
+    }
+    let suggestion = match dominant {
+        Script::Latin => "eng",
+        Script::Kana => "jpn",
+        // 漢字だけでは日本語と中国語を区別できないため、かながあれば日本語とする
+        Script::Han if counts.contains_key(&Script::Kana) => "jpn",
+        Script::Han => "chi_sim",
+        Script::Hangul => "kor",
+        Script::Cyrillic => "rus",
+    };
+    Verdict::Mismatch(suggestion)
+}
+
+/// 連続する認識結果の文字種の不一致の検出
+#[derive(Debug, Default)]
+pub struct ScriptCheck {
+    /// 不一致が続いている回数と推測した言語
+    streak: Option<(&'static str, u32)>,
+    /// 提案済みかどうか（言語を変えるまで再度は提案しない）
+    suggested: bool,
+}
+
+impl ScriptCheck {
+    /// 認識結果を記録し、不一致が required_ticks 回続いたら提案する言語を1回だけ返す（0で無効）
+    pub fn observe(&mut self, language: &str, text: &str, mismatch_ratio: f32, required_ticks: u32) -> Option<String> {
+        if required_ticks == 0 || self.suggested {
+            return None;
+        }
+        match judge(language, text, mismatch_ratio) {
+            // 判定できない認識結果は連続の回数を変えない
+            Verdict::Undecided => None,
+            Verdict::Consistent => {
+                self.streak = None;
+                None
+            }
+            Verdict::Mismatch(suggestion) => {
+                let ticks = match self.streak {
+                    Some((previous, ticks)) if previous == suggestion => ticks + 1,
+                    _ => 1,
+                };
+                self.streak = Some((suggestion, ticks));
+                if ticks < required_ticks {
+                    return None;
+                }
+                self.suggested = true;
+                Some(suggestion.to_string())
+            }
+        }
+    }
+
+    /// 言語を変えた後は最初から数え直す
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// スレッド間で共有する提案中の言語
+pub type SharedLanguageSuggestion = Arc<Mutex<Option<String>>>;
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_with_any_kana_is_japanese() {
+        assert_eq!(line_language("eng+jpn", "Windowsを起動"), Some("jpn".to_string()));
+        assert_eq!(line_language("eng+jpn_vert", "Settings > ネットワーク"), Some("jpn_vert".to_string()));
+    }
+
+    #[test]
+    fn line_without_kana_uses_the_most_common_script() {
+        assert_eq!(line_language("jpn+eng", "Error code 404"), Some("eng".to_string()));
+        assert_eq!(line_language("eng+jpn", "漢字 A"), Some("jpn".to_string()));
+        assert_eq!(line_language("jpn+eng", "12345"), None);
+        assert_eq!(line_language("jpn", "テキスト"), None);
+    }
+}
@@ -0,0 +1,53 @@
+// 終了シグナル（SIGTERM）の受け付け
+//
+// 無人で動かす場合に、停止ファイルと同じく監視を通常の停止と同じ手順で終えてからアプリを終了できるようにする。
+// シグナルハンドラではフラグを立てるだけにし、監視スレッドと終了を待つスレッドがフラグを確認する。
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// 終了シグナルを受け取ったかどうか
+static TERMINATION_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// 終了シグナルを受け取ったら立つフラグ（監視スレッドが毎フレーム確認する）
+pub fn termination_flag() -> &'static AtomicBool {
+    &TERMINATION_REQUESTED
+}
+
+/// 終了シグナルを受け取ったかどうか
+pub fn termination_requested() -> bool {
+    TERMINATION_REQUESTED.load(Ordering::Relaxed)
+}
+
+/// 終了シグナルのハンドラを登録（SIGTERMの無いUnix以外では何もしない）
+pub fn install() {
+    #[cfg(unix)]
+    unix_signal::install();
+}
+
+/// SIGTERMのハンドラ（Unixのみ）
+#[cfg(unix)]
+mod unix_signal {
+    use std::os::raw::c_int;
+    use std::sync::atomic::Ordering;
+
+    /// SIGTERMのシグナル番号（LinuxとmacOSで共通）
+    const SIGTERM: c_int = 15;
+
+    /// 登録に失敗した場合にsignalが返す値（SIG_ERR）
+    const SIG_ERR: usize = usize::MAX;
+
+    extern "C" {
+        fn signal(signum: c_int, handler: extern "C" fn(c_int)) -> usize;
+    }
+
+    /// シグナルハンドラの中では非同期シグナル安全な処理しかできないため、フラグを立てるだけにする
+    extern "C" fn handle_termination(_signum: c_int) {
+        super::TERMINATION_REQUESTED.store(true, Ordering::Relaxed);
+    }
+
+    pub fn install() {
+        // 登録できなければ既定の動作（即時に終了）のまま
+        if unsafe { signal(SIGTERM, handle_termination) } == SIG_ERR {
+            log::warn!("SIGTERMのハンドラを登録できません（受け取ると監視を停止せずに終了します）");
+        }
+    }
+}
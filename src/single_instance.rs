@@ -0,0 +1,127 @@
+// 多重起動の防止と、後から起動したプロセスの --url の転送
+//
+// 最初に起動したプロセスがループバックのポートで待ち受け、後から起動したプロセスはそのポートに
+// 接続して --url の値を渡してすぐに終了する。OSがURLのスキームの関連付けから新しいプロセスを
+// 起動しても、監視は既に起動しているアプリで始まる。
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+/// 起動中のプロセスが待ち受けるポート
+pub const FORWARD_PORT: u16 = 47615;
+
+/// 転送のメッセージの先頭（このアプリ以外からの接続を無視するため）
+const MESSAGE_PREFIX: &str = "screen_text_monitor/1 ";
+
+/// 1回の転送で受け取る最大のバイト数（URLの上限として十分な大きさ）
+const MAX_MESSAGE_BYTES: u64 = 8 * 1024;
+
+/// 接続・送受信の時間の上限
+const IO_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 起動の判定結果
+pub enum Instance {
+    /// 最初に起動したプロセス（後から起動したプロセスの転送を待ち受ける）
+    Primary(TcpListener),
+    /// 既に起動しているプロセスに転送した
+    Forwarded,
+    /// 待ち受けも転送もできない（多重起動を防がずに起動する）
+    Unavailable,
+}
+
+/// 既定のポートで多重起動を判定（後から起動した場合はurlを転送する）
+pub fn acquire(url: Option<&str>) -> Instance {
+    acquire_on(FORWARD_PORT, url)
+}
+
+/// 指定したポートで多重起動を判定
+pub fn acquire_on(port: u16, url: Option<&str>) -> Instance {
+    let address = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    let bind_error = match TcpListener::bind(address) {
+        Ok(listener) => return Instance::Primary(listener),
+        Err(e) => e,
+    };
+    match forward(address, url) {
+        Ok(()) => Instance::Forwarded,
+        Err(e) => {
+            log::warn!("起動中のプロセスに転送できません（{}）: {}", bind_error, e);
+            Instance::Unavailable
+        }
+    }
+}
+
+/// 起動中のプロセスにURLを送る（URLが無ければ空のメッセージで前面に出すだけにする）
+fn forward(address: SocketAddr, url: Option<&str>) -> io::Result<()> {
+    let mut stream = TcpStream::connect_timeout(&address, IO_TIMEOUT)?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    let url = url.unwrap_or_default().replace(['\r', '\n'], "");
+    writeln!(stream, "{}{}", MESSAGE_PREFIX, url)?;
+    stream.flush()
+}
+
+/// 後から起動したプロセスの転送を専用スレッドで受け取り続ける（URLが無い転送は空の文字列）
+pub fn listen(listener: TcpListener, mut on_forwarded: impl FnMut(String) + Send + 'static) {
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream.and_then(receive) {
+                Ok(Some(url)) => on_forwarded(url),
+                Ok(None) => log::debug!("多重起動の転送ではない接続を無視しました"),
+                Err(e) => log::debug!("多重起動の転送を受け取れません: {}", e),
+            }
+        }
+    });
+}
+
+/// 1つの接続からメッセージを読む（このアプリのメッセージでなければNone）
+fn receive(stream: TcpStream) -> io::Result<Option<String>> {
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    let mut line = String::new();
+    BufReader::new(stream.take(MAX_MESSAGE_BYTES)).read_line(&mut line)?;
+    Ok(line
+        .trim_end_matches(['\r', '\n'])
+        .strip_prefix(MESSAGE_PREFIX)
+        .map(str::to_string))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    /// テストごとに空いているポート
+    fn free_port() -> u16 {
+        TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap().local_addr().unwrap().port()
+    }
+
+    #[test]
+    fn second_instance_forwards_its_url() {
+        let port = free_port();
+        let Instance::Primary(listener) = acquire_on(port, None) else {
+            panic!("最初のプロセスが待ち受けられません");
+        };
+        let (sender, receiver) = mpsc::channel();
+        listen(listener, move |url| sender.send(url).unwrap());
+
+        let url = "ocr://monitor?x=1&y=2&w=3&h=4";
+        assert!(matches!(acquire_on(port, Some(url)), Instance::Forwarded));
+        assert!(matches!(acquire_on(port, None), Instance::Forwarded));
+        assert_eq!(receiver.recv_timeout(IO_TIMEOUT).unwrap(), url);
+        assert_eq!(receiver.recv_timeout(IO_TIMEOUT).unwrap(), "");
+    }
+
+    #[test]
+    fn ignores_connections_from_other_programs() {
+        let port = free_port();
+        let Instance::Primary(listener) = acquire_on(port, None) else {
+            panic!("最初のプロセスが待ち受けられません");
+        };
+        let client = thread::spawn(move || {
+            let mut stream = TcpStream::connect((Ipv4Addr::LOCALHOST, port)).unwrap();
+            stream.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+        });
+        let (stream, _) = listener.accept().unwrap();
+        assert_eq!(receive(stream).unwrap(), None);
+        client.join().unwrap();
+    }
+}
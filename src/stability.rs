@@ -0,0 +1,186 @@
+// 行ごとの認識安定度の追跡
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// 安定度の計算に使う直近のティック数
+const STABILITY_WINDOW: usize = 20;
+
+/// 追跡する行の最大数（超えた場合は最終検出が古い行から破棄）
+const MAX_TRACKED_LINES: usize = 200;
+
+/// 前回の行と同じ行とみなす類似度の下限
+pub(crate) const MATCH_THRESHOLD: f32 = 0.5;
+
+/// 行の安定度
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LineStability {
+    /// 最後に認識された行のテキスト
+    pub line_text: String,
+    /// 直近のティックで前回と同じ結果になった割合（0.0〜1.0）
+    pub stability: f32,
+    /// 最後に検出された時刻（UNIXエポックからのミリ秒）
+    pub last_seen: u64,
+}
+
+/// 追跡中の1行
+#[derive(Debug)]
+struct TrackedLine {
+    /// 最後に認識されたテキスト
+    text: String,
+    /// 最後に検出された行位置
+    position: usize,
+    /// 直近のティックごとの一致結果
+    agreements: VecDeque<bool>,
+    /// 最後に検出された時刻
+    last_seen: u64,
+}
+
+impl TrackedLine {
+    /// 一致結果を記録（古いものから破棄）
+    fn record(&mut self, agreed: bool) {
+        if self.agreements.len() >= STABILITY_WINDOW {
+            self.agreements.pop_front();
+        }
+        self.agreements.push_back(agreed);
+    }
+
+    /// 直近の一致率を計算
+    fn stability(&self) -> f32 {
+        if self.agreements.is_empty() {
+            return 1.0;
+        }
+        let agreed = self.agreements.iter().filter(|&&agreed| agreed).count();
+        agreed as f32 / self.agreements.len() as f32
+    }
+
+    fn to_stability(&self) -> LineStability {
+        LineStability {
+            line_text: self.text.clone(),
+            stability: self.stability(),
+            last_seen: self.last_seen,
+        }
+    }
+}
+
+/// ティックをまたいで行ごとの認識の一致率を追跡する
+#[derive(Debug, Default)]
+pub struct LineStabilityTracker {
+    lines: Vec<TrackedLine>,
+}
+
+impl LineStabilityTracker {
+    /// 1ティック分の認識結果を記録
+    ///
+    /// 各行は、位置が近くテキストが類似した追跡中の行と対応付け、
+    /// 前回とテキストが完全に一致したかどうかを記録する。
+    /// 対応する行が無ければ新しい行として追跡を開始する。nowはセッションの時計の現在時刻（UNIXエポックからのミリ秒）。
+    pub fn observe(&mut self, text: &str, now: u64) {
+        let mut matched = vec![false; self.lines.len()];
+
+        for (position, line) in text.lines().map(str::trim).enumerate() {
+            if line.is_empty() {
+                continue;
+            }
+
+            // 未対応の行の中から最も類似した行を探す（同程度なら位置が近い方を優先）
+            let best = self
+                .lines
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| !matched[*index])
+                .map(|(index, tracked)| {
+                    let distance = tracked.position.abs_diff(position) as f32;
+                    let score = similarity(&tracked.text, line) - distance * 0.01;
+                    (index, score)
+                })
+                .filter(|(_, score)| *score >= MATCH_THRESHOLD)
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+
+            match best {
+                Some((index, _)) => {
+                    matched[index] = true;
+                    let tracked = &mut self.lines[index];
+                    let agreed = tracked.text == line;
+                    tracked.record(agreed);
+                    tracked.text = line.to_string();
+                    tracked.position = position;
+                    tracked.last_seen = now;
+                }
+                None => {
+                    self.lines.push(TrackedLine {
+                        text: line.to_string(),
+                        position,
+                        agreements: VecDeque::with_capacity(STABILITY_WINDOW),
+                        last_seen: now,
+                    });
+                    matched.push(true);
+                }
+            }
+        }
+
+        self.evict();
+    }
+
+    /// 上限を超えた分を最終検出が古い順に破棄
+    fn evict(&mut self) {
+        if self.lines.len() <= MAX_TRACKED_LINES {
+            return;
+        }
+        self.lines.sort_by_key(|line| std::cmp::Reverse(line.last_seen));
+        self.lines.truncate(MAX_TRACKED_LINES);
+    }
+
+    /// 追跡中の全行の安定度を取得（最終検出が新しい順）
+    pub fn snapshot(&self) -> Vec<LineStability> {
+        let mut lines: Vec<LineStability> = self.lines.iter().map(TrackedLine::to_stability).collect();
+        lines.sort_by_key(|line| std::cmp::Reverse(line.last_seen));
+        lines
+    }
+
+    /// 指定したテキストの行の安定度を取得
+    pub fn lookup(&self, line: &str) -> Option<LineStability> {
+        let line = line.trim();
+        self.lines
+            .iter()
+            .find(|tracked| tracked.text == line)
+            .map(TrackedLine::to_stability)
+    }
+
+    /// 追跡中の行をすべて破棄
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+}
+
+/// 2つの行の類似度を文字バイグラムのDice係数で計算（0.0〜1.0）
+pub(crate) fn similarity(a: &str, b: &str) -> f32 {
+    if a == b {
+        return 1.0;
+    }
+
+    let bigrams = |s: &str| -> Vec<(char, char)> {
+        let chars: Vec<char> = s.chars().collect();
+        chars.windows(2).map(|w| (w[0], w[1])).collect()
+    };
+    let a_bigrams = bigrams(a);
+    let mut b_bigrams = bigrams(b);
+    if a_bigrams.is_empty() || b_bigrams.is_empty() {
+        return 0.0;
+    }
+
+    let total = a_bigrams.len() + b_bigrams.len();
+    let mut common = 0;
+    for bigram in &a_bigrams {
+        if let Some(index) = b_bigrams.iter().position(|other| other == bigram) {
+            b_bigrams.swap_remove(index);
+            common += 1;
+        }
+    }
+
+    (2 * common) as f32 / total as f32
+}
+
+/// スレッド間で共有する安定度トラッカー
+pub type SharedStability = Arc<Mutex<LineStabilityTracker>>;
+
@@ -0,0 +1,87 @@
+// 監視を開始せずに開始できるかを確かめた結果（自動化スクリプトの事前確認用）
+use serde::Serialize;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// 配信先への接続を待つ時間
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 1つの確認の状態
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Passed,
+    Failed,
+    /// 前の確認が失敗した、または対象が無いため確認しなかった
+    Skipped,
+}
+
+/// 1つの確認の結果
+#[derive(Debug, Clone, Serialize)]
+pub struct StartupCheck {
+    /// 確認の名前（phase、config、regionなど）
+    pub name: String,
+    pub status: CheckStatus,
+    /// 確認した内容、または失敗した理由
+    pub details: String,
+}
+
+/// validate_monitoringの結果
+#[derive(Debug, Clone, Serialize)]
+pub struct StartupReport {
+    /// 失敗した確認が無いかどうか
+    pub ok: bool,
+    pub checks: Vec<StartupCheck>,
+}
+
+impl StartupReport {
+    pub fn new() -> Self {
+        Self {
+            ok: true,
+            checks: Vec::new(),
+        }
+    }
+
+    /// 確認の結果を追加（Errは失敗、成功したかどうかを返す）
+    pub fn record(&mut self, name: &str, result: Result<String, String>) -> bool {
+        let (status, details) = match result {
+            Ok(details) => (CheckStatus::Passed, details),
+            Err(details) => (CheckStatus::Failed, details),
+        };
+        self.ok &= status != CheckStatus::Failed;
+        self.checks.push(StartupCheck {
+            name: name.to_string(),
+            status,
+            details,
+        });
+        status == CheckStatus::Passed
+    }
+
+    /// 確認しなかった項目を理由とともに追加（失敗とはしない）
+    pub fn skip(&mut self, name: &str, reason: impl Into<String>) {
+        self.checks.push(StartupCheck {
+            name: name.to_string(),
+            status: CheckStatus::Skipped,
+            details: reason.into(),
+        });
+    }
+}
+
+/// アドレス（ホスト:ポート）にTCPで接続できるか確認
+pub fn check_tcp(address: &str, timeout: Duration) -> Result<String, String> {
+    let addresses: Vec<_> = address
+        .to_socket_addrs()
+        .map_err(|e| format!("{} の名前を解決できません: {}", address, e))?
+        .collect();
+    let mut last_error = None;
+    for resolved in &addresses {
+        match TcpStream::connect_timeout(resolved, timeout) {
+            Ok(_) => return Ok(format!("{}（{}）に接続できました", address, resolved)),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(match last_error {
+        Some(e) => format!("{} に接続できません: {}", address, e),
+        None => format!("{} のアドレスが見つかりません", address),
+    })
+}
@@ -0,0 +1,325 @@
+// アプリケーションの状態だけを操作するコマンドの中身
+//
+// Tauriのコマンドは状態を渡してここの関数を呼ぶだけにする。Tauriの実行環境が無いテストでも、
+// フロントエンドと同じ名前と引数でコマンドを呼び出せる（test_helpers::TestHarness::call_command）。
+use log::info;
+use std::sync::Mutex;
+
+use crate::events::{EventFilter, EventPage, HistoryEntry};
+use crate::locking::lock;
+use crate::monitor::{MonitorConfig, MonitorSnapshot};
+use crate::phase::MonitorPhase;
+use crate::stats::MonitorStats;
+use crate::summary::SessionSummary;
+use crate::validation::Validate;
+use crate::watchlist::WatchlistConfig;
+use crate::{AppState, MonitoringStatus, SettingChange};
+
+/// 比較の基準とするテキストを設定（空のテキストはエラー）
+pub fn set_reference_text(text: String, state: &Mutex<AppState>) -> Result<(), String> {
+    if text.trim().is_empty() {
+        return Err("基準のテキストが空です".to_string());
+    }
+    update_reference_text(state, Some(text));
+    Ok(())
+}
+
+/// 比較の基準とするテキストを解除
+pub fn clear_reference_text(state: &Mutex<AppState>) {
+    update_reference_text(state, None);
+}
+
+/// 基準のテキストを保存し、監視中なら監視スレッドに送る
+fn update_reference_text(state: &Mutex<AppState>, text: Option<String>) {
+    let mut app_state = lock(state);
+    if app_state.phase == MonitorPhase::Monitoring {
+        if let Some(sender) = &app_state.reference_updates {
+            let _ = sender.send(text.clone());
+        }
+    }
+    app_state.reference_text = text;
+}
+
+/// 監視の状態と現在有効な設定
+pub fn get_status(state: &Mutex<AppState>) -> MonitoringStatus {
+    let app_state = lock(state);
+    let monitor_config = lock(&app_state.monitor_config).clone();
+    let latched_keywords = lock(&app_state.watchlist).latched();
+    MonitoringStatus {
+        is_monitoring: app_state.phase == MonitorPhase::Monitoring,
+        phase: app_state.phase,
+        session_id: app_state.session_id,
+        selected_region: app_state.selected_region,
+        monitor_config,
+        tile_config: app_state.tile_config.clone(),
+        capture_config: app_state.capture_config.clone(),
+        ocr_config: app_state.ocr_config.clone(),
+        latched_keywords,
+        process_guard: app_state.process_guard_config.status(),
+    }
+}
+
+/// 監視処理の統計
+pub fn get_stats(state: &Mutex<AppState>) -> MonitorStats {
+    let stats = lock(state).stats.clone();
+    let snapshot = lock(&stats).clone();
+    snapshot
+}
+
+/// 監視の設定
+pub fn get_monitor_config(state: &Mutex<AppState>) -> MonitorConfig {
+    let config = lock(state).monitor_config.clone();
+    let snapshot = lock(&config).clone();
+    snapshot
+}
+
+/// 監視の設定を変更（不正な設定は変更せずにエラー）
+pub fn set_monitor_config(config: MonitorConfig, state: &Mutex<AppState>) -> Result<SettingChange, String> {
+    config.validate().map_err(|e| e.to_string())?;
+    info!("監視の設定を変更しました: {:?}", config);
+    let shared = lock(state).monitor_config.clone();
+    *lock(&shared) = config;
+    Ok(SettingChange::Applied)
+}
+
+/// キーワード監視の設定
+pub fn get_watchlist_config(state: &Mutex<AppState>) -> WatchlistConfig {
+    let watchlist = lock(state).watchlist.clone();
+    let config = lock(&watchlist).config().clone();
+    config
+}
+
+/// キーワード監視の設定を変更（一致の状態は破棄）
+pub fn set_watchlist_config(config: WatchlistConfig, state: &Mutex<AppState>) -> Result<SettingChange, String> {
+    config.validate().map_err(|e| e.to_string())?;
+    info!("キーワード監視の設定を変更しました: {:?}", config);
+    let watchlist = lock(state).watchlist.clone();
+    lock(&watchlist).set_config(config);
+    Ok(SettingChange::Applied)
+}
+
+/// イベントに付けるサムネイルの最大サイズを変更
+pub fn set_thumbnail_size(width: u32, height: u32, state: &Mutex<AppState>) -> Result<SettingChange, String> {
+    let shared = lock(state).monitor_config.clone();
+    let mut config = lock(&shared).clone();
+    config.thumbnail_width = width;
+    config.thumbnail_height = height;
+    config.validate().map_err(|e| e.to_string())?;
+    info!("サムネイルの最大サイズを変更しました: {}x{}", width, height);
+    *lock(&shared) = config;
+    Ok(SettingChange::Applied)
+}
+
+/// イベント履歴（古い順）
+pub fn get_history(include_info: Option<bool>, state: &Mutex<AppState>) -> Vec<HistoryEntry> {
+    let history = lock(state).history.clone();
+    let entries = lock(&history).entries(include_info.unwrap_or(true));
+    entries
+}
+
+/// 履歴の条件に一致するイベントのページ（新しい順）
+pub fn get_event_page(page: usize, page_size: usize, filter: EventFilter, state: &Mutex<AppState>) -> EventPage {
+    let history = lock(state).history.clone();
+    let page = lock(&history).page(page, page_size, &filter);
+    page
+}
+
+/// 直近の監視セッションの要約（古い順）
+pub fn get_session_summaries(state: &Mutex<AppState>) -> Vec<SessionSummary> {
+    let summaries = lock(state).summaries.clone();
+    let summaries = lock(&summaries).summaries();
+    summaries
+}
+
+/// 監視セッションの実行時の設定
+pub fn get_session_config(session_id: u64, state: &Mutex<AppState>) -> Result<MonitorSnapshot, String> {
+    let summaries = lock(state).summaries.clone();
+    let config = lock(&summaries).config(session_id);
+    config.ok_or_else(|| format!("監視セッション {} の設定が見つかりません", session_id))
+}
+
+/// コマンドを名前で呼び出す（テスト用）
+///
+/// 引数はフロントエンドから渡すのと同じキャメルケースのキーのオブジェクトで、無い引数はnullとして扱う。
+/// 結果はTauriと同じく、成功した値またはエラーをJSONにして返す。
+#[cfg(test)]
+pub fn call(
+    name: &str,
+    args: &serde_json::Value,
+    state: &Mutex<AppState>,
+) -> Result<serde_json::Value, serde_json::Value> {
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use serde_json::Value;
+
+    fn arg<T: DeserializeOwned>(args: &Value, key: &str) -> Result<T, Value> {
+        let value = args.get(key).cloned().unwrap_or(Value::Null);
+        serde_json::from_value(value).map_err(|e| Value::String(format!("引数 {} が正しくありません: {}", key, e)))
+    }
+    fn json<T: Serialize>(value: T) -> Value {
+        serde_json::to_value(value).expect("コマンドの結果をJSONにできません")
+    }
+    fn done<T: Serialize>(result: Result<T, String>) -> Result<Value, Value> {
+        result.map(json).map_err(Value::String)
+    }
+
+    match name {
+        "set_reference_text" => done(set_reference_text(arg(args, "text")?, state)),
+        "clear_reference_text" => {
+            clear_reference_text(state);
+            Ok(Value::Null)
+        }
+        "get_status" => Ok(json(get_status(state))),
+        "get_stats" => Ok(json(get_stats(state))),
+        "get_monitor_config" => Ok(json(get_monitor_config(state))),
+        "set_monitor_config" => done(set_monitor_config(arg(args, "config")?, state)),
+        "get_watchlist_config" => Ok(json(get_watchlist_config(state))),
+        "set_watchlist_config" => done(set_watchlist_config(arg(args, "config")?, state)),
+        "set_thumbnail_size" => done(set_thumbnail_size(arg(args, "width")?, arg(args, "height")?, state)),
+        "get_history" => Ok(json(get_history(arg(args, "includeInfo")?, state))),
+        "get_event_page" => Ok(json(get_event_page(
+            arg(args, "page")?,
+            arg(args, "pageSize")?,
+            arg(args, "filter")?,
+            state,
+        ))),
+        "get_session_summaries" => Ok(json(get_session_summaries(state))),
+        "get_session_config" => done(get_session_config(arg(args, "sessionId")?, state)),
+        _ => panic!("テストから呼び出せないコマンドです: {}", name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::events::TextChangeEvent;
+    use crate::locking::lock;
+    use crate::monitor::MonitorConfig;
+    use crate::phase::MonitorPhase;
+    use crate::test_helpers::TestHarness;
+    use crate::watchlist::WatchlistConfig;
+    use serde_json::{json, Value};
+    use std::sync::mpsc;
+
+    fn new_text(text: &str) -> TextChangeEvent {
+        TextChangeEvent::NewText { text: text.to_string() }
+    }
+
+    #[test]
+    fn default_state_is_idle_and_valid() {
+        let harness = TestHarness::new();
+        assert_eq!(harness.state().validate_session_config(None), Ok(()));
+        let status: Value = harness.call_command("get_status", json!({}));
+        assert_eq!(status["phase"], "idle");
+        assert_eq!(status["is_monitoring"], false);
+        assert_eq!(status["session_id"], 0);
+        assert_eq!(status["selected_region"], Value::Null);
+        assert_eq!(status["latched_keywords"], json!([]));
+        let config: MonitorConfig = serde_json::from_value(status["monitor_config"].clone()).unwrap();
+        assert_eq!(serde_json::to_value(config).unwrap(), serde_json::to_value(MonitorConfig::default()).unwrap());
+    }
+
+    #[test]
+    fn monitor_config_is_applied_only_when_valid() {
+        let harness = TestHarness::new();
+        let mut config: MonitorConfig = harness.call_command("get_monitor_config", json!({}));
+        config.interval_ms = 750;
+        let change: Value = harness.call_command("set_monitor_config", json!({ "config": config }));
+        assert_eq!(change, json!({ "status": "applied" }));
+        let applied: MonitorConfig = harness.call_command("get_monitor_config", json!({}));
+        assert_eq!(applied.interval_ms, 750);
+
+        // 不正な設定は反映しない
+        config.interval_ms = 0;
+        let error = harness.call_command_error("set_monitor_config", json!({ "config": config }));
+        assert!(error.as_str().unwrap().contains("interval_ms"), "{}", error);
+        let kept: MonitorConfig = harness.call_command("get_monitor_config", json!({}));
+        assert_eq!(kept.interval_ms, 750);
+    }
+
+    #[test]
+    fn thumbnail_size_is_validated_with_the_rest_of_the_config() {
+        let harness = TestHarness::new();
+        let _: Value = harness.call_command("set_thumbnail_size", json!({ "width": 320, "height": 80 }));
+        let config: MonitorConfig = harness.call_command("get_monitor_config", json!({}));
+        assert_eq!((config.thumbnail_width, config.thumbnail_height), (320, 80));
+
+        let error = harness.call_command_error("set_thumbnail_size", json!({ "width": 321, "height": 80 }));
+        assert!(error.as_str().unwrap().contains("thumbnail_width は 16 以上 320 以下"), "{}", error);
+        let error = harness.call_command_error("set_thumbnail_size", json!({ "width": "wide", "height": 80 }));
+        assert!(error.as_str().unwrap().contains("引数 width が正しくありません"), "{}", error);
+        let config: MonitorConfig = harness.call_command("get_monitor_config", json!({}));
+        assert_eq!((config.thumbnail_width, config.thumbnail_height), (320, 80));
+    }
+
+    #[test]
+    fn watchlist_config_round_trips() {
+        let harness = TestHarness::new();
+        let config = WatchlistConfig { keywords: vec!["エラー".to_string()], rearm_ms: 5_000 };
+        let _: Value = harness.call_command("set_watchlist_config", json!({ "config": config }));
+        let applied: WatchlistConfig = harness.call_command("get_watchlist_config", json!({}));
+        assert_eq!(applied, config);
+
+        let error = harness.call_command_error("set_watchlist_config", json!({ "config": { "keywords": [" "] } }));
+        assert!(error.as_str().unwrap().contains("空のキーワード"), "{}", error);
+        let kept: WatchlistConfig = harness.call_command("get_watchlist_config", json!({}));
+        assert_eq!(kept, config);
+    }
+
+    #[test]
+    fn reference_text_is_stored_and_sent_to_a_running_session() {
+        let harness = TestHarness::new();
+        let error = harness.call_command_error("set_reference_text", json!({ "text": "  " }));
+        assert_eq!(error, "基準のテキストが空です");
+
+        let () = harness.call_command("set_reference_text", json!({ "text": "基準" }));
+        assert_eq!(harness.state().reference_text.as_deref(), Some("基準"));
+
+        // 監視中は監視スレッドにも送る
+        let (sender, receiver) = mpsc::channel();
+        {
+            let mut state = harness.state();
+            state.phase = MonitorPhase::Monitoring;
+            state.reference_updates = Some(sender);
+        }
+        let () = harness.call_command("set_reference_text", json!({ "text": "次の基準" }));
+        let () = harness.call_command("clear_reference_text", json!({}));
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), vec![Some("次の基準".to_string()), None]);
+        assert_eq!(harness.state().reference_text, None);
+    }
+
+    #[test]
+    fn history_and_pages_reflect_emitted_events() {
+        let harness = TestHarness::new();
+        let mut emitter = harness.emitter();
+        emitter.emit(new_text("一行目"));
+        emitter.info("engine_slow", "認識に時間がかかっています");
+        emitter.emit(new_text("二行目"));
+
+        let history: Vec<Value> = harness.call_command("get_history", json!({}));
+        assert_eq!(history.len(), 3);
+        let history: Vec<Value> = harness.call_command("get_history", json!({ "includeInfo": false }));
+        let texts: Vec<&Value> = history.iter().map(|entry| &entry["event"]["text"]).collect();
+        assert_eq!(texts, [&json!("一行目"), &json!("二行目")]);
+
+        let page: Value = harness.call_command(
+            "get_event_page",
+            json!({ "page": 0, "pageSize": 1, "filter": { "event_types": ["new"] } }),
+        );
+        assert_eq!(page["total"], 2);
+        assert_eq!(page["events"].as_array().unwrap().len(), 1);
+        assert_eq!(page["events"][0]["event"]["text"], "二行目");
+
+        let stats: Value = harness.call_command("get_stats", json!({}));
+        assert_eq!(stats["events_emitted"]["new"], 2);
+        assert_eq!(lock(&harness.state().history).entries(true).len(), 3);
+    }
+
+    #[test]
+    fn unknown_session_config_is_an_error() {
+        let harness = TestHarness::new();
+        let summaries: Vec<Value> = harness.call_command("get_session_summaries", json!({}));
+        assert!(summaries.is_empty());
+        let error = harness.call_command_error("get_session_config", json!({ "sessionId": 3 }));
+        assert_eq!(error, "監視セッション 3 の設定が見つかりません");
+    }
+}
@@ -0,0 +1,339 @@
+// 監視処理の統計とPrometheus形式のメトリクス出力
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write;
+use std::io::{BufRead, BufReader, Write as IoWrite};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::fast_mode::FastModeStats;
+use crate::locking::lock;
+use crate::memory::MemoryUsage;
+use crate::ocr::PreprocessTimings;
+use crate::preprocessing::ImageMetrics;
+
+/// OCR所要時間のヒストグラムの境界（秒）
+const OCR_DURATION_BUCKETS: [f64; 9] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+/// 前処理の所要時間の移動平均に使うフレーム数
+const PREPROCESS_AVERAGE_WINDOW: usize = 100;
+
+/// メトリクスサーバーが停止シグナルを確認する間隔
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// フレームのOCRを省略した理由: 前回と画像がほぼ同じ
+pub const SKIP_HASH_UNCHANGED: &str = "hash-unchanged";
+
+/// フレームのテキストを採用しなかった理由: 内容はあるが読み取れない（クリアとはみなさない）
+pub const SKIP_UNREADABLE: &str = "unreadable";
+
+/// フレームをキャプチャしなかった理由: 拒否リストのプロセスのウィンドウが領域に重なっている
+pub const SKIP_PROCESS_DENIED: &str = "process-denied";
+
+/// フレームをキャプチャしなかった理由: カーソルを重ねている間だけ監視する設定で、カーソルが領域の外にある
+pub const SKIP_CURSOR_OUTSIDE: &str = "cursor-outside";
+
+/// 直近のフレームの所要時間と、時間の予算が尽きて省略した処理
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TickTiming {
+    /// キャプチャから認識までの所要時間（ミリ秒）
+    pub duration_ms: u64,
+    /// 時間の予算（ミリ秒）
+    pub budget_ms: u64,
+    /// 省略した処理（認識精度が下がった理由の確認用）
+    pub skipped_stages: Vec<String>,
+}
+
+/// 所要時間のヒストグラム（Prometheusのhistogramと同じ累積形式）
+#[derive(Debug, Clone, Serialize)]
+pub struct DurationHistogram {
+    /// 各境界以下の観測数（累積）
+    pub buckets: Vec<(f64, u64)>,
+    /// 観測値の合計（秒）
+    pub sum_seconds: f64,
+    /// 観測数
+    pub count: u64,
+}
+
+impl Default for DurationHistogram {
+    fn default() -> Self {
+        Self {
+            buckets: OCR_DURATION_BUCKETS.iter().map(|&bound| (bound, 0)).collect(),
+            sum_seconds: 0.0,
+            count: 0,
+        }
+    }
+}
+
+impl DurationHistogram {
+    /// 観測値を記録
+    pub fn observe(&mut self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        for (bound, count) in &mut self.buckets {
+            if seconds <= *bound {
+                *count += 1;
+            }
+        }
+        self.sum_seconds += seconds;
+        self.count += 1;
+    }
+}
+
+/// 監視処理の統計
+///
+/// get_statsコマンドとメトリクスエンドポイントの両方がこの値を参照する。
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MonitorStats {
+    /// 監視ループの実行回数
+    pub ticks_total: u64,
+    /// 画面の更新通知をきっかけにキャプチャした回数
+    pub ticks_event_triggered: u64,
+    /// 間隔の経過をきっかけにキャプチャした回数
+    pub ticks_timer_triggered: u64,
+    /// 領域全体をOCRした回数
+    pub full_ocr_count: u64,
+    /// 変化した部分のみOCRした回数
+    pub partial_ocr_count: u64,
+    /// OCRの所要時間
+    pub ocr_duration: DurationHistogram,
+    /// 種類ごとのキャプチャ・認識エラー数
+    pub capture_errors: BTreeMap<String, u64>,
+    /// 種類ごとの送信イベント数
+    pub events_emitted: BTreeMap<String, u64>,
+    /// 理由ごとのOCRを省略したフレーム数
+    pub frames_skipped: BTreeMap<String, u64>,
+    /// 処理ごとの時間の予算が尽きて省略した回数
+    pub stages_skipped: BTreeMap<String, u64>,
+    /// 直近に認識したフレームの所要時間
+    pub last_tick: Option<TickTiming>,
+    /// 直近に全体をOCRしたフレームの画像のゆがみの指標
+    pub last_image_metrics: Option<ImageMetrics>,
+    /// 直近に全体をOCRしたフレームで文字の占めた割合（計測しない設定や部分OCRではNone）
+    pub last_text_coverage: Option<f32>,
+    /// 高速モードの指定した頻度と達成した頻度（高速モードでない場合はNone）
+    pub fast_mode: Option<FastModeStats>,
+    /// OCRの子プロセスを再起動した回数
+    pub ocr_worker_restarts: u64,
+    /// 履歴・画像・集計のメモリ使用量
+    pub memory: MemoryUsage,
+    /// 前処理の各ステップの累計所要時間
+    pub preprocess_total: PreprocessTimings,
+    /// 直近のフレームの前処理の所要時間（移動平均用）
+    #[serde(skip)]
+    recent_preprocess: VecDeque<PreprocessTimings>,
+}
+
+impl MonitorStats {
+    /// エラーを記録
+    pub fn record_capture_error(&mut self, kind: &str) {
+        *self.capture_errors.entry(kind.to_string()).or_insert(0) += 1;
+    }
+
+    /// 送信したイベントを記録
+    pub fn record_event(&mut self, event_type: &str) {
+        *self.events_emitted.entry(event_type.to_string()).or_insert(0) += 1;
+    }
+
+    /// OCRを省略したフレームを記録
+    pub fn record_skip(&mut self, reason: &str) {
+        *self.frames_skipped.entry(reason.to_string()).or_insert(0) += 1;
+    }
+
+    /// 認識したフレームの所要時間と省略した処理を記録
+    pub fn record_tick(&mut self, timing: TickTiming) {
+        for stage in &timing.skipped_stages {
+            *self.stages_skipped.entry(stage.clone()).or_insert(0) += 1;
+        }
+        self.last_tick = Some(timing);
+    }
+
+    /// フレームの前処理の所要時間を記録
+    pub fn record_preprocess(&mut self, timings: &PreprocessTimings) {
+        self.preprocess_total.accumulate(timings);
+        if self.recent_preprocess.len() >= PREPROCESS_AVERAGE_WINDOW {
+            self.recent_preprocess.pop_front();
+        }
+        self.recent_preprocess.push_back(*timings);
+    }
+
+    /// 直近100フレームの前処理の平均所要時間
+    pub fn preprocess_average(&self) -> PreprocessTimings {
+        let mut sum = PreprocessTimings::default();
+        for timings in &self.recent_preprocess {
+            sum.accumulate(timings);
+        }
+        sum.divided_by(self.recent_preprocess.len() as u64)
+    }
+
+    /// Prometheusのテキスト形式に変換
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+
+        let _ = writeln!(out, "# HELP ticks_total 監視ループの実行回数");
+        let _ = writeln!(out, "# TYPE ticks_total counter");
+        let _ = writeln!(out, "ticks_total {}", self.ticks_total);
+
+        let _ = writeln!(out, "# HELP ticks_triggered_total きっかけごとのキャプチャ回数");
+        let _ = writeln!(out, "# TYPE ticks_triggered_total counter");
+        let _ = writeln!(out, "ticks_triggered_total{{trigger=\"screen_change\"}} {}", self.ticks_event_triggered);
+        let _ = writeln!(out, "ticks_triggered_total{{trigger=\"timer\"}} {}", self.ticks_timer_triggered);
+
+        let _ = writeln!(out, "# HELP ocr_runs_total 範囲ごとのOCR実行回数");
+        let _ = writeln!(out, "# TYPE ocr_runs_total counter");
+        let _ = writeln!(out, "ocr_runs_total{{scope=\"full\"}} {}", self.full_ocr_count);
+        let _ = writeln!(out, "ocr_runs_total{{scope=\"partial\"}} {}", self.partial_ocr_count);
+
+        let _ = writeln!(out, "# HELP ocr_duration_seconds OCRの所要時間");
+        let _ = writeln!(out, "# TYPE ocr_duration_seconds histogram");
+        for (bound, count) in &self.ocr_duration.buckets {
+            let _ = writeln!(out, "ocr_duration_seconds_bucket{{le=\"{}\"}} {}", bound, count);
+        }
+        let _ = writeln!(out, "ocr_duration_seconds_bucket{{le=\"+Inf\"}} {}", self.ocr_duration.count);
+        let _ = writeln!(out, "ocr_duration_seconds_sum {}", self.ocr_duration.sum_seconds);
+        let _ = writeln!(out, "ocr_duration_seconds_count {}", self.ocr_duration.count);
+
+        let _ = writeln!(out, "# HELP ocr_worker_restarts_total OCRの子プロセスを再起動した回数");
+        let _ = writeln!(out, "# TYPE ocr_worker_restarts_total counter");
+        let _ = writeln!(out, "ocr_worker_restarts_total {}", self.ocr_worker_restarts);
+
+        if let Some(coverage) = self.last_text_coverage {
+            let _ = writeln!(out, "# HELP text_coverage_ratio 直近に認識したフレームで文字の占めた割合");
+            let _ = writeln!(out, "# TYPE text_coverage_ratio gauge");
+            let _ = writeln!(out, "text_coverage_ratio {}", coverage);
+        }
+
+        if let Some(fast_mode) = &self.fast_mode {
+            let _ = writeln!(out, "# HELP fast_mode_rate_hz 高速モードの指定した頻度と達成した頻度（回/秒）");
+            let _ = writeln!(out, "# TYPE fast_mode_rate_hz gauge");
+            let _ = writeln!(out, "fast_mode_rate_hz{{kind=\"requested\"}} {}", fast_mode.requested_rate_hz);
+            if let Some(achieved) = fast_mode.achieved_rate_hz {
+                let _ = writeln!(out, "fast_mode_rate_hz{{kind=\"achieved\"}} {}", achieved);
+            }
+            let _ = writeln!(out, "# HELP fast_mode_degraded 高速モードで代わりの間隔に切り替えたかどうか");
+            let _ = writeln!(out, "# TYPE fast_mode_degraded gauge");
+            let _ = writeln!(out, "fast_mode_degraded {}", u8::from(fast_mode.degraded));
+        }
+
+        let _ = writeln!(out, "# HELP memory_bytes 保持しているデータのおおよそのメモリ使用量");
+        let _ = writeln!(out, "# TYPE memory_bytes gauge");
+        for (component, bytes) in &self.memory.components {
+            let _ = writeln!(out, "memory_bytes{{component=\"{}\"}} {}", component, bytes);
+        }
+        let _ = writeln!(out, "# HELP memory_trims_total メモリ使用量が上限を超えて削った回数");
+        let _ = writeln!(out, "# TYPE memory_trims_total counter");
+        let _ = writeln!(out, "memory_trims_total {}", self.memory.trims);
+
+        let _ = writeln!(out, "# HELP preprocess_step_seconds_total 前処理のステップごとの累計所要時間");
+        let _ = writeln!(out, "# TYPE preprocess_step_seconds_total counter");
+        for (step, duration_us) in self.preprocess_total.steps() {
+            let _ = writeln!(out, "preprocess_step_seconds_total{{step=\"{}\"}} {}", step, duration_us as f64 / 1_000_000.0);
+        }
+
+        write_labeled_counter(&mut out, "capture_errors_total", "種類ごとのエラー数", "kind", &self.capture_errors);
+        write_labeled_counter(&mut out, "events_emitted_total", "種類ごとの送信イベント数", "type", &self.events_emitted);
+        write_labeled_counter(&mut out, "frames_skipped_total", "理由ごとのOCRを省略したフレーム数", "reason", &self.frames_skipped);
+        write_labeled_counter(&mut out, "stages_skipped_total", "時間の予算が尽きて省略した処理の回数", "stage", &self.stages_skipped);
+
+        out
+    }
+}
+
+/// ラベル付きのカウンターを出力
+fn write_labeled_counter(out: &mut String, name: &str, help: &str, label: &str, values: &BTreeMap<String, u64>) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} counter", name);
+    for (value, count) in values {
+        let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+        let _ = writeln!(out, "{}{{{}=\"{}\"}} {}", name, label, escaped, count);
+    }
+}
+
+/// スレッド間で共有する統計
+pub type SharedStats = Arc<Mutex<MonitorStats>>;
+
+/// メトリクスサーバー（停止シグナルを立てると終了する）
+pub struct MetricsServer {
+    shutdown: Arc<AtomicBool>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl MetricsServer {
+    /// 127.0.0.1の指定ポートで /metrics の配信を開始
+    pub fn start(port: u16, stats: SharedStats) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], port)))?;
+        // 停止シグナルを確認できるようノンブロッキングで待ち受ける
+        listener.set_nonblocking(true)?;
+        log::info!("メトリクスサーバーを起動しました: http://{}/metrics", listener.local_addr()?);
+
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let shutdown_signal = shutdown.clone();
+        let handle = thread::spawn(move || {
+            while !shutdown_signal.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        if let Err(e) = respond(stream, &stats) {
+                            log::warn!("メトリクスの応答に失敗: {}", e);
+                        }
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                        thread::sleep(SHUTDOWN_POLL_INTERVAL);
+                    }
+                    Err(e) => log::warn!("メトリクスサーバーの接続エラー: {}", e),
+                }
+            }
+            log::info!("メトリクスサーバーを停止しました");
+        });
+
+        Ok(Self {
+            shutdown,
+            handle: Some(handle),
+        })
+    }
+
+    /// サーバーを停止し、スレッドの終了を待つ
+    pub fn stop(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for MetricsServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// 1件のHTTPリクエストに応答
+fn respond(stream: TcpStream, stats: &Mutex<MonitorStats>) -> std::io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    // ヘッダーは読み飛ばす
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let mut stream = reader.into_inner();
+    let path = request_line.split_whitespace().nth(1).unwrap_or("");
+    if path == "/metrics" {
+        let body = lock(stats).to_prometheus();
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    } else {
+        write!(stream, "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+    }
+}
@@ -0,0 +1,336 @@
+// 監視セッションの要約（監視の終了時に作成する）
+//
+// 終了時に履歴を読み直さずに済むよう、監視中に逐次集計する。
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::clock::SessionClock;
+use crate::monitor::MonitorSnapshot;
+
+/// 保持する要約の数
+const SUMMARY_CAPACITY: usize = 10;
+
+/// OCRの所要時間のパーセンタイル計算に使う標本の数
+const LATENCY_RESERVOIR_SIZE: usize = 256;
+
+/// 重複を除いた行数の集計で記録する行の上限（メモリ使用量を抑えるため）
+const MAX_TRACKED_LINES: usize = 10_000;
+
+/// 最も変化の多かった期間を求める区間の長さ
+const BUSIEST_PERIOD_MS: u64 = 60_000;
+
+/// 最も変化の多かった期間
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct BusiestPeriod {
+    /// 区間の開始（UNIXエポックからのミリ秒）
+    pub start_ms: u64,
+    /// 区間の長さ（ミリ秒）
+    pub duration_ms: u64,
+    /// 区間内の変化イベント数
+    pub change_events: u64,
+}
+
+/// 認識したフレームで文字の占めた割合の集計
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct TextCoverage {
+    /// 最小（0.0-1.0）
+    pub min: f32,
+    /// 平均
+    pub mean: f32,
+    /// 最大
+    pub max: f32,
+    /// 計測したフレームの数
+    pub samples: u64,
+}
+
+/// 監視が終了した理由
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopReason {
+    /// 停止コマンドによる停止
+    Requested,
+    /// 停止ファイルが見つかった
+    Killswitch,
+    /// 初期化の失敗
+    Error,
+    /// モニターの解像度・拡大率が変わり、領域の選択し直しが必要になった
+    RegionInvalidated,
+}
+
+/// 監視セッションの要約
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SessionSummary {
+    /// 監視セッションの識別子
+    pub session_id: u64,
+    /// 開始時刻（UNIXエポックからのミリ秒）
+    pub started_at_ms: u64,
+    /// 終了時刻（UNIXエポックからのミリ秒）
+    pub stopped_at_ms: u64,
+    /// 監視していた時間（ミリ秒）
+    pub duration_ms: u64,
+    /// 終了した理由
+    pub stop_reason: StopReason,
+    /// 変化イベント（新規・変更・クリア・差分）の数
+    pub change_events: u64,
+    /// 認識されたテキストの重複を除いた行数
+    pub unique_lines: u64,
+    /// 行数が記録の上限に達したかどうか（達した場合 unique_lines は下限値）
+    pub unique_lines_capped: bool,
+    /// キャプチャとOCRのエラー数
+    pub error_count: u64,
+    /// OCRの回数
+    pub ocr_count: u64,
+    /// OCRの平均所要時間（ミリ秒）
+    pub ocr_latency_avg_ms: f64,
+    /// OCRの所要時間の中央値（ミリ秒、標本からの推定）
+    pub ocr_latency_p50_ms: f64,
+    /// OCRの所要時間の95パーセンタイル（ミリ秒、標本からの推定）
+    pub ocr_latency_p95_ms: f64,
+    /// 最も変化の多かった1分間（変化が無ければNone）
+    pub busiest_period: Option<BusiestPeriod>,
+    /// 文字の占めた割合（計測したフレームが無ければNone）
+    pub text_coverage: Option<TextCoverage>,
+}
+
+/// 監視中に要約を逐次集計する
+#[derive(Debug)]
+pub struct SessionAggregator {
+    session_id: u64,
+    /// 開始・終了時刻と区間を決める時計（送信器と同じセッションの時計）
+    clock: SessionClock,
+    started_at_ms: u64,
+    change_events: u64,
+    line_hashes: HashSet<u64>,
+    unique_lines_capped: bool,
+    error_count: u64,
+    ocr_count: u64,
+    ocr_total_ms: f64,
+    /// OCRの所要時間の標本（リザーバサンプリング）
+    reservoir: Vec<f64>,
+    rng_state: u64,
+    /// 集計中の区間の開始と変化イベント数
+    current_period: Option<(u64, u64)>,
+    busiest_period: Option<BusiestPeriod>,
+    /// 文字の占めた割合の最小・合計・最大と計測したフレームの数
+    coverage_min: f32,
+    coverage_total: f64,
+    coverage_max: f32,
+    coverage_samples: u64,
+}
+
+impl SessionAggregator {
+    /// 監視セッションの識別子
+    pub fn session_id(&self) -> u64 {
+        self.session_id
+    }
+
+    /// 開始時刻（UNIXエポックからのミリ秒）
+    pub fn started_at_ms(&self) -> u64 {
+        self.started_at_ms
+    }
+
+    /// 監視セッションの開始時に作成
+    pub fn new(session_id: u64, clock: SessionClock) -> Self {
+        let started_at_ms = clock.started_at_ms();
+        Self {
+            session_id,
+            clock,
+            started_at_ms,
+            change_events: 0,
+            line_hashes: HashSet::new(),
+            unique_lines_capped: false,
+            error_count: 0,
+            ocr_count: 0,
+            ocr_total_ms: 0.0,
+            reservoir: Vec::with_capacity(LATENCY_RESERVOIR_SIZE),
+            // 0だと乱数が変化しないため最下位ビットを立てる
+            rng_state: started_at_ms | 1,
+            current_period: None,
+            busiest_period: None,
+            coverage_min: f32::MAX,
+            coverage_total: 0.0,
+            coverage_max: 0.0,
+            coverage_samples: 0,
+        }
+    }
+
+    /// OCRの所要時間を記録
+    pub fn record_ocr(&mut self, duration: Duration) {
+        let duration_ms = duration.as_secs_f64() * 1000.0;
+        self.ocr_count += 1;
+        self.ocr_total_ms += duration_ms;
+
+        if self.reservoir.len() < LATENCY_RESERVOIR_SIZE {
+            self.reservoir.push(duration_ms);
+        } else {
+            let index = self.next_random() % self.ocr_count;
+            if let Some(sample) = self.reservoir.get_mut(index as usize) {
+                *sample = duration_ms;
+            }
+        }
+    }
+
+    /// 認識したフレームで文字の占めた割合を記録
+    pub fn record_text_coverage(&mut self, coverage: f32) {
+        self.coverage_min = self.coverage_min.min(coverage);
+        self.coverage_max = self.coverage_max.max(coverage);
+        self.coverage_total += coverage as f64;
+        self.coverage_samples += 1;
+    }
+
+    /// これまでの文字の占めた割合の集計（計測したフレームが無ければNone）
+    pub fn text_coverage(&self) -> Option<TextCoverage> {
+        (self.coverage_samples > 0).then(|| TextCoverage {
+            min: self.coverage_min,
+            mean: (self.coverage_total / self.coverage_samples as f64) as f32,
+            max: self.coverage_max,
+            samples: self.coverage_samples,
+        })
+    }
+
+    /// キャプチャまたはOCRのエラーを記録
+    pub fn record_error(&mut self) {
+        self.error_count += 1;
+    }
+
+    /// 送信した変化イベントと、その時点の認識テキストを記録
+    pub fn record_changes(&mut self, events: usize, text: Option<&str>) {
+        if events == 0 {
+            return;
+        }
+        self.change_events += events as u64;
+        self.record_period(self.clock.now_ms(), events as u64);
+
+        for line in text.unwrap_or_default().lines() {
+            if self.line_hashes.len() >= MAX_TRACKED_LINES {
+                self.unique_lines_capped = true;
+                break;
+            }
+            let mut hasher = DefaultHasher::new();
+            line.hash(&mut hasher);
+            self.line_hashes.insert(hasher.finish());
+        }
+    }
+
+    /// 集計を終えて要約を作成
+    pub fn finish(mut self, stop_reason: StopReason) -> SessionSummary {
+        self.close_period();
+        let stopped_at_ms = self.clock.now_ms();
+        let text_coverage = self.text_coverage();
+
+        let mut samples = self.reservoir;
+        samples.sort_by(f64::total_cmp);
+        let ocr_latency_avg_ms = if self.ocr_count > 0 {
+            self.ocr_total_ms / self.ocr_count as f64
+        } else {
+            0.0
+        };
+
+        SessionSummary {
+            session_id: self.session_id,
+            started_at_ms: self.started_at_ms,
+            stopped_at_ms,
+            duration_ms: stopped_at_ms.saturating_sub(self.started_at_ms),
+            stop_reason,
+            change_events: self.change_events,
+            unique_lines: self.line_hashes.len() as u64,
+            unique_lines_capped: self.unique_lines_capped,
+            error_count: self.error_count,
+            ocr_count: self.ocr_count,
+            ocr_latency_avg_ms,
+            ocr_latency_p50_ms: percentile(&samples, 0.50),
+            ocr_latency_p95_ms: percentile(&samples, 0.95),
+            busiest_period: self.busiest_period,
+            text_coverage,
+        }
+    }
+
+    /// 1分ごとの区間で変化イベント数を数え、最も多い区間を残す
+    fn record_period(&mut self, timestamp_ms: u64, events: u64) {
+        let start_ms = timestamp_ms - timestamp_ms % BUSIEST_PERIOD_MS;
+        match &mut self.current_period {
+            Some((current_start, count)) if *current_start == start_ms => *count += events,
+            _ => {
+                self.close_period();
+                self.current_period = Some((start_ms, events));
+            }
+        }
+    }
+
+    /// 集計中の区間を閉じる
+    fn close_period(&mut self) {
+        let Some((start_ms, change_events)) = self.current_period.take() else {
+            return;
+        };
+        if self.busiest_period.map_or(true, |busiest| change_events > busiest.change_events) {
+            self.busiest_period = Some(BusiestPeriod {
+                start_ms,
+                duration_ms: BUSIEST_PERIOD_MS,
+                change_events,
+            });
+        }
+    }
+
+    /// 標本の置き換え位置を決める乱数（xorshift）
+    fn next_random(&mut self) -> u64 {
+        let mut x = self.rng_state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.rng_state = x;
+        x
+    }
+}
+
+/// 昇順に並べた標本のパーセンタイル（標本が無ければ0）
+fn percentile(sorted: &[f64], ratio: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let index = ((sorted.len() - 1) as f64 * ratio).round() as usize;
+    sorted[index]
+}
+
+/// 直近の監視セッションの要約
+#[derive(Debug, Default)]
+pub struct SummaryHistory {
+    summaries: VecDeque<SessionSummary>,
+    /// 要約と同じセッションの実行時の設定
+    configs: VecDeque<MonitorSnapshot>,
+}
+
+impl SummaryHistory {
+    /// 要約を追加（上限を超えた古いものは破棄）
+    pub fn push(&mut self, summary: SessionSummary) {
+        if self.summaries.len() >= SUMMARY_CAPACITY {
+            self.summaries.pop_front();
+        }
+        self.summaries.push_back(summary);
+    }
+
+    /// セッションの実行時の設定を追加（上限を超えた古いものは破棄）
+    pub fn push_config(&mut self, config: MonitorSnapshot) {
+        if self.configs.len() >= SUMMARY_CAPACITY {
+            self.configs.pop_front();
+        }
+        self.configs.push_back(config);
+    }
+
+    /// セッションの実行時の設定を取得（破棄済みならNone）
+    pub fn config(&self, session_id: u64) -> Option<MonitorSnapshot> {
+        self.configs.iter().find(|config| config.session_id == session_id).cloned()
+    }
+
+    /// 保持している要約を古い順に取得
+    pub fn summaries(&self) -> Vec<SessionSummary> {
+        self.summaries.iter().cloned().collect()
+    }
+}
+
+/// スレッド間で共有する要約の履歴
+pub type SharedSummaries = Arc<Mutex<SummaryHistory>>;
+
@@ -0,0 +1,450 @@
+// Tesseract言語データ（.traineddata）の管理と自動ダウンロード
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// 既定の認識言語
+pub const DEFAULT_LANGUAGE: &str = "jpn";
+
+/// 言語データの配布元（GitHub上のtessdata_fastリポジトリのリリース 4.1.0）
+///
+/// マニフェストのチェックサムが変わらないよう、更新され得るブランチではなくリリースのタグを指定する。
+const TESSDATA_BASE_URL: &str = "https://github.com/tesseract-ocr/tessdata_fast/raw/4.1.0";
+
+/// ダウンロード時の読み込みバッファサイズ
+const DOWNLOAD_CHUNK_SIZE: usize = 64 * 1024;
+
+/// 言語データのSHA-256マニフェスト（`sha256sum` 形式）
+const CHECKSUM_MANIFEST: &str = include_str!("tessdata_manifest.txt");
+
+/// 言語データファイルのパスを取得
+pub fn traineddata_path(dir: &Path, language: &str) -> PathBuf {
+    dir.join(format!("{}.traineddata", language))
+}
+
+/// ディレクトリに言語データが1つも無いかどうか
+pub fn is_tessdata_empty(dir: &Path) -> bool {
+    match fs::read_dir(dir) {
+        Ok(entries) => !entries
+            .filter_map(|entry| entry.ok())
+            .any(|entry| entry.path().extension().is_some_and(|ext| ext == "traineddata")),
+        // ディレクトリが存在しない場合も空とみなす
+        Err(_) => true,
+    }
+}
+
+/// OCRエンジンに渡す言語データのディレクトリを決定
+///
+/// アプリのディレクトリに指定言語のデータがあればそれを使い、
+/// 無ければNone（システムにインストールされたTesseractの既定パス）を返す。
+pub fn resolve_datapath(app_tessdata_dir: Option<&Path>, language: &str) -> Option<PathBuf> {
+    app_tessdata_dir
+        .filter(|dir| traineddata_path(dir, language).is_file())
+        .map(Path::to_path_buf)
+}
+
+/// 言語データのディレクトリを走査する間隔
+const WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 言語データファイルの大きさと更新時刻
+type FileStamp = (u64, Option<SystemTime>);
+
+/// 言語データのディレクトリを定期的に走査し、追加・更新された .traineddata を検出する
+///
+/// コピー中のファイルを読み込まないよう、変化したファイルは次の走査でも
+/// 大きさと更新時刻が変わっていない場合にのみ報告する。
+pub struct TraineddataWatcher {
+    dir: PathBuf,
+    /// 報告済みのファイル
+    known: BTreeMap<String, FileStamp>,
+    /// 変化を検出し、落ち着くのを待っているファイル
+    pending: BTreeMap<String, FileStamp>,
+    last_scan: Instant,
+}
+
+impl TraineddataWatcher {
+    /// 現在のファイルを既知として監視を開始
+    pub fn new(dir: PathBuf) -> Self {
+        let known = scan_traineddata(&dir);
+        Self {
+            dir,
+            known,
+            pending: BTreeMap::new(),
+            last_scan: Instant::now(),
+        }
+    }
+
+    /// 追加・更新された言語コードを取得（走査の間隔に満たない場合は走査せず空を返す）
+    pub fn poll(&mut self) -> Vec<String> {
+        if self.last_scan.elapsed() < WATCH_INTERVAL {
+            return Vec::new();
+        }
+        self.last_scan = Instant::now();
+
+        let current = scan_traineddata(&self.dir);
+        let mut settled = Vec::new();
+        let mut pending = BTreeMap::new();
+        for (language, stamp) in current {
+            if self.known.get(&language) == Some(&stamp) {
+                continue;
+            }
+            if self.pending.get(&language) == Some(&stamp) {
+                self.known.insert(language.clone(), stamp);
+                settled.push(language);
+            } else {
+                pending.insert(language, stamp);
+            }
+        }
+        self.pending = pending;
+        settled
+    }
+}
+
+/// ディレクトリ内の言語データの言語コード
+pub fn installed_languages(dir: &Path) -> Vec<String> {
+    scan_traineddata(dir).into_keys().collect()
+}
+
+/// ディレクトリ内の言語データファイルの一覧
+fn scan_traineddata(dir: &Path) -> BTreeMap<String, FileStamp> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return BTreeMap::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension().map_or(true, |ext| ext != "traineddata") {
+                return None;
+            }
+            let language = path.file_stem()?.to_str()?.to_string();
+            let metadata = entry.metadata().ok()?;
+            Some((language, (metadata.len(), metadata.modified().ok())))
+        })
+        .collect()
+}
+
+/// 初回セットアップ（言語データのダウンロード）が必要かどうか
+///
+/// アプリのディレクトリが空で、かつシステムの既定パスでも指定言語を
+/// 初期化できない場合にtrueを返す。
+pub fn needs_first_run_setup(app_tessdata_dir: &Path, language: &str) -> bool {
+    is_tessdata_empty(app_tessdata_dir) && tesseract::Tesseract::new(None, Some(language)).is_err()
+}
+
+/// OCRの利用可否の確認結果
+#[derive(Debug, Clone, Serialize)]
+pub struct OcrAvailability {
+    /// 指定言語でOCRを利用できるかどうか
+    pub available: bool,
+    /// 確認した言語コード
+    pub language: String,
+    /// 使用される言語データのディレクトリ（Noneはシステムの既定パス）
+    pub tessdata_dir: Option<PathBuf>,
+    /// 利用者向けのメッセージ
+    pub message: String,
+    /// 利用できない場合の対処方法
+    pub remediation: Option<Remediation>,
+}
+
+/// 利用できない場合の対処方法
+#[derive(Debug, Clone, Serialize)]
+pub struct Remediation {
+    /// 対処のために呼び出すコマンド名
+    pub command: String,
+    /// コマンドに渡す言語コード
+    pub language: String,
+    /// 利用者向けの説明
+    pub description: String,
+}
+
+/// 指定言語でOCRを利用できるか確認する
+pub fn check_availability(app_tessdata_dir: Option<&Path>, language: &str) -> OcrAvailability {
+    let datapath = resolve_datapath(app_tessdata_dir, language);
+    let datapath_str = datapath.as_ref().and_then(|p| p.to_str());
+
+    match tesseract::Tesseract::new(datapath_str, Some(language)) {
+        Ok(_) => OcrAvailability {
+            available: true,
+            language: language.to_string(),
+            tessdata_dir: datapath,
+            message: format!("言語データ（{}）を利用できます", language),
+            remediation: None,
+        },
+        Err(e) => OcrAvailability {
+            available: false,
+            language: language.to_string(),
+            tessdata_dir: datapath,
+            message: format!("言語データ（{}）でTesseractを初期化できません: {}", language, e),
+            // アプリのディレクトリがあり、照合できる言語ならダウンロードで解決できる
+            remediation: app_tessdata_dir.filter(|_| is_downloadable(language)).map(|_| Remediation {
+                command: "download_language_data".to_string(),
+                language: language.to_string(),
+                description: format!("言語データ（{}）をダウンロードしてください", language),
+            }),
+        },
+    }
+}
+
+/// 言語データのダウンロードエラー
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DownloadError {
+    /// 言語コードが不正
+    InvalidLanguage { language: String },
+    /// 接続できない・通信が途切れたなどのネットワークエラー
+    Network { message: String },
+    /// サーバーがエラー応答を返した
+    HttpStatus { status: u16 },
+    /// マニフェストにチェックサムが無く、ダウンロードしたファイルを照合できない
+    UnverifiableLanguage { language: String },
+    /// ダウンロードしたファイルのチェックサムがマニフェストと一致しない
+    ChecksumMismatch { expected: String, actual: String },
+    /// ファイルの書き込みなどのI/Oエラー
+    Io { message: String },
+}
+
+impl fmt::Display for DownloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DownloadError::InvalidLanguage { language } => write!(f, "無効な言語コードです: {}", language),
+            DownloadError::Network { message } => write!(f, "ネットワークエラー: {}", message),
+            DownloadError::HttpStatus { status } => write!(f, "サーバーがエラーを返しました（HTTP {}）", status),
+            DownloadError::UnverifiableLanguage { language } => write!(
+                f,
+                "言語データ（{}）のチェックサムがマニフェストに無いため、ダウンロードできません。配布元から手動で配置してください",
+                language
+            ),
+            DownloadError::ChecksumMismatch { expected, actual } => {
+                write!(f, "チェックサムが一致しません（期待値: {}, 実際: {}）", expected, actual)
+            }
+            DownloadError::Io { message } => write!(f, "ファイル操作エラー: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for DownloadError {}
+
+impl From<io::Error> for DownloadError {
+    fn from(e: io::Error) -> Self {
+        DownloadError::Io { message: e.to_string() }
+    }
+}
+
+/// ダウンロード結果
+#[derive(Debug, Clone, Serialize)]
+pub struct DownloadReport {
+    /// 言語コード
+    pub language: String,
+    /// 保存先のパス
+    pub path: PathBuf,
+    /// ファイルサイズ
+    pub size_bytes: u64,
+    /// ファイルのSHA-256（マニフェストと照合済み）
+    pub sha256: String,
+    /// 中断したダウンロードを再開したかどうか
+    pub resumed: bool,
+}
+
+/// 言語コードの妥当性を確認（ファイル名・URLとして安全な文字のみ許可）
+fn validate_language(language: &str) -> Result<(), DownloadError> {
+    let valid = !language.is_empty()
+        && language.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(DownloadError::InvalidLanguage { language: language.to_string() });
+    }
+    Ok(())
+}
+
+/// 言語データをダウンロードできるか（マニフェストにチェックサムのある言語のみ）
+pub fn is_downloadable(language: &str) -> bool {
+    validate_language(language).is_ok() && expected_checksum(language).is_some()
+}
+
+/// マニフェストから言語データの期待チェックサムを取得
+fn expected_checksum(language: &str) -> Option<String> {
+    checksum_in_manifest(CHECKSUM_MANIFEST, language)
+}
+
+/// `sha256sum` 形式のマニフェストから言語データのチェックサムを探す
+fn checksum_in_manifest(manifest: &str, language: &str) -> Option<String> {
+    let file_name = format!("{}.traineddata", language);
+    manifest
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .find_map(|line| {
+            let mut parts = line.split_whitespace();
+            let checksum = parts.next()?;
+            (parts.next()? == file_name).then(|| checksum.to_ascii_lowercase())
+        })
+}
+
+/// ファイルのSHA-256を16進文字列で計算
+fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; DOWNLOAD_CHUNK_SIZE];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// 言語データをダウンロードして保存する
+///
+/// `on_progress` にはダウンロード済みバイト数と総バイト数（不明な場合は0）が渡される。
+/// 途中のデータは `.part` ファイルに書き込み、前回中断した `.part` があれば
+/// Rangeリクエストで続きから再開する。完了後にマニフェストのSHA-256と照合し、
+/// 一致した場合のみ正式なファイル名にリネームするため、壊れた言語データが残ることはない。
+/// マニフェストに無い言語は照合できないため、ダウンロードせずにエラーを返す。
+pub fn download_language<F>(language: &str, dir: &Path, mut on_progress: F) -> Result<DownloadReport, DownloadError>
+where
+    F: FnMut(u64, u64),
+{
+    validate_language(language)?;
+    let expected = expected_checksum(language)
+        .ok_or_else(|| DownloadError::UnverifiableLanguage { language: language.to_string() })?;
+    fs::create_dir_all(dir)?;
+
+    let url = format!("{}/{}.traineddata", TESSDATA_BASE_URL, language);
+    let part_path = dir.join(format!("{}.traineddata.part", language));
+
+    // 中断したダウンロードがあれば続きから要求
+    let existing_bytes = fs::metadata(&part_path).map(|m| m.len()).unwrap_or(0);
+    let mut request = ureq::get(&url);
+    if existing_bytes > 0 {
+        log::info!("中断したダウンロードを再開します: {} bytes から", existing_bytes);
+        request = request.set("Range", &format!("bytes={}-", existing_bytes));
+    }
+    log::info!("言語データをダウンロードしています: {}", url);
+
+    let response = match request.call() {
+        Ok(response) => response,
+        // 範囲外（既に全体を受信済み）の場合は最初からやり直す
+        Err(ureq::Error::Status(416, _)) => {
+            fs::remove_file(&part_path)?;
+            return download_language(language, dir, on_progress);
+        }
+        Err(ureq::Error::Status(status, _)) => return Err(DownloadError::HttpStatus { status }),
+        Err(e) => return Err(DownloadError::Network { message: e.to_string() }),
+    };
+
+    // 206なら追記、200ならサーバーが再開に未対応のため最初から書き直す
+    let resumed = response.status() == 206;
+    let mut downloaded_bytes = if resumed { existing_bytes } else { 0 };
+    let content_length = response
+        .header("Content-Length")
+        .and_then(|value| value.parse::<u64>().ok())
+        .unwrap_or(0);
+    let total_bytes = if content_length > 0 { downloaded_bytes + content_length } else { 0 };
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(resumed)
+        .truncate(!resumed)
+        .open(&part_path)?;
+
+    let mut reader = response.into_reader();
+    let mut buffer = vec![0u8; DOWNLOAD_CHUNK_SIZE];
+    on_progress(downloaded_bytes, total_bytes);
+
+    loop {
+        // 受信途中の切断はネットワークエラーとして扱い、.partは再開用に残す
+        let read = reader
+            .read(&mut buffer)
+            .map_err(|e| DownloadError::Network { message: e.to_string() })?;
+        if read == 0 {
+            break;
+        }
+        file.write_all(&buffer[..read])?;
+        downloaded_bytes += read as u64;
+        on_progress(downloaded_bytes, total_bytes);
+    }
+
+    file.sync_all()?;
+    drop(file);
+
+    // チェックサムを照合
+    let sha256 = sha256_file(&part_path)?;
+    if expected != sha256 {
+        // 破損したデータから再開しないよう削除する
+        let _ = fs::remove_file(&part_path);
+        return Err(DownloadError::ChecksumMismatch { expected, actual: sha256 });
+    }
+
+    let final_path = traineddata_path(dir, language);
+    fs::rename(&part_path, &final_path)?;
+
+    log::info!("言語データを保存しました: {}（{} bytes）", final_path.display(), downloaded_bytes);
+    Ok(DownloadReport {
+        language: language.to_string(),
+        path: final_path,
+        size_bytes: downloaded_bytes,
+        sha256,
+        resumed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = "# コメント\n\nABCDEF0123  jpn.traineddata\n0123abcd eng.traineddata\n";
+
+    #[test]
+    fn finds_checksum_by_file_name() {
+        assert_eq!(checksum_in_manifest(MANIFEST, "jpn").as_deref(), Some("abcdef0123"));
+        assert_eq!(checksum_in_manifest(MANIFEST, "eng").as_deref(), Some("0123abcd"));
+        assert_eq!(checksum_in_manifest(MANIFEST, "jpn_vert"), None);
+        assert_eq!(checksum_in_manifest("# コメントのみ\n", "jpn"), None);
+    }
+
+    #[test]
+    fn bundled_manifest_has_well_formed_checksums() {
+        assert_eq!(
+            expected_checksum("eng").as_deref(),
+            Some("7d4322bd2a7749724879683fc3912cb542f19906c83bcc1a52132556427170b2")
+        );
+        for line in CHECKSUM_MANIFEST.lines().filter(|line| !line.trim().is_empty() && !line.starts_with('#')) {
+            let checksum = line.split_whitespace().next().unwrap();
+            assert_eq!(checksum.len(), 64, "{}", line);
+            assert!(checksum.chars().all(|c| c.is_ascii_hexdigit()), "{}", line);
+        }
+    }
+
+    #[test]
+    fn only_languages_in_the_manifest_are_downloadable() {
+        assert!(is_downloadable("eng"));
+        assert!(!is_downloadable("zz_not_in_manifest"));
+        assert!(!is_downloadable("../eng"));
+    }
+
+    #[test]
+    fn refuses_to_download_languages_without_checksum() {
+        let dir = std::env::temp_dir().join(format!("tessdata_unverifiable_{}", std::process::id()));
+        let language = "zz_not_in_manifest";
+        assert!(expected_checksum(language).is_none());
+
+        let result = download_language(language, &dir, |_, _| panic!("ダウンロードを始めてはいけません"));
+        assert!(matches!(result, Err(DownloadError::UnverifiableLanguage { .. })));
+        assert!(!traineddata_path(&dir, language).exists());
+    }
+
+    #[test]
+    fn rejects_invalid_language_codes() {
+        for language in ["", "../jpn", "jpn.traineddata"] {
+            assert!(matches!(validate_language(language), Err(DownloadError::InvalidLanguage { .. })));
+        }
+    }
+}
@@ -0,0 +1,185 @@
+// 初期化時にだけ読み込まれる変数を指定して作成するTesseract
+//
+// user_words_file・user_patterns_file は辞書を読み込む初期化（Init）の時にだけ読まれ、初期化後の
+// set_variable では何も変わらない。tesseractクレートのTesseract::newは変数を渡せないため、
+// そのような変数がある場合はC APIのTessBaseAPIInit4で変数を渡して初期化する。
+use anyhow::{bail, Context, Result};
+use image::DynamicImage;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::ptr;
+use tesseract::Tesseract;
+use tesseract_sys::{
+    TessBaseAPI, TessBaseAPICreate, TessBaseAPIDelete, TessBaseAPIGetTsvText, TessBaseAPIGetUTF8Text, TessBaseAPIInit4,
+    TessBaseAPIMeanTextConf, TessBaseAPISetImage, TessBaseAPISetVariable, TessDeleteText, TessOcrEngineMode_OEM_DEFAULT,
+};
+
+/// 認識に使うTesseract（初期化時の変数が無ければtesseractクレートのTesseractを使う）
+pub enum TesseractApi {
+    /// tesseractクレートのTesseract
+    Plain(Tesseract),
+    /// 初期化時の変数を指定したTesseract
+    WithInitVariables(InitVariablesTesseract),
+}
+
+impl TesseractApi {
+    /// Tesseractを初期化（init_variablesは初期化時にだけ読まれる変数の名前と値）
+    pub fn new(datapath: Option<&str>, language: &str, init_variables: &[(&str, &str)]) -> Result<Self> {
+        if init_variables.is_empty() {
+            return Ok(TesseractApi::Plain(Tesseract::new(datapath, Some(language))?));
+        }
+        InitVariablesTesseract::new(datapath, language, init_variables).map(TesseractApi::WithInitVariables)
+    }
+
+    /// 変数を設定
+    pub fn set_variable(self, name: &str, value: &str) -> Result<Self> {
+        Ok(match self {
+            TesseractApi::Plain(tesseract) => TesseractApi::Plain(tesseract.set_variable(name, value)?),
+            TesseractApi::WithInitVariables(mut tesseract) => {
+                tesseract.set_variable(name, value)?;
+                TesseractApi::WithInitVariables(tesseract)
+            }
+        })
+    }
+
+    /// 認識する画像を設定（tesseractクレートのTesseractは保存済みのファイルから、それ以外はimageから読む）
+    pub fn set_image(self, image: &DynamicImage, saved_path: &str) -> Result<Self> {
+        Ok(match self {
+            TesseractApi::Plain(tesseract) => TesseractApi::Plain(tesseract.set_image(saved_path)?),
+            TesseractApi::WithInitVariables(mut tesseract) => {
+                tesseract.set_image(image);
+                TesseractApi::WithInitVariables(tesseract)
+            }
+        })
+    }
+
+    /// テキストを認識
+    pub fn get_text(&mut self) -> Result<String> {
+        match self {
+            TesseractApi::Plain(tesseract) => Ok(tesseract.get_text()?),
+            TesseractApi::WithInitVariables(tesseract) => tesseract.get_text(),
+        }
+    }
+
+    /// 単語ごとの位置と信頼度をTSVで認識
+    pub fn get_tsv_text(&mut self, page: i32) -> Result<String> {
+        match self {
+            TesseractApi::Plain(tesseract) => Ok(tesseract.get_tsv_text(page)?),
+            TesseractApi::WithInitVariables(tesseract) => tesseract.get_tsv_text(page),
+        }
+    }
+
+    /// 単語単位の平均信頼度（0-100）
+    pub fn mean_text_conf(&mut self) -> i32 {
+        match self {
+            TesseractApi::Plain(tesseract) => tesseract.mean_text_conf(),
+            TesseractApi::WithInitVariables(tesseract) => tesseract.mean_text_conf(),
+        }
+    }
+}
+
+/// TessBaseAPIInit4で変数を渡して初期化したTesseract
+pub struct InitVariablesTesseract {
+    handle: *mut TessBaseAPI,
+    /// 設定した画像（Tesseractは画像をコピーしないため、認識が終わるまで保持する）
+    image: Option<image::GrayImage>,
+}
+
+// TessBaseAPIは同時に複数のスレッドから使わなければ、スレッド間で移動してよい
+unsafe impl Send for InitVariablesTesseract {}
+
+impl InitVariablesTesseract {
+    /// 変数を渡して初期化
+    pub fn new(datapath: Option<&str>, language: &str, variables: &[(&str, &str)]) -> Result<Self> {
+        let datapath = datapath.map(CString::new).transpose().context("言語データのパスに使えない文字があります")?;
+        let language = CString::new(language).context("言語に使えない文字があります")?;
+        let names = variables
+            .iter()
+            .map(|(name, _)| CString::new(*name))
+            .collect::<Result<Vec<_>, _>>()
+            .context("変数名に使えない文字があります")?;
+        let values = variables
+            .iter()
+            .map(|(_, value)| CString::new(*value))
+            .collect::<Result<Vec<_>, _>>()
+            .context("変数の値に使えない文字があります")?;
+        // C APIの引数はconstではないが、Tesseractは書き換えない
+        let mut name_ptrs: Vec<*mut c_char> = names.iter().map(|name| name.as_ptr() as *mut c_char).collect();
+        let mut value_ptrs: Vec<*mut c_char> = values.iter().map(|value| value.as_ptr() as *mut c_char).collect();
+
+        // 初期化に失敗した場合もDropで解放する
+        let tesseract = Self {
+            handle: unsafe { TessBaseAPICreate() },
+            image: None,
+        };
+        let result = unsafe {
+            TessBaseAPIInit4(
+                tesseract.handle,
+                datapath.as_ref().map_or(ptr::null(), |path| path.as_ptr()),
+                language.as_ptr(),
+                TessOcrEngineMode_OEM_DEFAULT,
+                ptr::null_mut(),
+                0,
+                name_ptrs.as_mut_ptr(),
+                value_ptrs.as_mut_ptr(),
+                name_ptrs.len(),
+                0,
+            )
+        };
+        if result != 0 {
+            bail!("TessBaseApi failed to initialize");
+        }
+        Ok(tesseract)
+    }
+
+    /// 変数を設定
+    pub fn set_variable(&mut self, name: &str, value: &str) -> Result<()> {
+        let name = CString::new(name).context("変数名に使えない文字があります")?;
+        let value = CString::new(value).context("変数の値に使えない文字があります")?;
+        if unsafe { TessBaseAPISetVariable(self.handle, name.as_ptr(), value.as_ptr()) } == 0 {
+            bail!("TessBaseApi failed to set variable {:?}", name);
+        }
+        Ok(())
+    }
+
+    /// 認識する画像を設定（グレースケールに変換して渡す）
+    pub fn set_image(&mut self, image: &DynamicImage) {
+        let gray = image.to_luma8();
+        let (width, height) = (gray.width() as i32, gray.height() as i32);
+        unsafe { TessBaseAPISetImage(self.handle, gray.as_ptr(), width, height, 1, width) };
+        self.image = Some(gray);
+    }
+
+    /// テキストを認識
+    pub fn get_text(&mut self) -> Result<String> {
+        take_text(unsafe { TessBaseAPIGetUTF8Text(self.handle) })
+    }
+
+    /// 単語ごとの位置と信頼度をTSVで認識
+    pub fn get_tsv_text(&mut self, page: i32) -> Result<String> {
+        take_text(unsafe { TessBaseAPIGetTsvText(self.handle, page) })
+    }
+
+    /// 単語単位の平均信頼度（0-100）
+    pub fn mean_text_conf(&mut self) -> i32 {
+        unsafe { TessBaseAPIMeanTextConf(self.handle) }
+    }
+}
+
+impl Drop for InitVariablesTesseract {
+    fn drop(&mut self) {
+        if !self.handle.is_null() {
+            unsafe { TessBaseAPIDelete(self.handle) };
+        }
+    }
+}
+
+/// Tesseractが確保した文字列をStringにして解放
+fn take_text(text: *mut c_char) -> Result<String> {
+    if text.is_null() {
+        bail!("Tesseractがテキストを返しませんでした");
+    }
+    let result = unsafe { CStr::from_ptr(text) }.to_string_lossy().into_owned();
+    unsafe { TessDeleteText(text) };
+    Ok(result)
+}
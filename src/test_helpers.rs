@@ -0,0 +1,258 @@
+// コマンドと監視処理のテストで共通の準備（アプリケーションの状態と、送信したイベントを記録するウィンドウ）
+//
+// Tauriのコマンドが受け取るStateはTauriの実行環境でしか作れないため、コマンドの中身（state_commands）を
+// 名前で呼び出し、監視スレッドと同じEventEmitterでの送信とあわせてこのハーネスで確かめる。
+use serde::de::DeserializeOwned;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::clock::SessionClock;
+use crate::events::{EventEmitter, EventWindow};
+use crate::AppState;
+use crate::locking::lock;
+use crate::state_commands;
+
+/// ウィンドウに送信されたイベント
+#[derive(Debug, Clone)]
+pub struct RecordedEvent {
+    /// 送信先のチャンネル名
+    pub channel: String,
+    /// 送信したペイロード
+    pub payload: serde_json::Value,
+}
+
+/// 送信されたイベントを記録するウィンドウ（複製したウィンドウは同じ記録を共有する）
+#[derive(Debug, Clone, Default)]
+pub struct MockWindow {
+    events: Arc<Mutex<Vec<RecordedEvent>>>,
+}
+
+impl MockWindow {
+    /// 記録したすべてのイベント（送信した順）
+    pub fn events(&self) -> Vec<RecordedEvent> {
+        lock(&self.events).clone()
+    }
+
+    /// 指定したチャンネルに送信したペイロードを型に変換して取得
+    pub fn payloads<T: DeserializeOwned>(&self, channel: &str) -> Vec<T> {
+        lock(&self.events)
+            .iter()
+            .filter(|event| event.channel == channel)
+            .map(|event| serde_json::from_value(event.payload.clone()).expect("ペイロードの形式が正しくありません"))
+            .collect()
+    }
+
+}
+
+impl EventWindow for MockWindow {
+    fn emit_payload(&self, channel: &str, payload: serde_json::Value) -> tauri::Result<()> {
+        lock(&self.events).push(RecordedEvent {
+            channel: channel.to_string(),
+            payload,
+        });
+        Ok(())
+    }
+}
+
+/// 既定の設定のアプリケーションの状態と、記録するウィンドウ
+pub struct TestHarness {
+    pub state: Mutex<AppState>,
+    pub window: MockWindow,
+}
+
+impl TestHarness {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(AppState::default()),
+            window: MockWindow::default(),
+        }
+    }
+
+    /// アプリケーションの状態のロックを取得
+    pub fn state(&self) -> MutexGuard<'_, AppState> {
+        lock(&self.state)
+    }
+
+    /// コマンドと同じ送信器（送信したイベントはwindowに記録される）
+    pub fn emitter(&self) -> EventEmitter {
+        self.session_emitter(SessionClock::start())
+    }
+
+    /// 監視スレッドと同じ、現在の監視セッションの送信器
+    pub fn session_emitter(&self, clock: SessionClock) -> EventEmitter {
+        let state = self.state();
+        let mut emitter = state.emitter_with_clock(self.window.clone(), clock);
+        emitter.set_session_id(state.session_id);
+        emitter
+    }
+
+    /// コマンドをフロントエンドと同じ名前と引数で呼び出し、結果を型に変換して取得（エラーならパニック）
+    pub fn call_command<T: DeserializeOwned>(&self, name: &str, payload: serde_json::Value) -> T {
+        match state_commands::call(name, &payload, &self.state) {
+            Ok(value) => serde_json::from_value(value).expect("コマンドの結果の形式が正しくありません"),
+            Err(error) => panic!("コマンド {} が失敗しました: {}", name, error),
+        }
+    }
+
+    /// 失敗するはずのコマンドを呼び出し、エラーを取得（成功したらパニック）
+    pub fn call_command_error(&self, name: &str, payload: serde_json::Value) -> serde_json::Value {
+        match state_commands::call(name, &payload, &self.state) {
+            Ok(value) => panic!("コマンド {} が成功しました: {}", name, value),
+            Err(error) => error,
+        }
+    }
+}
+
+/// テストの入力を作る疑似乱数（xorshift、シードを固定して毎回同じ入力にする）
+pub struct Xorshift(pub u64);
+
+impl Xorshift {
+    /// 0以上n未満の値
+    pub fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+
+    /// min以上max以下の値
+    pub fn between(&mut self, min: i32, max: i32) -> i32 {
+        min + self.below((max - min) as usize + 1) as i32
+    }
+
+    /// 候補から1つ選ぶ
+    pub fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.below(items.len())]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::TextChangeEvent;
+    use crate::phase::MonitorPhase;
+    use crate::schema::v1;
+
+    fn new_text(text: &str) -> TextChangeEvent {
+        TextChangeEvent::NewText { text: text.to_string() }
+    }
+
+    #[test]
+    fn emitted_events_reach_window_and_history() {
+        let harness = TestHarness::new();
+        let mut emitter = harness.emitter();
+        let first = emitter.emit(new_text("一行目"));
+        let second = emitter.emit(TextChangeEvent::TextChanged {
+            old: "一行目".to_string(),
+            new: "二行目".to_string(),
+        });
+        assert_eq!(second, first + 1);
+
+        let payloads: Vec<v1::TextChangedPayload> = harness.window.payloads("text-changed");
+        assert_eq!(payloads.len(), 2);
+        assert_eq!(payloads[0].sequence, Some(first));
+        assert_eq!(payloads[0].schema_version, 1);
+        assert_eq!(payloads[1].event, v1::Event::from(&TextChangeEvent::TextChanged {
+            old: "一行目".to_string(),
+            new: "二行目".to_string(),
+        }));
+
+        let state = harness.state();
+        assert_eq!(lock(&state.history).entries(true).len(), 2);
+        assert_eq!(lock(&state.stats).events_emitted.get("changed"), Some(&1));
+    }
+
+    #[test]
+    fn throttled_events_are_sent_as_one_batch() {
+        let harness = TestHarness::new();
+        let mut emitter = harness.emitter();
+        emitter.set_max_batch_size(3);
+        // 連続して送信できる20件を超えた5件が保留され、まとめには新しい3件が残る
+        let sequences: Vec<u64> = (0..25).map(|index| emitter.emit(new_text(&format!("行 {}", index)))).collect();
+        emitter.flush_all();
+
+        let payloads: Vec<v1::TextChangedPayload> = harness.window.payloads("text-changed");
+        assert_eq!(payloads.len(), 21);
+        let sent: Vec<Option<u64>> = payloads[..20].iter().map(|payload| payload.sequence).collect();
+        assert_eq!(sent, sequences[..20].iter().copied().map(Some).collect::<Vec<_>>());
+
+        let batch = &payloads[20];
+        assert_eq!(batch.sequence, None);
+        let v1::Event::Batch { events, total_dropped } = &batch.event else {
+            panic!("まとめのイベントではありません: {:?}", batch.event);
+        };
+        assert_eq!(*total_dropped, 5);
+        let held: Vec<(Option<u64>, &v1::Event)> = events.iter().map(|payload| (payload.sequence, &payload.event)).collect();
+        let expected: Vec<(Option<u64>, v1::Event)> = (22..25)
+            .map(|index| (Some(sequences[index]), v1::Event::from(&new_text(&format!("行 {}", index)))))
+            .collect();
+        assert_eq!(held, expected.iter().map(|(sequence, event)| (*sequence, event)).collect::<Vec<_>>());
+
+        // 抑制したイベントも履歴には個別に残る
+        assert_eq!(lock(&harness.state().history).entries(true).len(), 25);
+        // 送信した後は保留が無くなる
+        emitter.flush_all();
+        assert_eq!(harness.window.events().len(), 21);
+    }
+
+    #[test]
+    fn info_events_are_rate_limited_per_code() {
+        let harness = TestHarness::new();
+        let mut emitter = harness.emitter();
+        emitter.info("engine_slow", "認識に時間がかかっています");
+        emitter.info("engine_slow", "認識に時間がかかっています");
+        emitter.info("other", "別の通知");
+        assert_eq!(harness.window.events().len(), 2);
+    }
+
+    #[test]
+    fn errors_and_lifecycle_use_their_channels() {
+        let harness = TestHarness::new();
+        harness.state().session_id = 7;
+        let emitter = harness.session_emitter(SessionClock::start());
+        emitter.lifecycle(v1::LifecycleState::Started);
+        emitter.error("キャプチャに失敗しました".to_string());
+
+        let lifecycle: Vec<v1::LifecyclePayload> = harness.window.payloads("lifecycle");
+        assert_eq!(lifecycle.len(), 1);
+        assert_eq!(lifecycle[0].session_id, 7);
+        assert_eq!(lifecycle[0].state, v1::LifecycleState::Started);
+        let errors: Vec<v1::ErrorPayload> = harness.window.payloads("error");
+        assert_eq!(errors[0].message, "キャプチャに失敗しました");
+    }
+
+    #[test]
+    fn releasing_a_session_returns_to_idle() {
+        let harness = TestHarness::new();
+        let mut state = harness.state();
+        state.phase = MonitorPhase::Monitoring;
+        crate::release_session(&mut state);
+        assert_eq!(state.phase, MonitorPhase::Idle);
+        assert!(state.monitor_handle.is_none());
+    }
+
+    #[test]
+    fn state_lock_recovers_after_a_panic_while_held() {
+        let harness = TestHarness::new();
+        std::thread::scope(|scope| {
+            let panicked = scope
+                .spawn(|| {
+                    let mut state = harness.state();
+                    state.phase = MonitorPhase::Monitoring;
+                    panic!("ロックを保持したままパニック");
+                })
+                .join();
+            assert!(panicked.is_err());
+        });
+        assert!(harness.state.is_poisoned());
+
+        // パニックの前の更新は残り、汚染も解除されてコマンドと同じ操作が続けられる
+        let mut state = harness.state();
+        assert_eq!(state.phase, MonitorPhase::Monitoring);
+        crate::release_session(&mut state);
+        assert_eq!(state.validate_session_config(None), Ok(()));
+        drop(state);
+        assert!(!harness.state.is_poisoned());
+        harness.emitter().emit(new_text("回復後"));
+        assert_eq!(lock(&harness.state().history).entries(true).len(), 1);
+    }
+}
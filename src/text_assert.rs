@@ -0,0 +1,136 @@
+// 領域のテキストの確認（UIの自動テスト向け、監視とは独立して実行する）
+//
+// 一定の間隔でキャプチャ・認識を繰り返し、期待するテキストが表示されるか、
+// テキストが変わらないままかを確かめる。期限までに条件を満たさない場合もエラーにはしない。
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::capture::{CaptureConfig, CaptureRegion, ScreenCapture};
+use crate::monitor::texts_equivalent;
+use crate::ocr::{OcrConfig, OcrEngine};
+use crate::process_guard::{self, ProcessGuardConfig};
+
+/// キャプチャ・認識の間隔
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 1回の確認で認識を試す回数
+const READ_ATTEMPTS: u32 = 3;
+
+/// 待つ時間の上限（ミリ秒）
+pub const MAX_WAIT_MS: u64 = 600_000;
+
+/// 確認の結果
+#[derive(Debug, Clone, Serialize)]
+pub struct AssertResult {
+    /// 条件を満たしたかどうか
+    pub matched: bool,
+    /// 最後に認識したテキスト（テキストが変わった場合は変わった後のテキスト）
+    pub actual_text: String,
+    /// 確認にかかった時間（ミリ秒）
+    pub elapsed_ms: u64,
+}
+
+/// 1つの領域を繰り返し認識する
+pub struct TextProbe {
+    capture: ScreenCapture,
+    /// キャプチャの前に確認する拒否リスト
+    process_guard: ProcessGuardConfig,
+    engine: OcrEngine,
+}
+
+impl TextProbe {
+    /// 領域と認識の設定を指定して作成（監視と同じ前処理を使う）
+    pub fn new(
+        region: CaptureRegion,
+        capture_config: &CaptureConfig,
+        process_guard: ProcessGuardConfig,
+        tessdata_dir: Option<PathBuf>,
+        language: &str,
+        ocr_config: OcrConfig,
+    ) -> Result<Self> {
+        let mut engine = OcrEngine::with_language(tessdata_dir, language)?;
+        engine.set_config(ocr_config);
+        Ok(Self {
+            capture: ScreenCapture::with_config(region, capture_config),
+            process_guard,
+            engine,
+        })
+    }
+
+    /// テキストが正規表現に一致するまで待つ（timeoutを過ぎたら一致しなかった結果を返す）
+    pub fn wait_for_match(&self, pattern: &Regex, timeout: Duration) -> Result<AssertResult> {
+        check_wait("timeout_ms", timeout)?;
+        let start = Instant::now();
+        loop {
+            let poll_start = Instant::now();
+            let text = self.read()?;
+            let matched = pattern.is_match(&text);
+            if matched || start.elapsed() >= timeout {
+                return Ok(result(matched, text, start));
+            }
+            sleep_until_next_poll(poll_start, start + timeout);
+        }
+    }
+
+    /// テキストが stable_for の間変わらないか確かめる（変わった時点で変わらなかった結果を返す）
+    pub fn wait_for_stable(&self, stable_for: Duration) -> Result<AssertResult> {
+        check_wait("stable_for_ms", stable_for)?;
+        let start = Instant::now();
+        let mut poll_start = start;
+        let baseline = self.read()?;
+        while start.elapsed() < stable_for {
+            sleep_until_next_poll(poll_start, start + stable_for);
+            poll_start = Instant::now();
+            let text = self.read()?;
+            // 空白の違いだけは変化とみなさない（監視と同じ判定）
+            if !texts_equivalent(&baseline, &text) {
+                return Ok(result(false, text, start));
+            }
+        }
+        Ok(result(true, baseline, start))
+    }
+
+    /// キャプチャして認識（認識に失敗した場合はキャプチャからやり直し、READ_ATTEMPTS 回失敗したらエラー）
+    ///
+    /// 失敗を空のテキストとすると、一時的な失敗がテキストの変化や `^$` への一致として扱われるため。
+    fn read(&self) -> Result<String> {
+        let mut attempt = 1;
+        loop {
+            let image = process_guard::guarded_capture(&self.process_guard, &self.capture).context("キャプチャに失敗しました")?;
+            match self.engine.recognize_detailed(&image) {
+                Ok(result) => return Ok(result.text),
+                Err(e) if attempt < READ_ATTEMPTS => {
+                    log::debug!("テキストの確認で認識に失敗しました（{}回目、やり直します）: {}", attempt, e);
+                    attempt += 1;
+                }
+                Err(e) => return Err(e.context(format!("{}回続けて認識に失敗しました", READ_ATTEMPTS))),
+            }
+        }
+    }
+}
+
+/// 待つ時間が上限以内か確認
+fn check_wait(name: &str, wait: Duration) -> Result<()> {
+    if wait.as_millis() > u128::from(MAX_WAIT_MS) {
+        bail!("{} は {} 以下で指定してください: {}", name, MAX_WAIT_MS, wait.as_millis());
+    }
+    Ok(())
+}
+
+/// 次の認識まで待つ（期限を過ぎないよう短くする）
+fn sleep_until_next_poll(poll_start: Instant, deadline: Instant) {
+    let next = (poll_start + POLL_INTERVAL).min(deadline);
+    thread::sleep(next.saturating_duration_since(Instant::now()));
+}
+
+fn result(matched: bool, actual_text: String, start: Instant) -> AssertResult {
+    AssertResult {
+        matched,
+        actual_text,
+        elapsed_ms: start.elapsed().as_millis() as u64,
+    }
+}
@@ -0,0 +1,93 @@
+// 最新の認識テキストをHTTPでポーリングするツール向けの配信の設定
+//
+// サーバー本体はREST APIと同じくrestフィーチャー有効時のみ利用できる（rest.rsのserve_text）。
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, SocketAddr};
+
+use crate::validation::{Validate, Validator};
+
+/// テキスト配信サーバーの設定
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TextServerConfig {
+    /// 監視の開始・停止に合わせてサーバーも起動・停止するかどうか
+    pub enabled: bool,
+    /// 待ち受けるアドレス
+    pub bind_address: String,
+    /// 待ち受けるポート
+    pub port: u16,
+    /// ループバック以外のアドレスでの待ち受けを許可するかどうか
+    pub allow_remote: bool,
+}
+
+impl Default for TextServerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind_address: "127.0.0.1".to_string(),
+            port: 8766,
+            allow_remote: false,
+        }
+    }
+}
+
+impl TextServerConfig {
+    /// 待ち受けるソケットアドレス（検証済みの設定でのみ使う）
+    #[cfg_attr(not(feature = "rest"), allow(dead_code))]
+    pub fn socket_addr(&self) -> Option<SocketAddr> {
+        let address: IpAddr = self.bind_address.parse().ok()?;
+        Some(SocketAddr::new(address, self.port))
+    }
+}
+
+impl Validate for TextServerConfig {
+    const PREFIX: &'static str = "text_server";
+
+    fn check(&self, validator: &mut Validator) {
+        validator.range("port", self.port, 1, u16::MAX);
+        match self.bind_address.parse::<IpAddr>() {
+            Ok(address) if !address.is_loopback() && !self.allow_remote => validator.invalid(
+                "bind_address",
+                format!("{} はループバックではありません（allow_remoteを指定してください）", address),
+            ),
+            Ok(_) => {}
+            Err(e) => validator.invalid("bind_address", e),
+        }
+    }
+}
+
+/// 起動中のテキスト配信サーバー（破棄すると停止する）
+#[cfg(feature = "rest")]
+pub struct TextServer {
+    task: tauri::async_runtime::JoinHandle<()>,
+    /// 監視の開始時に起動したかどうか（監視の停止時に一緒に停止する）
+    pub with_monitoring: bool,
+}
+
+#[cfg(feature = "rest")]
+impl TextServer {
+    /// 設定のアドレスで待ち受けを開始（バインドに失敗した場合はエラー）
+    pub fn start(config: &TextServerConfig, app: tauri::AppHandle, with_monitoring: bool) -> anyhow::Result<Self> {
+        use anyhow::Context;
+
+        config.validate().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let address = config.socket_addr().context("待ち受けるアドレスが正しくありません")?;
+        let listener = std::net::TcpListener::bind(address)
+            .with_context(|| format!("{} で待ち受けできません", address))?;
+
+        let task = tauri::async_runtime::spawn(async move {
+            if let Err(e) = crate::rest::serve_text(app, listener).await {
+                log::error!("テキスト配信サーバーエラー: {}", e);
+            }
+        });
+        Ok(Self { task, with_monitoring })
+    }
+}
+
+#[cfg(feature = "rest")]
+impl Drop for TextServer {
+    fn drop(&mut self) {
+        self.task.abort();
+        log::info!("テキスト配信サーバーを停止しました");
+    }
+}
@@ -0,0 +1,318 @@
+// タイル単位の変化検出による部分OCR
+use anyhow::Result;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
+use std::sync::Mutex;
+
+use crate::locking::lock;
+use crate::ocr::{OcrEngine, OcrLine};
+use crate::ocr_stats;
+use crate::stats::{MonitorStats, SKIP_HASH_UNCHANGED};
+use crate::validation::{Validate, Validator};
+
+/// タイル分割の設定
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TileConfig {
+    /// タイル単位の変化検出を行うかどうか
+    pub enabled: bool,
+    /// 横方向の分割数
+    pub grid_cols: u32,
+    /// 縦方向の分割数
+    pub grid_rows: u32,
+    /// 部分OCRを行う変化タイルの割合の上限（超えた場合は領域全体をOCR）
+    pub max_changed_ratio: f32,
+}
+
+impl Default for TileConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            grid_cols: 4,
+            grid_rows: 4,
+            max_changed_ratio: 0.5,
+        }
+    }
+}
+
+impl Validate for TileConfig {
+    const PREFIX: &'static str = "tile";
+
+    fn check(&self, validator: &mut Validator) {
+        validator.range("grid_cols", self.grid_cols, 1, 32);
+        validator.range("grid_rows", self.grid_rows, 1, 32);
+        validator.range("max_changed_ratio", self.max_changed_ratio, 0.0, 1.0);
+    }
+}
+
+/// 画像上の矩形（ピクセル座標）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ImageRect {
+    /// 右端の座標（含まない）
+    fn right(&self) -> u32 {
+        self.x + self.width
+    }
+
+    /// 下端の座標（含まない）
+    fn bottom(&self) -> u32 {
+        self.y + self.height
+    }
+
+    /// 2つの矩形が重なっているかどうか
+    pub fn intersects(&self, other: &ImageRect) -> bool {
+        self.x < other.right() && other.x < self.right() && self.y < other.bottom() && other.y < self.bottom()
+    }
+
+    /// 2つの矩形を含む最小の矩形
+    pub fn union(&self, other: &ImageRect) -> ImageRect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        ImageRect {
+            x,
+            y,
+            width: self.right().max(other.right()) - x,
+            height: self.bottom().max(other.bottom()) - y,
+        }
+    }
+
+    /// 画像サイズに収まるように切り詰める
+    fn clamp_to(&self, width: u32, height: u32) -> ImageRect {
+        let x = self.x.min(width);
+        let y = self.y.min(height);
+        ImageRect {
+            x,
+            y,
+            width: self.right().min(width) - x,
+            height: self.bottom().min(height) - y,
+        }
+    }
+}
+
+/// 前フレームとの比較結果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileChange {
+    /// 比較対象が無い、またはサイズが変わった
+    Initial,
+    /// 変化なし
+    Unchanged,
+    /// 一部のタイルのみ変化（変化したタイル全体を囲む矩形）
+    Partial(ImageRect),
+    /// 多くのタイルが変化
+    Full,
+}
+
+/// タイルごとのハッシュを前フレームと比較して変化を検出する
+#[derive(Debug)]
+pub struct TileChangeDetector {
+    config: TileConfig,
+    /// 前フレームの画像サイズとタイルごとのハッシュ
+    previous: Option<((u32, u32), Vec<u64>)>,
+}
+
+impl TileChangeDetector {
+    /// 新しい検出器を作成
+    pub fn new(config: TileConfig) -> Self {
+        Self { config, previous: None }
+    }
+
+    /// 画像をタイルに分割した矩形の一覧（行優先）
+    fn tiles(&self, width: u32, height: u32) -> Vec<ImageRect> {
+        let cols = self.config.grid_cols.clamp(1, width.max(1));
+        let rows = self.config.grid_rows.clamp(1, height.max(1));
+        let mut tiles = Vec::with_capacity((cols * rows) as usize);
+        for row in 0..rows {
+            for col in 0..cols {
+                // 割り切れない分は右端・下端のタイルに含める
+                let x = width * col / cols;
+                let y = height * row / rows;
+                tiles.push(ImageRect {
+                    x,
+                    y,
+                    width: width * (col + 1) / cols - x,
+                    height: height * (row + 1) / rows - y,
+                });
+            }
+        }
+        tiles
+    }
+
+    /// 前フレームと比較して変化を検出し、現在のフレームを記録
+    pub fn detect(&mut self, image: &DynamicImage) -> TileChange {
+        let rgba = image.to_rgba8();
+        let size = (rgba.width(), rgba.height());
+        let tiles = self.tiles(size.0, size.1);
+
+        let hashes: Vec<u64> = tiles
+            .iter()
+            .map(|tile| {
+                let mut hasher = DefaultHasher::new();
+                for y in tile.y..tile.bottom() {
+                    for x in tile.x..tile.right() {
+                        hasher.write(&rgba.get_pixel(x, y).0);
+                    }
+                }
+                hasher.finish()
+            })
+            .collect();
+
+        let change = match &self.previous {
+            Some((previous_size, previous_hashes)) if *previous_size == size => {
+                let changed: Vec<&ImageRect> = tiles
+                    .iter()
+                    .zip(hashes.iter().zip(previous_hashes))
+                    .filter(|(_, (current, previous))| current != previous)
+                    .map(|(tile, _)| tile)
+                    .collect();
+
+                if changed.is_empty() {
+                    TileChange::Unchanged
+                } else if changed.len() as f32 / tiles.len() as f32 > self.config.max_changed_ratio {
+                    TileChange::Full
+                } else {
+                    let bounds = changed.iter().skip(1).fold(*changed[0], |acc, tile| acc.union(tile));
+                    TileChange::Partial(bounds)
+                }
+            }
+            _ => TileChange::Initial,
+        };
+
+        self.previous = Some((size, hashes));
+        change
+    }
+}
+
+/// タイル単位の変化検出を使って、変化した部分のみを再認識する
+pub struct TiledRecognizer {
+    detector: TileChangeDetector,
+    /// 前回の認識結果（行の位置が取得できなかった場合はNone）
+    cached_lines: Option<Vec<OcrLine>>,
+    /// 前回の認識テキスト
+    cached_text: Option<String>,
+}
+
+impl TiledRecognizer {
+    /// 新しいTiledRecognizerを作成
+    pub fn new(config: TileConfig) -> Self {
+        Self {
+            detector: TileChangeDetector::new(config),
+            cached_lines: None,
+            cached_text: None,
+        }
+    }
+
+    /// 画像を認識し、領域全体のテキストを返す
+    ///
+    /// 幾何補正はタイルに分ける前のフレーム全体に1回だけ行い、変化の検出・切り出し・行の位置は
+    /// すべて補正後の画像の座標で扱う。
+    pub fn recognize(&mut self, engine: &OcrEngine, image: &DynamicImage, stats: &Mutex<MonitorStats>) -> Result<String> {
+        let corrected = engine.correct_geometry(image)?;
+        let image = corrected.as_ref();
+        let change = self.detector.detect(image);
+
+        // 変化が無ければ前回の結果をそのまま使う
+        if let (TileChange::Unchanged, Some(text)) = (change, &self.cached_text) {
+            lock(stats).record_skip(SKIP_HASH_UNCHANGED);
+            return Ok(text.clone());
+        }
+
+        // 部分OCRと、部分OCRができなかった場合の全体のOCRは、OCRの統計には1回の認識として数える
+        ocr_stats::recognition(
+            || {
+                if let (TileChange::Partial(changed), Some(_)) = (change, &self.cached_text) {
+                    if let Some(text) = self.recognize_partial(engine, image, changed)? {
+                        log::debug!("部分OCR: {:?}", changed);
+                        lock(stats).partial_ocr_count += 1;
+                        return Ok(text);
+                    }
+                }
+                self.recognize_full(engine, image, stats)
+            },
+            |text| (ocr_stats::count_characters(text), None),
+        )
+    }
+
+    /// 領域全体を認識してキャッシュを更新
+    fn recognize_full(&mut self, engine: &OcrEngine, image: &DynamicImage, stats: &Mutex<MonitorStats>) -> Result<String> {
+        lock(stats).full_ocr_count += 1;
+
+        let text = match engine.recognize_lines_corrected(image) {
+            Ok(lines) => {
+                let text = join_lines(&lines);
+                self.cached_lines = Some(lines);
+                text
+            }
+            Err(e) => {
+                // 行の位置が取れない場合は通常の認識を行い、次回も全体をOCRする
+                log::warn!("行単位の認識に失敗したため通常の認識を行います: {}", e);
+                self.cached_lines = None;
+                engine.recognize_detailed_corrected(image)?.text
+            }
+        };
+
+        self.cached_text = Some(text.clone());
+        Ok(text)
+    }
+
+    /// 変化した部分のみを認識してキャッシュに反映（行の位置が無い場合はNone）
+    fn recognize_partial(&mut self, engine: &OcrEngine, image: &DynamicImage, changed: ImageRect) -> Result<Option<String>> {
+        let Some(lines) = &self.cached_lines else {
+            return Ok(None);
+        };
+
+        // 変化部分にかかる行は行全体を読み直す
+        let target = lines
+            .iter()
+            .filter(|line| line.bbox.intersects(&changed))
+            .fold(changed, |acc, line| acc.union(&line.bbox))
+            .clamp_to(image.width(), image.height());
+        if target.width == 0 || target.height == 0 {
+            return Ok(None);
+        }
+
+        let crop = image.crop_imm(target.x, target.y, target.width, target.height);
+        let new_lines = match engine.recognize_lines_corrected(&crop) {
+            Ok(new_lines) => new_lines,
+            Err(e) => {
+                log::warn!("部分OCRに失敗したため全体をOCRします: {}", e);
+                return Ok(None);
+            }
+        };
+
+        // 読み直した範囲の行を置き換え、位置順に並べ直す
+        let mut merged: Vec<OcrLine> = lines
+            .iter()
+            .filter(|line| !line.bbox.intersects(&target))
+            .cloned()
+            .collect();
+        merged.extend(new_lines.into_iter().map(|mut line| {
+            line.bbox.x += target.x;
+            line.bbox.y += target.y;
+            line
+        }));
+        merged.sort_by_key(|line| (line.bbox.y, line.bbox.x));
+
+        let text = join_lines(&merged);
+        self.cached_lines = Some(merged);
+        self.cached_text = Some(text.clone());
+        Ok(Some(text))
+    }
+}
+
+/// 行の一覧を改行区切りのテキストにする
+fn join_lines(lines: &[OcrLine]) -> String {
+    lines
+        .iter()
+        .map(|line| line.text.as_str())
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,210 @@
+// キャプチャの幾何補正（曲面のディスプレイや斜めから撮影した映像のゆがみを戻す）
+use anyhow::{bail, Result};
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::tiling::ImageRect;
+
+/// 逆行列を求められないとみなす行列式の大きさ
+const SINGULAR_EPSILON: f64 = 1e-12;
+
+/// キャプチャに適用する幾何変換（キャプチャ上の座標を補正後の座標に写す行列）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureTransform {
+    /// 補正しない
+    #[default]
+    #[serde(rename = "none")]
+    None_,
+    /// 射影変換（3x3の同次座標の行列）
+    Homography([[f64; 3]; 3]),
+    /// アフィン変換（2x3の行列）
+    Affine([[f64; 3]; 2]),
+}
+
+impl CaptureTransform {
+    /// 3x3の同次座標の行列（補正しない場合はNone）
+    fn matrix(&self) -> Option<[[f64; 3]; 3]> {
+        match self {
+            CaptureTransform::None_ => None,
+            CaptureTransform::Homography(matrix) => Some(*matrix),
+            CaptureTransform::Affine([row0, row1]) => Some([*row0, *row1, [0.0, 0.0, 1.0]]),
+        }
+    }
+
+    /// 逆変換を求められるかどうか（設定の検証用）
+    pub fn is_invertible(&self) -> bool {
+        self.matrix().map_or(true, |matrix| invert(&matrix).is_some())
+    }
+
+    /// 画像を補正（出力は入力と同じ大きさで、元の画像の外側は端の画素で埋める）
+    pub fn apply(&self, image: &DynamicImage) -> Result<DynamicImage> {
+        let Some(matrix) = self.matrix() else {
+            return Ok(image.clone());
+        };
+        let Some(inverse) = invert(&matrix) else {
+            bail!("逆変換を求められない変換行列です");
+        };
+
+        // 補正後の各画素に対応するキャプチャ上の位置をバイリニア補間で読む
+        let source = image.to_rgba8();
+        let (width, height) = image.dimensions();
+        let output = RgbaImage::from_fn(width, height, |x, y| {
+            let (u, v) = project(&inverse, x as f64, y as f64);
+            sample_bilinear(&source, u, v)
+        });
+        Ok(DynamicImage::ImageRgba8(output))
+    }
+
+    /// 補正後の画像上の矩形をキャプチャ上の矩形に戻す（4隅を逆変換した外接矩形を画像の大きさに収める）
+    pub fn unmap_rect(&self, rect: ImageRect, width: u32, height: u32) -> ImageRect {
+        let Some(inverse) = self.matrix().and_then(|matrix| invert(&matrix)) else {
+            return rect;
+        };
+        let (left, top) = (rect.x as f64, rect.y as f64);
+        let (right, bottom) = (left + rect.width as f64, top + rect.height as f64);
+        let corners = [(left, top), (right, top), (left, bottom), (right, bottom)].map(|(x, y)| project(&inverse, x, y));
+
+        let clamp = |value: f64, max: u32| if value.is_finite() { value.clamp(0.0, max as f64) } else { 0.0 };
+        let min_x = clamp(corners.iter().map(|c| c.0).fold(f64::INFINITY, f64::min), width).floor() as u32;
+        let min_y = clamp(corners.iter().map(|c| c.1).fold(f64::INFINITY, f64::min), height).floor() as u32;
+        let max_x = clamp(corners.iter().map(|c| c.0).fold(f64::NEG_INFINITY, f64::max), width).ceil() as u32;
+        let max_y = clamp(corners.iter().map(|c| c.1).fold(f64::NEG_INFINITY, f64::max), height).ceil() as u32;
+        ImageRect {
+            x: min_x,
+            y: min_y,
+            width: max_x.saturating_sub(min_x),
+            height: max_y.saturating_sub(min_y),
+        }
+    }
+}
+
+/// 4組の対応点から射影変換の行列を求める（src_pointsをdst_pointsに写す、右下の要素は1）
+pub fn homography_from_points(src_points: &[(f32, f32); 4], dst_points: &[(f32, f32); 4]) -> Result<[[f64; 3]; 3]> {
+    // 未知数 h0..h7 の8元連立方程式（対応点1組につき2式）
+    let mut system = [[0.0f64; 9]; 8];
+    for (i, (&(x, y), &(u, v))) in src_points.iter().zip(dst_points).enumerate() {
+        let (x, y, u, v) = (x as f64, y as f64, u as f64, v as f64);
+        system[i * 2] = [x, y, 1.0, 0.0, 0.0, 0.0, -u * x, -u * y, u];
+        system[i * 2 + 1] = [0.0, 0.0, 0.0, x, y, 1.0, -v * x, -v * y, v];
+    }
+
+    let Some(h) = solve(system) else {
+        bail!("対応点から変換を求められません（3点以上が一直線上にある可能性があります）");
+    };
+    Ok([[h[0], h[1], h[2]], [h[3], h[4], h[5]], [h[6], h[7], 1.0]])
+}
+
+/// 部分ピボット選択付きのガウスの消去法で連立方程式を解く（各行の最後の列が右辺）
+fn solve<const N: usize, const M: usize>(mut system: [[f64; M]; N]) -> Option<[f64; N]> {
+    for column in 0..N {
+        let pivot = (column..N).max_by(|&a, &b| system[a][column].abs().total_cmp(&system[b][column].abs()))?;
+        if system[pivot][column].abs() < SINGULAR_EPSILON {
+            return None;
+        }
+        system.swap(column, pivot);
+
+        let pivot_row = system[column];
+        for row in system.iter_mut().skip(column + 1) {
+            let factor = row[column] / pivot_row[column];
+            for (value, pivot_value) in row.iter_mut().zip(pivot_row).skip(column) {
+                *value -= factor * pivot_value;
+            }
+        }
+    }
+
+    // 後退代入
+    let mut solution = [0.0f64; N];
+    for row in (0..N).rev() {
+        let sum: f64 = (row + 1..N).map(|k| system[row][k] * solution[k]).sum();
+        solution[row] = (system[row][N] - sum) / system[row][row];
+    }
+    Some(solution)
+}
+
+/// 3x3行列の逆行列（特異なら None）
+fn invert(m: &[[f64; 3]; 3]) -> Option<[[f64; 3]; 3]> {
+    let cofactor = |r0: usize, r1: usize, c0: usize, c1: usize| m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0];
+    let det = m[0][0] * cofactor(1, 2, 1, 2) - m[0][1] * cofactor(1, 2, 0, 2) + m[0][2] * cofactor(1, 2, 0, 1);
+    if !det.is_finite() || det.abs() < SINGULAR_EPSILON {
+        return None;
+    }
+    Some([
+        [cofactor(1, 2, 1, 2) / det, -cofactor(0, 2, 1, 2) / det, cofactor(0, 1, 1, 2) / det],
+        [-cofactor(1, 2, 0, 2) / det, cofactor(0, 2, 0, 2) / det, -cofactor(0, 1, 0, 2) / det],
+        [cofactor(1, 2, 0, 1) / det, -cofactor(0, 2, 0, 1) / det, cofactor(0, 1, 0, 1) / det],
+    ])
+}
+
+/// 同次座標で点を写す
+fn project(m: &[[f64; 3]; 3], x: f64, y: f64) -> (f64, f64) {
+    let w = m[2][0] * x + m[2][1] * y + m[2][2];
+    let w = if w.abs() < SINGULAR_EPSILON { SINGULAR_EPSILON } else { w };
+    (
+        (m[0][0] * x + m[0][1] * y + m[0][2]) / w,
+        (m[1][0] * x + m[1][1] * y + m[1][2]) / w,
+    )
+}
+
+/// バイリニア補間で画素を読む（範囲外は端の画素）
+fn sample_bilinear(image: &RgbaImage, u: f64, v: f64) -> Rgba<u8> {
+    let max_x = (image.width() - 1) as f64;
+    let max_y = (image.height() - 1) as f64;
+    let u = if u.is_finite() { u.clamp(0.0, max_x) } else { 0.0 };
+    let v = if v.is_finite() { v.clamp(0.0, max_y) } else { 0.0 };
+
+    let (x0, y0) = (u.floor() as u32, v.floor() as u32);
+    let (x1, y1) = ((x0 + 1).min(image.width() - 1), (y0 + 1).min(image.height() - 1));
+    let (fx, fy) = (u - x0 as f64, v - y0 as f64);
+
+    let (p00, p10, p01, p11) = (image.get_pixel(x0, y0), image.get_pixel(x1, y0), image.get_pixel(x0, y1), image.get_pixel(x1, y1));
+    Rgba([0, 1, 2, 3].map(|channel| {
+        let top = p00[channel] as f64 * (1.0 - fx) + p10[channel] as f64 * fx;
+        let bottom = p01[channel] as f64 * (1.0 - fx) + p11[channel] as f64 * fx;
+        (top * (1.0 - fy) + bottom * fy).round() as u8
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// キャプチャの内容を左に dx、上に dy ずらす補正
+    fn shift(dx: f64, dy: f64) -> CaptureTransform {
+        CaptureTransform::Affine([[1.0, 0.0, -dx], [0.0, 1.0, -dy]])
+    }
+
+    #[test]
+    fn apply_moves_content_by_the_matrix() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_fn(8, 8, |x, _| {
+            if x == 5 { Rgba([255, 255, 255, 255]) } else { Rgba([0, 0, 0, 255]) }
+        }));
+        let corrected = shift(2.0, 0.0).apply(&image).unwrap().to_rgba8();
+        assert_eq!(corrected.get_pixel(3, 4)[0], 255);
+        assert_eq!(corrected.get_pixel(5, 4)[0], 0);
+    }
+
+    #[test]
+    fn unmap_rect_inverts_the_correction() {
+        let rect = ImageRect { x: 10, y: 20, width: 30, height: 5 };
+        let unmapped = shift(4.0, 2.0).unmap_rect(rect, 100, 100);
+        assert_eq!(unmapped, ImageRect { x: 14, y: 22, width: 30, height: 5 });
+        // 補正しない場合とはみ出す場合
+        assert_eq!(CaptureTransform::None_.unmap_rect(rect, 100, 100), rect);
+        let clamped = shift(80.0, 0.0).unmap_rect(rect, 100, 100);
+        assert_eq!((clamped.x, clamped.width), (90, 10));
+    }
+
+    #[test]
+    fn homography_maps_the_given_points() {
+        let src = [(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)];
+        let dst = [(1.0, 2.0), (12.0, 1.0), (11.0, 12.0), (0.0, 11.0)];
+        let matrix = homography_from_points(&src, &dst).unwrap();
+        for (&(x, y), &(u, v)) in src.iter().zip(&dst) {
+            let (pu, pv) = project(&matrix, x as f64, y as f64);
+            assert!((pu - u as f64).abs() < 1e-9 && (pv - v as f64).abs() < 1e-9);
+        }
+        assert!(CaptureTransform::Homography(matrix).is_invertible());
+        assert!(!CaptureTransform::Affine([[0.0; 3], [0.0; 3]]).is_invertible());
+    }
+}
@@ -0,0 +1,156 @@
+// 設定値の範囲チェック
+//
+// コマンド・監視開始・ファイルからの読み込みのいずれの経路でも同じ範囲を適用するため、
+// 各設定の範囲はその設定の型の Validate 実装にまとめて定義する。
+use serde::Serialize;
+use std::fmt;
+
+/// 範囲外のフィールド
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldError {
+    /// フィールド名（例: "monitor.interval_ms"）
+    pub field: String,
+    /// 許容範囲を含むエラーメッセージ
+    pub message: String,
+}
+
+/// 範囲外のフィールドの一覧
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigErrors(pub Vec<FieldError>);
+
+impl fmt::Display for ConfigErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let messages: Vec<&str> = self.0.iter().map(|error| error.message.as_str()).collect();
+        write!(f, "設定値が不正です: {}", messages.join("、"))
+    }
+}
+
+impl std::error::Error for ConfigErrors {}
+
+/// フィールドの範囲チェックを行い、エラーを集める
+pub struct Validator {
+    /// フィールド名の接頭辞（設定の種類）
+    prefix: &'static str,
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    /// 新しいValidatorを作成
+    pub fn new(prefix: &'static str) -> Self {
+        Self {
+            prefix,
+            errors: Vec::new(),
+        }
+    }
+
+    /// 値が min 以上 max 以下であることを確認（NaNは範囲外とみなす）
+    pub fn range<T: PartialOrd + fmt::Display>(&mut self, field: &str, value: T, min: T, max: T) {
+        if !(min <= value && value <= max) {
+            let field = format!("{}.{}", self.prefix, field);
+            self.errors.push(FieldError {
+                message: format!("{} は {} 以上 {} 以下で指定してください（指定値: {}）", field, min, max, value),
+                field,
+            });
+        }
+    }
+
+    /// 範囲以外の理由で不正な値を記録
+    pub fn invalid(&mut self, field: &str, reason: impl fmt::Display) {
+        let field = format!("{}.{}", self.prefix, field);
+        self.errors.push(FieldError {
+            message: format!("{} が不正です: {}", field, reason),
+            field,
+        });
+    }
+
+    /// 集めたエラーを結果にする
+    fn finish(self) -> Result<(), ConfigErrors> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigErrors(self.errors))
+        }
+    }
+}
+
+/// 範囲チェックを持つ設定
+pub trait Validate {
+    /// フィールド名の接頭辞
+    const PREFIX: &'static str;
+
+    /// 各フィールドの範囲を確認
+    fn check(&self, validator: &mut Validator);
+
+    /// 範囲外のフィールドがあればすべてまとめてエラーにする
+    fn validate(&self) -> Result<(), ConfigErrors> {
+        let mut validator = Validator::new(Self::PREFIX);
+        self.check(&mut validator);
+        validator.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 1つの値を範囲チェックした結果
+    fn check_range<T: PartialOrd + fmt::Display>(value: T, min: T, max: T) -> Result<(), ConfigErrors> {
+        let mut validator = Validator::new("test");
+        validator.range("value", value, min, max);
+        validator.finish()
+    }
+
+    #[test]
+    fn integer_range_includes_both_bounds() {
+        assert!(check_range(9, 10, 20).is_err());
+        assert_eq!(check_range(10, 10, 20), Ok(()));
+        assert_eq!(check_range(20, 10, 20), Ok(()));
+        assert!(check_range(21, 10, 20).is_err());
+        assert_eq!(check_range(0u32, 0, 0), Ok(()));
+        assert!(check_range(1u32, 0, 0).is_err());
+    }
+
+    #[test]
+    fn float_range_includes_both_bounds_and_rejects_nan() {
+        assert!(check_range(0.099_f32, 0.1, 10.0).is_err());
+        assert_eq!(check_range(0.1_f32, 0.1, 10.0), Ok(()));
+        assert_eq!(check_range(10.0_f32, 0.1, 10.0), Ok(()));
+        assert!(check_range(10.001_f32, 0.1, 10.0).is_err());
+        assert!(check_range(f32::NAN, 0.1, 10.0).is_err());
+        assert!(check_range(f32::INFINITY, 0.1, 10.0).is_err());
+        assert!(check_range(f32::NEG_INFINITY, 0.1, 10.0).is_err());
+    }
+
+    #[test]
+    fn out_of_range_error_names_the_field_and_the_bounds() {
+        assert_eq!(
+            check_range(101, 0, 100),
+            Err(ConfigErrors(vec![FieldError {
+                field: "test.value".to_string(),
+                message: "test.value は 0 以上 100 以下で指定してください（指定値: 101）".to_string(),
+            }]))
+        );
+        let Err(ConfigErrors(errors)) = check_range(f32::NAN, 0.0, 1.0) else {
+            panic!("NaNは範囲外です");
+        };
+        assert_eq!(errors[0].message, "test.value は 0 以上 1 以下で指定してください（指定値: NaN）");
+    }
+
+    #[test]
+    fn collects_every_error_in_order() {
+        let mut validator = Validator::new("ocr");
+        validator.range("psm", 14, 0, 13);
+        validator.range("scale", 1.5, 1.0, 4.0);
+        validator.invalid("backend", "このビルドでは使えません");
+        validator.range("threshold", -1, 0, 255);
+        let errors = validator.finish().unwrap_err();
+        let fields: Vec<&str> = errors.0.iter().map(|error| error.field.as_str()).collect();
+        assert_eq!(fields, vec!["ocr.psm", "ocr.backend", "ocr.threshold"]);
+        assert_eq!(
+            errors.to_string(),
+            "設定値が不正です: ocr.psm は 0 以上 13 以下で指定してください（指定値: 14）、\
+             ocr.backend が不正です: このビルドでは使えません、\
+             ocr.threshold は 0 以上 255 以下で指定してください（指定値: -1）"
+        );
+    }
+}
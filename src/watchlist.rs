@@ -0,0 +1,233 @@
+// キーワードの監視（認識テキストに指定したキーワードが現れたら通知する）
+//
+// 画面に残り続けるキーワードが毎フレーム通知されないよう、キーワードごとに
+// 「現れた」状態を保持し、消えてから再通知できるまでの時間を設ける。
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::validation::{Validate, Validator};
+
+/// キーワード監視の設定
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatchlistConfig {
+    /// 監視するキーワード（空なら監視しない）
+    pub keywords: Vec<String>,
+    /// キーワードが消えてから再び通知できるようになるまでの時間（ミリ秒）
+    pub rearm_ms: u64,
+}
+
+impl Default for WatchlistConfig {
+    fn default() -> Self {
+        Self {
+            keywords: Vec::new(),
+            rearm_ms: 30_000,
+        }
+    }
+}
+
+impl Validate for WatchlistConfig {
+    const PREFIX: &'static str = "watchlist";
+
+    fn check(&self, validator: &mut Validator) {
+        validator.range("keywords", self.keywords.len(), 0, 100);
+        if self.keywords.iter().any(|keyword| keyword.trim().is_empty()) {
+            validator.invalid("keywords", "空のキーワードは指定できません");
+        }
+        validator.range("rearm_ms", self.rearm_ms, 0, 3_600_000);
+    }
+}
+
+/// キーワードが現れた行
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeywordMatch {
+    /// 一致したキーワード
+    pub keyword: String,
+    /// キーワードを含む最初の行
+    pub line: String,
+}
+
+/// キーワードごとの一致の状態
+#[derive(Debug, Clone, Copy, Default)]
+struct KeywordState {
+    /// テキストに現れている間はtrue（この間は再通知しない）
+    latched: bool,
+    /// 最後に消えた時刻（再通知までの待機用）
+    absent_since: Option<Instant>,
+}
+
+/// キーワードごとの一致の状態を保持し、通知すべき一致を判定する
+#[derive(Debug, Default)]
+pub struct KeywordWatcher {
+    config: WatchlistConfig,
+    states: HashMap<String, KeywordState>,
+}
+
+impl KeywordWatcher {
+    /// 設定を変更（一致の状態は破棄する）
+    pub fn set_config(&mut self, config: WatchlistConfig) {
+        self.config = config;
+        self.reset();
+    }
+
+    /// 現在の設定
+    pub fn config(&self) -> &WatchlistConfig {
+        &self.config
+    }
+
+    /// 一致の状態を破棄（監視の停止時）
+    pub fn reset(&mut self) {
+        self.states.clear();
+    }
+
+    /// 認識テキストを確認し、新たに現れたキーワードを返す
+    ///
+    /// 消えてから rearm_ms 以内に再び現れた場合は通知せず、現れている状態に戻す。
+    pub fn observe(&mut self, text: &str, now: Instant) -> Vec<KeywordMatch> {
+        let rearm = Duration::from_millis(self.config.rearm_ms);
+        let mut matches = Vec::new();
+
+        for keyword in &self.config.keywords {
+            let state = self.states.entry(keyword.clone()).or_default();
+            match text.lines().find(|line| line.contains(keyword.as_str())) {
+                Some(line) => {
+                    if state.latched {
+                        continue;
+                    }
+                    state.latched = true;
+                    let rearmed = state
+                        .absent_since
+                        .map_or(true, |since| now.saturating_duration_since(since) >= rearm);
+                    if rearmed {
+                        matches.push(KeywordMatch {
+                            keyword: keyword.clone(),
+                            line: line.trim().to_string(),
+                        });
+                    }
+                }
+                None => {
+                    if state.latched {
+                        state.latched = false;
+                        state.absent_since = Some(now);
+                    }
+                }
+            }
+        }
+
+        matches
+    }
+
+    /// 現在テキストに現れているキーワード（設定順）
+    pub fn latched(&self) -> Vec<String> {
+        self.config
+            .keywords
+            .iter()
+            .filter(|keyword| self.states.get(*keyword).is_some_and(|state| state.latched))
+            .cloned()
+            .collect()
+    }
+}
+
+/// スレッド間で共有するキーワードの監視
+pub type SharedWatchlist = Arc<Mutex<KeywordWatcher>>;
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn watcher(keywords: &[&str], rearm_ms: u64) -> KeywordWatcher {
+        let mut watcher = KeywordWatcher::default();
+        watcher.set_config(WatchlistConfig {
+            keywords: keywords.iter().map(|keyword| keyword.to_string()).collect(),
+            rearm_ms,
+        });
+        watcher
+    }
+
+    fn notified(matches: Vec<KeywordMatch>) -> Vec<String> {
+        matches.into_iter().map(|found| found.keyword).collect()
+    }
+
+    fn after(start: Instant, ms: u64) -> Instant {
+        start + Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn keyword_that_stays_on_screen_is_notified_once() {
+        let mut watcher = watcher(&["エラー"], 1_000);
+        let start = Instant::now();
+        let matches = watcher.observe("状態\n  エラー: 接続できません  ", start);
+        assert_eq!(matches, vec![KeywordMatch {
+            keyword: "エラー".to_string(),
+            line: "エラー: 接続できません".to_string(),
+        }]);
+        for ms in [100, 5_000, 60_000] {
+            assert!(watcher.observe("エラー: 接続できません", after(start, ms)).is_empty());
+        }
+        assert_eq!(watcher.latched(), ["エラー"]);
+    }
+
+    #[test]
+    fn keyword_returning_within_the_window_is_not_notified_again() {
+        let mut watcher = watcher(&["エラー"], 1_000);
+        let start = Instant::now();
+        assert_eq!(notified(watcher.observe("エラー", start)), ["エラー"]);
+        assert!(watcher.observe("正常", after(start, 100)).is_empty());
+        assert!(watcher.latched().is_empty());
+
+        // 消えてから再通知までの時間が経つ前に戻った（現れている状態には戻る）
+        assert!(watcher.observe("エラー", after(start, 1_099)).is_empty());
+        assert_eq!(watcher.latched(), ["エラー"]);
+    }
+
+    #[test]
+    fn keyword_returning_after_the_window_is_notified_again() {
+        let mut watcher = watcher(&["エラー"], 1_000);
+        let start = Instant::now();
+        assert_eq!(notified(watcher.observe("エラー", start)), ["エラー"]);
+        assert!(watcher.observe("正常", after(start, 100)).is_empty());
+
+        // 消えた時刻からちょうど再通知までの時間が経った
+        assert_eq!(notified(watcher.observe("エラー", after(start, 1_100))), ["エラー"]);
+    }
+
+    #[test]
+    fn window_restarts_each_time_the_keyword_disappears() {
+        let mut watcher = watcher(&["エラー"], 1_000);
+        let start = Instant::now();
+        watcher.observe("エラー", start);
+        watcher.observe("", after(start, 100));
+        assert!(watcher.observe("エラー", after(start, 900)).is_empty());
+        watcher.observe("", after(start, 1_000));
+
+        // 最初に消えてからは1秒以上経つが、2回目に消えてからはまだ経っていない
+        assert!(watcher.observe("エラー", after(start, 1_500)).is_empty());
+        watcher.observe("", after(start, 1_600));
+        assert_eq!(notified(watcher.observe("エラー", after(start, 2_600))), ["エラー"]);
+    }
+
+    #[test]
+    fn keywords_are_tracked_independently() {
+        let mut watcher = watcher(&["警告", "エラー"], 0);
+        let start = Instant::now();
+        assert_eq!(notified(watcher.observe("エラー\n警告", start)), ["警告", "エラー"]);
+        assert!(watcher.observe("エラー", after(start, 10)).is_empty());
+        assert_eq!(watcher.latched(), ["エラー"]);
+
+        // 再通知までの時間が0なら、消えた次のフレームで戻っても通知する
+        assert_eq!(notified(watcher.observe("警告\nエラー", after(start, 20))), ["警告"]);
+    }
+
+    #[test]
+    fn changing_the_config_forgets_the_state() {
+        let mut watcher = watcher(&["エラー"], 1_000);
+        let start = Instant::now();
+        watcher.observe("エラー", start);
+        watcher.set_config(watcher.config().clone());
+        assert!(watcher.latched().is_empty());
+        assert_eq!(notified(watcher.observe("エラー", after(start, 10))), ["エラー"]);
+    }
+}
@@ -0,0 +1,414 @@
+// 初回起動のウィザード向けの情報（モニターの選択 → 領域の選択 → 言語の選択 → 認識の確認 → 監視の開始）
+//
+// 各段階で必要な情報を1回の呼び出しでまとめて返し、問題には対処方法の固定コードを付ける。
+// フロントエンドはコードで対処の画面（言語データのダウンロードなど）を選び、メッセージはそのまま表示する。
+use image::DynamicImage;
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::capture::{CaptureConfig, CaptureRegion, DisplayGeometry, ScreenCapture, ScreenListing};
+use crate::ocr::{encode_png_base64, BinarizationMode, GrayscaleMode, OcrConfig, OcrEngine};
+use crate::palette::{self, DominantColor};
+use crate::process_guard::{self, ProcessGuardConfig};
+use crate::tessdata::{self, OcrAvailability};
+
+/// 確認する領域の最小の幅・高さ（領域選択画面で選択できる最小の大きさと揃える）
+const MIN_REGION_SIZE: u32 = 11;
+
+/// 確認結果のサムネイルの大きさ
+const THUMBNAIL_SIZE: (u32, u32) = (240, 160);
+
+/// 背景と文字の明るさの差がこれより小さければ、均等化だけでは文字を分けにくいとみなす
+const LOW_CONTRAST_LUMINANCE: f32 = 96.0;
+
+/// 上位2色の占める割合がこれ以上なら、2色で描かれた領域とみなす
+const TWO_TONE_FRACTION: f32 = 0.9;
+
+/// 文字の色のRGBの最大値と最小値の差がこれ以上なら、色の付いた文字とみなす
+const COLORED_TEXT_CHROMA: u8 = 64;
+
+/// 問題の対処方法（フロントエンドで判別するための固定コード）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RemediationCode {
+    /// モニターを接続する（モニターを取得できない）
+    ConnectDisplay,
+    /// 画面収録を許可する（macOSでキャプチャできない）
+    GrantScreenRecording,
+    /// Tesseractをインストールする
+    InstallTesseract,
+    /// 言語データをダウンロードする（download_language_data）
+    DownloadLanguage,
+    /// モニターの範囲内で領域を選択し直す
+    SelectRegionOnDisplay,
+    /// 領域を大きく選択し直す
+    EnlargeRegion,
+    /// 文字を含むよう領域を選択し直す、または前処理を調整する
+    AdjustRegion,
+    /// 時間をおいてやり直す
+    Retry,
+}
+
+impl RemediationCode {
+    /// シリアライズした時と同じ固定コード
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RemediationCode::ConnectDisplay => "connect_display",
+            RemediationCode::GrantScreenRecording => "grant_screen_recording",
+            RemediationCode::InstallTesseract => "install_tesseract",
+            RemediationCode::DownloadLanguage => "download_language",
+            RemediationCode::SelectRegionOnDisplay => "select_region_on_display",
+            RemediationCode::EnlargeRegion => "enlarge_region",
+            RemediationCode::AdjustRegion => "adjust_region",
+            RemediationCode::Retry => "retry",
+        }
+    }
+}
+
+/// ウィザードの段階で見つかった問題
+#[derive(Debug, Clone, Serialize)]
+pub struct WizardIssue {
+    /// 対処方法
+    pub remediation: RemediationCode,
+    /// 利用者向けのメッセージ
+    pub message: String,
+}
+
+impl WizardIssue {
+    fn new(remediation: RemediationCode, message: impl Into<String>) -> Self {
+        Self {
+            remediation,
+            message: message.into(),
+        }
+    }
+}
+
+/// 認識の確認を続けられないエラー
+#[derive(Debug, Clone)]
+pub enum WizardError {
+    /// 領域が小さすぎる
+    RegionTooSmall(CaptureRegion),
+    /// 領域がどのモニターにも重ならない
+    OutsideDisplay(CaptureRegion),
+    /// 言語データが無い、またはTesseractを初期化できない
+    OcrUnavailable(Box<OcrAvailability>),
+    /// キャプチャに失敗
+    Capture(String),
+    /// その他のエラー
+    Internal(String),
+}
+
+impl WizardError {
+    /// フロントエンドで判別するための固定識別子
+    fn kind(&self) -> &'static str {
+        match self {
+            WizardError::RegionTooSmall(_) => "region_too_small",
+            WizardError::OutsideDisplay(_) => "outside_display",
+            WizardError::OcrUnavailable(_) => "ocr_unavailable",
+            WizardError::Capture(_) => "capture_failed",
+            WizardError::Internal(_) => "internal",
+        }
+    }
+
+    /// エラーの対処方法
+    pub fn remediation(&self) -> RemediationCode {
+        match self {
+            WizardError::RegionTooSmall(_) => RemediationCode::EnlargeRegion,
+            WizardError::OutsideDisplay(_) => RemediationCode::SelectRegionOnDisplay,
+            WizardError::OcrUnavailable(availability) => ocr_remediation(availability),
+            WizardError::Capture(_) => capture_remediation(),
+            WizardError::Internal(_) => RemediationCode::Retry,
+        }
+    }
+}
+
+impl fmt::Display for WizardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WizardError::RegionTooSmall(region) => write!(
+                f,
+                "領域が小さすぎます（{}×{}、幅・高さとも{}ピクセル以上にしてください）",
+                region.width, region.height, MIN_REGION_SIZE
+            ),
+            WizardError::OutsideDisplay(region) => write!(f, "領域がモニターの範囲外です: {:?}", region),
+            WizardError::OcrUnavailable(availability) => write!(f, "{}", availability.message),
+            WizardError::Capture(message) => write!(f, "キャプチャエラー: {}", message),
+            WizardError::Internal(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+// フロントエンドには { kind, message, remediation } の形式で返す
+impl Serialize for WizardError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("WizardError", 3)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("remediation", &self.remediation())?;
+        state.end()
+    }
+}
+
+/// 言語データが使えない場合の対処方法（アプリのディレクトリがあればダウンロードで解決できる）
+fn ocr_remediation(availability: &OcrAvailability) -> RemediationCode {
+    if availability.remediation.is_some() {
+        RemediationCode::DownloadLanguage
+    } else {
+        RemediationCode::InstallTesseract
+    }
+}
+
+/// キャプチャに失敗した場合の対処方法（macOSでは画面収録の許可が無いと失敗する）
+fn capture_remediation() -> RemediationCode {
+    if cfg!(target_os = "macos") {
+        RemediationCode::GrantScreenRecording
+    } else {
+        RemediationCode::Retry
+    }
+}
+
+/// この環境で使える機能
+#[derive(Debug, Clone, Serialize)]
+pub struct PlatformCapabilities {
+    pub os: &'static str,
+    /// 領域選択画面を透明にして下の画面を見せられるか（Linuxではコンポジタのあるセッション、判断できない場合はfalse）
+    pub transparent_overlay: bool,
+    /// カーソルの位置を取得できるか（カーソルが領域にある間だけ監視する設定に必要）
+    pub cursor_position: bool,
+    /// 前面のプロセスを判定できるか（キャプチャしないプロセスの設定に必要）
+    pub process_guard: bool,
+}
+
+impl PlatformCapabilities {
+    pub fn detect() -> Self {
+        let transparent_overlay = if cfg!(any(target_os = "windows", target_os = "macos")) {
+            true
+        } else {
+            // Waylandのセッションは常に合成されるが、X11ではコンポジタの有無を判別できない
+            std::env::var_os("WAYLAND_DISPLAY").is_some()
+        };
+        Self {
+            os: std::env::consts::OS,
+            transparent_overlay,
+            cursor_position: ScreenCapture::cursor_position_supported(),
+            process_guard: cfg!(target_os = "windows"),
+        }
+    }
+}
+
+/// ウィザードの開始時に表示する環境の情報（wizard_environmentの結果）
+#[derive(Debug, Clone, Serialize)]
+pub struct WizardEnvironment {
+    /// 接続中のモニター（取得できない場合は空）
+    pub displays: Vec<ScreenListing>,
+    /// 設定中の言語でのOCRの利用可否
+    pub ocr: OcrAvailability,
+    /// アプリのディレクトリにある言語データ
+    pub installed_languages: Vec<String>,
+    /// この環境で使える機能
+    pub capabilities: PlatformCapabilities,
+    /// 見つかった問題（問題が無ければ空）
+    pub issues: Vec<WizardIssue>,
+}
+
+impl WizardEnvironment {
+    /// 環境の情報を集める（Tesseractの初期化を試すため時間がかかることがある）
+    pub fn collect(tessdata_dir: Option<&Path>, language: &str) -> Self {
+        let mut issues = Vec::new();
+        let displays = match ScreenListing::all() {
+            Ok(displays) => displays,
+            Err(e) => {
+                issues.push(WizardIssue::new(capture_remediation(), format!("モニターの情報を取得できません: {:#}", e)));
+                Vec::new()
+            }
+        };
+        if displays.is_empty() && issues.is_empty() {
+            issues.push(WizardIssue::new(RemediationCode::ConnectDisplay, "接続中のモニターが見つかりません"));
+        }
+
+        let ocr = tessdata::check_availability(tessdata_dir, language);
+        if !ocr.available {
+            issues.push(WizardIssue::new(ocr_remediation(&ocr), ocr.message.clone()));
+        }
+
+        Self {
+            displays,
+            installed_languages: tessdata_dir.map(tessdata::installed_languages).unwrap_or_default(),
+            ocr,
+            capabilities: PlatformCapabilities::detect(),
+            issues,
+        }
+    }
+}
+
+/// 色の分析から提案する前処理
+#[derive(Debug, Clone, Serialize)]
+pub struct PreprocessingSuggestion {
+    /// 背景と推定した色（最も多い色）
+    pub background: DominantColor,
+    /// 文字と推定した色（背景との明るさの差が最も大きい色）
+    pub text: DominantColor,
+    pub invert: bool,
+    pub binarization: BinarizationMode,
+    pub grayscale_mode: GrayscaleMode,
+    /// 提案を反映した認識の設定（そのまま set_ocr_config に渡せる）
+    pub config: OcrConfig,
+    /// 提案の理由（利用者向け）
+    pub reasons: Vec<String>,
+}
+
+impl PreprocessingSuggestion {
+    /// 代表色から前処理を提案する（色が1つしか無い場合はNone）
+    pub fn from_colors(colors: &[DominantColor], current: &OcrConfig) -> Option<Self> {
+        let (background, rest) = colors.split_first()?;
+        let text = rest
+            .iter()
+            .max_by(|a, b| {
+                let contrast = |color: &DominantColor| (color.luminance() - background.luminance()).abs();
+                contrast(a).total_cmp(&contrast(b))
+            })
+            .copied()?;
+
+        let mut reasons = Vec::new();
+        let mut config = current.clone();
+
+        // 暗い背景に明るい文字は反転して白地に黒い文字にする
+        config.invert = background.luminance() < text.luminance();
+        if config.invert {
+            reasons.push("背景が文字より暗いため、画像を反転します".to_string());
+        }
+
+        let contrast = (background.luminance() - text.luminance()).abs();
+        let two_tone = colors.iter().take(2).map(|color| color.fraction).sum::<f32>() >= TWO_TONE_FRACTION;
+        config.binarization = if contrast < LOW_CONTRAST_LUMINANCE || two_tone {
+            reasons.push(if contrast < LOW_CONTRAST_LUMINANCE {
+                format!("背景と文字の明るさの差が小さいため（{:.0}）、二値化します", contrast)
+            } else {
+                "2色で描かれた領域のため、二値化します".to_string()
+            });
+            BinarizationMode::Otsu
+        } else {
+            BinarizationMode::Equalize
+        };
+
+        // 色の付いた文字は輝度で変換すると背景との差が小さくなるため、チャンネルの中央値で変換する
+        let chroma = [text.r, text.g, text.b].iter().max().copied().unwrap_or(0)
+            - [text.r, text.g, text.b].iter().min().copied().unwrap_or(0);
+        config.grayscale_mode = if chroma >= COLORED_TEXT_CHROMA {
+            reasons.push("文字に色が付いているため、チャンネルの中央値でグレースケールにします".to_string());
+            GrayscaleMode::ChannelMedian
+        } else {
+            current.grayscale_mode
+        };
+
+        Some(Self {
+            background: *background,
+            text,
+            invert: config.invert,
+            binarization: config.binarization,
+            grayscale_mode: config.grayscale_mode,
+            config,
+            reasons,
+        })
+    }
+}
+
+/// 認識の確認の結果（wizard_testの結果）
+#[derive(Debug, Clone, Serialize)]
+pub struct WizardTestResult {
+    /// 認識したテキスト（文字が無く認識に失敗した場合は空）
+    pub text: String,
+    /// 認識の信頼度（0.0-1.0、認識に失敗した場合はNone）
+    pub confidence: Option<f32>,
+    /// キャプチャと認識にかかった時間（ミリ秒）
+    pub elapsed_ms: u64,
+    /// キャプチャした領域のサムネイル（PNGのBase64）
+    pub thumbnail: String,
+    /// 色の分析から提案する前処理（色を分析できない場合はNone）
+    pub suggestion: Option<PreprocessingSuggestion>,
+    /// 見つかった問題（問題が無ければ空）
+    pub issues: Vec<WizardIssue>,
+}
+
+/// 領域を1回キャプチャ・認識して、監視を始める前に結果を確かめる
+pub fn run_test(
+    region: CaptureRegion,
+    capture_config: &CaptureConfig,
+    process_guard: &ProcessGuardConfig,
+    tessdata_dir: Option<&Path>,
+    language: &str,
+    ocr_config: OcrConfig,
+) -> Result<WizardTestResult, WizardError> {
+    if region.width < MIN_REGION_SIZE || region.height < MIN_REGION_SIZE {
+        return Err(WizardError::RegionTooSmall(region));
+    }
+    if DisplayGeometry::for_region(&region).is_err() {
+        return Err(WizardError::OutsideDisplay(region));
+    }
+    let availability = tessdata::check_availability(tessdata_dir, language);
+    if !availability.available {
+        return Err(WizardError::OcrUnavailable(Box::new(availability)));
+    }
+
+    let start = Instant::now();
+    let image = process_guard::guarded_capture(process_guard, &ScreenCapture::with_config(region, capture_config))
+        .map_err(|e| WizardError::Capture(format!("{:#}", e)))?;
+    let engine = create_engine(tessdata::resolve_datapath(tessdata_dir, language), language, ocr_config.clone())?;
+
+    let mut issues = Vec::new();
+    let (text, confidence) = match engine.recognize_detailed(&image) {
+        Ok(result) => (result.text, result.confidence),
+        Err(e) => {
+            log::debug!("ウィザードの確認で認識できませんでした（空のテキストとします）: {}", e);
+            (String::new(), None)
+        }
+    };
+    let elapsed_ms = start.elapsed().as_millis() as u64;
+    if text.trim().is_empty() {
+        issues.push(WizardIssue::new(
+            RemediationCode::AdjustRegion,
+            "文字を認識できませんでした。文字を含むよう領域を選択し直すか、提案する前処理を試してください",
+        ));
+    }
+
+    let suggestion = match palette::dominant_colors(&image, 3) {
+        Ok(colors) => PreprocessingSuggestion::from_colors(&colors, &ocr_config),
+        Err(e) => {
+            log::debug!("ウィザードの確認で色を分析できません: {}", e);
+            None
+        }
+    };
+
+    Ok(WizardTestResult {
+        text,
+        confidence,
+        elapsed_ms,
+        thumbnail: thumbnail(&image)?,
+        suggestion,
+        issues,
+    })
+}
+
+/// 確認に使うエンジンを作成（初期化に失敗した場合は言語データの問題として返す）
+fn create_engine(datapath: Option<PathBuf>, language: &str, ocr_config: OcrConfig) -> Result<OcrEngine, WizardError> {
+    let mut engine = OcrEngine::with_language(datapath.clone(), language).map_err(|e| {
+        WizardError::OcrUnavailable(Box::new(OcrAvailability {
+            available: false,
+            language: language.to_string(),
+            tessdata_dir: datapath,
+            message: format!("OCRエンジンを初期化できません: {:#}", e),
+            remediation: None,
+        }))
+    })?;
+    engine.set_config(ocr_config);
+    Ok(engine)
+}
+
+fn thumbnail(image: &DynamicImage) -> Result<String, WizardError> {
+    encode_png_base64(&image.thumbnail(THUMBNAIL_SIZE.0, THUMBNAIL_SIZE.1))
+        .map_err(|e| WizardError::Internal(format!("サムネイルを作成できません: {:#}", e)))
+}
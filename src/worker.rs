@@ -0,0 +1,1155 @@
+// 監視スレッドの処理
+//
+// 監視の開始コマンドがAppStateから集めた設定と共有の状態（MonitorSession）を監視スレッドに渡し、
+// スレッドの中でキャプチャやOCRエンジンなどのセッション中の状態を持つMonitorWorkerを作って
+// 1フレームずつ処理する。キャプチャや画面の更新通知はスレッドをまたいで渡せないため、スレッドの中で作る。
+use image::DynamicImage;
+use log::info;
+use std::ops::ControlFlow;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc as std_mpsc;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::capture::{CaptureConfig, CaptureRegion, DisplayMatch, ScreenCapture};
+use crate::change_visual::ChangeVisualWorker;
+use crate::clock::SessionClock;
+use crate::contrast_probe::ContrastProber;
+use crate::corrections::SharedCorrections;
+use crate::events::{EventEmitter, SharedHistory, TextChangeEvent};
+use crate::evidence::{EvidenceConfig, SharedEvidence};
+use crate::fast_mode::{FastModeConfig, FastModeGovernor};
+use crate::hooks::MonitorError;
+use crate::line_lifetime::LineLifetimeTracker;
+use crate::line_parser::LineParser;
+use crate::locking::lock;
+use crate::memory::{MemoryAccounted, COMPONENT_EVIDENCE, COMPONENT_HISTORY, COMPONENT_TEXT_FREQUENCY};
+use crate::monitor::{texts_equivalent, MonitorConfig, MonitorSnapshot, ScreenMonitor, SharedMonitorConfig, SharedTextFrequency, KILL_SWITCH_ENV};
+use crate::ocr::{OcrConfig, OcrEngine};
+use crate::preprocessing::ImageMetrics;
+use crate::process_guard::{GuardDecision, ProcessGuard, ProcessGuardConfig};
+use crate::schema::{v1, v1::LifecycleState};
+use crate::screen_change::{self, ScreenChangeWaiter};
+use crate::shutdown;
+use crate::script_check::{line_language, ScriptCheck, SharedLanguageSuggestion};
+use crate::stability::SharedStability;
+use crate::stats::{SharedStats, TickTiming, SKIP_CURSOR_OUTSIDE, SKIP_HASH_UNCHANGED, SKIP_PROCESS_DENIED, SKIP_UNREADABLE};
+use crate::summary::{SessionAggregator, SharedSummaries, StopReason};
+use crate::tessdata::{self, TraineddataWatcher};
+use crate::tiling::{TileConfig, TiledRecognizer};
+use crate::watchlist::SharedWatchlist;
+use crate::wizard::RemediationCode;
+use crate::{create_session_engine_with_retry, download_with_progress, ocr_init_failure, AppState, OcrReloadRequest};
+
+/// 監視スレッドが待機中に停止シグナルを確認する間隔
+pub const STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 監視セッションの設定と共有の状態（開始コマンドでAppStateから集め、監視スレッドに渡す）
+pub struct MonitorSession {
+    /// 監視する領域
+    region: CaptureRegion,
+    /// 停止シグナル（セッションごとに新しく作る）
+    stop_signal: Arc<AtomicBool>,
+    /// 終了シグナル（SIGTERM）を受け取ったら立つフラグ（停止ファイルと同じ手順で監視を終える）
+    termination: &'static AtomicBool,
+    /// 監視の設定（監視中の変更も毎フレーム反映する）
+    monitor_config: SharedMonitorConfig,
+    capture_config: CaptureConfig,
+    ocr_baseline: Option<f32>,
+    /// OCRの設定（高速モードの設定を適用済み）
+    ocr_config: OcrConfig,
+    /// タイル単位の変化検出の設定（高速モードの設定を適用済み）
+    tile_config: TileConfig,
+    evidence_config: EvidenceConfig,
+    process_guard_config: ProcessGuardConfig,
+    tessdata_dir: Option<PathBuf>,
+    skip_auto_download: bool,
+    /// 認識言語（再読み込みで変わる）
+    language: String,
+    /// 比較の基準とするテキスト（最初の認識結果と比較する）
+    reference_text: Option<String>,
+    line_parser: Option<LineParser>,
+    /// 高速モードの間隔の切り替え
+    fast_governor: Option<FastModeGovernor>,
+    /// 言語データが無く、監視スレッドでダウンロードしてからエンジンを作成するかどうか
+    first_run_setup: bool,
+    /// 開始コマンドで作成済みのOCRエンジン
+    prepared_engine: Option<OcrEngine>,
+    emitter: EventEmitter,
+    aggregator: SessionAggregator,
+    stats: SharedStats,
+    history: SharedHistory,
+    line_stability: SharedStability,
+    text_frequency: SharedTextFrequency,
+    corrections: SharedCorrections,
+    evidence: SharedEvidence,
+    summaries: SharedSummaries,
+    watchlist: SharedWatchlist,
+    language_suggestion: SharedLanguageSuggestion,
+    reload_requests: std_mpsc::Receiver<OcrReloadRequest>,
+    reference_updates: std_mpsc::Receiver<Option<String>>,
+}
+
+impl MonitorSession {
+    /// AppStateから監視セッションの設定と共有の状態を集める
+    ///
+    /// 再読み込みと基準のテキストの変更のチャンネルはセッションごとに新しく作り、送信側をAppStateに置く。
+    /// 停止シグナルとセッションの識別子は呼び出し側で新しくしておく（要約の時刻は送信側の時計で決める）。
+    pub fn from_state(
+        app_state: &mut AppState,
+        region: CaptureRegion,
+        fast_mode: Option<FastModeConfig>,
+        emitter: EventEmitter,
+    ) -> Self {
+        let (reload_sender, reload_requests) = std_mpsc::channel();
+        app_state.ocr_reload = Some(reload_sender);
+        let (reference_sender, reference_updates) = std_mpsc::channel();
+        app_state.reference_updates = Some(reference_sender);
+        // 前回のセッションの提案は使わない
+        *lock(&app_state.language_suggestion) = None;
+
+        // 高速モードでは最小限の前処理とタイル単位の変化検出を使う（監視の設定は毎回の取得時に適用）
+        let mut ocr_config = app_state.ocr_config.clone();
+        let mut tile_config = app_state.tile_config.clone();
+        let fast_governor = fast_mode.map(|fast_mode| {
+            fast_mode.apply_ocr(&mut ocr_config);
+            fast_mode.apply_tile(&mut tile_config);
+            FastModeGovernor::new(fast_mode)
+        });
+        lock(&app_state.stats).fast_mode = fast_governor.as_ref().map(FastModeGovernor::stats);
+        let aggregator = SessionAggregator::new(app_state.session_id, emitter.clock());
+
+        Self {
+            region,
+            stop_signal: app_state.stop_monitoring.clone(),
+            termination: shutdown::termination_flag(),
+            monitor_config: app_state.monitor_config.clone(),
+            capture_config: app_state.capture_config.clone(),
+            ocr_baseline: app_state.ocr_baseline,
+            ocr_config,
+            tile_config,
+            evidence_config: app_state.evidence_config.clone(),
+            process_guard_config: app_state.process_guard_config.clone(),
+            tessdata_dir: app_state.tessdata_dir.clone(),
+            skip_auto_download: app_state.skip_auto_download,
+            language: app_state.ocr_language.clone().unwrap_or_else(|| tessdata::DEFAULT_LANGUAGE.to_string()),
+            reference_text: app_state.reference_text.clone(),
+            line_parser: LineParser::from_config(&app_state.diff_config),
+            fast_governor,
+            first_run_setup: false,
+            prepared_engine: None,
+            emitter,
+            aggregator,
+            stats: app_state.stats.clone(),
+            history: app_state.history.clone(),
+            line_stability: app_state.line_stability.clone(),
+            text_frequency: app_state.text_frequency.clone(),
+            corrections: app_state.corrections.clone(),
+            evidence: app_state.evidence.clone(),
+            summaries: app_state.summaries.clone(),
+            watchlist: app_state.watchlist.clone(),
+            language_suggestion: app_state.language_suggestion.clone(),
+            reload_requests,
+            reference_updates,
+        }
+    }
+
+    /// セッションの識別子
+    pub fn session_id(&self) -> u64 {
+        self.aggregator.session_id()
+    }
+
+    /// 言語データのディレクトリがアプリの管理下にあるかどうか
+    pub fn has_app_tessdata(&self) -> bool {
+        self.tessdata_dir.is_some()
+    }
+
+    /// 言語データをダウンロードしない場合はOCRエンジンをここで作成する
+    ///
+    /// 言語データが無いなどの設定の誤りを、監視スレッドのイベントではなく開始コマンドの結果として返すため。
+    pub fn prepare_engine(&mut self) -> anyhow::Result<()> {
+        self.first_run_setup = self
+            .tessdata_dir
+            .as_deref()
+            .is_some_and(|dir| tessdata::needs_first_run_setup(dir, &self.language));
+        if !self.first_run_setup || self.skip_auto_download {
+            self.prepared_engine = Some(self.create_engine(&self.language)?);
+        }
+        Ok(())
+    }
+
+    /// セッションの設定でOCRエンジンを作成（再読み込み時も同じ設定で作成する）
+    fn create_engine(&self, language: &str) -> anyhow::Result<OcrEngine> {
+        create_session_engine_with_retry(
+            self.tessdata_dir.as_deref(),
+            language,
+            self.ocr_baseline,
+            &self.ocr_config,
+            self.evidence_config.enabled,
+        )
+    }
+
+    /// 終了時に保存する実行時の設定（言語は再読み込みで、監視の設定は監視中に変わるため終了時点の値を使う）
+    fn snapshot(&self) -> MonitorSnapshot {
+        let monitor_config = lock(&self.monitor_config).clone();
+        MonitorSnapshot {
+            session_id: self.aggregator.session_id(),
+            region: self.region,
+            language: self.language.clone(),
+            interval_ms: monitor_config.interval_ms,
+            ocr_config: self.ocr_config.clone(),
+            monitor_config,
+            tessdata_dir: self.tessdata_dir.clone(),
+            started_at: self.aggregator.started_at_ms(),
+            snapshot_at: self.emitter.clock().now_ms(),
+        }
+    }
+
+    /// 監視セッションの要約を保存し、要約と続けられなかった理由を付けて監視の終了を通知
+    fn finish(self, stop_reason: StopReason, failure: Option<v1::SessionFailure>) {
+        let snapshot = self.snapshot();
+        let summary = self.aggregator.finish(stop_reason);
+        info!(
+            "監視セッション {} の要約（{:?}）: {}ms、変化 {} 件、エラー {} 件",
+            summary.session_id, summary.stop_reason, summary.duration_ms, summary.change_events, summary.error_count
+        );
+        self.emitter.lifecycle_stopped(&summary, failure);
+        let mut history = lock(&self.summaries);
+        history.push(summary);
+        history.push_config(snapshot);
+    }
+
+    /// 監視を始める前に続けられなくなった場合に、理由を付けてセッションを終える
+    fn fail(mut self, message: String, remediation: RemediationCode) -> StopReason {
+        self.aggregator.record_error();
+        let failure = v1::SessionFailure {
+            message,
+            remediation: remediation.as_str().to_string(),
+        };
+        self.finish(StopReason::Error, Some(failure));
+        StopReason::Error
+    }
+}
+
+/// 置き換えにまとめるため送信を保留しているクリア
+struct PendingClear {
+    /// クリアされる前のテキスト
+    text: String,
+    /// クリアを観測した時刻（UNIXエポックからのミリ秒）
+    cleared_at_ms: u64,
+    /// クリアの後に行ったキャプチャの回数
+    ticks_waited: u32,
+}
+
+/// 1フレームの認識結果
+struct Recognition {
+    /// 学習済みの補正を適用したテキスト
+    text: String,
+    /// 正規化済みの信頼度（部分OCRではNone）
+    confidence: Option<f32>,
+    /// 画像の指標（部分OCRではNone）
+    metrics: Option<ImageMetrics>,
+}
+
+/// 監視スレッドで1セッションの監視を行う（セッションの設定と、キャプチャ・OCRエンジンなどのセッション中の状態を持つ）
+pub struct MonitorWorker {
+    session: MonitorSession,
+    ocr_engine: OcrEngine,
+    /// 行の表示期間やクリアの時刻を決めるセッションの時計
+    clock: SessionClock,
+    /// 言語データが追加・更新されたら自動で再読み込みする
+    tessdata_watcher: Option<TraineddataWatcher>,
+    pending_reload: Option<OcrReloadRequest>,
+    script_check: ScriptCheck,
+    line_lifetimes: LineLifetimeTracker,
+    cursor_unavailable_reported: bool,
+    process_guard: ProcessGuard,
+    capture: ScreenCapture,
+    /// 解像度の変更で比例で移した後の領域
+    active_region: CaptureRegion,
+    last_display_check: Instant,
+    /// 画面の更新通知が使えれば、間隔の経過を待たずに更新をきっかけにキャプチャする
+    change_waiter: Option<Box<dyn ScreenChangeWaiter>>,
+    /// タイル単位の変化検出が有効なら変化した部分のみ再認識する
+    tiled_recognizer: Option<TiledRecognizer>,
+    last_hash: Option<u64>,
+    last_text: Option<String>,
+    pending_clear: Option<PendingClear>,
+    first_recognition_reported: bool,
+    /// 最初のフレームで明暗の反転の要否を判定したかどうか
+    contrast_probed: bool,
+    low_coverage_reported: bool,
+    /// 比較画像の作成用に保持する前回の認識のフレーム
+    change_visual_worker: Option<ChangeVisualWorker>,
+    previous_capture: Option<DynamicImage>,
+    /// 設定で停止ファイルが指定されていない場合に使うパス（環境変数は起動時に一度だけ読む）
+    kill_switch_env: Option<PathBuf>,
+}
+
+impl MonitorWorker {
+    /// 監視セッションを終了まで実行し、終了の理由を返す
+    pub fn run(session: MonitorSession) -> StopReason {
+        info!("画面監視スレッドを開始しました: region={:?}", session.region);
+        session.emitter.lifecycle(LifecycleState::Started);
+        let mut worker = match Self::start(session) {
+            Ok(worker) => worker,
+            Err(stop_reason) => return stop_reason,
+        };
+        let stop_reason = loop {
+            if let ControlFlow::Break(stop_reason) = worker.tick() {
+                break stop_reason;
+            }
+        };
+        worker.finish(stop_reason);
+        stop_reason
+    }
+
+    /// 言語データのダウンロードとOCRエンジンの初期化を行い、セッション中の状態を用意する
+    /// （続けられない場合はセッションを終えてErrを返す）
+    fn start(mut session: MonitorSession) -> Result<Self, StopReason> {
+        // 初回起動で言語データが無い場合は認識言語をダウンロード
+        if let Some(dir) = session.tessdata_dir.clone().filter(|_| session.first_run_setup) {
+            if session.skip_auto_download {
+                session.emitter.info("tessdata_download_skipped", "言語データが見つかりませんが、自動ダウンロードは無効です");
+            } else if let Err(e) = download_with_progress(&mut session.emitter, &session.language, &dir) {
+                let message = format!("言語データのダウンロードエラー: {}", e);
+                session.emitter.error(message.clone());
+                return Err(session.fail(message, RemediationCode::Retry));
+            }
+        }
+
+        // OCRエンジンの初期化（ウォームアップ、コマンドで作成済みならそのエンジンを使う）
+        session.emitter.info("ocr_warmup_started", "OCRエンジンを初期化しています");
+        let warmup_start = Instant::now();
+        let ocr_engine = match session.prepared_engine.take().map_or_else(|| session.create_engine(&session.language), Ok) {
+            Ok(engine) => engine,
+            Err(e) => {
+                // ここで失敗するのは、ダウンロードした言語データでも初期化できない場合など
+                let (message, remediation) = ocr_init_failure(&e, session.has_app_tessdata());
+                session.emitter.error(format!("OCR初期化エラー: {}", message));
+                return Err(session.fail(message, remediation));
+            }
+        };
+        session.emitter.info(
+            "ocr_warmup_finished",
+            format!("OCRエンジンの初期化が完了しました（{}ms）", warmup_start.elapsed().as_millis()),
+        );
+
+        // 画面キャプチャの初期化（渡された領域を使用）
+        let region = session.region;
+        let mut active_region = region;
+        // 選択時のモニターと識別子が一致しなければ、最も近いモニターでキャプチャすることを知らせる
+        if let Some(original) = region.display {
+            match original.resolve() {
+                Ok(DisplayMatch::Closest(current)) => {
+                    session.emitter.info(
+                        "display_fallback",
+                        format!(
+                            "選択時のモニター（{}）が見つからないため、最も近いモニター（{}）でキャプチャします",
+                            original.stable_id(),
+                            current.stable_id()
+                        ),
+                    );
+                    // 以降の解像度・拡大率の確認は、実際にキャプチャするモニターと比べる
+                    active_region.display = Some(current);
+                }
+                Ok(DisplayMatch::NotFound) => log::warn!("選択時のモニター（{}）が見つかりません", original.stable_id()),
+                Ok(DisplayMatch::Exact(_)) => {}
+                Err(e) => log::warn!("モニターの情報を取得できません: {}", e),
+            }
+        }
+
+        // 基準のテキストがあれば最初の認識結果から比較する
+        let last_text = session.reference_text.take();
+        if let Some(text) = &last_text {
+            session.emitter.emit(TextChangeEvent::ReferenceSet { text: text.clone() });
+        }
+        let change_visual_worker = session
+            .evidence_config
+            .change_visual
+            .then(|| ChangeVisualWorker::start(session.evidence_config.clone(), session.evidence.clone()))
+            .flatten();
+
+        Ok(Self {
+            ocr_engine,
+            clock: session.emitter.clock(),
+            tessdata_watcher: session.tessdata_dir.clone().map(TraineddataWatcher::new),
+            pending_reload: None,
+            script_check: ScriptCheck::default(),
+            line_lifetimes: LineLifetimeTracker::default(),
+            cursor_unavailable_reported: false,
+            process_guard: ProcessGuard::default(),
+            capture: ScreenCapture::with_config(region, &session.capture_config),
+            active_region,
+            last_display_check: Instant::now(),
+            change_waiter: screen_change::create_waiter(session.capture_config.trigger, &region),
+            tiled_recognizer: session.tile_config.enabled.then(|| TiledRecognizer::new(session.tile_config.clone())),
+            last_hash: None,
+            last_text,
+            pending_clear: None,
+            first_recognition_reported: false,
+            contrast_probed: false,
+            low_coverage_reported: false,
+            change_visual_worker,
+            previous_capture: None,
+            kill_switch_env: std::env::var_os(KILL_SWITCH_ENV).map(PathBuf::from),
+            session,
+        })
+    }
+
+    /// 1フレームを処理（監視を終える場合は終了の理由を返す）
+    fn tick(&mut self) -> ControlFlow<StopReason> {
+        if self.session.stop_signal.load(Ordering::Relaxed) {
+            info!("監視停止シグナルを受信しました");
+            return ControlFlow::Break(StopReason::Requested);
+        }
+
+        // 監視の設定は監視中にも変更できるため毎回取得（高速モードでは間隔とハッシュの設定を置き換える）
+        let mut monitor_config = lock(&self.session.monitor_config).clone();
+        if let Some(governor) = &self.session.fast_governor {
+            governor.apply_monitor(&mut monitor_config);
+        }
+        self.session.emitter.set_max_batch_size(monitor_config.max_batch_size);
+
+        if let Some(stop_reason) = self.stop_condition(&monitor_config) {
+            return ControlFlow::Break(stop_reason);
+        }
+
+        // 送信レートの制限で保留したイベントがあれば、回復し次第まとめて送信
+        self.session.emitter.flush_throttled();
+
+        let screen_changed = self.wait_for_next_frame(&monitor_config);
+        if self.session.stop_signal.load(Ordering::Relaxed) {
+            info!("監視停止シグナルを受信しました");
+            return ControlFlow::Break(StopReason::Requested);
+        }
+        if let Some(stop_reason) = self.terminated() {
+            return ControlFlow::Break(stop_reason);
+        }
+
+        self.reload_engine_if_requested();
+        self.apply_reference_updates();
+        if let Some(stop_reason) = self.check_display(&monitor_config) {
+            return ControlFlow::Break(stop_reason);
+        }
+
+        let tick_start = Instant::now();
+        self.begin_tick(&monitor_config, tick_start, screen_changed);
+        let Some(image) = self.capture_frame(&monitor_config) else {
+            return ControlFlow::Continue(());
+        };
+        self.probe_contrast(&image);
+        let Some(recognition) = self.recognize(&monitor_config, &image, tick_start) else {
+            return ControlFlow::Continue(());
+        };
+
+        // 認識結果が空でも画像に内容があれば一時的に隠れただけとみなし、前回のテキストを保持
+        if recognition.text.is_empty() && self.last_text.is_some() && monitor_config.is_unreadable_frame(&image) {
+            log::debug!("読み取れないフレームのためテキストのクリアを保留しました");
+            lock(&self.session.stats).record_skip(SKIP_UNREADABLE);
+            return ControlFlow::Continue(());
+        }
+
+        self.observe_text(&monitor_config, &recognition.text, tick_start);
+        let sequences = self.emit_changes(&monitor_config, &image, recognition);
+        self.retain_frame(&monitor_config, image, sequences);
+        ControlFlow::Continue(())
+    }
+
+    /// 停止の要求以外で監視を終える条件（終了シグナル・停止ファイル）
+    fn stop_condition(&mut self, monitor_config: &MonitorConfig) -> Option<StopReason> {
+        if let Some(stop_reason) = self.terminated() {
+            return Some(stop_reason);
+        }
+        // 停止ファイルがあれば通常の停止と同じ手順で終了する
+        if monitor_config.kill_switch_triggered(self.kill_switch_env.as_deref()) {
+            self.session.emitter.info("killswitch", "停止ファイルが見つかったため監視を停止します");
+            return Some(StopReason::Killswitch);
+        }
+        None
+    }
+
+    /// 終了シグナルを受け取っていれば、停止ファイルと同じ理由で監視を終える
+    fn terminated(&mut self) -> Option<StopReason> {
+        if !self.session.termination.load(Ordering::Relaxed) {
+            return None;
+        }
+        self.session.emitter.info("killswitch", "終了シグナルを受け取ったため監視を停止します");
+        Some(StopReason::Killswitch)
+    }
+
+    /// 設定された間隔で待機し、画面の更新の通知で待機を打ち切ったかどうかを返す
+    ///
+    /// 間隔が長くても停止要求・終了シグナルにすぐ応じられるよう分割して待機する。
+    /// 再読み込みの要求、または領域に重なる画面の更新の通知があれば待機を打ち切る。
+    fn wait_for_next_frame(&mut self, monitor_config: &MonitorConfig) -> bool {
+        let wait_until = Instant::now() + Duration::from_millis(monitor_config.interval_ms);
+        while !self.session.stop_signal.load(Ordering::Relaxed)
+            && !self.session.termination.load(Ordering::Relaxed)
+            && Instant::now() < wait_until
+        {
+            self.pending_reload = self.pending_reload.take().or_else(|| self.session.reload_requests.try_recv().ok());
+            if self.pending_reload.is_some() {
+                break;
+            }
+            let timeout = STOP_POLL_INTERVAL.min(wait_until.saturating_duration_since(Instant::now()));
+            match &mut self.change_waiter {
+                Some(waiter) => match waiter.wait(&self.active_region, timeout) {
+                    Ok(true) => return true,
+                    Ok(false) => {}
+                    // 通知が使えなくなったらポーリングに戻す
+                    Err(e) => {
+                        log::warn!("画面の更新通知のエラーのためポーリングに戻します: {}", e);
+                        self.change_waiter = None;
+                    }
+                },
+                None => thread::sleep(timeout),
+            }
+        }
+        false
+    }
+
+    /// 最初のフレームで明暗の反転を判定し、反転した方が良ければ以降は反転した設定のエンジンで認識する
+    ///
+    /// 判定に失敗した場合は反転せずに監視を続ける。エンジンを再読み込みした場合は次のフレームで判定し直す。
+    fn probe_contrast(&mut self, image: &DynamicImage) {
+        if std::mem::replace(&mut self.contrast_probed, true) || self.ocr_engine.config().invert {
+            return;
+        }
+        match ContrastProber::probe_engine(&self.ocr_engine, image) {
+            Ok(Some(engine)) => {
+                info!("暗い背景に明るい文字の画面と判定したため、画像を反転して認識します");
+                self.ocr_engine = engine;
+                self.session.emitter.info("dark_mode_detected", "ダークモード検出: 画像を反転しました");
+            }
+            Ok(None) => {}
+            Err(e) => log::warn!("明暗の判定に失敗しました（反転せずに監視します）: {}", e),
+        }
+    }
+
+    /// OCRエンジンの再読み込み（キャプチャの合間に行うため、認識結果や前回のテキストは失われない）
+    fn reload_engine_if_requested(&mut self) {
+        let changed_languages = self.tessdata_watcher.as_mut().map(|watcher| watcher.poll()).unwrap_or_default();
+        let reload = match self.pending_reload.take() {
+            Some(request) => Some((request.language, Some(request.reply))),
+            None if !changed_languages.is_empty() => {
+                info!("言語データの追加・更新を検出しました: {:?}", changed_languages);
+                Some((self.session.language.clone(), None))
+            }
+            None => None,
+        };
+        let Some((new_language, reply)) = reload else {
+            return;
+        };
+        let reload_start = Instant::now();
+        let result = match self.session.create_engine(&new_language) {
+            Ok(engine) => {
+                self.ocr_engine = engine;
+                self.contrast_probed = false;
+                self.session.language = new_language;
+                // 言語を変えたら文字種の確認は最初からやり直す
+                self.script_check.reset();
+                *lock(&self.session.language_suggestion) = None;
+                self.session.emitter.info(
+                    "ocr_engine_reloaded",
+                    format!(
+                        "OCRエンジンを再読み込みしました（{}、{}ms）",
+                        self.session.language,
+                        reload_start.elapsed().as_millis()
+                    ),
+                );
+                Ok(())
+            }
+            // 失敗した場合は読み込み済みのエンジンで監視を続ける
+            Err(e) => {
+                let message = format!("OCRエンジンの再読み込みエラー（{}）: {}", new_language, e);
+                self.session.emitter.error(message.clone());
+                Err(message)
+            }
+        };
+        if let Some(reply) = reply {
+            let _ = reply.send(result);
+        }
+    }
+
+    /// 基準のテキストの変更（キャプチャは行わず、次の認識結果と比較する）
+    fn apply_reference_updates(&mut self) {
+        while let Ok(update) = self.session.reference_updates.try_recv() {
+            self.pending_clear = None;
+            if let Some(text) = &update {
+                info!("基準のテキストを設定しました: {}", text);
+                self.session.emitter.emit(TextChangeEvent::ReferenceSet { text: text.clone() });
+            }
+            self.last_text = update;
+            // 画面が変わらなくても次のフレームを認識し直して基準と比較する（タイルの認識結果も使わない）
+            self.last_hash = None;
+            self.reset_tiles();
+        }
+    }
+
+    /// タイル単位の認識結果を捨てて最初から認識し直す
+    fn reset_tiles(&mut self) {
+        let tile_config = &self.session.tile_config;
+        self.tiled_recognizer = tile_config.enabled.then(|| TiledRecognizer::new(tile_config.clone()));
+    }
+
+    /// 領域のモニターの解像度・拡大率が選択時から変わっていないか確認（領域を移せなければ終了の理由を返す）
+    fn check_display(&mut self, monitor_config: &MonitorConfig) -> Option<StopReason> {
+        let display_check_due = monitor_config.display_check_interval_ms > 0
+            && self.last_display_check.elapsed() >= Duration::from_millis(monitor_config.display_check_interval_ms);
+        let original = self.active_region.display.filter(|_| display_check_due)?;
+        self.last_display_check = Instant::now();
+        match original.current() {
+            // 列挙の順番でidが変わっても、識別子が一致すれば同じモニターのまま
+            Ok(Some(current)) if current.stable_id() == original.stable_id() => {}
+            Ok(current) => {
+                let remapped = current
+                    .filter(|_| monitor_config.remap_on_display_change)
+                    .map(|current| original.remap(&self.active_region, &current));
+                log::warn!("モニターの解像度・拡大率が変わりました: {:?} -> {:?}", original, current);
+                self.session.emitter.emit(TextChangeEvent::RegionInvalidated {
+                    region: self.active_region,
+                    original,
+                    current,
+                    remapped,
+                });
+                let Some(remapped) = remapped else {
+                    return Some(StopReason::RegionInvalidated);
+                };
+                // 移した領域で最初から監視し直す（前回のテキストは比較に使える）
+                info!("領域を移しました: {:?}", remapped);
+                self.active_region = remapped;
+                self.capture = ScreenCapture::with_config(remapped, &self.session.capture_config);
+                self.change_waiter = screen_change::create_waiter(self.session.capture_config.trigger, &remapped);
+                self.reset_tiles();
+                self.last_hash = None;
+            }
+            Err(e) => log::warn!("モニターの情報を取得できません: {}", e),
+        }
+        None
+    }
+
+    /// フレームの開始を統計に記録し、待ち続けたクリアを送信
+    fn begin_tick(&mut self, monitor_config: &MonitorConfig, tick_start: Instant, screen_changed: bool) {
+        {
+            let mut stats = lock(&self.session.stats);
+            if let Some(governor) = &mut self.session.fast_governor {
+                governor.record_start(tick_start);
+                stats.fast_mode = Some(governor.stats());
+            }
+            stats.ticks_total += 1;
+            if screen_changed {
+                stats.ticks_event_triggered += 1;
+            } else {
+                stats.ticks_timer_triggered += 1;
+            }
+        }
+        // 指定回数のキャプチャの間に新しいテキストが現れなければ、保留していたクリアを送信
+        if let Some(pending) = &mut self.pending_clear {
+            pending.ticks_waited += 1;
+        }
+        if self
+            .pending_clear
+            .as_ref()
+            .is_some_and(|pending| pending.ticks_waited > monitor_config.coalesce_clear_ticks)
+        {
+            if let Some(pending) = self.pending_clear.take() {
+                self.session.emitter.emit(TextChangeEvent::TextCleared { text: pending.text });
+                self.session.aggregator.record_changes(1, None);
+            }
+        }
+    }
+
+    /// 画面をキャプチャ（キャプチャしない状況や、前回OCRしたフレームとほぼ同じ場合はNone）
+    fn capture_frame(&mut self, monitor_config: &MonitorConfig) -> Option<DynamicImage> {
+        // カーソルを重ねている間だけ監視する設定なら、カーソルが領域の外にある間はキャプチャしない
+        if monitor_config.monitor_on_hover {
+            match ScreenCapture::region_contains_cursor(&self.active_region) {
+                Ok(true) => {}
+                Ok(false) => {
+                    lock(&self.session.stats).record_skip(SKIP_CURSOR_OUTSIDE);
+                    return None;
+                }
+                Err(e) => {
+                    if !self.cursor_unavailable_reported {
+                        self.cursor_unavailable_reported = true;
+                        self.session
+                            .emitter
+                            .info("hover_unavailable", format!("カーソルの位置を取得できないため、常に監視します: {}", e));
+                    }
+                }
+            }
+        }
+        // 拒否リストのプロセスのウィンドウが重なっていればキャプチャしない
+        if let GuardDecision::Suppress { process, notify } =
+            self.process_guard.check(&self.session.process_guard_config, &self.active_region)
+        {
+            lock(&self.session.stats).record_skip(SKIP_PROCESS_DENIED);
+            if notify {
+                info!("{} のウィンドウが領域に重なっているためキャプチャしません", process);
+                self.session.emitter.emit(TextChangeEvent::CaptureSuppressed { process });
+            }
+            return None;
+        }
+        let image = match self.capture.capture() {
+            Ok(image) => image,
+            Err(e) => {
+                log::error!("キャプチャエラー: {}", e);
+                lock(&self.session.stats).record_capture_error("capture");
+                self.session.aggregator.record_error();
+                self.session.emitter.monitor_error(MonitorError::Capture(e.to_string()));
+                return None;
+            }
+        };
+        self.session.emitter.frame_captured(&image);
+
+        // 前回OCRしたフレームとほぼ同じならOCRを省略
+        if monitor_config.should_skip_frame(&mut self.last_hash, &image) {
+            lock(&self.session.stats).record_skip(SKIP_HASH_UNCHANGED);
+            return None;
+        }
+        Some(image)
+    }
+
+    /// OCRでテキスト認識（予算を過ぎたら任意の処理を省略、失敗した場合はNone）
+    fn recognize(&mut self, monitor_config: &MonitorConfig, image: &DynamicImage, tick_start: Instant) -> Option<Recognition> {
+        let stats = &self.session.stats;
+        let aggregator = &mut self.session.aggregator;
+        let emitter = &mut self.session.emitter;
+        let ocr_engine = &mut self.ocr_engine;
+        let tick_budget = monitor_config.tick_budget();
+        ocr_engine.set_deadline(Some(tick_start + tick_budget));
+        let ocr_start = Instant::now();
+        // 部分OCRでは信頼度と画像の指標を取得しない
+        let mut confidence = None;
+        let mut metrics = None;
+        // 列に分割する場合はタイル単位の変化検出を使わない
+        let recognition = match &mut self.tiled_recognizer {
+            Some(recognizer) if monitor_config.column_split.is_none() => {
+                recognizer.recognize(ocr_engine, image, stats).inspect(|_| {
+                    // タイル単位の認識は画像の指標を返さないため、文字の占める割合だけを計測する
+                    if let Some(coverage) = ocr_engine.sample_text_coverage(image) {
+                        lock(stats).last_text_coverage = Some(coverage);
+                        aggregator.record_text_coverage(coverage);
+                    }
+                })
+            }
+            _ => {
+                lock(stats).full_ocr_count += 1;
+                let result = match monitor_config.column_split {
+                    Some(columns) => ScreenMonitor::recognize_columns(ocr_engine, image, columns),
+                    None => ocr_engine.recognize_detailed(image),
+                };
+                result.map(|result| {
+                    log::debug!("認識信頼度（正規化済み）: {:?}", result.confidence);
+                    emitter.text_recognized(&result);
+                    confidence = result.confidence;
+                    metrics = Some(result.metrics);
+                    lock(stats).last_image_metrics = Some(result.metrics);
+                    // 計測の間隔ごとにしか計測しないため、計測したフレームの値だけを反映する
+                    if let Some(coverage) = result.metrics.text_coverage {
+                        lock(stats).last_text_coverage = Some(coverage);
+                        aggregator.record_text_coverage(coverage);
+                    }
+                    result.text
+                })
+            }
+        };
+        lock(stats).ocr_duration.observe(ocr_start.elapsed());
+        aggregator.record_ocr(ocr_start.elapsed());
+        if let Some(timings) = ocr_engine.take_preprocess_timings() {
+            lock(stats).record_preprocess(&timings);
+            let threshold_ms = monitor_config.slow_preprocess_threshold_ms;
+            if threshold_ms > 0 && timings.total_us > threshold_ms * 1000 {
+                emitter.info(
+                    "slow_preprocess",
+                    format!(
+                        "前処理に時間がかかっています: {}ms（しきい値 {}ms）",
+                        timings.total_us / 1000,
+                        threshold_ms
+                    ),
+                );
+            }
+        }
+        let skipped_stages = ocr_engine.take_skipped_stages();
+        let worker_restarts = ocr_engine.take_worker_restarts();
+        if worker_restarts > 0 {
+            lock(stats).ocr_worker_restarts += worker_restarts;
+        }
+        match recognition {
+            // 学習済みの補正を適用（無効時はそのまま）
+            Ok(text) => {
+                lock(stats).record_tick(TickTiming {
+                    duration_ms: tick_start.elapsed().as_millis() as u64,
+                    budget_ms: tick_budget.as_millis() as u64,
+                    skipped_stages: skipped_stages.iter().map(|stage| stage.to_string()).collect(),
+                });
+                // 高速モードで予算を超える状態が続いたら代わりの間隔に落とす
+                if let Some(governor) = &mut self.session.fast_governor {
+                    if governor.record_duration(tick_start.elapsed(), tick_budget) {
+                        emitter.info(
+                            "fast_mode_degraded",
+                            format!("処理が間に合わないため監視の間隔を{}msに切り替えました", governor.interval_ms()),
+                        );
+                    }
+                    lock(stats).fast_mode = Some(governor.stats());
+                }
+                Some(Recognition {
+                    text: lock(&self.session.corrections).apply(&text),
+                    confidence,
+                    metrics,
+                })
+            }
+            Err(e) => {
+                log::error!("OCRエラー: {}", e);
+                lock(stats).record_capture_error("ocr");
+                aggregator.record_error();
+                emitter.monitor_error(MonitorError::Ocr(e.to_string()));
+                None
+            }
+        }
+    }
+
+    /// 認識したテキストからキーワード・言語・文字の占める割合・行の安定度と表示期間を更新
+    fn observe_text(&mut self, monitor_config: &MonitorConfig, current_text: &str, tick_start: Instant) {
+        let session = &mut self.session;
+        // キーワードは現れた時点で1回だけ通知（消えるまで、または再通知までの時間内は通知しない）
+        let keyword_matches = lock(&session.watchlist).observe(current_text, Instant::now());
+        for keyword_match in keyword_matches {
+            info!("キーワードを検出: {} ({})", keyword_match.keyword, keyword_match.line);
+            session.emitter.emit(TextChangeEvent::KeywordMatched {
+                keyword: keyword_match.keyword,
+                line: keyword_match.line,
+            });
+        }
+
+        // 初回認識の所要時間を通知
+        if !self.first_recognition_reported {
+            self.first_recognition_reported = true;
+            session.emitter.info(
+                "first_recognition",
+                format!("最初の認識が完了しました（{}ms）", tick_start.elapsed().as_millis()),
+            );
+        }
+
+        // 文字種が認識言語と合わない状態が続いたら言語を1回だけ提案（設定は変更しない）
+        if let Some(suggested) = self.script_check.observe(
+            &session.language,
+            current_text,
+            monitor_config.script_mismatch_ratio,
+            monitor_config.script_check_ticks,
+        ) {
+            session.emitter.info(
+                "language_mismatch",
+                format!(
+                    "認識結果の文字が認識言語（{}）と合いません。言語を {} にすると正しく認識できる可能性があります",
+                    session.language, suggested
+                ),
+            );
+            *lock(&session.language_suggestion) = Some(suggested);
+        }
+
+        // 文字の占める割合の平均が低い状態が続いたら領域を狭めるよう1回だけ提案（領域は変更しない）
+        if !self.low_coverage_reported && monitor_config.low_coverage_min_samples > 0 {
+            if let Some(coverage) = session.aggregator.text_coverage() {
+                if coverage.samples >= monitor_config.low_coverage_min_samples as u64
+                    && coverage.mean < monitor_config.low_coverage_threshold
+                {
+                    self.low_coverage_reported = true;
+                    session.emitter.info(
+                        "low_text_coverage",
+                        format!(
+                            "領域のうち文字が占めるのは平均 {:.0}% です。文字の周りに領域を狭めると認識が速く正確になります",
+                            coverage.mean * 100.0
+                        ),
+                    );
+                }
+            }
+        }
+
+        // 行ごとの安定度を更新
+        lock(&session.line_stability).observe(current_text, self.clock.now_ms());
+
+        // 行ごとの表示期間を更新（追跡を無効にした場合は追跡中の行を消えたことにする）
+        let line_transitions = if monitor_config.track_line_lifetimes {
+            self.line_lifetimes.observe(current_text, self.clock.now_ms())
+        } else {
+            self.line_lifetimes.finish(self.clock.now_ms())
+        };
+        for transition in line_transitions {
+            session.emitter.emit(transition.into());
+        }
+    }
+
+    /// 前回のテキストと比較して変化のイベントを送信し、送信したイベントの連番を返す
+    fn emit_changes(&mut self, monitor_config: &MonitorConfig, image: &DynamicImage, recognition: Recognition) -> Vec<u64> {
+        let Recognition { text: current_text, confidence, metrics } = recognition;
+        let session = &mut self.session;
+        let now_ms = self.clock.now_ms();
+        let mut sequences = Vec::new();
+        match &self.last_text {
+            None => {
+                if current_text.is_empty() {
+                    // テキストが無いまま
+                } else if let Some(pending) = self.pending_clear.take() {
+                    // 保留中のクリアと新しいテキストを1つの置き換えにまとめる（同じテキストに戻っただけなら送信しない）
+                    if !texts_equivalent(&pending.text, &current_text) {
+                        info!("テキストが置き換えられました: {} -> {}", pending.text, current_text);
+                        sequences.push(session.emitter.emit(TextChangeEvent::TextReplaced {
+                            old: pending.text,
+                            new: current_text.clone(),
+                            cleared_at_ms: pending.cleared_at_ms,
+                            replaced_at_ms: now_ms,
+                        }));
+                        lock(&session.text_frequency).record(&current_text);
+                    }
+                    self.last_text = Some(current_text);
+                } else {
+                    // 初回認識
+                    info!("新しいテキストを検出: {}", current_text);
+                    sequences.push(session.emitter.emit(text_event(monitor_config, image, None, current_text.clone(), confidence, metrics)));
+                    lock(&session.text_frequency).record(&current_text);
+                    self.last_text = Some(current_text);
+                }
+            }
+            Some(prev_text) => {
+                // 空白の違いだけは変化とみなさない（送信するテキストは認識したまま）
+                if !texts_equivalent(prev_text, &current_text) {
+                    if current_text.is_empty() {
+                        // テキストがクリアされた（まとめる設定なら次のテキストを待つ間は送信を保留）
+                        info!("テキストがクリアされました");
+                        if monitor_config.coalesce_clear_ticks > 0 {
+                            self.pending_clear = Some(PendingClear {
+                                text: prev_text.clone(),
+                                cleared_at_ms: now_ms,
+                                ticks_waited: 0,
+                            });
+                        } else {
+                            sequences.push(session.emitter.emit(TextChangeEvent::TextCleared { text: prev_text.clone() }));
+                        }
+                        self.last_text = None;
+                    } else {
+                        // テキストが変更された
+                        info!("テキストが変更されました: {} -> {}", prev_text, current_text);
+
+                        // 差分を検出
+                        let (added, removed) = detect_text_diff(prev_text, &current_text);
+
+                        // 差分がある場合は差分イベントも送信
+                        if !added.is_empty() || !removed.is_empty() {
+                            info!("差分検出 - 追加: {:?}, 削除: {:?}", added, removed);
+                            let line_stability = {
+                                let tracker = lock(&session.line_stability);
+                                added
+                                    .iter()
+                                    .chain(removed.iter())
+                                    .filter_map(|line| tracker.lookup(line))
+                                    .collect()
+                            };
+                            // 分解は通知内容の補足のみで、変化の検出には影響しない
+                            let parsed_added = session
+                                .line_parser
+                                .as_ref()
+                                .map(|parser| added.iter().map(|line| parser.parse(line)).collect())
+                                .unwrap_or_default();
+                            // 複数の言語を設定した場合のみ、追加行ごとの言語を付ける
+                            let added_languages = if session.language.contains('+') {
+                                added.iter().map(|line| line_language(&session.language, line)).collect()
+                            } else {
+                                Vec::new()
+                            };
+                            sequences.push(session.emitter.emit(TextChangeEvent::DiffDetected {
+                                added: added.clone(),
+                                removed: removed.clone(),
+                                line_stability,
+                                parsed_added,
+                                added_languages,
+                            }));
+                        }
+
+                        // 通常の変更イベントも送信
+                        sequences.push(session.emitter.emit(text_event(
+                            monitor_config,
+                            image,
+                            Some(prev_text.clone()),
+                            current_text.clone(),
+                            confidence,
+                            metrics,
+                        )));
+                        lock(&session.text_frequency).record(&current_text);
+                        self.last_text = Some(current_text);
+                    }
+                }
+            }
+        }
+
+        session.aggregator.record_changes(sequences.len(), self.last_text.as_deref());
+        sequences
+    }
+
+    /// 変化のイベントの元になったフレームを保持し、保持しているデータの使用量を上限に収める
+    fn retain_frame(&mut self, monitor_config: &MonitorConfig, image: DynamicImage, sequences: Vec<u64>) {
+        let session = &self.session;
+        // 変化イベントを送信したら前回の認識のフレームとの比較画像を別のスレッドで作成
+        if let Some(worker) = &self.change_visual_worker {
+            match self.previous_capture.take() {
+                Some(previous) if !sequences.is_empty() => worker.submit(sequences.clone(), previous, image.clone()),
+                _ => {}
+            }
+            self.previous_capture = Some(image);
+        }
+
+        // イベントの元になった前処理済み画像を保持
+        if session.evidence_config.enabled {
+            if let Some(image) = self.ocr_engine.take_last_preprocessed_image() {
+                lock(&session.evidence).record(&session.evidence_config, sequences, &image);
+            }
+        }
+
+        // 保持しているデータの使用量を集計し、上限を超えていれば削る
+        let mut history = lock(&session.history);
+        let mut evidence = lock(&session.evidence);
+        let mut text_frequency = lock(&session.text_frequency);
+        let mut components: [(&str, &mut dyn MemoryAccounted); 3] = [
+            (COMPONENT_HISTORY, &mut *history),
+            (COMPONENT_EVIDENCE, &mut *evidence),
+            (COMPONENT_TEXT_FREQUENCY, &mut *text_frequency),
+        ];
+        lock(&session.stats).memory.account(monitor_config.memory_soft_limit_mb * 1024 * 1024, &mut components);
+    }
+
+    /// 保留していたイベントを送信し、セッションの要約を保存して監視の終了を通知
+    fn finish(mut self, stop_reason: StopReason) {
+        // 保留していたクリアは監視の終了時に送信
+        if let Some(pending) = self.pending_clear.take() {
+            self.session.emitter.emit(TextChangeEvent::TextCleared { text: pending.text });
+            self.session.aggregator.record_changes(1, None);
+        }
+        // 表示されたままの行は監視の終了時に消えたことにする
+        for transition in self.line_lifetimes.finish(self.clock.now_ms()) {
+            self.session.emitter.emit(transition.into());
+        }
+
+        // 保持していた画像は監視の終了とともに破棄（作成中の比較画像を待ってから破棄する）
+        drop(self.change_visual_worker);
+        lock(&self.session.evidence).clear();
+        self.session.emitter.info("monitoring_worker_stopped", "画面監視スレッドを終了しました");
+        self.session.emitter.flush_all();
+        self.session.finish(stop_reason, None);
+    }
+}
+
+/// テキストの新規・変更イベント（サムネイルを付ける設定ならサムネイル付きの変更イベント）
+fn text_event(
+    monitor_config: &MonitorConfig,
+    image: &DynamicImage,
+    old: Option<String>,
+    new: String,
+    confidence: Option<f32>,
+    metrics: Option<ImageMetrics>,
+) -> TextChangeEvent {
+    if !monitor_config.attach_thumbnail {
+        return match old {
+            Some(old) => TextChangeEvent::TextChanged { old, new },
+            None => TextChangeEvent::NewText { text: new },
+        };
+    }
+    TextChangeEvent::RichTextChanged {
+        old,
+        new,
+        thumbnail: monitor_config.thumbnail(image),
+        confidence,
+        metrics,
+    }
+}
+
+/// テキストの差分を検出する関数
+fn detect_text_diff(old_text: &str, new_text: &str) -> (Vec<String>, Vec<String>) {
+    let old_lines: Vec<&str> = old_text.lines().collect();
+    let new_lines: Vec<&str> = new_text.lines().collect();
+
+    let mut added = Vec::new();
+    let mut removed = Vec::new();
+
+    // 新しく追加された行を検出
+    for new_line in &new_lines {
+        if !old_lines.contains(new_line) && !new_line.trim().is_empty() {
+            added.push(new_line.to_string());
+        }
+    }
+
+    // 削除された行を検出
+    for old_line in &old_lines {
+        if !new_lines.contains(old_line) && !old_line.trim().is_empty() {
+            removed.push(old_line.to_string());
+        }
+    }
+
+    (added, removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends::OcrBackendKind;
+    use crate::test_helpers::TestHarness;
+
+    /// 監視の開始と同じ手順で、作成済みのエンジンを使うセッションを用意する
+    fn session(harness: &TestHarness) -> MonitorSession {
+        let emitter = harness.session_emitter(SessionClock::start());
+        let region = CaptureRegion { x: 0, y: 0, width: 64, height: 32, display: None };
+        let mut session = MonitorSession::from_state(&mut harness.state(), region, None, emitter);
+        session.prepared_engine = Some(OcrEngine::from_parts(None, "eng", OcrBackendKind::Tesseract, None));
+        session
+    }
+
+    fn stop_reasons(harness: &TestHarness) -> Vec<StopReason> {
+        let summaries = harness.state().summaries.clone();
+        let reasons = lock(&summaries).summaries().iter().map(|summary| summary.stop_reason).collect();
+        reasons
+    }
+
+    #[test]
+    fn worker_stops_with_killswitch_when_the_file_exists() {
+        let harness = TestHarness::new();
+        let path = std::env::temp_dir().join(format!("worker_kill_switch_{}", std::process::id()));
+        std::fs::write(&path, b"").unwrap();
+        lock(&harness.state().monitor_config).kill_switch_path = Some(path.clone());
+
+        assert_eq!(MonitorWorker::run(session(&harness)), StopReason::Killswitch);
+        assert_eq!(stop_reasons(&harness), vec![StopReason::Killswitch]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn worker_stops_with_killswitch_on_termination_while_waiting() {
+        static TERMINATION: AtomicBool = AtomicBool::new(false);
+        let harness = TestHarness::new();
+        // 間隔の経過を待たずに終了シグナルで待機を打ち切る
+        lock(&harness.state().monitor_config).interval_ms = 60_000;
+        let mut session = session(&harness);
+        session.termination = &TERMINATION;
+
+        let started = Instant::now();
+        let signal = thread::spawn(|| {
+            thread::sleep(Duration::from_millis(50));
+            TERMINATION.store(true, Ordering::Relaxed);
+        });
+        assert_eq!(MonitorWorker::run(session), StopReason::Killswitch);
+        signal.join().unwrap();
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(stop_reasons(&harness), vec![StopReason::Killswitch]);
+    }
+
+    #[test]
+    fn worker_stops_as_requested_with_the_stop_signal() {
+        let harness = TestHarness::new();
+        let session = session(&harness);
+        harness.state().stop_monitoring.store(true, Ordering::Relaxed);
+
+        assert_eq!(MonitorWorker::run(session), StopReason::Requested);
+        assert_eq!(stop_reasons(&harness), vec![StopReason::Requested]);
+    }
+}